@@ -8,6 +8,12 @@ use std::process::Command;
 /// GNOME adapter using gsettings
 pub struct GnomeAdapter;
 
+impl Default for GnomeAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl GnomeAdapter {
     pub fn new() -> Self {
         Self
@@ -92,7 +98,7 @@ impl AdapterTrait for GnomeAdapter {
                                 if let Some((w, h)) = res.split_once('x') {
                                     if let (Ok(width), Ok(height)) = (w.parse::<u32>(), h.parse::<u32>()) {
                                         let coords: Vec<&str> = pos.split('+').collect();
-                                        let x = coords.get(0).and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
+                                        let x = coords.first().and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
                                         let y = coords.get(1).and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
 
                                         monitors.push(Monitor {
@@ -124,6 +130,12 @@ impl AdapterTrait for GnomeAdapter {
 /// KDE adapter using qdbus
 pub struct KdeAdapter;
 
+impl Default for KdeAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl KdeAdapter {
     pub fn new() -> Self {
         Self
@@ -198,7 +210,7 @@ impl AdapterTrait for KdeAdapter {
                                 if let Some((w, h)) = res.split_once('x') {
                                     if let (Ok(width), Ok(height)) = (w.parse::<u32>(), h.parse::<u32>()) {
                                         let coords: Vec<&str> = pos.split('+').collect();
-                                        let x = coords.get(0).and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
+                                        let x = coords.first().and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
                                         let y = coords.get(1).and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
 
                                         monitors.push(Monitor {
@@ -230,6 +242,12 @@ impl AdapterTrait for KdeAdapter {
 /// XFCE adapter using xfconf-query
 pub struct XfceAdapter;
 
+impl Default for XfceAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl XfceAdapter {
     pub fn new() -> Self {
         Self
@@ -318,7 +336,7 @@ impl AdapterTrait for XfceAdapter {
                                 if let Some((w, h)) = res.split_once('x') {
                                     if let (Ok(width), Ok(height)) = (w.parse::<u32>(), h.parse::<u32>()) {
                                         let coords: Vec<&str> = pos.split('+').collect();
-                                        let x = coords.get(0).and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
+                                        let x = coords.first().and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
                                         let y = coords.get(1).and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
 
                                         monitors.push(Monitor {
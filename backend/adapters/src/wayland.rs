@@ -8,6 +8,12 @@ use std::process::Command;
 /// swww adapter - Wayland wallpaper daemon with transitions
 pub struct SwwwAdapter;
 
+impl Default for SwwwAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SwwwAdapter {
     pub fn new() -> Self {
         Self
@@ -136,6 +142,12 @@ impl AdapterTrait for SwwwAdapter {
 /// hyprpaper adapter - Hyprland's wallpaper utility
 pub struct HyprpaperAdapter;
 
+impl Default for HyprpaperAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl HyprpaperAdapter {
     pub fn new() -> Self {
         Self
@@ -264,6 +276,12 @@ impl AdapterTrait for HyprpaperAdapter {
 /// swaybg adapter - Sway's background utility
 pub struct SwaybgAdapter;
 
+impl Default for SwaybgAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SwaybgAdapter {
     pub fn new() -> Self {
         Self
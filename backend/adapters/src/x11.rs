@@ -8,6 +8,12 @@ use std::process::Command;
 /// feh adapter - lightweight and fast
 pub struct FehAdapter;
 
+impl Default for FehAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl FehAdapter {
     pub fn new() -> Self {
         Self
@@ -80,7 +86,7 @@ impl AdapterTrait for FehAdapter {
                             if let Some((w, h)) = res.split_once('x') {
                                 if let (Ok(width), Ok(height)) = (w.parse::<u32>(), h.parse::<u32>()) {
                                     let coords: Vec<&str> = pos.split('+').collect();
-                                    let x = coords.get(0).and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
+                                    let x = coords.first().and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
                                     let y = coords.get(1).and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
 
                                     monitors.push(Monitor {
@@ -111,6 +117,12 @@ impl AdapterTrait for FehAdapter {
 /// nitrogen adapter - good for X11, has GUI
 pub struct NitrogenAdapter;
 
+impl Default for NitrogenAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl NitrogenAdapter {
     pub fn new() -> Self {
         Self
@@ -176,7 +188,7 @@ impl AdapterTrait for NitrogenAdapter {
                             if let Some((w, h)) = res.split_once('x') {
                                 if let (Ok(width), Ok(height)) = (w.parse::<u32>(), h.parse::<u32>()) {
                                     let coords: Vec<&str> = pos.split('+').collect();
-                                    let x = coords.get(0).and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
+                                    let x = coords.first().and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
                                     let y = coords.get(1).and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
 
                                     monitors.push(Monitor {
@@ -206,6 +218,12 @@ impl AdapterTrait for NitrogenAdapter {
 /// xwallpaper adapter - minimal X11 wallpaper setter
 pub struct XWallpaperAdapter;
 
+impl Default for XWallpaperAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl XWallpaperAdapter {
     pub fn new() -> Self {
         Self
@@ -274,7 +292,7 @@ impl AdapterTrait for XWallpaperAdapter {
                             if let Some((w, h)) = res.split_once('x') {
                                 if let (Ok(width), Ok(height)) = (w.parse::<u32>(), h.parse::<u32>()) {
                                     let coords: Vec<&str> = pos.split('+').collect();
-                                    let x = coords.get(0).and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
+                                    let x = coords.first().and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
                                     let y = coords.get(1).and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
 
                                     monitors.push(Monitor {
@@ -0,0 +1,145 @@
+//! Glue between the download queue and the wallpaper library
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::{broadcast, Mutex};
+use uuid::Uuid;
+use wallmgr_connectors::{DownloadProgress, DownloadQueue, DownloadedFile, Importer, QueueEvent};
+use wallmgr_core::{
+    error::{Error, Result},
+    types::{DownloadJob, Wallpaper, WallpaperType},
+    Config, Database,
+};
+use crate::websocket::{DownloadProgressEvent, WsChannel, WsMessage};
+
+/// Adds finished downloads to the library with a thumbnail
+pub struct LibraryImporter {
+    pub config: Arc<Config>,
+    pub database: Arc<Mutex<Database>>,
+}
+
+#[async_trait]
+impl Importer for LibraryImporter {
+    async fn import(&self, job: &DownloadJob, file: &DownloadedFile) -> Result<()> {
+        let image = &job.image;
+        let path_str = file.path.to_string_lossy().to_string();
+        let wallpaper_type = WallpaperType::from_path(&path_str).unwrap_or(WallpaperType::Image);
+
+        let path = file.path.clone();
+        let thumbnails_dir = self.config.thumbnails_dir.clone();
+        let thumbnail_name = format!("{}-{}", image.source, image.id);
+        let is_image = wallpaper_type == WallpaperType::Image;
+        let (hash, dimensions, thumbnail) = tokio::task::spawn_blocking(move || {
+            use sha2::{Sha256, Digest};
+            let hash = std::fs::read(&path).map(|content| format!("{:x}", Sha256::digest(&content)));
+            let dimensions = image::image_dimensions(&path).ok();
+            let thumbnail = if is_image {
+                wallmgr_core::thumbnail::generate(&path, &thumbnails_dir, &thumbnail_name)
+                    .map_err(|e| tracing::warn!("No thumbnail for {}: {}", path.display(), e))
+                    .ok()
+            } else {
+                None
+            };
+            (hash, dimensions, thumbnail)
+        })
+        .await
+        .map_err(|e| Error::Io(std::io::Error::other(e)))?;
+        let hash = hash?;
+
+        let db = self.database.lock().await;
+        // A retried job may already have been imported
+        if db.has_wallpaper_with_hash(&hash)? {
+            return Ok(());
+        }
+
+        let (width, height) = dimensions.unwrap_or((image.width, image.height));
+        let wallpaper = Wallpaper {
+            id: Uuid::new_v4(),
+            path: path_str,
+            filename: file
+                .path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("unknown")
+                .to_string(),
+            wallpaper_type,
+            width,
+            height,
+            size: file.size,
+            hash,
+            source: Some(image.source.clone()),
+            source_url: Some(image.file_url.clone()),
+            tags: image.tags.clone(),
+            thumbnail_path: thumbnail.map(|p| p.to_string_lossy().to_string()),
+            rating: Some(image.rating),
+            stats: Default::default(),
+            created_at: chrono::Utc::now(),
+            modified_at: chrono::Utc::now(),
+        };
+
+        db.categorize_tags(&image.tag_categories)?;
+        db.add_wallpaper(&wallpaper)
+    }
+}
+
+/// Report queue progress to WebSocket clients until the queue goes away
+pub async fn forward_events(queue: Arc<DownloadQueue>, ws: Arc<WsChannel>) {
+    let mut events = queue.subscribe();
+    drop(queue);
+
+    loop {
+        let message = match events.recv().await {
+            Ok(QueueEvent::Progress { image_id, progress, .. }) => download_event(
+                &image_id,
+                progress.fraction().unwrap_or(0.0),
+                "downloading",
+                Some(&progress),
+            ),
+            Ok(QueueEvent::Done { image_id, .. }) => download_event(&image_id, 1.0, "complete", None),
+            Ok(QueueEvent::Failed { image_id, retry_at, .. }) => {
+                let status = if retry_at.is_some() { "retrying" } else { "error" };
+                download_event(&image_id, 0.0, status, None)
+            }
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        ws.send(message);
+    }
+}
+
+fn download_event(
+    image_id: &str,
+    progress: f32,
+    status: &str,
+    stats: Option<&DownloadProgress>,
+) -> WsMessage {
+    WsMessage::DownloadProgress(DownloadProgressEvent {
+        image_id: image_id.to_string(),
+        progress,
+        status: status.to_string(),
+        speed: stats.map(|s| format_speed(s.bytes_per_second)),
+        eta: stats.and_then(|s| s.eta()).map(format_eta),
+    })
+}
+
+/// e.g. "1.2 MB/s"
+fn format_speed(bytes_per_second: f64) -> String {
+    const UNITS: [&str; 4] = ["B/s", "KB/s", "MB/s", "GB/s"];
+    let mut value = bytes_per_second;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// e.g. "2m 30s"
+fn format_eta(eta: std::time::Duration) -> String {
+    let secs = eta.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, (secs % 3600) / 60),
+    }
+}
@@ -1,6 +1,7 @@
 use std::path::PathBuf;
+use std::time::Duration;
 use axum::{
-    extract::{Path, Query, Multipart},
+    extract::{Path, Query},
     http::StatusCode,
     response::Json,
     Extension,
@@ -8,12 +9,9 @@ use axum::{
 use serde::{Deserialize, Serialize};
 use tokio::fs;
 use uuid::Uuid;
-use wallmgr_core::{
-    error::{Error, Result},
-    Config, Database,
-};
-use wallmgr_adapters::Adapter;
-use wallmgr_renderers::Renderer;
+use wallmgr_core::{error::Error, Collection, LibraryQuery, Playlist};
+use wallmgr_connectors::{FavoritesSync, FederatedSearch, WallpaperSearchFilter};
+use wallmgr_core::types::{DownloadJob, DownloadState, TagAlias, TagImplication};
 use crate::state::AppState;
 
 #[derive(Deserialize)]
@@ -30,12 +28,43 @@ pub struct SetWallpaperRequest {
     pub monitor: Option<String>,
 }
 
+#[derive(Deserialize)]
+pub struct GenerateWallpaperRequest {
+    pub spec: wallmgr_core::types::GeneratorSpec,
+    pub name: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+pub struct DownloadRequest {
+    pub source: String,
+    pub id: String,
+    /// Extra tags on top of the ones from the booru
+    pub tags: Option<Vec<String>>,
+    /// Queue priority; higher runs first
+    pub priority: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct DownloadPriorityRequest {
+    pub priority: i32,
+}
+
 #[derive(Deserialize)]
 pub struct SearchRequest {
+    #[serde(default)]
     pub tags: Vec<String>,
+    /// Source names; all enabled sources when empty
+    #[serde(default)]
     pub sources: Vec<String>,
     pub limit: Option<u32>,
     pub rating: Option<String>,
+    /// Only wallpapers that fit this monitor at its native resolution, or
+    /// the combined span of all monitors for `span`
+    pub for_monitor: Option<String>,
+    /// `next_cursor` of an earlier result, to fetch its next page; the
+    /// other fields are then ignored
+    pub cursor: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -53,12 +82,39 @@ pub struct WallpaperResponse {
     pub source: Option<String>,
     pub source_url: Option<String>,
     pub thumbnail_path: Option<String>,
+    pub favorite: bool,
+    pub stars: Option<u8>,
+    pub times_set: u32,
+    /// Seconds shown in total
+    pub display_seconds: u64,
+    pub last_shown: Option<String>,
 }
 
 #[derive(Serialize)]
 pub struct SearchResult {
     pub images: Vec<SearchedImage>,
     pub has_more: bool,
+    pub next_cursor: Option<String>,
+    /// Sources that failed; `images` holds the results of the others
+    pub errors: Vec<SourceError>,
+}
+
+#[derive(Serialize)]
+pub struct SourceError {
+    pub source: String,
+    /// e.g. "rate_limited", "auth_failed", "not_found", "source_unavailable", "parse"
+    pub kind: &'static str,
+    pub message: String,
+}
+
+impl SourceError {
+    fn new(source: &str, error: &Error) -> Self {
+        Self {
+            source: source.to_string(),
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -72,6 +128,8 @@ pub struct SearchedImage {
     pub tags: Vec<String>,
     pub score: Option<i32>,
     pub author: Option<String>,
+    /// Other sources the same image was found on
+    pub also_on: Vec<String>,
 }
 
 #[derive(Serialize)]
@@ -88,6 +146,7 @@ pub struct MonitorResponse {
 pub struct TagResponse {
     pub name: String,
     pub count: i64,
+    pub category: Option<String>,
 }
 
 // Convert database wallpaper to API response
@@ -100,6 +159,10 @@ fn wallpaper_to_response(wallpaper: &wallmgr_core::types::Wallpaper) -> Wallpape
             wallmgr_core::types::WallpaperType::Video => "video".to_string(),
             wallmgr_core::types::WallpaperType::Spine => "spine".to_string(),
             wallmgr_core::types::WallpaperType::WallpaperEngine => "wallpaper_engine".to_string(),
+            wallmgr_core::types::WallpaperType::Live2D => "live2d".to_string(),
+            wallmgr_core::types::WallpaperType::Shader => "shader".to_string(),
+            wallmgr_core::types::WallpaperType::Web => "web".to_string(),
+            wallmgr_core::types::WallpaperType::Generated => "generated".to_string(),
         },
         path: wallpaper.path.clone(),
         width: wallpaper.width,
@@ -111,6 +174,11 @@ fn wallpaper_to_response(wallpaper: &wallmgr_core::types::Wallpaper) -> Wallpape
         source: wallpaper.source.clone(),
         source_url: wallpaper.source_url.clone(),
         thumbnail_path: wallpaper.thumbnail_path.clone(),
+        favorite: wallpaper.stats.favorite,
+        stars: wallpaper.stats.stars,
+        times_set: wallpaper.stats.times_set,
+        display_seconds: wallpaper.stats.display_seconds,
+        last_shown: wallpaper.stats.last_shown.map(|t| t.to_rfc3339()),
     }
 }
 
@@ -122,33 +190,131 @@ pub async fn health_check() -> Json<serde_json::Value> {
     }))
 }
 
+/// `?q=` takes a library query (see [`wallmgr_core::library`]); `type`,
+/// `limit` and `offset` narrow it down
 pub async fn list_wallpapers(
     Query(params): Query<std::collections::HashMap<String, String>>,
     Extension(state): Extension<AppState>,
 ) -> Result<Json<Vec<WallpaperResponse>>, StatusCode> {
-    let mut db = state.database.write().await;
-
-    // Parse filter_type parameter
-    let filter_type = params.get("type").and_then(|ftype| match ftype.as_str() {
-        "image" => Some(wallmgr_core::types::WallpaperType::Image),
-        "video" => Some(wallmgr_core::types::WallpaperType::Video),
-        "spine" => Some(wallmgr_core::types::WallpaperType::Spine),
-        "wallpaper_engine" => Some(wallmgr_core::types::WallpaperType::WallpaperEngine),
-        _ => None,
-    });
-
-    let wallpapers = db
-        .list_wallpapers(filter_type)
+    let mut query = LibraryQuery::parse(params.get("q").map_or("", |q| q.as_str()))
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    if let Some(filter_type) = params.get("type") {
+        query.types = vec![filter_type.parse().map_err(|_| StatusCode::BAD_REQUEST)?];
+    }
+    apply_paging(&mut query, &params)?;
+
+    let db = state.database.lock().await;
+    let page = db
+        .query_wallpapers(&query)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let responses = wallpapers
-        .into_iter()
-        .map(|w| wallpaper_to_response(&w))
+    let responses = page
+        .wallpapers
+        .iter()
+        .map(wallpaper_to_response)
         .collect();
 
     Ok(Json(responses))
 }
 
+/// Take `limit` and `offset` from query parameters
+fn apply_paging(
+    query: &mut LibraryQuery,
+    params: &std::collections::HashMap<String, String>,
+) -> std::result::Result<(), StatusCode> {
+    if let Some(limit) = params.get("limit") {
+        query.limit = Some(limit.parse().map_err(|_| StatusCode::BAD_REQUEST)?);
+    }
+    if let Some(offset) = params.get("offset") {
+        query.offset = offset.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct QueryWallpapersRequest {
+    /// Query text, combined with the structured filters
+    #[serde(default)]
+    pub q: Option<String>,
+    #[serde(flatten)]
+    pub query: LibraryQuery,
+}
+
+#[derive(Serialize)]
+pub struct QueryWallpapersResponse {
+    pub wallpapers: Vec<WallpaperResponse>,
+    /// Matches on all pages
+    pub total: usize,
+}
+
+/// Search the library with a query and structured filters
+pub async fn query_wallpapers(
+    Extension(state): Extension<AppState>,
+    Json(request): Json<QueryWallpapersRequest>,
+) -> Result<Json<QueryWallpapersResponse>, StatusCode> {
+    let mut query = request.query;
+    if let Some(q) = request.q.as_deref() {
+        query.narrow(LibraryQuery::parse(q).map_err(|_| StatusCode::BAD_REQUEST)?);
+    }
+
+    let db = state.database.lock().await;
+    let page = db
+        .query_wallpapers(&query)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(QueryWallpapersResponse {
+        wallpapers: page.wallpapers.iter().map(wallpaper_to_response).collect(),
+        total: page.total,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct RateWallpaperRequest {
+    pub wallpaper_id: Uuid,
+    pub favorite: Option<bool>,
+    /// 1 to 5 stars; 0 clears the rating
+    pub stars: Option<u8>,
+}
+
+/// Mark a wallpaper as favorite and rate it
+pub async fn rate_wallpaper(
+    Extension(state): Extension<AppState>,
+    Json(request): Json<RateWallpaperRequest>,
+) -> Result<Json<WallpaperResponse>, StatusCode> {
+    let db = state.database.lock().await;
+    if let Some(favorite) = request.favorite {
+        db.set_favorite(&request.wallpaper_id, favorite).map_err(library_status)?;
+    }
+    if let Some(stars) = request.stars {
+        db.set_stars(&request.wallpaper_id, (stars > 0).then_some(stars))
+            .map_err(library_status)?;
+    }
+
+    let wallpaper = db
+        .get_wallpaper(&request.wallpaper_id)
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok(Json(wallpaper_to_response(&wallpaper)))
+}
+
+/// Pick a wallpaper matching a query that may be set; by default weighted
+/// towards favorites and higher rated wallpapers
+pub async fn random_wallpaper(
+    Extension(state): Extension<AppState>,
+    Json(request): Json<QueryWallpapersRequest>,
+) -> Result<Json<WallpaperResponse>, StatusCode> {
+    let mut query = request.query;
+    if let Some(q) = request.q.as_deref() {
+        query.narrow(LibraryQuery::parse(q).map_err(|_| StatusCode::BAD_REQUEST)?);
+    }
+
+    let db = state.database.lock().await;
+    let wallpaper = db
+        .pick_wallpaper(&query, |w| state.policy.check_wallpaper(w).is_ok())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(wallpaper_to_response(&wallpaper)))
+}
+
 pub async fn get_wallpaper(
     Path(id): Path<String>,
     Extension(state): Extension<AppState>,
@@ -156,50 +322,63 @@ pub async fn get_wallpaper(
     let wallpaper_id = Uuid::parse_str(&id)
         .map_err(|_| StatusCode::BAD_REQUEST)?;
 
-    let mut db = state.database.write().await;
+    let db = state.database.lock().await;
 
-    // Get wallpaper info
-    let mut wallpaper = db
+    // Get wallpaper info, with its tags
+    let wallpaper = db
         .get_wallpaper(&wallpaper_id)
         .map_err(|_| StatusCode::NOT_FOUND)?;
 
-    // Get tags
-    wallpaper.tags = db
-        .get_wallpaper_tags(&wallpaper_id)
-        .unwrap_or_default();
-
     let response = wallpaper_to_response(&wallpaper);
     Ok(Json(response))
 }
 
 pub async fn add_wallpaper(
-    Json(request): Json<AddWallpaperRequest>,
     Extension(state): Extension<AppState>,
+    Json(request): Json<AddWallpaperRequest>,
 ) -> Result<StatusCode, StatusCode> {
-    // Validate path
+    // Validate path (web wallpapers may also be remote URLs)
+    let is_url = request.path.starts_with("http://") || request.path.starts_with("https://");
     let path = PathBuf::from(&request.path);
-    if !path.exists() {
+    if !is_url && !path.exists() {
         return Err(StatusCode::BAD_REQUEST);
     }
 
     // Get file metadata
-    let metadata = fs::metadata(&path)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let size = if is_url {
+        0
+    } else {
+        let metadata = fs::metadata(&path)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    if !metadata.is_file() && !path.is_dir() {
-        return Err(StatusCode::BAD_REQUEST);
-    }
+        if !metadata.is_file() && !path.is_dir() {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        metadata.len()
+    };
 
     // Determine wallpaper type
     let wallpaper_type = wallmgr_core::types::WallpaperType::from_path(&request.path)
         .ok_or(StatusCode::BAD_REQUEST)?;
 
+    // Reject shaders that don't compile
+    if wallpaper_type == wallmgr_core::types::WallpaperType::Shader {
+        if let Err(e) = wallmgr_renderers::ShaderRenderer::validate(&path) {
+            tracing::warn!("Rejected shader {}: {}", request.path, e);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
     // Generate hash
     use sha2::{Sha256, Digest};
-    let file_content = fs::read(&path)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let file_content = if is_url {
+        request.path.clone().into_bytes()
+    } else {
+        fs::read(&path)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    };
 
     let hash = format!("{:x}", Sha256::digest(&file_content));
 
@@ -207,6 +386,7 @@ pub async fn add_wallpaper(
     let (width, height) = match wallpaper_type {
         wallmgr_core::types::WallpaperType::Image => {
             // Use image crate for static images
+            let path = path.clone();
             tokio::task::spawn_blocking(move || {
                 image::image_dimensions(&path)
             })
@@ -216,7 +396,11 @@ pub async fn add_wallpaper(
         }
         wallmgr_core::types::WallpaperType::Video |
         wallmgr_core::types::WallpaperType::WallpaperEngine |
-        wallmgr_core::types::WallpaperType::Spine => {
+        wallmgr_core::types::WallpaperType::Spine |
+        wallmgr_core::types::WallpaperType::Live2D |
+        wallmgr_core::types::WallpaperType::Shader |
+        wallmgr_core::types::WallpaperType::Web |
+        wallmgr_core::types::WallpaperType::Generated => {
             // TODO: Add proper video/GIF dimension detection using ffmpeg
             // For now use sensible defaults
             (1920, 1080)
@@ -234,17 +418,19 @@ pub async fn add_wallpaper(
         wallpaper_type,
         width,
         height,
-        size: metadata.len(),
+        size,
         hash,
         source: request.source,
         source_url: request.source_url,
         tags: request.tags.unwrap_or_default(),
         thumbnail_path: None,
+        rating: None,
+        stats: Default::default(),
         created_at: chrono::Utc::now(),
         modified_at: chrono::Utc::now(),
     };
 
-    let mut db = state.database.write().await;
+    let db = state.database.lock().await;
     db.add_wallpaper(&wallpaper)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
@@ -252,25 +438,58 @@ pub async fn add_wallpaper(
 }
 
 pub async fn set_wallpaper(
-    Json(request): Json<SetWallpaperRequest>,
     Extension(state): Extension<AppState>,
+    Json(request): Json<SetWallpaperRequest>,
 ) -> Result<StatusCode, StatusCode> {
-    let mut db = state.database.write().await;
+    let db = state.database.lock().await;
 
     // Get wallpaper info
     let wallpaper = db
         .get_wallpaper(&request.wallpaper_id)
         .map_err(|_| StatusCode::NOT_FOUND)?;
+    state
+        .policy
+        .check_wallpaper(&wallpaper)
+        .map_err(|_| StatusCode::FORBIDDEN)?;
 
-    let mut adapter_lock = state.adapter.write().await;
+    // The daemon picks the adapter at startup; none fits this desktop
+    let adapter_lock = state.adapter.read().await;
+    let adapter = adapter_lock.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
 
-    // Initialize adapter if needed
-    if adapter_lock.is_none() {
-        // TODO: Detect and select appropriate adapter
-        todo!("Adapter selection");
-    }
+    // Generated wallpapers are static images rendered for the target monitor
+    if wallpaper.wallpaper_type == wallmgr_core::types::WallpaperType::Generated {
+        let spec = db
+            .get_generator_spec(&wallpaper.id)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .ok_or(StatusCode::NOT_FOUND)?;
+
+        let monitors = adapter
+            .get_trait()
+            .list_monitors()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let monitor = match request.monitor.as_deref() {
+            Some(name) => monitors.iter().find(|m| m.name == name),
+            None => monitors.iter().find(|m| m.primary).or(monitors.first()),
+        }
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+        let image = wallmgr_renderers::generator::ensure_rendered(
+            &spec,
+            std::path::Path::new(&wallpaper.path),
+            monitor.width,
+            monitor.height,
+        )
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let adapter = adapter_lock.as_ref().unwrap();
+        adapter
+            .get_trait()
+            .set_wallpaper(&image, request.monitor.as_deref(), wallmgr_core::types::DisplayMode::Fill)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        db.record_shown(&wallpaper.id, request.monitor.as_deref())
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        return Ok(StatusCode::OK);
+    }
 
     // Create renderer based on wallpaper type
     let mut renderer_lock = state.renderer.write().await;
@@ -283,28 +502,198 @@ pub async fn set_wallpaper(
                 Some(Renderer::Video(VideoRenderer::new()))
             },
             wallmgr_core::types::WallpaperType::Spine => {
-                use wallmgr_renderers::{Renderer, SpineRenderer};
-                Some(Renderer::Spine(SpineRenderer::new()))
+                use wallmgr_renderers::{Renderer, SpineOptions, SpineRenderer};
+                Some(Renderer::Spine(SpineRenderer::with_options(SpineOptions {
+                    fps: state.config.renderer.video_fps,
+                    ..SpineOptions::default()
+                })))
             },
             wallmgr_core::types::WallpaperType::WallpaperEngine => {
                 use wallmgr_renderers::{Renderer, WallpaperEngineRenderer};
                 Some(Renderer::WallpaperEngine(WallpaperEngineRenderer::new()))
             },
+            wallmgr_core::types::WallpaperType::Live2D => {
+                use wallmgr_renderers::{Live2DOptions, Live2DRenderer, Renderer};
+                Some(Renderer::Live2D(Live2DRenderer::with_options(Live2DOptions {
+                    follow_mouse: state.config.renderer.live2d_follow_mouse,
+                    core_path: state.config.renderer.live2d_core_path.clone(),
+                    fps: state.config.renderer.video_fps,
+                    ..Live2DOptions::default()
+                })))
+            },
+            wallmgr_core::types::WallpaperType::Shader => {
+                use wallmgr_renderers::{Renderer, ShaderOptions, ShaderRenderer};
+                Some(Renderer::Shader(ShaderRenderer::with_options(ShaderOptions {
+                    fps: state.config.renderer.video_fps,
+                    software: !state.config.renderer.hardware_accel,
+                    ..ShaderOptions::default()
+                })))
+            },
+            wallmgr_core::types::WallpaperType::Web => {
+                use wallmgr_renderers::{Renderer, WebOptions, WebRenderer};
+                Some(Renderer::Web(WebRenderer::with_options(WebOptions {
+                    offline: state.config.renderer.web_offline,
+                    browser: state.config.renderer.web_browser.clone(),
+                    fps: state.config.renderer.video_fps,
+                    ..WebOptions::default()
+                })))
+            },
+            // Handled above without a renderer
+            wallmgr_core::types::WallpaperType::Generated => None,
         };
 
         *renderer_lock = renderer;
     }
 
     if let Some(renderer) = renderer_lock.as_mut() {
+        let renderer = renderer.get_trait_mut();
+        // In-process renderers can't draw in every session (e.g. Wayland)
+        if !renderer.is_available() {
+            tracing::warn!("The {} renderer is not available in this session", renderer.name());
+            return Err(StatusCode::SERVICE_UNAVAILABLE);
+        }
         renderer
-            .get_trait_mut()
             .start(std::path::Path::new(&wallpaper.path), request.monitor.as_deref())
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     }
 
+    db.record_shown(&wallpaper.id, request.monitor.as_deref())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     Ok(StatusCode::OK)
 }
 
+/// Monitors to render generated wallpapers for; falls back to 1920x1080
+/// when no adapter is active yet
+async fn current_monitors(state: &AppState) -> Vec<wallmgr_core::types::Monitor> {
+    let adapter_lock = state.adapter.read().await;
+    let monitors = adapter_lock
+        .as_ref()
+        .and_then(|adapter| adapter.get_trait().list_monitors().ok())
+        .unwrap_or_default();
+
+    if monitors.is_empty() {
+        vec![wallmgr_core::types::Monitor {
+            name: "default".to_string(),
+            width: 1920,
+            height: 1080,
+            x: 0,
+            y: 0,
+            primary: true,
+        }]
+    } else {
+        monitors
+    }
+}
+
+pub async fn generate_wallpaper(
+    Extension(state): Extension<AppState>,
+    Json(request): Json<GenerateWallpaperRequest>,
+) -> Result<(StatusCode, Json<WallpaperResponse>), StatusCode> {
+    use sha2::{Sha256, Digest};
+
+    let spec_json = serde_json::to_string(&request.spec)
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let hash = format!("{:x}", Sha256::digest(spec_json.as_bytes()));
+
+    // Same parameters were generated before
+    let exists = state
+        .database
+        .lock()
+        .await
+        .has_wallpaper_with_hash(&hash)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if exists {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let id = Uuid::new_v4();
+    let dir = state.config.data_dir.join("generated").join(id.to_string());
+    let monitors = current_monitors(&state).await;
+    let primary = monitors
+        .iter()
+        .find(|m| m.primary)
+        .unwrap_or(&monitors[0])
+        .clone();
+
+    // Render every monitor resolution up front
+    let spec = request.spec.clone();
+    let render_dir = dir.clone();
+    let size = tokio::task::spawn_blocking(move || -> wallmgr_core::Result<u64> {
+        let mut size = 0;
+        for monitor in &monitors {
+            let file = wallmgr_renderers::generator::ensure_rendered(&spec, &render_dir, monitor.width, monitor.height)?;
+            size += std::fs::metadata(file)?.len();
+        }
+        Ok(size)
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let wallpaper = wallmgr_core::types::Wallpaper {
+        id,
+        path: dir.to_string_lossy().to_string(),
+        filename: request
+            .name
+            .unwrap_or_else(|| request.spec.kind().to_string()),
+        wallpaper_type: wallmgr_core::types::WallpaperType::Generated,
+        width: primary.width,
+        height: primary.height,
+        size,
+        hash,
+        source: Some("generated".to_string()),
+        source_url: None,
+        tags: request.tags.unwrap_or_default(),
+        thumbnail_path: None,
+        rating: None,
+        stats: Default::default(),
+        created_at: chrono::Utc::now(),
+        modified_at: chrono::Utc::now(),
+    };
+
+    let db = state.database.lock().await;
+    if db
+        .add_wallpaper(&wallpaper)
+        .and_then(|_| db.set_generator_spec(&wallpaper.id, &request.spec))
+        .is_err()
+    {
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = db.delete_wallpaper(&wallpaper.id);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    Ok((StatusCode::CREATED, Json(wallpaper_to_response(&wallpaper))))
+}
+
+/// Re-render generated wallpapers for the current monitor layout
+pub async fn rerender_generated(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let monitors = current_monitors(&state).await;
+    let generated = state
+        .database
+        .lock()
+        .await
+        .list_generated()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let count = generated.len();
+    tokio::task::spawn_blocking(move || -> wallmgr_core::Result<()> {
+        for (wallpaper, spec) in &generated {
+            let dir = std::path::Path::new(&wallpaper.path);
+            for monitor in &monitors {
+                wallmgr_renderers::generator::ensure_rendered(spec, dir, monitor.width, monitor.height)?;
+            }
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(serde_json::json!({ "rendered": count })))
+}
+
 pub async fn delete_wallpaper(
     Path(id): Path<String>,
     Extension(state): Extension<AppState>,
@@ -312,7 +701,15 @@ pub async fn delete_wallpaper(
     let wallpaper_id = Uuid::parse_str(&id)
         .map_err(|_| StatusCode::BAD_REQUEST)?;
 
-    let mut db = state.database.write().await;
+    let db = state.database.lock().await;
+
+    // Generated wallpapers own their rendered images
+    if let Ok(wallpaper) = db.get_wallpaper(&wallpaper_id) {
+        if wallpaper.wallpaper_type == wallmgr_core::types::WallpaperType::Generated {
+            let _ = fs::remove_dir_all(&wallpaper.path).await;
+        }
+    }
+
     db.delete_wallpaper(&wallpaper_id)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
@@ -320,73 +717,232 @@ pub async fn delete_wallpaper(
 }
 
 pub async fn search_booru(
-    Json(request): Json<SearchRequest>,
     Extension(state): Extension<AppState>,
+    Json(request): Json<SearchRequest>,
 ) -> Result<Json<SearchResult>, StatusCode> {
     let limit = request.limit.unwrap_or(20).min(100);
-    let mut all_images = Vec::new();
-
-    // Search each requested source
-    for source in request.sources {
-        let connector = match source.as_str() {
-            "danbooru" => &state.booru_clients.danbooru,
-            "yandere" => &state.booru_clients.yandere,
-            "safebooru" => &state.booru_clients.safebooru,
-            "gelbooru" => &state.booru_clients.gelbooru,
-            _ => continue,
-        };
+    let search = FederatedSearch::new(
+        state.connectors.clone(),
+        Duration::from_secs(state.config.network.search_timeout_secs),
+    )
+    .with_policy(state.policy.clone());
 
-        match connector.search(&request.tags, limit, 1).await {
-            Ok(images) => {
-                for img in images {
-                    all_images.push(SearchedImage {
-                        id: img.id,
-                        source: source.clone(),
-                        url: img.file_url,
-                        preview_url: img.preview_url,
-                        width: img.width,
-                        height: img.height,
-                        tags: img.tags,
-                        score: img.score,
-                        author: img.author,
-                    });
-                }
-            }
-            Err(_) => continue, // Skip failed sources
-        }
+    let mut tags = request.tags;
+    if let Some(name) = &request.for_monitor {
+        let adapter_lock = state.adapter.read().await;
+        let monitors = adapter_lock
+            .as_ref()
+            .and_then(|adapter| adapter.get_trait().list_monitors().ok())
+            .unwrap_or_default();
+        let filter = WallpaperSearchFilter::for_monitor_named(&monitors, name)
+            .map_err(|_| StatusCode::NOT_FOUND)?;
+        tags.extend(filter.query_terms());
     }
 
-    // Sort by score if available
-    all_images.sort_by(|a, b| {
-        match (a.score, b.score) {
-            (Some(a_score), Some(b_score)) => b_score.cmp(&a_score),
-            (Some(_), None) => std::cmp::Ordering::Greater,
-            (None, Some(_)) => std::cmp::Ordering::Less,
-            _ => std::cmp::Ordering::Equal,
-        }
-    });
+    // Search the requested sources, or every enabled one if none are given
+    let page = match &request.cursor {
+        Some(cursor) => search.next(cursor).await,
+        None => search.search(&request.sources, &tags, limit).await,
+    }
+    // An invalid cursor or query
+    .map_err(|_| StatusCode::BAD_REQUEST)?;
 
-    let has_more = all_images.len() > limit as usize;
-    all_images.truncate(limit as usize);
+    let errors = page
+        .errors
+        .iter()
+        .map(|(source, e)| {
+            tracing::warn!("Search of {} failed: {}", source, e);
+            SourceError::new(source, e)
+        })
+        .collect();
+    let images = page
+        .images
+        .into_iter()
+        .map(|found| SearchedImage {
+            id: found.image.id,
+            source: found.image.source,
+            url: found.image.file_url,
+            preview_url: found.image.preview_url,
+            width: found.image.width,
+            height: found.image.height,
+            tags: found.image.tags,
+            score: found.image.score,
+            author: found.image.author,
+            also_on: found.also_on,
+        })
+        .collect();
 
     Ok(Json(SearchResult {
-        images: all_images,
-        has_more,
+        images,
+        has_more: page.cursor.is_some(),
+        next_cursor: page.cursor,
+        errors,
     }))
 }
 
 pub async fn download_image(
-    Json(request): Json<serde_json::Value>,
+    Extension(state): Extension<AppState>,
+    Json(request): Json<DownloadRequest>,
+) -> Result<(StatusCode, Json<DownloadJob>), StatusCode> {
+    let connector = state
+        .connectors
+        .get(&request.source)
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    let mut image = connector
+        .get_image(&request.id)
+        .await
+        .map_err(source_status)?;
+    image.tags.extend(request.tags.unwrap_or_default());
+
+    // Progress and the result are reported over the WebSocket
+    let job = state
+        .download_queue
+        .enqueue(&image, request.priority.unwrap_or(0))
+        .map_err(|e| match e {
+            Error::Blocked(_) => StatusCode::FORBIDDEN,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+pub async fn list_downloads(
+    Query(params): Query<std::collections::HashMap<String, String>>,
+    Extension(state): Extension<AppState>,
+) -> Result<Json<Vec<DownloadJob>>, StatusCode> {
+    let filter = params
+        .get("state")
+        .map(|s| s.parse::<DownloadState>())
+        .transpose()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let jobs = state
+        .download_queue
+        .jobs(filter)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(jobs))
+}
+
+pub async fn pause_download(
+    Path(id): Path<i64>,
+    Extension(state): Extension<AppState>,
+) -> Result<Json<DownloadJob>, StatusCode> {
+    state
+        .download_queue
+        .pause(id)
+        .map(Json)
+        .map_err(download_status)
+}
+
+pub async fn resume_download(
+    Path(id): Path<i64>,
+    Extension(state): Extension<AppState>,
+) -> Result<Json<DownloadJob>, StatusCode> {
+    state
+        .download_queue
+        .resume(id)
+        .map(Json)
+        .map_err(download_status)
+}
+
+pub async fn cancel_download(
+    Path(id): Path<i64>,
     Extension(state): Extension<AppState>,
 ) -> Result<StatusCode, StatusCode> {
-    // TODO: Implement download and add to library
-    // This would require:
-    // 1. Download image to temp
-    // 2. Move to library directory
-    // 3. Add to database
-    // 4. Generate thumbnail
+    state
+        .download_queue
+        .cancel(id)
+        .await
+        .map_err(download_status)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn set_download_priority(
+    Path(id): Path<i64>,
+    Extension(state): Extension<AppState>,
+    Json(request): Json<DownloadPriorityRequest>,
+) -> Result<Json<DownloadJob>, StatusCode> {
+    state
+        .download_queue
+        .set_priority(id, request.priority)
+        .map(Json)
+        .map_err(download_status)
+}
+
+fn download_status(error: Error) -> StatusCode {
+    match error {
+        Error::NotFound(_) => StatusCode::NOT_FOUND,
+        Error::InvalidInput(_) => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Serialize)]
+pub struct SourceResponse {
+    pub name: String,
+    pub engine: wallmgr_core::config::ConnectorEngine,
+    pub base_url: String,
+    pub authenticated: bool,
+}
+
+pub async fn list_sources(
+    Extension(state): Extension<AppState>,
+) -> Json<Vec<SourceResponse>> {
+    let sources = state
+        .connectors
+        .configs()
+        .map(|config| SourceResponse {
+            name: config.name.clone(),
+            engine: config.engine,
+            base_url: config.base_url.clone(),
+            authenticated: config.authenticated(),
+        })
+        .collect();
+    Json(sources)
+}
 
-    Ok(StatusCode::NOT_IMPLEMENTED)
+#[derive(Serialize)]
+pub struct SyncResponse {
+    /// Favorites newly queued for download
+    pub queued: usize,
+}
+
+/// Queue the source account's favorites that aren't in the library yet
+pub async fn sync_favorites(
+    Path(name): Path<String>,
+    Extension(state): Extension<AppState>,
+) -> Result<Json<SyncResponse>, StatusCode> {
+    let config = state
+        .connectors
+        .configs()
+        .find(|c| c.name == name)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let connector = state.connectors.get(&name).ok_or(StatusCode::NOT_FOUND)?;
+
+    let sync = FavoritesSync::new(
+        state.connectors.clone(),
+        state.download_queue.clone(),
+        state.config.favorites.clone(),
+    );
+    let queued = sync
+        .sync_source(config, connector.as_ref())
+        .await
+        .map_err(|e| match e {
+            Error::Config(_) | Error::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            e => source_status(e),
+        })?;
+    Ok(Json(SyncResponse { queued }))
+}
+
+/// Status for a request to a source that failed
+fn source_status(error: Error) -> StatusCode {
+    match error {
+        Error::NotFound(_) => StatusCode::NOT_FOUND,
+        Error::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+        Error::SourceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::BAD_GATEWAY,
+    }
 }
 
 pub async fn autocomplete_tags(
@@ -398,7 +954,7 @@ pub async fn autocomplete_tags(
         .map(|s| s.as_str())
         .unwrap_or("");
 
-    let mut db = state.database.write().await;
+    let db = state.database.lock().await;
     let tags = db
         .search_tags(prefix, 20)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -416,7 +972,7 @@ pub async fn search_tags(
         .map(|s| s.as_str())
         .unwrap_or("");
 
-    let mut db = state.database.write().await;
+    let db = state.database.lock().await;
     let tags = db
         .search_tags(query, 50)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -426,12 +982,385 @@ pub async fn search_tags(
         .map(|t| TagResponse {
             name: t.name,
             count: t.count,
+            category: t.category.map(|c| c.as_str().to_string()),
         })
         .collect();
 
     Ok(Json(responses))
 }
 
+#[derive(Deserialize)]
+pub struct EditTagsRequest {
+    pub wallpaper_ids: Vec<Uuid>,
+    #[serde(default)]
+    pub add: Vec<String>,
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct EditTagsResponse {
+    pub added: usize,
+    pub removed: usize,
+}
+
+/// Add and remove tags on many wallpapers at once
+pub async fn edit_wallpaper_tags(
+    Extension(state): Extension<AppState>,
+    Json(request): Json<EditTagsRequest>,
+) -> Result<Json<EditTagsResponse>, StatusCode> {
+    let (added, removed) = state
+        .database
+        .lock()
+        .await
+        .edit_tags(&request.wallpaper_ids, &request.add, &request.remove)
+        .map_err(library_status)?;
+    Ok(Json(EditTagsResponse { added, removed }))
+}
+
+#[derive(Deserialize)]
+pub struct RenameTagRequest {
+    pub from: String,
+    pub to: String,
+}
+
+pub async fn rename_tag(
+    Extension(state): Extension<AppState>,
+    Json(request): Json<RenameTagRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let db = state.database.lock().await;
+    db.rename_tag(&request.from, &request.to).map_err(library_status)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+pub struct MergeTagRequest {
+    pub from: String,
+    pub into: String,
+}
+
+pub async fn merge_tag(
+    Extension(state): Extension<AppState>,
+    Json(request): Json<MergeTagRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let db = state.database.lock().await;
+    db.merge_tag(&request.from, &request.into).map_err(library_status)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_tag_aliases(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<Vec<TagAlias>>, StatusCode> {
+    let db = state.database.lock().await;
+    db.list_aliases().map(Json).map_err(library_status)
+}
+
+pub async fn add_tag_alias(
+    Extension(state): Extension<AppState>,
+    Json(alias): Json<TagAlias>,
+) -> Result<StatusCode, StatusCode> {
+    let db = state.database.lock().await;
+    db.add_alias(&alias.alias, &alias.tag).map_err(library_status)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn remove_tag_alias(
+    Path(alias): Path<String>,
+    Extension(state): Extension<AppState>,
+) -> Result<StatusCode, StatusCode> {
+    let db = state.database.lock().await;
+    db.remove_alias(&alias).map_err(library_status)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_tag_implications(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<Vec<TagImplication>>, StatusCode> {
+    let db = state.database.lock().await;
+    db.list_implications().map(Json).map_err(library_status)
+}
+
+pub async fn add_tag_implication(
+    Extension(state): Extension<AppState>,
+    Json(implication): Json<TagImplication>,
+) -> Result<StatusCode, StatusCode> {
+    let db = state.database.lock().await;
+    db.add_implication(&implication.tag, &implication.implied)
+        .map_err(library_status)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn remove_tag_implication(
+    Path((tag, implied)): Path<(String, String)>,
+    Extension(state): Extension<AppState>,
+) -> Result<StatusCode, StatusCode> {
+    let db = state.database.lock().await;
+    db.remove_implication(&tag, &implied).map_err(library_status)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Rounds of implications of implied tags followed on import
+const IMPLICATION_DEPTH: usize = 3;
+
+#[derive(Serialize)]
+pub struct TagImportResponse {
+    pub aliases: usize,
+    pub implications: usize,
+}
+
+/// Import a source's aliases and implications of the library's tags
+pub async fn import_tag_relations(
+    Path(name): Path<String>,
+    Extension(state): Extension<AppState>,
+) -> Result<Json<TagImportResponse>, StatusCode> {
+    let connector = state.connectors.get(&name).ok_or(StatusCode::NOT_FOUND)?;
+    let tags = state
+        .database
+        .lock()
+        .await
+        .all_tag_names()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let import_status = |e: Error| match e {
+        Error::InvalidInput(_) => StatusCode::BAD_REQUEST,
+        e => source_status(e),
+    };
+
+    let aliases = connector.tag_aliases(&tags).await.map_err(import_status)?;
+    let mut implications = Vec::new();
+    let mut seen: std::collections::HashSet<String> = tags.iter().cloned().collect();
+    let mut pending = tags;
+    for _ in 0..IMPLICATION_DEPTH {
+        if pending.is_empty() {
+            break;
+        }
+        let found = connector.tag_implications(&pending).await.map_err(import_status)?;
+        pending = found
+            .iter()
+            .map(|i| i.implied.clone())
+            .filter(|tag| seen.insert(tag.clone()))
+            .collect();
+        implications.extend(found);
+    }
+
+    // Relations conflicting with local ones are skipped
+    let db = state.database.lock().await;
+    let mut imported = TagImportResponse { aliases: 0, implications: 0 };
+    for alias in &aliases {
+        match db.add_alias(&alias.alias, &alias.tag) {
+            Ok(()) => imported.aliases += 1,
+            Err(Error::InvalidInput(e)) => tracing::warn!("Alias not imported: {}", e),
+            Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+        }
+    }
+    for implication in &implications {
+        match db.add_implication(&implication.tag, &implication.implied) {
+            Ok(()) => imported.implications += 1,
+            Err(Error::InvalidInput(e)) => tracing::warn!("Implication not imported: {}", e),
+            Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+        }
+    }
+    Ok(Json(imported))
+}
+
+#[derive(Deserialize)]
+pub struct CollectionRequest {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CollectionItemsRequest {
+    pub wallpaper_ids: Vec<Uuid>,
+    /// Where to insert them; at the end by default
+    #[serde(default)]
+    pub position: Option<usize>,
+}
+
+pub async fn list_collections(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<Vec<Collection>>, StatusCode> {
+    let db = state.database.lock().await;
+    db.list_collections().map(Json).map_err(library_status)
+}
+
+pub async fn create_collection(
+    Extension(state): Extension<AppState>,
+    Json(request): Json<CollectionRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let db = state.database.lock().await;
+    db.create_collection(&request.name, request.description.as_deref())
+        .map_err(library_status)?;
+    Ok(StatusCode::CREATED)
+}
+
+pub async fn get_collection(
+    Path(name): Path<String>,
+    Extension(state): Extension<AppState>,
+) -> Result<Json<Collection>, StatusCode> {
+    let db = state.database.lock().await;
+    db.get_collection(&name).map(Json).map_err(library_status)
+}
+
+/// Rename a collection and change its description
+pub async fn update_collection(
+    Path(name): Path<String>,
+    Extension(state): Extension<AppState>,
+    Json(request): Json<CollectionRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let db = state.database.lock().await;
+    db.update_collection(&name, &request.name, request.description.as_deref())
+        .map_err(library_status)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn delete_collection(
+    Path(name): Path<String>,
+    Extension(state): Extension<AppState>,
+) -> Result<StatusCode, StatusCode> {
+    let db = state.database.lock().await;
+    db.delete_collection(&name).map_err(library_status)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Add wallpapers to a collection, or move them within it
+pub async fn add_to_collection(
+    Path(name): Path<String>,
+    Extension(state): Extension<AppState>,
+    Json(request): Json<CollectionItemsRequest>,
+) -> Result<Json<Collection>, StatusCode> {
+    let db = state.database.lock().await;
+    db.add_to_collection(&name, &request.wallpaper_ids, request.position)
+        .map_err(library_status)?;
+    db.get_collection(&name).map(Json).map_err(library_status)
+}
+
+pub async fn remove_from_collection(
+    Path(name): Path<String>,
+    Extension(state): Extension<AppState>,
+    Json(request): Json<CollectionItemsRequest>,
+) -> Result<Json<Collection>, StatusCode> {
+    let db = state.database.lock().await;
+    db.remove_from_collection(&name, &request.wallpaper_ids)
+        .map_err(library_status)?;
+    db.get_collection(&name).map(Json).map_err(library_status)
+}
+
+/// Wallpapers of a collection in its order; takes `limit` and `offset`
+pub async fn collection_wallpapers(
+    Path(name): Path<String>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+    Extension(state): Extension<AppState>,
+) -> Result<Json<QueryWallpapersResponse>, StatusCode> {
+    let mut query = LibraryQuery::default();
+    query.collections = vec![name.clone()];
+    apply_paging(&mut query, &params)?;
+
+    let db = state.database.lock().await;
+    db.get_collection(&name).map_err(library_status)?;
+    let page = db.query_wallpapers(&query).map_err(library_status)?;
+    Ok(Json(QueryWallpapersResponse {
+        wallpapers: page.wallpapers.iter().map(wallpaper_to_response).collect(),
+        total: page.total,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct PlaylistRequest {
+    pub name: String,
+    /// Query text, parsed whenever the playlist is used
+    #[serde(default)]
+    pub q: Option<String>,
+    #[serde(flatten)]
+    pub query: LibraryQuery,
+}
+
+pub async fn list_playlists(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<Vec<Playlist>>, StatusCode> {
+    let db = state.database.lock().await;
+    db.list_playlists().map(Json).map_err(library_status)
+}
+
+pub async fn create_playlist(
+    Extension(state): Extension<AppState>,
+    Json(request): Json<PlaylistRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let playlist = Playlist {
+        name: request.name,
+        q: request.q,
+        query: request.query,
+        created_at: chrono::Utc::now(),
+    };
+
+    let db = state.database.lock().await;
+    db.create_playlist(&playlist).map_err(library_status)?;
+    Ok(StatusCode::CREATED)
+}
+
+pub async fn get_playlist(
+    Path(name): Path<String>,
+    Extension(state): Extension<AppState>,
+) -> Result<Json<Playlist>, StatusCode> {
+    let db = state.database.lock().await;
+    db.get_playlist(&name).map(Json).map_err(library_status)
+}
+
+/// Replace a playlist's name and query
+pub async fn update_playlist(
+    Path(name): Path<String>,
+    Extension(state): Extension<AppState>,
+    Json(request): Json<PlaylistRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let db = state.database.lock().await;
+    let existing = db.get_playlist(&name).map_err(library_status)?;
+    let playlist = Playlist {
+        name: request.name,
+        q: request.q,
+        query: request.query,
+        created_at: existing.created_at,
+    };
+    db.update_playlist(&name, &playlist).map_err(library_status)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn delete_playlist(
+    Path(name): Path<String>,
+    Extension(state): Extension<AppState>,
+) -> Result<StatusCode, StatusCode> {
+    let db = state.database.lock().await;
+    db.delete_playlist(&name).map_err(library_status)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Wallpapers a playlist matches now; takes `limit` and `offset`
+pub async fn playlist_wallpapers(
+    Path(name): Path<String>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+    Extension(state): Extension<AppState>,
+) -> Result<Json<QueryWallpapersResponse>, StatusCode> {
+    let mut query = LibraryQuery::default();
+    query.playlists = vec![name];
+    apply_paging(&mut query, &params)?;
+
+    let db = state.database.lock().await;
+    let page = db.query_wallpapers(&query).map_err(library_status)?;
+    Ok(Json(QueryWallpapersResponse {
+        wallpapers: page.wallpapers.iter().map(wallpaper_to_response).collect(),
+        total: page.total,
+    }))
+}
+
+/// Status for a failed edit of tags, ratings, collections or playlists
+fn library_status(error: Error) -> StatusCode {
+    match error {
+        Error::NotFound(_) => StatusCode::NOT_FOUND,
+        Error::AlreadyExists(_) => StatusCode::CONFLICT,
+        Error::InvalidInput(_) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
 pub async fn list_monitors(
     Extension(state): Extension<AppState>,
 ) -> Result<Json<Vec<MonitorResponse>>, StatusCode> {
@@ -439,6 +1368,7 @@ pub async fn list_monitors(
 
     if let Some(adapter) = &*adapter_lock {
         let monitors = adapter
+            .get_trait()
             .list_monitors()
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
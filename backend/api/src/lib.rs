@@ -1,5 +1,6 @@
 pub mod routes;
 pub mod handlers;
+pub mod downloads;
 pub mod state;
 pub mod websocket;
 
@@ -1,18 +1,15 @@
 use axum::{
-    middleware,
-    routing::{get, post, delete, put, get_service},
+    routing::{get, post, delete, put},
     Router,
     Extension,
 };
 use tower_http::services::ServeDir;
-use std::sync::Arc;
-use std::path::PathBuf;
 use crate::state::AppState;
 use crate::handlers::*;
 use crate::websocket::ws_handler;
 
 /// Configure file server for thumbnails and assets
-pub fn file_routes() -> Router<Extension<AppState>> {
+pub fn file_routes() -> Router {
     Router::new()
         .nest_service("/thumbnails", ServeDir::new("thumbnails"))
         .nest_service("/assets", ServeDir::new("assets"))
@@ -26,15 +23,46 @@ pub fn create_router(state: AppState) -> Router {
         .route("/wallpapers/:id", get(get_wallpaper))
         .route("/wallpapers/:id", delete(delete_wallpaper))
         .route("/wallpapers/add", post(add_wallpaper))
+        .route("/wallpapers/query", post(query_wallpapers))
+        .route("/wallpapers/random", post(random_wallpaper))
+        .route("/wallpapers/rate", post(rate_wallpaper))
         .route("/wallpapers/set", post(set_wallpaper))
+        .route("/wallpapers/generate", post(generate_wallpaper))
+        .route("/wallpapers/generated/rerender", post(rerender_generated))
 
         // Search and download
         .route("/search", post(search_booru))
         .route("/search/download", post(download_image))
+        .route("/sources", get(list_sources))
+        .route("/sources/:name/sync", post(sync_favorites))
+
+        // Download queue
+        .route("/downloads", get(list_downloads))
+        .route("/downloads/:id", delete(cancel_download))
+        .route("/downloads/:id/pause", post(pause_download))
+        .route("/downloads/:id/resume", post(resume_download))
+        .route("/downloads/:id/priority", put(set_download_priority))
 
         // Tags
         .route("/tags/autocomplete", get(autocomplete_tags))
         .route("/tags/search", get(search_tags))
+        .route("/tags/rename", post(rename_tag))
+        .route("/tags/merge", post(merge_tag))
+        .route("/tags/aliases", get(list_tag_aliases).post(add_tag_alias))
+        .route("/tags/aliases/:alias", delete(remove_tag_alias))
+        .route("/tags/implications", get(list_tag_implications).post(add_tag_implication))
+        .route("/tags/implications/:tag/:implied", delete(remove_tag_implication))
+        .route("/tags/import/:source", post(import_tag_relations))
+        .route("/wallpapers/tags", post(edit_wallpaper_tags))
+
+        // Collections and smart playlists
+        .route("/collections", get(list_collections).post(create_collection))
+        .route("/collections/:name", get(get_collection).put(update_collection).delete(delete_collection))
+        .route("/collections/:name/wallpapers", get(collection_wallpapers).post(add_to_collection))
+        .route("/collections/:name/remove", post(remove_from_collection))
+        .route("/playlists", get(list_playlists).post(create_playlist))
+        .route("/playlists/:name", get(get_playlist).put(update_playlist).delete(delete_playlist))
+        .route("/playlists/:name/wallpapers", get(playlist_wallpapers))
 
         // System info
         .route("/monitors", get(list_monitors))
@@ -43,7 +71,7 @@ pub fn create_router(state: AppState) -> Router {
         // WebSocket
         .route("/ws", get(ws_handler));
 
-    let app = Router::new()
+    Router::new()
         // API v1 routes
         .nest("/api/v1", api_routes)
 
@@ -53,11 +81,38 @@ pub fn create_router(state: AppState) -> Router {
             .route("/wallpapers/:id", get(get_wallpaper))
             .route("/wallpapers/:id", delete(delete_wallpaper))
             .route("/wallpapers/add", post(add_wallpaper))
+            .route("/wallpapers/query", post(query_wallpapers))
+            .route("/wallpapers/random", post(random_wallpaper))
+            .route("/wallpapers/rate", post(rate_wallpaper))
             .route("/wallpapers/set", post(set_wallpaper))
+            .route("/wallpapers/generate", post(generate_wallpaper))
+            .route("/wallpapers/generated/rerender", post(rerender_generated))
             .route("/search", post(search_booru))
             .route("/search/download", post(download_image))
+            .route("/sources", get(list_sources))
+            .route("/sources/:name/sync", post(sync_favorites))
+            .route("/downloads", get(list_downloads))
+            .route("/downloads/:id", delete(cancel_download))
+            .route("/downloads/:id/pause", post(pause_download))
+            .route("/downloads/:id/resume", post(resume_download))
+            .route("/downloads/:id/priority", put(set_download_priority))
             .route("/tags/autocomplete", get(autocomplete_tags))
             .route("/tags/search", get(search_tags))
+            .route("/tags/rename", post(rename_tag))
+            .route("/tags/merge", post(merge_tag))
+            .route("/tags/aliases", get(list_tag_aliases).post(add_tag_alias))
+            .route("/tags/aliases/:alias", delete(remove_tag_alias))
+            .route("/tags/implications", get(list_tag_implications).post(add_tag_implication))
+            .route("/tags/implications/:tag/:implied", delete(remove_tag_implication))
+            .route("/tags/import/:source", post(import_tag_relations))
+            .route("/wallpapers/tags", post(edit_wallpaper_tags))
+            .route("/collections", get(list_collections).post(create_collection))
+            .route("/collections/:name", get(get_collection).put(update_collection).delete(delete_collection))
+            .route("/collections/:name/wallpapers", get(collection_wallpapers).post(add_to_collection))
+            .route("/collections/:name/remove", post(remove_from_collection))
+            .route("/playlists", get(list_playlists).post(create_playlist))
+            .route("/playlists/:name", get(get_playlist).put(update_playlist).delete(delete_playlist))
+            .route("/playlists/:name/wallpapers", get(playlist_wallpapers))
             .route("/monitors", get(list_monitors))
             .route("/health", get(health_check))
             .route("/ws", get(ws_handler))
@@ -66,7 +121,63 @@ pub fn create_router(state: AppState) -> Router {
         // File serving
         .nest_service("/files", file_routes())
 
-        .layer(Extension(state));
+        .layer(Extension(state))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+    use wallmgr_core::{Config, Database};
+
+    fn app(dir: &std::path::Path) -> Router {
+        let config = Config {
+            data_dir: dir.to_path_buf(),
+            cache_dir: dir.join("cache"),
+            thumbnails_dir: dir.join("thumbnails"),
+            database_path: dir.join("wallmgr.db"),
+            ..Config::default()
+        };
+        let database = Database::new(&config.database_path).unwrap();
+        create_router(AppState::new(config, database).unwrap())
+    }
 
-    app
-}
\ No newline at end of file
+    async fn send(app: &Router, method: &str, uri: &str, body: serde_json::Value) -> (StatusCode, serde_json::Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn test_library_routes() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = app(dir.path());
+        let none = serde_json::Value::Null;
+
+        assert_eq!(send(&app, "GET", "/api/health", none.clone()).await.0, StatusCode::OK);
+        let created = send(&app, "POST", "/api/v1/collections", serde_json::json!({"name": "walls"})).await;
+        assert_eq!(created.0, StatusCode::CREATED);
+        let (status, collection) = send(&app, "GET", "/api/v1/collections/walls", none.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(collection["name"], "walls");
+
+        let playlist = serde_json::json!({"name": "mine", "q": "in:walls"});
+        assert_eq!(send(&app, "POST", "/api/v1/playlists", playlist).await.0, StatusCode::CREATED);
+        let (status, page) = send(&app, "GET", "/api/v1/playlists/mine/wallpapers", none.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(page["total"], 0);
+
+        let missing = serde_json::json!({"wallpaper_id": uuid::Uuid::new_v4()});
+        assert_eq!(send(&app, "POST", "/api/v1/wallpapers/set", missing).await.0, StatusCode::NOT_FOUND);
+        let (status, _) = send(&app, "GET", "/api/v1/wallpapers?q=rating:", none).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
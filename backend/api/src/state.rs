@@ -1,45 +1,113 @@
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use wallmgr_core::{Config, Database};
+use tokio::sync::{Mutex, RwLock};
+use wallmgr_core::{Config, ContentPolicy, Database};
 use wallmgr_adapters::Adapter;
 use wallmgr_renderers::Renderer;
-use wallmgr_connectors::danbooru::DanbooruConnector;
-use wallmgr_connectors::yandere::YandereConnector;
-use wallmgr_connectors::safebooru::SafebooruConnector;
-use wallmgr_connectors::gelbooru::GelbooruConnector;
+use wallmgr_connectors::{ConnectorRegistry, DailyWallpaper, DownloadQueue, FavoritesSync};
+use wallmgr_core::error::{Error, Result};
+use wallmgr_core::types::DisplayMode;
+use crate::downloads::{forward_events, LibraryImporter};
 use crate::websocket::WsChannel;
 
 #[derive(Clone)]
 pub struct AppState {
     pub config: Arc<Config>,
-    pub database: Arc<RwLock<Database>>,
+    /// A connection isn't `Sync`, so requests take turns with it
+    pub database: Arc<Mutex<Database>>,
     pub adapter: Arc<RwLock<Option<Adapter>>>,
     pub renderer: Arc<RwLock<Option<Renderer>>>,
-    pub booru_clients: Arc<BooruClients>,
+    pub connectors: Arc<ConnectorRegistry>,
     pub ws_channel: Arc<WsChannel>,
-}
-
-pub struct BooruClients {
-    pub danbooru: DanbooruConnector,
-    pub yandere: YandereConnector,
-    pub safebooru: SafebooruConnector,
-    pub gelbooru: GelbooruConnector,
+    pub download_queue: Arc<DownloadQueue>,
+    /// What may be searched, downloaded and set, from `config.policy`
+    pub policy: Arc<ContentPolicy>,
 }
 
 impl AppState {
-    pub fn new(config: Config, database: Database) -> Self {
-        Self {
-            config: Arc::new(config),
-            database: Arc::new(RwLock::new(database)),
+    pub fn new(config: Config, database: Database) -> Result<Self> {
+        let config = Arc::new(config);
+        let database = Arc::new(Mutex::new(database));
+        wallmgr_connectors::transport::configure(&config.network, &config.cache_dir);
+        let connectors = Arc::new(ConnectorRegistry::from_config(&config.sources)?);
+        let policy = Arc::new(ContentPolicy::new(&config.policy));
+
+        let download_queue = DownloadQueue::new(
+            Database::new(&config.database_path)?,
+            config.library.clone(),
+            config.downloads.clone(),
+            policy.clone(),
+            Arc::new(LibraryImporter {
+                config: config.clone(),
+                database: database.clone(),
+            }),
+        );
+
+        Ok(Self {
+            config,
+            database,
             adapter: Arc::new(RwLock::new(None)),
             renderer: Arc::new(RwLock::new(None)),
-            booru_clients: Arc::new(BooruClients {
-                danbooru: DanbooruConnector::new(),
-                yandere: YandereConnector::new(),
-                safebooru: SafebooruConnector::new(),
-                gelbooru: GelbooruConnector::new(),
-            }),
+            connectors,
             ws_channel: Arc::new(WsChannel::new()),
+            download_queue,
+            policy,
+        })
+    }
+
+    /// Start processing the download queue, resuming unfinished jobs
+    pub fn start_download_queue(&self) {
+        tokio::spawn(self.download_queue.clone().run());
+        tokio::spawn(forward_events(self.download_queue.clone(), self.ws_channel.clone()));
+    }
+
+    /// Start the periodic favorites sync if it is enabled
+    pub fn start_favorites_sync(&self) {
+        if !self.config.favorites.enabled {
+            return;
         }
+        let sync = FavoritesSync::new(
+            self.connectors.clone(),
+            self.download_queue.clone(),
+            self.config.favorites.clone(),
+        );
+        tokio::spawn(sync.run());
+    }
+
+    /// Start the daily wallpaper job if it is enabled
+    pub fn start_daily_wallpaper(&self) -> Result<()> {
+        let daily = &self.config.daily;
+        if !daily.enabled {
+            return Ok(());
+        }
+        let connector = self.connectors.get(&daily.source).ok_or_else(|| {
+            Error::Config(format!("Daily wallpaper source is not enabled: {}", daily.source))
+        })?;
+
+        let job = DailyWallpaper::new(connector, self.download_queue.clone(), daily.tags.clone());
+        let apply = daily.apply;
+        let monitor = daily.monitor.clone();
+        let adapter = self.adapter.clone();
+        tokio::spawn(job.run(move |path| {
+            let adapter = adapter.clone();
+            let monitor = monitor.clone();
+            async move {
+                tracing::info!("Daily wallpaper downloaded: {}", path);
+                if !apply {
+                    return;
+                }
+                let Some(adapter) = &*adapter.read().await else {
+                    tracing::warn!("No adapter to set the daily wallpaper with");
+                    return;
+                };
+                if let Err(e) = adapter.get_trait().set_wallpaper(
+                    std::path::Path::new(&path),
+                    monitor.as_deref(),
+                    DisplayMode::Fill,
+                ) {
+                    tracing::error!("Failed to set daily wallpaper: {}", e);
+                }
+            }
+        }));
+        Ok(())
     }
 }
@@ -1,13 +1,10 @@
 use axum::{
-    extract::{ws::WebSocketUpgrade, Extension, TypedHeader},
+    extract::{ws::WebSocketUpgrade, Extension},
     response::IntoResponse,
 };
 use axum::extract::ws::{WebSocket, Message};
-use futures::{sink::SinkExt, stream::StreamExt};
 use tokio::sync::broadcast;
 use serde::{Serialize, Deserialize};
-use std::collections::HashMap;
-use chrono::Utc;
 use crate::state::AppState;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -84,10 +81,15 @@ impl WsChannel {
     }
 }
 
+impl Default for WsChannel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub async fn ws_handler(
     ws: WebSocketUpgrade,
     Extension(state): Extension<AppState>,
-    user_agent: Option<TypedHeader<headers::UserAgent>>,
 ) -> impl IntoResponse {
     ws.on_upgrade(|socket| handle_socket(socket, state))
 }
@@ -0,0 +1,330 @@
+use crate::booru::{BooruClient, BooruConnector, SendWith, parse_rating, parse_error, request_error};
+use async_trait::async_trait;
+use serde::Deserialize;
+use wallmgr_core::error::{Error, Result};
+use wallmgr_core::types::BooruImage;
+use std::collections::HashMap;
+
+const IMAGES_URL: &str = "https://oimages.anime-pictures.net";
+const PREVIEWS_URL: &str = "https://opreviews.anime-pictures.net";
+
+/// Anime-Pictures connector
+/// API: https://api.anime-pictures.net/api/v3
+///
+/// Like Zerochan, tags contain spaces; they are exposed with underscores.
+pub struct AnimePicturesConnector {
+    client: BooruClient,
+    name: String,
+    base_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnimePicturesPost {
+    id: u64,
+    md5: String,
+    ext: String,
+    width: u32,
+    height: u32,
+    /// 0 = none, 1 = light, 2 = moderate, 3 = hard
+    #[serde(default)]
+    erotics: Option<u8>,
+    #[serde(default)]
+    adult: Option<bool>,
+    #[serde(default)]
+    have_alpha: bool,
+    #[serde(default)]
+    score_number: Option<i32>,
+    #[serde(default)]
+    size: Option<u64>,
+    #[serde(default)]
+    tags_string: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnimePicturesListing {
+    #[serde(default)]
+    posts: Vec<AnimePicturesPost>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnimePicturesDetail {
+    post: AnimePicturesPost,
+    #[serde(default)]
+    tags: Vec<AnimePicturesPostTag>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnimePicturesPostTag {
+    tag: AnimePicturesTag,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnimePicturesTag {
+    tag: String,
+}
+
+impl AnimePicturesConnector {
+    pub fn new() -> Self {
+        Self::with_base_url("anime_pictures", "https://api.anime-pictures.net")
+    }
+
+    pub fn with_base_url(name: &str, base_url: &str) -> Self {
+        Self {
+            client: BooruClient::new(),
+            name: name.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn post_to_image(&self, post: AnimePicturesPost, tags: Vec<String>) -> Option<BooruImage> {
+        let dir = post.md5.get(..3)?;
+        let ext = post.ext.trim_start_matches('.');
+        let preview_ext = if post.have_alpha { "png" } else { "jpg" };
+
+        let rating = match (post.erotics, post.adult) {
+            (Some(0), _) | (None, Some(false)) | (None, None) => "s",
+            (Some(1), _) => "q",
+            _ => "e",
+        };
+
+        let tags = if tags.is_empty() {
+            post.tags_string
+                .as_deref()
+                .unwrap_or_default()
+                .split(',')
+                .filter(|t| !t.trim().is_empty())
+                .map(to_tag)
+                .collect()
+        } else {
+            tags
+        };
+
+        Some(BooruImage {
+            id: post.id.to_string(),
+            source: self.name.clone(),
+            file_url: format!("{}/{}/{}.{}", IMAGES_URL, dir, post.md5, ext),
+            preview_url: Some(format!("{}/{}/{}_sp.{}", PREVIEWS_URL, dir, post.md5, preview_ext)),
+            sample_url: Some(format!("{}/{}/{}_bp.{}", PREVIEWS_URL, dir, post.md5, preview_ext)),
+            width: post.width,
+            height: post.height,
+            tags,
+            rating: parse_rating(rating),
+            score: post.score_number,
+            author: None,
+            is_nsfw: rating != "s",
+            md5: Some(post.md5),
+            file_size: post.size,
+            page_url: None,
+            origin_url: None,
+            attribution: None,
+            tag_categories: HashMap::new(),
+        })
+    }
+}
+
+impl Default for AnimePicturesConnector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl BooruConnector for AnimePicturesConnector {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn search(&self, tags: &[String], limit: u32, page: u32) -> Result<Vec<BooruImage>> {
+        let search_tag = tags.iter().map(|t| from_tag(t)).collect::<Vec<_>>().join("&&");
+        // Pages are zero-based
+        let url = format!(
+            "{}/api/v3/posts?page={}&posts_per_page={}&search_tag={}&order_by=date&lang=en",
+            self.base_url,
+            page.saturating_sub(1),
+            limit.min(80),
+            urlencoding::encode(&search_tag)
+        );
+
+        let response = self.client.client()
+            .get(&url)
+            .fetch(&self.client, &self.name)
+            .await?;
+
+        let listing: AnimePicturesListing = response
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))?;
+
+        Ok(listing
+            .posts
+            .into_iter()
+            .filter_map(|p| self.post_to_image(p, Vec::new()))
+            .collect())
+    }
+
+    async fn autocomplete_tags(&self, prefix: &str, limit: u32) -> Result<Vec<String>> {
+        let url = format!(
+            "{}/api/v3/tags:autocomplete?tag={}&lang=en",
+            self.base_url,
+            urlencoding::encode(&from_tag(prefix))
+        );
+
+        let response = self.client.client()
+            .get(&url)
+            .send_with(&self.client)
+            .await
+            .map_err(|e| request_error(&self.name, e))?;
+
+        if !response.status().is_success() {
+            return Ok(Vec::new());
+        }
+
+        #[derive(Deserialize)]
+        struct Suggestions {
+            #[serde(default)]
+            tags: Vec<Suggestion>,
+        }
+
+        /// `t` highlights the matched part, e.g. "<b>long</b> hair"
+        #[derive(Deserialize)]
+        struct Suggestion {
+            t: String,
+        }
+
+        let suggestions: Suggestions = response
+            .json()
+            .await
+            .unwrap_or(Suggestions { tags: Vec::new() });
+
+        Ok(suggestions
+            .tags
+            .into_iter()
+            .take(limit as usize)
+            .map(|s| to_tag(&s.t.replace("<b>", "").replace("</b>", "")))
+            .collect())
+    }
+
+    async fn get_image(&self, id: &str) -> Result<BooruImage> {
+        let url = format!(
+            "{}/api/v3/posts/{}?lang=en",
+            self.base_url,
+            urlencoding::encode(id)
+        );
+
+        let response = self.client.client()
+            .get(&url)
+            .fetch(&self.client, &self.name)
+            .await?;
+
+        let detail: AnimePicturesDetail = response
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))?;
+
+        let tags = detail.tags.into_iter().map(|t| to_tag(&t.tag.tag)).collect();
+        self.post_to_image(detail.post, tags)
+            .ok_or_else(|| Error::NotFound("No file URL found".to_string()))
+    }
+}
+
+/// "long hair" -> "long_hair"
+fn to_tag(name: &str) -> String {
+    name.trim().to_lowercase().replace(' ', "_")
+}
+
+fn from_tag(tag: &str) -> String {
+    tag.trim().replace('_', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const LISTING: &str = r#"{
+        "posts": [
+            {"id": 812345, "md5": "a1b2c3d4e5f6", "ext": ".jpg", "width": 3840, "height": 2160,
+             "erotics": 0, "have_alpha": false, "score_number": 25, "size": 2048000},
+            {"id": 812346, "md5": "ffeeddccbbaa", "ext": ".png", "width": 1200, "height": 1600,
+             "erotics": 2, "have_alpha": true}
+        ],
+        "max_pages": 10
+    }"#;
+
+    const POST: &str = r#"{
+        "post": {"id": 812345, "md5": "a1b2c3d4e5f6", "ext": ".jpg", "width": 3840,
+                 "height": 2160, "erotics": 1, "have_alpha": false},
+        "tags": [
+            {"tag": {"id": 1, "tag": "long hair", "type": 0}},
+            {"tag": {"id": 2, "tag": "Sky", "type": 0}}
+        ]
+    }"#;
+
+    #[tokio::test]
+    async fn test_search() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/posts"))
+            .and(query_param("page", "0"))
+            .and(query_param("posts_per_page", "20"))
+            .and(query_param("search_tag", "long hair&&sky"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(LISTING))
+            .mount(&server)
+            .await;
+
+        let connector = AnimePicturesConnector::with_base_url("anime_pictures", &server.uri());
+        let images = connector
+            .search(&["long_hair".to_string(), "sky".to_string()], 20, 1)
+            .await
+            .unwrap();
+
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].id, "812345");
+        assert_eq!(images[0].file_url, "https://oimages.anime-pictures.net/a1b/a1b2c3d4e5f6.jpg");
+        assert_eq!(
+            images[0].preview_url.as_deref(),
+            Some("https://opreviews.anime-pictures.net/a1b/a1b2c3d4e5f6_sp.jpg")
+        );
+        assert_eq!(images[0].score, Some(25));
+        assert!(!images[0].is_nsfw);
+
+        assert_eq!(images[1].file_url, "https://oimages.anime-pictures.net/ffe/ffeeddccbbaa.png");
+        assert_eq!(
+            images[1].sample_url.as_deref(),
+            Some("https://opreviews.anime-pictures.net/ffe/ffeeddccbbaa_bp.png")
+        );
+        assert!(images[1].is_nsfw);
+    }
+
+    #[tokio::test]
+    async fn test_get_image_and_autocomplete() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/posts/812345"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(POST))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/tags:autocomplete"))
+            .and(query_param("tag", "long"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"tags": [{"id": 1, "t": "<b>long</b> hair", "t2": null, "c": 500000},
+                             {"id": 3, "t": "<b>long</b> sleeves", "t2": null, "c": 90000}]}"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let connector = AnimePicturesConnector::with_base_url("anime_pictures", &server.uri());
+        let image = connector.get_image("812345").await.unwrap();
+        assert_eq!(image.tags, vec!["long_hair", "sky"]);
+        assert_eq!(image.md5.as_deref(), Some("a1b2c3d4e5f6"));
+        assert!(image.is_nsfw);
+
+        assert_eq!(
+            connector.autocomplete_tags("long", 1).await.unwrap(),
+            vec!["long_hair"]
+        );
+        assert!(connector.get_image("1").await.is_err());
+    }
+}
@@ -0,0 +1,217 @@
+use crate::booru::{BooruClient, BooruConnector, SendWith, parse_error};
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDate, Utc};
+use serde::Deserialize;
+use wallmgr_core::error::{Error, Result};
+use wallmgr_core::types::{BooruImage, Rating};
+use std::collections::HashMap;
+
+/// NASA's Astronomy Picture of the Day
+/// API: https://api.nasa.gov (the shared `DEMO_KEY` is heavily rate limited)
+///
+/// Pictures have no tags, so searches with tags find nothing and an untagged
+/// search pages backwards through the days. Days with a video are skipped.
+pub struct ApodConnector {
+    client: BooruClient,
+    name: String,
+    base_url: String,
+    api_key: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApodEntry {
+    /// `YYYY-MM-DD`
+    date: String,
+    title: String,
+    url: String,
+    #[serde(default)]
+    hdurl: Option<String>,
+    media_type: String,
+    #[serde(default)]
+    copyright: Option<String>,
+}
+
+impl ApodConnector {
+    pub fn new(api_key: Option<&str>) -> Self {
+        Self::with_base_url("apod", "https://api.nasa.gov", api_key)
+    }
+
+    pub fn with_base_url(name: &str, base_url: &str, api_key: Option<&str>) -> Self {
+        Self {
+            client: BooruClient::new(),
+            name: name.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.unwrap_or("DEMO_KEY").to_string(),
+        }
+    }
+
+    async fn get(&self, query: &[(&str, String)]) -> Result<reqwest::Response> {
+        let response = self.client.client()
+            .get(format!("{}/planetary/apod", self.base_url))
+            .query(&[("api_key", self.api_key.as_str())])
+            .query(query)
+            .fetch(&self.client, &self.name)
+            .await?;
+        Ok(response)
+    }
+
+    fn to_image(&self, entry: ApodEntry) -> Option<BooruImage> {
+        if entry.media_type != "image" {
+            return None;
+        }
+        // Public domain pictures have no copyright holder
+        let author = entry
+            .copyright
+            .map(|c| c.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|c| !c.is_empty());
+        let day = NaiveDate::parse_from_str(&entry.date, "%Y-%m-%d").ok()?;
+
+        Some(BooruImage {
+            source: self.name.clone(),
+            file_url: entry.hdurl.clone().unwrap_or_else(|| entry.url.clone()),
+            preview_url: Some(entry.url.clone()),
+            sample_url: Some(entry.url),
+            width: 0,
+            height: 0,
+            tags: Vec::new(),
+            rating: Rating::Safe,
+            score: None,
+            attribution: Some(match &author {
+                Some(author) => format!("{} (© {})", entry.title, author),
+                None => format!("{} (NASA)", entry.title),
+            }),
+            tag_categories: HashMap::new(),
+            author,
+            is_nsfw: false,
+            md5: None,
+            file_size: None,
+            page_url: Some(format!("https://apod.nasa.gov/apod/ap{}.html", day.format("%y%m%d"))),
+            origin_url: None,
+            id: entry.date,
+        })
+    }
+}
+
+/// Days shown on `page`, newest first from `today`
+fn page_dates(today: NaiveDate, limit: u32, page: u32) -> (NaiveDate, NaiveDate) {
+    let end = today - Duration::days((page.saturating_sub(1) * limit) as i64);
+    let start = end - Duration::days(limit.saturating_sub(1) as i64);
+    (start, end)
+}
+
+#[async_trait]
+impl BooruConnector for ApodConnector {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn search(&self, tags: &[String], limit: u32, page: u32) -> Result<Vec<BooruImage>> {
+        if !tags.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // A new picture is published around midnight US Eastern time
+        let today = (Utc::now() - Duration::hours(5)).date_naive();
+        let (start, end) = page_dates(today, limit.clamp(1, 100), page);
+
+        let entries: Vec<ApodEntry> = self
+            .get(&[
+                ("start_date", start.format("%Y-%m-%d").to_string()),
+                ("end_date", end.format("%Y-%m-%d").to_string()),
+            ])
+            .await?
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))?;
+
+        Ok(entries.into_iter().rev().filter_map(|e| self.to_image(e)).collect())
+    }
+
+    async fn autocomplete_tags(&self, _prefix: &str, _limit: u32) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    /// `id` is the picture's date, `YYYY-MM-DD`
+    async fn get_image(&self, id: &str) -> Result<BooruImage> {
+        let entry: ApodEntry = self
+            .get(&[("date", id.to_string())])
+            .await
+            .map_err(|_| Error::NotFound(format!("No {} picture for {}", self.name, id)))?
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))?;
+
+        self.to_image(entry)
+            .ok_or_else(|| Error::NotFound(format!("The {} entry for {} is not an image", self.name, id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const ENTRIES: &str = r#"[
+        {"date": "2024-10-16", "title": "The Moon", "media_type": "image",
+         "url": "https://apod.nasa.gov/apod/image/2410/moon_1024.jpg",
+         "hdurl": "https://apod.nasa.gov/apod/image/2410/moon.jpg",
+         "copyright": "\nJohn Stargazer\n", "explanation": "...", "service_version": "v1"},
+        {"date": "2024-10-17", "title": "A Comet", "media_type": "video",
+         "url": "https://www.youtube.com/embed/xyz"},
+        {"date": "2024-10-18", "title": "Andromeda", "media_type": "image",
+         "url": "https://apod.nasa.gov/apod/image/2410/m31_1024.jpg", "explanation": "..."}
+    ]"#;
+
+    #[test]
+    fn test_page_dates() {
+        let today = NaiveDate::from_ymd_opt(2024, 10, 18).unwrap();
+        assert_eq!(
+            page_dates(today, 3, 1),
+            (NaiveDate::from_ymd_opt(2024, 10, 16).unwrap(), today)
+        );
+        assert_eq!(
+            page_dates(today, 3, 2),
+            (
+                NaiveDate::from_ymd_opt(2024, 10, 13).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 10, 15).unwrap()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_and_get_image() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/planetary/apod"))
+            .and(query_param("api_key", "key"))
+            .and(query_param("date", "2024-10-16"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                serde_json::to_string(&serde_json::from_str::<serde_json::Value>(ENTRIES).unwrap()[0])
+                    .unwrap(),
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/planetary/apod"))
+            .and(query_param("api_key", "key"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(ENTRIES))
+            .mount(&server)
+            .await;
+
+        let connector = ApodConnector::with_base_url("apod", &server.uri(), Some("key"));
+        let images = connector.search(&[], 3, 1).await.unwrap();
+
+        // Newest first, without the video
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].id, "2024-10-18");
+        assert_eq!(images[0].author, None);
+        assert_eq!(images[0].attribution.as_deref(), Some("Andromeda (NASA)"));
+        assert_eq!(images[1].file_url, "https://apod.nasa.gov/apod/image/2410/moon.jpg");
+        assert_eq!(images[1].author.as_deref(), Some("John Stargazer"));
+        assert_eq!(images[1].page_url.as_deref(), Some("https://apod.nasa.gov/apod/ap241016.html"));
+
+        assert!(connector.search(&["moon".to_string()], 3, 1).await.unwrap().is_empty());
+        assert_eq!(connector.get_image("2024-10-16").await.unwrap().id, "2024-10-16");
+    }
+}
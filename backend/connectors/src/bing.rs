@@ -0,0 +1,206 @@
+use crate::booru::{BooruClient, BooruConnector, SendWith, parse_error};
+use async_trait::async_trait;
+use serde::Deserialize;
+use wallmgr_core::error::{Error, Result};
+use wallmgr_core::types::{BooruImage, Rating};
+use std::collections::HashMap;
+
+/// Days Bing keeps in its image archive
+const ARCHIVE_DAYS: u32 = 8;
+
+/// Bing's image of the day; the archive covers the last eight days
+///
+/// Images have no tags, so searches with tags find nothing and an untagged
+/// search lists the archive newest first.
+pub struct BingConnector {
+    client: BooruClient,
+    name: String,
+    base_url: String,
+    market: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BingArchive {
+    images: Vec<BingImage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BingImage {
+    /// `YYYYMMDD`
+    startdate: String,
+    /// 1920x1080 rendition, relative to the site root
+    url: String,
+    /// Prefix for other renditions, e.g. `/th?id=OHR.Name_EN-US123`
+    urlbase: String,
+    /// "Description (© Photographer/Agency)"
+    copyright: String,
+    #[serde(default)]
+    copyrightlink: Option<String>,
+}
+
+impl BingConnector {
+    pub fn new() -> Self {
+        Self::with_base_url("bing", "https://www.bing.com")
+    }
+
+    pub fn with_base_url(name: &str, base_url: &str) -> Self {
+        Self {
+            client: BooruClient::new(),
+            name: name.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            market: "en-US".to_string(),
+        }
+    }
+
+    /// Regional edition, e.g. "de-DE"; images and captions differ per market
+    pub fn with_market(mut self, market: &str) -> Self {
+        self.market = market.to_string();
+        self
+    }
+
+    async fn archive(&self, offset: u32, count: u32) -> Result<Vec<BingImage>> {
+        let url = format!(
+            "{}/HPImageArchive.aspx?format=js&idx={}&n={}&mkt={}",
+            self.base_url,
+            offset,
+            count,
+            urlencoding::encode(&self.market)
+        );
+
+        let response = self.client.client()
+            .get(&url)
+            .fetch(&self.client, &self.name)
+            .await?;
+
+        let archive: BingArchive = response
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))?;
+        Ok(archive.images)
+    }
+
+    fn to_image(&self, image: BingImage) -> BooruImage {
+        // The credit is the parenthesised part after ©
+        let author = image
+            .copyright
+            .rsplit_once("(©")
+            .and_then(|(_, credit)| credit.split(')').next())
+            .map(|credit| credit.trim().to_string())
+            .filter(|credit| !credit.is_empty());
+
+        BooruImage {
+            id: image.startdate,
+            source: self.name.clone(),
+            file_url: format!("{}{}_UHD.jpg", self.base_url, image.urlbase),
+            preview_url: Some(format!("{}{}_320x240.jpg", self.base_url, image.urlbase)),
+            sample_url: Some(format!("{}{}", self.base_url, image.url)),
+            width: 3840,
+            height: 2160,
+            tags: Vec::new(),
+            rating: Rating::Safe,
+            score: None,
+            author,
+            is_nsfw: false,
+            md5: None,
+            file_size: None,
+            page_url: image.copyrightlink.filter(|l| l.starts_with("http")),
+            origin_url: None,
+            attribution: Some(image.copyright),
+            tag_categories: HashMap::new(),
+        }
+    }
+}
+
+impl Default for BingConnector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl BooruConnector for BingConnector {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn search(&self, tags: &[String], limit: u32, page: u32) -> Result<Vec<BooruImage>> {
+        let limit = limit.clamp(1, ARCHIVE_DAYS);
+        let offset = page.saturating_sub(1) * limit;
+        if !tags.is_empty() || offset >= ARCHIVE_DAYS {
+            return Ok(Vec::new());
+        }
+
+        let images = self.archive(offset, limit.min(ARCHIVE_DAYS - offset)).await?;
+        Ok(images.into_iter().map(|i| self.to_image(i)).collect())
+    }
+
+    async fn autocomplete_tags(&self, _prefix: &str, _limit: u32) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    /// `id` is the image's date, `YYYYMMDD`
+    async fn get_image(&self, id: &str) -> Result<BooruImage> {
+        self.archive(0, ARCHIVE_DAYS)
+            .await?
+            .into_iter()
+            .find(|i| i.startdate == id)
+            .map(|i| self.to_image(i))
+            .ok_or_else(|| Error::NotFound(format!("No {} image for {}", self.name, id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const ARCHIVE: &str = r#"{"images": [
+        {"startdate": "20241018", "fullstartdate": "202410180700", "enddate": "20241019",
+         "url": "/th?id=OHR.AutumnLake_EN-US123_1920x1080.jpg&rf=LaDigue_1920x1080.jpg&pid=hp",
+         "urlbase": "/th?id=OHR.AutumnLake_EN-US123",
+         "copyright": "Autumn at a lake in Canada (© Jane Photographer/Getty Images)",
+         "copyrightlink": "https://www.bing.com/search?q=autumn+lake", "title": "Info", "hsh": "abc"},
+        {"startdate": "20241017", "url": "/th?id=OHR.Desert_EN-US9_1920x1080.jpg",
+         "urlbase": "/th?id=OHR.Desert_EN-US9", "copyright": "A desert",
+         "copyrightlink": "javascript:void(0)"}
+    ]}"#;
+
+    #[tokio::test]
+    async fn test_archive() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/HPImageArchive.aspx"))
+            .and(query_param("idx", "0"))
+            .and(query_param("mkt", "de-DE"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(ARCHIVE))
+            .mount(&server)
+            .await;
+
+        let connector = BingConnector::with_base_url("bing", &server.uri()).with_market("de-DE");
+        let images = connector.search(&[], 8, 1).await.unwrap();
+
+        assert_eq!(images.len(), 2);
+        let image = &images[0];
+        assert_eq!(image.id, "20241018");
+        assert_eq!(
+            image.file_url,
+            format!("{}/th?id=OHR.AutumnLake_EN-US123_UHD.jpg", server.uri())
+        );
+        assert_eq!(image.author.as_deref(), Some("Jane Photographer/Getty Images"));
+        assert_eq!(
+            image.attribution.as_deref(),
+            Some("Autumn at a lake in Canada (© Jane Photographer/Getty Images)")
+        );
+        assert_eq!(image.page_url.as_deref(), Some("https://www.bing.com/search?q=autumn+lake"));
+        assert_eq!(images[1].author, None);
+        assert_eq!(images[1].page_url, None);
+
+        // Tags match nothing and the archive ends after eight days
+        assert!(connector.search(&["lake".to_string()], 8, 1).await.unwrap().is_empty());
+        assert!(connector.search(&[], 8, 2).await.unwrap().is_empty());
+
+        assert_eq!(connector.get_image("20241017").await.unwrap().id, "20241017");
+        assert!(matches!(connector.get_image("20200101").await, Err(Error::NotFound(_))));
+    }
+}
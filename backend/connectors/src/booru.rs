@@ -1,6 +1,22 @@
-use wallmgr_core::error::Result;
-use wallmgr_core::types::{BooruImage, Rating};
+use wallmgr_core::error::{Error, Result};
+use wallmgr_core::types::{BooruImage, Rating, TagAlias, TagCategory, TagImplication};
+use crate::query::{Query, Translation};
+use crate::transport::{self, HttpPolicy};
 use async_trait::async_trait;
+use reqwest::StatusCode;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Pages [`BooruConnector::search_query`] fetches at most to fill a request
+pub const PAGE_BUDGET: u32 = 5;
+
+/// Images found by a query and where to continue
+#[derive(Debug, Default)]
+pub struct QueryPage {
+    pub images: Vec<BooruImage>,
+    /// Page to search next; `None` once the source ran out of results
+    pub next_page: Option<u32>,
+}
 
 #[async_trait]
 pub trait BooruConnector: Send + Sync {
@@ -10,31 +26,152 @@ pub trait BooruConnector: Send + Sync {
     /// Search images with tags
     async fn search(&self, tags: &[String], limit: u32, page: u32) -> Result<Vec<BooruImage>>;
 
+    /// Translate a wallmgr query into tags for [`search`](Self::search).
+    /// By default only required tags are passed on and the rest is filtered.
+    fn translate(&self, query: &Query) -> Translation {
+        Translation::new(query).tags()
+    }
+
+    /// Search with a wallmgr query from `page` on, filtering what the site
+    /// can't express. Pages are fetched until `limit` images match or
+    /// [`PAGE_BUDGET`] pages were searched.
+    async fn search_query(&self, query: &Query, limit: u32, page: u32) -> Result<QueryPage> {
+        let translation = self.translate(query);
+        let mut found = QueryPage::default();
+        let mut page = page;
+
+        for _ in 0..PAGE_BUDGET {
+            let images = match self.search(&translation.tags, limit, page).await {
+                Ok(images) => images,
+                // Keep what was found; the failed page is tried again next time
+                Err(e) if !found.images.is_empty() => {
+                    tracing::warn!("Search of {} stopped at page {}: {}", self.name(), page, e);
+                    break;
+                }
+                Err(e) => return Err(e),
+            };
+            // Sites may return short pages before the last one
+            if images.is_empty() {
+                return Ok(found);
+            }
+            page += 1;
+            found.images.extend(translation.residual.filter(images));
+            if found.images.len() >= limit as usize {
+                break;
+            }
+        }
+
+        found.next_page = Some(page);
+        Ok(found)
+    }
+
     /// Get tag autocomplete suggestions
     async fn autocomplete_tags(&self, prefix: &str, limit: u32) -> Result<Vec<String>>;
 
     /// Get image details by ID
     async fn get_image(&self, id: &str) -> Result<BooruImage>;
+
+    /// Images favorited by the authenticated account, newest first
+    async fn favorites(&self, _limit: u32, _page: u32) -> Result<Vec<BooruImage>> {
+        Err(Error::InvalidInput(format!("{} has no account favorites", self.name())))
+    }
+
+    /// Aliases the site defines for any of `tags`, either way round
+    async fn tag_aliases(&self, _tags: &[String]) -> Result<Vec<TagAlias>> {
+        Err(Error::InvalidInput(format!("{} has no tag aliases", self.name())))
+    }
+
+    /// Implications the site defines for any of `tags`
+    async fn tag_implications(&self, _tags: &[String]) -> Result<Vec<TagImplication>> {
+        Err(Error::InvalidInput(format!("{} has no tag implications", self.name())))
+    }
 }
 
 pub struct BooruClient {
     client: reqwest::Client,
+    policy: Arc<HttpPolicy>,
 }
 
 impl BooruClient {
     pub fn new() -> Self {
+        Self::with_policy(transport::default_policy())
+    }
+
+    /// Client with its own rate limits, retries and cache
+    pub fn with_policy(policy: Arc<HttpPolicy>) -> Self {
         let client = reqwest::Client::builder()
             .user_agent("Wallmgr/1.0")
             .timeout(std::time::Duration::from_secs(30))
             .build()
             .unwrap();
 
-        Self { client }
+        Self { client, policy }
     }
 
     pub fn client(&self) -> &reqwest::Client {
         &self.client
     }
+
+    /// Send an API request, rate limited, retried and cached
+    pub async fn send(&self, request: reqwest::RequestBuilder) -> reqwest::Result<reqwest::Response> {
+        let request = request.build()?;
+        self.policy.execute(&self.client, request).await
+    }
+}
+
+/// Lets request chains end in `.send_with(&self.client)` instead of `.send()`
+#[async_trait]
+pub trait SendWith {
+    async fn send_with(self, client: &BooruClient) -> reqwest::Result<reqwest::Response>;
+
+    /// Send, turning failed requests and unsuccessful statuses into errors
+    /// naming `source`
+    async fn fetch(self, client: &BooruClient, source: &str) -> Result<reqwest::Response>;
+}
+
+#[async_trait]
+impl SendWith for reqwest::RequestBuilder {
+    async fn send_with(self, client: &BooruClient) -> reqwest::Result<reqwest::Response> {
+        client.send(self).await
+    }
+
+    async fn fetch(self, client: &BooruClient, source: &str) -> Result<reqwest::Response> {
+        let response = client.send(self).await.map_err(|e| request_error(source, e))?;
+        if !response.status().is_success() {
+            return Err(status_error(source, response.status()));
+        }
+        Ok(response)
+    }
+}
+
+/// First web link in a post's free-form source field
+pub fn origin_url(source: &str) -> Option<String> {
+    source
+        .split_whitespace()
+        .find(|s| s.starts_with("https://") || s.starts_with("http://"))
+        .map(|s| s.to_string())
+}
+
+/// Error for a request to `source` that got no response
+pub fn request_error(source: &str, error: reqwest::Error) -> Error {
+    Error::SourceUnavailable(format!("{} could not be reached: {}", source, error))
+}
+
+/// Error for a response of `source` with an unsuccessful status
+pub fn status_error(source: &str, status: StatusCode) -> Error {
+    let message = format!("{} returned status {}", source, status);
+    match status {
+        StatusCode::TOO_MANY_REQUESTS => Error::RateLimited(message),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Error::AuthFailed(message),
+        StatusCode::NOT_FOUND => Error::NotFound(message),
+        status if status.is_server_error() => Error::SourceUnavailable(message),
+        _ => Error::Api(message),
+    }
+}
+
+/// Error for a response of `source` that doesn't parse
+pub fn parse_error(source: &str, error: impl std::fmt::Display) -> Error {
+    Error::Parse(format!("Failed to parse {} response: {}", source, error))
 }
 
 impl Default for BooruClient {
@@ -46,8 +183,129 @@ impl Default for BooruClient {
 pub fn parse_rating(rating: &str) -> Rating {
     match rating.to_lowercase().as_str() {
         "s" | "safe" | "general" => Rating::Safe,
-        "q" | "questionable" => Rating::Questionable,
-        "e" | "explicit" => Rating::Explicit,
+        "q" | "questionable" | "sketchy" => Rating::Questionable,
+        "e" | "explicit" | "unsafe" => Rating::Explicit,
         _ => Rating::Safe,
     }
 }
+
+/// Category of each tag, from a post's tags grouped by category
+pub fn tag_categories<'a, T>(groups: impl IntoIterator<Item = (TagCategory, T)>) -> HashMap<String, TagCategory>
+where
+    T: IntoIterator<Item = &'a str>,
+{
+    groups
+        .into_iter()
+        .flat_map(|(category, tags)| tags.into_iter().map(move |tag| (tag.to_string(), category)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::path;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_fetch_maps_failures_to_error_kinds() {
+        let server = MockServer::start().await;
+        for (route, status) in [("/limited", 429), ("/private", 401), ("/gone", 404), ("/teapot", 418)] {
+            Mock::given(path(route))
+                .respond_with(ResponseTemplate::new(status))
+                .mount(&server)
+                .await;
+        }
+        Mock::given(path("/ok"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let policy = Arc::new(HttpPolicy::new(&Default::default(), None));
+        let client = BooruClient::with_policy(policy);
+        let fetch = |route: &str| client.client().get(format!("{}{}", server.uri(), route)).fetch(&client, "mybooru");
+
+        assert!(matches!(fetch("/limited").await, Err(Error::RateLimited(_))));
+        assert!(matches!(fetch("/private").await, Err(Error::AuthFailed(_))));
+        assert!(matches!(fetch("/gone").await, Err(Error::NotFound(_))));
+        assert!(matches!(fetch("/teapot").await, Err(Error::Api(_))));
+        assert!(fetch("/ok").await.is_ok());
+
+        let unreachable = client.client().get("http://127.0.0.1:1/").fetch(&client, "mybooru").await;
+        match unreachable {
+            Err(e @ Error::SourceUnavailable(_)) => assert!(e.to_string().contains("mybooru")),
+            other => panic!("unexpected result: {:?}", other.map(|r| r.status())),
+        }
+    }
+
+    /// Serves `pages` of images one wide, as many per page as given
+    struct Paged {
+        pages: Vec<Vec<u32>>,
+    }
+
+    #[async_trait]
+    impl BooruConnector for Paged {
+        fn name(&self) -> &str {
+            "paged"
+        }
+
+        async fn search(&self, _tags: &[String], _limit: u32, page: u32) -> Result<Vec<BooruImage>> {
+            let widths = self.pages.get(page as usize - 1).cloned().unwrap_or_default();
+            Ok(widths
+                .into_iter()
+                .map(|width| BooruImage {
+                    id: width.to_string(),
+                    source: "paged".to_string(),
+                    file_url: String::new(),
+                    preview_url: None,
+                    sample_url: None,
+                    width,
+                    height: 1000,
+                    tags: Vec::new(),
+                    rating: Rating::Safe,
+                    score: None,
+                    author: None,
+                    is_nsfw: false,
+                    md5: None,
+                    file_size: None,
+                    page_url: None,
+                    origin_url: None,
+                    attribution: None,
+                    tag_categories: HashMap::new(),
+                })
+                .collect())
+        }
+
+        async fn autocomplete_tags(&self, _prefix: &str, _limit: u32) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        async fn get_image(&self, id: &str) -> Result<BooruImage> {
+            Err(Error::NotFound(id.to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_query_fills_from_further_pages() {
+        let connector = Paged {
+            pages: vec![vec![500, 2000], vec![600], vec![2100, 2200, 700], vec![2300]],
+        };
+        let query = Query::parse("width:>=1920").unwrap();
+
+        let found = connector.search_query(&query, 3, 1).await.unwrap();
+        let ids: Vec<_> = found.images.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(ids, vec!["2000", "2100", "2200"]);
+        assert_eq!(found.next_page, Some(4));
+
+        let found = connector.search_query(&query, 3, 4).await.unwrap();
+        assert_eq!(found.images.len(), 1);
+        assert_eq!(found.next_page, None);
+
+        // Nothing matches within the page budget
+        let connector = Paged {
+            pages: vec![vec![500]; PAGE_BUDGET as usize + 1],
+        };
+        let found = connector.search_query(&query, 3, 1).await.unwrap();
+        assert!(found.images.is_empty());
+        assert_eq!(found.next_page, Some(PAGE_BUDGET + 1));
+    }
+}
@@ -0,0 +1,205 @@
+//! Daily wallpaper job
+//!
+//! Once a day the newest image of a source (e.g. Bing's image of the day)
+//! that the content policy allows is queued for download ahead of everything
+//! else and handed to a callback once it is in the library, typically to set
+//! it as the wallpaper.
+
+use crate::booru::BooruConnector;
+use crate::queue::{DownloadQueue, QueueEvent};
+use chrono::{DateTime, Local, TimeZone};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use wallmgr_core::error::{Error, Result};
+use wallmgr_core::types::DownloadState;
+
+/// Queue priority of the daily image, above anything queued by hand
+const DAILY_PRIORITY: i32 = 100;
+
+/// Newest images considered, in case the newest is blocked by the policy
+const CANDIDATES: u32 = 10;
+
+/// Wait before trying again after the source or download failed
+const RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+pub struct DailyWallpaper {
+    connector: Arc<dyn BooruConnector>,
+    queue: Arc<DownloadQueue>,
+    tags: Vec<String>,
+}
+
+impl DailyWallpaper {
+    pub fn new(connector: Arc<dyn BooruConnector>, queue: Arc<DownloadQueue>, tags: Vec<String>) -> Self {
+        Self {
+            connector,
+            queue,
+            tags,
+        }
+    }
+
+    /// Download today's image; returns its library path, or `None` if the
+    /// source had nothing the content policy allows
+    pub async fn fetch(&self) -> Result<Option<String>> {
+        // Subscribe first so the job can't finish unnoticed
+        let mut events = self.queue.subscribe();
+
+        let images = self.connector.search(&self.tags, CANDIDATES, 1).await?;
+        let Some(image) = images.into_iter().find(|image| self.queue.policy().allows(image)) else {
+            return Ok(None);
+        };
+        let job = self.queue.enqueue(&image, DAILY_PRIORITY)?;
+        match job.state {
+            DownloadState::Done => return Ok(job.path),
+            // Queued earlier by hand; move it to the front
+            DownloadState::Queued if job.priority < DAILY_PRIORITY => {
+                self.queue.set_priority(job.id, DAILY_PRIORITY)?;
+            }
+            _ => {}
+        }
+
+        loop {
+            match events.recv().await {
+                Ok(QueueEvent::Done { job_id, path, .. }) if job_id == job.id => return Ok(Some(path)),
+                Ok(QueueEvent::Failed { job_id, error, retry_at: None, .. }) if job_id == job.id => {
+                    return Err(Error::Network(error));
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => {
+                    return Err(Error::Cancelled("Download queue stopped".to_string()));
+                }
+            }
+        }
+    }
+
+    /// Fetch an image now and then after each local midnight, passing every
+    /// downloaded path to `on_ready`
+    pub async fn run<F, Fut>(self, on_ready: F)
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        loop {
+            let delay = match self.fetch().await {
+                Ok(Some(path)) => {
+                    on_ready(path).await;
+                    until_next_day(Local::now())
+                }
+                Ok(None) => {
+                    tracing::warn!("{} has no image for the daily wallpaper", self.connector.name());
+                    until_next_day(Local::now())
+                }
+                Err(e) => {
+                    tracing::error!("Daily wallpaper from {} failed: {}", self.connector.name(), e);
+                    RETRY_DELAY
+                }
+            };
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Time from `now` until the following midnight
+fn until_next_day<Tz: TimeZone>(now: DateTime<Tz>) -> Duration {
+    let midnight = now
+        .date_naive()
+        .succ_opt()
+        .and_then(|day| day.and_hms_opt(0, 0, 0))
+        .and_then(|time| time.and_local_timezone(now.timezone()).earliest());
+
+    match midnight {
+        Some(midnight) => (midnight - now).to_std().unwrap_or(RETRY_DELAY),
+        None => RETRY_DELAY,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bing::BingConnector;
+    use crate::download::DownloadedFile;
+    use crate::queue::Importer;
+    use async_trait::async_trait;
+    use chrono::FixedOffset;
+    use wallmgr_core::config::{DownloadsConfig, LibraryConfig};
+    use wallmgr_core::types::DownloadJob;
+    use wallmgr_core::Database;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    struct NoImport;
+
+    #[async_trait]
+    impl Importer for NoImport {
+        async fn import(&self, _job: &DownloadJob, _file: &DownloadedFile) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_until_next_day() {
+        let now = FixedOffset::east_opt(2 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2024, 10, 18, 22, 30, 0)
+            .unwrap();
+        assert_eq!(until_next_day(now), Duration::from_secs(90 * 60));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_downloads_newest_image() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/HPImageArchive.aspx"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"images": [{"startdate": "20241018", "url": "/th?id=OHR.Lake_1920x1080.jpg",
+                    "urlbase": "/th?id=OHR.Lake", "copyright": "A lake (© Someone)"}]}"#,
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/th"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![7u8; 2_000]))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let queue = DownloadQueue::new(
+            Database::new(&dir.path().join("wallmgr.db")).unwrap(),
+            LibraryConfig {
+                dir: dir.path().join("library"),
+                layout: "{source}/{id}.{ext}".to_string(),
+            },
+            DownloadsConfig::default(),
+            Arc::default(),
+            Arc::new(NoImport),
+        );
+        tokio::spawn(queue.clone().run());
+
+        let daily = DailyWallpaper::new(
+            Arc::new(BingConnector::with_base_url("bing", &server.uri())),
+            queue.clone(),
+            Vec::new(),
+        );
+        let path = tokio::time::timeout(Duration::from_secs(10), daily.fetch())
+            .await
+            .expect("download did not finish")
+            .unwrap()
+            .unwrap();
+
+        let expected = dir.path().join("library/bing/20241018.jpg");
+        assert_eq!(path, expected.to_str().unwrap());
+        assert_eq!(std::fs::read(&expected).unwrap().len(), 2_000);
+        assert_eq!(queue.jobs(None).unwrap()[0].priority, DAILY_PRIORITY);
+
+        // A second run the same day reuses the finished job
+        assert_eq!(daily.fetch().await.unwrap(), Some(path));
+
+        let tagged = DailyWallpaper::new(
+            Arc::new(BingConnector::with_base_url("bing", &server.uri())),
+            queue,
+            vec!["lake".to_string()],
+        );
+        assert_eq!(tagged.fetch().await.unwrap(), None);
+    }
+}
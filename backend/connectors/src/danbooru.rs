@@ -1,10 +1,10 @@
-use crate::booru::{BooruClient, BooruConnector, parse_rating};
+use crate::booru::{BooruClient, BooruConnector, SendWith, parse_rating, parse_error, origin_url, tag_categories};
+use crate::query::{self, Order, Query, Translation};
 use async_trait::async_trait;
 use serde::Deserialize;
 use wallmgr_core::error::{Error, Result};
-use wallmgr_core::types::BooruImage;
-
-const API_BASE: &str = "https://danbooru.donmai.us";
+use wallmgr_core::types::{BooruImage, Rating, TagAlias, TagCategory, TagImplication};
+use std::collections::HashMap;
 
 #[derive(Debug, Deserialize)]
 struct DanbooruPost {
@@ -24,6 +24,33 @@ struct DanbooruPost {
     score: Option<i32>,
     #[serde(default)]
     tag_string_artist: String,
+    #[serde(default)]
+    tag_string_character: String,
+    #[serde(default)]
+    tag_string_copyright: String,
+    #[serde(default)]
+    tag_string_general: String,
+    #[serde(default)]
+    tag_string_meta: String,
+    #[serde(default)]
+    md5: Option<String>,
+    #[serde(default)]
+    file_size: Option<u64>,
+    /// Free-form, often a link to the original
+    #[serde(default)]
+    source: Option<String>,
+}
+
+impl DanbooruPost {
+    fn tag_categories(&self) -> HashMap<String, TagCategory> {
+        tag_categories([
+            (TagCategory::Artist, self.tag_string_artist.split_whitespace()),
+            (TagCategory::Character, self.tag_string_character.split_whitespace()),
+            (TagCategory::Copyright, self.tag_string_copyright.split_whitespace()),
+            (TagCategory::General, self.tag_string_general.split_whitespace()),
+            (TagCategory::Meta, self.tag_string_meta.split_whitespace()),
+        ])
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -33,15 +60,124 @@ struct DanbooruTag {
     post_count: i64,
 }
 
+/// An approved tag alias or implication
+#[derive(Debug, Deserialize)]
+struct DanbooruTagRelation {
+    antecedent_name: String,
+    consequent_name: String,
+}
+
+/// Danbooru's own rating codes: general is safe, sensitive counts as
+/// questionable
+fn danbooru_rating(code: &str) -> Rating {
+    match code {
+        "g" | "general" => Rating::Safe,
+        "s" | "sensitive" => Rating::Questionable,
+        code => parse_rating(code),
+    }
+}
+
+/// Codes searched for a [`Rating`], the inverse of [`danbooru_rating`]
+fn rating_codes(rating: Rating) -> &'static str {
+    match rating {
+        Rating::Safe => "g",
+        Rating::Questionable => "s,q",
+        Rating::Explicit => "e",
+    }
+}
+
+/// Tags looked up per alias or implication request
+const RELATION_BATCH: usize = 100;
+
+/// Danbooru 2 sites: danbooru.donmai.us and its mirrors
 pub struct DanbooruConnector {
     client: BooruClient,
+    name: String,
+    base_url: String,
+    auth: Vec<(&'static str, String)>,
 }
 
 impl DanbooruConnector {
     pub fn new() -> Self {
+        Self::with_base_url("danbooru", "https://danbooru.donmai.us")
+    }
+
+    pub fn with_base_url(name: &str, base_url: &str) -> Self {
         Self {
             client: BooruClient::new(),
+            name: name.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            auth: Vec::new(),
+        }
+    }
+
+    /// Authenticate requests with a login and API key
+    pub fn with_credentials(mut self, login: &str, api_key: &str) -> Self {
+        self.auth = vec![("login", login.to_string()), ("api_key", api_key.to_string())];
+        self
+    }
+
+    fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        self.client.client().get(url).query(&self.auth)
+    }
+
+    fn login(&self) -> Result<&str> {
+        self.auth
+            .iter()
+            .find(|(key, _)| *key == "login")
+            .map(|(_, login)| login.as_str())
+            .ok_or_else(|| Error::Config(format!("{} favorites need a login", self.name)))
+    }
+
+    /// A post as an image; `None` when it has no file to link to
+    fn to_image(&self, post: DanbooruPost) -> Option<BooruImage> {
+        // Without the original, the checksum and size don't describe the
+        // file we link to
+        let original = post.file_url.is_some();
+        let file_url = post.file_url.clone().or_else(|| post.large_file_url.clone())?;
+        let rating = danbooru_rating(&post.rating);
+        let tag_categories = post.tag_categories();
+
+        Some(BooruImage {
+            id: post.id.to_string(),
+            source: self.name.clone(),
+            file_url,
+            preview_url: post.preview_file_url,
+            sample_url: post.large_file_url,
+            width: post.image_width,
+            height: post.image_height,
+            tags: post.tag_string.split_whitespace().map(|s| s.to_string()).collect(),
+            rating,
+            score: post.score,
+            author: (!post.tag_string_artist.is_empty()).then_some(post.tag_string_artist),
+            is_nsfw: rating != Rating::Safe,
+            md5: post.md5.filter(|_| original),
+            file_size: post.file_size.filter(|_| original),
+            page_url: None,
+            origin_url: post.source.as_deref().and_then(origin_url),
+            attribution: None,
+            tag_categories,
+        })
+    }
+
+    /// Active `tag_aliases` or `tag_implications` whose antecedent or
+    /// consequent (`side`) is one of `tags`
+    async fn tag_relations(&self, kind: &str, side: &str, tags: &[String]) -> Result<Vec<DanbooruTagRelation>> {
+        let mut relations = Vec::new();
+        for batch in tags.chunks(RELATION_BATCH) {
+            let response = self.get(&format!("{}/{}.json", self.base_url, kind))
+                .query(&[("search[status]", "active"), ("limit", "1000")])
+                .query(&[(format!("search[{}_name_comma]", side), batch.join(","))])
+                .fetch(&self.client, &self.name)
+                .await?;
+
+            let batch: Vec<DanbooruTagRelation> = response
+                .json()
+                .await
+                .map_err(|e| parse_error(&self.name, e))?;
+            relations.extend(batch);
         }
+        Ok(relations)
     }
 }
 
@@ -54,85 +190,78 @@ impl Default for DanbooruConnector {
 #[async_trait]
 impl BooruConnector for DanbooruConnector {
     fn name(&self) -> &str {
-        "danbooru"
+        &self.name
+    }
+
+    fn translate(&self, query: &Query) -> Translation {
+        // Anonymous searches are limited to two tags, accounts to six
+        let mut groups = 0;
+        Translation::new(query)
+            .limit(if self.auth.is_empty() { 2 } else { 6 })
+            .tags()
+            .any_of(|group| {
+                // Every `~tag` belongs to the same alternative
+                groups += 1;
+                (groups == 1).then(|| group.iter().map(|tag| format!("~{}", tag)).collect())
+            })
+            .excluded("-")
+            .rating(|ratings| {
+                // Every rating is no filter at all
+                (ratings.len() < 3).then(|| {
+                    let codes: Vec<_> = ratings.iter().map(|rating| rating_codes(*rating)).collect();
+                    vec![format!("rating:{}", codes.join(","))]
+                })
+            })
+            .size(|width, height| {
+                let mut terms = Vec::new();
+                terms.extend(width.map(|w| format!("width:>={}", w)));
+                terms.extend(height.map(|h| format!("height:>={}", h)));
+                Some(terms)
+            })
+            .ratio(|ratio| Some(vec![format!("ratio:{}", query::range_value(ratio.min, ratio.max))]))
+            .order(|order| {
+                Some(match order {
+                    Order::Score => vec!["order:score".to_string()],
+                    Order::Date => Vec::new(),
+                    Order::Random => vec!["order:random".to_string()],
+                    Order::Favorites => vec!["order:favcount".to_string()],
+                })
+            })
     }
 
     async fn search(&self, tags: &[String], limit: u32, page: u32) -> Result<Vec<BooruImage>> {
         let tags_str = tags.join(" ");
         let url = format!(
             "{}/posts.json?tags={}&limit={}&page={}",
-            API_BASE,
+            self.base_url,
             urlencoding::encode(&tags_str),
             limit.min(200),
             page
         );
 
-        let response = self.client.client()
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| Error::Renderer(format!("Danbooru API error: {}", e)))?;
-
-        if !response.status().is_success() {
-            return Err(Error::Renderer(format!(
-                "Danbooru API returned status: {}",
-                response.status()
-            )));
-        }
+        let response = self.get(&url)
+            .fetch(&self.client, &self.name)
+            .await?;
 
         let posts: Vec<DanbooruPost> = response
             .json()
             .await
-            .map_err(|e| Error::Renderer(format!("Failed to parse Danbooru response: {}", e)))?;
+            .map_err(|e| parse_error(&self.name, e))?;
 
-        let images = posts
-            .into_iter()
-            .filter_map(|post| {
-                let file_url = post.file_url.clone().or_else(|| post.large_file_url.clone())?;
-                let sample_url = post.large_file_url.clone();
-                let is_nsfw = post.rating != "s";
-
-                Some(BooruImage {
-                    id: post.id.to_string(),
-                    source: "danbooru".to_string(),
-                    file_url,
-                    preview_url: post.preview_file_url.clone(),
-                    sample_url,
-                    width: post.image_width,
-                    height: post.image_height,
-                    tags: post.tag_string.split_whitespace().map(|s| s.to_string()).collect(),
-                    rating: parse_rating(&post.rating),
-                    score: post.score,
-                    author: if !post.tag_string_artist.is_empty() {
-                        Some(post.tag_string_artist)
-                    } else {
-                        None
-                    },
-                    is_nsfw,
-                })
-            })
-            .collect();
-
-        Ok(images)
+        Ok(posts.into_iter().filter_map(|post| self.to_image(post)).collect())
     }
 
     async fn autocomplete_tags(&self, prefix: &str, limit: u32) -> Result<Vec<String>> {
         let url = format!(
             "{}/autocomplete.json?search[query]={}&search[type]=tag_query&limit={}",
-            API_BASE,
+            self.base_url,
             urlencoding::encode(prefix),
             limit.min(20)
         );
 
-        let response = self.client.client()
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| Error::Renderer(format!("Danbooru autocomplete error: {}", e)))?;
-
-        if !response.status().is_success() {
-            return Ok(Vec::new());
-        }
+        let response = self.get(&url)
+            .fetch(&self.client, &self.name)
+            .await?;
 
         #[derive(Deserialize)]
         struct AutocompleteResult {
@@ -142,53 +271,192 @@ impl BooruConnector for DanbooruConnector {
         let results: Vec<AutocompleteResult> = response
             .json()
             .await
-            .unwrap_or_default();
+            .map_err(|e| parse_error(&self.name, e))?;
 
         Ok(results.into_iter().map(|r| r.value).collect())
     }
 
     async fn get_image(&self, id: &str) -> Result<BooruImage> {
-        let url = format!("{}/posts/{}.json", API_BASE, id);
-
-        let response = self.client.client()
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| Error::Renderer(format!("Danbooru API error: {}", e)))?;
+        let url = format!("{}/posts/{}.json", self.base_url, id);
 
-        if !response.status().is_success() {
-            return Err(Error::NotFound(format!("Post {} not found", id)));
-        }
+        let response = self.get(&url)
+            .fetch(&self.client, &self.name)
+            .await?;
 
         let post: DanbooruPost = response
             .json()
             .await
-            .map_err(|e| Error::Renderer(format!("Failed to parse Danbooru response: {}", e)))?;
+            .map_err(|e| parse_error(&self.name, e))?;
 
-        let file_url = post.file_url.clone()
-            .or_else(|| post.large_file_url.clone())
-            .ok_or_else(|| Error::NotFound("No file URL found".to_string()))?;
-        
-        let sample_url = post.large_file_url.clone();
-        let is_nsfw = post.rating != "s";
+        self.to_image(post)
+            .ok_or_else(|| Error::NotFound("No file URL found".to_string()))
+    }
 
-        Ok(BooruImage {
-            id: post.id.to_string(),
-            source: "danbooru".to_string(),
-            file_url,
-            preview_url: post.preview_file_url,
-            sample_url,
-            width: post.image_width,
-            height: post.image_height,
-            tags: post.tag_string.split_whitespace().map(|s| s.to_string()).collect(),
-            rating: parse_rating(&post.rating),
-            score: post.score,
-            author: if !post.tag_string_artist.is_empty() {
-                Some(post.tag_string_artist)
-            } else {
-                None
-            },
-            is_nsfw,
-        })
+    async fn favorites(&self, limit: u32, page: u32) -> Result<Vec<BooruImage>> {
+        let tag = format!("ordfav:{}", self.login()?);
+        self.search(&[tag], limit, page).await
+    }
+
+    async fn tag_aliases(&self, tags: &[String]) -> Result<Vec<TagAlias>> {
+        let mut relations = self.tag_relations("tag_aliases", "antecedent", tags).await?;
+        relations.extend(self.tag_relations("tag_aliases", "consequent", tags).await?);
+
+        let mut aliases: Vec<TagAlias> = relations
+            .into_iter()
+            .map(|r| TagAlias { alias: r.antecedent_name, tag: r.consequent_name })
+            .collect();
+        aliases.sort_by(|a, b| a.alias.cmp(&b.alias));
+        aliases.dedup_by(|a, b| a.alias == b.alias);
+        Ok(aliases)
+    }
+
+    async fn tag_implications(&self, tags: &[String]) -> Result<Vec<TagImplication>> {
+        let relations = self.tag_relations("tag_implications", "antecedent", tags).await?;
+        Ok(relations
+            .into_iter()
+            .map(|r| TagImplication { tag: r.antecedent_name, implied: r.consequent_name })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_tag_relations() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/tag_aliases.json"))
+            .and(query_param("search[status]", "active"))
+            .and(query_param("search[antecedent_name_comma]", "bg,hatsune_miku"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"[{"id": 1, "antecedent_name": "bg", "consequent_name": "background", "status": "active"}]"#,
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/tag_aliases.json"))
+            .and(query_param("search[consequent_name_comma]", "bg,hatsune_miku"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"[{"id": 2, "antecedent_name": "miku", "consequent_name": "hatsune_miku", "status": "active"}]"#,
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/tag_implications.json"))
+            .and(query_param("search[antecedent_name_comma]", "bg,hatsune_miku"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"[{"id": 3, "antecedent_name": "hatsune_miku", "consequent_name": "vocaloid", "status": "active"}]"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let connector = DanbooruConnector::with_base_url("danbooru", &server.uri());
+        let tags = vec!["bg".to_string(), "hatsune_miku".to_string()];
+        assert_eq!(
+            connector.tag_aliases(&tags).await.unwrap(),
+            vec![
+                TagAlias { alias: "bg".to_string(), tag: "background".to_string() },
+                TagAlias { alias: "miku".to_string(), tag: "hatsune_miku".to_string() },
+            ]
+        );
+        assert_eq!(
+            connector.tag_implications(&tags).await.unwrap(),
+            vec![TagImplication { tag: "hatsune_miku".to_string(), implied: "vocaloid".to_string() }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ratings() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/posts.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"[{"id": 1, "file_url": "https://cdn.donmai.us/a.jpg", "md5": "a1", "image_width": 1920, "image_height": 1080, "tag_string": "sky", "rating": "g"},
+                    {"id": 2, "file_url": "https://cdn.donmai.us/b.jpg", "image_width": 1920, "image_height": 1080, "tag_string": "sky", "rating": "s"}]"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let connector = DanbooruConnector::with_base_url("danbooru", &server.uri());
+        let images = connector.search(&["sky".to_string()], 10, 1).await.unwrap();
+        let ratings: Vec<_> = images.iter().map(|image| (image.rating, image.is_nsfw)).collect();
+        assert_eq!(ratings, vec![(Rating::Safe, false), (Rating::Questionable, true)]);
+        assert_eq!(images[0].md5.as_deref(), Some("a1"));
+
+        // Only the original's checksum is kept
+        Mock::given(method("GET"))
+            .and(path("/posts/3.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"id": 3, "large_file_url": "https://cdn.donmai.us/sample.jpg", "md5": "abc", "file_size": 9000000,
+                    "image_width": 4000, "image_height": 3000, "tag_string": "sky", "rating": "g"}"#,
+            ))
+            .mount(&server)
+            .await;
+        let image = connector.get_image("3").await.unwrap();
+        assert_eq!(image.file_url, "https://cdn.donmai.us/sample.jpg");
+        assert_eq!((image.md5, image.file_size), (None, None));
+
+        let translation = connector.translate(&Query::parse("sky rating:safe").unwrap());
+        assert_eq!(translation.tags, vec!["sky", "rating:g"]);
+        let translation = connector.translate(&Query::parse("rating:s,q").unwrap());
+        assert_eq!(translation.tags, vec!["rating:g,s,q"]);
+    }
+
+    #[tokio::test]
+    async fn test_autocomplete_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/autocomplete.json"))
+            .and(query_param("search[query]", "sky"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"[{"value": "sky"}, {"value": "skyline"}]"#))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/autocomplete.json"))
+            .and(query_param("search[query]", "broken"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html>"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/autocomplete.json"))
+            .and(query_param("search[query]", "private"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&server)
+            .await;
+
+        let connector = DanbooruConnector::with_base_url("danbooru", &server.uri());
+        assert_eq!(connector.autocomplete_tags("sky", 5).await.unwrap(), vec!["sky", "skyline"]);
+        assert!(matches!(connector.autocomplete_tags("broken", 5).await, Err(Error::Parse(_))));
+        assert!(matches!(connector.autocomplete_tags("private", 5).await, Err(Error::AuthFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_meta_terms_over_the_limit() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/posts.json"))
+            .and(query_param("tags", "sky cloud"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"[{"id": 7, "file_url": "https://cdn.donmai.us/a.jpg", "image_width": 1920, "image_height": 1080,
+                     "tag_string": "sky cloud", "rating": "g"}]"#,
+            ))
+            .mount(&server)
+            .await;
+
+        // Anonymous searches take two terms; the id range can't be checked on tags
+        let connector = DanbooruConnector::with_base_url("danbooru", &server.uri());
+        let query = Query::parse("sky cloud id:>5 -user:bob").unwrap();
+        let translation = connector.translate(&query);
+        assert_eq!(translation.tags, vec!["sky", "cloud"]);
+        assert!(translation.residual.tags.is_empty() && translation.residual.excluded.is_empty());
+
+        let page = connector.search_query(&query, 10, 1).await.unwrap();
+        let ids: Vec<_> = page.images.iter().map(|image| image.id.as_str()).collect();
+        assert_eq!(ids, vec!["7"]);
     }
 }
@@ -0,0 +1,512 @@
+use crate::booru::BooruClient;
+use md5::{Digest, Md5};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use wallmgr_core::config::LibraryConfig;
+use wallmgr_core::error::{Error, Result};
+use wallmgr_core::types::BooruImage;
+
+/// How often progress callbacks fire while a download is running
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Snapshot of a running download
+#[derive(Debug, Clone, Copy)]
+pub struct DownloadProgress {
+    pub downloaded: u64,
+    pub total: Option<u64>,
+    /// Average speed since the download started
+    pub bytes_per_second: f64,
+}
+
+impl DownloadProgress {
+    /// Completed fraction (0.0 - 1.0), if the total size is known
+    pub fn fraction(&self) -> Option<f32> {
+        self.total
+            .filter(|&t| t > 0)
+            .map(|t| (self.downloaded as f64 / t as f64).min(1.0) as f32)
+    }
+
+    /// Estimated time remaining, if the total size is known
+    pub fn eta(&self) -> Option<Duration> {
+        let remaining = self.total?.saturating_sub(self.downloaded);
+        if self.bytes_per_second <= 0.0 {
+            return None;
+        }
+        Some(Duration::from_secs_f64(remaining as f64 / self.bytes_per_second))
+    }
+}
+
+/// A file that was downloaded and moved into the library
+#[derive(Debug, Clone)]
+pub struct DownloadedFile {
+    pub path: PathBuf,
+    pub size: u64,
+    pub md5: String,
+}
+
+/// Downloads booru images into the library directory
+pub struct Downloader {
+    client: BooruClient,
+    library: LibraryConfig,
+}
+
+impl Downloader {
+    pub fn new(library: LibraryConfig) -> Self {
+        Self {
+            client: BooruClient::new(),
+            library,
+        }
+    }
+
+    /// Where `image` is stored, following the configured layout
+    pub fn library_path(&self, image: &BooruImage) -> PathBuf {
+        let now = chrono::Local::now();
+        let ext = file_extension(&image.file_url);
+
+        let mut relative = self.library.layout.clone();
+        for (key, value) in [
+            ("{source}", image.source.clone()),
+            ("{id}", image.id.clone()),
+            ("{md5}", image.md5.clone().unwrap_or_else(|| image.id.clone())),
+            ("{rating}", image.rating.as_str().to_string()),
+            ("{artist}", image.author.clone().unwrap_or_else(|| "unknown".to_string())),
+            ("{year}", now.format("%Y").to_string()),
+            ("{month}", now.format("%m").to_string()),
+            ("{ext}", ext),
+        ] {
+            relative = relative.replace(key, &sanitize(&value));
+        }
+
+        // Placeholders are sanitized, but the template itself may contain anything
+        let relative: PathBuf = Path::new(&relative)
+            .components()
+            .filter(|c| matches!(c, std::path::Component::Normal(_)))
+            .collect();
+
+        self.library.dir.join(relative)
+    }
+
+    /// Stream `image` to a temporary file, verify it and move it into the library
+    ///
+    /// The size and MD5 are checked when the source provides them. If the
+    /// destination already holds the same file it is returned as is.
+    pub async fn download<F>(&self, image: &BooruImage, on_progress: F) -> Result<DownloadedFile>
+    where
+        F: FnMut(&DownloadProgress),
+    {
+        self.download_until(image, &AtomicBool::new(false), on_progress).await
+    }
+
+    /// Like [`download`](Self::download), but stops with `Error::Cancelled` once
+    /// `stop` is set
+    ///
+    /// A partial file left by an interrupted or stopped download is resumed
+    /// with an HTTP Range request. It is only discarded when verification
+    /// fails or the server doesn't support ranges.
+    pub async fn download_until<F>(
+        &self,
+        image: &BooruImage,
+        stop: &AtomicBool,
+        mut on_progress: F,
+    ) -> Result<DownloadedFile>
+    where
+        F: FnMut(&DownloadProgress),
+    {
+        let destination = self.library_path(image);
+        if destination.exists() {
+            return self.existing(image, destination).await;
+        }
+
+        let temp_path = self.partial_path(image);
+        if let Some(parent) = temp_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let (size, md5) = self.fetch(image, &temp_path, stop, &mut on_progress).await?;
+
+        if let Some(parent) = destination.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        move_file(&temp_path, &destination).await?;
+
+        Ok(DownloadedFile {
+            path: destination,
+            size,
+            md5,
+        })
+    }
+
+    /// Where the unfinished download of `image` is kept
+    pub fn partial_path(&self, image: &BooruImage) -> PathBuf {
+        self.library.dir.join(".partial").join(format!(
+            "{}-{}.part",
+            sanitize(&image.source),
+            sanitize(&image.id)
+        ))
+    }
+
+    /// Delete the unfinished download of `image`, if any
+    pub async fn discard_partial(&self, image: &BooruImage) -> Result<()> {
+        match tokio::fs::remove_file(self.partial_path(image)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn fetch<F>(
+        &self,
+        image: &BooruImage,
+        temp_path: &Path,
+        stop: &AtomicBool,
+        on_progress: &mut F,
+    ) -> Result<(u64, String)>
+    where
+        F: FnMut(&DownloadProgress),
+    {
+        let mut offset = tokio::fs::metadata(temp_path).await.map(|m| m.len()).unwrap_or(0);
+
+        let mut request = self.client.client().get(&image.file_url);
+        // Hotlink-protected hosts such as Pixiv's only serve their own pages
+        if let Some(page_url) = &image.page_url {
+            request = request.header(reqwest::header::REFERER, page_url);
+        }
+        if offset > 0 {
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+        }
+        let mut response = request
+            .send()
+            .await
+            .map_err(|e| Error::Network(format!("Download of {} failed: {}", image.file_url, e)))?;
+
+        if response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+            // The partial file doesn't match what the server has; start over
+            tokio::fs::remove_file(temp_path).await?;
+            return Err(Error::Network(format!(
+                "Partial download of {} is no longer valid",
+                image.file_url
+            )));
+        }
+        response = response
+            .error_for_status()
+            .map_err(|e| Error::Network(format!("Download of {} failed: {}", image.file_url, e)))?;
+
+        let resumed = offset > 0 && response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+        let mut hasher = Md5::new();
+        let mut file = if resumed {
+            // Seed the hash with what is already on disk
+            let mut existing = tokio::fs::File::open(temp_path).await?;
+            let mut buffer = vec![0u8; 64 * 1024];
+            loop {
+                let read = existing.read(&mut buffer).await?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..read]);
+            }
+            tokio::fs::OpenOptions::new().append(true).open(temp_path).await?
+        } else {
+            offset = 0;
+            tokio::fs::File::create(temp_path).await?
+        };
+
+        let content_length = response.content_length().map(|len| len + offset);
+        let total = content_length.or(image.file_size);
+        let mut downloaded = offset;
+        let started = Instant::now();
+        let mut last_report = started;
+
+        let progress = |downloaded: u64| DownloadProgress {
+            downloaded,
+            total,
+            bytes_per_second: (downloaded - offset) as f64 / started.elapsed().as_secs_f64().max(1e-3),
+        };
+
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| Error::Network(format!("Download of {} interrupted: {}", image.file_url, e)))?
+        {
+            file.write_all(&chunk).await?;
+            hasher.update(&chunk);
+            downloaded += chunk.len() as u64;
+
+            if last_report.elapsed() >= PROGRESS_INTERVAL {
+                last_report = Instant::now();
+                on_progress(&progress(downloaded));
+            }
+
+            if stop.load(Ordering::Relaxed) {
+                file.flush().await?;
+                on_progress(&progress(downloaded));
+                return Err(Error::Cancelled(format!("Download of {} stopped", image.file_url)));
+            }
+        }
+        file.flush().await?;
+        on_progress(&progress(downloaded));
+
+        for expected in [content_length, image.file_size].into_iter().flatten() {
+            if downloaded != expected {
+                tokio::fs::remove_file(temp_path).await?;
+                return Err(Error::Network(format!(
+                    "Size mismatch for {}: expected {} bytes, got {}",
+                    image.file_url, expected, downloaded
+                )));
+            }
+        }
+
+        let md5 = format!("{:x}", hasher.finalize());
+        if let Some(expected) = &image.md5 {
+            if !expected.eq_ignore_ascii_case(&md5) {
+                tokio::fs::remove_file(temp_path).await?;
+                return Err(Error::Network(format!(
+                    "MD5 mismatch for {}: expected {}, got {}",
+                    image.file_url, expected, md5
+                )));
+            }
+        }
+
+        Ok((downloaded, md5))
+    }
+
+    async fn existing(&self, image: &BooruImage, path: PathBuf) -> Result<DownloadedFile> {
+        let content = tokio::fs::read(&path).await?;
+        let md5 = format!("{:x}", Md5::digest(&content));
+
+        match &image.md5 {
+            Some(expected) if expected.eq_ignore_ascii_case(&md5) => Ok(DownloadedFile {
+                path,
+                size: content.len() as u64,
+                md5,
+            }),
+            _ => Err(Error::AlreadyExists(format!(
+                "{} already exists in the library",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// Rename, falling back to copy + delete across filesystems
+async fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if tokio::fs::rename(from, to).await.is_ok() {
+        return Ok(());
+    }
+    tokio::fs::copy(from, to).await?;
+    tokio::fs::remove_file(from).await?;
+    Ok(())
+}
+
+/// Extension of the file in a URL, without query string
+fn file_extension(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit('/')
+        .next()
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, ext)| ext.to_lowercase())
+        .filter(|ext| !ext.is_empty() && ext.len() <= 5 && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+        .unwrap_or_else(|| "jpg".to_string())
+}
+
+/// Make a value safe to use as a single path component
+fn sanitize(value: &str) -> String {
+    let cleaned: String = value
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | '\0') || c.is_control() { '_' } else { c })
+        .collect();
+
+    match cleaned.trim() {
+        "" | "." | ".." => "_".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use wallmgr_core::types::Rating;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn image(url: String, body: &[u8]) -> BooruImage {
+        BooruImage {
+            id: "42".to_string(),
+            source: "danbooru".to_string(),
+            file_url: url,
+            preview_url: None,
+            sample_url: None,
+            width: 1,
+            height: 1,
+            tags: vec![],
+            rating: Rating::Safe,
+            score: None,
+            author: Some("some/artist".to_string()),
+            is_nsfw: false,
+            md5: Some(format!("{:x}", Md5::digest(body))),
+            file_size: Some(body.len() as u64),
+            page_url: None,
+            origin_url: None,
+            attribution: None,
+            tag_categories: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_library_layout() {
+        let downloader = Downloader::new(LibraryConfig {
+            dir: PathBuf::from("/lib"),
+            layout: "{source}/{rating}/{artist}/../{id}.{ext}".to_string(),
+        });
+
+        let img = image("https://cdn.example/a/b.PNG?x=1".to_string(), b"");
+        assert_eq!(
+            downloader.library_path(&img),
+            PathBuf::from("/lib/danbooru/safe/some_artist/42.png")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_verifies_and_moves() {
+        let server = MockServer::start().await;
+        let body = vec![7u8; 100_000];
+        Mock::given(method("GET"))
+            .and(path("/data/42.jpg"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(body.clone()))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let downloader = Downloader::new(LibraryConfig {
+            dir: dir.path().to_path_buf(),
+            layout: "{source}/{id}.{ext}".to_string(),
+        });
+
+        let img = image(format!("{}/data/42.jpg", server.uri()), &body);
+        let mut last = None;
+        let file = downloader.download(&img, |p| last = Some(*p)).await.unwrap();
+
+        assert_eq!(file.path, dir.path().join("danbooru/42.jpg"));
+        assert_eq!(file.size, body.len() as u64);
+        assert_eq!(std::fs::read(&file.path).unwrap(), body);
+        assert_eq!(last.unwrap().fraction(), Some(1.0));
+        assert_eq!(std::fs::read_dir(dir.path().join(".partial")).unwrap().count(), 0);
+
+        // Downloading the same image again finds the existing file
+        assert!(downloader.download(&img, |_| {}).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_download_sends_page_as_referer() {
+        let server = MockServer::start().await;
+        let body = b"protected".to_vec();
+        Mock::given(method("GET"))
+            .and(header("referer", "https://www.pixiv.net/artworks/42"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(body.clone()))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let downloader = Downloader::new(LibraryConfig {
+            dir: dir.path().to_path_buf(),
+            layout: "{id}.{ext}".to_string(),
+        });
+
+        let mut img = image(format!("{}/42.png", server.uri()), &body);
+        assert!(downloader.download(&img, |_| {}).await.is_err());
+        img.page_url = Some("https://www.pixiv.net/artworks/42".to_string());
+        assert!(downloader.download(&img, |_| {}).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_download_rejects_corrupt_files() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"corrupted".to_vec()))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let downloader = Downloader::new(LibraryConfig {
+            dir: dir.path().to_path_buf(),
+            layout: "{id}.{ext}".to_string(),
+        });
+
+        let mut img = image(format!("{}/42.jpg", server.uri()), b"original!");
+        let err = downloader.download(&img, |_| {}).await.unwrap_err();
+        assert!(err.to_string().contains("MD5 mismatch"), "{}", err);
+
+        img.file_size = Some(3);
+        let err = downloader.download(&img, |_| {}).await.unwrap_err();
+        assert!(err.to_string().contains("Size mismatch"), "{}", err);
+
+        assert!(!dir.path().join("42.jpg").exists());
+        assert_eq!(std::fs::read_dir(dir.path().join(".partial")).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_download_resumes_partial_files() {
+        let server = MockServer::start().await;
+        let body: Vec<u8> = (0..50_000u32).map(|i| (i % 251) as u8).collect();
+        Mock::given(method("GET"))
+            .and(header("range", "bytes=20000-"))
+            .respond_with(ResponseTemplate::new(206).set_body_bytes(body[20_000..].to_vec()))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let downloader = Downloader::new(LibraryConfig {
+            dir: dir.path().to_path_buf(),
+            layout: "{id}.{ext}".to_string(),
+        });
+
+        let img = image(format!("{}/42.jpg", server.uri()), &body);
+        let partial = downloader.partial_path(&img);
+        std::fs::create_dir_all(partial.parent().unwrap()).unwrap();
+        std::fs::write(&partial, &body[..20_000]).unwrap();
+
+        let mut first = None;
+        let file = downloader
+            .download(&img, |p| {
+                first.get_or_insert(*p);
+            })
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&file.path).unwrap(), body);
+        assert_eq!(first.unwrap().total, Some(body.len() as u64));
+        assert!(!partial.exists());
+    }
+
+    #[tokio::test]
+    async fn test_stopped_download_keeps_partial_file() {
+        let server = MockServer::start().await;
+        let body = vec![1u8; 10_000];
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(body.clone()))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let downloader = Downloader::new(LibraryConfig {
+            dir: dir.path().to_path_buf(),
+            layout: "{id}.{ext}".to_string(),
+        });
+
+        let img = image(format!("{}/42.jpg", server.uri()), &body);
+        let err = downloader
+            .download_until(&img, &AtomicBool::new(true), |_| {})
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Cancelled(_)), "{}", err);
+        assert!(downloader.partial_path(&img).exists());
+
+        // A server that ignores the Range header sends the whole file again
+        let file = downloader.download(&img, |_| {}).await.unwrap();
+        assert_eq!(std::fs::read(&file.path).unwrap(), body);
+    }
+}
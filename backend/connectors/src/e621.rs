@@ -0,0 +1,387 @@
+use crate::booru::{BooruClient, BooruConnector, SendWith, parse_rating, parse_error, request_error, origin_url, tag_categories};
+use crate::query::{self, Order, Query, Translation};
+use async_trait::async_trait;
+use serde::Deserialize;
+use wallmgr_core::error::{Error, Result};
+use wallmgr_core::types::{BooruImage, TagCategory};
+
+/// e621 and its safe-for-work mirror e926
+/// API: https://e621.net/help/api
+pub struct E621Connector {
+    client: BooruClient,
+    name: String,
+    base_url: String,
+    auth: Option<(String, String)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct E621Post {
+    id: i64,
+    file: E621File,
+    #[serde(default)]
+    preview: E621Url,
+    #[serde(default)]
+    sample: E621Url,
+    #[serde(default)]
+    score: E621Score,
+    #[serde(default)]
+    tags: E621Tags,
+    #[serde(default)]
+    sources: Vec<String>,
+    rating: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct E621File {
+    width: u32,
+    height: u32,
+    /// Null for posts hidden from anonymous users
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    md5: Option<String>,
+    #[serde(default)]
+    size: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct E621Url {
+    #[serde(default)]
+    url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct E621Score {
+    #[serde(default)]
+    total: i32,
+}
+
+/// Tags grouped by category
+#[derive(Debug, Default, Deserialize)]
+struct E621Tags {
+    #[serde(default)]
+    general: Vec<String>,
+    #[serde(default)]
+    artist: Vec<String>,
+    #[serde(default)]
+    character: Vec<String>,
+    #[serde(default)]
+    copyright: Vec<String>,
+    #[serde(default)]
+    species: Vec<String>,
+    #[serde(default)]
+    meta: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct E621Posts {
+    posts: Vec<E621Post>,
+}
+
+#[derive(Debug, Deserialize)]
+struct E621SinglePost {
+    post: E621Post,
+}
+
+#[derive(Debug, Deserialize)]
+struct E621Tag {
+    name: String,
+}
+
+/// Tag searches without results return `{"tags": []}` instead of a list
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum E621TagResponse {
+    Tags(Vec<E621Tag>),
+    Empty {},
+}
+
+impl E621Connector {
+    pub fn new(name: &str, base_url: &str) -> Self {
+        Self {
+            client: BooruClient::new(),
+            name: name.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            auth: None,
+        }
+    }
+
+    /// e621.net
+    pub fn e621() -> Self {
+        Self::new("e621", "https://e621.net")
+    }
+
+    /// e926.net, which only serves safe posts
+    pub fn e926() -> Self {
+        Self::new("e926", "https://e926.net")
+    }
+
+    /// Authenticate requests with a username and API key
+    pub fn with_credentials(mut self, login: &str, api_key: &str) -> Self {
+        self.auth = Some((login.to_string(), api_key.to_string()));
+        self
+    }
+
+    fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        let request = self.client.client().get(url);
+        match &self.auth {
+            Some((login, key)) => request.basic_auth(login, Some(key)),
+            None => request,
+        }
+    }
+
+    fn post_to_image(&self, post: E621Post) -> Option<BooruImage> {
+        let file_url = post.file.url?;
+        let rating = parse_rating(&post.rating);
+        let E621Tags { general, artist, character, copyright, species, meta } = post.tags;
+        let author = artist.iter().find(|a| *a != "conditional_dnp").cloned();
+        let tag_categories = tag_categories([
+            (TagCategory::Artist, &artist),
+            (TagCategory::Copyright, &copyright),
+            (TagCategory::Character, &character),
+            (TagCategory::General, &species),
+            (TagCategory::General, &general),
+            (TagCategory::Meta, &meta),
+        ]
+        .map(|(category, tags)| (category, tags.iter().map(String::as_str))));
+
+        Some(BooruImage {
+            id: post.id.to_string(),
+            source: self.name.clone(),
+            file_url,
+            preview_url: post.preview.url,
+            sample_url: post.sample.url,
+            width: post.file.width,
+            height: post.file.height,
+            tags: [artist, copyright, character, species, general, meta].concat(),
+            rating,
+            score: Some(post.score.total),
+            author,
+            is_nsfw: post.rating != "s",
+            md5: post.file.md5,
+            file_size: post.file.size,
+            page_url: None,
+            origin_url: post.sources.iter().find_map(|s| origin_url(s)),
+            attribution: None,
+            tag_categories,
+        })
+    }
+}
+
+#[async_trait]
+impl BooruConnector for E621Connector {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn translate(&self, query: &Query) -> Translation {
+        let mut groups = 0;
+        Translation::new(query)
+            .limit(40)
+            .tags()
+            .any_of(|group| {
+                // Every `~tag` belongs to the same alternative
+                groups += 1;
+                (groups == 1).then(|| group.iter().map(|tag| format!("~{}", tag)).collect())
+            })
+            .excluded("-")
+            .rating(|ratings| {
+                query::rating_codes(ratings)?;
+                Some(query::rating_terms(ratings, "rating", |r| &r.as_str()[..1]))
+            })
+            .size(|width, height| {
+                let mut terms = Vec::new();
+                terms.extend(width.map(|w| format!("width:>={}", w)));
+                terms.extend(height.map(|h| format!("height:>={}", h)));
+                Some(terms)
+            })
+            .ratio(|ratio| Some(vec![format!("ratio:{}", query::range_value(ratio.min, ratio.max))]))
+            .order(|order| {
+                Some(match order {
+                    Order::Score => vec!["order:score".to_string()],
+                    Order::Date => Vec::new(),
+                    Order::Random => vec!["order:random".to_string()],
+                    Order::Favorites => vec!["order:favcount".to_string()],
+                })
+            })
+    }
+
+    async fn search(&self, tags: &[String], limit: u32, page: u32) -> Result<Vec<BooruImage>> {
+        let tags_str = tags.join(" ");
+        let url = format!(
+            "{}/posts.json?tags={}&limit={}&page={}",
+            self.base_url,
+            urlencoding::encode(&tags_str),
+            limit.min(320),
+            page.max(1)
+        );
+
+        let response = self.get(&url)
+            .fetch(&self.client, &self.name)
+            .await?;
+
+        let posts: E621Posts = response
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))?;
+
+        Ok(posts.posts.into_iter().filter_map(|p| self.post_to_image(p)).collect())
+    }
+
+    async fn autocomplete_tags(&self, prefix: &str, limit: u32) -> Result<Vec<String>> {
+        let url = format!(
+            "{}/tags.json?search[name_matches]={}*&search[order]=count&limit={}",
+            self.base_url,
+            urlencoding::encode(prefix),
+            limit.min(20)
+        );
+
+        let response = self.get(&url)
+            .send_with(&self.client)
+            .await
+            .map_err(|e| request_error(&self.name, e))?;
+
+        if !response.status().is_success() {
+            return Ok(Vec::new());
+        }
+
+        let tags = match response.json::<E621TagResponse>().await {
+            Ok(E621TagResponse::Tags(tags)) => tags,
+            _ => Vec::new(),
+        };
+
+        Ok(tags.into_iter().map(|t| t.name).collect())
+    }
+
+    async fn get_image(&self, id: &str) -> Result<BooruImage> {
+        let url = format!("{}/posts/{}.json", self.base_url, urlencoding::encode(id));
+
+        let response = self.get(&url)
+            .fetch(&self.client, &self.name)
+            .await?;
+
+        let post: E621SinglePost = response
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))?;
+
+        self.post_to_image(post.post)
+            .ok_or_else(|| Error::NotFound("No file URL found".to_string()))
+    }
+
+    async fn favorites(&self, limit: u32, page: u32) -> Result<Vec<BooruImage>> {
+        let (login, _) = self
+            .auth
+            .as_ref()
+            .ok_or_else(|| Error::Config(format!("{} favorites need a login", self.name)))?;
+        self.search(&[format!("fav:{}", login)], limit, page).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const POST: &str = r#"{
+        "id": 4123456,
+        "file": {"width": 2560, "height": 1440, "ext": "png", "size": 4567890,
+                 "md5": "d41d8cd98f00b204e9800998ecf8427e",
+                 "url": "https://static1.e621.net/data/d4/1d/d41d8cd98f00b204e9800998ecf8427e.png"},
+        "preview": {"width": 150, "height": 84, "url": "https://static1.e621.net/data/preview/d4/1d/d41d8cd98f00b204e9800998ecf8427e.jpg"},
+        "sample": {"has": true, "height": 478, "width": 850, "url": "https://static1.e621.net/data/sample/d4/1d/d41d8cd98f00b204e9800998ecf8427e.jpg"},
+        "score": {"up": 40, "down": -2, "total": 38},
+        "tags": {"general": ["forest", "sky"], "artist": ["conditional_dnp", "someartist"],
+                 "character": [], "copyright": [], "species": ["wolf"], "invalid": [],
+                 "meta": ["hi_res"], "lore": []},
+        "rating": "s"
+    }"#;
+
+    #[tokio::test]
+    async fn test_search() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/posts.json"))
+            .and(query_param("tags", "wolf forest"))
+            .and(query_param("page", "3"))
+            .and(query_param("limit", "320"))
+            .and(header("authorization", "Basic dXNlcjprZXk="))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                r#"{{"posts": [{}, {{"id": 2, "file": {{"width": 1, "height": 1, "url": null}},
+                   "rating": "e"}}]}}"#,
+                POST
+            )))
+            .mount(&server)
+            .await;
+
+        let connector = E621Connector::new("e621", &server.uri()).with_credentials("user", "key");
+        let images = connector
+            .search(&["wolf".to_string(), "forest".to_string()], 500, 3)
+            .await
+            .unwrap();
+
+        // Posts without a file URL are skipped
+        assert_eq!(images.len(), 1);
+        let image = &images[0];
+        assert_eq!(image.id, "4123456");
+        assert_eq!(image.tags, vec!["conditional_dnp", "someartist", "wolf", "forest", "sky", "hi_res"]);
+        assert_eq!(image.author.as_deref(), Some("someartist"));
+        assert_eq!(image.tag_categories["someartist"], TagCategory::Artist);
+        assert_eq!(image.tag_categories["wolf"], TagCategory::General);
+        assert_eq!(image.tag_categories["hi_res"], TagCategory::Meta);
+        assert_eq!(image.score, Some(38));
+        assert_eq!(image.file_size, Some(4567890));
+        assert!(!image.is_nsfw);
+    }
+
+    #[tokio::test]
+    async fn test_get_image_and_autocomplete() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/posts/4123456.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(r#"{{"post": {}}}"#, POST)))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/tags.json"))
+            .and(query_param("search[name_matches]", "wol*"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"[{"id": 1, "name": "wolf", "post_count": 90000, "category": 5}]"#,
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/tags.json"))
+            .and(query_param("search[name_matches]", "zzz*"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"tags": []}"#))
+            .mount(&server)
+            .await;
+
+        let connector = E621Connector::new("e926", &server.uri());
+        let image = connector.get_image("4123456").await.unwrap();
+        assert_eq!(image.source, "e926");
+        assert_eq!(image.md5.as_deref(), Some("d41d8cd98f00b204e9800998ecf8427e"));
+
+        assert_eq!(connector.autocomplete_tags("wol", 5).await.unwrap(), vec!["wolf"]);
+        assert!(connector.autocomplete_tags("zzz", 5).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_translate() {
+        let connector = E621Connector::e621();
+        let query = Query::parse("wolf -cat fox|dog a|b rating:s,q res:1920x1080 ratio:>=1.5 order:favorites").unwrap();
+        let translation = connector.translate(&query);
+
+        assert_eq!(
+            translation.tags,
+            vec![
+                "wolf", "~fox", "~dog", "-cat", "-rating:e", "width:>=1920", "height:>=1080",
+                "ratio:1.50..", "order:favcount",
+            ]
+        );
+        // Only one alternative fits in e621's syntax
+        assert_eq!(translation.residual.any_of, vec![vec!["a", "b"]]);
+        assert_eq!(translation.residual.ratings, vec![]);
+    }
+}
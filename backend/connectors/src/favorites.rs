@@ -0,0 +1,216 @@
+//! Favorites sync
+//!
+//! Mirrors the favorites of authenticated source accounts into the library.
+//! New favorites are queued for download with the tag
+//! `favorite:<source>:<account>`. Listings are newest first, so a sync stops
+//! at the first page that holds nothing new.
+
+use crate::booru::BooruConnector;
+use crate::queue::DownloadQueue;
+use crate::registry::ConnectorRegistry;
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Duration;
+use wallmgr_core::config::{ConnectorConfig, FavoritesConfig};
+use wallmgr_core::error::{Error, Result};
+
+/// Favorites fetched per request
+const PAGE_LIMIT: u32 = 100;
+
+/// Upper bound on pages per sync, in case a source ignores the page number
+const MAX_PAGES: u32 = 500;
+
+/// Queue priority of synced favorites, behind downloads queued by hand
+const SYNC_PRIORITY: i32 = -1;
+
+pub struct FavoritesSync {
+    registry: Arc<ConnectorRegistry>,
+    queue: Arc<DownloadQueue>,
+    config: FavoritesConfig,
+}
+
+impl FavoritesSync {
+    pub fn new(registry: Arc<ConnectorRegistry>, queue: Arc<DownloadQueue>, config: FavoritesConfig) -> Self {
+        Self {
+            registry,
+            queue,
+            config,
+        }
+    }
+
+    /// Configured sources, or every authenticated one
+    fn sources(&self) -> Vec<(&ConnectorConfig, Arc<dyn BooruConnector>)> {
+        self.registry
+            .configs()
+            .filter(|c| match self.config.sources.is_empty() {
+                true => c.authenticated(),
+                false => self.config.sources.contains(&c.name),
+            })
+            .filter_map(|c| Some((c, self.registry.get(&c.name)?)))
+            .collect()
+    }
+
+    /// Sync every source; returns the number of newly queued favorites per source
+    pub async fn sync(&self) -> Vec<(String, Result<usize>)> {
+        let mut results = Vec::new();
+        for (config, connector) in self.sources() {
+            results.push((config.name.clone(), self.sync_source(config, connector.as_ref()).await));
+        }
+        results
+    }
+
+    /// Queue the favorites of one source that aren't queued or downloaded yet
+    pub async fn sync_source(&self, config: &ConnectorConfig, connector: &dyn BooruConnector) -> Result<usize> {
+        let started = Utc::now();
+        let tag = favorite_tag(config);
+        let mut queued = 0;
+
+        for page in 1..=MAX_PAGES {
+            let images = connector.favorites(PAGE_LIMIT, page).await?;
+            if images.is_empty() {
+                break;
+            }
+
+            let mut new = 0;
+            for mut image in images {
+                if !image.tags.contains(&tag) {
+                    image.tags.push(tag.clone());
+                }
+                // Images that already have a job keep it
+                match self.queue.enqueue(&image, SYNC_PRIORITY) {
+                    Ok(job) if job.created_at >= started => new += 1,
+                    Ok(_) => {}
+                    Err(Error::Blocked(reason)) => tracing::debug!("Not syncing favorite: {}", reason),
+                    Err(e) => return Err(e),
+                }
+            }
+            queued += new;
+            if new == 0 {
+                break;
+            }
+        }
+        Ok(queued)
+    }
+
+    /// Sync now and then every `interval_minutes`
+    pub async fn run(self) {
+        let interval = Duration::from_secs(self.config.interval_minutes.max(1) * 60);
+        loop {
+            for (source, result) in self.sync().await {
+                match result {
+                    Ok(0) => {}
+                    Ok(count) => tracing::info!("Queued {} new favorites from {}", count, source),
+                    Err(e) => tracing::error!("Favorites sync of {} failed: {}", source, e),
+                }
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+}
+
+/// Tag marking images favorited by the source account
+pub fn favorite_tag(config: &ConnectorConfig) -> String {
+    match &config.username {
+        Some(account) => format!("favorite:{}:{}", config.name, account),
+        None => format!("favorite:{}", config.name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::download::DownloadedFile;
+    use crate::queue::Importer;
+    use async_trait::async_trait;
+    use wallmgr_core::config::{ConnectorEngine, DownloadsConfig, LibraryConfig, SourcesConfig};
+    use wallmgr_core::types::DownloadJob;
+    use wallmgr_core::Database;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    struct NoImport;
+
+    #[async_trait]
+    impl Importer for NoImport {
+        async fn import(&self, _job: &DownloadJob, _file: &DownloadedFile) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn post(id: u32) -> String {
+        format!(
+            r#"{{"id": {0}, "file_url": "https://cdn.example/{0}.jpg", "image_width": 1920,
+                "image_height": 1080, "tag_string": "sky", "rating": "s"}}"#,
+            id
+        )
+    }
+
+    #[tokio::test]
+    async fn test_sync_queues_new_favorites() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/posts.json"))
+            .and(query_param("tags", "ordfav:alice"))
+            .and(query_param("login", "alice"))
+            .and(query_param("page", "1"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string(format!("[{}, {}]", post(3), post(2))),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/posts.json"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!("[{}]", post(1))))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/posts.json"))
+            .and(query_param("page", "3"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let sources = SourcesConfig {
+            connectors: vec![ConnectorConfig {
+                username: Some("alice".to_string()),
+                api_key: Some("key".to_string()),
+                ..ConnectorConfig::new("mybooru", ConnectorEngine::Danbooru2, &server.uri())
+            }],
+            ..SourcesConfig::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let queue = DownloadQueue::new(
+            Database::new(&dir.path().join("wallmgr.db")).unwrap(),
+            LibraryConfig {
+                dir: dir.path().join("library"),
+                layout: "{source}/{id}.{ext}".to_string(),
+            },
+            DownloadsConfig::default(),
+            Arc::default(),
+            Arc::new(NoImport),
+        );
+        let sync = FavoritesSync::new(
+            Arc::new(ConnectorRegistry::from_config(&sources).unwrap()),
+            queue.clone(),
+            FavoritesConfig::default(),
+        );
+
+        // Only the authenticated source is synced
+        let results = sync.sync().await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, "mybooru");
+        assert_eq!(*results[0].1.as_ref().unwrap(), 3);
+
+        let jobs = queue.jobs(None).unwrap();
+        assert_eq!(jobs.len(), 3);
+        assert!(jobs.iter().all(|j| j.image.tags.contains(&"favorite:mybooru:alice".to_string())));
+        assert!(jobs.iter().all(|j| j.priority == SYNC_PRIORITY));
+
+        // Nothing new on the first page ends the next sync there
+        assert_eq!(*sync.sync().await[0].1.as_ref().unwrap(), 0);
+    }
+}
@@ -0,0 +1,498 @@
+//! Federated search
+//!
+//! Searches several sources at once and merges their results page by page.
+//! Sources are queried concurrently with a timeout each, so a slow or failing
+//! site only costs its own results. Scores aren't comparable between sites,
+//! so images are ranked by their place in their own source's results, in the
+//! query's order (by score within a source for `order:score`), and an
+//! image cross-posted to several sites is listed once, matched by MD5 or by
+//! the original it links to. Each page comes with an opaque cursor that
+//! fetches the next page of every source that still has results. It
+//! remembers the images already listed, so cross-posts aren't listed again
+//! on later pages, and how often each source failed, so one that stays down
+//! is eventually left out. The query
+//! is written in wallmgr's [query language](crate::query) and translated for
+//! each source. Images the content policy blocks are left out.
+
+use crate::query::{Order, Query};
+use crate::registry::ConnectorRegistry;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use wallmgr_core::error::{Error, Result};
+use wallmgr_core::types::BooruImage;
+use wallmgr_core::ContentPolicy;
+
+pub struct FederatedSearch {
+    registry: Arc<ConnectorRegistry>,
+    timeout: Duration,
+    policy: Arc<ContentPolicy>,
+}
+
+/// One page of merged results
+#[derive(Debug)]
+pub struct FederatedPage {
+    pub images: Vec<FederatedImage>,
+    /// Continues the search with [`FederatedSearch::next`]; `None` once every
+    /// source ran out of results
+    pub cursor: Option<String>,
+    /// Sources that failed; the results of the others are still in `images`
+    pub errors: Vec<(String, Error)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FederatedImage {
+    pub image: BooruImage,
+    /// Place in its source's results, from 1.0 for the first towards 0.0
+    pub rank: f64,
+    /// Other sources the same image was found on
+    pub also_on: Vec<String>,
+}
+
+/// Times a source that is rate limited or unavailable is tried before it is
+/// left out of the search
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Identity keys of earlier pages a cursor remembers, about 7 KB once
+/// encoded; cross-posts of older images may be listed again
+const MAX_SEEN: usize = 200;
+
+/// The query and the next page of each source still searched
+#[derive(Serialize, Deserialize)]
+struct Cursor {
+    tags: Vec<String>,
+    /// Images asked of each source per page
+    limit: u32,
+    pages: Vec<(String, u32)>,
+    /// Failed attempts at the current page, by source
+    #[serde(default)]
+    failures: HashMap<String, u32>,
+    /// Hashed identity keys of the images listed so far, with their source
+    #[serde(default)]
+    seen: Vec<(String, String)>,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).expect("cursor serializes"))
+    }
+
+    fn decode(cursor: &str) -> Result<Self> {
+        URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| Error::InvalidInput("Invalid search cursor".to_string()))
+    }
+}
+
+impl FederatedSearch {
+    pub fn new(registry: Arc<ConnectorRegistry>, timeout: Duration) -> Self {
+        Self {
+            registry,
+            timeout,
+            policy: Arc::default(),
+        }
+    }
+
+    /// Leave out the images `policy` blocks
+    pub fn with_policy(mut self, policy: Arc<ContentPolicy>) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// First page of a search of `sources`, or of every enabled source when
+    /// empty; about `limit` images are asked for, split between the sources.
+    /// Fails only if the query doesn't parse.
+    pub async fn search(&self, sources: &[String], tags: &[String], limit: u32) -> Result<FederatedPage> {
+        let sources: Vec<String> = match sources.is_empty() {
+            true => self.registry.names().into_iter().map(String::from).collect(),
+            false => sources.to_vec(),
+        };
+        let per_source = limit.max(1).div_ceil(sources.len().max(1) as u32);
+
+        self.fetch(Cursor {
+            tags: tags.to_vec(),
+            limit: per_source,
+            pages: sources.into_iter().map(|source| (source, 1)).collect(),
+            failures: HashMap::new(),
+            seen: Vec::new(),
+        })
+        .await
+    }
+
+    /// The page after the one `cursor` came with
+    pub async fn next(&self, cursor: &str) -> Result<FederatedPage> {
+        self.fetch(Cursor::decode(cursor)?).await
+    }
+
+    async fn fetch(&self, mut cursor: Cursor) -> Result<FederatedPage> {
+        let query = Arc::new(Query::from_tags(&cursor.tags)?);
+        let searches: Vec<_> = cursor
+            .pages
+            .into_iter()
+            .map(|(source, page)| {
+                let search = self.registry.get(&source).map(|connector| {
+                    let (query, limit, timeout) = (query.clone(), cursor.limit, self.timeout);
+                    let name = source.clone();
+                    tokio::spawn(async move {
+                        tokio::time::timeout(timeout, connector.search_query(&query, limit, page))
+                            .await
+                            .unwrap_or_else(|_| {
+                                Err(Error::SourceUnavailable(format!("{} timed out after {:?}", name, timeout)))
+                            })
+                    })
+                });
+                (source, page, search)
+            })
+            .collect();
+
+        let mut results = Vec::new();
+        let mut pages = Vec::new();
+        let mut failures = HashMap::new();
+        let mut errors = Vec::new();
+        for (source, page, search) in searches {
+            let result = match search {
+                Some(handle) => handle
+                    .await
+                    .unwrap_or_else(|e| Err(Error::Api(format!("Search of {} failed: {}", source, e)))),
+                None => Err(Error::NotFound(format!("No enabled source named {}", source))),
+            };
+            match result {
+                Ok(found) => {
+                    if let Some(next) = found.next_page {
+                        pages.push((source, next));
+                    }
+                    results.push(self.policy.filter(found.images));
+                }
+                Err(e) => {
+                    // Worth trying the same page again with the next request,
+                    // unless it keeps failing
+                    let attempts = cursor.failures.get(&source).copied().unwrap_or(0) + 1;
+                    if matches!(e, Error::RateLimited(_) | Error::SourceUnavailable(_)) && attempts < MAX_ATTEMPTS {
+                        pages.push((source.clone(), page));
+                        failures.insert(source.clone(), attempts);
+                    }
+                    errors.push((source, e));
+                }
+            }
+        }
+
+        let images = merge(results, query.order, &mut cursor.seen);
+        let cursor = (!pages.is_empty()).then(|| {
+            Cursor {
+                tags: cursor.tags,
+                limit: cursor.limit,
+                pages,
+                failures,
+                seen: cursor.seen,
+            }
+            .encode()
+        });
+        Ok(FederatedPage {
+            images,
+            cursor,
+            errors,
+        })
+    }
+}
+
+/// Rank each source's images by place and list cross-posted images once,
+/// leaving out those listed on earlier pages (`seen`, which the images listed
+/// now are added to)
+fn merge(results: Vec<Vec<BooruImage>>, order: Option<Order>, seen: &mut Vec<(String, String)>) -> Vec<FederatedImage> {
+    let mut ranked = Vec::new();
+    for mut images in results {
+        // Scores only compare within a source; unscored images keep their order
+        if order == Some(Order::Score) {
+            images.sort_by_key(|image| Reverse(image.score));
+        }
+        let count = images.len() as f64;
+        ranked.extend(images.into_iter().enumerate().map(|(place, image)| FederatedImage {
+            image,
+            rank: 1.0 - place as f64 / count,
+            also_on: Vec::new(),
+        }));
+    }
+    // Stable, so equally ranked images alternate between sources
+    ranked.sort_by(|a, b| b.rank.total_cmp(&a.rank));
+
+    let mut merged: Vec<FederatedImage> = Vec::new();
+    let mut listed: HashMap<String, usize> = HashMap::new();
+    let mut listed_now = Vec::new();
+    for image in ranked {
+        let keys: Vec<String> = identity_keys(&image.image).iter().map(|key| hash_key(key)).collect();
+        // Pages of one pixiv work share a link, so only other sources count
+        let listed_before = keys
+            .iter()
+            .any(|key| seen.iter().any(|(seen, source)| seen == key && *source != image.image.source));
+        if listed_before {
+            continue;
+        }
+        let duplicate = keys
+            .iter()
+            .filter_map(|key| listed.get(key).copied())
+            .find(|&index| merged[index].image.source != image.image.source);
+
+        match duplicate {
+            Some(index) => {
+                let kept = &mut merged[index];
+                if !kept.also_on.contains(&image.image.source) {
+                    kept.also_on.push(image.image.source);
+                }
+            }
+            None => {
+                for key in keys {
+                    listed_now.push((key.clone(), image.image.source.clone()));
+                    listed.entry(key).or_insert(merged.len());
+                }
+                merged.push(image);
+            }
+        }
+    }
+
+    seen.extend(listed_now);
+    let excess = seen.len().saturating_sub(MAX_SEEN);
+    seen.drain(..excess);
+    merged
+}
+
+/// Short stand-in for an identity key, to keep cursors small
+fn hash_key(key: &str) -> String {
+    let digest = Md5::digest(key.as_bytes());
+    digest[..8].iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Keys under which the same picture shows up on different sites
+fn identity_keys(image: &BooruImage) -> Vec<String> {
+    let mut keys = Vec::new();
+    if let Some(md5) = &image.md5 {
+        keys.push(format!("md5:{}", md5.to_lowercase()));
+    }
+    for url in [&image.origin_url, &image.page_url].into_iter().flatten() {
+        keys.push(format!("url:{}", normalize_url(url)));
+    }
+    keys
+}
+
+fn normalize_url(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    let url = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .unwrap_or(url);
+    url.strip_prefix("www.").unwrap_or(url).to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wallmgr_core::config::{ConnectorConfig, ConnectorEngine, SourcesConfig};
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn post(id: u32, score: i32, md5: &str, source: &str) -> String {
+        format!(
+            r#"{{"id": {}, "file_url": "https://cdn.example/{}.jpg", "image_width": 1920,
+                "image_height": 1080, "tag_string": "sky", "rating": "g", "score": {},
+                "md5": "{}", "source": "{}"}}"#,
+            id, md5, score, md5, source
+        )
+    }
+
+    async fn mount_page(server: &MockServer, page: u32, posts: &[String]) {
+        Mock::given(method("GET"))
+            .and(path("/posts.json"))
+            .and(query_param("page", page.to_string()))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!("[{}]", posts.join(","))))
+            .mount(server)
+            .await;
+    }
+
+    fn registry(servers: &[(&str, &MockServer)]) -> Arc<ConnectorRegistry> {
+        let sources = SourcesConfig {
+            connectors: servers
+                .iter()
+                .map(|(name, server)| ConnectorConfig::new(name, ConnectorEngine::Danbooru2, &server.uri()))
+                .collect(),
+            ..SourcesConfig::default()
+        };
+        Arc::new(ConnectorRegistry::from_config(&sources).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_merges_ranks_and_pages() {
+        let first = MockServer::start().await;
+        mount_page(&first, 1, &[post(1, 5, "aaa", ""), post(2, 900, "bbb", "")]).await;
+        mount_page(&first, 2, &[]).await;
+
+        let second = MockServer::start().await;
+        mount_page(
+            &second,
+            1,
+            &[post(7, 3, "ccc", ""), post(8, 2, "BBB", "https://www.pixiv.net/artworks/8")],
+        )
+        .await;
+        mount_page(&second, 2, &[post(9, 1, "ddd", "")]).await;
+        mount_page(&second, 3, &[]).await;
+
+        let missing = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&missing)
+            .await;
+
+        let search = FederatedSearch::new(
+            registry(&[("first", &first), ("second", &second), ("missing", &missing)]),
+            Duration::from_secs(5),
+        );
+        let sources = ["first", "second", "missing"].map(String::from);
+        let page = search.search(&sources, &["sky order:score".to_string()], 6).await.unwrap();
+
+        // Best of each source first, alternating; the cross-post is listed once
+        let ids: Vec<_> = page.images.iter().map(|i| (i.image.source.as_str(), i.image.id.as_str())).collect();
+        assert_eq!(ids, vec![("first", "2"), ("second", "7"), ("first", "1")]);
+        assert_eq!(page.images[0].also_on, vec!["second".to_string()]);
+
+        assert_eq!(page.errors.len(), 1);
+        assert_eq!(page.errors[0].0, "missing");
+        assert!(matches!(page.errors[0].1, Error::NotFound(_)));
+
+        // Only the sources that had results are searched further
+        let page = search.next(&page.cursor.unwrap()).await.unwrap();
+        let ids: Vec<_> = page.images.iter().map(|i| i.image.id.as_str()).collect();
+        assert_eq!(ids, vec!["9"]);
+        assert!(page.errors.is_empty());
+        // Both sources ran out on the way
+        assert!(page.cursor.is_none());
+
+        assert!(matches!(search.next("not a cursor").await, Err(Error::InvalidInput(_))));
+
+        // Other orders keep each source's own
+        let page = search.search(&sources, &["sky".to_string()], 6).await.unwrap();
+        let ids: Vec<_> = page.images.iter().map(|i| (i.image.source.as_str(), i.image.id.as_str())).collect();
+        assert_eq!(ids, vec![("first", "1"), ("second", "7"), ("first", "2")]);
+    }
+
+    #[tokio::test]
+    async fn test_slow_source_times_out_and_is_retried() {
+        let fast = MockServer::start().await;
+        mount_page(&fast, 1, &[post(1, 1, "aaa", "")]).await;
+
+        let slow = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("[]").set_delay(Duration::from_secs(5)))
+            .mount(&slow)
+            .await;
+
+        let search = FederatedSearch::new(registry(&[("fast", &fast), ("slow", &slow)]), Duration::from_millis(300));
+        let page = search
+            .search(&["fast".to_string(), "slow".to_string()], &[], 10)
+            .await
+            .unwrap();
+
+        assert_eq!(page.images.len(), 1);
+        assert!(matches!(page.errors[0].1, Error::SourceUnavailable(_)));
+
+        let cursor = Cursor::decode(&page.cursor.unwrap()).unwrap();
+        assert_eq!(cursor.limit, 5);
+        assert_eq!(cursor.pages, vec![("fast".to_string(), 2), ("slow".to_string(), 1)]);
+        assert_eq!(cursor.failures, HashMap::from([("slow".to_string(), 1)]));
+
+        // The fast source ran out; the slow one is given up on after its last attempt
+        let page = search.next(&cursor.encode()).await.unwrap();
+        let cursor = Cursor::decode(&page.cursor.unwrap()).unwrap();
+        assert_eq!(cursor.pages, vec![("slow".to_string(), 1)]);
+        assert_eq!(cursor.failures, HashMap::from([("slow".to_string(), 2)]));
+
+        let page = search.next(&cursor.encode()).await.unwrap();
+        assert!(matches!(page.errors[0].1, Error::SourceUnavailable(_)));
+        assert!(page.cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cross_posts_on_later_pages_are_left_out() {
+        let first = MockServer::start().await;
+        mount_page(&first, 1, &[post(1, 1, "aaa", "https://www.pixiv.net/artworks/1")]).await;
+        mount_page(&first, 2, &[post(2, 1, "bbb", "https://www.pixiv.net/artworks/1")]).await;
+        mount_page(&first, 3, &[]).await;
+
+        let second = MockServer::start().await;
+        mount_page(&second, 1, &[post(7, 1, "ccc", "")]).await;
+        mount_page(&second, 2, &[post(8, 1, "AAA", ""), post(9, 1, "ddd", "")]).await;
+        mount_page(&second, 3, &[]).await;
+
+        let search = FederatedSearch::new(registry(&[("first", &first), ("second", &second)]), Duration::from_secs(5));
+        let sources = ["first", "second"].map(String::from);
+        let page = search.search(&sources, &[], 2).await.unwrap();
+        let ids: Vec<_> = page.images.iter().map(|i| i.image.id.as_str()).collect();
+        assert_eq!(ids, vec!["1", "7"]);
+
+        // Another page of the same pixiv work is kept
+        let page = search.next(&page.cursor.unwrap()).await.unwrap();
+        let ids: Vec<_> = page.images.iter().map(|i| i.image.id.as_str()).collect();
+        assert_eq!(ids, vec!["2", "9"]);
+    }
+
+    #[test]
+    fn test_cursors_remember_recent_images_only() {
+        let image = |n: u32| -> BooruImage {
+            serde_json::from_str(&format!(
+                r#"{{"id": "{n}", "source": "first", "file_url": "https://cdn.example/{n}.jpg",
+                    "width": 1920, "height": 1080, "tags": [], "rating": "safe", "is_nsfw": false,
+                    "md5": "{n:032x}", "origin_url": "https://www.pixiv.net/artworks/{n}"}}"#
+            ))
+            .unwrap()
+        };
+
+        let mut seen = Vec::new();
+        merge(vec![(0..300).map(image).collect()], None, &mut seen);
+        assert_eq!(seen.len(), MAX_SEEN);
+        // The newest images are the ones kept
+        assert_eq!(seen.last().unwrap().0, hash_key("url:pixiv.net/artworks/299"));
+
+        let cursor = Cursor {
+            tags: vec!["sky".to_string()],
+            limit: 20,
+            pages: vec![("first".to_string(), 16)],
+            failures: HashMap::new(),
+            seen,
+        };
+        assert!(cursor.encode().len() < 8 * 1024);
+    }
+
+    #[tokio::test]
+    async fn test_query_is_translated_and_filtered() {
+        let server = MockServer::start().await;
+        mount_page(&server, 2, &[]).await;
+        Mock::given(method("GET"))
+            .and(path("/posts.json"))
+            .and(query_param("page", "1"))
+            .and(query_param("tags", "sky rating:g"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                "[{}, {}]",
+                post(1, 5, "aaa", ""),
+                post(2, 9, "bbb", "").replace("1920", "1280").replace("1080", "720")
+            )))
+            .mount(&server)
+            .await;
+
+        let search = FederatedSearch::new(registry(&[("danbooru", &server)]), Duration::from_secs(5));
+        let sources = ["danbooru".to_string()];
+        // Anonymous danbooru searches take two terms, so the size is checked here
+        let tags = ["sky rating:safe res:hd".to_string()];
+        let page = search.search(&sources, &tags, 10).await.unwrap();
+
+        let ids: Vec<_> = page.images.iter().map(|i| i.image.id.as_str()).collect();
+        assert_eq!(ids, vec!["1"]);
+        assert!(page.errors.is_empty());
+
+        let tags = ["order:sideways".to_string()];
+        assert!(matches!(search.search(&sources, &tags, 10).await, Err(Error::InvalidInput(_))));
+    }
+}
@@ -1,4 +1,10 @@
-use wallmgr_core::types::BooruImage;
+use crate::query::{self, Query, RatioRange};
+use wallmgr_core::error::{Error, Result};
+use wallmgr_core::types::{BooruImage, Monitor, Rating};
+
+/// How far a wallpaper's ratio may be off a monitor's, relatively; the rest
+/// is cropped
+const MONITOR_RATIO_TOLERANCE: f64 = 0.1;
 
 /// Wallpaper search filters optimized for desktop backgrounds
 #[derive(Debug, Clone)]
@@ -82,6 +88,46 @@ impl WallpaperSearchFilter {
         }
     }
     
+    /// Create filter for wallpapers that fill `monitor` at its native
+    /// resolution, including portrait (rotated) monitors
+    pub fn for_monitor(monitor: &Monitor) -> Self {
+        Self::for_size(monitor.width, monitor.height)
+    }
+    
+    /// Create filter for one wallpaper spanning all `monitors` as laid out
+    pub fn for_span(monitors: &[Monitor]) -> Self {
+        let left = monitors.iter().map(|m| m.x).min().unwrap_or(0);
+        let top = monitors.iter().map(|m| m.y).min().unwrap_or(0);
+        let right = monitors.iter().map(|m| m.x + m.width as i32).max().unwrap_or(0);
+        let bottom = monitors.iter().map(|m| m.y + m.height as i32).max().unwrap_or(0);
+        Self::for_size((right - left).max(0) as u32, (bottom - top).max(0) as u32)
+    }
+    
+    /// Create filter for the monitor called `name` among `monitors`, or for
+    /// their combined span when `name` is `span`
+    pub fn for_monitor_named(monitors: &[Monitor], name: &str) -> Result<Self> {
+        if name == "span" && !monitors.is_empty() {
+            return Ok(Self::for_span(monitors));
+        }
+        monitors
+            .iter()
+            .find(|monitor| monitor.name == name)
+            .map(Self::for_monitor)
+            .ok_or_else(|| Error::NotFound(format!("No monitor named {}", name)))
+    }
+    
+    fn for_size(width: u32, height: u32) -> Self {
+        let ratio = width as f64 / height.max(1) as f64;
+        Self {
+            min_width: width,
+            min_height: height,
+            aspect_ratio_min: ratio * (1.0 - MONITOR_RATIO_TOLERANCE),
+            aspect_ratio_max: ratio * (1.0 + MONITOR_RATIO_TOLERANCE),
+            portrait_threshold: 0.0,
+            ..Self::default()
+        }
+    }
+    
     /// Check if an image passes this filter
     pub fn matches(&self, image: &BooruImage) -> bool {
         // Check dimensions
@@ -117,6 +163,34 @@ impl WallpaperSearchFilter {
             .collect()
     }
     
+    /// The filter as a query for `base_tags`, so sources that can search
+    /// by size and ratio do so themselves
+    pub fn to_query(&self, base_tags: &[String]) -> Query {
+        Query {
+            tags: base_tags.to_vec(),
+            ratings: match self.allow_nsfw {
+                true => Vec::new(),
+                false => vec![Rating::Safe],
+            },
+            min_width: Some(self.min_width),
+            min_height: Some(self.min_height),
+            ratio: Some(RatioRange {
+                min: self.aspect_ratio_min,
+                max: self.aspect_ratio_max,
+            }),
+            ..Query::default()
+        }
+    }
+    
+    /// Size and ratio as query terms, to add to a search's tags
+    pub fn query_terms(&self) -> Vec<String> {
+        vec![
+            format!("width:>={}", self.min_width),
+            format!("height:>={}", self.min_height),
+            format!("ratio:{}", query::range_value(self.aspect_ratio_min, self.aspect_ratio_max)),
+        ]
+    }
+    
     /// Get human-readable description of filter
     pub fn description(&self) -> String {
         format!(
@@ -160,6 +234,7 @@ pub fn build_landscape_query(base_tags: &[String], filter: &WallpaperSearchFilte
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
     use wallmgr_core::types::Rating;
     
     fn create_test_image(width: u32, height: u32, is_nsfw: bool) -> BooruImage {
@@ -176,6 +251,12 @@ mod tests {
             score: None,
             author: None,
             is_nsfw,
+            md5: None,
+            file_size: None,
+            page_url: None,
+            origin_url: None,
+            attribution: None,
+            tag_categories: HashMap::new(),
         }
     }
     
@@ -248,4 +329,40 @@ mod tests {
         let filtered = filter.filter(images);
         assert_eq!(filtered.len(), 2);
     }
+    
+    #[test]
+    fn test_monitor_filters() {
+        let monitor = |name: &str, x, y, width, height| Monitor {
+            name: name.to_string(),
+            width,
+            height,
+            x,
+            y,
+            primary: false,
+        };
+        let monitors = vec![
+            monitor("DP-1", 0, 0, 2560, 1440),
+            monitor("DP-2", 2560, 0, 2560, 1440),
+            monitor("HDMI-1", 5120, 0, 1080, 1920),
+        ];
+        
+        let filter = WallpaperSearchFilter::for_monitor_named(&monitors, "DP-1").unwrap();
+        assert!(filter.matches(&create_test_image(2560, 1440, false)));
+        assert!(!filter.matches(&create_test_image(1920, 1080, false)));
+        assert!(!filter.matches(&create_test_image(3440, 1440, false)));
+        
+        // A rotated monitor wants portrait wallpapers
+        let filter = WallpaperSearchFilter::for_monitor_named(&monitors, "HDMI-1").unwrap();
+        assert!(filter.matches(&create_test_image(2160, 3840, false)));
+        assert!(!filter.matches(&create_test_image(3840, 2160, false)));
+        
+        let filter = WallpaperSearchFilter::for_monitor_named(&monitors, "span").unwrap();
+        assert_eq!((filter.min_width, filter.min_height), (6200, 1920));
+        assert_eq!(filter.query_terms(), vec!["width:>=6200", "height:>=1920", "ratio:2.91..3.55"]);
+        
+        assert!(matches!(
+            WallpaperSearchFilter::for_monitor_named(&monitors, "eDP-1"),
+            Err(Error::NotFound(_))
+        ));
+    }
 }
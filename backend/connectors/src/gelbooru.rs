@@ -1,43 +1,130 @@
-use crate::booru::{BooruClient, BooruConnector, parse_rating};
+use crate::booru::{BooruClient, BooruConnector, SendWith, parse_rating, parse_error, request_error, origin_url};
+use crate::query::{self, Order, Query, Translation};
 use async_trait::async_trait;
 use serde::Deserialize;
 use wallmgr_core::error::{Error, Result};
-use wallmgr_core::types::BooruImage;
-
-const API_BASE: &str = "https://gelbooru.com";
+use wallmgr_core::types::{BooruImage, Rating};
+use std::collections::HashMap;
 
 #[derive(Debug, Deserialize)]
 struct GelbooruPost {
     id: i64,
     #[serde(default)]
     file_url: Option<String>,
+    /// File name and directory, for sites that don't send `file_url`
+    #[serde(default)]
+    image: Option<String>,
+    #[serde(default)]
+    directory: Option<serde_json::Value>,
+    #[serde(default)]
+    preview_url: Option<String>,
+    #[serde(default)]
+    sample_url: Option<String>,
     width: u32,
     height: u32,
     #[serde(default)]
     tags: String,
+    #[serde(default)]
     rating: String,
     #[serde(default)]
     score: Option<i32>,
     #[serde(default)]
     owner: Option<String>,
+    /// Free-form, often a link to the original
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default, alias = "hash")]
+    md5: Option<String>,
 }
 
+/// gelbooru.com wraps results in an object, most other instances don't
 #[derive(Debug, Deserialize)]
-struct GelbooruResponse {
-    #[serde(rename = "post", default)]
-    posts: Vec<GelbooruPost>,
+#[serde(untagged)]
+enum GelbooruResponse<T> {
+    Bare(Vec<T>),
+    Wrapped {
+        #[serde(alias = "post", alias = "tag", default = "Vec::new")]
+        items: Vec<T>,
+    },
+}
+
+impl<T> GelbooruResponse<T> {
+    fn into_items(self) -> Vec<T> {
+        match self {
+            Self::Wrapped { items } | Self::Bare(items) => items,
+        }
+    }
 }
 
+/// Gelbooru 0.2 sites: gelbooru.com, safebooru.org, rule34.xxx and any other
+/// instance given its base URL
 pub struct GelbooruConnector {
     client: BooruClient,
+    name: String,
+    base_url: String,
+    auth: Vec<(&'static str, String)>,
 }
 
 impl GelbooruConnector {
     pub fn new() -> Self {
+        Self::with_base_url("gelbooru", "https://gelbooru.com")
+    }
+
+    pub fn with_base_url(name: &str, base_url: &str) -> Self {
         Self {
             client: BooruClient::new(),
+            name: name.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            auth: Vec::new(),
         }
     }
+
+    /// Authenticate requests with a user ID and API key
+    pub fn with_credentials(mut self, user_id: &str, api_key: &str) -> Self {
+        self.auth = vec![("user_id", user_id.to_string()), ("api_key", api_key.to_string())];
+        self
+    }
+
+    fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        self.client.client().get(url).query(&self.auth)
+    }
+
+    fn post_to_image(&self, post: GelbooruPost) -> Option<BooruImage> {
+        let file_url = match (post.file_url, post.image) {
+            (Some(url), _) if !url.is_empty() => url,
+            (_, Some(image)) => {
+                let directory = match post.directory {
+                    Some(serde_json::Value::String(dir)) => dir,
+                    Some(serde_json::Value::Number(dir)) => dir.to_string(),
+                    _ => return None,
+                };
+                format!("{}/images/{}/{}", self.base_url, directory, image)
+            }
+            _ => return None,
+        };
+        let is_nsfw = !matches!(post.rating.as_str(), "" | "s" | "general" | "safe");
+
+        Some(BooruImage {
+            id: post.id.to_string(),
+            source: self.name.clone(),
+            file_url,
+            preview_url: post.preview_url.filter(|u| !u.is_empty()),
+            sample_url: post.sample_url.filter(|u| !u.is_empty()),
+            width: post.width,
+            height: post.height,
+            tags: post.tags.split_whitespace().map(|s| s.to_string()).collect(),
+            rating: parse_rating(&post.rating),
+            score: post.score,
+            author: post.owner,
+            is_nsfw,
+            md5: post.md5,
+            file_size: None,
+            page_url: None,
+            origin_url: post.source.as_deref().and_then(origin_url),
+            attribution: None,
+            tag_categories: HashMap::new(),
+        })
+    }
 }
 
 impl Default for GelbooruConnector {
@@ -49,141 +136,182 @@ impl Default for GelbooruConnector {
 #[async_trait]
 impl BooruConnector for GelbooruConnector {
     fn name(&self) -> &str {
-        "gelbooru"
+        &self.name
+    }
+
+    fn translate(&self, query: &Query) -> Translation {
+        Translation::new(query)
+            .tags()
+            .any_of(|group| Some(vec![format!("{{{}}}", group.join(" ~ "))]))
+            .excluded("-")
+            .rating(|ratings| {
+                query::rating_codes(ratings)?;
+                Some(query::rating_terms(ratings, "rating", |rating| match rating {
+                    Rating::Safe => "general",
+                    Rating::Questionable => "questionable",
+                    Rating::Explicit => "explicit",
+                }))
+            })
+            .size(|width, height| {
+                let mut terms = Vec::new();
+                terms.extend(width.map(|w| format!("width:>={}", w)));
+                terms.extend(height.map(|h| format!("height:>={}", h)));
+                Some(terms)
+            })
+            .order(|order| match order {
+                Order::Score => Some(vec!["sort:score:desc".to_string()]),
+                Order::Date => Some(Vec::new()),
+                Order::Random => Some(vec!["sort:random".to_string()]),
+                Order::Favorites => None,
+            })
     }
 
     async fn search(&self, tags: &[String], limit: u32, page: u32) -> Result<Vec<BooruImage>> {
         let tags_str = tags.join(" ");
         let url = format!(
             "{}/index.php?page=dapi&s=post&q=index&json=1&tags={}&limit={}&pid={}",
-            API_BASE,
+            self.base_url,
             urlencoding::encode(&tags_str),
             limit.min(100),
             page
         );
 
-        let response = self.client.client()
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| Error::Renderer(format!("Gelbooru API error: {}", e)))?;
+        let response = self.get(&url)
+            .fetch(&self.client, &self.name)
+            .await?;
 
-        if !response.status().is_success() {
-            return Err(Error::Renderer(format!(
-                "Gelbooru API returned status: {}",
-                response.status()
-            )));
+        // An empty result is sometimes an empty body rather than an empty list
+        let body = response
+            .text()
+            .await
+            .map_err(|e| request_error(&self.name, e))?;
+        if body.trim().is_empty() {
+            return Ok(Vec::new());
         }
 
-        let wrapper: GelbooruResponse = response
-            .json()
-            .await
-            .map_err(|e| Error::Renderer(format!("Failed to parse Gelbooru response: {}", e)))?;
+        let posts: GelbooruResponse<GelbooruPost> = serde_json::from_str(&body)
+            .map_err(|e| parse_error(&self.name, e))?;
 
-        let images = wrapper.posts
+        Ok(posts
+            .into_items()
             .into_iter()
-            .filter_map(|post| {
-                let file_url = post.file_url?;
-                let is_nsfw = post.rating != "general" && post.rating != "safe";
-
-                Some(BooruImage {
-                    id: post.id.to_string(),
-                    source: "gelbooru".to_string(),
-                    file_url,
-                    preview_url: None,
-                    sample_url: None,
-                    width: post.width,
-                    height: post.height,
-                    tags: post.tags.split_whitespace().map(|s| s.to_string()).collect(),
-                    rating: parse_rating(&post.rating),
-                    score: post.score,
-                    author: post.owner,
-                    is_nsfw,
-                })
-            })
-            .collect();
-
-        Ok(images)
+            .filter_map(|post| self.post_to_image(post))
+            .collect())
     }
 
     async fn autocomplete_tags(&self, prefix: &str, limit: u32) -> Result<Vec<String>> {
         let url = format!(
             "{}/index.php?page=dapi&s=tag&q=index&json=1&name_pattern={}%&limit={}",
-            API_BASE,
+            self.base_url,
             urlencoding::encode(prefix),
             limit.min(20)
         );
 
-        let response = self.client.client()
-            .get(&url)
-            .send()
+        let response = self.get(&url)
+            .send_with(&self.client)
             .await
-            .map_err(|e| Error::Renderer(format!("Gelbooru autocomplete error: {}", e)))?;
+            .map_err(|e| request_error(&self.name, e))?;
 
         if !response.status().is_success() {
             return Ok(Vec::new());
         }
 
-        #[derive(Deserialize)]
-        struct TagWrapper {
-            tag: Vec<Tag>,
-        }
-
         #[derive(Deserialize)]
         struct Tag {
             name: String,
         }
 
-        let wrapper: TagWrapper = response
-            .json()
+        let tags = response
+            .json::<GelbooruResponse<Tag>>()
             .await
-            .unwrap_or(TagWrapper { tag: Vec::new() });
+            .map(GelbooruResponse::into_items)
+            .unwrap_or_default();
 
-        Ok(wrapper.tag.into_iter().map(|t| t.name).collect())
+        Ok(tags.into_iter().map(|t| t.name).collect())
     }
 
     async fn get_image(&self, id: &str) -> Result<BooruImage> {
         let url = format!(
             "{}/index.php?page=dapi&s=post&q=index&json=1&id={}",
-            API_BASE, id
+            self.base_url, id
         );
 
-        let response = self.client.client()
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| Error::Renderer(format!("Gelbooru API error: {}", e)))?;
+        let response = self.get(&url)
+            .fetch(&self.client, &self.name)
+            .await?;
 
-        if !response.status().is_success() {
-            return Err(Error::NotFound(format!("Post {} not found", id)));
-        }
-
-        let wrapper: GelbooruResponse = response
+        let posts: GelbooruResponse<GelbooruPost> = response
             .json()
             .await
-            .map_err(|e| Error::Renderer(format!("Failed to parse Gelbooru response: {}", e)))?;
+            .map_err(|e| parse_error(&self.name, e))?;
 
-        let post = wrapper.posts.into_iter().next()
+        let post = posts.into_items().into_iter().next()
             .ok_or_else(|| Error::NotFound(format!("Post {} not found", id)))?;
 
-        let file_url = post.file_url
-            .ok_or_else(|| Error::NotFound("No file URL found".to_string()))?;
-        
-        let is_nsfw = post.rating != "general" && post.rating != "safe";
+        self.post_to_image(post)
+            .ok_or_else(|| Error::NotFound("No file URL found".to_string()))
+    }
 
-        Ok(BooruImage {
-            id: post.id.to_string(),
-            source: "gelbooru".to_string(),
-            file_url,
-            preview_url: None,
-            sample_url: None,
-            width: post.width,
-            height: post.height,
-            tags: post.tags.split_whitespace().map(|s| s.to_string()).collect(),
-            rating: parse_rating(&post.rating),
-            score: post.score,
-            author: post.owner,
-            is_nsfw,
-        })
+    async fn favorites(&self, limit: u32, page: u32) -> Result<Vec<BooruImage>> {
+        let user_id = self
+            .auth
+            .iter()
+            .find(|(key, _)| *key == "user_id")
+            .map(|(_, id)| id.clone())
+            .ok_or_else(|| Error::Config(format!("{} favorites need a user ID", self.name)))?;
+        self.search(&[format!("fav:{}", user_id)], limit, page).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_rule34_style_instance() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/index.php"))
+            .and(query_param("tags", "scenery"))
+            .and(query_param("pid", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"[{"preview_url": "https://api.example.org/thumbnails/1234/thumbnail_f00.jpg",
+                     "sample_url": "", "file_url": "https://api.example.org/images/1234/f00.jpeg",
+                     "directory": 1234, "hash": "f00", "width": 2400, "height": 1350,
+                     "id": 9876543, "image": "f00.jpeg", "change": 1700000000,
+                     "owner": "someone", "parent_id": 0, "rating": "explicit", "sample": false,
+                     "score": 15, "tags": "scenery sunset", "source": "", "status": "active",
+                     "has_notes": false, "comment_count": 0}]"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let connector = GelbooruConnector::with_base_url("rule34", &server.uri());
+        let images = connector.search(&["scenery".to_string()], 42, 2).await.unwrap();
+
+        assert_eq!(images.len(), 1);
+        let image = &images[0];
+        assert_eq!(image.file_url, "https://api.example.org/images/1234/f00.jpeg");
+        assert_eq!(
+            image.preview_url.as_deref(),
+            Some("https://api.example.org/thumbnails/1234/thumbnail_f00.jpg")
+        );
+        assert_eq!(image.sample_url, None);
+        assert_eq!(image.author.as_deref(), Some("someone"));
+        assert!(matches!(image.rating, wallmgr_core::types::Rating::Explicit));
+        assert!(image.is_nsfw);
+    }
+
+    #[test]
+    fn test_translate() {
+        let connector = GelbooruConnector::new();
+        let query = Query::parse("sky a|b rating:q ratio:16:9 order:score").unwrap();
+        let translation = connector.translate(&query);
+
+        assert_eq!(translation.tags, vec!["sky", "{a ~ b}", "rating:questionable", "sort:score:desc"]);
+        // Gelbooru has no ratio search
+        assert!(translation.residual.ratio.is_some());
     }
 }
@@ -1,11 +1,37 @@
 pub mod booru;
+pub mod query;
+pub mod transport;
 pub mod danbooru;
-pub mod yandere;
-pub mod safebooru;
+pub mod moebooru;
 pub mod gelbooru;
-pub mod konachan;
 pub mod wallhaven;
+pub mod zerochan;
+pub mod anime_pictures;
+pub mod e621;
+pub mod sankaku;
+pub mod szurubooru;
+pub mod shimmie;
+pub mod unsplash;
+pub mod pexels;
+pub mod reddit;
+pub mod bing;
+pub mod apod;
+pub mod pixiv;
+pub mod registry;
 pub mod filter;
+pub mod download;
+pub mod queue;
+pub mod daily;
+pub mod favorites;
+pub mod federated;
 
-pub use booru::{BooruClient, BooruConnector};
+pub use booru::{BooruClient, BooruConnector, QueryPage, SendWith};
+pub use query::{Query, Translation};
+pub use transport::HttpPolicy;
+pub use registry::ConnectorRegistry;
 pub use filter::WallpaperSearchFilter;
+pub use download::{DownloadProgress, DownloadedFile, Downloader};
+pub use queue::{DownloadQueue, Importer, QueueEvent};
+pub use daily::DailyWallpaper;
+pub use favorites::FavoritesSync;
+pub use federated::{FederatedImage, FederatedPage, FederatedSearch};
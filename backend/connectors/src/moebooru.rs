@@ -0,0 +1,313 @@
+use crate::booru::{BooruClient, BooruConnector, SendWith, parse_rating, parse_error, request_error, origin_url};
+use crate::query::{self, Order, Query, Translation};
+use async_trait::async_trait;
+use serde::Deserialize;
+use wallmgr_core::error::{Error, Result};
+use wallmgr_core::types::BooruImage;
+use std::collections::HashMap;
+
+/// Moebooru sites: yande.re, konachan and self-hosted instances
+/// API: https://yande.re/help/api
+pub struct MoebooruConnector {
+    client: BooruClient,
+    name: String,
+    base_url: String,
+    auth: Vec<(&'static str, String)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MoebooruPost {
+    id: i64,
+    #[serde(default)]
+    file_url: Option<String>,
+    #[serde(default)]
+    sample_url: Option<String>,
+    #[serde(default)]
+    preview_url: Option<String>,
+    width: u32,
+    height: u32,
+    #[serde(default)]
+    tags: String,
+    rating: String,
+    #[serde(default)]
+    score: Option<i32>,
+    #[serde(default)]
+    author: Option<String>,
+    #[serde(default)]
+    md5: Option<String>,
+    #[serde(default)]
+    file_size: Option<u64>,
+    /// Free-form, often a link to the original
+    #[serde(default)]
+    source: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct MoebooruTag {
+    name: String,
+    count: i64,
+}
+
+impl MoebooruConnector {
+    pub fn new(name: &str, base_url: &str) -> Self {
+        Self {
+            client: BooruClient::new(),
+            name: name.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            auth: Vec::new(),
+        }
+    }
+
+    /// yande.re
+    pub fn yandere() -> Self {
+        Self::new("yandere", "https://yande.re")
+    }
+
+    /// konachan.net, the safe-for-work Konachan mirror
+    pub fn konachan() -> Self {
+        Self::new("konachan", "https://konachan.net")
+    }
+
+    /// Authenticate requests with a login and password hash
+    pub fn with_credentials(mut self, login: &str, password_hash: &str) -> Self {
+        self.auth = vec![
+            ("login", login.to_string()),
+            ("password_hash", password_hash.to_string()),
+        ];
+        self
+    }
+
+    fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        self.client.client().get(url).query(&self.auth)
+    }
+
+    fn post_to_image(&self, post: MoebooruPost) -> Option<BooruImage> {
+        let file_url = post.file_url?;
+        let is_nsfw = post.rating != "s";
+
+        Some(BooruImage {
+            id: post.id.to_string(),
+            source: self.name.clone(),
+            file_url,
+            preview_url: post.preview_url,
+            sample_url: post.sample_url,
+            width: post.width,
+            height: post.height,
+            tags: post.tags.split_whitespace().map(|s| s.to_string()).collect(),
+            rating: parse_rating(&post.rating),
+            score: post.score,
+            author: post.author,
+            is_nsfw,
+            md5: post.md5,
+            file_size: post.file_size,
+            page_url: None,
+            origin_url: post.source.as_deref().and_then(origin_url),
+            attribution: None,
+            tag_categories: HashMap::new(),
+        })
+    }
+}
+
+#[async_trait]
+impl BooruConnector for MoebooruConnector {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn translate(&self, query: &Query) -> Translation {
+        Translation::new(query)
+            .limit(6)
+            .tags()
+            .excluded("-")
+            .rating(|ratings| {
+                query::rating_codes(ratings)?;
+                Some(query::rating_terms(ratings, "rating", |r| &r.as_str()[..1]))
+            })
+            .size(|width, height| {
+                let mut terms = Vec::new();
+                terms.extend(width.map(|w| format!("width:>={}", w)));
+                terms.extend(height.map(|h| format!("height:>={}", h)));
+                Some(terms)
+            })
+            .order(|order| match order {
+                Order::Score => Some(vec!["order:score".to_string()]),
+                Order::Date => Some(Vec::new()),
+                Order::Random => Some(vec!["order:random".to_string()]),
+                Order::Favorites => None,
+            })
+    }
+
+    async fn search(&self, tags: &[String], limit: u32, page: u32) -> Result<Vec<BooruImage>> {
+        let tags_str = tags.join(" ");
+        let url = format!(
+            "{}/post.json?tags={}&limit={}&page={}",
+            self.base_url,
+            urlencoding::encode(&tags_str),
+            limit.min(100),
+            page
+        );
+
+        let response = self.get(&url)
+            .fetch(&self.client, &self.name)
+            .await?;
+
+        let posts: Vec<MoebooruPost> = response
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))?;
+
+        Ok(posts.into_iter().filter_map(|p| self.post_to_image(p)).collect())
+    }
+
+    async fn autocomplete_tags(&self, prefix: &str, limit: u32) -> Result<Vec<String>> {
+        let url = format!(
+            "{}/tag.json?name={}*&limit={}&order=count",
+            self.base_url,
+            urlencoding::encode(prefix),
+            limit.min(20)
+        );
+
+        let response = self.get(&url)
+            .send_with(&self.client)
+            .await
+            .map_err(|e| request_error(&self.name, e))?;
+
+        if !response.status().is_success() {
+            return Ok(Vec::new());
+        }
+
+        let tags: Vec<MoebooruTag> = response
+            .json()
+            .await
+            .unwrap_or_default();
+
+        Ok(tags.into_iter().map(|t| t.name).collect())
+    }
+
+    async fn get_image(&self, id: &str) -> Result<BooruImage> {
+        let url = format!("{}/post.json?tags=id:{}", self.base_url, id);
+
+        let response = self.get(&url)
+            .fetch(&self.client, &self.name)
+            .await?;
+
+        let mut posts: Vec<MoebooruPost> = response
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))?;
+
+        let post = posts.pop()
+            .ok_or_else(|| Error::NotFound(format!("Post {} not found", id)))?;
+
+        self.post_to_image(post)
+            .ok_or_else(|| Error::NotFound("No file URL found".to_string()))
+    }
+
+    async fn favorites(&self, limit: u32, page: u32) -> Result<Vec<BooruImage>> {
+        let login = self
+            .auth
+            .iter()
+            .find(|(key, _)| *key == "login")
+            .map(|(_, login)| login.clone())
+            .ok_or_else(|| Error::Config(format!("{} favorites need a login", self.name)))?;
+        // A favorite is a vote of 3
+        self.search(&[format!("vote:3:{}", login), "order:vote".to_string()], limit, page)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wallmgr_core::types::Rating;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_self_hosted_instance() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/post.json"))
+            .and(query_param("tags", "sky"))
+            .and(query_param("login", "alice"))
+            .and(query_param("password_hash", "hash"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"[
+                    {"id": 7, "file_url": "http://img/7.png", "width": 1920, "height": 1080,
+                     "tags": "sky cloud", "rating": "s", "score": 3, "md5": "abc"},
+                    {"id": 8, "width": 10, "height": 10, "tags": "", "rating": "e"}
+                ]"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let connector = MoebooruConnector::new("home", &format!("{}/", server.uri()))
+            .with_credentials("alice", "hash");
+        let images = connector.search(&["sky".to_string()], 10, 1).await.unwrap();
+
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].source, "home");
+        assert_eq!(images[0].tags, vec!["sky", "cloud"]);
+        assert_eq!(images[0].md5.as_deref(), Some("abc"));
+    }
+
+    #[tokio::test]
+    async fn test_konachan_search() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/post.json"))
+            .and(query_param("tags", "landscape rating:safe"))
+            .and(query_param("limit", "10"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"[
+                    {"id": 381504, "file_url": "https://konachan.net/image/a/Konachan.com%20-%20381504.jpg",
+                     "sample_url": "https://konachan.net/sample/a/sample.jpg", "width": 2560, "height": 1440,
+                     "tags": "clouds landscape scenic sky", "rating": "s", "score": 42, "author": "kanade",
+                     "file_size": 2000000}
+                ]"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let connector = MoebooruConnector::new("konachan", &server.uri());
+        let images = connector
+            .search(&["landscape".to_string(), "rating:safe".to_string()], 10, 1)
+            .await
+            .unwrap();
+
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].id, "381504");
+        assert_eq!(images[0].source, "konachan");
+        assert_eq!((images[0].width, images[0].height), (2560, 1440));
+        assert_eq!(images[0].rating, Rating::Safe);
+        assert!(!images[0].is_nsfw);
+        assert_eq!(images[0].score, Some(42));
+        assert_eq!(images[0].sample_url.as_deref(), Some("https://konachan.net/sample/a/sample.jpg"));
+    }
+
+    #[tokio::test]
+    async fn test_konachan_autocomplete() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/tag.json"))
+            .and(query_param("name", "lands*"))
+            .and(query_param("limit", "5"))
+            .and(query_param("order", "count"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"[{"id": 1, "name": "landscape", "count": 20000, "type": 0},
+                    {"id": 2, "name": "landscape_(nature)", "count": 12, "type": 0}]"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let connector = MoebooruConnector::new("konachan", &server.uri());
+        let tags = connector.autocomplete_tags("lands", 5).await.unwrap();
+        assert_eq!(tags, vec!["landscape", "landscape_(nature)"]);
+
+        // A failing lookup gives no suggestions rather than an error
+        let connector = MoebooruConnector::new("konachan", &format!("{}/missing", server.uri()));
+        assert!(connector.autocomplete_tags("lands", 5).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+use crate::booru::{BooruClient, BooruConnector, SendWith, parse_error};
+use async_trait::async_trait;
+use serde::Deserialize;
+use wallmgr_core::error::Result;
+use wallmgr_core::types::{BooruImage, Rating};
+use std::collections::HashMap;
+
+/// Pexels photos; requires an API key
+/// API: https://www.pexels.com/api/documentation
+pub struct PexelsConnector {
+    client: BooruClient,
+    name: String,
+    base_url: String,
+    api_key: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PexelsPhoto {
+    id: u64,
+    width: u32,
+    height: u32,
+    /// Page on pexels.com
+    url: String,
+    photographer: String,
+    src: PexelsSources,
+}
+
+#[derive(Debug, Deserialize)]
+struct PexelsSources {
+    original: String,
+    #[serde(default)]
+    large2x: Option<String>,
+    #[serde(default)]
+    medium: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PexelsPage {
+    photos: Vec<PexelsPhoto>,
+}
+
+impl PexelsConnector {
+    pub fn new(api_key: &str) -> Self {
+        Self::with_base_url("pexels", "https://api.pexels.com", api_key)
+    }
+
+    pub fn with_base_url(name: &str, base_url: &str, api_key: &str) -> Self {
+        Self {
+            client: BooruClient::new(),
+            name: name.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+        }
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T> {
+        let response = self.client.client()
+            .get(url)
+            .header(reqwest::header::AUTHORIZATION, &self.api_key)
+            .fetch(&self.client, &self.name)
+            .await?;
+
+        response
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))
+    }
+
+    fn photo_to_image(&self, photo: PexelsPhoto) -> BooruImage {
+        BooruImage {
+            id: photo.id.to_string(),
+            source: self.name.clone(),
+            file_url: photo.src.original,
+            preview_url: photo.src.medium,
+            sample_url: photo.src.large2x,
+            width: photo.width,
+            height: photo.height,
+            tags: Vec::new(),
+            rating: Rating::Safe,
+            score: None,
+            attribution: Some(format!("Photo by {} on Pexels", photo.photographer)),
+            tag_categories: HashMap::new(),
+            author: Some(photo.photographer),
+            is_nsfw: false,
+            md5: None,
+            file_size: None,
+            page_url: Some(photo.url),
+            origin_url: None,
+        }
+    }
+}
+
+#[async_trait]
+impl BooruConnector for PexelsConnector {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn search(&self, tags: &[String], limit: u32, page: u32) -> Result<Vec<BooruImage>> {
+        let per_page = limit.min(80);
+        let page = page.max(1);
+
+        // Without a query, list the curated feed
+        let url = if tags.is_empty() {
+            format!("{}/v1/curated?page={}&per_page={}", self.base_url, page, per_page)
+        } else {
+            format!(
+                "{}/v1/search?query={}&page={}&per_page={}&orientation=landscape",
+                self.base_url,
+                urlencoding::encode(&tags.join(" ").replace('_', " ")),
+                page,
+                per_page
+            )
+        };
+
+        let page: PexelsPage = self.get(&url).await?;
+        Ok(page.photos.into_iter().map(|p| self.photo_to_image(p)).collect())
+    }
+
+    async fn autocomplete_tags(&self, _prefix: &str, _limit: u32) -> Result<Vec<String>> {
+        // Pexels photos aren't tagged
+        Ok(Vec::new())
+    }
+
+    async fn get_image(&self, id: &str) -> Result<BooruImage> {
+        let photo: PexelsPhoto = self
+            .get(&format!("{}/v1/photos/{}", self.base_url, urlencoding::encode(id)))
+            .await?;
+        Ok(self.photo_to_image(photo))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const PHOTO: &str = r##"{
+        "id": 2014422, "width": 3024, "height": 2016,
+        "url": "https://www.pexels.com/photo/brown-rocks-during-golden-hour-2014422/",
+        "photographer": "Joey Farina", "photographer_url": "https://www.pexels.com/@joey",
+        "photographer_id": 680589, "avg_color": "#978E82", "liked": false,
+        "src": {"original": "https://images.pexels.com/photos/2014422/pexels-photo-2014422.jpeg",
+                "large2x": "https://images.pexels.com/photos/2014422/pexels-photo-2014422.jpeg?w=1880",
+                "large": "https://images.pexels.com/photos/2014422/pexels-photo-2014422.jpeg?h=650",
+                "medium": "https://images.pexels.com/photos/2014422/pexels-photo-2014422.jpeg?h=350",
+                "small": "https://images.pexels.com/photos/2014422/pexels-photo-2014422.jpeg?h=130"},
+        "alt": "Brown Rocks During Golden Hour"
+    }"##;
+
+    #[tokio::test]
+    async fn test_search_curated_and_get_image() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/search"))
+            .and(query_param("query", "golden hour"))
+            .and(query_param("page", "3"))
+            .and(header("authorization", "key"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                r#"{{"page": 3, "per_page": 15, "photos": [{}], "total_results": 1}}"#,
+                PHOTO
+            )))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/curated"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                r#"{{"page": 1, "per_page": 15, "photos": [{}, {}]}}"#,
+                PHOTO, PHOTO
+            )))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/photos/2014422"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(PHOTO))
+            .mount(&server)
+            .await;
+
+        let connector = PexelsConnector::with_base_url("pexels", &server.uri(), "key");
+        let images = connector.search(&["golden_hour".to_string()], 15, 3).await.unwrap();
+
+        assert_eq!(images.len(), 1);
+        let image = &images[0];
+        assert_eq!(image.id, "2014422");
+        assert_eq!(image.file_url, "https://images.pexels.com/photos/2014422/pexels-photo-2014422.jpeg");
+        assert_eq!(image.author.as_deref(), Some("Joey Farina"));
+        assert_eq!(image.attribution.as_deref(), Some("Photo by Joey Farina on Pexels"));
+        assert!(image.page_url.as_deref().unwrap().starts_with("https://www.pexels.com/photo/"));
+
+        assert_eq!(connector.search(&[], 15, 1).await.unwrap().len(), 2);
+        assert_eq!(connector.get_image("2014422").await.unwrap().height, 2016);
+    }
+}
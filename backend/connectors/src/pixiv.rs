@@ -0,0 +1,636 @@
+use crate::booru::{BooruClient, BooruConnector, SendWith, parse_error, request_error, status_error};
+use async_trait::async_trait;
+use md5::{Digest, Md5};
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+use wallmgr_core::error::{Error, Result};
+use wallmgr_core::types::{BooruImage, Rating};
+
+// Credentials of the official Android app, which the app API requires
+const CLIENT_ID: &str = "MOBrBDS8blbauoSck0ZfDbtuzpyT";
+const CLIENT_SECRET: &str = "lsACyCD94FhDUtGTXi3QzcFE2uU1hqtDaKeqrdwj";
+const HASH_SECRET: &str = "28c1fdd170a5204386cb1313c7077b34f83e4aaf4aa829ce78c231e05b0bae2c";
+const APP_USER_AGENT: &str = "PixivAndroidApp/5.0.234 (Android 11; Pixel 5)";
+
+/// Results per page of every listing endpoint
+const PAGE_SIZE: u32 = 30;
+
+/// Refresh this long before the access token actually expires
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// Pixiv illustrations through the app API; requires an OAuth refresh token
+///
+/// Besides tag searches, two meta tags list other feeds:
+/// - `ranking:<mode>`: a ranking, e.g. `ranking:day`, `ranking:week`, `ranking:month`
+/// - `bookmarks` / `bookmarks:private`: the account's bookmarked illustrations
+///
+/// Works with several pages yield one image per page, with ids `<id>_p<n>`.
+/// Animated works (ugoira) are skipped. Image hosts check the Referer, which
+/// the downloader takes from `page_url`.
+pub struct PixivConnector {
+    client: BooruClient,
+    name: String,
+    base_url: String,
+    auth_url: String,
+    session: Mutex<Session>,
+}
+
+struct Session {
+    refresh_token: String,
+    access: Option<AccessToken>,
+}
+
+#[derive(Clone)]
+struct AccessToken {
+    token: String,
+    user_id: String,
+    expires_at: Instant,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: String,
+    expires_in: u64,
+    user: TokenUser,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenUser {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IllustPage {
+    illusts: Vec<Illust>,
+    #[serde(default)]
+    next_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IllustDetail {
+    illust: Illust,
+}
+
+#[derive(Debug, Deserialize)]
+struct Illust {
+    id: u64,
+    #[serde(rename = "type")]
+    kind: String,
+    image_urls: ImageUrls,
+    user: PixivUser,
+    #[serde(default)]
+    tags: Vec<PixivTag>,
+    width: u32,
+    height: u32,
+    #[serde(default)]
+    sanity_level: u32,
+    /// 1 = R-18, 2 = R-18G
+    #[serde(default)]
+    x_restrict: u32,
+    #[serde(default)]
+    total_bookmarks: Option<i32>,
+    meta_single_page: SinglePage,
+    #[serde(default)]
+    meta_pages: Vec<MetaPage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ImageUrls {
+    #[serde(default)]
+    square_medium: Option<String>,
+    #[serde(default)]
+    medium: Option<String>,
+    #[serde(default)]
+    large: Option<String>,
+    #[serde(default)]
+    original: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SinglePage {
+    #[serde(default)]
+    original_image_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetaPage {
+    image_urls: ImageUrls,
+}
+
+#[derive(Debug, Deserialize)]
+struct PixivUser {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PixivTag {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AutocompleteResponse {
+    tags: Vec<PixivTag>,
+}
+
+impl PixivConnector {
+    pub fn new(refresh_token: &str) -> Self {
+        Self::with_base_url("pixiv", "https://app-api.pixiv.net", refresh_token)
+    }
+
+    pub fn with_base_url(name: &str, base_url: &str, refresh_token: &str) -> Self {
+        Self {
+            client: BooruClient::new(),
+            name: name.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            auth_url: "https://oauth.secure.pixiv.net".to_string(),
+            session: Mutex::new(Session {
+                refresh_token: refresh_token.to_string(),
+                access: None,
+            }),
+        }
+    }
+
+    /// OAuth server to exchange the refresh token with
+    pub fn with_auth_url(mut self, auth_url: &str) -> Self {
+        self.auth_url = auth_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// A valid access token, refreshing it when it is missing or about to expire
+    async fn access_token(&self) -> Result<AccessToken> {
+        let mut session = self.session.lock().await;
+        if let Some(access) = &session.access {
+            if access.expires_at > Instant::now() + EXPIRY_MARGIN {
+                return Ok(access.clone());
+            }
+        }
+
+        let client_time = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S+00:00").to_string();
+        let client_hash = format!("{:x}", Md5::digest(format!("{}{}", client_time, HASH_SECRET)));
+
+        let response = self.client.client()
+            .post(format!("{}/auth/token", self.auth_url))
+            .header(reqwest::header::USER_AGENT, APP_USER_AGENT)
+            .header("X-Client-Time", client_time)
+            .header("X-Client-Hash", client_hash)
+            .form(&[
+                ("client_id", CLIENT_ID),
+                ("client_secret", CLIENT_SECRET),
+                ("grant_type", "refresh_token"),
+                ("include_policy", "true"),
+                ("refresh_token", session.refresh_token.as_str()),
+            ])
+            .send_with(&self.client)
+            .await
+            .map_err(|e| request_error(&self.name, e))?;
+
+        // A revoked or mistyped refresh token is a 400
+        let status = response.status();
+        if status == reqwest::StatusCode::BAD_REQUEST || status == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(Error::AuthFailed(format!("{} refused the refresh token", self.name)));
+        }
+        if !status.is_success() {
+            return Err(status_error(&self.name, status));
+        }
+
+        let token: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))?;
+
+        let access = AccessToken {
+            token: token.access_token,
+            user_id: token.user.id,
+            expires_at: Instant::now() + Duration::from_secs(token.expires_in),
+        };
+        session.refresh_token = token.refresh_token;
+        session.access = Some(access.clone());
+        Ok(access)
+    }
+
+    /// Drop the access token so the next request refreshes it
+    async fn invalidate(&self) {
+        self.session.lock().await.access = None;
+    }
+
+    /// GET an API URL, refreshing the access token once if the API rejects it
+    async fn get<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T> {
+        let mut retried = false;
+        loop {
+            let access = self.access_token().await?;
+            let response = self.client.client()
+                .get(url)
+                .header(reqwest::header::USER_AGENT, APP_USER_AGENT)
+                .header("App-OS", "android")
+                .header("Accept-Language", "en-us")
+                .bearer_auth(&access.token)
+                .send_with(&self.client)
+                .await
+                .map_err(|e| request_error(&self.name, e))?;
+
+            let status = response.status();
+            if status.is_success() {
+                return response
+                    .json()
+                    .await
+                    .map_err(|e| parse_error(&self.name, e));
+            }
+
+            // An expired token is reported as a 400 mentioning OAuth
+            let body = response.text().await.unwrap_or_default();
+            let token_rejected = status == reqwest::StatusCode::UNAUTHORIZED
+                || (status == reqwest::StatusCode::BAD_REQUEST && body.contains("OAuth"));
+            if token_rejected && !retried {
+                retried = true;
+                self.invalidate().await;
+                continue;
+            }
+
+            if token_rejected {
+                return Err(Error::AuthFailed(format!("{} rejected the access token", self.name)));
+            }
+            return Err(status_error(&self.name, status));
+        }
+    }
+
+    /// Illustrations of a ranking, e.g. "day", "week", "month", "day_male"
+    pub async fn ranking(&self, mode: &str, page: u32) -> Result<Vec<BooruImage>> {
+        let url = format!(
+            "{}/v1/illust/ranking?mode={}&filter=for_android&offset={}",
+            self.base_url,
+            urlencoding::encode(mode),
+            offset(page)
+        );
+        let page: IllustPage = self.get(&url).await?;
+        Ok(self.to_images(page.illusts))
+    }
+
+    /// The account's public or private bookmarks, newest first
+    pub async fn bookmarks(&self, private: bool, page: u32) -> Result<Vec<BooruImage>> {
+        let user_id = self.access_token().await?.user_id;
+        let mut url = format!(
+            "{}/v1/user/bookmarks/illust?user_id={}&restrict={}&filter=for_android",
+            self.base_url,
+            urlencoding::encode(&user_id),
+            if private { "private" } else { "public" }
+        );
+
+        // Bookmarks page with a cursor, so follow next_url to the wanted page
+        for _ in 1..page.max(1) {
+            let listing: IllustPage = self.get(&url).await?;
+            match listing.next_url {
+                Some(next) => url = next,
+                None => return Ok(Vec::new()),
+            }
+        }
+        let listing: IllustPage = self.get(&url).await?;
+        Ok(self.to_images(listing.illusts))
+    }
+
+    fn to_images(&self, illusts: Vec<Illust>) -> Vec<BooruImage> {
+        illusts.into_iter().flat_map(|i| self.illust_to_images(i)).collect()
+    }
+
+    /// One image per page of the work
+    fn illust_to_images(&self, illust: Illust) -> Vec<BooruImage> {
+        if illust.kind == "ugoira" {
+            return Vec::new();
+        }
+
+        let rating = match (illust.x_restrict, illust.sanity_level) {
+            (0, level) if level < 6 => Rating::Safe,
+            (0, _) => Rating::Questionable,
+            _ => Rating::Explicit,
+        };
+        let tags: Vec<String> = illust
+            .tags
+            .iter()
+            .map(|t| t.name.replace(' ', "_"))
+            .collect();
+        let page_url = format!("https://www.pixiv.net/artworks/{}", illust.id);
+
+        let image = |id: String, file_url: String, urls: &ImageUrls, width: u32, height: u32| BooruImage {
+            id,
+            source: self.name.clone(),
+            file_url,
+            preview_url: urls.square_medium.clone().or_else(|| urls.medium.clone()),
+            sample_url: urls.large.clone(),
+            width,
+            height,
+            tags: tags.clone(),
+            rating,
+            score: illust.total_bookmarks,
+            author: Some(illust.user.name.clone()),
+            is_nsfw: !matches!(rating, Rating::Safe),
+            md5: None,
+            file_size: None,
+            page_url: Some(page_url.clone()),
+            origin_url: None,
+            attribution: Some(format!("{} on pixiv", illust.user.name)),
+            tag_categories: HashMap::new(),
+        };
+
+        if illust.meta_pages.is_empty() {
+            let file_url = illust
+                .meta_single_page
+                .original_image_url
+                .clone()
+                .or_else(|| illust.image_urls.large.clone());
+            return file_url
+                .map(|url| image(illust.id.to_string(), url, &illust.image_urls, illust.width, illust.height))
+                .into_iter()
+                .collect();
+        }
+
+        // Only the first page's size is known
+        illust
+            .meta_pages
+            .iter()
+            .enumerate()
+            .filter_map(|(n, page)| {
+                let url = page.image_urls.original.clone().or_else(|| page.image_urls.large.clone())?;
+                let (width, height) = if n == 0 { (illust.width, illust.height) } else { (0, 0) };
+                Some(image(format!("{}_p{}", illust.id, n), url, &page.image_urls, width, height))
+            })
+            .collect()
+    }
+}
+
+fn offset(page: u32) -> u32 {
+    page.saturating_sub(1) * PAGE_SIZE
+}
+
+#[async_trait]
+impl BooruConnector for PixivConnector {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Pixiv pages are a fixed 30 works, so `limit` only caps the result
+    async fn search(&self, tags: &[String], limit: u32, page: u32) -> Result<Vec<BooruImage>> {
+        let mut images = match tags {
+            [tag] if tag.starts_with("ranking:") => self.ranking(&tag["ranking:".len()..], page).await?,
+            [tag] if tag == "bookmarks" => self.bookmarks(false, page).await?,
+            [tag] if tag == "bookmarks:private" => self.bookmarks(true, page).await?,
+            [] => self.ranking("day", page).await?,
+            _ => {
+                let word = tags.iter().map(|t| t.replace('_', " ")).collect::<Vec<_>>().join(" ");
+                let url = format!(
+                    "{}/v1/search/illust?word={}&search_target=partial_match_for_tags&sort=date_desc&filter=for_android&offset={}",
+                    self.base_url,
+                    urlencoding::encode(&word),
+                    offset(page)
+                );
+                let listing: IllustPage = self.get(&url).await?;
+                self.to_images(listing.illusts)
+            }
+        };
+        images.truncate(limit as usize);
+        Ok(images)
+    }
+
+    async fn autocomplete_tags(&self, prefix: &str, limit: u32) -> Result<Vec<String>> {
+        let url = format!(
+            "{}/v2/search/autocomplete?merge_plain_keyword_results=true&word={}",
+            self.base_url,
+            urlencoding::encode(prefix)
+        );
+        let response: AutocompleteResponse = self.get(&url).await?;
+        Ok(response
+            .tags
+            .into_iter()
+            .take(limit as usize)
+            .map(|t| t.name.replace(' ', "_"))
+            .collect())
+    }
+
+    /// `id` is an illustration id, or `<id>_p<n>` for a page of a longer work
+    async fn get_image(&self, id: &str) -> Result<BooruImage> {
+        let (illust_id, page) = match id.split_once("_p") {
+            Some((illust_id, page)) => (illust_id, page.parse::<usize>().ok()),
+            None => (id, None),
+        };
+        let url = format!(
+            "{}/v1/illust/detail?illust_id={}",
+            self.base_url,
+            urlencoding::encode(illust_id)
+        );
+        let detail: IllustDetail = self.get(&url).await?;
+
+        let images = self.illust_to_images(detail.illust);
+        let found = match page {
+            Some(page) => images.into_iter().nth(page),
+            None => images.into_iter().next(),
+        };
+        found.ok_or_else(|| Error::NotFound(format!("Post {} not found", id)))
+    }
+
+    async fn favorites(&self, limit: u32, page: u32) -> Result<Vec<BooruImage>> {
+        let mut images = self.bookmarks(false, page).await?;
+        images.truncate(limit as usize);
+        Ok(images)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_string_contains, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn token(access: &str, expires_in: u64) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_string(format!(
+            r#"{{"access_token": "{}", "expires_in": {}, "token_type": "bearer",
+                "scope": "", "refresh_token": "refresh-2",
+                "user": {{"id": "42", "name": "me", "account": "me"}}}}"#,
+            access, expires_in
+        ))
+    }
+
+    const SINGLE: &str = r#"{
+        "id": 1001, "title": "Sky", "type": "illust",
+        "image_urls": {"square_medium": "https://i.pximg.net/sq/1001.jpg",
+                       "medium": "https://i.pximg.net/m/1001.jpg",
+                       "large": "https://i.pximg.net/l/1001.jpg"},
+        "user": {"id": 7, "name": "Artist", "account": "artist"},
+        "tags": [{"name": "風景", "translated_name": "scenery"}, {"name": "blue sky"}],
+        "width": 2560, "height": 1440, "sanity_level": 2, "x_restrict": 0,
+        "page_count": 1, "total_bookmarks": 500,
+        "meta_single_page": {"original_image_url": "https://i.pximg.net/img-original/1001_p0.png"},
+        "meta_pages": []
+    }"#;
+
+    const MULTI: &str = r#"{
+        "id": 1002, "title": "Set", "type": "manga",
+        "image_urls": {"square_medium": "https://i.pximg.net/sq/1002.jpg"},
+        "user": {"id": 7, "name": "Artist"},
+        "tags": [], "width": 1920, "height": 1080, "sanity_level": 6, "x_restrict": 0,
+        "meta_single_page": {},
+        "meta_pages": [
+            {"image_urls": {"large": "https://i.pximg.net/l/1002_p0.jpg",
+                            "original": "https://i.pximg.net/img-original/1002_p0.jpg"}},
+            {"image_urls": {"large": "https://i.pximg.net/l/1002_p1.jpg",
+                            "original": "https://i.pximg.net/img-original/1002_p1.jpg"}}
+        ]
+    }"#;
+
+    const UGOIRA: &str = r#"{
+        "id": 1003, "type": "ugoira", "image_urls": {}, "user": {"name": "Artist"},
+        "width": 100, "height": 100, "x_restrict": 1, "meta_single_page": {}
+    }"#;
+
+    #[tokio::test]
+    async fn test_refreshes_access_token() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/auth/token"))
+            .and(body_string_contains("grant_type=refresh_token"))
+            .and(body_string_contains("refresh_token=refresh-1"))
+            .respond_with(token("expired", 30))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/auth/token"))
+            .and(body_string_contains("refresh_token=refresh-2"))
+            .respond_with(token("fresh", 3600))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/illust/ranking"))
+            .and(header("authorization", "Bearer fresh"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                r#"{{"illusts": [{}], "next_url": null}}"#,
+                SINGLE
+            )))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let connector = PixivConnector::with_base_url("pixiv", &server.uri(), "refresh-1")
+            .with_auth_url(&server.uri());
+
+        // The first token is within the expiry margin, so it is replaced at once
+        assert_eq!(connector.access_token().await.unwrap().token, "expired");
+        assert_eq!(connector.ranking("day", 1).await.unwrap().len(), 1);
+        // The fresh token is reused
+        assert_eq!(connector.search(&["ranking:week".to_string()], 30, 1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retries_rejected_token() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/auth/token"))
+            .respond_with(token("token", 3600))
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/illust/detail"))
+            .respond_with(ResponseTemplate::new(400).set_body_string(
+                r#"{"error": {"message": "Error occurred at the OAuth process."}}"#,
+            ))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/illust/detail"))
+            .and(query_param("illust_id", "1002"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string(format!(r#"{{"illust": {}}}"#, MULTI)),
+            )
+            .mount(&server)
+            .await;
+
+        let connector = PixivConnector::with_base_url("pixiv", &server.uri(), "refresh-1")
+            .with_auth_url(&server.uri());
+        let image = connector.get_image("1002_p1").await.unwrap();
+
+        assert_eq!(image.id, "1002_p1");
+        assert_eq!(image.file_url, "https://i.pximg.net/img-original/1002_p1.jpg");
+        assert!(matches!(image.rating, Rating::Questionable));
+        assert!(matches!(connector.get_image("1002_p5").await, Err(Error::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_search_bookmarks_and_autocomplete() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/auth/token"))
+            .respond_with(token("token", 3600))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/search/illust"))
+            .and(query_param("word", "blue sky"))
+            .and(query_param("offset", "30"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                r#"{{"illusts": [{}, {}, {}]}}"#,
+                SINGLE, MULTI, UGOIRA
+            )))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/user/bookmarks/illust"))
+            .and(query_param("user_id", "42"))
+            .and(query_param("restrict", "private"))
+            .and(query_param("max_bookmark_id", "9"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                r#"{{"illusts": [{}], "next_url": null}}"#,
+                MULTI
+            )))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/user/bookmarks/illust"))
+            .and(query_param("restrict", "private"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                r#"{{"illusts": [{}], "next_url": "{}/v1/user/bookmarks/illust?user_id=42&restrict=private&max_bookmark_id=9"}}"#,
+                SINGLE,
+                server.uri()
+            )))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/search/autocomplete"))
+            .and(query_param("word", "blue"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"tags": [{"name": "blue sky", "translated_name": null}, {"name": "blue eyes"}]}"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let connector = PixivConnector::with_base_url("pixiv", &server.uri(), "refresh-1")
+            .with_auth_url(&server.uri());
+
+        let images = connector.search(&["blue_sky".to_string()], 30, 2).await.unwrap();
+        let ids: Vec<&str> = images.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(ids, vec!["1001", "1002_p0", "1002_p1"]);
+        let single = &images[0];
+        assert_eq!(single.file_url, "https://i.pximg.net/img-original/1001_p0.png");
+        assert_eq!(single.tags, vec!["風景", "blue_sky"]);
+        assert_eq!(single.author.as_deref(), Some("Artist"));
+        assert_eq!(single.page_url.as_deref(), Some("https://www.pixiv.net/artworks/1001"));
+        assert_eq!(single.score, Some(500));
+        assert!(!single.is_nsfw);
+        assert_eq!((images[2].width, images[2].height), (0, 0));
+        assert_eq!(connector.search(&["blue_sky".to_string()], 2, 2).await.unwrap().len(), 2);
+
+        let first = connector.search(&["bookmarks:private".to_string()], 30, 1).await.unwrap();
+        assert_eq!(first[0].id, "1001");
+        let second = connector.search(&["bookmarks:private".to_string()], 30, 2).await.unwrap();
+        assert_eq!(second.len(), 2);
+        assert!(connector.search(&["bookmarks:private".to_string()], 30, 3).await.unwrap().is_empty());
+
+        assert_eq!(
+            connector.autocomplete_tags("blue", 10).await.unwrap(),
+            vec!["blue_sky", "blue_eyes"]
+        );
+    }
+}
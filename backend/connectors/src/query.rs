@@ -0,0 +1,453 @@
+//! Wallmgr's tag query language
+//!
+//! One syntax for every source. A query is parsed once into a [`Query`] and
+//! each connector translates it into its site's own syntax (see
+//! [`BooruConnector::translate`](crate::booru::BooruConnector::translate)).
+//! What a site can't express is checked on its results instead.
+//!
+//! - `sky cloud`: images with both tags
+//! - `-city`: images without the tag
+//! - `sunset | sunrise` or `sunset|sunrise`: images with either tag
+//! - `rating:safe,questionable` (or `s`, `q`, `e`), `-rating:explicit`
+//! - `res:1920x1080` (or `hd`, `qhd`, `4k`), `width:>=1920`, `height:>=1080`:
+//!   minimum size
+//! - `ratio:16:9`, `ratio:>=1.5`, `ratio:1.5..2.5`, `ratio:landscape`,
+//!   `ratio:portrait`: aspect ratio, width over height
+//! - `order:score`, `order:date`, `order:random`, `order:favorites`
+//!
+//! Any other term, such as a site's own meta tag, is passed on unchanged.
+//! Terms and ranges are parsed by [`wallmgr_core::query`], as library queries
+//! are.
+
+use wallmgr_core::error::{Error, Result};
+use wallmgr_core::query::{self, Bounds, Term, RATIO_TOLERANCE};
+use wallmgr_core::types::{BooruImage, Rating};
+
+/// Meta terms of source searches
+const KEYS: &[&str] = &["rating", "res", "width", "height", "ratio", "order"];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    /// Tags every image must have
+    pub tags: Vec<String>,
+    /// Tags no image may have
+    pub excluded: Vec<String>,
+    /// Groups of tags of which an image must have at least one each
+    pub any_of: Vec<Vec<String>>,
+    /// Allowed ratings; any when empty
+    pub ratings: Vec<Rating>,
+    pub min_width: Option<u32>,
+    pub min_height: Option<u32>,
+    pub ratio: Option<RatioRange>,
+    pub order: Option<Order>,
+}
+
+/// Aspect ratios (width over height) from `min` to `max`, inclusive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RatioRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl RatioRange {
+    pub fn exact(ratio: f64) -> Self {
+        query::ratio_around(ratio).into()
+    }
+
+    /// The ratio this range was made from by [`exact`](Self::exact)
+    pub fn as_exact(&self) -> Option<f64> {
+        let ratio = (self.min + self.max) / 2.0;
+        ((self.max - self.min) <= ratio * RATIO_TOLERANCE * 2.0 + f64::EPSILON).then_some(ratio)
+    }
+
+    pub fn contains(&self, ratio: f64) -> bool {
+        ratio >= self.min && ratio <= self.max
+    }
+}
+
+impl From<Bounds<f64>> for RatioRange {
+    fn from(bounds: Bounds<f64>) -> Self {
+        Self {
+            min: bounds.min.unwrap_or(0.0),
+            max: bounds.max.unwrap_or(f64::INFINITY),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Score,
+    Date,
+    Random,
+    Favorites,
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Self> {
+        let mut parsed = Self::default();
+        for term in query::terms(query, KEYS)? {
+            parsed.add_term(term)?;
+        }
+        Ok(parsed)
+    }
+
+    /// A query from search terms given one or more per string
+    pub fn from_tags(tags: &[String]) -> Result<Self> {
+        Self::parse(&tags.join(" "))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn add_term(&mut self, term: Term) -> Result<()> {
+        let (key, value, negated) = match &term {
+            Term::Meta { key, value, negated } => (*key, value.as_str(), *negated),
+            Term::Tag { name, negated } => {
+                match negated {
+                    true => self.excluded.push(name.clone()),
+                    false => self.tags.push(name.clone()),
+                }
+                return Ok(());
+            }
+            Term::AnyOf(group) => {
+                self.any_of.push(group.clone());
+                return Ok(());
+            }
+        };
+
+        let invalid = || term.invalid();
+        match key {
+            "rating" => {
+                self.ratings = query::parse_ratings(value, negated, &self.ratings).ok_or_else(invalid)?;
+                return Ok(());
+            }
+            _ if negated => {
+                return Err(Error::InvalidInput(format!("{} can't be negated", key)));
+            }
+            "res" => {
+                let (width, height) = query::parse_resolution(value).ok_or_else(invalid)?;
+                self.min_width = Some(width);
+                self.min_height = Some(height);
+            }
+            "width" => self.min_width = Some(parse_minimum(value).ok_or_else(invalid)?),
+            "height" => self.min_height = Some(parse_minimum(value).ok_or_else(invalid)?),
+            "ratio" => self.ratio = Some(query::parse_ratio_bounds(value).ok_or_else(invalid)?.into()),
+            "order" => {
+                self.order = Some(match value {
+                    "score" | "popular" => Order::Score,
+                    "date" | "new" | "recent" => Order::Date,
+                    "random" => Order::Random,
+                    "favorites" | "favcount" => Order::Favorites,
+                    _ => return Err(invalid()),
+                })
+            }
+            _ => unreachable!("terms() only returns known keys"),
+        }
+        Ok(())
+    }
+
+    /// Whether `image` satisfies the query; unknown sizes pass
+    pub fn matches(&self, image: &BooruImage) -> bool {
+        let has = |tag: &String| image.tags.iter().any(|t| t.eq_ignore_ascii_case(tag));
+
+        self.tags.iter().all(has)
+            && !self.excluded.iter().any(has)
+            && self.any_of.iter().all(|group| group.iter().any(has))
+            && (self.ratings.is_empty() || self.ratings.contains(&image.rating))
+            && (image.width == 0 || self.min_width.is_none_or(|w| image.width >= w))
+            && (image.height == 0 || self.min_height.is_none_or(|h| image.height >= h))
+            && (image.height == 0
+                || self.ratio.is_none_or(|r| r.contains(image.width as f64 / image.height as f64)))
+    }
+
+    /// Drop the images that don't match, and sort by score if asked to
+    pub fn filter(&self, images: Vec<BooruImage>) -> Vec<BooruImage> {
+        let mut images: Vec<_> = images.into_iter().filter(|image| self.matches(image)).collect();
+        if self.order == Some(Order::Score) {
+            images.sort_by_key(|image| std::cmp::Reverse(image.score));
+        }
+        images
+    }
+}
+
+/// Whether a term left out of a search can be checked on image tags. A
+/// site's own `key:value` terms, like `id:>5`, can't; tags starting with a
+/// colon, like `:d`, are plain tags.
+fn checkable(term: &str) -> bool {
+    let meta = term.split_once(':').is_some_and(|(key, value)| {
+        !key.is_empty() && !value.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    });
+    if meta {
+        tracing::warn!("Dropped {} from a search: too many terms for the site", term);
+    }
+    !meta
+}
+
+/// `1920`, `>=1920` or `>1920`
+fn parse_minimum(value: &str) -> Option<u32> {
+    match query::parse_bounds(value, |v| v.parse().ok().map(|n| (n, n)))? {
+        Bounds { min: Some(min), max: None } => Some(min),
+        Bounds { min: Some(min), max: Some(max) } if min == max => Some(min),
+        _ => None,
+    }
+}
+
+/// A query in a site's own syntax, plus what the site can't express
+///
+/// Connectors build one by taking the parts of the query their site
+/// understands; each part is either translated whole or left to filtering.
+#[derive(Debug, Clone)]
+pub struct Translation {
+    /// Terms for [`BooruConnector::search`](crate::booru::BooruConnector::search)
+    pub tags: Vec<String>,
+    /// Checked on the results
+    pub residual: Query,
+    /// Terms the site accepts; unlimited when `None`
+    limit: Option<usize>,
+}
+
+impl Translation {
+    /// Nothing translated yet: everything is left to filtering
+    pub fn new(query: &Query) -> Self {
+        Self {
+            tags: Vec::new(),
+            residual: query.clone(),
+            limit: None,
+        }
+    }
+
+    /// Send at most `limit` terms, for sites that refuse longer queries.
+    /// Parts are translated in the order they are taken, so take the most
+    /// selective ones first.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    fn push(&mut self, terms: Vec<String>) -> bool {
+        let fits = self.limit.is_none_or(|limit| self.tags.len() + terms.len() <= limit);
+        if fits {
+            self.tags.extend(terms);
+        }
+        fits
+    }
+
+    /// Required tags, as they are. A site's own meta terms that don't fit,
+    /// like `id:>5`, are dropped, as no image tag could match them.
+    pub fn tags(mut self) -> Self {
+        for tag in std::mem::take(&mut self.residual.tags) {
+            if !self.push(vec![tag.clone()]) && checkable(&tag) {
+                self.residual.tags.push(tag);
+            }
+        }
+        self
+    }
+
+    /// Excluded tags, each after `prefix`; meta terms that don't fit are
+    /// dropped as with [`tags`](Self::tags)
+    pub fn excluded(mut self, prefix: &str) -> Self {
+        for tag in std::mem::take(&mut self.residual.excluded) {
+            if !self.push(vec![format!("{}{}", prefix, tag)]) && checkable(&tag) {
+                self.residual.excluded.push(tag);
+            }
+        }
+        self
+    }
+
+    /// Alternatives; `format` turns a group into terms, or returns `None`
+    /// when the site can't take (another) group
+    pub fn any_of(mut self, mut format: impl FnMut(&[String]) -> Option<Vec<String>>) -> Self {
+        for group in std::mem::take(&mut self.residual.any_of) {
+            let translated = format(&group).is_some_and(|terms| self.push(terms));
+            if !translated {
+                self.residual.any_of.push(group);
+            }
+        }
+        self
+    }
+
+    /// Allowed ratings, when some are left out
+    pub fn rating(mut self, format: impl FnOnce(&[Rating]) -> Option<Vec<String>>) -> Self {
+        if !self.residual.ratings.is_empty() {
+            if let Some(terms) = format(&self.residual.ratings) {
+                if self.push(terms) {
+                    self.residual.ratings.clear();
+                }
+            }
+        }
+        self
+    }
+
+    /// Minimum width and height, when either is set
+    pub fn size(mut self, format: impl FnOnce(Option<u32>, Option<u32>) -> Option<Vec<String>>) -> Self {
+        let (width, height) = (self.residual.min_width, self.residual.min_height);
+        if width.is_some() || height.is_some() {
+            if let Some(terms) = format(width, height) {
+                if self.push(terms) {
+                    self.residual.min_width = None;
+                    self.residual.min_height = None;
+                }
+            }
+        }
+        self
+    }
+
+    pub fn ratio(mut self, format: impl FnOnce(RatioRange) -> Option<Vec<String>>) -> Self {
+        if let Some(ratio) = self.residual.ratio {
+            if let Some(terms) = format(ratio) {
+                if self.push(terms) {
+                    self.residual.ratio = None;
+                }
+            }
+        }
+        self
+    }
+
+    /// Sort order; an empty list of terms means the site sorts that way already
+    pub fn order(mut self, format: impl FnOnce(Order) -> Option<Vec<String>>) -> Self {
+        if let Some(order) = self.residual.order {
+            if let Some(terms) = format(order) {
+                if self.push(terms) {
+                    self.residual.order = None;
+                }
+            }
+        }
+        self
+    }
+}
+
+/// One-letter codes like `s`, or `None` when all ratings are allowed
+pub fn rating_codes(ratings: &[Rating]) -> Option<Vec<&'static str>> {
+    (ratings.len() < 3).then(|| {
+        ratings
+            .iter()
+            .map(|rating| match rating {
+                Rating::Safe => "s",
+                Rating::Questionable => "q",
+                Rating::Explicit => "e",
+            })
+            .collect()
+    })
+}
+
+/// `rating:s` for one allowed rating, `-rating:e` for all but one, for sites
+/// without rating lists
+pub fn rating_terms(ratings: &[Rating], key: &str, name: impl Fn(Rating) -> &'static str) -> Vec<String> {
+    let all = [Rating::Safe, Rating::Questionable, Rating::Explicit];
+    match ratings {
+        [rating] => vec![format!("{}:{}", key, name(*rating))],
+        _ => all
+            .into_iter()
+            .filter(|rating| !ratings.contains(rating))
+            .map(|rating| format!("-{}:{}", key, name(rating)))
+            .collect(),
+    }
+}
+
+/// A range as `min..max`, `min..` or `..max`
+pub fn range_value(min: f64, max: f64) -> String {
+    match (min > 0.0, max.is_finite()) {
+        (true, true) => format!("{:.2}..{:.2}", min, max),
+        (true, false) => format!("{:.2}..", min),
+        (false, true) => format!("..{:.2}", max),
+        (false, false) => "..".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn image(tags: &[&str], width: u32, height: u32, rating: Rating) -> BooruImage {
+        BooruImage {
+            id: "1".to_string(),
+            source: "test".to_string(),
+            file_url: "https://example.com/1.jpg".to_string(),
+            preview_url: None,
+            sample_url: None,
+            width,
+            height,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            rating,
+            score: None,
+            author: None,
+            is_nsfw: rating != Rating::Safe,
+            md5: None,
+            file_size: None,
+            page_url: None,
+            origin_url: None,
+            attribution: None,
+            tag_categories: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let query = Query::parse(
+            "sky -city sunset | sunrise night|day rating:s,q res:1920x1080 ratio:16:9 order:score id:>5",
+        )
+        .unwrap();
+
+        assert_eq!(query.tags, vec!["sky", "id:>5"]);
+        assert_eq!(query.excluded, vec!["city"]);
+        assert_eq!(query.any_of, vec![vec!["sunset", "sunrise"], vec!["night", "day"]]);
+        assert_eq!(query.ratings, vec![Rating::Safe, Rating::Questionable]);
+        assert_eq!((query.min_width, query.min_height), (Some(1920), Some(1080)));
+        assert_eq!(query.ratio.unwrap().as_exact().map(|r| (r * 100.0).round()), Some(178.0));
+        assert_eq!(query.order, Some(Order::Score));
+
+        let query = Query::parse("-rating:e width:>1919 ratio:landscape").unwrap();
+        assert_eq!(query.ratings, vec![Rating::Safe, Rating::Questionable]);
+        assert_eq!(query.min_width, Some(1920));
+        assert!(query.ratio.unwrap().contains(1.5) && !query.ratio.unwrap().contains(1.0));
+
+        assert!(Query::parse("order:sideways").is_err());
+        assert!(Query::parse("-ratio:16:9").is_err());
+        assert!(Query::parse("a | -b").is_err());
+        assert!(Query::parse("width:<1920").is_err());
+
+        let query = Query::parse("ratio:>=1.5 rating:s|q").unwrap();
+        assert_eq!(query.ratio, Some(RatioRange { min: 1.5, max: f64::INFINITY }));
+        assert_eq!(query.ratings, vec![Rating::Safe, Rating::Questionable]);
+    }
+
+    #[test]
+    fn test_matches() {
+        let query = Query::parse("sky -city sunset|sunrise rating:s res:hd ratio:>=1.5").unwrap();
+
+        assert!(query.matches(&image(&["sky", "Sunset"], 1920, 1080, Rating::Safe)));
+        assert!(!query.matches(&image(&["sky", "sunset", "city"], 1920, 1080, Rating::Safe)));
+        assert!(!query.matches(&image(&["sky"], 1920, 1080, Rating::Safe)));
+        assert!(!query.matches(&image(&["sky", "sunset"], 1920, 1080, Rating::Questionable)));
+        assert!(!query.matches(&image(&["sky", "sunset"], 1280, 720, Rating::Safe)));
+        assert!(!query.matches(&image(&["sky", "sunset"], 2000, 1600, Rating::Safe)));
+        // Listings without sizes aren't dropped
+        assert!(query.matches(&image(&["sky", "sunrise"], 0, 0, Rating::Safe)));
+    }
+
+    #[test]
+    fn test_translation_leaves_the_rest_to_filtering() {
+        let query = Query::parse("sky cloud -city a|b c|d rating:s order:date res:hd").unwrap();
+        let mut groups = 0;
+        let translation = Translation::new(&query)
+            .limit(5)
+            .tags()
+            .excluded("-")
+            .any_of(|group| {
+                groups += 1;
+                (groups == 1).then(|| group.iter().map(|tag| format!("~{}", tag)).collect())
+            })
+            .rating(|ratings| Some(rating_terms(ratings, "rating", |r| r.as_str())))
+            .order(|_| Some(Vec::new()))
+            .size(|_, _| Some(vec!["width:>=1920".to_string(), "height:>=1080".to_string()]));
+
+        assert_eq!(translation.tags, vec!["sky", "cloud", "-city", "~a", "~b"]);
+        assert_eq!(translation.residual.any_of, vec![vec!["c", "d"]]);
+        assert_eq!(translation.residual.ratings, vec![Rating::Safe]);
+        assert_eq!(translation.residual.order, None);
+        assert_eq!(translation.residual.min_width, Some(1920));
+        assert!(translation.residual.tags.is_empty());
+    }
+}
@@ -0,0 +1,568 @@
+//! Persistent download queue
+//!
+//! Jobs are stored in the `download_jobs` table so they survive daemon
+//! restarts; anything that was running when the daemon stopped is queued
+//! again and resumes from its partial file. Jobs run highest priority first,
+//! with a bounded number of concurrent downloads per host, and failed
+//! attempts are retried with exponential backoff. Images the content policy
+//! blocks are never queued.
+
+use crate::download::{DownloadProgress, DownloadedFile, Downloader};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::{broadcast, Notify};
+use wallmgr_core::config::{DownloadsConfig, LibraryConfig};
+use wallmgr_core::error::{Error, Result};
+use wallmgr_core::types::{BooruImage, DownloadJob, DownloadState};
+use wallmgr_core::{ContentPolicy, Database};
+
+/// How long the scheduler sleeps when nothing is waiting for a retry
+const IDLE_POLL: Duration = Duration::from_secs(60);
+
+/// Upper bound for the retry delay
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Something that happened to a job
+#[derive(Debug, Clone)]
+pub enum QueueEvent {
+    Progress {
+        job_id: i64,
+        image_id: String,
+        progress: DownloadProgress,
+    },
+    Done {
+        job_id: i64,
+        image_id: String,
+        path: String,
+    },
+    Failed {
+        job_id: i64,
+        image_id: String,
+        error: String,
+        /// When the next attempt runs; `None` once the job has given up
+        retry_at: Option<DateTime<Utc>>,
+    },
+}
+
+/// Registers finished downloads, e.g. in the wallpaper library
+#[async_trait]
+pub trait Importer: Send + Sync {
+    /// Called with the verified file before the job is marked done; an error
+    /// counts as a failed attempt
+    async fn import(&self, job: &DownloadJob, file: &DownloadedFile) -> Result<()>;
+}
+
+/// Stop signals for a running job
+struct ActiveJob {
+    host: String,
+    stop: AtomicBool,
+    cancelled: AtomicBool,
+}
+
+pub struct DownloadQueue {
+    database: Mutex<Database>,
+    downloader: Downloader,
+    config: DownloadsConfig,
+    policy: Arc<ContentPolicy>,
+    importer: Arc<dyn Importer>,
+    active: Mutex<HashMap<i64, Arc<ActiveJob>>>,
+    wake: Notify,
+    events: broadcast::Sender<QueueEvent>,
+}
+
+impl DownloadQueue {
+    /// `database` should be a connection of its own to the library database
+    pub fn new(
+        database: Database,
+        library: LibraryConfig,
+        config: DownloadsConfig,
+        policy: Arc<ContentPolicy>,
+        importer: Arc<dyn Importer>,
+    ) -> Arc<Self> {
+        let (events, _) = broadcast::channel(256);
+        Arc::new(Self {
+            database: Mutex::new(database),
+            downloader: Downloader::new(library),
+            config,
+            policy,
+            importer,
+            active: Mutex::new(HashMap::new()),
+            wake: Notify::new(),
+            events,
+        })
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<QueueEvent> {
+        self.events.subscribe()
+    }
+
+    /// The policy images are checked against before they are queued
+    pub fn policy(&self) -> &ContentPolicy {
+        &self.policy
+    }
+
+    /// Queue `image`; an image that is already queued keeps its job
+    pub fn enqueue(&self, image: &BooruImage, priority: i32) -> Result<DownloadJob> {
+        self.policy.check(image)?;
+        let job = self.db().enqueue_download(image, priority)?;
+        self.wake.notify_one();
+        Ok(job)
+    }
+
+    pub fn jobs(&self, state: Option<DownloadState>) -> Result<Vec<DownloadJob>> {
+        self.db().list_download_jobs(state)
+    }
+
+    pub fn job(&self, id: i64) -> Result<DownloadJob> {
+        self.db().get_download_job(id)
+    }
+
+    /// Hold a queued job back, or stop a running one keeping its partial file
+    pub fn pause(&self, id: i64) -> Result<DownloadJob> {
+        let job = self.job(id)?;
+        if !matches!(job.state, DownloadState::Queued | DownloadState::Downloading) {
+            return Err(Error::InvalidInput(format!(
+                "Download job {} is {} and can't be paused",
+                id,
+                job.state.as_str()
+            )));
+        }
+
+        self.db().set_download_state(id, DownloadState::Paused)?;
+        if let Some(active) = self.active_job(id) {
+            active.stop.store(true, Ordering::Relaxed);
+        }
+        self.job(id)
+    }
+
+    /// Queue a paused or failed job again
+    pub fn resume(&self, id: i64) -> Result<DownloadJob> {
+        let job = self.job(id)?;
+        if !matches!(job.state, DownloadState::Paused | DownloadState::Failed) {
+            return Err(Error::InvalidInput(format!(
+                "Download job {} is {} and can't be resumed",
+                id,
+                job.state.as_str()
+            )));
+        }
+
+        self.db().resume_download(id)?;
+        self.wake.notify_one();
+        self.job(id)
+    }
+
+    /// Remove a job, stopping it and deleting its partial file
+    ///
+    /// Files of finished jobs stay in the library.
+    pub async fn cancel(&self, id: i64) -> Result<()> {
+        let job = self.job(id)?;
+        self.db().delete_download_job(id)?;
+
+        match self.active_job(id) {
+            Some(active) => {
+                // The worker deletes the partial file once it has stopped writing
+                active.cancelled.store(true, Ordering::Relaxed);
+                active.stop.store(true, Ordering::Relaxed);
+            }
+            None if job.state != DownloadState::Done => {
+                self.downloader.discard_partial(&job.image).await?;
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    pub fn set_priority(&self, id: i64, priority: i32) -> Result<DownloadJob> {
+        self.db().set_download_priority(id, priority)?;
+        self.wake.notify_one();
+        self.job(id)
+    }
+
+    /// Run the scheduler; jobs interrupted by a previous shutdown are queued again
+    pub async fn run(self: Arc<Self>) {
+        match self.db().requeue_interrupted_downloads() {
+            Ok(0) => {}
+            Ok(count) => tracing::info!("Resuming {} interrupted downloads", count),
+            Err(e) => tracing::error!("Failed to requeue interrupted downloads: {}", e),
+        }
+
+        loop {
+            let wait = match self.dispatch() {
+                Ok(Some(retry_at)) => (retry_at - Utc::now()).to_std().unwrap_or_default(),
+                Ok(None) => IDLE_POLL,
+                Err(e) => {
+                    tracing::error!("Download queue error: {}", e);
+                    IDLE_POLL
+                }
+            };
+
+            tokio::select! {
+                _ = self.wake.notified() => {}
+                _ = tokio::time::sleep(wait) => {}
+            }
+        }
+    }
+
+    /// Start every due job that fits within the per-host limit, returning
+    /// when the next waiting retry is due
+    fn dispatch(self: &Arc<Self>) -> Result<Option<DateTime<Utc>>> {
+        let now = Utc::now();
+        let mut next_retry: Option<DateTime<Utc>> = None;
+
+        for job in self.jobs(Some(DownloadState::Queued))? {
+            if let Some(retry_at) = job.retry_at.filter(|&t| t > now) {
+                next_retry = Some(next_retry.map_or(retry_at, |t| t.min(retry_at)));
+                continue;
+            }
+
+            let host = host_of(&job.image.file_url);
+            let active = {
+                let mut active = self.active.lock().unwrap();
+                let running = active.values().filter(|a| a.host == host).count();
+                if active.contains_key(&job.id) || running >= self.config.per_host.max(1) {
+                    continue;
+                }
+
+                let job_control = Arc::new(ActiveJob {
+                    host,
+                    stop: AtomicBool::new(false),
+                    cancelled: AtomicBool::new(false),
+                });
+                active.insert(job.id, job_control.clone());
+                job_control
+            };
+
+            if let Err(e) = self.db().set_download_state(job.id, DownloadState::Downloading) {
+                self.active.lock().unwrap().remove(&job.id);
+                return Err(e);
+            }
+
+            let queue = self.clone();
+            tokio::spawn(async move { queue.process(job, active).await });
+        }
+
+        Ok(next_retry)
+    }
+
+    async fn process(self: Arc<Self>, job: DownloadJob, active: Arc<ActiveJob>) {
+        let image_id = format!("{}:{}", job.image.source, job.image.id);
+        let mut last_progress = None;
+
+        let result = self
+            .downloader
+            .download_until(&job.image, &active.stop, |progress| {
+                last_progress = Some(*progress);
+                let _ = self.events.send(QueueEvent::Progress {
+                    job_id: job.id,
+                    image_id: image_id.clone(),
+                    progress: *progress,
+                });
+            })
+            .await;
+
+        if let Some(progress) = last_progress {
+            // Fails harmlessly if the job was cancelled meanwhile
+            let _ = self.db().set_download_progress(job.id, progress.downloaded, progress.total);
+        }
+
+        let result = match result {
+            Ok(file) => self.importer.import(&job, &file).await.map(|_| file),
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(file) => {
+                let path = file.path.to_string_lossy().to_string();
+                if let Err(e) = self.db().complete_download(job.id, &path) {
+                    tracing::debug!("Download job {} finished after removal: {}", job.id, e);
+                }
+                tracing::info!("Downloaded {} to {}", image_id, path);
+                let _ = self.events.send(QueueEvent::Done {
+                    job_id: job.id,
+                    image_id,
+                    path,
+                });
+            }
+            // pause() and cancel() have already updated the job, also when the
+            // request failed on its own before it noticed
+            Err(e) if matches!(e, Error::Cancelled(_)) || active.stop.load(Ordering::Relaxed) => {
+                if active.cancelled.load(Ordering::Relaxed) {
+                    if let Err(e) = self.downloader.discard_partial(&job.image).await {
+                        tracing::warn!("Failed to remove partial download of {}: {}", image_id, e);
+                    }
+                }
+            }
+            Err(e) => {
+                let attempts = job.attempts + 1;
+                let retry_at = (attempts < self.config.max_attempts && is_retryable(&e)).then(|| {
+                    let delay = retry_delay(self.config.retry_delay_secs, attempts);
+                    Utc::now() + chrono::Duration::from_std(delay).unwrap_or_default()
+                });
+
+                tracing::warn!("Download of {} failed (attempt {}): {}", image_id, attempts, e);
+                let error = e.to_string();
+                if let Err(e) = self.db().record_download_failure(job.id, &error, retry_at) {
+                    tracing::debug!("Download job {} failed after removal: {}", job.id, e);
+                }
+                let _ = self.events.send(QueueEvent::Failed {
+                    job_id: job.id,
+                    image_id,
+                    error,
+                    retry_at,
+                });
+            }
+        }
+
+        self.active.lock().unwrap().remove(&job.id);
+        self.wake.notify_one();
+    }
+
+    fn active_job(&self, id: i64) -> Option<Arc<ActiveJob>> {
+        self.active.lock().unwrap().get(&id).cloned()
+    }
+
+    fn db(&self) -> MutexGuard<'_, Database> {
+        self.database.lock().unwrap()
+    }
+}
+
+/// Delay before retry number `attempt` (1-based): doubles every attempt
+fn retry_delay(base_secs: u64, attempt: u32) -> Duration {
+    let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+    Duration::from_secs(base_secs.saturating_mul(factor)).min(MAX_RETRY_DELAY)
+}
+
+/// Whether another attempt could succeed
+fn is_retryable(error: &Error) -> bool {
+    !matches!(error, Error::AlreadyExists(_) | Error::InvalidInput(_))
+}
+
+fn host_of(url: &str) -> String {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_string()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md5::{Digest, Md5};
+    use std::path::PathBuf;
+    use wallmgr_core::types::Rating;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[derive(Default)]
+    struct RecordingImporter {
+        imported: Mutex<Vec<PathBuf>>,
+    }
+
+    #[async_trait]
+    impl Importer for RecordingImporter {
+        async fn import(&self, _job: &DownloadJob, file: &DownloadedFile) -> Result<()> {
+            self.imported.lock().unwrap().push(file.path.clone());
+            Ok(())
+        }
+    }
+
+    fn image(id: &str, url: String, body: &[u8]) -> BooruImage {
+        BooruImage {
+            id: id.to_string(),
+            source: "danbooru".to_string(),
+            file_url: url,
+            preview_url: None,
+            sample_url: None,
+            width: 1,
+            height: 1,
+            tags: vec![],
+            rating: Rating::Safe,
+            score: None,
+            author: None,
+            is_nsfw: false,
+            md5: Some(format!("{:x}", Md5::digest(body))),
+            file_size: Some(body.len() as u64),
+            page_url: None,
+            origin_url: None,
+            attribution: None,
+            tag_categories: HashMap::new(),
+        }
+    }
+
+    fn queue(dir: &std::path::Path, config: DownloadsConfig, importer: Arc<dyn Importer>) -> Arc<DownloadQueue> {
+        let database = Database::new(&dir.join("wallmgr.db")).unwrap();
+        let library = LibraryConfig {
+            dir: dir.join("library"),
+            layout: "{source}/{id}.{ext}".to_string(),
+        };
+        DownloadQueue::new(database, library, config, Arc::default(), importer)
+    }
+
+    async fn wait_for_done(events: &mut broadcast::Receiver<QueueEvent>, count: usize) {
+        let mut done = 0;
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while done < count {
+                if let Ok(QueueEvent::Done { .. }) = events.recv().await {
+                    done += 1;
+                }
+            }
+        })
+        .await
+        .expect("downloads did not finish");
+    }
+
+    #[test]
+    fn test_retry_delay_doubles() {
+        assert_eq!(retry_delay(30, 1), Duration::from_secs(30));
+        assert_eq!(retry_delay(30, 3), Duration::from_secs(120));
+        assert_eq!(retry_delay(30, 60), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_blocked_images_are_not_queued() {
+        let dir = tempfile::tempdir().unwrap();
+        let policy = ContentPolicy::new(&wallmgr_core::config::PolicyConfig {
+            blacklist: vec!["gore".to_string()],
+            ..Default::default()
+        });
+        let queue = DownloadQueue::new(
+            Database::new(&dir.path().join("wallmgr.db")).unwrap(),
+            LibraryConfig::default(),
+            DownloadsConfig::default(),
+            Arc::new(policy),
+            Arc::new(RecordingImporter::default()),
+        );
+
+        let mut blocked = image("1", "https://example.com/1.png".to_string(), b"");
+        blocked.tags.push("gore".to_string());
+        assert!(matches!(queue.enqueue(&blocked, 0), Err(Error::Blocked(_))));
+        assert!(queue.jobs(None).unwrap().is_empty());
+        assert!(queue.enqueue(&image("2", "https://example.com/2.png".to_string(), b""), 0).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_retries_failed_downloads() {
+        let server = MockServer::start().await;
+        let body = vec![3u8; 5_000];
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/1.png"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(body.clone()))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let importer = Arc::new(RecordingImporter::default());
+        let config = DownloadsConfig {
+            retry_delay_secs: 0,
+            ..DownloadsConfig::default()
+        };
+        let queue = queue(dir.path(), config, importer.clone());
+        let mut events = queue.subscribe();
+
+        let job = queue.enqueue(&image("1", format!("{}/1.png", server.uri()), &body), 0).unwrap();
+        tokio::spawn(queue.clone().run());
+        wait_for_done(&mut events, 1).await;
+
+        let job = queue.job(job.id).unwrap();
+        assert_eq!(job.state, DownloadState::Done);
+        assert_eq!(job.attempts, 1);
+        assert_eq!(job.downloaded, body.len() as u64);
+        let expected = dir.path().join("library/danbooru/1.png");
+        assert_eq!(job.path.as_deref(), Some(expected.to_str().unwrap()));
+        assert_eq!(*importer.imported.lock().unwrap(), vec![expected]);
+    }
+
+    #[tokio::test]
+    async fn test_limits_downloads_per_host() {
+        let server = MockServer::start().await;
+        let body = vec![9u8; 1_000];
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_bytes(body.clone())
+                    .set_delay(Duration::from_millis(300)),
+            )
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = DownloadsConfig {
+            per_host: 1,
+            ..DownloadsConfig::default()
+        };
+        let queue = queue(dir.path(), config, Arc::new(RecordingImporter::default()));
+        let mut events = queue.subscribe();
+
+        let low = queue.enqueue(&image("1", format!("{}/1.jpg", server.uri()), &body), 0).unwrap();
+        let high = queue.enqueue(&image("2", format!("{}/2.jpg", server.uri()), &body), 5).unwrap();
+        tokio::spawn(queue.clone().run());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(queue.job(high.id).unwrap().state, DownloadState::Downloading);
+        assert_eq!(queue.job(low.id).unwrap().state, DownloadState::Queued);
+
+        wait_for_done(&mut events, 2).await;
+        assert!(queue.jobs(None).unwrap().iter().all(|j| j.state == DownloadState::Done));
+    }
+
+    #[tokio::test]
+    async fn test_paused_jobs_stay_paused_when_the_request_fails() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503).set_delay(Duration::from_millis(300)))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = DownloadsConfig {
+            retry_delay_secs: 0,
+            ..DownloadsConfig::default()
+        };
+        let queue = queue(dir.path(), config, Arc::new(RecordingImporter::default()));
+        let job = queue.enqueue(&image("1", format!("{}/1.jpg", server.uri()), b"1"), 0).unwrap();
+        tokio::spawn(queue.clone().run());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        queue.pause(job.id).unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let job = queue.job(job.id).unwrap();
+        assert_eq!((job.state, job.attempts), (DownloadState::Paused, 0));
+    }
+
+    #[tokio::test]
+    async fn test_pause_resume_cancel() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = queue(dir.path(), DownloadsConfig::default(), Arc::new(RecordingImporter::default()));
+
+        let first = queue.enqueue(&image("1", "https://a.example/1.jpg".to_string(), b"1"), 0).unwrap();
+        let second = queue.enqueue(&image("2", "https://a.example/2.jpg".to_string(), b"2"), 0).unwrap();
+        assert_eq!(queue.enqueue(&first.image, 3).unwrap().id, first.id);
+
+        assert_eq!(queue.pause(first.id).unwrap().state, DownloadState::Paused);
+        assert!(queue.pause(first.id).is_err());
+        assert_eq!(queue.resume(first.id).unwrap().state, DownloadState::Queued);
+        assert!(queue.resume(first.id).is_err());
+
+        queue.set_priority(second.id, 10).unwrap();
+        let order: Vec<i64> = queue.jobs(None).unwrap().iter().map(|j| j.id).collect();
+        assert_eq!(order, vec![second.id, first.id]);
+
+        let partial = queue.downloader.partial_path(&second.image);
+        std::fs::create_dir_all(partial.parent().unwrap()).unwrap();
+        std::fs::write(&partial, b"half").unwrap();
+        queue.cancel(second.id).await.unwrap();
+        assert!(!partial.exists());
+        assert!(matches!(queue.job(second.id), Err(Error::NotFound(_))));
+    }
+}
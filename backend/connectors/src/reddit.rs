@@ -0,0 +1,353 @@
+use crate::booru::{BooruClient, BooruConnector, SendWith, parse_error};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use wallmgr_core::error::{Error, Result};
+use wallmgr_core::types::{BooruImage, Rating};
+
+/// Image posts of one subreddit, e.g. `https://www.reddit.com/r/wallpapers`
+///
+/// Reddit pages with an opaque `after` cursor rather than page numbers, so
+/// the cursor of each page is remembered and earlier pages are walked when a
+/// later one is asked for first.
+pub struct RedditConnector {
+    client: BooruClient,
+    name: String,
+    /// Subreddit URL
+    base_url: String,
+    /// Site root, for permalinks
+    origin: String,
+    /// `after` cursor of each (query, page size, page)
+    cursors: Mutex<HashMap<(String, u32, u32), String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RedditListing {
+    data: RedditListingData,
+}
+
+#[derive(Debug, Deserialize)]
+struct RedditListingData {
+    #[serde(default)]
+    after: Option<String>,
+    children: Vec<RedditChild>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RedditChild {
+    data: RedditPost,
+}
+
+#[derive(Debug, Deserialize)]
+struct RedditPost {
+    id: String,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    author: Option<String>,
+    subreddit: String,
+    permalink: String,
+    #[serde(default)]
+    over_18: bool,
+    #[serde(default)]
+    score: Option<i32>,
+    #[serde(default)]
+    post_hint: Option<String>,
+    #[serde(default)]
+    preview: Option<RedditPreview>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RedditPreview {
+    images: Vec<RedditPreviewImage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RedditPreviewImage {
+    source: RedditImageSize,
+    #[serde(default)]
+    resolutions: Vec<RedditImageSize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RedditImageSize {
+    url: String,
+    width: u32,
+    height: u32,
+}
+
+impl RedditConnector {
+    pub fn new(name: &str, subreddit_url: &str) -> Self {
+        let base_url = subreddit_url.trim_end_matches('/').to_string();
+        let origin = url::Url::parse(&base_url)
+            .map(|u| u.origin().ascii_serialization())
+            .unwrap_or_else(|_| "https://www.reddit.com".to_string());
+
+        Self {
+            client: BooruClient::new(),
+            name: name.to_string(),
+            base_url,
+            origin,
+            cursors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// r/wallpapers
+    pub fn wallpapers() -> Self {
+        Self::new("reddit", "https://www.reddit.com/r/wallpapers")
+    }
+
+    async fn fetch(&self, url: &str) -> Result<reqwest::Response> {
+        let response = self.client.client()
+            .get(url)
+            .fetch(&self.client, &self.name)
+            .await?;
+        Ok(response)
+    }
+
+    async fn listing(&self, query: &str, limit: u32, after: Option<&str>) -> Result<RedditListingData> {
+        // raw_json=1 stops Reddit from HTML-escaping the URLs
+        let mut url = if query.is_empty() {
+            format!("{}/hot.json?raw_json=1&limit={}", self.base_url, limit)
+        } else {
+            format!(
+                "{}/search.json?raw_json=1&restrict_sr=1&sort=top&t=all&q={}&limit={}",
+                self.base_url,
+                urlencoding::encode(query),
+                limit
+            )
+        };
+        if let Some(after) = after {
+            url.push_str(&format!("&after={}", urlencoding::encode(after)));
+        }
+
+        let listing: RedditListing = self
+            .fetch(&url)
+            .await?
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))?;
+        Ok(listing.data)
+    }
+
+    fn post_to_image(&self, post: RedditPost) -> Option<BooruImage> {
+        let file_url = post.url.filter(|u| is_image_url(u) || post.post_hint.as_deref() == Some("image"))?;
+        let preview = post.preview.and_then(|p| p.images.into_iter().next());
+        let (width, height) = preview.as_ref().map(|p| (p.source.width, p.source.height)).unwrap_or((0, 0));
+        let preview_url = preview.as_ref().and_then(|p| {
+            p.resolutions
+                .iter()
+                .find(|r| r.width >= 320)
+                .or(p.resolutions.last())
+                .map(|r| r.url.clone())
+        });
+        let author = post.author.filter(|a| a != "[deleted]");
+
+        Some(BooruImage {
+            id: post.id,
+            source: self.name.clone(),
+            file_url,
+            preview_url,
+            sample_url: preview.map(|p| p.source.url),
+            width,
+            height,
+            tags: Vec::new(),
+            rating: if post.over_18 { Rating::Explicit } else { Rating::Safe },
+            score: post.score,
+            attribution: author
+                .as_ref()
+                .map(|a| format!("Posted by u/{} on r/{}", a, post.subreddit)),
+            tag_categories: HashMap::new(),
+            author,
+            is_nsfw: post.over_18,
+            md5: None,
+            file_size: None,
+            page_url: Some(format!("{}{}", self.origin, post.permalink)),
+            origin_url: None,
+        })
+    }
+}
+
+fn is_image_url(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or_default().to_lowercase();
+    [".jpg", ".jpeg", ".png", ".webp"].iter().any(|ext| path.ends_with(ext))
+}
+
+#[async_trait]
+impl BooruConnector for RedditConnector {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn search(&self, tags: &[String], limit: u32, page: u32) -> Result<Vec<BooruImage>> {
+        let query = tags.join(" ");
+        let limit = limit.min(100);
+        let page = page.max(1);
+
+        // Start from the closest page whose cursor is known
+        let (mut current, mut after) = {
+            let cursors = self.cursors.lock().unwrap();
+            (2..=page)
+                .rev()
+                .find_map(|p| cursors.get(&(query.clone(), limit, p)).map(|a| (p, Some(a.clone()))))
+                .unwrap_or((1, None))
+        };
+
+        loop {
+            let listing = self.listing(&query, limit, after.as_deref()).await?;
+            if let Some(next) = &listing.after {
+                self.cursors
+                    .lock()
+                    .unwrap()
+                    .insert((query.clone(), limit, current + 1), next.clone());
+            }
+
+            if current == page {
+                return Ok(listing
+                    .children
+                    .into_iter()
+                    .filter_map(|c| self.post_to_image(c.data))
+                    .collect());
+            }
+            match listing.after {
+                Some(next) => {
+                    after = Some(next);
+                    current += 1;
+                }
+                // Fewer pages than asked for
+                None => return Ok(Vec::new()),
+            }
+        }
+    }
+
+    async fn autocomplete_tags(&self, _prefix: &str, _limit: u32) -> Result<Vec<String>> {
+        // Posts have no tags
+        Ok(Vec::new())
+    }
+
+    async fn get_image(&self, id: &str) -> Result<BooruImage> {
+        let url = format!("{}/comments/{}.json?raw_json=1", self.base_url, urlencoding::encode(id));
+
+        // The post listing followed by its comments
+        let listings: Vec<RedditListing> = self
+            .fetch(&url)
+            .await
+            .map_err(|_| Error::NotFound(format!("Post {} not found", id)))?
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))?;
+
+        let post = listings
+            .into_iter()
+            .next()
+            .and_then(|l| l.data.children.into_iter().next())
+            .ok_or_else(|| Error::NotFound(format!("Post {} not found", id)))?;
+
+        self.post_to_image(post.data)
+            .ok_or_else(|| Error::NotFound(format!("Post {} is not an image", id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param, query_param_is_missing};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn listing(after: Option<&str>, ids: &[&str]) -> String {
+        let posts: Vec<String> = ids
+            .iter()
+            .map(|id| {
+                format!(
+                    r#"{{"kind": "t3", "data": {{"id": "{id}", "subreddit": "wallpapers",
+                        "title": "Lake [3840x2160]", "author": "poster", "over_18": false,
+                        "score": 1500, "post_hint": "image",
+                        "url": "https://i.redd.it/{id}.jpg",
+                        "permalink": "/r/wallpapers/comments/{id}/lake_3840x2160/",
+                        "preview": {{"images": [{{
+                            "source": {{"url": "https://preview.redd.it/{id}.jpg?width=3840&s=a", "width": 3840, "height": 2160}},
+                            "resolutions": [
+                                {{"url": "https://preview.redd.it/{id}.jpg?width=108&s=b", "width": 108, "height": 60}},
+                                {{"url": "https://preview.redd.it/{id}.jpg?width=320&s=c", "width": 320, "height": 180}}
+                            ]}}]}}}}}}"#
+                )
+            })
+            .collect();
+        format!(
+            r#"{{"kind": "Listing", "data": {{"after": {}, "children": [{},
+                {{"kind": "t3", "data": {{"id": "self", "subreddit": "wallpapers", "author": "x",
+                  "permalink": "/r/wallpapers/comments/self/", "url": "https://www.reddit.com/r/wallpapers/comments/self/",
+                  "is_self": true}}}}]}}}}"#,
+            after.map(|a| format!("\"{}\"", a)).unwrap_or("null".to_string()),
+            posts.join(",")
+        )
+    }
+
+    #[tokio::test]
+    async fn test_listing_pages() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/r/wallpapers/hot.json"))
+            .and(query_param_is_missing("after"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(listing(Some("t3_a"), &["a"])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/r/wallpapers/hot.json"))
+            .and(query_param("after", "t3_a"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(listing(Some("t3_b"), &["b"])))
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        let connector = RedditConnector::new("reddit", &format!("{}/r/wallpapers/", server.uri()));
+
+        // Page 2 walks page 1 first; asking again reuses the cursor
+        for _ in 0..2 {
+            let images = connector.search(&[], 25, 2).await.unwrap();
+            assert_eq!(images.len(), 1);
+            assert_eq!(images[0].id, "b");
+        }
+
+        let image = &connector.search(&[], 25, 2).await.unwrap()[0];
+        assert_eq!(image.file_url, "https://i.redd.it/b.jpg");
+        assert_eq!((image.width, image.height), (3840, 2160));
+        assert_eq!(image.preview_url.as_deref(), Some("https://preview.redd.it/b.jpg?width=320&s=c"));
+        assert_eq!(image.attribution.as_deref(), Some("Posted by u/poster on r/wallpapers"));
+        assert_eq!(
+            image.page_url,
+            Some(format!("{}/r/wallpapers/comments/b/lake_3840x2160/", server.uri()))
+        );
+        assert!(!image.is_nsfw);
+    }
+
+    #[tokio::test]
+    async fn test_search_and_get_image() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/r/wallpapers/search.json"))
+            .and(query_param("q", "lake"))
+            .and(query_param("restrict_sr", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(listing(None, &["c"])))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/r/wallpapers/comments/c.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                r#"[{}, {{"kind": "Listing", "data": {{"after": null, "children": []}}}}]"#,
+                listing(None, &["c"])
+            )))
+            .mount(&server)
+            .await;
+
+        let connector = RedditConnector::new("reddit", &format!("{}/r/wallpapers", server.uri()));
+        assert_eq!(connector.search(&["lake".to_string()], 10, 1).await.unwrap().len(), 1);
+        // No second page
+        assert!(connector.search(&["lake".to_string()], 10, 2).await.unwrap().is_empty());
+
+        assert_eq!(connector.get_image("c").await.unwrap().score, Some(1500));
+        assert!(matches!(connector.get_image("zz").await, Err(Error::NotFound(_))));
+    }
+}
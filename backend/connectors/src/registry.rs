@@ -0,0 +1,258 @@
+//! Connectors built from the `[sources]` configuration
+
+use crate::anime_pictures::AnimePicturesConnector;
+use crate::apod::ApodConnector;
+use crate::bing::BingConnector;
+use crate::booru::BooruConnector;
+use crate::danbooru::DanbooruConnector;
+use crate::e621::E621Connector;
+use crate::gelbooru::GelbooruConnector;
+use crate::moebooru::MoebooruConnector;
+use crate::pexels::PexelsConnector;
+use crate::pixiv::PixivConnector;
+use crate::reddit::RedditConnector;
+use crate::sankaku::SankakuConnector;
+use crate::shimmie::ShimmieConnector;
+use crate::szurubooru::SzurubooruConnector;
+use crate::unsplash::UnsplashConnector;
+use crate::wallhaven::WallHavenConnector;
+use crate::zerochan::ZerochanConnector;
+use std::sync::Arc;
+use wallmgr_core::config::{ConnectorConfig, ConnectorEngine, SourcesConfig};
+use wallmgr_core::error::{Error, Result};
+
+/// The enabled connectors, in configuration order
+#[derive(Default)]
+pub struct ConnectorRegistry {
+    entries: Vec<(ConnectorConfig, Arc<dyn BooruConnector>)>,
+}
+
+impl ConnectorRegistry {
+    pub fn from_config(sources: &SourcesConfig) -> Result<Self> {
+        let mut registry = Self::default();
+        for config in sources.connectors().into_iter().filter(|c| c.enabled) {
+            registry.add(config)?;
+        }
+        Ok(registry)
+    }
+
+    /// Build and register a connector; names must be unique
+    pub fn add(&mut self, config: ConnectorConfig) -> Result<()> {
+        if self.get(&config.name).is_some() {
+            return Err(Error::Config(format!("Duplicate source name: {}", config.name)));
+        }
+        let connector = build(&config)?;
+        self.entries.push((config, connector));
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn BooruConnector>> {
+        self.entries
+            .iter()
+            .find(|(config, _)| config.name == name)
+            .map(|(_, connector)| connector.clone())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.entries.iter().map(|(config, _)| config.name.as_str()).collect()
+    }
+
+    /// Configuration of each registered connector
+    pub fn configs(&self) -> impl Iterator<Item = &ConnectorConfig> {
+        self.entries.iter().map(|(config, _)| config)
+    }
+
+    pub fn connectors(&self) -> impl Iterator<Item = &Arc<dyn BooruConnector>> {
+        self.entries.iter().map(|(_, connector)| connector)
+    }
+}
+
+/// Create the connector for one configured site
+pub fn build(config: &ConnectorConfig) -> Result<Arc<dyn BooruConnector>> {
+    let base_url = url::Url::parse(&config.base_url)
+        .ok()
+        .filter(|u| matches!(u.scheme(), "http" | "https"))
+        .map(|_| config.base_url.as_str())
+        .ok_or_else(|| {
+            Error::Config(format!("Invalid base URL for {}: {}", config.name, config.base_url))
+        })?;
+
+    let api_key = config.secret()?;
+    let credentials = config.username.as_deref().zip(api_key.as_deref());
+    let name = config.name.as_str();
+
+    Ok(match config.engine {
+        ConnectorEngine::Danbooru2 => {
+            let connector = DanbooruConnector::with_base_url(name, base_url);
+            match credentials {
+                Some((login, key)) => Arc::new(connector.with_credentials(login, key)),
+                None => Arc::new(connector),
+            }
+        }
+        ConnectorEngine::Moebooru => {
+            let connector = MoebooruConnector::new(name, base_url);
+            match credentials {
+                Some((login, hash)) => Arc::new(connector.with_credentials(login, hash)),
+                None => Arc::new(connector),
+            }
+        }
+        ConnectorEngine::Gelbooru02 => {
+            let connector = GelbooruConnector::with_base_url(name, base_url);
+            match credentials {
+                Some((user_id, key)) => Arc::new(connector.with_credentials(user_id, key)),
+                None => Arc::new(connector),
+            }
+        }
+        ConnectorEngine::Wallhaven => {
+            let mut connector = WallHavenConnector::with_base_url(name, base_url);
+            if let Some(key) = &api_key {
+                connector = connector.with_api_key(key);
+            }
+            if let Some(username) = &config.username {
+                connector = connector.with_username(username);
+            }
+            Arc::new(connector)
+        }
+        ConnectorEngine::Zerochan => Arc::new(ZerochanConnector::with_base_url(name, base_url)),
+        ConnectorEngine::AnimePictures => {
+            Arc::new(AnimePicturesConnector::with_base_url(name, base_url))
+        }
+        ConnectorEngine::E621 => {
+            let connector = E621Connector::new(name, base_url);
+            match credentials {
+                Some((login, key)) => Arc::new(connector.with_credentials(login, key)),
+                None => Arc::new(connector),
+            }
+        }
+        ConnectorEngine::Sankaku => {
+            let connector = SankakuConnector::with_base_url(name, base_url);
+            match &api_key {
+                Some(token) => Arc::new(connector.with_access_token(token)),
+                None => Arc::new(connector),
+            }
+        }
+        ConnectorEngine::Szurubooru => {
+            let connector = SzurubooruConnector::new(name, base_url);
+            match credentials {
+                Some((username, token)) => Arc::new(connector.with_credentials(username, token)),
+                None => Arc::new(connector),
+            }
+        }
+        ConnectorEngine::Shimmie2 => Arc::new(ShimmieConnector::new(name, base_url)),
+        ConnectorEngine::Unsplash => {
+            Arc::new(UnsplashConnector::with_base_url(name, base_url, required_key(config, &api_key)?))
+        }
+        ConnectorEngine::Pexels => {
+            Arc::new(PexelsConnector::with_base_url(name, base_url, required_key(config, &api_key)?))
+        }
+        ConnectorEngine::Reddit => Arc::new(RedditConnector::new(name, base_url)),
+        ConnectorEngine::Bing => Arc::new(BingConnector::with_base_url(name, base_url)),
+        ConnectorEngine::Apod => {
+            Arc::new(ApodConnector::with_base_url(name, base_url, api_key.as_deref()))
+        }
+        ConnectorEngine::Pixiv => {
+            let refresh_token = api_key.as_deref().ok_or_else(|| {
+                Error::Config(format!("{} needs an OAuth refresh token as api_key", config.name))
+            })?;
+            Arc::new(PixivConnector::with_base_url(name, base_url, refresh_token))
+        }
+    })
+}
+
+fn required_key<'a>(config: &ConnectorConfig, api_key: &'a Option<String>) -> Result<&'a str> {
+    api_key
+        .as_deref()
+        .ok_or_else(|| Error::Config(format!("{} needs an api_key", config.name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_builtin_sites_and_overrides() {
+        let mut sources = SourcesConfig::default();
+        let registry = ConnectorRegistry::from_config(&sources).unwrap();
+        assert_eq!(
+            registry.names(),
+            vec![
+                "danbooru", "yandere", "konachan", "safebooru", "gelbooru", "wallhaven",
+                "zerochan", "anime_pictures", "e926", "reddit", "bing", "apod",
+            ]
+        );
+        assert_eq!(registry.get("konachan").unwrap().name(), "konachan");
+
+        sources.enable_gelbooru = false;
+        sources.connectors = vec![
+            ConnectorConfig {
+                enabled: false,
+                ..ConnectorConfig::new("wallhaven", ConnectorEngine::Wallhaven, "https://wallhaven.cc")
+            },
+            ConnectorConfig::new("local", ConnectorEngine::Moebooru, "http://127.0.0.1:3000"),
+        ];
+        let registry = ConnectorRegistry::from_config(&sources).unwrap();
+        assert_eq!(
+            registry.names(),
+            vec![
+                "danbooru", "yandere", "konachan", "safebooru", "zerochan", "anime_pictures",
+                "e926", "reddit", "bing", "apod", "local",
+            ]
+        );
+
+        sources.connectors = vec![ConnectorConfig::new("broken", ConnectorEngine::Danbooru2, "not a url")];
+        assert!(ConnectorRegistry::from_config(&sources).is_err());
+
+        // Key-only sites can't be enabled without one
+        sources.connectors = vec![ConnectorConfig::new("unsplash", ConnectorEngine::Unsplash, "https://api.unsplash.com")];
+        assert!(matches!(ConnectorRegistry::from_config(&sources), Err(Error::Config(_))));
+        sources.connectors[0].api_key = Some("key".to_string());
+        assert!(ConnectorRegistry::from_config(&sources).unwrap().get("unsplash").is_some());
+
+        // Pixiv is switched on by the dedicated settings
+        sources.connectors.clear();
+        sources.enable_pixiv = true;
+        assert!(matches!(ConnectorRegistry::from_config(&sources), Err(Error::Config(_))));
+        sources.pixiv_refresh_token = Some("token".to_string());
+        assert!(ConnectorRegistry::from_config(&sources).unwrap().get("pixiv").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_gelbooru_instance_with_credentials() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/index.php"))
+            .and(query_param("s", "post"))
+            .and(query_param("user_id", "12"))
+            .and(query_param("api_key", "secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"[{"id": 3, "image": "abc.jpg", "directory": "1a/2b", "hash": "f00",
+                     "width": 800, "height": 600, "tags": "tree", "rating": "general"}]"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let sources = SourcesConfig {
+            connectors: vec![ConnectorConfig {
+                username: Some("12".to_string()),
+                api_key: Some("secret".to_string()),
+                ..ConnectorConfig::new("mybooru", ConnectorEngine::Gelbooru02, &server.uri())
+            }],
+            ..SourcesConfig::default()
+        };
+        let registry = ConnectorRegistry::from_config(&sources).unwrap();
+        let images = registry
+            .get("mybooru")
+            .unwrap()
+            .search(&["tree".to_string()], 5, 0)
+            .await
+            .unwrap();
+
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].source, "mybooru");
+        assert_eq!(images[0].file_url, format!("{}/images/1a/2b/abc.jpg", server.uri()));
+        assert_eq!(images[0].md5.as_deref(), Some("f00"));
+        assert!(!images[0].is_nsfw);
+    }
+}
@@ -0,0 +1,313 @@
+use crate::booru::{BooruClient, BooruConnector, SendWith, parse_rating, parse_error, request_error, origin_url};
+use crate::query::{Order, Query, Translation};
+use async_trait::async_trait;
+use serde::Deserialize;
+use wallmgr_core::error::{Error, Result};
+use wallmgr_core::types::{BooruImage, TagCategory};
+use std::collections::HashMap;
+
+/// Sankaku Channel
+/// API: https://capi-v2.sankakucomplex.com
+pub struct SankakuConnector {
+    client: BooruClient,
+    name: String,
+    base_url: String,
+    access_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SankakuPost {
+    /// Numeric on older posts, an opaque string on newer ones
+    id: serde_json::Value,
+    rating: String,
+    /// Null for posts hidden from anonymous users
+    #[serde(default)]
+    file_url: Option<String>,
+    #[serde(default)]
+    sample_url: Option<String>,
+    #[serde(default)]
+    preview_url: Option<String>,
+    width: u32,
+    height: u32,
+    #[serde(default)]
+    file_size: Option<u64>,
+    #[serde(default)]
+    md5: Option<String>,
+    /// Free-form, often a link to the original
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    total_score: Option<i32>,
+    #[serde(default)]
+    author: Option<SankakuAuthor>,
+    #[serde(default)]
+    tags: Vec<SankakuTag>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SankakuAuthor {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SankakuTag {
+    #[serde(default)]
+    name_en: Option<String>,
+    name: String,
+    #[serde(default, rename = "type")]
+    kind: u8,
+}
+
+impl SankakuTag {
+    fn into_name(self) -> String {
+        self.name_en.filter(|n| !n.is_empty()).unwrap_or(self.name)
+    }
+
+    fn category(&self) -> TagCategory {
+        match self.kind {
+            1 | 2 => TagCategory::Artist,
+            3 => TagCategory::Copyright,
+            4 => TagCategory::Character,
+            5 | 8 | 9 => TagCategory::Meta,
+            _ => TagCategory::General,
+        }
+    }
+}
+
+impl SankakuConnector {
+    pub fn new() -> Self {
+        Self::with_base_url("sankaku", "https://capi-v2.sankakucomplex.com")
+    }
+
+    pub fn with_base_url(name: &str, base_url: &str) -> Self {
+        Self {
+            client: BooruClient::new(),
+            name: name.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            access_token: None,
+        }
+    }
+
+    /// Authenticate requests with an access token from the site's login
+    pub fn with_access_token(mut self, token: &str) -> Self {
+        self.access_token = Some(token.to_string());
+        self
+    }
+
+    fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        let request = self.client.client().get(url);
+        match &self.access_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    fn post_to_image(&self, post: SankakuPost) -> Option<BooruImage> {
+        let file_url = post.file_url.filter(|u| !u.is_empty())?;
+        let id = match post.id {
+            serde_json::Value::String(id) => id,
+            id => id.to_string(),
+        };
+        let mut tag_categories = HashMap::new();
+        let tags = post
+            .tags
+            .into_iter()
+            .map(|tag| {
+                let category = tag.category();
+                let name = tag.into_name();
+                tag_categories.insert(name.clone(), category);
+                name
+            })
+            .collect();
+
+        Some(BooruImage {
+            id,
+            source: self.name.clone(),
+            file_url,
+            preview_url: post.preview_url,
+            sample_url: post.sample_url,
+            width: post.width,
+            height: post.height,
+            tags,
+            rating: parse_rating(&post.rating),
+            score: post.total_score,
+            author: post.author.map(|a| a.name),
+            is_nsfw: post.rating != "s",
+            md5: post.md5,
+            file_size: post.file_size,
+            page_url: None,
+            origin_url: post.source.as_deref().and_then(origin_url),
+            attribution: None,
+            tag_categories,
+        })
+    }
+}
+
+impl Default for SankakuConnector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl BooruConnector for SankakuConnector {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn translate(&self, query: &Query) -> Translation {
+        // Only a handful of tags are allowed without a paid account
+        Translation::new(query)
+            .limit(4)
+            .tags()
+            .excluded("-")
+            .order(|order| match order {
+                Order::Score => Some(vec!["order:quality".to_string()]),
+                Order::Date => Some(Vec::new()),
+                Order::Random => Some(vec!["order:random".to_string()]),
+                Order::Favorites => None,
+            })
+    }
+
+    async fn search(&self, tags: &[String], limit: u32, page: u32) -> Result<Vec<BooruImage>> {
+        let tags_str = tags.join(" ");
+        let url = format!(
+            "{}/posts?lang=en&tags={}&limit={}&page={}",
+            self.base_url,
+            urlencoding::encode(&tags_str),
+            limit.min(100),
+            page.max(1)
+        );
+
+        let response = self.get(&url)
+            .fetch(&self.client, &self.name)
+            .await?;
+
+        let posts: Vec<SankakuPost> = response
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))?;
+
+        Ok(posts.into_iter().filter_map(|p| self.post_to_image(p)).collect())
+    }
+
+    async fn autocomplete_tags(&self, prefix: &str, limit: u32) -> Result<Vec<String>> {
+        let url = format!(
+            "{}/tags?lang=en&name={}&order=count&limit={}",
+            self.base_url,
+            urlencoding::encode(prefix),
+            limit.min(20)
+        );
+
+        let response = self.get(&url)
+            .send_with(&self.client)
+            .await
+            .map_err(|e| request_error(&self.name, e))?;
+
+        if !response.status().is_success() {
+            return Ok(Vec::new());
+        }
+
+        let tags: Vec<SankakuTag> = response
+            .json()
+            .await
+            .unwrap_or_default();
+
+        Ok(tags.into_iter().map(SankakuTag::into_name).collect())
+    }
+
+    async fn get_image(&self, id: &str) -> Result<BooruImage> {
+        let url = format!("{}/posts/{}?lang=en", self.base_url, urlencoding::encode(id));
+
+        let response = self.get(&url)
+            .fetch(&self.client, &self.name)
+            .await?;
+
+        let post: SankakuPost = response
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))?;
+
+        self.post_to_image(post)
+            .ok_or_else(|| Error::NotFound("No file URL found".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const POSTS: &str = r#"[
+        {"id": 31234567, "rating": "q", "status": "active",
+         "author": {"id": 5, "name": "uploader"},
+         "sample_url": "https://v.sankakucomplex.com/data/sample/ab/cd/sample-abcd.jpg",
+         "preview_url": "https://v.sankakucomplex.com/data/preview/ab/cd/abcd.jpg",
+         "file_url": "https://v.sankakucomplex.com/data/ab/cd/abcd.png",
+         "width": 3000, "height": 2000, "file_size": 5000000, "file_type": "image/png",
+         "md5": "abcd", "total_score": 120,
+         "tags": [{"id": 1, "name_en": "night_sky", "name_ja": "夜空", "name": "night_sky", "type": 0},
+                  {"id": 2, "name_en": "", "name": "original", "type": 3}]},
+        {"id": "kxa3Lp0qWe", "rating": "s", "file_url": null, "width": 800, "height": 600}
+    ]"#;
+
+    #[tokio::test]
+    async fn test_search() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/posts"))
+            .and(query_param("tags", "night_sky"))
+            .and(query_param("page", "2"))
+            .and(header("authorization", "Bearer token"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(POSTS))
+            .mount(&server)
+            .await;
+
+        let connector = SankakuConnector::with_base_url("sankaku", &server.uri())
+            .with_access_token("token");
+        let images = connector.search(&["night_sky".to_string()], 40, 2).await.unwrap();
+
+        assert_eq!(images.len(), 1);
+        let image = &images[0];
+        assert_eq!(image.id, "31234567");
+        assert_eq!(image.tags, vec!["night_sky", "original"]);
+        assert_eq!(image.tag_categories["night_sky"], TagCategory::General);
+        assert_eq!(image.tag_categories["original"], TagCategory::Copyright);
+        assert_eq!(image.author.as_deref(), Some("uploader"));
+        assert_eq!(image.score, Some(120));
+        assert!(image.is_nsfw);
+    }
+
+    #[tokio::test]
+    async fn test_get_image_and_autocomplete() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/posts/kxa3Lp0qWe"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"id": "kxa3Lp0qWe", "rating": "s", "file_url": "https://v.sankakucomplex.com/data/x.jpg",
+                    "width": 800, "height": 600, "tags": []}"#,
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/tags"))
+            .and(query_param("name", "night"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"[{"id": 1, "name": "night", "name_en": "night", "count": 100000},
+                    {"id": 2, "name": "night_sky", "name_en": "night_sky", "count": 20000}]"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let connector = SankakuConnector::with_base_url("sankaku", &server.uri());
+        let image = connector.get_image("kxa3Lp0qWe").await.unwrap();
+        assert_eq!(image.id, "kxa3Lp0qWe");
+        assert!(!image.is_nsfw);
+
+        assert_eq!(
+            connector.autocomplete_tags("night", 5).await.unwrap(),
+            vec!["night", "night_sky"]
+        );
+    }
+}
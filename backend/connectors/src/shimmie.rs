@@ -0,0 +1,225 @@
+use crate::booru::{BooruClient, BooruConnector, SendWith, parse_rating, request_error, origin_url};
+use crate::query::{Query, Translation};
+use async_trait::async_trait;
+use scraper::{Html, Selector};
+use wallmgr_core::error::{Error, Result};
+use wallmgr_core::types::BooruImage;
+use std::collections::HashMap;
+
+/// Self-hosted Shimmie2 instances, through the bundled Danbooru-compatible
+/// API extension which answers in XML
+/// API: https://github.com/shish/shimmie2/tree/main/ext/danbooru_api
+pub struct ShimmieConnector {
+    client: BooruClient,
+    name: String,
+    base_url: String,
+}
+
+impl ShimmieConnector {
+    pub fn new(name: &str, base_url: &str) -> Self {
+        Self {
+            client: BooruClient::new(),
+            name: name.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn absolute_url(&self, url: &str) -> String {
+        if url.starts_with('/') {
+            format!("{}{}", self.base_url, url)
+        } else {
+            url.to_string()
+        }
+    }
+
+    async fn find_posts(&self, query: &[(&str, String)]) -> Result<Vec<BooruImage>> {
+        let response = self.client.client()
+            .get(format!("{}/api/danbooru/find_posts", self.base_url))
+            .query(query)
+            .fetch(&self.client, &self.name)
+            .await?;
+
+        let body = response
+            .text()
+            .await
+            .map_err(|e| request_error(&self.name, e))?;
+
+        Ok(self.parse_posts(&body))
+    }
+
+    /// `<posts><post id=".." file_url=".." rating="s" tags=".." .../></posts>`
+    fn parse_posts(&self, xml: &str) -> Vec<BooruImage> {
+        let document = Html::parse_document(xml);
+        let selector = Selector::parse("post").expect("valid selector");
+
+        document
+            .select(&selector)
+            .filter_map(|post| {
+                let attr = |name: &str| post.value().attr(name).filter(|v| !v.is_empty());
+                let number = |name: &str| attr(name).and_then(|v| v.parse().ok());
+
+                let rating = attr("rating").unwrap_or("?");
+                Some(BooruImage {
+                    id: attr("id")?.to_string(),
+                    source: self.name.clone(),
+                    file_url: self.absolute_url(attr("file_url")?),
+                    preview_url: attr("preview_url").map(|u| self.absolute_url(u)),
+                    sample_url: None,
+                    width: number("width").unwrap_or(0),
+                    height: number("height").unwrap_or(0),
+                    tags: attr("tags")
+                        .unwrap_or_default()
+                        .split_whitespace()
+                        .map(|s| s.to_string())
+                        .collect(),
+                    rating: parse_rating(rating),
+                    score: attr("score").and_then(|v| v.parse().ok()),
+                    author: attr("author").map(|a| a.to_string()),
+                    // Unrated ("?") posts aren't assumed to be safe
+                    is_nsfw: rating != "s",
+                    md5: attr("md5").map(|m| m.to_string()),
+                    file_size: None,
+                    page_url: None,
+                    origin_url: attr("source").and_then(origin_url),
+                    attribution: None,
+                    tag_categories: HashMap::new(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl BooruConnector for ShimmieConnector {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn translate(&self, query: &Query) -> Translation {
+        Translation::new(query)
+            .tags()
+            .excluded("-")
+            .size(|width, height| {
+                let mut terms = Vec::new();
+                terms.extend(width.map(|w| format!("width>={}", w)));
+                terms.extend(height.map(|h| format!("height>={}", h)));
+                Some(terms)
+            })
+    }
+
+    async fn search(&self, tags: &[String], limit: u32, page: u32) -> Result<Vec<BooruImage>> {
+        self.find_posts(&[
+            ("tags", tags.join(" ")),
+            ("limit", limit.min(100).to_string()),
+            ("page", page.max(1).to_string()),
+        ])
+        .await
+    }
+
+    async fn autocomplete_tags(&self, prefix: &str, limit: u32) -> Result<Vec<String>> {
+        let response = self.client.client()
+            .get(format!("{}/api/internal/autocomplete", self.base_url))
+            .query(&[("s", prefix)])
+            .send_with(&self.client)
+            .await
+            .map_err(|e| request_error(&self.name, e))?;
+
+        if !response.status().is_success() {
+            return Ok(Vec::new());
+        }
+
+        // `{"tag": count}`, or `{"tag": {"newtag": .., "count": ..}}` on newer versions
+        let tags: serde_json::Map<String, serde_json::Value> = response
+            .json()
+            .await
+            .unwrap_or_default();
+
+        let mut tags: Vec<(String, u64)> = tags
+            .into_iter()
+            .map(|(name, value)| {
+                let count = value.as_u64().or_else(|| value["count"].as_u64()).unwrap_or(0);
+                (name, count)
+            })
+            .collect();
+        tags.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+        Ok(tags.into_iter().take(limit as usize).map(|(name, _)| name).collect())
+    }
+
+    async fn get_image(&self, id: &str) -> Result<BooruImage> {
+        self.find_posts(&[("id", id.to_string())])
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::NotFound(format!("Post {} not found", id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const POSTS: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<posts count="3" offset="20">
+<post id="21" md5="aaa111" file_name="21.jpg" file_url="/_images/aaa111/21%20-%20lake.jpg" height="1080" width="1920" preview_url="/_thumbs/aaa111/thumb.jpg" preview_height="108" preview_width="192" rating="s" date="2024-02-01 10:00:00" is_warehoused="false" tags="lake sunset" source="" score="4" author="admin"/>
+<post id="22" md5="bbb222" file_name="22.png" file_url="https://cdn.example.org/22.png" height="900" width="1600" rating="?" tags="lake" score="0" author="guest"/>
+<post id="23" md5="ccc333" height="10" width="10" rating="e"/>
+</posts>"#;
+
+    #[tokio::test]
+    async fn test_search() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/danbooru/find_posts"))
+            .and(query_param("tags", "lake"))
+            .and(query_param("page", "3"))
+            .and(query_param("limit", "10"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(POSTS))
+            .mount(&server)
+            .await;
+
+        let connector = ShimmieConnector::new("shimmie", &server.uri());
+        let images = connector.search(&["lake".to_string()], 10, 3).await.unwrap();
+
+        // The post without a file URL is skipped
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].id, "21");
+        assert_eq!(images[0].file_url, format!("{}/_images/aaa111/21%20-%20lake.jpg", server.uri()));
+        assert_eq!(images[0].tags, vec!["lake", "sunset"]);
+        assert_eq!((images[0].width, images[0].height), (1920, 1080));
+        assert_eq!(images[0].score, Some(4));
+        assert!(!images[0].is_nsfw);
+
+        assert_eq!(images[1].file_url, "https://cdn.example.org/22.png");
+        assert_eq!(images[1].author.as_deref(), Some("guest"));
+        assert!(images[1].is_nsfw);
+    }
+
+    #[tokio::test]
+    async fn test_get_image_and_autocomplete() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/danbooru/find_posts"))
+            .and(query_param("id", "21"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(POSTS))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/internal/autocomplete"))
+            .and(query_param("s", "la"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"lake": {"newtag": null, "count": 12}, "landscape": {"newtag": null, "count": 40}, "lamp": 3}"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let connector = ShimmieConnector::new("shimmie", &server.uri());
+        assert_eq!(connector.get_image("21").await.unwrap().md5.as_deref(), Some("aaa111"));
+        assert_eq!(
+            connector.autocomplete_tags("la", 2).await.unwrap(),
+            vec!["landscape", "lake"]
+        );
+    }
+}
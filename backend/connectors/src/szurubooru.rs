@@ -0,0 +1,315 @@
+use crate::booru::{BooruClient, BooruConnector, SendWith, parse_rating, parse_error, request_error, origin_url, tag_categories};
+use crate::query::{self, Order, Query, Translation};
+use async_trait::async_trait;
+use base64::Engine;
+use serde::Deserialize;
+use wallmgr_core::error::Result;
+use wallmgr_core::types::{BooruImage, Rating};
+
+/// Self-hosted Szurubooru instances
+/// API: https://github.com/rr-/szurubooru/blob/master/doc/API.md
+pub struct SzurubooruConnector {
+    client: BooruClient,
+    name: String,
+    base_url: String,
+    /// `Authorization` header value
+    auth: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SzurubooruPost {
+    id: i64,
+    /// Relative to the instance root, e.g. `data/posts/1_abc.jpg`
+    content_url: String,
+    #[serde(default)]
+    thumbnail_url: Option<String>,
+    canvas_width: u32,
+    canvas_height: u32,
+    /// "safe", "sketchy" or "unsafe"
+    safety: String,
+    #[serde(default)]
+    score: Option<i32>,
+    #[serde(default)]
+    tags: Vec<SzurubooruTag>,
+    #[serde(default)]
+    user: Option<SzurubooruUser>,
+    #[serde(default, rename = "checksumMD5")]
+    checksum_md5: Option<String>,
+    #[serde(default)]
+    file_size: Option<u64>,
+    /// Free-form, often a link to the original
+    #[serde(default)]
+    source: Option<String>,
+}
+
+/// A tag with its aliases; the first name is the primary one
+#[derive(Debug, Deserialize)]
+struct SzurubooruTag {
+    names: Vec<String>,
+    /// Categories are defined per instance; those named like the usual ones
+    /// are recognized
+    #[serde(default)]
+    category: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SzurubooruUser {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SzurubooruPage<T> {
+    results: Vec<T>,
+}
+
+impl SzurubooruConnector {
+    pub fn new(name: &str, base_url: &str) -> Self {
+        Self {
+            client: BooruClient::new(),
+            name: name.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            auth: None,
+        }
+    }
+
+    /// Authenticate requests with a username and login token
+    pub fn with_credentials(mut self, username: &str, token: &str) -> Self {
+        let encoded = base64::engine::general_purpose::STANDARD
+            .encode(format!("{}:{}", username, token));
+        self.auth = Some(format!("Token {}", encoded));
+        self
+    }
+
+    fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        let request = self.client.client()
+            .get(url)
+            .header(reqwest::header::ACCEPT, "application/json");
+        match &self.auth {
+            Some(auth) => request.header(reqwest::header::AUTHORIZATION, auth),
+            None => request,
+        }
+    }
+
+    fn absolute_url(&self, url: &str) -> String {
+        if url.starts_with("http://") || url.starts_with("https://") {
+            url.to_string()
+        } else {
+            format!("{}/{}", self.base_url, url.trim_start_matches('/'))
+        }
+    }
+
+    fn post_to_image(&self, post: SzurubooruPost) -> BooruImage {
+        let tag_categories = tag_categories(post.tags.iter().filter_map(|t| {
+            let category = t.category.as_deref()?.parse().ok()?;
+            Some((category, t.names.first().map(String::as_str)))
+        }));
+        let tags = post
+            .tags
+            .into_iter()
+            .filter_map(|t| t.names.into_iter().next())
+            .collect();
+
+        BooruImage {
+            id: post.id.to_string(),
+            source: self.name.clone(),
+            file_url: self.absolute_url(&post.content_url),
+            preview_url: post.thumbnail_url.as_deref().map(|u| self.absolute_url(u)),
+            sample_url: None,
+            width: post.canvas_width,
+            height: post.canvas_height,
+            tags,
+            rating: parse_rating(&post.safety),
+            score: post.score,
+            author: post.user.map(|u| u.name),
+            is_nsfw: post.safety != "safe",
+            md5: post.checksum_md5,
+            file_size: post.file_size,
+            page_url: None,
+            origin_url: post.source.as_deref().and_then(origin_url),
+            attribution: None,
+            tag_categories,
+        }
+    }
+}
+
+#[async_trait]
+impl BooruConnector for SzurubooruConnector {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn translate(&self, query: &Query) -> Translation {
+        Translation::new(query)
+            .tags()
+            .excluded("-")
+            .any_of(|group| Some(vec![group.join(",")]))
+            .rating(|ratings| {
+                query::rating_codes(ratings)?;
+                let names = ratings
+                    .iter()
+                    .map(|rating| match rating {
+                        Rating::Safe => "safe",
+                        Rating::Questionable => "sketchy",
+                        Rating::Explicit => "unsafe",
+                    })
+                    .collect::<Vec<_>>();
+                Some(vec![format!("safety:{}", names.join(","))])
+            })
+            .size(|width, height| {
+                let mut terms = Vec::new();
+                terms.extend(width.map(|w| format!("image-width:{}..", w)));
+                terms.extend(height.map(|h| format!("image-height:{}..", h)));
+                Some(terms)
+            })
+            .ratio(|ratio| Some(vec![format!("image-ar:{}", query::range_value(ratio.min, ratio.max))]))
+            .order(|order| {
+                Some(vec![match order {
+                    Order::Score => "sort:score",
+                    Order::Date => "sort:creation-date",
+                    Order::Random => "sort:random",
+                    Order::Favorites => "sort:fav-count",
+                }
+                .to_string()])
+            })
+    }
+
+    async fn search(&self, tags: &[String], limit: u32, page: u32) -> Result<Vec<BooruImage>> {
+        let limit = limit.min(100);
+        let url = format!(
+            "{}/api/posts/?query={}&offset={}&limit={}",
+            self.base_url,
+            urlencoding::encode(&tags.join(" ")),
+            page.saturating_sub(1) * limit,
+            limit
+        );
+
+        let response = self.get(&url)
+            .fetch(&self.client, &self.name)
+            .await?;
+
+        let page: SzurubooruPage<SzurubooruPost> = response
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))?;
+
+        Ok(page.results.into_iter().map(|p| self.post_to_image(p)).collect())
+    }
+
+    async fn autocomplete_tags(&self, prefix: &str, limit: u32) -> Result<Vec<String>> {
+        let url = format!(
+            "{}/api/tags/?query={}&limit={}",
+            self.base_url,
+            urlencoding::encode(&format!("{}* sort:usages", prefix)),
+            limit.min(20)
+        );
+
+        let response = self.get(&url)
+            .send_with(&self.client)
+            .await
+            .map_err(|e| request_error(&self.name, e))?;
+
+        if !response.status().is_success() {
+            return Ok(Vec::new());
+        }
+
+        let tags = response
+            .json::<SzurubooruPage<SzurubooruTag>>()
+            .await
+            .map(|page| page.results)
+            .unwrap_or_default();
+
+        Ok(tags.into_iter().filter_map(|t| t.names.into_iter().next()).collect())
+    }
+
+    async fn get_image(&self, id: &str) -> Result<BooruImage> {
+        let url = format!("{}/api/post/{}", self.base_url, urlencoding::encode(id));
+
+        let response = self.get(&url)
+            .fetch(&self.client, &self.name)
+            .await?;
+
+        let post: SzurubooruPost = response
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))?;
+
+        Ok(self.post_to_image(post))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const POST: &str = r#"{
+        "version": 3, "id": 42, "creationTime": "2024-01-01T00:00:00.000Z",
+        "safety": "sketchy", "type": "image", "checksum": "sha1", "checksumMD5": "md5sum",
+        "fileSize": 123456, "canvasWidth": 1920, "canvasHeight": 1080,
+        "contentUrl": "data/posts/42_abcdef.jpg", "thumbnailUrl": "data/generated-thumbnails/42_abcdef.jpg",
+        "tags": [{"names": ["mountain", "mountains"], "category": "default", "usages": 10}],
+        "user": {"name": "admin", "avatarUrl": ""}, "score": 2
+    }"#;
+
+    #[tokio::test]
+    async fn test_search() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/posts/"))
+            .and(query_param("query", "mountain"))
+            .and(query_param("offset", "50"))
+            .and(query_param("limit", "50"))
+            .and(header("accept", "application/json"))
+            .and(header("authorization", "Token YWRtaW46dG9rZW4="))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                r#"{{"query": "mountain", "offset": 50, "limit": 50, "total": 51, "results": [{}]}}"#,
+                POST
+            )))
+            .mount(&server)
+            .await;
+
+        let connector = SzurubooruConnector::new("home", &server.uri())
+            .with_credentials("admin", "token");
+        let images = connector.search(&["mountain".to_string()], 50, 2).await.unwrap();
+
+        assert_eq!(images.len(), 1);
+        let image = &images[0];
+        assert_eq!(image.file_url, format!("{}/data/posts/42_abcdef.jpg", server.uri()));
+        assert_eq!(
+            image.preview_url,
+            Some(format!("{}/data/generated-thumbnails/42_abcdef.jpg", server.uri()))
+        );
+        assert_eq!(image.tags, vec!["mountain"]);
+        assert_eq!(image.tag_categories["mountain"], wallmgr_core::types::TagCategory::General);
+        assert_eq!(image.md5.as_deref(), Some("md5sum"));
+        assert!(matches!(image.rating, wallmgr_core::types::Rating::Questionable));
+        assert!(image.is_nsfw);
+    }
+
+    #[tokio::test]
+    async fn test_get_image_and_autocomplete() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/post/42"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(POST))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/tags/"))
+            .and(query_param("query", "moun* sort:usages"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"query": "moun*", "offset": 0, "limit": 5, "total": 1,
+                    "results": [{"names": ["mountain", "mountains"], "usages": 10}]}"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let connector = SzurubooruConnector::new("home", &server.uri());
+        let image = connector.get_image("42").await.unwrap();
+        assert_eq!(image.author.as_deref(), Some("admin"));
+        assert_eq!(connector.autocomplete_tags("moun", 5).await.unwrap(), vec!["mountain"]);
+        assert!(connector.get_image("7").await.is_err());
+    }
+}
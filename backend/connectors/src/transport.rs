@@ -0,0 +1,557 @@
+//! How connectors talk to sites
+//!
+//! Every API request goes through an [`HttpPolicy`]:
+//! - Sites with a published rate limit are throttled to it, per host and
+//!   across all connectors.
+//! - 429 and 5xx responses and failed connections are retried with jittered
+//!   exponential backoff, honoring `Retry-After`.
+//! - Successful GET responses are kept in an on-disk cache and served from it
+//!   until they expire. When a site can't be reached, expired responses are
+//!   served instead, so earlier searches keep working offline. Entries are
+//!   deleted once they are too old to be served either way.
+
+use md5::{Digest, Md5};
+use reqwest::header::{HeaderValue, AUTHORIZATION, CONTENT_TYPE, RETRY_AFTER};
+use reqwest::{Method, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use wallmgr_core::config::NetworkConfig;
+
+/// Published limits of known sites, in requests per second
+const SITE_LIMITS: &[(&str, f64)] = &[
+    // 10 reads per second
+    ("danbooru.donmai.us", 10.0),
+    // 2 per second hard limit, 1 per second asked for
+    ("e621.net", 1.0),
+    ("e926.net", 1.0),
+    // 45 per minute
+    ("wallhaven.cc", 0.75),
+    // 60 per minute
+    ("zerochan.net", 1.0),
+    // 10 per minute without OAuth
+    ("reddit.com", 1.0 / 6.0),
+];
+
+/// First retry delay; doubled on each further attempt
+const BACKOFF_BASE: Duration = Duration::from_millis(500);
+
+/// Longest wait honored from `Retry-After` or backoff
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(120);
+
+/// Query parameters carrying credentials; requests with them aren't cached
+const CREDENTIAL_PARAMS: &[&str] = &["login", "api_key", "apikey", "password_hash", "user_id"];
+
+/// Time between sweeps of the cache for entries past their age
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Header marking responses served from the cache: `hit` or `stale`
+pub const CACHE_HEADER: &str = "x-wallmgr-cache";
+
+pub struct HttpPolicy {
+    /// Minimum time between requests by host
+    intervals: HashMap<String, Duration>,
+    next_slot: Mutex<HashMap<String, Instant>>,
+    cache: Option<ResponseCache>,
+    max_retries: u32,
+    offline_fallback: bool,
+}
+
+static DEFAULT_POLICY: RwLock<Option<Arc<HttpPolicy>>> = RwLock::new(None);
+
+/// Set the policy of connectors created from now on; the cache lives in
+/// `cache_dir/http`
+pub fn configure(config: &NetworkConfig, cache_dir: &Path) {
+    let policy = HttpPolicy::new(config, Some(cache_dir.join("http")));
+    *DEFAULT_POLICY.write().unwrap() = Some(Arc::new(policy));
+}
+
+/// The shared policy, without a cache unless [`configure`] was called
+pub(crate) fn default_policy() -> Arc<HttpPolicy> {
+    if let Some(policy) = &*DEFAULT_POLICY.read().unwrap() {
+        return policy.clone();
+    }
+    DEFAULT_POLICY
+        .write()
+        .unwrap()
+        .get_or_insert_with(|| Arc::new(HttpPolicy::new(&NetworkConfig::default(), None)))
+        .clone()
+}
+
+impl HttpPolicy {
+    /// `cache_dir` of `None` disables the response cache
+    pub fn new(config: &NetworkConfig, cache_dir: Option<PathBuf>) -> Self {
+        let mut intervals: HashMap<String, Duration> = SITE_LIMITS
+            .iter()
+            .map(|(host, rate)| (host.to_string(), Duration::from_secs_f64(1.0 / rate)))
+            .collect();
+        for (host, rate) in &config.rate_limits {
+            if *rate > 0.0 {
+                intervals.insert(host.clone(), Duration::from_secs_f64(1.0 / rate));
+            } else {
+                intervals.remove(host);
+            }
+        }
+
+        Self {
+            intervals,
+            next_slot: Mutex::new(HashMap::new()),
+            cache: cache_dir
+                .filter(|_| config.cache_ttl_secs > 0 || config.offline_fallback)
+                .map(|dir| {
+                    let ttl = Duration::from_secs(config.cache_ttl_secs);
+                    let max_age = match config.offline_fallback {
+                        true => ttl.max(Duration::from_secs(config.cache_max_age_days * 86400)),
+                        false => ttl,
+                    };
+                    ResponseCache {
+                        dir,
+                        ttl,
+                        max_age,
+                        last_pruned: Mutex::new(None),
+                    }
+                }),
+            max_retries: config.max_retries,
+            offline_fallback: config.offline_fallback,
+        }
+    }
+
+    /// Limit for `host`; a limit for a domain covers its subdomains
+    fn interval(&self, host: &str) -> Option<Duration> {
+        let mut domain = host;
+        loop {
+            if let Some(interval) = self.intervals.get(domain) {
+                return Some(*interval);
+            }
+            domain = domain.split_once('.')?.1;
+        }
+    }
+
+    /// Wait for the next free request slot of `host`
+    async fn throttle(&self, host: &str) {
+        let Some(interval) = self.interval(host) else {
+            return;
+        };
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let now = Instant::now();
+            let slot = next_slot.get(host).copied().filter(|s| *s > now).unwrap_or(now);
+            next_slot.insert(host.to_string(), slot + interval);
+            slot
+        };
+        tokio::time::sleep_until(slot.into()).await;
+    }
+
+    pub async fn execute(&self, client: &reqwest::Client, request: Request) -> reqwest::Result<Response> {
+        let host = request.url().host_str().unwrap_or_default().to_string();
+        // Answers for an account stay off the disk
+        let cache = self
+            .cache
+            .as_ref()
+            .filter(|_| request.method() == Method::GET && !has_credentials(&request));
+        let key = cache_key(&request);
+
+        if let Some(cache) = cache {
+            if let Some(entry) = cache.load(&key).await.filter(|e| cache.is_fresh(e)) {
+                return Ok(entry.into_response("hit"));
+            }
+        }
+
+        let mut attempt = 0;
+        let mut request = Some(request);
+        loop {
+            // Bodies that can't be cloned get a single attempt
+            let current = request.as_ref().and_then(|r| r.try_clone());
+            let (current, last) = match current {
+                Some(current) if attempt < self.max_retries => (current, false),
+                _ => match request.take() {
+                    Some(request) => (request, true),
+                    None => unreachable!("the last attempt ends the loop"),
+                },
+            };
+
+            self.throttle(&host).await;
+            let result = client.execute(current).await;
+
+            let retry = match &result {
+                Ok(response) if is_retryable(response.status()) => {
+                    Some(retry_after(response).unwrap_or_else(|| backoff(attempt)))
+                }
+                Err(e) if e.is_connect() || e.is_timeout() => Some(backoff(attempt)),
+                _ => None,
+            };
+
+            match (result, retry) {
+                (result, Some(delay)) if !last => {
+                    match &result {
+                        Ok(response) => tracing::debug!("{} returned {}, retrying in {:?}", host, response.status(), delay),
+                        Err(e) => tracing::debug!("Request to {} failed ({}), retrying in {:?}", host, e, delay),
+                    }
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                (Ok(response), None) => {
+                    return match cache {
+                        Some(cache) if response.status().is_success() => cache.store(&key, response).await,
+                        _ => Ok(response),
+                    };
+                }
+                (result, _) => {
+                    // The site is down or unreachable: fall back to what was seen before
+                    if let Some(cache) = cache.filter(|_| self.offline_fallback) {
+                        if let Some(entry) = cache.load(&key).await {
+                            tracing::info!("{} unavailable, serving a cached response", host);
+                            return Ok(entry.into_response("stale"));
+                        }
+                    }
+                    return result;
+                }
+            }
+        }
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Delay asked for by `Retry-After`, in seconds or as an HTTP date
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    let delay = match value.parse::<u64>() {
+        Ok(seconds) => Duration::from_secs(seconds),
+        Err(_) => {
+            let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+            (at.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().unwrap_or_default()
+        }
+    };
+    Some(delay.min(MAX_RETRY_DELAY))
+}
+
+/// Exponential backoff with ±50% jitter, so clients don't retry in lockstep
+fn backoff(attempt: u32) -> Duration {
+    let base = BACKOFF_BASE.saturating_mul(1 << attempt.min(16)).min(MAX_RETRY_DELAY);
+    let random = std::collections::hash_map::RandomState::new().build_hasher().finish();
+    let factor = 0.5 + (random % 1000) as f64 / 1000.0;
+    base.mul_f64(factor)
+}
+
+fn cache_key(request: &Request) -> String {
+    let mut hasher = Md5::new();
+    hasher.update(request.method().as_str());
+    hasher.update(request.url().as_str());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Whether `request` is made for an account, by header or query parameter
+fn has_credentials(request: &Request) -> bool {
+    request.headers().contains_key(AUTHORIZATION)
+        || request
+            .url()
+            .query_pairs()
+            .any(|(key, _)| CREDENTIAL_PARAMS.contains(&key.as_ref()))
+}
+
+struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration,
+    /// Age past which entries are deleted
+    max_age: Duration,
+    last_pruned: Mutex<Option<Instant>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheMeta {
+    url: String,
+    status: u16,
+    content_type: Option<String>,
+    /// Unix time the response was received
+    stored_at: i64,
+}
+
+struct CacheEntry {
+    meta: CacheMeta,
+    body: Vec<u8>,
+}
+
+impl CacheEntry {
+    fn into_response(self, cache_state: &'static str) -> Response {
+        let mut builder = http::Response::builder()
+            .status(self.meta.status)
+            .header(CACHE_HEADER, cache_state);
+        if let Some(content_type) = &self.meta.content_type {
+            builder = builder.header(CONTENT_TYPE, content_type);
+        }
+        builder
+            .body(self.body)
+            .map(Response::from)
+            .expect("cached status and headers were valid when stored")
+    }
+}
+
+impl ResponseCache {
+    fn paths(&self, key: &str) -> (PathBuf, PathBuf) {
+        (self.dir.join(format!("{}.json", key)), self.dir.join(format!("{}.body", key)))
+    }
+
+    fn is_fresh(&self, entry: &CacheEntry) -> bool {
+        let age = chrono::Utc::now().timestamp() - entry.meta.stored_at;
+        age >= 0 && (age as u64) < self.ttl.as_secs()
+    }
+
+    async fn load(&self, key: &str) -> Option<CacheEntry> {
+        let (meta_path, body_path) = self.paths(key);
+        let meta = serde_json::from_slice(&tokio::fs::read(meta_path).await.ok()?).ok()?;
+        let body = tokio::fs::read(body_path).await.ok()?;
+        Some(CacheEntry { meta, body })
+    }
+
+    /// Read `response` into the cache and hand back an equivalent response
+    async fn store(&self, key: &str, response: Response) -> reqwest::Result<Response> {
+        let status = response.status();
+        let headers = response.headers().clone();
+        let meta = CacheMeta {
+            url: response.url().to_string(),
+            status: status.as_u16(),
+            content_type: headers
+                .get(CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string()),
+            stored_at: chrono::Utc::now().timestamp(),
+        };
+        let body = response.bytes().await?;
+
+        if let Err(e) = self.write(key, &meta, &body).await {
+            tracing::warn!("Failed to cache {}: {}", meta.url, e);
+        }
+        self.prune_if_due().await;
+
+        let mut rebuilt = http::Response::builder().status(status);
+        if let Some(map) = rebuilt.headers_mut() {
+            *map = headers;
+            map.insert(CACHE_HEADER, HeaderValue::from_static("miss"));
+        }
+        Ok(rebuilt
+            .body(body)
+            .map(Response::from)
+            .expect("status and headers come from a valid response"))
+    }
+
+    /// Delete entries past `max_age`, on the first store and then at most
+    /// every [`PRUNE_INTERVAL`]
+    async fn prune_if_due(&self) {
+        {
+            let mut last_pruned = self.last_pruned.lock().unwrap();
+            if last_pruned.is_some_and(|at| at.elapsed() < PRUNE_INTERVAL) {
+                return;
+            }
+            *last_pruned = Some(Instant::now());
+        }
+        if let Err(e) = self.prune().await {
+            tracing::warn!("Failed to prune the response cache: {}", e);
+        }
+    }
+
+    async fn prune(&self) -> std::io::Result<()> {
+        let oldest = chrono::Utc::now().timestamp() - self.max_age.as_secs() as i64;
+        let mut entries = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let stored_at = tokio::fs::read(&path)
+                .await
+                .ok()
+                .and_then(|meta| serde_json::from_slice::<CacheMeta>(&meta).ok())
+                .map(|meta| meta.stored_at);
+            // Unreadable meta files may be in the middle of being written
+            if stored_at.is_some_and(|stored_at| stored_at < oldest) {
+                tokio::fs::remove_file(path.with_extension("body")).await.ok();
+                tokio::fs::remove_file(&path).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn write(&self, key: &str, meta: &CacheMeta, body: &[u8]) -> std::io::Result<()> {
+        let (meta_path, body_path) = self.paths(key);
+        tokio::fs::create_dir_all(&self.dir).await?;
+        // The body goes first so a readable meta file implies a complete entry
+        tokio::fs::write(&body_path, body).await?;
+        tokio::fs::write(&meta_path, serde_json::to_vec(meta)?).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn policy(config: NetworkConfig, cache_dir: Option<&Path>) -> HttpPolicy {
+        HttpPolicy::new(&config, cache_dir.map(|d| d.to_path_buf()))
+    }
+
+    async fn get(policy: &HttpPolicy, url: &str) -> reqwest::Result<Response> {
+        let client = reqwest::Client::new();
+        let request = client.get(url).build().unwrap();
+        policy.execute(&client, request).await
+    }
+
+    #[test]
+    fn test_site_limits_cover_subdomains() {
+        let mut config = NetworkConfig::default();
+        config.rate_limits.insert("gelbooru.com".to_string(), 2.0);
+        config.rate_limits.insert("danbooru.donmai.us".to_string(), 0.0);
+        let policy = policy(config, None);
+
+        assert_eq!(policy.interval("www.reddit.com"), Some(Duration::from_secs(6)));
+        assert_eq!(policy.interval("gelbooru.com"), Some(Duration::from_millis(500)));
+        assert_eq!(policy.interval("danbooru.donmai.us"), None);
+        assert_eq!(policy.interval("localhost"), None);
+    }
+
+    #[tokio::test]
+    async fn test_throttles_per_host() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let mut config = NetworkConfig::default();
+        config.rate_limits.insert("127.0.0.1".to_string(), 5.0);
+        let policy = policy(config, None);
+
+        let started = Instant::now();
+        for _ in 0..3 {
+            get(&policy, &server.uri()).await.unwrap();
+        }
+        assert!(started.elapsed() >= Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn test_retries_honor_retry_after() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "1"))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/flaky"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+            .mount(&server)
+            .await;
+
+        let policy = policy(NetworkConfig::default(), None);
+        let started = Instant::now();
+        let response = get(&policy, &format!("{}/page", server.uri())).await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "ok");
+        assert!(started.elapsed() >= Duration::from_secs(1));
+
+        // Without retries left the error response is returned
+        let policy = self::policy(NetworkConfig { max_retries: 0, ..NetworkConfig::default() }, None);
+        let response = get(&policy, &format!("{}/flaky", server.uri())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_cache_and_offline_fallback() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/posts.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("[1]", "application/json"))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+        let url = format!("{}/posts.json", server.uri());
+
+        let cached = policy(NetworkConfig::default(), Some(dir.path()));
+        let first = get(&cached, &url).await.unwrap();
+        assert_eq!(first.headers()[CACHE_HEADER], "miss");
+        assert_eq!(first.text().await.unwrap(), "[1]");
+
+        let second = get(&cached, &url).await.unwrap();
+        assert_eq!(second.headers()[CACHE_HEADER], "hit");
+        assert_eq!(second.headers()[CONTENT_TYPE], "application/json");
+        assert_eq!(second.text().await.unwrap(), "[1]");
+
+        // Expired, and the site is down: the old response is still served
+        let expired = policy(
+            NetworkConfig {
+                cache_ttl_secs: 0,
+                max_retries: 0,
+                ..NetworkConfig::default()
+            },
+            Some(dir.path()),
+        );
+        let offline = get(&expired, &url).await.unwrap();
+        assert_eq!(offline.headers()[CACHE_HEADER], "stale");
+        assert_eq!(offline.json::<Vec<u32>>().await.unwrap(), vec![1]);
+
+        let strict = policy(
+            NetworkConfig {
+                max_retries: 0,
+                offline_fallback: false,
+                cache_ttl_secs: 0,
+                ..NetworkConfig::default()
+            },
+            Some(dir.path()),
+        );
+        let response = get(&strict, &url).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_cache_skips_credentials_and_prunes() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("[]", "application/json"))
+            .mount(&server)
+            .await;
+
+        let cached = policy(NetworkConfig::default(), Some(dir.path()));
+        let cache = cached.cache.as_ref().unwrap();
+        let old = CacheMeta {
+            url: "https://example.com/old.json".to_string(),
+            status: 200,
+            content_type: None,
+            stored_at: chrono::Utc::now().timestamp() - 31 * 86400,
+        };
+        cache.write("old", &old, b"[]").await.unwrap();
+
+        let url = format!("{}/posts.json?tags=sky", server.uri());
+        get(&cached, &url).await.unwrap();
+        get(&cached, &format!("{}&login=alice&api_key=secret", url)).await.unwrap();
+        let client = reqwest::Client::new();
+        let request = client.get(&url).bearer_auth("token").build().unwrap();
+        let response = cached.execute(&client, request).await.unwrap();
+        assert!(response.headers().get(CACHE_HEADER).is_none());
+
+        let files: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 2);
+        let meta_path = files.iter().find(|path| path.extension().unwrap() == "json").unwrap();
+        let meta: CacheMeta = serde_json::from_slice(&std::fs::read(meta_path).unwrap()).unwrap();
+        assert_eq!(meta.url, url);
+    }
+}
@@ -0,0 +1,232 @@
+use crate::booru::{BooruClient, BooruConnector, SendWith, parse_error};
+use async_trait::async_trait;
+use serde::Deserialize;
+use wallmgr_core::error::Result;
+use wallmgr_core::types::{BooruImage, Rating};
+use std::collections::HashMap;
+
+/// Unsplash photos; requires an access key
+/// API: https://unsplash.com/documentation
+pub struct UnsplashConnector {
+    client: BooruClient,
+    name: String,
+    base_url: String,
+    access_key: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnsplashPhoto {
+    id: String,
+    width: u32,
+    height: u32,
+    urls: UnsplashUrls,
+    links: UnsplashLinks,
+    user: UnsplashUser,
+    #[serde(default)]
+    likes: Option<i32>,
+    #[serde(default)]
+    tags: Vec<UnsplashTag>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnsplashUrls {
+    full: String,
+    #[serde(default)]
+    regular: Option<String>,
+    #[serde(default)]
+    small: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnsplashLinks {
+    html: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnsplashUser {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnsplashTag {
+    title: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnsplashSearch {
+    results: Vec<UnsplashPhoto>,
+}
+
+impl UnsplashConnector {
+    pub fn new(access_key: &str) -> Self {
+        Self::with_base_url("unsplash", "https://api.unsplash.com", access_key)
+    }
+
+    pub fn with_base_url(name: &str, base_url: &str, access_key: &str) -> Self {
+        Self {
+            client: BooruClient::new(),
+            name: name.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            access_key: access_key.to_string(),
+        }
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T> {
+        let response = self.client.client()
+            .get(url)
+            .header("Accept-Version", "v1")
+            .header(reqwest::header::AUTHORIZATION, format!("Client-ID {}", self.access_key))
+            .fetch(&self.client, &self.name)
+            .await?;
+
+        response
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))
+    }
+
+    fn photo_to_image(&self, photo: UnsplashPhoto) -> BooruImage {
+        BooruImage {
+            id: photo.id,
+            source: self.name.clone(),
+            file_url: photo.urls.full,
+            preview_url: photo.urls.small,
+            sample_url: photo.urls.regular,
+            width: photo.width,
+            height: photo.height,
+            tags: photo.tags.iter().map(|t| t.title.replace(' ', "_")).collect(),
+            rating: Rating::Safe,
+            score: photo.likes,
+            attribution: Some(format!("Photo by {} on Unsplash", photo.user.name)),
+            tag_categories: HashMap::new(),
+            author: Some(photo.user.name),
+            is_nsfw: false,
+            md5: None,
+            file_size: None,
+            page_url: Some(photo.links.html),
+            origin_url: None,
+        }
+    }
+}
+
+#[async_trait]
+impl BooruConnector for UnsplashConnector {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn search(&self, tags: &[String], limit: u32, page: u32) -> Result<Vec<BooruImage>> {
+        let per_page = limit.min(30);
+        let page = page.max(1);
+
+        // Without a query, list the editorial feed
+        let photos = if tags.is_empty() {
+            self.get::<Vec<UnsplashPhoto>>(&format!(
+                "{}/photos?page={}&per_page={}",
+                self.base_url, page, per_page
+            ))
+            .await?
+        } else {
+            let query = tags.join(" ").replace('_', " ");
+            self.get::<UnsplashSearch>(&format!(
+                "{}/search/photos?query={}&page={}&per_page={}&orientation=landscape",
+                self.base_url,
+                urlencoding::encode(&query),
+                page,
+                per_page
+            ))
+            .await?
+            .results
+        };
+
+        Ok(photos.into_iter().map(|p| self.photo_to_image(p)).collect())
+    }
+
+    async fn autocomplete_tags(&self, _prefix: &str, _limit: u32) -> Result<Vec<String>> {
+        // The public API has no tag suggestions
+        Ok(Vec::new())
+    }
+
+    async fn get_image(&self, id: &str) -> Result<BooruImage> {
+        let photo: UnsplashPhoto = self
+            .get(&format!("{}/photos/{}", self.base_url, urlencoding::encode(id)))
+            .await?;
+        Ok(self.photo_to_image(photo))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wallmgr_core::error::Error;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const PHOTO: &str = r#"{
+        "id": "Dwu85P9SOIk", "width": 6000, "height": 4000, "likes": 321,
+        "description": null, "alt_description": "snowy mountain",
+        "urls": {"raw": "https://images.unsplash.com/photo-1?ixid=raw",
+                 "full": "https://images.unsplash.com/photo-1?ixid=full",
+                 "regular": "https://images.unsplash.com/photo-1?w=1080",
+                 "small": "https://images.unsplash.com/photo-1?w=400",
+                 "thumb": "https://images.unsplash.com/photo-1?w=200"},
+        "links": {"self": "https://api.unsplash.com/photos/Dwu85P9SOIk",
+                  "html": "https://unsplash.com/photos/Dwu85P9SOIk",
+                  "download_location": "https://api.unsplash.com/photos/Dwu85P9SOIk/download"},
+        "user": {"id": "u1", "username": "jdoe", "name": "Jane Doe"},
+        "tags": [{"type": "search", "title": "mountain range"}]
+    }"#;
+
+    #[tokio::test]
+    async fn test_search_and_get_image() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search/photos"))
+            .and(query_param("query", "snowy mountain"))
+            .and(query_param("page", "2"))
+            .and(query_param("per_page", "30"))
+            .and(header("authorization", "Client-ID key"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                r#"{{"total": 1, "total_pages": 1, "results": [{}]}}"#,
+                PHOTO
+            )))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/photos/Dwu85P9SOIk"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(PHOTO))
+            .mount(&server)
+            .await;
+
+        let connector = UnsplashConnector::with_base_url("unsplash", &server.uri(), "key");
+        let images = connector
+            .search(&["snowy_mountain".to_string()], 50, 2)
+            .await
+            .unwrap();
+
+        assert_eq!(images.len(), 1);
+        let image = &images[0];
+        assert_eq!(image.file_url, "https://images.unsplash.com/photo-1?ixid=full");
+        assert_eq!(image.author.as_deref(), Some("Jane Doe"));
+        assert_eq!(image.attribution.as_deref(), Some("Photo by Jane Doe on Unsplash"));
+        assert_eq!(image.page_url.as_deref(), Some("https://unsplash.com/photos/Dwu85P9SOIk"));
+        assert_eq!(image.tags, vec!["mountain_range"]);
+
+        assert_eq!(connector.get_image("Dwu85P9SOIk").await.unwrap().width, 6000);
+        assert!(matches!(connector.get_image("missing").await, Err(Error::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_feed_without_tags() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/photos"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!("[{}]", PHOTO)))
+            .mount(&server)
+            .await;
+
+        let connector = UnsplashConnector::with_base_url("unsplash", &server.uri(), "key");
+        assert_eq!(connector.search(&[], 10, 0).await.unwrap().len(), 1);
+    }
+}
@@ -1,16 +1,20 @@
-use crate::booru::{BooruClient, BooruConnector};
+use crate::booru::{BooruClient, BooruConnector, SendWith, parse_error, origin_url};
+use crate::query::{Order, Query, RatioRange, Translation};
 use wallmgr_core::error::{Error, Result};
 use wallmgr_core::types::{BooruImage, Rating};
 use async_trait::async_trait;
 use serde::Deserialize;
+use std::collections::HashMap;
 
 /// WallHaven connector - Premium wallpaper site
 /// API: https://wallhaven.cc/help/api
 /// Note: Requires API key for NSFW access
 pub struct WallHavenConnector {
     client: BooruClient,
+    name: String,
     base_url: String,
     api_key: Option<String>,
+    username: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -62,19 +66,29 @@ struct WallHavenMeta {
 
 impl WallHavenConnector {
     pub fn new() -> Self {
+        Self::with_base_url("wallhaven", "https://wallhaven.cc")
+    }
+
+    /// A Wallhaven instance at `base_url` (the site root, without `/api/v1`)
+    pub fn with_base_url(name: &str, base_url: &str) -> Self {
         Self {
             client: BooruClient::new(),
-            base_url: "https://wallhaven.cc/api/v1".to_string(),
+            name: name.to_string(),
+            base_url: format!("{}/api/v1", base_url.trim_end_matches('/')),
             api_key: None,
+            username: None,
         }
     }
-    
-    pub fn with_api_key(api_key: impl Into<String>) -> Self {
-        Self {
-            client: BooruClient::new(),
-            base_url: "https://wallhaven.cc/api/v1".to_string(),
-            api_key: Some(api_key.into()),
-        }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Account whose collections hold the favorites
+    pub fn with_username(mut self, username: impl Into<String>) -> Self {
+        self.username = Some(username.into());
+        self
     }
     
     fn data_to_image(&self, data: WallHavenData) -> BooruImage {
@@ -92,7 +106,7 @@ impl WallHavenConnector {
         
         BooruImage {
             id: data.id.clone(),
-            source: "wallhaven".to_string(),
+            source: self.name.clone(),
             file_url: data.path,
             preview_url: Some(data.thumbs.small),
             sample_url: Some(data.thumbs.large),
@@ -103,10 +117,44 @@ impl WallHavenConnector {
             score: Some(data.favorites as i32),
             author: None,
             is_nsfw,
+            md5: None,
+            file_size: Some(data.file_size),
+            page_url: None,
+            origin_url: data.source.as_deref().and_then(origin_url),
+            attribution: None,
+            tag_categories: HashMap::new(),
         }
     }
 }
 
+/// Aspect ratios Wallhaven can search for
+const RATIOS: [(&str, f64); 12] = [
+    ("16x9", 16.0 / 9.0),
+    ("16x10", 1.6),
+    ("21x9", 21.0 / 9.0),
+    ("32x9", 32.0 / 9.0),
+    ("48x9", 48.0 / 9.0),
+    ("9x16", 9.0 / 16.0),
+    ("10x16", 0.625),
+    ("9x18", 0.5),
+    ("1x1", 1.0),
+    ("3x2", 1.5),
+    ("4x3", 4.0 / 3.0),
+    ("5x4", 1.25),
+];
+
+/// The `ratios` value for exactly `ratio`, if Wallhaven has one
+fn ratios_param(ratio: RatioRange) -> Option<&'static str> {
+    if ratio.as_exact().is_some() {
+        return RATIOS.iter().find(|(_, known)| ratio.contains(*known)).map(|(name, _)| *name);
+    }
+    match (ratio.min, ratio.max) {
+        (min, max) if max.is_infinite() && (1.0..1.1).contains(&min) => Some("landscape"),
+        (min, max) if min <= 0.0 && (0.9..1.0).contains(&max) => Some("portrait"),
+        _ => None,
+    }
+}
+
 impl Default for WallHavenConnector {
     fn default() -> Self {
         Self::new()
@@ -116,54 +164,90 @@ impl Default for WallHavenConnector {
 #[async_trait]
 impl BooruConnector for WallHavenConnector {
     fn name(&self) -> &str {
-        "wallhaven"
+        &self.name
+    }
+
+    /// Tags go into `q`; ratings, size, ratio and order become the
+    /// `purity:`, `atleast:`, `ratios:` and `sorting:` terms that
+    /// [`search`](Self::search) turns into parameters
+    fn translate(&self, query: &Query) -> Translation {
+        Translation::new(query)
+            .tags()
+            .excluded("-")
+            .rating(|ratings| {
+                let bits: String = [Rating::Safe, Rating::Questionable, Rating::Explicit]
+                    .iter()
+                    .map(|rating| if ratings.contains(rating) { '1' } else { '0' })
+                    .collect();
+                Some(vec![format!("purity:{}", bits)])
+            })
+            .size(|width, height| {
+                Some(vec![format!("atleast:{}x{}", width.unwrap_or(1), height.unwrap_or(1))])
+            })
+            .ratio(|ratio| Some(vec![format!("ratios:{}", ratios_param(ratio)?)]))
+            .order(|order| {
+                Some(vec![format!("sorting:{}", match order {
+                    Order::Score => "toplist",
+                    Order::Date => "date_added",
+                    Order::Random => "random",
+                    Order::Favorites => "favorites",
+                })])
+            })
     }
     
     async fn search(&self, tags: &[String], limit: u32, page: u32) -> Result<Vec<BooruImage>> {
-        // WallHaven uses "q" parameter for search query
-        let query = tags.join(" ");
-        
+        // NSFW needs an API key
+        let mut purity = match self.api_key {
+            Some(_) => "111".to_string(),
+            None => "100".to_string(),
+        };
+        let mut sorting = "relevance".to_string();
+        let mut ratios = "landscape".to_string();
+        let mut atleast = None;
+
+        // Search parameters may be given as terms; the rest is the "q" query
+        let mut terms = Vec::new();
+        for tag in tags {
+            match tag.split_once(':') {
+                Some(("purity", value)) => purity = value.to_string(),
+                Some(("sorting", value)) => sorting = value.to_string(),
+                Some(("ratios", value)) => ratios = value.to_string(),
+                Some(("atleast", value)) => atleast = Some(value.to_string()),
+                _ => terms.push(tag.as_str()),
+            }
+        }
+        if self.api_key.is_none() && purity.len() == 3 {
+            purity.replace_range(2.., "0");
+        }
+        if purity == "000" {
+            return Ok(Vec::new());
+        }
+
         let mut url = format!(
-            "{}/search?q={}&page={}",
+            "{}/search?q={}&page={}&purity={}&categories=111&sorting={}&ratios={}",
             self.base_url,
-            urlencoding::encode(&query),
-            page
+            urlencoding::encode(&terms.join(" ")),
+            page,
+            purity,
+            urlencoding::encode(&sorting),
+            urlencoding::encode(&ratios)
         );
-        
-        // Add API key if available (required for NSFW)
+        if let Some(atleast) = atleast {
+            url.push_str(&format!("&atleast={}", urlencoding::encode(&atleast)));
+        }
         if let Some(key) = &self.api_key {
             url.push_str(&format!("&apikey={}", key));
-            url.push_str("&purity=111"); // sfw+sketchy+nsfw
-        } else {
-            url.push_str("&purity=100"); // sfw only without API key
         }
         
-        // Add categories (general, anime, people)
-        url.push_str("&categories=111");
-        
-        // Sorting options: date_added, relevance, random, views, favorites
-        url.push_str("&sorting=relevance");
-        
-        // Aspect ratios: landscape, portrait
-        url.push_str("&ratios=landscape");
-        
         let response = self.client.client()
             .get(&url)
-            .send()
-            .await
-            .map_err(|e| Error::Network(e.to_string()))?;
-        
-        if !response.status().is_success() {
-            return Err(Error::Api(format!(
-                "WallHaven API error: {}",
-                response.status()
-            )));
-        }
+            .fetch(&self.client, &self.name)
+            .await?;
         
         let wallhaven_response: WallHavenResponse = response
             .json()
             .await
-            .map_err(|e| Error::Parse(e.to_string()))?;
+            .map_err(|e| parse_error(&self.name, e))?;
         
         let images: Vec<BooruImage> = wallhaven_response.data
             .into_iter()
@@ -198,16 +282,8 @@ impl BooruConnector for WallHavenConnector {
         
         let response = self.client.client()
             .get(&url)
-            .send()
-            .await
-            .map_err(|e| Error::Network(e.to_string()))?;
-        
-        if !response.status().is_success() {
-            return Err(Error::NotFound(format!(
-                "WallHaven image {} not found",
-                id
-            )));
-        }
+            .fetch(&self.client, &self.name)
+            .await?;
         
         #[derive(Deserialize)]
         struct SingleResponse {
@@ -217,38 +293,110 @@ impl BooruConnector for WallHavenConnector {
         let single: SingleResponse = response
             .json()
             .await
-            .map_err(|e| Error::Parse(e.to_string()))?;
+            .map_err(|e| parse_error(&self.name, e))?;
         
         Ok(self.data_to_image(single.data))
     }
+
+    /// Wallpapers in the account's first collection, where favorites go by default
+    async fn favorites(&self, limit: u32, page: u32) -> Result<Vec<BooruImage>> {
+        let (Some(key), Some(username)) = (&self.api_key, &self.username) else {
+            return Err(Error::Config(format!(
+                "{} favorites need a username and API key",
+                self.name
+            )));
+        };
+
+        #[derive(Deserialize)]
+        struct Collection {
+            id: u64,
+        }
+        #[derive(Deserialize)]
+        struct Collections {
+            data: Vec<Collection>,
+        }
+
+        let url = format!("{}/collections?apikey={}", self.base_url, key);
+        let response = self.client.client()
+            .get(&url)
+            .fetch(&self.client, &self.name)
+            .await?;
+        let collections: Collections = response
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))?;
+        let Some(collection) = collections.data.first() else {
+            return Ok(Vec::new());
+        };
+
+        let url = format!(
+            "{}/collections/{}/{}?apikey={}&page={}",
+            self.base_url,
+            urlencoding::encode(username),
+            collection.id,
+            key,
+            page.max(1)
+        );
+        let response = self.client.client()
+            .get(&url)
+            .fetch(&self.client, &self.name)
+            .await?;
+        let wallhaven_response: WallHavenResponse = response
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))?;
+
+        Ok(wallhaven_response.data
+            .into_iter()
+            .take(limit as usize)
+            .map(|d| self.data_to_image(d))
+            .collect())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
     #[tokio::test]
     async fn test_wallhaven_search() {
-        let connector = WallHavenConnector::new();
-        
-        // Search for landscape nature wallpapers
-        let results = connector.search(
-            &["nature".to_string()],
-            10,
-            1
-        ).await;
-        
-        assert!(results.is_ok());
-        let images = results.unwrap();
-        assert!(!images.is_empty());
-        
-        // All should be landscape
-        for img in images {
-            let aspect_ratio = img.width as f64 / img.height as f64;
-            assert!(aspect_ratio > 1.0, "Should be landscape orientation");
-        }
+        let server = MockServer::start().await;
+        // Landscape and safe wallpapers unless asked otherwise
+        Mock::given(method("GET"))
+            .and(path("/api/v1/search"))
+            .and(query_param("q", "nature"))
+            .and(query_param("page", "1"))
+            .and(query_param("purity", "100"))
+            .and(query_param("sorting", "relevance"))
+            .and(query_param("ratios", "landscape"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r##"{"data": [{"id": "zy8o2v", "url": "https://wallhaven.cc/w/zy8o2v",
+                    "short_url": "https://whvn.cc/zy8o2v", "views": 120, "favorites": 9,
+                    "source": "", "purity": "sfw", "category": "general",
+                    "dimension_x": 2560, "dimension_y": 1440, "resolution": "2560x1440",
+                    "ratio": "1.78", "file_size": 3000, "file_type": "image/jpeg",
+                    "created_at": "2024-03-01 00:00:00", "colors": ["#336600"],
+                    "path": "https://w.wallhaven.cc/full/zy/wallhaven-zy8o2v.jpg",
+                    "thumbs": {"large": "https://th.wallhaven.cc/lg/zy/zy8o2v.jpg",
+                               "original": "https://th.wallhaven.cc/orig/zy/zy8o2v.jpg",
+                               "small": "https://th.wallhaven.cc/small/zy/zy8o2v.jpg"}}],
+                    "meta": {"current_page": 1, "last_page": 1, "per_page": 24, "total": 1}}"##,
+            ))
+            .mount(&server)
+            .await;
+
+        let connector = WallHavenConnector::with_base_url("wallhaven", &server.uri());
+        let images = connector.search(&["nature".to_string()], 10, 1).await.unwrap();
+
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].id, "zy8o2v");
+        assert_eq!(images[0].file_url, "https://w.wallhaven.cc/full/zy/wallhaven-zy8o2v.jpg");
+        assert_eq!((images[0].width, images[0].height), (2560, 1440));
+        assert!(!images[0].is_nsfw);
     }
-    
+
     #[tokio::test]
     async fn test_wallhaven_autocomplete() {
         let connector = WallHavenConnector::new();
@@ -259,4 +407,73 @@ mod tests {
         let tag_list = tags.unwrap();
         assert!(tag_list.contains(&"landscape".to_string()));
     }
+
+    #[tokio::test]
+    async fn test_favorites_from_first_collection() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/collections"))
+            .and(query_param("apikey", "key"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"data": [{"id": 15, "label": "Default", "views": 2, "public": 1, "count": 1},
+                             {"id": 16, "label": "Other", "views": 0, "public": 0, "count": 5}]}"#,
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/collections/bob/15"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r##"{"data": [{"id": "94x38z", "url": "https://wallhaven.cc/w/94x38z",
+                    "short_url": "https://whvn.cc/94x38z", "views": 10, "favorites": 4,
+                    "source": "", "purity": "sketchy", "category": "anime",
+                    "dimension_x": 3840, "dimension_y": 2160, "resolution": "3840x2160",
+                    "ratio": "1.78", "file_size": 1000, "file_type": "image/png",
+                    "created_at": "2024-01-01 00:00:00", "colors": ["#000000"],
+                    "path": "https://w.wallhaven.cc/full/94/wallhaven-94x38z.png",
+                    "thumbs": {"large": "https://th.wallhaven.cc/lg/94/94x38z.jpg",
+                               "original": "https://th.wallhaven.cc/orig/94/94x38z.jpg",
+                               "small": "https://th.wallhaven.cc/small/94/94x38z.jpg"}}],
+                    "meta": {"current_page": 2, "last_page": 2, "per_page": 24, "total": 25}}"##,
+            ))
+            .mount(&server)
+            .await;
+
+        let connector = WallHavenConnector::with_base_url("wallhaven", &server.uri());
+        assert!(matches!(connector.favorites(24, 1).await, Err(Error::Config(_))));
+
+        let connector = connector.with_api_key("key").with_username("bob");
+        let images = connector.favorites(24, 2).await.unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].id, "94x38z");
+        assert!(images[0].is_nsfw);
+    }
+
+    #[tokio::test]
+    async fn test_search_query_sets_parameters() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/search"))
+            .and(query_param("q", "forest -city"))
+            .and(query_param("purity", "110"))
+            .and(query_param("sorting", "toplist"))
+            .and(query_param("ratios", "16x9"))
+            .and(query_param("atleast", "2560x1440"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"data": [], "meta": {"current_page": 1, "last_page": 1, "per_page": 24, "total": 0}}"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let connector = WallHavenConnector::with_base_url("wallhaven", &server.uri()).with_api_key("key");
+        let query = Query::parse("forest -city -rating:e res:qhd ratio:16:9 order:score").unwrap();
+        let translation = connector.translate(&query);
+        assert!(translation.residual.is_empty());
+        assert!(connector.search_query(&query, 24, 1).await.unwrap().images.is_empty());
+
+        // Without a key only safe wallpapers can be searched
+        let connector = WallHavenConnector::with_base_url("wallhaven", &server.uri());
+        let query = Query::parse("rating:e").unwrap();
+        assert!(connector.search_query(&query, 24, 1).await.unwrap().images.is_empty());
+    }
 }
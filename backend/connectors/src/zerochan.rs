@@ -0,0 +1,328 @@
+use crate::booru::{BooruClient, BooruConnector, SendWith, parse_rating, parse_error, request_error, status_error, origin_url};
+use async_trait::async_trait;
+use serde::Deserialize;
+use wallmgr_core::error::{Error, Result};
+use wallmgr_core::types::BooruImage;
+use std::collections::HashMap;
+
+/// Zerochan connector
+/// API: https://www.zerochan.net/api
+///
+/// Zerochan tags are title-cased and contain spaces ("Hatsune Miku"); they
+/// are exposed with underscores like booru tags and converted back in queries.
+pub struct ZerochanConnector {
+    client: BooruClient,
+    name: String,
+    base_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ZerochanItem {
+    id: u64,
+    #[serde(default)]
+    width: Option<u32>,
+    #[serde(default)]
+    height: Option<u32>,
+    #[serde(default)]
+    thumbnail: Option<String>,
+    #[serde(default)]
+    small: Option<String>,
+    #[serde(default)]
+    medium: Option<String>,
+    #[serde(default)]
+    large: Option<String>,
+    #[serde(default)]
+    full: Option<String>,
+    /// Primary tag only, in listings
+    #[serde(default)]
+    tag: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    hash: Option<String>,
+    #[serde(default)]
+    size: Option<u64>,
+    #[serde(default)]
+    fav: Option<i32>,
+    /// Free-form, often a link to the original
+    #[serde(default)]
+    source: Option<String>,
+}
+
+/// Listings are `{"items": [...]}`; older responses are a bare array
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ZerochanListing {
+    Items { items: Vec<ZerochanItem> },
+    Bare(Vec<ZerochanItem>),
+}
+
+impl ZerochanConnector {
+    pub fn new() -> Self {
+        Self::with_base_url("zerochan", "https://www.zerochan.net")
+    }
+
+    pub fn with_base_url(name: &str, base_url: &str) -> Self {
+        Self {
+            client: BooruClient::new(),
+            name: name.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn item_to_image(&self, item: ZerochanItem) -> Option<BooruImage> {
+        let file_url = item
+            .full
+            .clone()
+            .or_else(|| item.thumbnail.as_deref().and_then(full_from_thumbnail))
+            .or_else(|| item.large.clone())?;
+
+        let mut tags: Vec<String> = if item.tags.is_empty() {
+            item.tag.iter().cloned().collect()
+        } else {
+            item.tags
+        };
+        tags.iter_mut().for_each(|t| *t = to_tag(t));
+
+        Some(BooruImage {
+            id: item.id.to_string(),
+            source: self.name.clone(),
+            file_url,
+            preview_url: item.thumbnail.or(item.small),
+            sample_url: item.large.or(item.medium),
+            width: item.width.unwrap_or(0),
+            height: item.height.unwrap_or(0),
+            tags,
+            // Zerochan doesn't host explicit content
+            rating: parse_rating("safe"),
+            score: item.fav,
+            author: None,
+            is_nsfw: false,
+            md5: item.hash,
+            file_size: item.size,
+            page_url: None,
+            origin_url: item.source.as_deref().and_then(origin_url),
+            attribution: None,
+            tag_categories: HashMap::new(),
+        })
+    }
+}
+
+impl Default for ZerochanConnector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl BooruConnector for ZerochanConnector {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn search(&self, tags: &[String], limit: u32, page: u32) -> Result<Vec<BooruImage>> {
+        let path = tags
+            .iter()
+            .map(|t| urlencoding::encode(&from_tag(t)).into_owned())
+            .collect::<Vec<_>>()
+            .join(",");
+        let url = format!(
+            "{}/{}?json&p={}&l={}",
+            self.base_url,
+            path,
+            page.max(1),
+            limit.min(250)
+        );
+
+        let response = self.client.client()
+            .get(&url)
+            .send_with(&self.client)
+            .await
+            .map_err(|e| request_error(&self.name, e))?;
+
+        // Unknown tags are a 404 rather than an empty listing
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(Vec::new());
+        }
+        if !response.status().is_success() {
+            return Err(status_error(&self.name, response.status()));
+        }
+
+        let listing: ZerochanListing = response
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))?;
+        let items = match listing {
+            ZerochanListing::Items { items } | ZerochanListing::Bare(items) => items,
+        };
+
+        Ok(items.into_iter().filter_map(|i| self.item_to_image(i)).collect())
+    }
+
+    async fn autocomplete_tags(&self, prefix: &str, limit: u32) -> Result<Vec<String>> {
+        let url = format!(
+            "{}/suggest?json&q={}&limit={}",
+            self.base_url,
+            urlencoding::encode(&from_tag(prefix)),
+            limit.min(20)
+        );
+
+        let response = self.client.client()
+            .get(&url)
+            .send_with(&self.client)
+            .await
+            .map_err(|e| request_error(&self.name, e))?;
+
+        if !response.status().is_success() {
+            return Ok(Vec::new());
+        }
+
+        #[derive(Deserialize)]
+        struct Suggestions {
+            #[serde(default)]
+            suggestions: Vec<Suggestion>,
+        }
+
+        #[derive(Deserialize)]
+        struct Suggestion {
+            value: String,
+        }
+
+        let suggestions: Suggestions = response
+            .json()
+            .await
+            .unwrap_or(Suggestions { suggestions: Vec::new() });
+
+        Ok(suggestions
+            .suggestions
+            .into_iter()
+            .take(limit as usize)
+            .map(|s| to_tag(&s.value))
+            .collect())
+    }
+
+    async fn get_image(&self, id: &str) -> Result<BooruImage> {
+        let url = format!("{}/{}?json", self.base_url, urlencoding::encode(id));
+
+        let response = self.client.client()
+            .get(&url)
+            .fetch(&self.client, &self.name)
+            .await?;
+
+        let item: ZerochanItem = response
+            .json()
+            .await
+            .map_err(|e| parse_error(&self.name, e))?;
+
+        self.item_to_image(item)
+            .ok_or_else(|| Error::NotFound("No file URL found".to_string()))
+    }
+}
+
+/// "Hatsune Miku" -> "hatsune_miku"
+fn to_tag(name: &str) -> String {
+    name.trim().to_lowercase().replace(' ', "_")
+}
+
+/// "hatsune_miku" -> "hatsune miku" (Zerochan matches tags case-insensitively)
+fn from_tag(tag: &str) -> String {
+    tag.trim().replace('_', " ")
+}
+
+/// Listings only link thumbnails, e.g. `https://s1.zerochan.net/Name.600.123.jpg`;
+/// the original is `https://static.zerochan.net/Name.full.123.jpg`
+fn full_from_thumbnail(thumbnail: &str) -> Option<String> {
+    let file = thumbnail.rsplit('/').next()?;
+    let mut parts: Vec<&str> = file.split('.').collect();
+    if parts.len() < 4 || !parts[parts.len() - 3].chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let size = parts.len() - 3;
+    parts[size] = "full";
+    Some(format!("https://static.zerochan.net/{}", parts.join(".")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const LISTING: &str = r#"{
+        "items": [
+            {"id": 3906211, "width": 2000, "height": 1200,
+             "thumbnail": "https://s1.zerochan.net/Hatsune.Miku.600.3906211.jpg",
+             "source": "", "key": "Hatsune Miku", "tag": "Hatsune Miku",
+             "tags": ["Hatsune Miku", "VOCALOID", "Twin Tails"], "fav": 12},
+            {"id": 1, "tag": "Broken"}
+        ]
+    }"#;
+
+    const POST: &str = r#"{
+        "id": 3906211, "small": "https://s1.zerochan.net/Hatsune.Miku.240.3906211.jpg",
+        "medium": "https://s1.zerochan.net/Hatsune.Miku.600.3906211.jpg",
+        "large": "https://s1.zerochan.net/Hatsune.Miku.1200.3906211.jpg",
+        "full": "https://static.zerochan.net/Hatsune.Miku.full.3906211.png",
+        "width": 2000, "height": 1200, "size": 3456789, "hash": "0123abcd",
+        "source": "", "primary": "Hatsune Miku", "tags": ["Hatsune Miku", "VOCALOID"]
+    }"#;
+
+    #[tokio::test]
+    async fn test_search() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/hatsune%20miku,twin%20tails"))
+            .and(query_param("p", "2"))
+            .and(query_param("l", "10"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(LISTING))
+            .mount(&server)
+            .await;
+
+        let connector = ZerochanConnector::with_base_url("zerochan", &server.uri());
+        let images = connector
+            .search(&["hatsune_miku".to_string(), "twin_tails".to_string()], 10, 2)
+            .await
+            .unwrap();
+
+        assert_eq!(images.len(), 1);
+        let image = &images[0];
+        assert_eq!(image.id, "3906211");
+        assert_eq!(image.file_url, "https://static.zerochan.net/Hatsune.Miku.full.3906211.jpg");
+        assert_eq!(image.tags, vec!["hatsune_miku", "vocaloid", "twin_tails"]);
+        assert_eq!((image.width, image.height), (2000, 1200));
+        assert!(!image.is_nsfw);
+
+        // Unknown tags are not an error
+        assert!(connector.search(&["nothing".to_string()], 10, 1).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_image_and_autocomplete() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/3906211"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(POST))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/suggest"))
+            .and(query_param("q", "hatsune"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"suggestions": [{"value": "Hatsune Miku", "type": "Character", "total": 20000}]}"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let connector = ZerochanConnector::with_base_url("zerochan", &server.uri());
+        let image = connector.get_image("3906211").await.unwrap();
+        assert_eq!(image.file_url, "https://static.zerochan.net/Hatsune.Miku.full.3906211.png");
+        assert_eq!(image.sample_url.as_deref(), Some("https://s1.zerochan.net/Hatsune.Miku.1200.3906211.jpg"));
+        assert_eq!(image.md5.as_deref(), Some("0123abcd"));
+        assert_eq!(image.file_size, Some(3456789));
+
+        assert_eq!(
+            connector.autocomplete_tags("hatsune", 5).await.unwrap(),
+            vec!["hatsune_miku"]
+        );
+    }
+}
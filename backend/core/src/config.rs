@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use crate::error::{Error, Result};
+use crate::types::Rating;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -11,6 +13,18 @@ pub struct Config {
     pub api: ApiConfig,
     pub renderer: RendererConfig,
     pub sources: SourcesConfig,
+    #[serde(default)]
+    pub library: LibraryConfig,
+    #[serde(default)]
+    pub downloads: DownloadsConfig,
+    #[serde(default)]
+    pub daily: DailyConfig,
+    #[serde(default)]
+    pub favorites: FavoritesConfig,
+    #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(default)]
+    pub policy: PolicyConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +39,18 @@ pub struct RendererConfig {
     pub video_fps: u32,
     pub hardware_accel: bool,
     pub mpv_options: Vec<String>,
+    /// Path to `libLive2DCubismCore.so` (searched in standard locations if unset)
+    #[serde(default)]
+    pub live2d_core_path: Option<PathBuf>,
+    /// Make Live2D models look at the mouse pointer
+    #[serde(default)]
+    pub live2d_follow_mouse: bool,
+    /// Browser for web wallpapers (surf or Chromium-family; searched on PATH if unset)
+    #[serde(default)]
+    pub web_browser: Option<PathBuf>,
+    /// Block network access from web wallpapers except their own files
+    #[serde(default)]
+    pub web_offline: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +61,279 @@ pub struct SourcesConfig {
     pub enable_gelbooru: bool,
     pub enable_pixiv: bool,
     pub pixiv_refresh_token: Option<String>,
+    /// Additional sites, or overrides of the built-in ones by name
+    #[serde(default)]
+    pub connectors: Vec<ConnectorConfig>,
+}
+
+/// API family a site speaks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectorEngine {
+    Danbooru2,
+    Moebooru,
+    Gelbooru02,
+    Wallhaven,
+    Zerochan,
+    AnimePictures,
+    E621,
+    Sankaku,
+    Szurubooru,
+    Shimmie2,
+    Unsplash,
+    Pexels,
+    /// A subreddit, e.g. `https://www.reddit.com/r/wallpapers`
+    Reddit,
+    /// Bing's image of the day
+    Bing,
+    /// NASA's Astronomy Picture of the Day
+    Apod,
+    Pixiv,
+}
+
+/// One booru or wallpaper site
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectorConfig {
+    /// Source name used in searches and stored with downloads
+    pub name: String,
+    pub engine: ConnectorEngine,
+    pub base_url: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Login name (Danbooru, Moebooru, e621, Szurubooru, Wallhaven) or user ID
+    /// (Gelbooru); favorites sync tags images with it
+    #[serde(default)]
+    pub username: Option<String>,
+    /// API key (Danbooru, Gelbooru, Wallhaven, e621, Pexels, NASA), password
+    /// hash (Moebooru), login token (Szurubooru), access token (Sankaku),
+    /// access key (Unsplash) or OAuth refresh token (Pixiv)
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Read the API key from the system keyring instead (see `wallmgr login`)
+    #[serde(default)]
+    pub keyring: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl ConnectorConfig {
+    pub fn new(name: &str, engine: ConnectorEngine, base_url: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            engine,
+            base_url: base_url.to_string(),
+            enabled: true,
+            username: None,
+            api_key: None,
+            keyring: false,
+        }
+    }
+
+    /// The API key, from the keyring if configured so
+    pub fn secret(&self) -> Result<Option<String>> {
+        if !self.keyring {
+            return Ok(self.api_key.clone());
+        }
+        crate::keyring::lookup(&self.name)?
+            .map(Some)
+            .ok_or_else(|| Error::Config(format!("No key for {} in the keyring", self.name)))
+    }
+
+    /// Whether requests to this source are made as a user
+    pub fn authenticated(&self) -> bool {
+        self.keyring || self.api_key.is_some()
+    }
+}
+
+impl SourcesConfig {
+    /// The built-in sites with `connectors` applied on top: entries replace
+    /// built-ins of the same name, other entries are appended
+    pub fn connectors(&self) -> Vec<ConnectorConfig> {
+        let builtin = [
+            ("danbooru", ConnectorEngine::Danbooru2, "https://danbooru.donmai.us", self.enable_danbooru),
+            ("yandere", ConnectorEngine::Moebooru, "https://yande.re", self.enable_yandere),
+            ("konachan", ConnectorEngine::Moebooru, "https://konachan.net", true),
+            ("safebooru", ConnectorEngine::Gelbooru02, "https://safebooru.org", self.enable_safebooru),
+            ("gelbooru", ConnectorEngine::Gelbooru02, "https://gelbooru.com", self.enable_gelbooru),
+            ("wallhaven", ConnectorEngine::Wallhaven, "https://wallhaven.cc", true),
+            ("zerochan", ConnectorEngine::Zerochan, "https://www.zerochan.net", true),
+            ("anime_pictures", ConnectorEngine::AnimePictures, "https://api.anime-pictures.net", true),
+            ("e926", ConnectorEngine::E621, "https://e926.net", true),
+            ("reddit", ConnectorEngine::Reddit, "https://www.reddit.com/r/wallpapers", true),
+            ("bing", ConnectorEngine::Bing, "https://www.bing.com", true),
+            ("apod", ConnectorEngine::Apod, "https://api.nasa.gov", true),
+            // These need an API key
+            ("unsplash", ConnectorEngine::Unsplash, "https://api.unsplash.com", false),
+            ("pexels", ConnectorEngine::Pexels, "https://api.pexels.com", false),
+            ("pixiv", ConnectorEngine::Pixiv, "https://app-api.pixiv.net", self.enable_pixiv),
+            // Adult sites are opt-in
+            ("e621", ConnectorEngine::E621, "https://e621.net", false),
+            ("rule34", ConnectorEngine::Gelbooru02, "https://api.rule34.xxx", false),
+            ("sankaku", ConnectorEngine::Sankaku, "https://capi-v2.sankakucomplex.com", false),
+        ];
+
+        let mut connectors: Vec<ConnectorConfig> = builtin
+            .into_iter()
+            .map(|(name, engine, base_url, enabled)| ConnectorConfig {
+                enabled,
+                ..ConnectorConfig::new(name, engine, base_url)
+            })
+            .collect();
+        if let Some(pixiv) = connectors.iter_mut().find(|c| c.name == "pixiv") {
+            pixiv.api_key = self.pixiv_refresh_token.clone();
+        }
+
+        for custom in &self.connectors {
+            match connectors.iter_mut().find(|c| c.name == custom.name) {
+                Some(existing) => *existing = custom.clone(),
+                None => connectors.push(custom.clone()),
+            }
+        }
+        connectors
+    }
+}
+
+/// Where downloaded images are stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryConfig {
+    pub dir: PathBuf,
+    /// Path below `dir` for each download. Placeholders: `{source}`, `{id}`,
+    /// `{md5}`, `{rating}`, `{artist}`, `{year}`, `{month}`, `{ext}`
+    pub layout: String,
+}
+
+impl Default for LibraryConfig {
+    fn default() -> Self {
+        Self {
+            dir: dirs::picture_dir()
+                .or_else(dirs::data_local_dir)
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join("wallmgr"),
+            layout: "{source}/{id}.{ext}".to_string(),
+        }
+    }
+}
+
+/// Download queue behaviour
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloadsConfig {
+    /// Downloads running at once against the same host
+    pub per_host: usize,
+    /// Attempts before a job is marked failed
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled on each further attempt
+    pub retry_delay_secs: u64,
+}
+
+impl Default for DownloadsConfig {
+    fn default() -> Self {
+        Self {
+            per_host: 2,
+            max_attempts: 5,
+            retry_delay_secs: 30,
+        }
+    }
+}
+
+/// Fetch a new wallpaper from a source once a day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DailyConfig {
+    pub enabled: bool,
+    /// Source name; the newest matching image is used
+    pub source: String,
+    pub tags: Vec<String>,
+    /// Set the image as wallpaper once downloaded, not just add it to the library
+    pub apply: bool,
+    /// Monitor to set it on (all if unset)
+    pub monitor: Option<String>,
+}
+
+impl Default for DailyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            source: "bing".to_string(),
+            tags: Vec::new(),
+            apply: true,
+            monitor: None,
+        }
+    }
+}
+
+/// HTTP behaviour of source connectors
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// Seconds API responses are served from the on-disk cache (0 disables it)
+    pub cache_ttl_secs: u64,
+    /// Serve expired cached responses when a source can't be reached
+    pub offline_fallback: bool,
+    /// Days cached responses are kept for offline use before being deleted
+    pub cache_max_age_days: u64,
+    /// Retries after 429 and 5xx responses or failed connections
+    pub max_retries: u32,
+    /// Requests per second by host, overriding the built-in limits
+    pub rate_limits: HashMap<String, f64>,
+    /// Seconds each source gets to answer a search of several sources
+    pub search_timeout_secs: u64,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            cache_ttl_secs: 600,
+            offline_fallback: true,
+            cache_max_age_days: 30,
+            max_retries: 3,
+            rate_limits: HashMap::new(),
+            search_timeout_secs: 15,
+        }
+    }
+}
+
+/// Mirror the favorites of source accounts into the library
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FavoritesConfig {
+    pub enabled: bool,
+    /// Minutes between syncs
+    pub interval_minutes: u64,
+    /// Sources to sync (every authenticated source if empty)
+    pub sources: Vec<String>,
+}
+
+impl Default for FavoritesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_minutes: 360,
+            sources: Vec::new(),
+        }
+    }
+}
+
+/// What may be searched, downloaded and set as wallpaper; see
+/// [`ContentPolicy`](crate::policy::ContentPolicy)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PolicyConfig {
+    /// Only safe images, whatever else is configured
+    pub work_mode: bool,
+    /// Images with any of these tags are blocked; `*` matches anything
+    pub blacklist: Vec<String>,
+    /// Images need at least one of these tags, if any are given
+    pub whitelist: Vec<String>,
+    /// Highest rating allowed from any source
+    pub max_rating: Option<Rating>,
+    /// Highest rating allowed by source name
+    pub source_ratings: HashMap<String, Rating>,
+    /// Lowest score, or favorites count on sites that report those instead;
+    /// images without one pass
+    pub min_score: Option<i32>,
 }
 
 impl Default for Config {
@@ -58,6 +357,12 @@ impl Default for Config {
             api: ApiConfig::default(),
             renderer: RendererConfig::default(),
             sources: SourcesConfig::default(),
+            library: LibraryConfig::default(),
+            downloads: DownloadsConfig::default(),
+            daily: DailyConfig::default(),
+            favorites: FavoritesConfig::default(),
+            network: NetworkConfig::default(),
+            policy: PolicyConfig::default(),
         }
     }
 }
@@ -82,6 +387,10 @@ impl Default for RendererConfig {
                 "--no-audio".to_string(),
                 "--hwdec=auto".to_string(),
             ],
+            live2d_core_path: None,
+            live2d_follow_mouse: false,
+            web_browser: None,
+            web_offline: false,
         }
     }
 }
@@ -95,6 +404,7 @@ impl Default for SourcesConfig {
             enable_gelbooru: true,
             enable_pixiv: false,
             pixiv_refresh_token: None,
+            connectors: Vec::new(),
         }
     }
 }
@@ -105,7 +415,7 @@ impl Config {
             .ok_or_else(|| Error::Config("Cannot find config directory".to_string()))?
             .join("wallmgr");
 
-        Self::load_from_path(&config_dir.join("config.toml"))
+        Self::load_from_path(config_dir.join("config.toml"))
     }
 
     pub fn load_from_path<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
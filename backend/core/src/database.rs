@@ -1,7 +1,12 @@
-use crate::error::Result;
-use crate::types::{Tag, Wallpaper, WallpaperType};
-use chrono::DateTime;
-use rusqlite::{params, Connection};
+use crate::error::{Error, Result};
+use crate::library::{self, Collection, LibraryPage, LibraryQuery, Playlist, Sort, SortKey};
+use crate::types::{
+    BooruImage, DownloadJob, DownloadState, GeneratorSpec, Tag, TagAlias, TagCategory, TagImplication, Wallpaper,
+    WallpaperStats, WallpaperType,
+};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use std::collections::HashMap;
 use std::path::Path;
 use uuid::Uuid;
 
@@ -12,6 +17,8 @@ pub struct Database {
 impl Database {
     pub fn new(path: &Path) -> Result<Self> {
         let conn = Connection::open(path)?;
+        // The download queue keeps its own connection to the same file
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
         let db = Self { conn };
         db.initialize()?;
         Ok(db)
@@ -51,6 +58,17 @@ impl Database {
                 FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
             );
 
+            CREATE TABLE IF NOT EXISTS tag_aliases (
+                alias TEXT PRIMARY KEY,
+                tag TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS tag_implications (
+                tag TEXT NOT NULL,
+                implied TEXT NOT NULL,
+                PRIMARY KEY (tag, implied)
+            );
+
             CREATE TABLE IF NOT EXISTS monitors (
                 name TEXT PRIMARY KEY,
                 width INTEGER NOT NULL,
@@ -69,36 +87,148 @@ impl Database {
                 FOREIGN KEY (wallpaper_id) REFERENCES wallpapers(id) ON DELETE CASCADE
             );
 
+            CREATE TABLE IF NOT EXISTS generated_wallpapers (
+                wallpaper_id TEXT PRIMARY KEY,
+                params TEXT NOT NULL,
+                FOREIGN KEY (wallpaper_id) REFERENCES wallpapers(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS wallpaper_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                wallpaper_id TEXT NOT NULL,
+                monitor TEXT NOT NULL DEFAULT '',
+                shown_at TEXT NOT NULL,
+                ended_at TEXT
+            );
+
+            CREATE TABLE IF NOT EXISTS collections (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                description TEXT,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS collection_items (
+                collection_id INTEGER NOT NULL,
+                wallpaper_id TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                PRIMARY KEY (collection_id, wallpaper_id),
+                FOREIGN KEY (collection_id) REFERENCES collections(id) ON DELETE CASCADE,
+                FOREIGN KEY (wallpaper_id) REFERENCES wallpapers(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS playlists (
+                name TEXT PRIMARY KEY,
+                q TEXT,
+                query TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS download_jobs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                source TEXT NOT NULL,
+                image_id TEXT NOT NULL,
+                image TEXT NOT NULL,
+                state TEXT NOT NULL,
+                priority INTEGER NOT NULL DEFAULT 0,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                error TEXT,
+                downloaded INTEGER NOT NULL DEFAULT 0,
+                total INTEGER,
+                path TEXT,
+                retry_at TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                UNIQUE (source, image_id)
+            );
+
             CREATE INDEX IF NOT EXISTS idx_wallpapers_type ON wallpapers(type);
             CREATE INDEX IF NOT EXISTS idx_wallpapers_source ON wallpapers(source);
             CREATE INDEX IF NOT EXISTS idx_tags_name ON tags(name);
             CREATE INDEX IF NOT EXISTS idx_wallpaper_tags_wallpaper ON wallpaper_tags(wallpaper_id);
             CREATE INDEX IF NOT EXISTS idx_wallpaper_tags_tag ON wallpaper_tags(tag_id);
+            CREATE INDEX IF NOT EXISTS idx_download_jobs_state ON download_jobs(state);
+            CREATE INDEX IF NOT EXISTS idx_wallpapers_created ON wallpapers(created_at);
+            CREATE INDEX IF NOT EXISTS idx_wallpaper_history_wallpaper ON wallpaper_history(wallpaper_id);
+            CREATE INDEX IF NOT EXISTS idx_collection_items_wallpaper ON collection_items(wallpaper_id);
             "#,
         )?;
+
+        // Columns added after the initial schema
+        self.add_column_if_missing("wallpapers", "rating", "TEXT")?;
+        self.add_column_if_missing("wallpapers", "favorite", "INTEGER NOT NULL DEFAULT 0")?;
+        self.add_column_if_missing("wallpapers", "stars", "INTEGER")?;
+        self.add_column_if_missing("wallpapers", "times_set", "INTEGER NOT NULL DEFAULT 0")?;
+        self.add_column_if_missing("wallpapers", "display_seconds", "INTEGER NOT NULL DEFAULT 0")?;
+        self.add_column_if_missing("wallpapers", "last_shown", "TEXT")?;
+
+        // Full-text index of filenames, kept up to date by triggers
+        let indexed: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE name = 'wallpapers_fts'",
+            [],
+            |row| row.get(0),
+        )?;
+        self.conn.execute_batch(
+            r#"
+            CREATE VIRTUAL TABLE IF NOT EXISTS wallpapers_fts USING fts5(id UNINDEXED, filename);
+
+            CREATE TRIGGER IF NOT EXISTS wallpapers_fts_insert AFTER INSERT ON wallpapers BEGIN
+                INSERT INTO wallpapers_fts (id, filename) VALUES (new.id, new.filename);
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS wallpapers_fts_delete AFTER DELETE ON wallpapers BEGIN
+                DELETE FROM wallpapers_fts WHERE id = old.id;
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS wallpapers_fts_update AFTER UPDATE OF filename ON wallpapers BEGIN
+                UPDATE wallpapers_fts SET filename = new.filename WHERE id = old.id;
+            END;
+            "#,
+        )?;
+        if !indexed {
+            self.conn.execute(
+                "INSERT INTO wallpapers_fts (id, filename) SELECT id, filename FROM wallpapers",
+                [],
+            )?;
+        }
+
+        // Tag counts weren't kept up to date before
+        self.conn.execute(
+            "UPDATE tags SET count = (SELECT COUNT(*) FROM wallpaper_tags WHERE tag_id = tags.id)",
+            [],
+        )?;
         Ok(())
     }
 
-    pub fn add_wallpaper(&self, wallpaper: &Wallpaper) -> Result<()> {
-        let type_str = match wallpaper.wallpaper_type {
-            WallpaperType::Image => "image",
-            WallpaperType::Video => "video",
-            WallpaperType::Spine => "spine",
-            WallpaperType::WallpaperEngine => "wallpaper_engine",
-        };
+    fn add_column_if_missing(&self, table: &str, column: &str, definition: &str) -> Result<()> {
+        let exists: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+            params![table, column],
+            |row| row.get(0),
+        )?;
+
+        if !exists {
+            self.conn.execute(
+                &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+                [],
+            )?;
+        }
+        Ok(())
+    }
 
+    pub fn add_wallpaper(&self, wallpaper: &Wallpaper) -> Result<()> {
         self.conn.execute(
             r#"
             INSERT INTO wallpapers
             (id, path, filename, type, width, height, size, hash, source, source_url,
-             thumbnail_path, created_at, modified_at)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+             thumbnail_path, created_at, modified_at, rating)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
             "#,
             params![
                 wallpaper.id.to_string(),
                 &wallpaper.path,
                 &wallpaper.filename,
-                type_str,
+                wallpaper.wallpaper_type.as_str(),
                 wallpaper.width,
                 wallpaper.height,
                 wallpaper.size as i64,
@@ -108,139 +238,460 @@ impl Database {
                 &wallpaper.thumbnail_path,
                 wallpaper.created_at.to_rfc3339(),
                 wallpaper.modified_at.to_rfc3339(),
+                wallpaper.rating.map(|r| r.as_str()),
             ],
         )?;
 
-        // Add tags
-        for tag_name in &wallpaper.tags {
-            let tag_id = self.get_or_create_tag(tag_name, None)?;
-            self.conn.execute(
-                "INSERT OR IGNORE INTO wallpaper_tags (wallpaper_id, tag_id) VALUES (?1, ?2)",
-                params![wallpaper.id.to_string(), tag_id],
-            )?;
-        }
-
+        self.add_tags(&[wallpaper.id], &wallpaper.tags)?;
         Ok(())
     }
 
     pub fn get_wallpaper(&self, id: &Uuid) -> Result<Wallpaper> {
-        let mut stmt = self.conn.prepare(
-            r#"
-            SELECT id, path, filename, type, width, height, size, hash,
-                   source, source_url, thumbnail_path, created_at, modified_at
-            FROM wallpapers WHERE id = ?1
-            "#,
+        let wallpaper = self.conn.query_row(
+            &format!("SELECT {} FROM wallpapers w WHERE w.id = ?1", WALLPAPER_COLUMNS),
+            params![id.to_string()],
+            wallpaper_from_row,
         )?;
-
-        let wallpaper = stmt.query_row(params![id.to_string()], |row| {
-            let type_str: String = row.get(3)?;
-            let wallpaper_type = match type_str.as_str() {
-                "image" => WallpaperType::Image,
-                "video" => WallpaperType::Video,
-                "spine" => WallpaperType::Spine,
-                "wallpaper_engine" => WallpaperType::WallpaperEngine,
-                _ => WallpaperType::Image,
-            };
-
-            let id_str: String = row.get(0)?;
-            let created_str: String = row.get(11)?;
-            let modified_str: String = row.get(12)?;
-
-            Ok(Wallpaper {
-                id: Uuid::parse_str(&id_str).unwrap(),
-                path: row.get(1)?,
-                filename: row.get(2)?,
-                wallpaper_type,
-                width: row.get(4)?,
-                height: row.get(5)?,
-                size: row.get::<_, i64>(6)? as u64,
-                hash: row.get(7)?,
-                source: row.get(8)?,
-                source_url: row.get(9)?,
-                thumbnail_path: row.get(10)?,
-                created_at: DateTime::parse_from_rfc3339(&created_str).unwrap().into(),
-                modified_at: DateTime::parse_from_rfc3339(&modified_str).unwrap().into(),
-                tags: Vec::new(),
-            })
-        })?;
-
         Ok(wallpaper)
     }
 
     pub fn list_wallpapers(&self, filter_type: Option<WallpaperType>) -> Result<Vec<Wallpaper>> {
-        let query = if let Some(wtype) = filter_type {
-            let type_str = match wtype {
-                WallpaperType::Image => "image",
-                WallpaperType::Video => "video",
-                WallpaperType::Spine => "spine",
-                WallpaperType::WallpaperEngine => "wallpaper_engine",
-            };
-            format!(
-                "SELECT id, path, filename, type, width, height, size, hash,
-                        source, source_url, thumbnail_path, created_at, modified_at
-                 FROM wallpapers WHERE type = '{}' ORDER BY created_at DESC",
-                type_str
-            )
-        } else {
-            "SELECT id, path, filename, type, width, height, size, hash,
-                    source, source_url, thumbnail_path, created_at, modified_at
-             FROM wallpapers ORDER BY created_at DESC"
-                .to_string()
+        let query = LibraryQuery {
+            types: filter_type.into_iter().collect(),
+            ..LibraryQuery::default()
         };
+        Ok(self.query_wallpapers(&query)?.wallpapers)
+    }
 
-        let mut stmt = self.conn.prepare(&query)?;
+    /// Wallpapers matching `query`, and how many match on all pages
+    pub fn query_wallpapers(&self, query: &LibraryQuery) -> Result<LibraryPage> {
+        let query = self.resolve_query(query, &mut Vec::new())?;
+        let (conditions, mut values) = query.where_clause();
+        if query.is_weighted() {
+            let mut stmt = self
+                .conn
+                .prepare(&format!("SELECT {} FROM wallpapers w WHERE {}", WALLPAPER_COLUMNS, conditions))?;
+            let wallpapers = stmt
+                .query_map(params_from_iter(&values), wallpaper_from_row)?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            let total = wallpapers.len();
+            return Ok(LibraryPage {
+                wallpapers: query
+                    .weighted_order(wallpapers)
+                    .into_iter()
+                    .skip(query.offset)
+                    .take(query.limit.unwrap_or(usize::MAX))
+                    .collect(),
+                total,
+            });
+        }
+
+        let total: i64 = self.conn.query_row(
+            &format!("SELECT COUNT(*) FROM wallpapers w WHERE {}", conditions),
+            params_from_iter(&values),
+            |row| row.get(0),
+        )?;
+
+        // A negative limit is no limit
+        let (order, order_values) = query.order_clause();
+        values.extend(order_values);
+        values.push(query.limit.map_or(-1, |limit| limit as i64).into());
+        values.push((query.offset as i64).into());
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM wallpapers w WHERE {} ORDER BY {} LIMIT ? OFFSET ?",
+            WALLPAPER_COLUMNS, conditions, order
+        ))?;
         let wallpapers = stmt
-            .query_map([], |row| {
-                let type_str: String = row.get(3)?;
-                let wallpaper_type = match type_str.as_str() {
-                    "image" => WallpaperType::Image,
-                    "video" => WallpaperType::Video,
-                    "spine" => WallpaperType::Spine,
-                    "wallpaper_engine" => WallpaperType::WallpaperEngine,
-                    _ => WallpaperType::Image,
-                };
+            .query_map(params_from_iter(&values), wallpaper_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
 
-                let id_str: String = row.get(0)?;
-                let created_str: String = row.get(11)?;
-                let modified_str: String = row.get(12)?;
-
-                Ok(Wallpaper {
-                    id: Uuid::parse_str(&id_str).unwrap(),
-                    path: row.get(1)?,
-                    filename: row.get(2)?,
-                    wallpaper_type,
-                    width: row.get(4)?,
-                    height: row.get(5)?,
-                    size: row.get::<_, i64>(6)? as u64,
-                    hash: row.get(7)?,
-                    source: row.get(8)?,
-                    source_url: row.get(9)?,
-                    thumbnail_path: row.get(10)?,
-                    created_at: DateTime::parse_from_rfc3339(&created_str).unwrap().into(),
-                    modified_at: DateTime::parse_from_rfc3339(&modified_str).unwrap().into(),
-                    tags: Vec::new(),
-                })
+        Ok(LibraryPage {
+            wallpapers,
+            total: total as usize,
+        })
+    }
+
+    /// `query` with tag aliases resolved and the queries of its playlists
+    /// filled in; `within` are the playlists being resolved already
+    fn resolve_query(&self, query: &LibraryQuery, within: &mut Vec<String>) -> Result<LibraryQuery> {
+        let mut query = query.clone();
+        for tag in query
+            .tags
+            .iter_mut()
+            .chain(query.excluded.iter_mut())
+            .chain(query.any_of.iter_mut().flatten())
+        {
+            *tag = self.resolve_tag(tag)?;
+        }
+
+        query.playlist_queries.clear();
+        for name in &query.playlists {
+            if within.contains(name) {
+                return Err(Error::InvalidInput(format!("Playlist {} includes itself", name)));
+            }
+            let playlist = self.get_playlist(name)?;
+            within.push(name.clone());
+            let resolved = self.resolve_query(&playlist.to_query()?, within)?;
+            within.pop();
+            query.playlist_queries.push(resolved);
+        }
+        Ok(query)
+    }
+
+    /// The first wallpaper matching `query` that is `allowed`, as the query
+    /// orders them; by default weighted towards favorites and higher rated
+    /// wallpapers
+    pub fn pick_wallpaper(
+        &self,
+        query: &LibraryQuery,
+        allowed: impl Fn(&Wallpaper) -> bool,
+    ) -> Result<Option<Wallpaper>> {
+        let mut query = query.clone();
+        if query.sort.is_empty() {
+            query.sort.push(Sort {
+                key: SortKey::Weighted,
+                descending: true,
+            });
+        }
+        query.limit = None;
+        query.offset = 0;
+        Ok(self.query_wallpapers(&query)?.wallpapers.into_iter().find(allowed))
+    }
+
+    pub fn set_favorite(&self, id: &Uuid, favorite: bool) -> Result<()> {
+        self.ensure_wallpapers(&[*id])?;
+        self.conn.execute(
+            "UPDATE wallpapers SET favorite = ?2 WHERE id = ?1",
+            params![id.to_string(), favorite],
+        )?;
+        Ok(())
+    }
+
+    /// Rate a wallpaper 1 to 5 stars, or clear its rating with `None`
+    pub fn set_stars(&self, id: &Uuid, stars: Option<u8>) -> Result<()> {
+        if stars.is_some_and(|stars| !(1..=5).contains(&stars)) {
+            return Err(Error::InvalidInput("Ratings are 1 to 5 stars".to_string()));
+        }
+        self.ensure_wallpapers(&[*id])?;
+        self.conn.execute(
+            "UPDATE wallpapers SET stars = ?2 WHERE id = ?1",
+            params![id.to_string(), stars],
+        )?;
+        Ok(())
+    }
+
+    /// Record that a wallpaper is now shown on `monitor`, or on all monitors
+    /// for `None`. The wallpapers it replaces get their display time added.
+    pub fn record_shown(&self, id: &Uuid, monitor: Option<&str>) -> Result<()> {
+        self.record_shown_at(id, monitor, Utc::now())
+    }
+
+    /// Record that nothing is shown on `monitor` anymore, or on any monitor
+    /// for `None`
+    pub fn record_hidden(&self, monitor: Option<&str>) -> Result<()> {
+        self.end_shown(monitor, Utc::now())
+    }
+
+    fn record_shown_at(&self, id: &Uuid, monitor: Option<&str>, now: DateTime<Utc>) -> Result<()> {
+        self.ensure_wallpapers(&[*id])?;
+        self.end_shown(monitor, now)?;
+        self.conn.execute(
+            "INSERT INTO wallpaper_history (wallpaper_id, monitor, shown_at) VALUES (?1, ?2, ?3)",
+            params![id.to_string(), monitor.unwrap_or(""), now.to_rfc3339()],
+        )?;
+        self.conn.execute(
+            "UPDATE wallpapers SET times_set = times_set + 1, last_shown = ?2 WHERE id = ?1",
+            params![id.to_string(), now.to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Close the open shows on `monitor` (all for `None`) at `now`
+    fn end_shown(&self, monitor: Option<&str>, now: DateTime<Utc>) -> Result<()> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, wallpaper_id, shown_at FROM wallpaper_history \
+             WHERE ended_at IS NULL AND (?1 IS NULL OR monitor = ?1)",
+        )?;
+        let shows = stmt
+            .query_map(params![monitor], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
 
-        Ok(wallpapers)
+        for (show, wallpaper_id, shown_at) in shows {
+            let seconds = (now - parse_time(&shown_at)?).num_seconds().max(0);
+            self.conn.execute(
+                "UPDATE wallpapers SET display_seconds = display_seconds + ?2 WHERE id = ?1",
+                params![wallpaper_id, seconds],
+            )?;
+            self.conn.execute(
+                "UPDATE wallpaper_history SET ended_at = ?2 WHERE id = ?1",
+                params![show, now.to_rfc3339()],
+            )?;
+        }
+        Ok(())
     }
 
     pub fn delete_wallpaper(&self, id: &Uuid) -> Result<()> {
+        self.transaction(|| {
+            self.conn.execute(
+                "DELETE FROM generated_wallpapers WHERE wallpaper_id = ?1",
+                params![id.to_string()],
+            )?;
+            self.conn.execute(
+                "DELETE FROM wallpaper_history WHERE wallpaper_id = ?1",
+                params![id.to_string()],
+            )?;
+            self.conn.execute(
+                "DELETE FROM collection_items WHERE wallpaper_id = ?1",
+                params![id.to_string()],
+            )?;
+            self.conn.execute(
+                "UPDATE tags SET count = count - 1 \
+                 WHERE id IN (SELECT tag_id FROM wallpaper_tags WHERE wallpaper_id = ?1)",
+                params![id.to_string()],
+            )?;
+            self.conn.execute(
+                "DELETE FROM wallpaper_tags WHERE wallpaper_id = ?1",
+                params![id.to_string()],
+            )?;
+            self.conn.execute(
+                "DELETE FROM wallpapers WHERE id = ?1",
+                params![id.to_string()],
+            )?;
+            Ok(())
+        })
+    }
+
+    /// Store the parameters a generated wallpaper was rendered from
+    pub fn set_generator_spec(&self, wallpaper_id: &Uuid, spec: &GeneratorSpec) -> Result<()> {
         self.conn.execute(
-            "DELETE FROM wallpapers WHERE id = ?1",
-            params![id.to_string()],
+            "INSERT OR REPLACE INTO generated_wallpapers (wallpaper_id, params) VALUES (?1, ?2)",
+            params![wallpaper_id.to_string(), serde_json::to_string(spec)?],
         )?;
         Ok(())
     }
 
-    pub fn get_or_create_tag(&self, name: &str, category: Option<&str>) -> Result<i64> {
+    pub fn get_generator_spec(&self, wallpaper_id: &Uuid) -> Result<Option<GeneratorSpec>> {
+        let params_json: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT params FROM generated_wallpapers WHERE wallpaper_id = ?1",
+                params![wallpaper_id.to_string()],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(match params_json {
+            Some(json) => Some(serde_json::from_str(&json)?),
+            None => None,
+        })
+    }
+
+    /// All generated wallpapers with their parameters
+    pub fn list_generated(&self) -> Result<Vec<(Wallpaper, GeneratorSpec)>> {
+        let mut generated = Vec::new();
+        for wallpaper in self.list_wallpapers(Some(WallpaperType::Generated))? {
+            if let Some(spec) = self.get_generator_spec(&wallpaper.id)? {
+                generated.push((wallpaper, spec));
+            }
+        }
+        Ok(generated)
+    }
+
+    /// Whether a wallpaper with this content hash is already in the library
+    pub fn has_wallpaper_with_hash(&self, hash: &str) -> Result<bool> {
+        Ok(self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM wallpapers WHERE hash = ?1",
+            params![hash],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Add an image to the download queue
+    ///
+    /// An image that is already queued keeps its job; a failed one is queued again.
+    pub fn enqueue_download(&self, image: &BooruImage, priority: i32) -> Result<DownloadJob> {
+        let now = Utc::now().to_rfc3339();
+        let id = self.transaction(|| {
+            let existing: Option<(i64, String)> = self
+                .conn
+                .query_row(
+                    "SELECT id, state FROM download_jobs WHERE source = ?1 AND image_id = ?2",
+                    params![&image.source, &image.id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?;
+
+            Ok(match existing {
+                Some((id, state)) => {
+                    if state == DownloadState::Failed.as_str() {
+                        self.conn.execute(
+                            r#"
+                            UPDATE download_jobs
+                            SET state = ?1, priority = ?2, attempts = 0, error = NULL,
+                                retry_at = NULL, updated_at = ?3
+                            WHERE id = ?4
+                            "#,
+                            params![DownloadState::Queued.as_str(), priority, &now, id],
+                        )?;
+                    }
+                    id
+                }
+                None => {
+                    self.conn.execute(
+                        r#"
+                        INSERT INTO download_jobs
+                        (source, image_id, image, state, priority, total, created_at, updated_at)
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)
+                        "#,
+                        params![
+                            &image.source,
+                            &image.id,
+                            serde_json::to_string(image)?,
+                            DownloadState::Queued.as_str(),
+                            priority,
+                            image.file_size.map(|s| s as i64),
+                            &now,
+                        ],
+                    )?;
+                    self.conn.last_insert_rowid()
+                }
+            })
+        })?;
+
+        self.get_download_job(id)
+    }
+
+    pub fn get_download_job(&self, id: i64) -> Result<DownloadJob> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM download_jobs WHERE id = ?1",
+            DOWNLOAD_JOB_COLUMNS
+        ))?;
+
+        match stmt.query_row(params![id], download_job_from_row) {
+            Ok(job) => Ok(job),
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                Err(Error::NotFound(format!("Download job {} not found", id)))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Jobs in the order they run: highest priority first, then oldest first
+    pub fn list_download_jobs(&self, state: Option<DownloadState>) -> Result<Vec<DownloadJob>> {
+        let mut query = format!("SELECT {} FROM download_jobs", DOWNLOAD_JOB_COLUMNS);
+        if state.is_some() {
+            query.push_str(" WHERE state = ?1");
+        }
+        query.push_str(" ORDER BY priority DESC, id ASC");
+
+        let mut stmt = self.conn.prepare(&query)?;
+        let jobs = match state {
+            Some(state) => stmt.query_map(params![state.as_str()], download_job_from_row)?,
+            None => stmt.query_map([], download_job_from_row)?,
+        }
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(jobs)
+    }
+
+    pub fn set_download_state(&self, id: i64, state: DownloadState) -> Result<()> {
+        self.update_download_job(id, "state = ?1, retry_at = NULL", &[&state.as_str()])
+    }
+
+    pub fn set_download_priority(&self, id: i64, priority: i32) -> Result<()> {
+        self.update_download_job(id, "priority = ?1", &[&priority])
+    }
+
+    pub fn set_download_progress(&self, id: i64, downloaded: u64, total: Option<u64>) -> Result<()> {
+        self.update_download_job(
+            id,
+            "downloaded = ?1, total = COALESCE(?2, total)",
+            &[&(downloaded as i64), &total.map(|t| t as i64)],
+        )
+    }
+
+    /// Move a paused or failed job back into the queue, resetting failed attempts
+    pub fn resume_download(&self, id: i64) -> Result<()> {
+        self.update_download_job(
+            id,
+            "attempts = CASE WHEN state = 'failed' THEN 0 ELSE attempts END, \
+             state = ?1, retry_at = NULL, error = NULL",
+            &[&DownloadState::Queued.as_str()],
+        )
+    }
+
+    pub fn complete_download(&self, id: i64, path: &str) -> Result<()> {
+        self.update_download_job(
+            id,
+            "state = ?1, path = ?2, error = NULL, retry_at = NULL",
+            &[&DownloadState::Done.as_str(), &path],
+        )
+    }
+
+    /// Count a failed attempt; the job is retried at `retry_at`, or fails for good without one
+    pub fn record_download_failure(&self, id: i64, error: &str, retry_at: Option<DateTime<Utc>>) -> Result<()> {
+        let state = if retry_at.is_some() {
+            DownloadState::Queued
+        } else {
+            DownloadState::Failed
+        };
+        self.update_download_job(
+            id,
+            "state = ?1, attempts = attempts + 1, error = ?2, retry_at = ?3",
+            &[&state.as_str(), &error, &retry_at.map(|t| t.to_rfc3339())],
+        )
+    }
+
+    /// Put jobs that were running when the daemon stopped back in the queue
+    pub fn requeue_interrupted_downloads(&self) -> Result<usize> {
+        Ok(self.conn.execute(
+            "UPDATE download_jobs SET state = ?1 WHERE state = ?2",
+            params![DownloadState::Queued.as_str(), DownloadState::Downloading.as_str()],
+        )?)
+    }
+
+    pub fn delete_download_job(&self, id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM download_jobs WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Apply `assignments` (using ?1.. for `values`) and bump `updated_at`
+    fn update_download_job(&self, id: i64, assignments: &str, values: &[&dyn rusqlite::ToSql]) -> Result<()> {
+        let now = Utc::now().to_rfc3339();
+        let query = format!(
+            "UPDATE download_jobs SET {}, updated_at = ?{} WHERE id = ?{}",
+            assignments,
+            values.len() + 1,
+            values.len() + 2
+        );
+
+        let mut all = values.to_vec();
+        all.push(&now);
+        all.push(&id);
+        if self.conn.execute(&query, all.as_slice())? == 0 {
+            return Err(Error::NotFound(format!("Download job {} not found", id)));
+        }
+        Ok(())
+    }
+
+    /// Id of the tag `name`; an uncategorized tag gets `category` if given
+    pub fn get_or_create_tag(&self, name: &str, category: Option<TagCategory>) -> Result<i64> {
+        let category = category.map(|c| c.as_str());
+
         // Try to get existing tag
         if let Ok(id) = self.conn.query_row(
             "SELECT id FROM tags WHERE name = ?1",
             params![name],
-            |row| row.get(0),
+            |row| row.get::<_, i64>(0),
         ) {
+            if category.is_some() {
+                self.conn.execute(
+                    "UPDATE tags SET category = ?2 WHERE id = ?1 AND category IS NULL",
+                    params![id, category],
+                )?;
+            }
             return Ok(id);
         }
 
@@ -253,17 +704,35 @@ impl Database {
         Ok(self.conn.last_insert_rowid())
     }
 
+    /// Record the categories a source gave its tags
+    pub fn categorize_tags(&self, categories: &HashMap<String, TagCategory>) -> Result<()> {
+        for (name, category) in categories {
+            self.get_or_create_tag(name, Some(*category))?;
+        }
+        Ok(())
+    }
+
+    /// Tags in use starting with `prefix`, an exact match first, then by
+    /// category and how many wallpapers have them
     pub fn search_tags(&self, prefix: &str, limit: usize) -> Result<Vec<Tag>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, name, category, count FROM tags WHERE name LIKE ?1 ORDER BY count DESC LIMIT ?2",
-        )?;
+        let rank = TagCategory::ALL
+            .iter()
+            .map(|c| format!("WHEN '{}' THEN {}", c.as_str(), c.rank()))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, name, category, count FROM tags WHERE name LIKE ?1 ESCAPE '\\' AND count > 0 \
+             ORDER BY name = ?2 DESC, CASE category {} ELSE 0 END, count DESC, name LIMIT ?3",
+            rank
+        ))?;
 
+        let pattern = format!("{}%", prefix.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
         let tags = stmt
-            .query_map(params![format!("{}%", prefix), limit as i64], |row| {
+            .query_map(params![pattern, prefix, limit as i64], |row| {
                 Ok(Tag {
                     id: row.get(0)?,
                     name: row.get(1)?,
-                    category: row.get(2)?,
+                    category: row.get::<_, Option<String>>(2)?.and_then(|c| c.parse().ok()),
                     count: row.get(3)?,
                 })
             })?
@@ -272,6 +741,15 @@ impl Database {
         Ok(tags)
     }
 
+    /// Names of the tags on any wallpaper, alphabetically
+    pub fn all_tag_names(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT name FROM tags WHERE count > 0 ORDER BY name")?;
+        let names = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(names)
+    }
+
     pub fn get_wallpaper_tags(&self, wallpaper_id: &Uuid) -> Result<Vec<String>> {
         let mut stmt = self.conn.prepare(
             r#"
@@ -288,70 +766,1009 @@ impl Database {
         Ok(tags)
     }
 
-    pub fn search_by_tags(&self, tags: &[String]) -> Result<Vec<Wallpaper>> {
-        if tags.is_empty() {
-            return self.list_wallpapers(None);
+    /// Tag wallpapers, resolving aliases and adding the tags implied too.
+    /// Returns how many tags were added.
+    pub fn add_tags(&self, wallpaper_ids: &[Uuid], tags: &[String]) -> Result<usize> {
+        self.ensure_wallpapers(wallpaper_ids)?;
+
+        let mut names = Vec::new();
+        for tag in tags {
+            let tag = self.resolve_tag(tag)?;
+            names.extend(self.implied_tags(&tag)?);
+            names.push(tag);
         }
+        names.sort();
+        names.dedup();
 
-        let placeholders = tags.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-        let query = format!(
+        self.transaction(|| {
+            let mut added = 0;
+            for name in &names {
+                let tag_id = self.get_or_create_tag(name, None)?;
+                for id in wallpaper_ids {
+                    added += self.conn.execute(
+                        "INSERT OR IGNORE INTO wallpaper_tags (wallpaper_id, tag_id) VALUES (?1, ?2)",
+                        params![id.to_string(), tag_id],
+                    )?;
+                }
+                self.recount_tag(tag_id)?;
+            }
+            Ok(added)
+        })
+    }
+
+    /// Untag wallpapers; tags implied by the removed ones stay. Returns how
+    /// many tags were removed.
+    pub fn remove_tags(&self, wallpaper_ids: &[Uuid], tags: &[String]) -> Result<usize> {
+        self.ensure_wallpapers(wallpaper_ids)?;
+
+        self.transaction(|| {
+            let mut removed = 0;
+            for tag in tags {
+                let Some(tag_id) = self.tag_id(&self.resolve_tag(tag)?)? else {
+                    continue;
+                };
+                for id in wallpaper_ids {
+                    removed += self.conn.execute(
+                        "DELETE FROM wallpaper_tags WHERE wallpaper_id = ?1 AND tag_id = ?2",
+                        params![id.to_string(), tag_id],
+                    )?;
+                }
+                self.recount_tag(tag_id)?;
+            }
+            Ok(removed)
+        })
+    }
+
+    /// Untag then tag wallpapers, all or nothing. Returns how many tags were
+    /// added and removed.
+    pub fn edit_tags(&self, wallpaper_ids: &[Uuid], add: &[String], remove: &[String]) -> Result<(usize, usize)> {
+        self.transaction(|| {
+            let removed = self.remove_tags(wallpaper_ids, remove)?;
+            let added = self.add_tags(wallpaper_ids, add)?;
+            Ok((added, removed))
+        })
+    }
+
+    /// Rename a tag, keeping its wallpapers, aliases and implications
+    pub fn rename_tag(&self, from: &str, to: &str) -> Result<()> {
+        let id = self.tag_id(from)?.ok_or_else(|| Error::NotFound(format!("Tag {}", from)))?;
+        if self.tag_id(to)?.is_some() {
+            return Err(Error::AlreadyExists(format!("Tag {}; merge the tags instead", to)));
+        }
+
+        self.transaction(|| {
+            self.conn.execute("UPDATE tags SET name = ?2 WHERE id = ?1", params![id, to])?;
+            self.conn.execute("DELETE FROM tag_aliases WHERE alias = ?1", params![to])?;
+            self.replace_tag_references(from, to)
+        })
+    }
+
+    /// Merge tag `from` into `into`, keeping `from` as an alias
+    pub fn merge_tag(&self, from: &str, into: &str) -> Result<()> {
+        if self.tag_id(from)?.is_none() {
+            return Err(Error::NotFound(format!("Tag {}", from)));
+        }
+        self.add_alias(from, into)
+    }
+
+    /// Tag `name` stands for, following its alias if it has one
+    pub fn resolve_tag(&self, name: &str) -> Result<String> {
+        let tag = self
+            .conn
+            .query_row("SELECT tag FROM tag_aliases WHERE alias = ?1", params![name], |row| row.get(0))
+            .optional()?;
+        Ok(tag.unwrap_or_else(|| name.to_string()))
+    }
+
+    /// Make `alias` another name for `tag`. Wallpapers tagged with the alias
+    /// get the tag instead.
+    pub fn add_alias(&self, alias: &str, tag: &str) -> Result<()> {
+        let tag = self.resolve_tag(tag)?;
+        if alias == tag {
+            return Err(Error::InvalidInput(format!("{} can't be an alias of itself", alias)));
+        }
+
+        self.transaction(move || {
+            self.conn.execute(
+                "INSERT OR REPLACE INTO tag_aliases (alias, tag) VALUES (?1, ?2)",
+                params![alias, tag],
+            )?;
+            self.replace_tag_references(alias, &tag)?;
+
+            let Some(alias_id) = self.tag_id(alias)? else {
+                return Ok(());
+            };
+            let tag_id = self.get_or_create_tag(&tag, None)?;
+            self.conn.execute(
+                "UPDATE tags SET category = (SELECT category FROM tags WHERE id = ?1) \
+                 WHERE id = ?2 AND category IS NULL",
+                params![alias_id, tag_id],
+            )?;
+            let wallpapers = self.tagged_wallpapers(alias_id)?;
+            self.conn.execute("DELETE FROM wallpaper_tags WHERE tag_id = ?1", params![alias_id])?;
+            self.conn.execute("DELETE FROM tags WHERE id = ?1", params![alias_id])?;
+            self.add_tags(&wallpapers, &[tag])?;
+            Ok(())
+        })
+    }
+
+    pub fn remove_alias(&self, alias: &str) -> Result<()> {
+        let removed = self.conn.execute("DELETE FROM tag_aliases WHERE alias = ?1", params![alias])?;
+        if removed == 0 {
+            return Err(Error::NotFound(format!("Tag alias {}", alias)));
+        }
+        Ok(())
+    }
+
+    pub fn list_aliases(&self) -> Result<Vec<TagAlias>> {
+        let mut stmt = self.conn.prepare("SELECT alias, tag FROM tag_aliases ORDER BY tag, alias")?;
+        let aliases = stmt
+            .query_map([], |row| Ok(TagAlias { alias: row.get(0)?, tag: row.get(1)? }))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(aliases)
+    }
+
+    /// Make `tag` imply `implied`; wallpapers with the tag get the implied
+    /// one too
+    pub fn add_implication(&self, tag: &str, implied: &str) -> Result<()> {
+        let tag = self.resolve_tag(tag)?;
+        let implied = self.resolve_tag(implied)?;
+        if tag == implied || self.implied_tags(&implied)?.contains(&tag) {
+            return Err(Error::InvalidInput(format!("{} implying {} would be circular", tag, implied)));
+        }
+
+        self.transaction(move || {
+            self.conn.execute(
+                "INSERT OR IGNORE INTO tag_implications (tag, implied) VALUES (?1, ?2)",
+                params![tag, implied],
+            )?;
+            if let Some(tag_id) = self.tag_id(&tag)? {
+                let wallpapers = self.tagged_wallpapers(tag_id)?;
+                self.add_tags(&wallpapers, &[implied])?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Stop `tag` implying `implied`; wallpapers keep the tags they have
+    pub fn remove_implication(&self, tag: &str, implied: &str) -> Result<()> {
+        let removed = self.conn.execute(
+            "DELETE FROM tag_implications WHERE tag = ?1 AND implied = ?2",
+            params![tag, implied],
+        )?;
+        if removed == 0 {
+            return Err(Error::NotFound(format!("Tag implication {} -> {}", tag, implied)));
+        }
+        Ok(())
+    }
+
+    pub fn list_implications(&self) -> Result<Vec<TagImplication>> {
+        let mut stmt = self.conn.prepare("SELECT tag, implied FROM tag_implications ORDER BY tag, implied")?;
+        let implications = stmt
+            .query_map([], |row| Ok(TagImplication { tag: row.get(0)?, implied: row.get(1)? }))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(implications)
+    }
+
+    /// Tags `name` implies, directly or through other implications
+    pub fn implied_tags(&self, name: &str) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
             r#"
-            SELECT DISTINCT w.id, w.path, w.filename, w.type, w.width, w.height,
-                   w.size, w.hash, w.source, w.source_url, w.thumbnail_path,
-                   w.created_at, w.modified_at
-            FROM wallpapers w
-            JOIN wallpaper_tags wt ON w.id = wt.wallpaper_id
-            JOIN tags t ON wt.tag_id = t.id
-            WHERE t.name IN ({})
-            GROUP BY w.id
-            HAVING COUNT(DISTINCT t.name) = ?
-            ORDER BY w.created_at DESC
+            WITH RECURSIVE implied(name) AS (
+                SELECT implied FROM tag_implications WHERE tag = ?1
+                UNION
+                SELECT i.implied FROM tag_implications i JOIN implied ON i.tag = implied.name
+            )
+            SELECT name FROM implied
             "#,
-            placeholders
-        );
+        )?;
 
-        let mut stmt = self.conn.prepare(&query)?;
-        let tags_len = tags.len();
-        let params_vec: Vec<&dyn rusqlite::ToSql> = tags
-            .iter()
-            .map(|t| t as &dyn rusqlite::ToSql)
-            .chain(std::iter::once(&tags_len as &dyn rusqlite::ToSql))
-            .collect();
+        let tags = stmt
+            .query_map(params![name], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
 
-        let wallpapers = stmt
-            .query_map(params_vec.as_slice(), |row| {
-                let type_str: String = row.get(3)?;
-                let wallpaper_type = match type_str.as_str() {
-                    "image" => WallpaperType::Image,
-                    "video" => WallpaperType::Video,
-                    "spine" => WallpaperType::Spine,
-                    "wallpaper_engine" => WallpaperType::WallpaperEngine,
-                    _ => WallpaperType::Image,
-                };
+        Ok(tags)
+    }
 
-                let id_str: String = row.get(0)?;
-                let created_str: String = row.get(11)?;
-                let modified_str: String = row.get(12)?;
-
-                Ok(Wallpaper {
-                    id: Uuid::parse_str(&id_str).unwrap(),
-                    path: row.get(1)?,
-                    filename: row.get(2)?,
-                    wallpaper_type,
-                    width: row.get(4)?,
-                    height: row.get(5)?,
-                    size: row.get::<_, i64>(6)? as u64,
-                    hash: row.get(7)?,
-                    source: row.get(8)?,
-                    source_url: row.get(9)?,
-                    thumbnail_path: row.get(10)?,
-                    created_at: DateTime::parse_from_rfc3339(&created_str).unwrap().into(),
-                    modified_at: DateTime::parse_from_rfc3339(&modified_str).unwrap().into(),
-                    tags: Vec::new(),
-                })
-            })?
+    /// Run `f` in a transaction, or as part of the one already open
+    fn transaction<T>(&self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        if !self.conn.is_autocommit() {
+            return f();
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        let result = f()?;
+        tx.commit()?;
+        Ok(result)
+    }
+
+    fn tag_id(&self, name: &str) -> Result<Option<i64>> {
+        Ok(self
+            .conn
+            .query_row("SELECT id FROM tags WHERE name = ?1", params![name], |row| row.get(0))
+            .optional()?)
+    }
+
+    fn recount_tag(&self, tag_id: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE tags SET count = (SELECT COUNT(*) FROM wallpaper_tags WHERE tag_id = ?1) WHERE id = ?1",
+            params![tag_id],
+        )?;
+        Ok(())
+    }
+
+    fn tagged_wallpapers(&self, tag_id: i64) -> Result<Vec<Uuid>> {
+        let mut stmt = self.conn.prepare("SELECT wallpaper_id FROM wallpaper_tags WHERE tag_id = ?1")?;
+        let ids = stmt
+            .query_map(params![tag_id], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(ids.iter().filter_map(|id| Uuid::parse_str(id).ok()).collect())
+    }
+
+    fn ensure_wallpapers(&self, ids: &[Uuid]) -> Result<()> {
+        for id in ids {
+            let exists: bool = self.conn.query_row(
+                "SELECT COUNT(*) > 0 FROM wallpapers WHERE id = ?1",
+                params![id.to_string()],
+                |row| row.get(0),
+            )?;
+            if !exists {
+                return Err(Error::NotFound(format!("Wallpaper {}", id)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Point aliases and implications of tag `from` at `to`
+    fn replace_tag_references(&self, from: &str, to: &str) -> Result<()> {
+        self.conn.execute("UPDATE tag_aliases SET tag = ?2 WHERE tag = ?1", params![from, to])?;
+        for column in ["tag", "implied"] {
+            self.conn.execute(
+                &format!("UPDATE OR IGNORE tag_implications SET {0} = ?2 WHERE {0} = ?1", column),
+                params![from, to],
+            )?;
+            self.conn.execute(
+                &format!("DELETE FROM tag_implications WHERE {} = ?1", column),
+                params![from],
+            )?;
+        }
+        self.conn.execute("DELETE FROM tag_implications WHERE tag = implied", [])?;
+        Ok(())
+    }
+
+    pub fn search_by_tags(&self, tags: &[String]) -> Result<Vec<Wallpaper>> {
+        let query = LibraryQuery {
+            tags: tags.to_vec(),
+            ..LibraryQuery::default()
+        };
+        Ok(self.query_wallpapers(&query)?.wallpapers)
+    }
+
+    pub fn create_collection(&self, name: &str, description: Option<&str>) -> Result<()> {
+        library::check_name(name)?;
+        if self.collection_id(name)?.is_some() {
+            return Err(Error::AlreadyExists(format!("Collection {}", name)));
+        }
+        self.conn.execute(
+            "INSERT INTO collections (name, description, created_at) VALUES (?1, ?2, ?3)",
+            params![name, description, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_collection(&self, name: &str) -> Result<Collection> {
+        let (id, description, created_at): (i64, Option<String>, String) = self
+            .conn
+            .query_row(
+                "SELECT id, description, created_at FROM collections WHERE name = ?1",
+                params![name],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("Collection {}", name)))?;
+
+        Ok(Collection {
+            name: name.to_string(),
+            description,
+            wallpaper_ids: self.collection_items(id)?,
+            created_at: parse_time(&created_at)?,
+        })
+    }
+
+    pub fn list_collections(&self) -> Result<Vec<Collection>> {
+        let mut stmt = self.conn.prepare("SELECT name FROM collections ORDER BY name")?;
+        let names = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        names.iter().map(|name| self.get_collection(name)).collect()
+    }
+
+    /// Rename a collection and change its description
+    pub fn update_collection(&self, name: &str, new_name: &str, description: Option<&str>) -> Result<()> {
+        library::check_name(new_name)?;
+        let id = self.require_collection(name)?;
+        if new_name != name && self.collection_id(new_name)?.is_some() {
+            return Err(Error::AlreadyExists(format!("Collection {}", new_name)));
+        }
+        self.transaction(|| {
+            self.conn.execute(
+                "UPDATE collections SET name = ?2, description = ?3 WHERE id = ?1",
+                params![id, new_name, description],
+            )?;
+            self.rename_references("in", name, new_name)
+        })
+    }
+
+    /// Delete a collection no playlist uses
+    pub fn delete_collection(&self, name: &str) -> Result<()> {
+        self.transaction(|| {
+            let id = self.require_collection(name)?;
+            self.check_unreferenced("in", name)?;
+            self.conn.execute("DELETE FROM collection_items WHERE collection_id = ?1", params![id])?;
+            self.conn.execute("DELETE FROM collections WHERE id = ?1", params![id])?;
+            Ok(())
+        })
+    }
+
+    /// Put wallpapers into a collection at `position`, or at the end for
+    /// `None`. Wallpapers already in it move there. Returns how many are new.
+    pub fn add_to_collection(&self, name: &str, wallpaper_ids: &[Uuid], position: Option<usize>) -> Result<usize> {
+        let id = self.require_collection(name)?;
+        self.ensure_wallpapers(wallpaper_ids)?;
+
+        let mut moved = Vec::new();
+        for wallpaper_id in wallpaper_ids {
+            if !moved.contains(wallpaper_id) {
+                moved.push(*wallpaper_id);
+            }
+        }
+        let mut items = self.collection_items(id)?;
+        let before = items.len();
+        items.retain(|item| !moved.contains(item));
+        let added = moved.len() - (before - items.len());
+
+        let at = position.unwrap_or(items.len()).min(items.len());
+        items.splice(at..at, moved);
+        self.set_collection_items(id, &items)?;
+        Ok(added)
+    }
+
+    /// Take wallpapers out of a collection; returns how many were in it
+    pub fn remove_from_collection(&self, name: &str, wallpaper_ids: &[Uuid]) -> Result<usize> {
+        let id = self.require_collection(name)?;
+        let mut items = self.collection_items(id)?;
+        let before = items.len();
+        items.retain(|item| !wallpaper_ids.contains(item));
+        self.set_collection_items(id, &items)?;
+        Ok(before - items.len())
+    }
+
+    fn collection_id(&self, name: &str) -> Result<Option<i64>> {
+        Ok(self
+            .conn
+            .query_row("SELECT id FROM collections WHERE name = ?1", params![name], |row| row.get(0))
+            .optional()?)
+    }
+
+    fn require_collection(&self, name: &str) -> Result<i64> {
+        self.collection_id(name)?
+            .ok_or_else(|| Error::NotFound(format!("Collection {}", name)))
+    }
+
+    fn collection_items(&self, collection_id: i64) -> Result<Vec<Uuid>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT wallpaper_id FROM collection_items WHERE collection_id = ?1 ORDER BY position",
+        )?;
+        let ids = stmt
+            .query_map(params![collection_id], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        ids.iter()
+            .map(|id| Uuid::parse_str(id).map_err(|e| Error::Parse(format!("Invalid wallpaper id {}: {}", id, e))))
+            .collect()
+    }
+
+    fn set_collection_items(&self, collection_id: i64, wallpaper_ids: &[Uuid]) -> Result<()> {
+        self.transaction(|| {
+            self.conn.execute("DELETE FROM collection_items WHERE collection_id = ?1", params![collection_id])?;
+            for (position, wallpaper_id) in wallpaper_ids.iter().enumerate() {
+                self.conn.execute(
+                    "INSERT INTO collection_items (collection_id, wallpaper_id, position) VALUES (?1, ?2, ?3)",
+                    params![collection_id, wallpaper_id.to_string(), position as i64],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Save a new playlist; its query must be valid
+    pub fn create_playlist(&self, playlist: &Playlist) -> Result<()> {
+        if self.playlist_exists(&playlist.name)? {
+            return Err(Error::AlreadyExists(format!("Playlist {}", playlist.name)));
+        }
+        self.save_playlist(playlist, None)
+    }
+
+    pub fn get_playlist(&self, name: &str) -> Result<Playlist> {
+        self.conn
+            .query_row(
+                "SELECT name, q, query, created_at FROM playlists WHERE name = ?1",
+                params![name],
+                playlist_from_row,
+            )
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("Playlist {}", name)))
+    }
+
+    pub fn list_playlists(&self) -> Result<Vec<Playlist>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, q, query, created_at FROM playlists ORDER BY name")?;
+        let playlists = stmt
+            .query_map([], playlist_from_row)?
             .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(playlists)
+    }
 
-        Ok(wallpapers)
+    /// Replace playlist `name` with `playlist`, which may rename it
+    pub fn update_playlist(&self, name: &str, playlist: &Playlist) -> Result<()> {
+        if !self.playlist_exists(name)? {
+            return Err(Error::NotFound(format!("Playlist {}", name)));
+        }
+        if playlist.name != name && self.playlist_exists(&playlist.name)? {
+            return Err(Error::AlreadyExists(format!("Playlist {}", playlist.name)));
+        }
+        self.save_playlist(playlist, Some(name))
     }
+
+    /// Delete a playlist no other playlist uses
+    pub fn delete_playlist(&self, name: &str) -> Result<()> {
+        self.check_unreferenced("playlist", name)?;
+        if self.conn.execute("DELETE FROM playlists WHERE name = ?1", params![name])? == 0 {
+            return Err(Error::NotFound(format!("Playlist {}", name)));
+        }
+        Ok(())
+    }
+
+    fn playlist_exists(&self, name: &str) -> Result<bool> {
+        Ok(self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM playlists WHERE name = ?1",
+            params![name],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Store `playlist` in place of `replacing`, checking that its query
+    /// resolves without including itself
+    fn save_playlist(&self, playlist: &Playlist, replacing: Option<&str>) -> Result<()> {
+        library::check_name(&playlist.name)?;
+        self.transaction(|| {
+            if let Some(name) = replacing {
+                self.conn.execute("DELETE FROM playlists WHERE name = ?1", params![name])?;
+            }
+            self.conn.execute(
+                "INSERT INTO playlists (name, q, query, created_at) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    playlist.name,
+                    playlist.q,
+                    serde_json::to_string(&playlist.query)?,
+                    playlist.created_at.to_rfc3339()
+                ],
+            )?;
+            if let Some(name) = replacing.filter(|name| *name != playlist.name) {
+                self.rename_references("playlist", name, &playlist.name)?;
+            }
+            self.resolve_query(&LibraryQuery::parse(&format!("playlist:{}", playlist.name))?, &mut Vec::new())?;
+            Ok(())
+        })
+    }
+
+    /// Playlists whose query names collection (`key` "in") or playlist
+    /// (`key` "playlist") `name`
+    fn playlists_referencing(&self, key: &str, name: &str) -> Result<Vec<Playlist>> {
+        Ok(self
+            .list_playlists()?
+            .into_iter()
+            .filter(|playlist| playlist.references(key, name))
+            .collect())
+    }
+
+    /// Make playlists refer to `name` as `new_name`; part of the caller's
+    /// transaction
+    fn rename_references(&self, key: &str, name: &str, new_name: &str) -> Result<()> {
+        for mut playlist in self.playlists_referencing(key, name)? {
+            playlist.rename_reference(key, name, new_name);
+            self.conn.execute(
+                "UPDATE playlists SET q = ?2, query = ?3 WHERE name = ?1",
+                params![playlist.name, playlist.q, serde_json::to_string(&playlist.query)?],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn check_unreferenced(&self, key: &str, name: &str) -> Result<()> {
+        let users: Vec<String> = self
+            .playlists_referencing(key, name)?
+            .into_iter()
+            .map(|playlist| playlist.name)
+            .filter(|user| user != name)
+            .collect();
+        if !users.is_empty() {
+            return Err(Error::InvalidInput(format!(
+                "{} is used by playlist {}",
+                name,
+                users.join(", ")
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| Error::Parse(format!("Invalid time {}: {}", value, e)))
+}
+
+fn playlist_from_row(row: &rusqlite::Row) -> rusqlite::Result<Playlist> {
+    let invalid = |column: usize, e: Box<dyn std::error::Error + Send + Sync>| {
+        rusqlite::Error::FromSqlConversionFailure(column, rusqlite::types::Type::Text, e)
+    };
+    let query: String = row.get(2)?;
+    let created_at: String = row.get(3)?;
+
+    Ok(Playlist {
+        name: row.get(0)?,
+        q: row.get(1)?,
+        query: serde_json::from_str(&query).map_err(|e| invalid(2, Box::new(e)))?,
+        created_at: DateTime::parse_from_rfc3339(&created_at)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| invalid(3, Box::new(e)))?,
+    })
+}
+
+const WALLPAPER_COLUMNS: &str = "w.id, w.path, w.filename, w.type, w.width, w.height, w.size, w.hash, \
+     w.source, w.source_url, w.thumbnail_path, w.created_at, w.modified_at, w.rating, w.favorite, w.stars, \
+     w.times_set, w.display_seconds, w.last_shown, \
+     (SELECT GROUP_CONCAT(t.name, char(31)) FROM wallpaper_tags wt JOIN tags t ON t.id = wt.tag_id \
+      WHERE wt.wallpaper_id = w.id)";
+
+/// Separates the tag names of the last of [`WALLPAPER_COLUMNS`]
+const TAG_SEPARATOR: char = '\u{1f}';
+
+fn wallpaper_from_row(row: &rusqlite::Row) -> rusqlite::Result<Wallpaper> {
+    let invalid = |column: usize, e: Box<dyn std::error::Error + Send + Sync>| {
+        rusqlite::Error::FromSqlConversionFailure(column, rusqlite::types::Type::Text, e)
+    };
+    let parse_time = |column: usize, value: String| {
+        DateTime::parse_from_rfc3339(&value)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| invalid(column, Box::new(e)))
+    };
+
+    let id: String = row.get(0)?;
+    let wallpaper_type: String = row.get(3)?;
+
+    Ok(Wallpaper {
+        id: Uuid::parse_str(&id).map_err(|e| invalid(0, Box::new(e)))?,
+        path: row.get(1)?,
+        filename: row.get(2)?,
+        wallpaper_type: wallpaper_type.parse().unwrap_or(WallpaperType::Image),
+        width: row.get(4)?,
+        height: row.get(5)?,
+        size: row.get::<_, i64>(6)? as u64,
+        hash: row.get(7)?,
+        source: row.get(8)?,
+        source_url: row.get(9)?,
+        thumbnail_path: row.get(10)?,
+        rating: row.get::<_, Option<String>>(13)?.and_then(|r| r.parse().ok()),
+        stats: WallpaperStats {
+            favorite: row.get(14)?,
+            stars: row.get(15)?,
+            times_set: row.get(16)?,
+            display_seconds: row.get::<_, i64>(17)? as u64,
+            last_shown: row
+                .get::<_, Option<String>>(18)?
+                .map(|t| parse_time(18, t))
+                .transpose()?,
+        },
+        created_at: parse_time(11, row.get(11)?)?,
+        modified_at: parse_time(12, row.get(12)?)?,
+        tags: row
+            .get::<_, Option<String>>(19)?
+            .map(|tags| tags.split(TAG_SEPARATOR).map(String::from).collect())
+            .unwrap_or_default(),
+    })
+}
+
+const DOWNLOAD_JOB_COLUMNS: &str = "id, image, state, priority, attempts, error, downloaded, total, \
+     path, retry_at, created_at, updated_at";
+
+fn download_job_from_row(row: &rusqlite::Row) -> rusqlite::Result<DownloadJob> {
+    let invalid = |column: usize, e: Box<dyn std::error::Error + Send + Sync>| {
+        rusqlite::Error::FromSqlConversionFailure(column, rusqlite::types::Type::Text, e)
+    };
+    let parse_time = |column: usize, value: String| {
+        DateTime::parse_from_rfc3339(&value)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| invalid(column, Box::new(e)))
+    };
+
+    let image: String = row.get(1)?;
+    let state: String = row.get(2)?;
+
+    Ok(DownloadJob {
+        id: row.get(0)?,
+        image: serde_json::from_str(&image).map_err(|e| invalid(1, Box::new(e)))?,
+        state: state.parse().map_err(|e: Error| invalid(2, Box::new(e)))?,
+        priority: row.get(3)?,
+        attempts: row.get(4)?,
+        error: row.get(5)?,
+        downloaded: row.get::<_, i64>(6)? as u64,
+        total: row.get::<_, Option<i64>>(7)?.map(|t| t as u64),
+        path: row.get(8)?,
+        retry_at: row
+            .get::<_, Option<String>>(9)?
+            .map(|t| parse_time(9, t))
+            .transpose()?,
+        created_at: parse_time(10, row.get(10)?)?,
+        updated_at: parse_time(11, row.get(11)?)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wallpaper(path: &str, tags: &[&str]) -> Wallpaper {
+        Wallpaper {
+            id: Uuid::new_v4(),
+            path: path.to_string(),
+            filename: path.to_string(),
+            wallpaper_type: WallpaperType::Image,
+            width: 1920,
+            height: 1080,
+            size: 1,
+            hash: path.to_string(),
+            source: None,
+            source_url: None,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            thumbnail_path: None,
+            rating: None,
+            stats: Default::default(),
+            created_at: Utc::now(),
+            modified_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_policy_checks_stored_tags() {
+        use crate::config::PolicyConfig;
+        use crate::policy::ContentPolicy;
+
+        let db = Database::new(Path::new(":memory:")).unwrap();
+        let gory = wallpaper("/gory.png", &["blood_splatter", "night_sky"]);
+        let calm = wallpaper("/calm.png", &["night_sky"]);
+        db.add_wallpaper(&gory).unwrap();
+        db.add_wallpaper(&calm).unwrap();
+
+        let policy = ContentPolicy::new(&PolicyConfig {
+            blacklist: vec!["blood*".to_string()],
+            whitelist: vec!["*sky".to_string()],
+            ..PolicyConfig::default()
+        });
+        let stored = |w: &Wallpaper| db.get_wallpaper(&w.id).unwrap();
+        assert!(matches!(policy.check_wallpaper(&stored(&gory)), Err(Error::Blocked(_))));
+        assert!(policy.check_wallpaper(&stored(&calm)).is_ok());
+
+        let listed = db.query_wallpapers(&LibraryQuery::parse("name:calm").unwrap()).unwrap();
+        assert_eq!(listed.wallpapers[0].tags, vec!["night_sky"]);
+        assert!(policy.check_wallpaper(&listed.wallpapers[0]).is_ok());
+    }
+
+    #[test]
+    fn test_tag_counts_and_ranking() {
+        let db = Database::new(Path::new(":memory:")).unwrap();
+        db.categorize_tags(&HashMap::from([
+            ("saber".to_string(), TagCategory::Character),
+            ("sakimichan".to_string(), TagCategory::Artist),
+        ]))
+        .unwrap();
+        let first = wallpaper("/a.jpg", &["sakimichan", "sky", "saber"]);
+        db.add_wallpaper(&first).unwrap();
+        db.add_wallpaper(&wallpaper("/b.jpg", &["sky", "sa"])).unwrap();
+        db.add_wallpaper(&wallpaper("/c.jpg", &["sky", "sakimichan"])).unwrap();
+
+        let names = |tags: Vec<Tag>| tags.into_iter().map(|t| (t.name, t.count)).collect::<Vec<_>>();
+        assert_eq!(
+            names(db.search_tags("sa", 10).unwrap()),
+            vec![
+                ("sa".to_string(), 1),
+                ("saber".to_string(), 1),
+                ("sakimichan".to_string(), 2),
+            ]
+        );
+        assert_eq!(db.search_tags("sky", 1).unwrap()[0].count, 3);
+
+        db.delete_wallpaper(&first.id).unwrap();
+        let tags = db.search_tags("sa", 10).unwrap();
+        assert_eq!(names(tags.clone()), vec![("sa".to_string(), 1), ("sakimichan".to_string(), 1)]);
+        assert_eq!(tags[1].category, Some(TagCategory::Artist));
+        assert_eq!(db.all_tag_names().unwrap(), vec!["sa", "sakimichan", "sky"]);
+        // A category from a source doesn't overwrite a known one
+        db.get_or_create_tag("sakimichan", Some(TagCategory::General)).unwrap();
+        assert_eq!(db.search_tags("sakimichan", 1).unwrap()[0].category, Some(TagCategory::Artist));
+    }
+
+    #[test]
+    fn test_failed_edits_roll_back() {
+        let db = Database::new(Path::new(":memory:")).unwrap();
+        let failed: Result<()> = db.transaction(|| {
+            db.get_or_create_tag("sky", None)?;
+            db.transaction(|| Err(Error::InvalidInput("nested".to_string())))
+        });
+        assert!(failed.is_err());
+        assert!(db.tag_id("sky").unwrap().is_none());
+        assert!(db.conn.is_autocommit());
+    }
+
+    #[test]
+    fn test_aliases_and_implications() {
+        let db = Database::new(Path::new(":memory:")).unwrap();
+        let tags = |wallpaper: &Wallpaper| {
+            let mut tags = db.get_wallpaper_tags(&wallpaper.id).unwrap();
+            tags.sort();
+            tags
+        };
+
+        let first = wallpaper("/a.jpg", &["bg", "hatsune_miku"]);
+        let second = wallpaper("/b.jpg", &["sky"]);
+        db.add_wallpaper(&first).unwrap();
+        db.add_wallpaper(&second).unwrap();
+
+        // Existing wallpapers follow new aliases and implications
+        db.add_alias("bg", "background").unwrap();
+        db.add_implication("hatsune_miku", "vocaloid").unwrap();
+        assert_eq!(tags(&first), vec!["background", "hatsune_miku", "vocaloid"]);
+        assert!(matches!(db.add_implication("vocaloid", "hatsune_miku"), Err(Error::InvalidInput(_))));
+        assert!(matches!(db.add_alias("background", "bg"), Err(Error::InvalidInput(_))));
+
+        // New tags are resolved, implications followed transitively
+        db.add_implication("miku_append", "hatsune_miku").unwrap();
+        assert_eq!(db.add_tags(&[second.id], &["bg".to_string(), "miku_append".to_string()]).unwrap(), 4);
+        assert_eq!(tags(&second), vec!["background", "hatsune_miku", "miku_append", "sky", "vocaloid"]);
+        assert_eq!(db.search_tags("vocaloid", 1).unwrap()[0].count, 2);
+
+        assert_eq!(db.remove_tags(&[first.id, second.id], &["bg".to_string()]).unwrap(), 2);
+        assert!(db.search_tags("background", 1).unwrap().is_empty());
+        assert!(matches!(db.add_tags(&[Uuid::new_v4()], &["sky".to_string()]), Err(Error::NotFound(_))));
+        let swap = |add: &str, remove: &str| db.edit_tags(&[second.id], &[add.to_string()], &[remove.to_string()]);
+        assert_eq!(swap("clouds", "sky").unwrap(), (1, 1));
+        assert_eq!(swap("sky", "clouds").unwrap(), (1, 1));
+
+        // Renaming keeps implications, merging leaves an alias behind
+        db.rename_tag("vocaloid", "vocaloid_(series)").unwrap();
+        assert_eq!(db.implied_tags("hatsune_miku").unwrap(), vec!["vocaloid_(series)"]);
+        assert!(matches!(db.rename_tag("sky", "hatsune_miku"), Err(Error::AlreadyExists(_))));
+        db.merge_tag("miku_append", "hatsune_miku").unwrap();
+        assert_eq!(db.resolve_tag("miku_append").unwrap(), "hatsune_miku");
+        assert_eq!(tags(&second), vec!["hatsune_miku", "sky", "vocaloid_(series)"]);
+        assert_eq!(
+            db.list_implications().unwrap(),
+            vec![TagImplication { tag: "hatsune_miku".to_string(), implied: "vocaloid_(series)".to_string() }]
+        );
+    }
+
+    #[test]
+    fn test_query_wallpapers() {
+        let db = Database::new(Path::new(":memory:")).unwrap();
+        let mut beach = wallpaper("/sunset_beach.jpg", &["sky", "sea"]);
+        beach.size = 3 << 20;
+        beach.source = Some("danbooru".to_string());
+        let mut city = wallpaper("/city-night.png", &["sky", "city"]);
+        city.width = 1080;
+        city.height = 1920;
+        let mut clip = wallpaper("/beach_waves.mp4", &["sea"]);
+        clip.wallpaper_type = WallpaperType::Video;
+        for w in [&beach, &city, &clip] {
+            db.add_wallpaper(w).unwrap();
+        }
+        db.set_favorite(&city.id, true).unwrap();
+
+        let names = |query: &str| {
+            let page = db.query_wallpapers(&LibraryQuery::parse(query).unwrap()).unwrap();
+            page.wallpapers.into_iter().map(|w| w.filename).collect::<Vec<_>>()
+        };
+        assert_eq!(names("name:beach order:name"), vec!["/beach_waves.mp4", "/sunset_beach.jpg"]);
+        assert_eq!(names("name:bea name:wav"), vec!["/beach_waves.mp4"]);
+        assert_eq!(names("sky -city"), vec!["/sunset_beach.jpg"]);
+        assert_eq!(names("city|sea type:image order:name"), vec!["/city-night.png", "/sunset_beach.jpg"]);
+        assert_eq!(names("ratio:portrait"), vec!["/city-night.png"]);
+        assert_eq!(names("size:>=1mb source:danbooru"), vec!["/sunset_beach.jpg"]);
+        assert_eq!(names("is:favorite"), vec!["/city-night.png"]);
+        assert_eq!(names("-is:favorite type:video"), vec!["/beach_waves.mp4"]);
+        assert_eq!(names("size:>3mb"), Vec::<String>::new());
+        assert_eq!(names("width:<1920 height:>1080"), vec!["/city-night.png"]);
+
+        // Times written with another offset still compare as times
+        db.conn
+            .execute(
+                "UPDATE wallpapers SET created_at = '2024-01-02T01:00:00+02:00' WHERE id = ?1",
+                params![beach.id.to_string()],
+            )
+            .unwrap();
+        assert_eq!(names("added:2024-01-01"), vec!["/sunset_beach.jpg"]);
+        assert_eq!(names("added:>2024-01-01 order:name"), vec!["/beach_waves.mp4", "/city-night.png"]);
+
+        let page = db
+            .query_wallpapers(&LibraryQuery {
+                limit: Some(1),
+                offset: 1,
+                ..LibraryQuery::parse("order:name:desc").unwrap()
+            })
+            .unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.wallpapers[0].filename, "/city-night.png");
+
+        db.delete_wallpaper(&clip.id).unwrap();
+        assert_eq!(names("name:waves"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_stats_and_weighted_picks() {
+        let db = Database::new(Path::new(":memory:")).unwrap();
+        let walls: Vec<Wallpaper> = (0..4).map(|i| wallpaper(&format!("/{}.png", i), &[])).collect();
+        for w in &walls {
+            db.add_wallpaper(w).unwrap();
+        }
+
+        db.set_favorite(&walls[0].id, true).unwrap();
+        db.set_stars(&walls[0].id, Some(5)).unwrap();
+        assert!(matches!(db.set_stars(&walls[0].id, Some(6)), Err(Error::InvalidInput(_))));
+        assert!(matches!(db.set_favorite(&Uuid::new_v4(), true), Err(Error::NotFound(_))));
+
+        let start = Utc::now();
+        let at = |seconds| start + chrono::Duration::seconds(seconds);
+        db.record_shown_at(&walls[0].id, Some("DP-1"), at(0)).unwrap();
+        db.record_shown_at(&walls[1].id, Some("DP-2"), at(10)).unwrap();
+        db.record_shown_at(&walls[2].id, Some("DP-1"), at(60)).unwrap();
+        db.record_shown_at(&walls[0].id, None, at(100)).unwrap();
+
+        let stats = |i: usize| db.get_wallpaper(&walls[i].id).unwrap().stats;
+        assert_eq!(stats(0).times_set, 2);
+        assert_eq!(stats(0).display_seconds, 60);
+        assert_eq!(stats(0).stars, Some(5));
+        assert!(stats(0).favorite);
+        assert_eq!(stats(1).display_seconds, 90);
+        assert_eq!(stats(2).display_seconds, 40);
+        assert_eq!(stats(2).last_shown.unwrap().timestamp(), at(60).timestamp());
+        assert_eq!(stats(3), WallpaperStats::default());
+
+        let names = |query: &str| {
+            let page = db.query_wallpapers(&LibraryQuery::parse(query).unwrap()).unwrap();
+            page.wallpapers.into_iter().map(|w| w.filename).collect::<Vec<_>>()
+        };
+        assert_eq!(names("stars:>=4"), vec!["/0.png"]);
+        assert_eq!(names("shown:0"), vec!["/3.png"]);
+        assert_eq!(names("-recent:2 order:name"), vec!["/1.png", "/3.png"]);
+        assert_eq!(names("order:shown,name"), vec!["/0.png", "/1.png", "/2.png", "/3.png"]);
+
+        // A weight of 0 always comes last, however the draw goes
+        for _ in 0..20 {
+            assert_eq!(names("order:weighted weight:favorite=0").last().unwrap(), "/0.png");
+        }
+        let query = LibraryQuery::parse("-recent:3").unwrap();
+        assert_eq!(db.pick_wallpaper(&query, |_| true).unwrap().unwrap().filename, "/3.png");
+        assert!(db.pick_wallpaper(&query, |w| w.filename != "/3.png").unwrap().is_none());
+        db.record_shown_at(&walls[3].id, None, at(120)).unwrap();
+        assert!(db.pick_wallpaper(&LibraryQuery::parse("-recent:5").unwrap(), |_| true).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_collections_and_playlists() {
+        let db = Database::new(Path::new(":memory:")).unwrap();
+        let walls: Vec<Wallpaper> = (0..4).map(|i| wallpaper(&format!("/{}.png", i), &["sky"])).collect();
+        for w in &walls {
+            db.add_wallpaper(w).unwrap();
+        }
+        let ids: Vec<Uuid> = walls.iter().map(|w| w.id).collect();
+
+        db.create_collection("picks", Some("Best of")).unwrap();
+        assert!(matches!(db.create_collection("picks", None), Err(Error::AlreadyExists(_))));
+        assert!(matches!(db.create_collection("two words", None), Err(Error::InvalidInput(_))));
+        assert_eq!(db.add_to_collection("picks", &[ids[2], ids[0], ids[2]], None).unwrap(), 2);
+        assert_eq!(db.add_to_collection("picks", &[ids[3], ids[0]], Some(0)).unwrap(), 1);
+        assert_eq!(db.get_collection("picks").unwrap().wallpaper_ids, vec![ids[3], ids[0], ids[2]]);
+
+        let names = |query: &str| {
+            let page = db.query_wallpapers(&LibraryQuery::parse(query).unwrap()).unwrap();
+            page.wallpapers.into_iter().map(|w| w.filename).collect::<Vec<_>>()
+        };
+        assert_eq!(names("in:picks"), vec!["/3.png", "/0.png", "/2.png"]);
+        assert_eq!(names("in:picks order:position:desc"), vec!["/2.png", "/0.png", "/3.png"]);
+        assert_eq!(names("in:picks order:name"), vec!["/0.png", "/2.png", "/3.png"]);
+
+        assert_eq!(db.remove_from_collection("picks", &[ids[0], ids[1]]).unwrap(), 1);
+        db.update_collection("picks", "best", None).unwrap();
+        assert!(matches!(db.get_collection("picks"), Err(Error::NotFound(_))));
+        assert_eq!(names("in:best"), vec!["/3.png", "/2.png"]);
+
+        let playlist = |name: &str, q: &str| Playlist {
+            name: name.to_string(),
+            q: Some(q.to_string()),
+            query: LibraryQuery::default(),
+            created_at: Utc::now(),
+        };
+        db.create_playlist(&playlist("newest", "sky order:name:desc")).unwrap();
+        assert!(matches!(
+            db.create_playlist(&playlist("fresh", "playlist:newest -in:best")),
+            Err(Error::InvalidInput(_))
+        ));
+        db.create_playlist(&playlist("fresh", "playlist:newest -recent:1")).unwrap();
+        db.record_shown(&ids[3], None).unwrap();
+        assert_eq!(names("playlist:fresh order:name"), vec!["/0.png", "/1.png", "/2.png"]);
+        assert_eq!(names("playlist:fresh in:best"), vec!["/2.png"]);
+
+        // A playlist keeps to its limit, in its own order
+        let first_two = Playlist {
+            query: LibraryQuery { limit: Some(2), ..LibraryQuery::default() },
+            ..playlist("first_two", "order:name")
+        };
+        db.create_playlist(&first_two).unwrap();
+        assert_eq!(names("playlist:first_two order:name:desc"), vec!["/1.png", "/0.png"]);
+        db.delete_playlist("first_two").unwrap();
+
+        // Playlists can't end up including themselves
+        assert!(matches!(
+            db.update_playlist("newest", &playlist("newest", "playlist:fresh")),
+            Err(Error::InvalidInput(_))
+        ));
+        assert_eq!(db.get_playlist("newest").unwrap().q.as_deref(), Some("sky order:name:desc"));
+        assert!(matches!(db.create_playlist(&playlist("lost", "playlist:nowhere")), Err(Error::NotFound(_))));
+
+        db.delete_wallpaper(&ids[2]).unwrap();
+        assert_eq!(db.get_collection("best").unwrap().wallpaper_ids, vec![ids[3]]);
+        db.delete_collection("best").unwrap();
+        db.delete_playlist("fresh").unwrap();
+        assert!(db.list_collections().unwrap().is_empty());
+        assert_eq!(db.list_playlists().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_renames_keep_references() {
+        let db = Database::new(Path::new(":memory:")).unwrap();
+        let playlist = |name: &str, q: &str| Playlist {
+            name: name.to_string(),
+            q: Some(q.to_string()),
+            query: LibraryQuery::default(),
+            created_at: Utc::now(),
+        };
+        db.create_collection("picks", None).unwrap();
+        db.create_playlist(&playlist("mine", "In:picks sky")).unwrap();
+        let mut walls = playlist("walls", "order:name");
+        walls.query.collections = vec!["picks".to_string()];
+        db.create_playlist(&walls).unwrap();
+        db.create_playlist(&playlist("daily", "playlist:mine -recent:1")).unwrap();
+
+        db.update_collection("picks", "best", None).unwrap();
+        assert_eq!(db.get_playlist("mine").unwrap().q.as_deref(), Some("In:best sky"));
+        assert_eq!(db.get_playlist("walls").unwrap().query.collections, vec!["best"]);
+
+        db.update_playlist("mine", &playlist("ours", "In:best sky")).unwrap();
+        assert_eq!(db.get_playlist("daily").unwrap().q.as_deref(), Some("playlist:ours -recent:1"));
+
+        // A rename that fails leaves the references alone
+        assert!(matches!(
+            db.update_playlist("ours", &playlist("theirs", "playlist:daily")),
+            Err(Error::InvalidInput(_))
+        ));
+        assert_eq!(db.get_playlist("daily").unwrap().q.as_deref(), Some("playlist:ours -recent:1"));
+
+        // What is still used can't be deleted
+        assert!(matches!(db.delete_playlist("ours"), Err(Error::InvalidInput(_))));
+        assert!(matches!(db.delete_collection("best"), Err(Error::InvalidInput(_))));
+        db.delete_playlist("daily").unwrap();
+        db.delete_playlist("ours").unwrap();
+        db.delete_playlist("walls").unwrap();
+        db.delete_collection("best").unwrap();
+    }
+
 }
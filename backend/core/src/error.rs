@@ -43,6 +43,48 @@ pub enum Error {
 
     #[error("Parse error: {0}")]
     Parse(String),
+
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+
+    #[error("Rate limited: {0}")]
+    RateLimited(String),
+
+    #[error("Authentication failed: {0}")]
+    AuthFailed(String),
+
+    #[error("Source unavailable: {0}")]
+    SourceUnavailable(String),
+
+    #[error("Blocked by content policy: {0}")]
+    Blocked(String),
+}
+
+impl Error {
+    /// Stable name of the error kind, for API clients to tell failures apart
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Database(_) => "database",
+            Error::Io(_) => "io",
+            Error::Config(_) => "config",
+            Error::Serialization(_) => "serialization",
+            Error::Image(_) => "image",
+            Error::InvalidPath(_) => "invalid_path",
+            Error::AdapterUnavailable(_) => "adapter_unavailable",
+            Error::Renderer(_) => "renderer",
+            Error::NotFound(_) => "not_found",
+            Error::AlreadyExists(_) => "already_exists",
+            Error::InvalidInput(_) => "invalid_input",
+            Error::Network(_) => "network",
+            Error::Api(_) => "api",
+            Error::Parse(_) => "parse",
+            Error::Cancelled(_) => "cancelled",
+            Error::RateLimited(_) => "rate_limited",
+            Error::AuthFailed(_) => "auth_failed",
+            Error::SourceUnavailable(_) => "source_unavailable",
+            Error::Blocked(_) => "blocked",
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
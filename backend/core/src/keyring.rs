@@ -0,0 +1,64 @@
+//! Source credentials kept in the system keyring
+//!
+//! Secrets are stored through `secret-tool` (libsecret), which talks to
+//! GNOME Keyring, KWallet or any other Secret Service provider. Entries are
+//! identified by the attributes `service=wallmgr` and `source=<name>`.
+
+use crate::error::{Error, Result};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const SERVICE: &str = "wallmgr";
+
+/// The secret stored for `source`, if any
+pub fn lookup(source: &str) -> Result<Option<String>> {
+    let output = Command::new("secret-tool")
+        .args(["lookup", "service", SERVICE, "source", source])
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| Error::Config(format!("Failed to run secret-tool: {}", e)))?;
+
+    // secret-tool exits with 1 when nothing matches
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let secret = String::from_utf8_lossy(&output.stdout).trim_end_matches('\n').to_string();
+    Ok(Some(secret).filter(|s| !s.is_empty()))
+}
+
+/// Store `secret` for `source`, replacing an earlier one
+pub fn store(source: &str, secret: &str) -> Result<()> {
+    let mut child = Command::new("secret-tool")
+        .args([
+            "store",
+            &format!("--label=Wallmgr: {}", source),
+            "service",
+            SERVICE,
+            "source",
+            source,
+        ])
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::Config(format!("Failed to run secret-tool: {}", e)))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(secret.as_bytes())?;
+    }
+    if !child.wait()?.success() {
+        return Err(Error::Config(format!("Could not store the secret for {}", source)));
+    }
+    Ok(())
+}
+
+/// Remove the secret stored for `source`
+pub fn clear(source: &str) -> Result<()> {
+    let status = Command::new("secret-tool")
+        .args(["clear", "service", SERVICE, "source", source])
+        .status()
+        .map_err(|e| Error::Config(format!("Failed to run secret-tool: {}", e)))?;
+
+    if !status.success() {
+        return Err(Error::Config(format!("Could not clear the secret for {}", source)));
+    }
+    Ok(())
+}
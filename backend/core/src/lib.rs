@@ -2,7 +2,14 @@ pub mod config;
 pub mod database;
 pub mod types;
 pub mod error;
+pub mod thumbnail;
+pub mod keyring;
+pub mod policy;
+pub mod library;
+pub mod query;
 
 pub use config::Config;
 pub use database::Database;
+pub use policy::ContentPolicy;
+pub use library::{Collection, LibraryPage, LibraryQuery, Playlist};
 pub use error::{Error, Result};
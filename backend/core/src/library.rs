@@ -0,0 +1,761 @@
+//! Local library queries
+//!
+//! A [`LibraryQuery`] selects and sorts wallpapers of the library. It can be
+//! built directly or parsed from a query in the style sources are searched
+//! with:
+//!
+//! - `sky cloud`, `-city`, `sunset | sunrise`: tags, as in online searches
+//! - `name:beach`: filename words, full-text; word beginnings match too
+//! - `type:image,video`
+//! - `width:>=1920`, `height:..1080`, `res:1920x1080` (or `hd`, `qhd`, `4k`)
+//! - `ratio:16:9`, `ratio:>=1.5`, `ratio:1.5..2.5`, `ratio:landscape`
+//! - `size:>=2mb`, `size:..500kb`
+//! - `source:danbooru,yandere`
+//! - `added:2024-01-01..2024-01-31`, `added:>=2024-06-01`, `added:7d` (the
+//!   last seven days)
+//! - `is:favorite`, `-is:favorite`
+//! - `rating:safe,questionable` (or `s`, `q`, `e`), `-rating:explicit`
+//! - `stars:>=4`: the user's 1 to 5 star rating
+//! - `shown:0`, `shown:..3`: times set as wallpaper
+//! - `-recent:20`: none of the last 20 wallpapers shown
+//! - `in:wallpapers2024`: in a [`Collection`], in its order unless sorted
+//! - `playlist:cozy`: matching a saved [`Playlist`]
+//! - `order:added`, `order:size:asc,name`: sort keys, most significant first;
+//!   `order:position` is the order of the first collection
+//! - `order:weighted`: random, but favorites, higher rated and (with
+//!   `weight:unseen=2`) never shown wallpapers first more often; see
+//!   [`Weights`] for `weight:favorite=5,stars=2`
+//!
+//! Ranges are `>=x`, `>x`, `<=x`, `<x`, `x..y`, `x..`, `..y` or just `x`.
+
+use crate::error::{Error, Result};
+use crate::query::{self, Term};
+use crate::types::{Rating, Wallpaper, WallpaperStats, WallpaperType};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rusqlite::types::Value;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub use crate::query::Bounds;
+
+/// Meta terms of library queries
+const KEYS: &[&str] = &[
+    "name", "type", "res", "width", "height", "ratio", "size", "source", "added", "is", "rating", "stars", "shown",
+    "recent", "weight", "in", "playlist", "order",
+];
+
+/// Aspect ratio of `wallpapers w` in SQL
+const RATIO_SQL: &str = "(CAST(w.width AS REAL) / NULLIF(w.height, 0))";
+
+/// When `wallpapers w` was added, as a Julian day, so that times compare
+/// as times rather than as text
+const ADDED_SQL: &str = "julianday(w.created_at)";
+
+/// Place of `wallpapers w` in the collection named by the parameter
+const POSITION_SQL: &str = "(SELECT ci.position FROM collection_items ci \
+     JOIN collections c ON c.id = ci.collection_id WHERE ci.wallpaper_id = w.id AND c.name = ?)";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LibraryQuery {
+    /// Tags every wallpaper must have
+    pub tags: Vec<String>,
+    /// Tags no wallpaper may have
+    pub excluded: Vec<String>,
+    /// Groups of tags of which a wallpaper must have at least one each
+    pub any_of: Vec<Vec<String>>,
+    /// Words in the filename
+    pub text: Option<String>,
+    /// Allowed types; any when empty
+    pub types: Vec<WallpaperType>,
+    pub width: Bounds<u32>,
+    pub height: Bounds<u32>,
+    /// Width over height
+    pub ratio: Bounds<f64>,
+    /// File size in bytes
+    pub size: Bounds<u64>,
+    /// Allowed sources; any when empty
+    pub sources: Vec<String>,
+    pub added: Bounds<DateTime<Utc>>,
+    pub favorite: Option<bool>,
+    /// Allowed content ratings; any when empty
+    pub ratings: Vec<Rating>,
+    /// User rating; unrated wallpapers are outside any bounds
+    pub stars: Bounds<u8>,
+    /// Times set as wallpaper
+    pub times_set: Bounds<u32>,
+    /// Leave out the wallpapers of this many most recent shows
+    pub skip_recent: usize,
+    /// What `order:weighted` favors
+    pub weights: Weights,
+    /// Collections every wallpaper must be in
+    pub collections: Vec<String>,
+    /// Names of playlists every wallpaper must match
+    pub playlists: Vec<String>,
+    /// Queries of `playlists`, filled in by the database
+    #[serde(skip)]
+    pub(crate) playlist_queries: Vec<LibraryQuery>,
+    /// Sort keys, most significant first; newest first when empty
+    pub sort: Vec<Sort>,
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sort {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    Added,
+    Modified,
+    Name,
+    Size,
+    Width,
+    Height,
+    Resolution,
+    Ratio,
+    Stars,
+    TimesSet,
+    DisplayTime,
+    LastShown,
+    /// Place in the first collection of the query
+    Position,
+    Random,
+    /// Random with [`Weights`]; only as the first sort key, random after it
+    Weighted,
+}
+
+/// A named list of wallpapers, in the order they were arranged
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Collection {
+    pub name: String,
+    pub description: Option<String>,
+    pub wallpaper_ids: Vec<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A saved query; its wallpapers change along with the library
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Playlist {
+    pub name: String,
+    /// Query text, parsed again each time so `added:7d` stays the last week
+    #[serde(default)]
+    pub q: Option<String>,
+    /// Structured filters, narrowed by `q`
+    #[serde(default)]
+    pub query: LibraryQuery,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Playlist {
+    /// The query the playlist stands for as of now
+    pub fn to_query(&self) -> Result<LibraryQuery> {
+        let mut query = self.query.clone();
+        if let Some(q) = self.q.as_deref() {
+            query.narrow(LibraryQuery::parse(q)?);
+        }
+        Ok(query)
+    }
+
+    /// Whether the playlist's query names collection (`key` "in") or
+    /// playlist (`key` "playlist") `name`
+    pub(crate) fn references(&self, key: &str, name: &str) -> bool {
+        let names = match key {
+            "in" => &self.query.collections,
+            _ => &self.query.playlists,
+        };
+        names.iter().any(|n| n == name)
+            || self
+                .q
+                .as_deref()
+                .is_some_and(|q| q.split_whitespace().any(|term| query::meta(term, KEYS) == Some((key, name))))
+    }
+
+    /// Refer to collection or playlist `name` (see [`Self::references`]) as
+    /// `new_name` instead
+    pub(crate) fn rename_reference(&mut self, key: &str, name: &str, new_name: &str) {
+        let names = match key {
+            "in" => &mut self.query.collections,
+            _ => &mut self.query.playlists,
+        };
+        for n in names.iter_mut().filter(|n| *n == name) {
+            *n = new_name.to_string();
+        }
+        if let Some(q) = &mut self.q {
+            let terms: Vec<String> = q
+                .split_whitespace()
+                .map(|term| match query::meta(term, KEYS) {
+                    // Keep the key as it was written
+                    Some((k, value)) if k == key && value == name => {
+                        format!("{}{}", &term[..term.len() - value.len()], new_name)
+                    }
+                    _ => term.to_string(),
+                })
+                .collect();
+            *q = terms.join(" ");
+        }
+    }
+}
+
+/// Collection and playlist names are single words so queries can use them
+pub(crate) fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '|' || c == ',') {
+        return Err(Error::InvalidInput(format!(
+            "Invalid name \"{}\": use a single word without '|' or ','",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Factors by which `order:weighted` makes a wallpaper more likely to come
+/// first. They multiply, and a weight of 0 puts a wallpaper last.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Weights {
+    /// Factor for favorites
+    pub favorite: f64,
+    /// Factor per star above three, and divisor per star below; unrated
+    /// wallpapers count as three stars
+    pub stars: f64,
+    /// Factor for wallpapers never shown
+    pub unseen: f64,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            favorite: 3.0,
+            stars: 1.5,
+            unseen: 1.0,
+        }
+    }
+}
+
+impl Weights {
+    pub fn weight(&self, stats: &WallpaperStats) -> f64 {
+        let stars = stats.stars.map_or(0, |stars| stars as i32 - 3);
+        // 0 to a negative power is infinite, but a zero factor should put
+        // wallpapers below three stars last as well as those above
+        if stars != 0 && self.stars == 0.0 {
+            return 0.0;
+        }
+        let mut weight = self.stars.powi(stars);
+        if stats.favorite {
+            weight *= self.favorite;
+        }
+        if stats.times_set == 0 {
+            weight *= self.unseen;
+        }
+        weight
+    }
+}
+
+impl SortKey {
+    fn column(&self) -> &'static str {
+        match self {
+            Self::Added => "w.created_at",
+            Self::Modified => "w.modified_at",
+            Self::Name => "w.filename COLLATE NOCASE",
+            Self::Size => "w.size",
+            Self::Width => "w.width",
+            Self::Height => "w.height",
+            Self::Resolution => "(w.width * w.height)",
+            Self::Ratio => RATIO_SQL,
+            Self::Stars => "w.stars",
+            Self::TimesSet => "w.times_set",
+            Self::DisplayTime => "w.display_seconds",
+            Self::LastShown => "w.last_shown",
+            Self::Position => POSITION_SQL,
+            Self::Random | Self::Weighted => "RANDOM()",
+        }
+    }
+}
+
+impl std::str::FromStr for SortKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "added" | "date" | "new" => Ok(Self::Added),
+            "modified" => Ok(Self::Modified),
+            "name" | "filename" => Ok(Self::Name),
+            "size" => Ok(Self::Size),
+            "width" => Ok(Self::Width),
+            "height" => Ok(Self::Height),
+            "resolution" | "res" => Ok(Self::Resolution),
+            "ratio" => Ok(Self::Ratio),
+            "stars" => Ok(Self::Stars),
+            "shown" | "plays" => Ok(Self::TimesSet),
+            "time" | "displayed" => Ok(Self::DisplayTime),
+            "last" | "lastshown" => Ok(Self::LastShown),
+            "position" | "collection" => Ok(Self::Position),
+            "random" => Ok(Self::Random),
+            "weighted" => Ok(Self::Weighted),
+            _ => Err(Error::InvalidInput(format!("Invalid sort key: {}", s))),
+        }
+    }
+}
+
+/// Wallpapers matching a query, one page of them
+#[derive(Debug, Clone, Serialize)]
+pub struct LibraryPage {
+    pub wallpapers: Vec<Wallpaper>,
+    /// Matching wallpapers on all pages
+    pub total: usize,
+}
+
+impl LibraryQuery {
+    pub fn parse(query: &str) -> Result<Self> {
+        let mut parsed = Self::default();
+        for term in query::terms(query, KEYS)? {
+            parsed.add_term(term)?;
+        }
+        Ok(parsed)
+    }
+
+    /// Also apply the terms and sort keys of `other`; its filters replace
+    /// the ones it sets. Paging stays as it is.
+    pub fn narrow(&mut self, other: Self) {
+        self.tags.extend(other.tags);
+        self.excluded.extend(other.excluded);
+        self.any_of.extend(other.any_of);
+        self.text = match (self.text.take(), other.text) {
+            (Some(text), Some(more)) => Some(format!("{} {}", text, more)),
+            (text, more) => text.or(more),
+        };
+        self.sort.extend(other.sort);
+
+        if !other.sources.is_empty() {
+            self.sources = other.sources;
+        }
+        if !other.types.is_empty() {
+            self.types = other.types;
+        }
+        if !other.ratings.is_empty() {
+            self.ratings = other.ratings;
+        }
+        self.width.narrow(other.width);
+        self.height.narrow(other.height);
+        self.ratio.narrow(other.ratio);
+        self.size.narrow(other.size);
+        self.added.narrow(other.added);
+        self.favorite = other.favorite.or(self.favorite);
+        self.stars.narrow(other.stars);
+        self.times_set.narrow(other.times_set);
+        self.skip_recent = self.skip_recent.max(other.skip_recent);
+        if other.weights != Weights::default() {
+            self.weights = other.weights;
+        }
+        self.collections.extend(other.collections);
+        self.playlists.extend(other.playlists);
+    }
+
+    /// Whether wallpapers are ordered by [`Weights`], which SQL can't do
+    pub(crate) fn is_weighted(&self) -> bool {
+        self.sort.first().is_some_and(|sort| sort.key == SortKey::Weighted)
+    }
+
+    /// Order `wallpapers` randomly, each coming first with a chance in
+    /// proportion to its weight
+    pub(crate) fn weighted_order(&self, wallpapers: Vec<Wallpaper>) -> Vec<Wallpaper> {
+        // Efraimidis-Spirakis: sorting by u^(1/weight) draws without replacement
+        let mut keyed: Vec<(f64, Wallpaper)> = wallpapers
+            .into_iter()
+            .map(|wallpaper| (random_unit().powf(1.0 / self.weights.weight(&wallpaper.stats)), wallpaper))
+            .collect();
+        keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+        keyed.into_iter().map(|(_, wallpaper)| wallpaper).collect()
+    }
+
+    fn add_term(&mut self, term: Term) -> Result<()> {
+        let (key, value, negated) = match &term {
+            Term::Meta { key, value, negated } => (*key, value.as_str(), *negated),
+            Term::Tag { name, negated } => {
+                match negated {
+                    true => self.excluded.push(name.clone()),
+                    false => self.tags.push(name.clone()),
+                }
+                return Ok(());
+            }
+            Term::AnyOf(group) => {
+                self.any_of.push(group.clone());
+                return Ok(());
+            }
+        };
+
+        let invalid = || term.invalid();
+        match key {
+            "rating" => {
+                self.ratings = query::parse_ratings(value, negated, &self.ratings).ok_or_else(invalid)?;
+                return Ok(());
+            }
+            "is" => {
+                if !matches!(value.to_ascii_lowercase().as_str(), "favorite" | "fav") {
+                    return Err(invalid());
+                }
+                self.favorite = Some(!negated);
+                return Ok(());
+            }
+            "recent" => {
+                if !negated {
+                    return Err(Error::InvalidInput(format!("Use -{} to skip recently shown wallpapers", term.to_query())));
+                }
+                self.skip_recent = value.parse().map_err(|_| invalid())?;
+                return Ok(());
+            }
+            _ if negated => {
+                return Err(Error::InvalidInput(format!("{} can't be negated", key)));
+            }
+            "name" => {
+                let text = self.text.get_or_insert_with(String::new);
+                if !text.is_empty() {
+                    text.push(' ');
+                }
+                text.push_str(value);
+            }
+            "type" => {
+                self.types = value
+                    .split(',')
+                    .map(|t| t.parse().map_err(|_| invalid()))
+                    .collect::<Result<Vec<_>>>()?;
+            }
+            "res" => {
+                let (width, height) = query::parse_resolution(value).ok_or_else(invalid)?;
+                self.width.min = Some(width);
+                self.height.min = Some(height);
+            }
+            "width" => self.width = query::parse_bounds(value, |v| v.parse().ok().map(|n| (n, n))).ok_or_else(invalid)?,
+            "height" => self.height = query::parse_bounds(value, |v| v.parse().ok().map(|n| (n, n))).ok_or_else(invalid)?,
+            "ratio" => self.ratio = query::parse_ratio_bounds(value).ok_or_else(invalid)?,
+            "size" => self.size = query::parse_bounds(value, |v| parse_size(v).map(|n| (n, n))).ok_or_else(invalid)?,
+            "source" => self.sources = value.split(',').map(String::from).collect(),
+            "in" => self.collections.push(value.to_string()),
+            "playlist" => self.playlists.push(value.to_string()),
+            "added" => self.added = query::parse_bounds(value, parse_day).ok_or_else(invalid)?,
+            "stars" => {
+                self.stars = query::parse_bounds(value, |v| v.parse().ok().filter(|n| (1..=5).contains(n)).map(|n| (n, n)))
+                    .ok_or_else(invalid)?
+            }
+            "shown" => self.times_set = query::parse_bounds(value, |v| v.parse().ok().map(|n| (n, n))).ok_or_else(invalid)?,
+            "weight" => {
+                for factor in value.split(',') {
+                    let (name, value) = factor.split_once('=').ok_or_else(invalid)?;
+                    let value: f64 = value.parse().ok().filter(|v: &f64| v.is_finite() && *v >= 0.0).ok_or_else(invalid)?;
+                    match name {
+                        "favorite" | "fav" => self.weights.favorite = value,
+                        "stars" => self.weights.stars = value,
+                        "unseen" => self.weights.unseen = value,
+                        _ => return Err(invalid()),
+                    }
+                }
+            }
+            "order" => {
+                self.sort = value
+                    .split(',')
+                    .map(|sort| {
+                        let (key, direction) = sort.split_once(':').unwrap_or((sort, ""));
+                        let key: SortKey = key.parse()?;
+                        let descending = match direction {
+                            "" => key != SortKey::Name,
+                            "desc" => true,
+                            "asc" => false,
+                            _ => return Err(invalid()),
+                        };
+                        Ok(Sort { key, descending })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+            }
+            _ => unreachable!("terms() only returns known keys"),
+        }
+        Ok(())
+    }
+
+    /// Condition on `wallpapers w` for a WHERE clause, with its parameters
+    pub(crate) fn where_clause(&self) -> (String, Vec<Value>) {
+        let mut sql = Conditions::default();
+
+        for tag in &self.tags {
+            sql.tags("", std::slice::from_ref(tag));
+        }
+        for group in &self.any_of {
+            sql.tags("", group);
+        }
+        if !self.excluded.is_empty() {
+            sql.tags("NOT ", &self.excluded);
+        }
+        if let Some(text) = self.text.as_deref().and_then(fts_query) {
+            sql.push("w.id IN (SELECT id FROM wallpapers_fts WHERE wallpapers_fts MATCH ?)", [Value::Text(text)]);
+        }
+        sql.any_of("w.type", self.types.iter().map(|t| t.as_str().to_string()));
+        sql.bounds("w.width", &self.width, |w| Value::Integer(w.into()));
+        sql.bounds("w.height", &self.height, |h| Value::Integer(h.into()));
+        sql.bounds(RATIO_SQL, &self.ratio, Value::Real);
+        sql.bounds("w.size", &self.size, |s| Value::Integer(s as i64));
+        sql.any_of("w.source", self.sources.iter().cloned());
+        sql.bounds(ADDED_SQL, &self.added, |t| Value::Real(julian_day(t)));
+        if let Some(favorite) = self.favorite {
+            sql.push("w.favorite = ?", [Value::Integer(favorite.into())]);
+        }
+        sql.any_of("w.rating", self.ratings.iter().map(|r| r.as_str().to_string()));
+        sql.bounds("w.stars", &self.stars, |s| Value::Integer(s.into()));
+        sql.bounds("w.times_set", &self.times_set, |t| Value::Integer(t.into()));
+        for collection in &self.collections {
+            sql.push(
+                "EXISTS (SELECT 1 FROM collection_items ci JOIN collections c ON c.id = ci.collection_id \
+                 WHERE ci.wallpaper_id = w.id AND c.name = ?)",
+                [Value::Text(collection.clone())],
+            );
+        }
+        for playlist in &self.playlist_queries {
+            let (conditions, mut params) = playlist.where_clause();
+            let mut subquery = format!("w.id IN (SELECT w.id FROM wallpapers w WHERE {}", conditions);
+            // A playlist like "the 50 newest" keeps to its limit
+            if let Some(limit) = playlist.limit {
+                let (order, order_params) = playlist.order_clause();
+                subquery.push_str(&format!(" ORDER BY {} LIMIT ? OFFSET ?", order));
+                params.extend(order_params);
+                params.extend([Value::Integer(limit as i64), Value::Integer(playlist.offset as i64)]);
+            }
+            subquery.push(')');
+            sql.push(&subquery, params);
+        }
+        if self.skip_recent > 0 {
+            sql.push(
+                "w.id NOT IN (SELECT wallpaper_id FROM wallpaper_history ORDER BY id DESC LIMIT ?)",
+                [Value::Integer(self.skip_recent as i64)],
+            );
+        }
+
+        match sql.conditions.is_empty() {
+            true => ("1".to_string(), sql.params),
+            false => (sql.conditions.join(" AND "), sql.params),
+        }
+    }
+
+    /// Terms of an ORDER BY clause, with their parameters. Wallpapers of a
+    /// collection keep its order unless sorted otherwise; ties are broken
+    /// newest first.
+    pub(crate) fn order_clause(&self) -> (String, Vec<Value>) {
+        let in_collection = [Sort {
+            key: SortKey::Position,
+            descending: false,
+        }];
+        let sort = match self.sort.is_empty() {
+            true => &in_collection[..],
+            false => &self.sort[..],
+        };
+
+        let mut params = Vec::new();
+        let terms: Vec<String> = sort
+            .iter()
+            .filter_map(|sort| {
+                if sort.key == SortKey::Position {
+                    params.push(Value::Text(self.collections.first()?.clone()));
+                }
+                Some(format!("{} {}", sort.key.column(), if sort.descending { "DESC" } else { "ASC" }))
+            })
+            .chain(["w.created_at DESC".to_string(), "w.id".to_string()])
+            .collect();
+        (terms.join(", "), params)
+    }
+}
+
+/// SQL conditions, joined with AND, and their parameters in order
+#[derive(Default)]
+struct Conditions {
+    conditions: Vec<String>,
+    params: Vec<Value>,
+}
+
+impl Conditions {
+    fn push(&mut self, condition: &str, params: impl IntoIterator<Item = Value>) {
+        self.conditions.push(condition.to_string());
+        self.params.extend(params);
+    }
+
+    /// `column` is one of `values`, if there are any
+    fn any_of(&mut self, column: &str, values: impl Iterator<Item = String>) {
+        let values: Vec<Value> = values.map(Value::Text).collect();
+        if !values.is_empty() {
+            self.push(&format!("{} IN ({})", column, placeholders(values.len())), values);
+        }
+    }
+
+    /// The wallpaper has (or with `NOT `, has none) of `names`
+    fn tags(&mut self, negation: &str, names: &[String]) {
+        self.push(
+            &format!(
+                "{}EXISTS (SELECT 1 FROM wallpaper_tags wt JOIN tags t ON t.id = wt.tag_id \
+                 WHERE wt.wallpaper_id = w.id AND t.name IN ({}))",
+                negation,
+                placeholders(names.len())
+            ),
+            names.iter().cloned().map(Value::Text),
+        );
+    }
+
+    fn bounds<T: Copy>(&mut self, column: &str, bounds: &Bounds<T>, value: impl Fn(T) -> Value) {
+        if let Some(min) = bounds.min {
+            self.push(&format!("{} >= ?", column), [value(min)]);
+        }
+        if let Some(max) = bounds.max {
+            self.push(&format!("{} <= ?", column), [value(max)]);
+        }
+    }
+}
+
+fn placeholders(count: usize) -> String {
+    vec!["?"; count].join(", ")
+}
+
+/// FTS5 query matching every word of `text` at the start of a filename word
+fn fts_query(text: &str) -> Option<String> {
+    let words: Vec<String> = text
+        .split_whitespace()
+        .map(|word| word.replace('"', ""))
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"*", word))
+        .collect();
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+/// Bytes, with an optional `kb`, `mb` or `gb` unit
+fn parse_size(value: &str) -> Option<u64> {
+    let value = value.to_ascii_lowercase();
+    let (number, unit) = match value.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(index) => value.split_at(index),
+        None => (value.as_str(), ""),
+    };
+    let unit: u64 = match unit {
+        "" | "b" => 1,
+        "k" | "kb" => 1 << 10,
+        "m" | "mb" => 1 << 20,
+        "g" | "gb" => 1 << 30,
+        _ => return None,
+    };
+    let number: f64 = number.parse().ok()?;
+    (number >= 0.0).then_some((number * unit as f64) as u64)
+}
+
+/// `time` as SQLite's `julianday()` gives it: whole milliseconds since the
+/// Julian epoch, in days
+fn julian_day(time: DateTime<Utc>) -> f64 {
+    const UNIX_EPOCH_MS: i64 = 210_866_760_000_000;
+    (time.timestamp_millis() + UNIX_EPOCH_MS) as f64 / 86_400_000.0
+}
+
+/// Uniformly random number in (0, 1)
+fn random_unit() -> f64 {
+    let bits = Uuid::new_v4().as_u128() as u64 >> 11;
+    (bits as f64 + 0.5) / (1u64 << 53) as f64
+}
+
+/// A `YYYY-MM-DD` day from start to end, or the last `N` days (`7d`)
+fn parse_day(value: &str) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    if let Some(days) = value.strip_suffix('d') {
+        let now = Utc::now();
+        return Some((now - Duration::days(days.parse().ok()?), now));
+    }
+    let day = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    let start = day.and_hms_opt(0, 0, 0)?.and_utc();
+    Some((start, start + Duration::days(1) - Duration::milliseconds(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let query = LibraryQuery::parse(
+            "sky -city sunset|sunrise name:beach type:image,video res:hd ratio:16:9 \
+             size:..2mb source:danbooru added:2024-01-01..2024-01-31 -is:favorite \
+             -rating:e order:size:asc,name",
+        )
+        .unwrap();
+
+        assert_eq!(query.tags, vec!["sky"]);
+        assert_eq!(query.excluded, vec!["city"]);
+        assert_eq!(query.any_of, vec![vec!["sunset", "sunrise"]]);
+        assert_eq!(query.text.as_deref(), Some("beach"));
+        assert_eq!(query.types, vec![WallpaperType::Image, WallpaperType::Video]);
+        assert_eq!(query.width.min, Some(1920));
+        assert_eq!(query.height, Bounds { min: Some(1080), max: None });
+        assert!(query.ratio.min.unwrap() < 16.0 / 9.0 && query.ratio.max.unwrap() > 16.0 / 9.0);
+        assert_eq!(query.size, Bounds { min: None, max: Some(2 * 1024 * 1024) });
+        assert_eq!(query.sources, vec!["danbooru"]);
+        assert_eq!(query.added.min.unwrap().to_rfc3339(), "2024-01-01T00:00:00+00:00");
+        assert_eq!(query.added.max.unwrap().to_rfc3339(), "2024-01-31T23:59:59.999+00:00");
+        assert_eq!(query.favorite, Some(false));
+        assert_eq!(query.ratings, vec![Rating::Safe, Rating::Questionable]);
+        assert_eq!(
+            query.sort,
+            vec![
+                Sort { key: SortKey::Size, descending: false },
+                Sort { key: SortKey::Name, descending: false },
+            ]
+        );
+
+        assert!(LibraryQuery::parse("-size:>=1mb").is_err());
+        assert!(LibraryQuery::parse("type:gif").is_err());
+        assert!(LibraryQuery::parse("order:size:up").is_err());
+        assert!(LibraryQuery::parse("a | -b").is_err());
+
+        let query = LibraryQuery::parse("rating:s|q ratio:>=1.5 shown:>2 stars:<4").unwrap();
+        assert_eq!(query.ratings, vec![Rating::Safe, Rating::Questionable]);
+        assert_eq!(query.ratio, Bounds { min: Some(1.5), max: None });
+        assert_eq!(query.times_set, Bounds { min: Some(3), max: None });
+        assert_eq!(query.stars, Bounds { min: None, max: Some(3) });
+    }
+
+    #[test]
+    fn test_parse_stats_and_weights() {
+        let query = LibraryQuery::parse("stars:>=4 shown:0 -recent:20 order:weighted weight:favorite=5,unseen=2").unwrap();
+        assert_eq!(query.stars, Bounds { min: Some(4), max: None });
+        assert_eq!(query.times_set, Bounds { min: Some(0), max: Some(0) });
+        assert_eq!(query.skip_recent, 20);
+        assert!(query.is_weighted());
+        assert_eq!(query.weights, Weights { favorite: 5.0, stars: 1.5, unseen: 2.0 });
+
+        let stats = |favorite, stars, times_set| WallpaperStats { favorite, stars, times_set, ..Default::default() };
+        assert_eq!(query.weights.weight(&stats(false, None, 1)), 1.0);
+        assert_eq!(query.weights.weight(&stats(true, Some(5), 0)), 5.0 * 2.25 * 2.0);
+        assert_eq!(query.weights.weight(&stats(false, Some(1), 3)), 1.0 / 2.25);
+
+        assert!(LibraryQuery::parse("stars:6").is_err());
+        assert!(LibraryQuery::parse("recent:20").is_err());
+
+        let query = LibraryQuery::parse("order:weighted weight:stars=0").unwrap();
+        assert_eq!(query.weights.weight(&stats(false, Some(1), 3)), 0.0);
+        assert_eq!(query.weights.weight(&stats(false, Some(2), 3)), 0.0);
+        assert_eq!(query.weights.weight(&stats(false, Some(5), 3)), 0.0);
+        assert_eq!(query.weights.weight(&stats(false, Some(3), 3)), 1.0);
+        assert_eq!(query.weights.weight(&stats(false, None, 3)), 1.0);
+
+        assert!(LibraryQuery::parse("weight:favorite=-1").is_err());
+        assert!(LibraryQuery::parse("weight:size=2").is_err());
+    }
+
+    #[test]
+    fn test_where_clause_is_parameterized() {
+        let query = LibraryQuery::parse("sky' -city name:\"x\" source:a',b").unwrap();
+        let (sql, params) = query.where_clause();
+        assert!(!sql.contains('\''));
+        assert_eq!(
+            params,
+            vec![
+                Value::Text("sky'".to_string()),
+                Value::Text("city".to_string()),
+                Value::Text("\"x\"*".to_string()),
+                Value::Text("a'".to_string()),
+                Value::Text("b".to_string()),
+            ]
+        );
+        assert_eq!(LibraryQuery::default().where_clause(), ("1".to_string(), Vec::new()));
+    }
+}
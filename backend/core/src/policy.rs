@@ -0,0 +1,196 @@
+//! Content policy
+//!
+//! Decides what may reach the desktop: tag blacklists and whitelists with
+//! `*` wildcards, rating caps overall and per source, and a minimum score.
+//! Search results, downloads and wallpapers being set are all checked
+//! against the same policy, so with `work_mode` on nothing but safe images
+//! gets through.
+
+use crate::config::PolicyConfig;
+use crate::error::{Error, Result};
+use crate::types::{BooruImage, Rating, Wallpaper};
+
+#[derive(Debug, Clone, Default)]
+pub struct ContentPolicy {
+    config: PolicyConfig,
+}
+
+impl ContentPolicy {
+    pub fn new(config: &PolicyConfig) -> Self {
+        let mut config = config.clone();
+        for pattern in config.blacklist.iter_mut().chain(config.whitelist.iter_mut()) {
+            *pattern = pattern.to_lowercase();
+        }
+        Self { config }
+    }
+
+    /// Highest rating allowed from `source`
+    pub fn max_rating(&self, source: Option<&str>) -> Rating {
+        if self.config.work_mode {
+            return Rating::Safe;
+        }
+        let source_cap = source.and_then(|source| self.config.source_ratings.get(source).copied());
+        [self.config.max_rating, source_cap]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or(Rating::Explicit)
+    }
+
+    /// Whether a source image may be shown or downloaded, and why not
+    pub fn check(&self, image: &BooruImage) -> Result<()> {
+        let max_rating = self.max_rating(Some(&image.source));
+        if image.rating > max_rating || (self.config.work_mode && image.is_nsfw) {
+            return Err(Error::Blocked(format!(
+                "{} image {} is rated {}",
+                image.source,
+                image.id,
+                image.rating.as_str()
+            )));
+        }
+        if let (Some(min), Some(score)) = (self.config.min_score, image.score) {
+            if score < min {
+                return Err(Error::Blocked(format!(
+                    "{} image {} has a score of {} (at least {} wanted)",
+                    image.source, image.id, score, min
+                )));
+            }
+        }
+        self.check_tags(&image.tags)
+    }
+
+    pub fn allows(&self, image: &BooruImage) -> bool {
+        self.check(image).is_ok()
+    }
+
+    /// The images that may be shown
+    pub fn filter(&self, images: Vec<BooruImage>) -> Vec<BooruImage> {
+        images.into_iter().filter(|image| self.allows(image)).collect()
+    }
+
+    /// Whether a library wallpaper may be set. Wallpapers added by hand have
+    /// no rating; in work mode, unrated downloads are blocked.
+    pub fn check_wallpaper(&self, wallpaper: &Wallpaper) -> Result<()> {
+        let max_rating = self.max_rating(wallpaper.source.as_deref());
+        let blocked = match wallpaper.rating {
+            Some(rating) => rating > max_rating,
+            None => self.config.work_mode && wallpaper.source.is_some(),
+        };
+        if blocked {
+            return Err(Error::Blocked(format!(
+                "{} is rated {}",
+                wallpaper.filename,
+                wallpaper.rating.map_or("unknown", |r| r.as_str())
+            )));
+        }
+        self.check_tags(&wallpaper.tags)
+    }
+
+    fn check_tags(&self, tags: &[String]) -> Result<()> {
+        let tags: Vec<String> = tags.iter().map(|tag| tag.to_lowercase()).collect();
+        let matching = |pattern: &String| tags.iter().find(|tag| wildcard_match(pattern, tag));
+
+        if let Some(tag) = self.config.blacklist.iter().find_map(matching) {
+            return Err(Error::Blocked(format!("tagged {}", tag)));
+        }
+        if !self.config.whitelist.is_empty() && !self.config.whitelist.iter().any(|p| matching(p).is_some()) {
+            return Err(Error::Blocked("no whitelisted tag".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` stands for any characters
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcard at all
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn image(source: &str, tags: &[&str], rating: Rating, score: Option<i32>) -> BooruImage {
+        BooruImage {
+            id: "1".to_string(),
+            source: source.to_string(),
+            file_url: "https://example.com/1.jpg".to_string(),
+            preview_url: None,
+            sample_url: None,
+            width: 1920,
+            height: 1080,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            rating,
+            score,
+            author: None,
+            is_nsfw: rating != Rating::Safe,
+            md5: None,
+            file_size: None,
+            page_url: None,
+            origin_url: None,
+            attribution: None,
+            tag_categories: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("gore", "gore"));
+        assert!(!wildcard_match("gore", "gorengan"));
+        assert!(wildcard_match("*guro*", "ero_guro"));
+        assert!(wildcard_match("blood*", "blood_on_face"));
+        assert!(wildcard_match("*_(cosplay)", "saber_(cosplay)"));
+        assert!(wildcard_match("a*b*c", "axxbyyc"));
+        assert!(!wildcard_match("a*b*c", "axxcyyb"));
+        assert!(wildcard_match("*", "anything"));
+    }
+
+    #[test]
+    fn test_check() {
+        let policy = ContentPolicy::new(&PolicyConfig {
+            blacklist: vec!["Blood*".to_string()],
+            whitelist: vec!["scenery".to_string(), "*sky".to_string()],
+            max_rating: Some(Rating::Questionable),
+            source_ratings: HashMap::from([("danbooru".to_string(), Rating::Safe)]),
+            min_score: Some(10),
+            ..PolicyConfig::default()
+        });
+
+        assert!(policy.allows(&image("yandere", &["scenery"], Rating::Questionable, Some(50))));
+        assert!(policy.allows(&image("bing", &["night_sky"], Rating::Safe, None)));
+        assert!(!policy.allows(&image("yandere", &["scenery"], Rating::Explicit, Some(50))));
+        assert!(!policy.allows(&image("danbooru", &["scenery"], Rating::Questionable, Some(50))));
+        assert!(!policy.allows(&image("yandere", &["scenery"], Rating::Safe, Some(5))));
+        assert!(!policy.allows(&image("yandere", &["scenery", "blood_splatter"], Rating::Safe, None)));
+        assert!(matches!(
+            policy.check(&image("yandere", &["city"], Rating::Safe, None)),
+            Err(Error::Blocked(_))
+        ));
+
+        let policy = ContentPolicy::new(&PolicyConfig {
+            work_mode: true,
+            ..PolicyConfig::default()
+        });
+        assert_eq!(policy.max_rating(Some("danbooru")), Rating::Safe);
+        assert!(!policy.allows(&image("yandere", &[], Rating::Questionable, None)));
+        let mut nsfw = image("wallhaven", &[], Rating::Safe, None);
+        nsfw.is_nsfw = true;
+        assert!(!policy.allows(&nsfw));
+    }
+}
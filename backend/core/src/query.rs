@@ -0,0 +1,359 @@
+//! Query syntax shared by source searches and library queries
+//!
+//! Both are whitespace separated terms: tags, `-tag`, alternatives joined
+//! by `|`, and `key:value` meta terms with keys each kind of query defines
+//! for itself. [`terms`] splits a query into [`Term`]s; the functions below
+//! parse the values both kinds of query have in common.
+//!
+//! Ranges are `>=x`, `>x`, `<=x`, `<x`, `x..y`, `x..`, `..y` or just `x`.
+
+use crate::error::{Error, Result};
+use crate::types::Rating;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How far an exact ratio like `16:9` may be off, relatively
+pub const RATIO_TOLERANCE: f64 = 0.02;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    /// A tag, or with `negated` a tag to leave out
+    Tag { name: String, negated: bool },
+    /// Plain tags of which one is enough
+    AnyOf(Vec<String>),
+    /// A meta term; `key` is one of the keys given to [`terms`]
+    Meta {
+        key: &'static str,
+        value: String,
+        negated: bool,
+    },
+}
+
+impl Term {
+    /// The term as it would be written
+    pub fn to_query(&self) -> String {
+        let minus = |negated: bool| if negated { "-" } else { "" };
+        match self {
+            Self::Tag { name, negated } => format!("{}{}", minus(*negated), name),
+            Self::AnyOf(tags) => tags.join(" | "),
+            Self::Meta { key, value, negated } => format!("{}{}:{}", minus(*negated), key, value),
+        }
+    }
+
+    /// `Invalid value in <term>`
+    pub fn invalid(&self) -> Error {
+        Error::InvalidInput(format!("Invalid value in {}", self.to_query()))
+    }
+}
+
+/// The terms of `query`, where `keys` are the meta terms it knows. Any
+/// other `key:value` is a tag.
+pub fn terms(query: &str, keys: &[&'static str]) -> Result<Vec<Term>> {
+    split_terms(query, keys)
+        .into_iter()
+        .map(|term| match term.as_slice() {
+            [single] => {
+                let (negated, body) = match single.strip_prefix('-') {
+                    Some(body) if !body.is_empty() => (true, body),
+                    _ => (false, single.as_str()),
+                };
+                Ok(match meta(body, keys) {
+                    Some((key, value)) => Term::Meta {
+                        key,
+                        value: value.to_string(),
+                        negated,
+                    },
+                    None => Term::Tag {
+                        name: body.to_string(),
+                        negated,
+                    },
+                })
+            }
+            group => {
+                if group.iter().any(|tag| tag.starts_with('-') || meta(tag, keys).is_some()) {
+                    return Err(Error::InvalidInput(format!(
+                        "Only plain tags can be alternatives: {}",
+                        group.join(" | ")
+                    )));
+                }
+                Ok(Term::AnyOf(group.to_vec()))
+            }
+        })
+        .collect()
+}
+
+/// Whitespace separated terms, with alternatives joined by `|`
+fn split_terms(query: &str, keys: &[&'static str]) -> Vec<Vec<String>> {
+    let mut terms: Vec<Vec<String>> = Vec::new();
+    let mut join_next = false;
+    for token in query.split_whitespace() {
+        // `rating:s|q` is one term, not alternatives
+        if meta(token.trim_start_matches('-'), keys).is_some() {
+            terms.push(vec![token.replace('|', ",")]);
+            join_next = false;
+            continue;
+        }
+
+        let join = join_next || token.starts_with('|');
+        join_next = token.ends_with('|');
+        let parts: Vec<String> = token
+            .split('|')
+            .filter(|part| !part.is_empty())
+            .map(String::from)
+            .collect();
+        match terms.last_mut() {
+            _ if parts.is_empty() => join_next = true,
+            Some(last) if join => last.extend(parts),
+            _ => terms.push(parts),
+        }
+    }
+    terms
+}
+
+/// Key and value of a meta term with one of `keys`
+pub fn meta<'a>(term: &'a str, keys: &[&'static str]) -> Option<(&'static str, &'a str)> {
+    let (key, value) = term.split_once(':')?;
+    let key = keys.iter().find(|known| known.eq_ignore_ascii_case(key))?;
+    Some((key, value))
+}
+
+pub fn parse_rating(rating: &str) -> Option<Rating> {
+    match rating.to_ascii_lowercase().as_str() {
+        "s" | "safe" | "g" | "general" => Some(Rating::Safe),
+        "q" | "questionable" | "sketchy" | "sensitive" => Some(Rating::Questionable),
+        "e" | "explicit" | "nsfw" | "unsafe" => Some(Rating::Explicit),
+        _ => None,
+    }
+}
+
+/// The ratings allowed after `rating:value` (or with `negated`,
+/// `-rating:value`), where `allowed` were allowed before; empty means any
+pub fn parse_ratings(value: &str, negated: bool, allowed: &[Rating]) -> Option<Vec<Rating>> {
+    let ratings = value.split(',').map(parse_rating).collect::<Option<Vec<_>>>()?;
+    Some(match negated {
+        true => [Rating::Safe, Rating::Questionable, Rating::Explicit]
+            .into_iter()
+            .filter(|r| !ratings.contains(r))
+            .filter(|r| allowed.is_empty() || allowed.contains(r))
+            .collect(),
+        false => ratings,
+    })
+}
+
+/// Inclusive bounds, open where `None`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bounds<T> {
+    pub min: Option<T>,
+    pub max: Option<T>,
+}
+
+impl<T> Bounds<T> {
+    /// Take the bounds `other` sets
+    pub fn narrow(&mut self, other: Self) {
+        if other.min.is_some() {
+            self.min = other.min;
+        }
+        if other.max.is_some() {
+            self.max = other.max;
+        }
+    }
+}
+
+impl<T> Default for Bounds<T> {
+    fn default() -> Self {
+        Self { min: None, max: None }
+    }
+}
+
+/// A value with neighbours, so that `>x` and `<x` can be inclusive bounds
+pub trait Bound: Copy {
+    /// The closest value above, if any
+    fn above(self) -> Option<Self>;
+    /// The closest value below, if any
+    fn below(self) -> Option<Self>;
+}
+
+impl Bound for u8 {
+    fn above(self) -> Option<Self> {
+        self.checked_add(1)
+    }
+
+    fn below(self) -> Option<Self> {
+        self.checked_sub(1)
+    }
+}
+
+impl Bound for u32 {
+    fn above(self) -> Option<Self> {
+        self.checked_add(1)
+    }
+
+    fn below(self) -> Option<Self> {
+        self.checked_sub(1)
+    }
+}
+
+impl Bound for u64 {
+    fn above(self) -> Option<Self> {
+        self.checked_add(1)
+    }
+
+    fn below(self) -> Option<Self> {
+        self.checked_sub(1)
+    }
+}
+
+impl Bound for f64 {
+    fn above(self) -> Option<Self> {
+        Some(self.next_up())
+    }
+
+    fn below(self) -> Option<Self> {
+        Some(self.next_down())
+    }
+}
+
+/// To the millisecond, which is as precise as stored times are compared
+impl Bound for DateTime<Utc> {
+    fn above(self) -> Option<Self> {
+        self.checked_add_signed(Duration::milliseconds(1))
+    }
+
+    fn below(self) -> Option<Self> {
+        self.checked_sub_signed(Duration::milliseconds(1))
+    }
+}
+
+/// A range (see the [module docs](self)), where `parse` gives the lowest and
+/// highest value `x` stands for
+pub fn parse_bounds<T: Bound>(value: &str, parse: impl Fn(&str) -> Option<(T, T)>) -> Option<Bounds<T>> {
+    let bounds = |min, max| Some(Bounds { min, max });
+    if let Some((min, max)) = value.split_once("..") {
+        let min = if min.is_empty() { None } else { Some(parse(min)?.0) };
+        let max = if max.is_empty() { None } else { Some(parse(max)?.1) };
+        return bounds(min, max);
+    }
+    if let Some(min) = value.strip_prefix(">=") {
+        return bounds(Some(parse(min)?.0), None);
+    }
+    if let Some(min) = value.strip_prefix('>') {
+        return bounds(Some(parse(min)?.1.above()?), None);
+    }
+    if let Some(max) = value.strip_prefix("<=") {
+        return bounds(None, Some(parse(max)?.1));
+    }
+    if let Some(max) = value.strip_prefix('<') {
+        return bounds(None, Some(parse(max)?.0.below()?));
+    }
+    let (min, max) = parse(value)?;
+    bounds(Some(min), Some(max))
+}
+
+/// `1920x1080`, `>=1920x1080` or a named size, as minimum width and height
+pub fn parse_resolution(value: &str) -> Option<(u32, u32)> {
+    match value.to_ascii_lowercase().as_str() {
+        "hd" | "fhd" | "1080p" => return Some((1920, 1080)),
+        "qhd" | "1440p" => return Some((2560, 1440)),
+        "4k" | "uhd" | "2160p" => return Some((3840, 2160)),
+        _ => {}
+    }
+    let value = value.strip_prefix(">=").unwrap_or(value);
+    let (width, height) = value.split_once(['x', 'X'])?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+/// `16:9`, `16x9` or `1.78`
+pub fn parse_ratio(value: &str) -> Option<f64> {
+    let ratio = match value.split_once([':', 'x']) {
+        Some((width, height)) => width.parse::<f64>().ok()? / height.parse::<f64>().ok()?,
+        None => value.parse().ok()?,
+    };
+    (ratio.is_finite() && ratio > 0.0).then_some(ratio)
+}
+
+/// Aspect ratios, width over height. A single ratio like `16:9` allows
+/// [`RATIO_TOLERANCE`] either way; the bounds of a range are exact.
+pub fn parse_ratio_bounds(value: &str) -> Option<Bounds<f64>> {
+    match value.to_ascii_lowercase().as_str() {
+        "landscape" | "wide" => Some(Bounds { min: Some(1.0 + RATIO_TOLERANCE), max: None }),
+        "portrait" | "tall" => Some(Bounds { min: None, max: Some(1.0 - RATIO_TOLERANCE) }),
+        "square" => Some(ratio_around(1.0)),
+        _ => match parse_ratio(value) {
+            Some(ratio) => Some(ratio_around(ratio)),
+            None => parse_bounds(value, |ratio| parse_ratio(ratio).map(|ratio| (ratio, ratio))),
+        },
+    }
+}
+
+/// `ratio` give or take [`RATIO_TOLERANCE`]
+pub fn ratio_around(ratio: f64) -> Bounds<f64> {
+    Bounds {
+        min: Some(ratio * (1.0 - RATIO_TOLERANCE)),
+        max: Some(ratio * (1.0 + RATIO_TOLERANCE)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYS: &[&str] = &["rating", "width", "ratio"];
+
+    fn tag(name: &str, negated: bool) -> Term {
+        Term::Tag { name: name.to_string(), negated }
+    }
+
+    #[test]
+    fn test_terms() {
+        let terms = terms("sky -city a | b c|d Rating:s|q -ratio:1 id:>5 -", KEYS).unwrap();
+        assert_eq!(
+            terms,
+            vec![
+                tag("sky", false),
+                tag("city", true),
+                Term::AnyOf(vec!["a".to_string(), "b".to_string()]),
+                Term::AnyOf(vec!["c".to_string(), "d".to_string()]),
+                Term::Meta { key: "rating", value: "s,q".to_string(), negated: false },
+                Term::Meta { key: "ratio", value: "1".to_string(), negated: true },
+                tag("id:>5", false),
+                tag("-", false),
+            ]
+        );
+        assert_eq!(terms[5].to_query(), "-ratio:1");
+        assert!(super::terms("a | -b", KEYS).is_err());
+    }
+
+    #[test]
+    fn test_bounds() {
+        let number = |value: &str| parse_bounds(value, |v| v.parse::<u32>().ok().map(|n| (n, n)));
+        assert_eq!(number(">=5"), Some(Bounds { min: Some(5), max: None }));
+        assert_eq!(number(">5"), Some(Bounds { min: Some(6), max: None }));
+        assert_eq!(number("<=5"), Some(Bounds { min: None, max: Some(5) }));
+        assert_eq!(number("<5"), Some(Bounds { min: None, max: Some(4) }));
+        assert_eq!(number("2..5"), Some(Bounds { min: Some(2), max: Some(5) }));
+        assert_eq!(number("..5"), Some(Bounds { min: None, max: Some(5) }));
+        assert_eq!(number("5"), Some(Bounds { min: Some(5), max: Some(5) }));
+        assert_eq!(number("<0"), None);
+        assert_eq!(number(">x"), None);
+
+        assert_eq!(parse_ratings("s,q", true, &[]), Some(vec![Rating::Explicit]));
+        assert_eq!(parse_ratings("e", true, &[Rating::Safe, Rating::Explicit]), Some(vec![Rating::Safe]));
+        assert_eq!(parse_ratings("x", false, &[]), None);
+    }
+
+    #[test]
+    fn test_ratio_bounds() {
+        let exact = parse_ratio_bounds("16:9").unwrap();
+        assert!(exact.min.unwrap() < 16.0 / 9.0 && exact.max.unwrap() > 16.0 / 9.0);
+
+        // Explicit bounds are not loosened
+        assert_eq!(parse_ratio_bounds(">=1.5"), Some(Bounds { min: Some(1.5), max: None }));
+        assert_eq!(parse_ratio_bounds("<=4:3").unwrap().max, Some(4.0 / 3.0));
+        assert_eq!(parse_ratio_bounds("1.5..2"), Some(Bounds { min: Some(1.5), max: Some(2.0) }));
+        assert!(parse_ratio_bounds(">1.5").unwrap().min.unwrap() > 1.5);
+
+        assert_eq!(parse_ratio_bounds("0:1"), None);
+        assert_eq!(parse_ratio_bounds("sideways"), None);
+    }
+}
@@ -0,0 +1,18 @@
+use crate::error::Result;
+use std::path::{Path, PathBuf};
+
+/// Longest edge of generated thumbnails, in pixels
+pub const THUMBNAIL_SIZE: u32 = 400;
+
+/// Write a JPEG thumbnail of `image_path` to `<thumbnails_dir>/<name>.jpg`
+pub fn generate(image_path: &Path, thumbnails_dir: &Path, name: &str) -> Result<PathBuf> {
+    std::fs::create_dir_all(thumbnails_dir)?;
+
+    let output = thumbnails_dir.join(format!("{}.jpg", name));
+    image::open(image_path)?
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .into_rgb8()
+        .save(&output)?;
+
+    Ok(output)
+}
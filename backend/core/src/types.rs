@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,10 +17,29 @@ pub struct Wallpaper {
     pub source_url: Option<String>,
     pub tags: Vec<String>,
     pub thumbnail_path: Option<String>,
+    /// Content rating reported by the source, for downloaded images
+    #[serde(default)]
+    pub rating: Option<Rating>,
+    /// How much it is liked and shown
+    #[serde(default)]
+    pub stats: WallpaperStats,
     pub created_at: DateTime<Utc>,
     pub modified_at: DateTime<Utc>,
 }
 
+/// Favorite status, user rating and usage of a library wallpaper
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WallpaperStats {
+    pub favorite: bool,
+    /// 1 to 5 stars; `None` when unrated
+    pub stars: Option<u8>,
+    /// Times it was set as wallpaper
+    pub times_set: u32,
+    /// Seconds it was shown in total, up to when it was last replaced
+    pub display_seconds: u64,
+    pub last_shown: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum WallpaperType {
@@ -27,14 +47,33 @@ pub enum WallpaperType {
     Video,
     Spine,           // Standalone Spine animations (.skel, .atlas, .png)
     WallpaperEngine, // Full Wallpaper Engine projects (project.json)
+    Live2D,          // Live2D Cubism models (.model3.json + .moc3)
+    Shader,          // Shadertoy-style GLSL fragment shaders (.frag, .glsl)
+    Web,             // HTML pages, URLs and Wallpaper Engine web projects
+    Generated,       // Procedural images rendered per monitor resolution
 }
 
 impl WallpaperType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Image => "image",
+            Self::Video => "video",
+            Self::Spine => "spine",
+            Self::WallpaperEngine => "wallpaper_engine",
+            Self::Live2D => "live2d",
+            Self::Shader => "shader",
+            Self::Web => "web",
+            Self::Generated => "generated",
+        }
+    }
+
     pub fn from_extension(ext: &str) -> Option<Self> {
         match ext.to_lowercase().as_str() {
             "jpg" | "jpeg" | "png" | "webp" | "bmp" => Some(Self::Image),
             "mp4" | "webm" | "mkv" | "avi" | "gif" => Some(Self::Video),
             "skel" | "atlas" => Some(Self::Spine),
+            "frag" | "glsl" => Some(Self::Shader),
+            "html" | "htm" => Some(Self::Web),
             _ => None,
         }
     }
@@ -42,6 +81,21 @@ impl WallpaperType {
     pub fn from_path(path: &str) -> Option<Self> {
         let path_obj = std::path::Path::new(path);
 
+        // Remote pages are web wallpapers
+        if path.starts_with("http://") || path.starts_with("https://") {
+            return Some(Self::Web);
+        }
+
+        // Wallpaper Engine "web" projects are plain HTML pages
+        let project_file = if path_obj.is_dir() {
+            path_obj.join("project.json")
+        } else {
+            path_obj.with_file_name("project.json")
+        };
+        if Self::is_web_project(&project_file) {
+            return Some(Self::Web);
+        }
+
         // Check for Wallpaper Engine project (has project.json)
         if path.ends_with("project.json") {
             return Some(Self::WallpaperEngine);
@@ -58,32 +112,67 @@ impl WallpaperType {
             }
         }
 
+        // Check for Live2D Cubism model (*.model3.json)
+        if Self::is_model3_file(path_obj) {
+            return Some(Self::Live2D);
+        }
+        if path_obj.is_dir() {
+            let has_model3 = std::fs::read_dir(path_obj)
+                .ok()?
+                .filter_map(|e| e.ok())
+                .any(|entry| Self::is_model3_file(&entry.path()));
+            if has_model3 {
+                return Some(Self::Live2D);
+            }
+        }
+
+        // Check for a shader directory (shader file plus iChannel textures)
+        if path_obj.is_dir() {
+            let has_shader = std::fs::read_dir(path_obj)
+                .ok()?
+                .filter_map(|e| e.ok())
+                .any(|entry| {
+                    entry.path()
+                        .extension()
+                        .and_then(|s| s.to_str())
+                        .map(|ext| Self::from_extension(ext) == Some(Self::Shader))
+                        .unwrap_or(false)
+                });
+            if has_shader {
+                return Some(Self::Shader);
+            }
+        }
+
         // Check for standalone Spine animation
-        // Must have .skel file AND NO project.json
+        // Must have .skel file (or a JSON skeleton next to an .atlas) AND NO project.json
         if let Some(parent) = if path_obj.is_dir() {
             Some(path_obj)
         } else {
             path_obj.parent()
         } {
-            let has_skel = std::fs::read_dir(parent).ok()?.any(|e| {
-                if let Ok(entry) = e {
-                    entry.path().extension()
-                        .and_then(|s| s.to_str())
-                        .map(|s| s == "skel")
-                        .unwrap_or(false)
-                } else {
-                    false
-                }
-            });
+            let names: Vec<String> = std::fs::read_dir(parent)
+                .ok()?
+                .filter_map(|e| e.ok())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .collect();
+
+            let has_skel = names.iter().any(|n| n.ends_with(".skel"));
+            let has_atlas = names.iter().any(|n| n.ends_with(".atlas") || n.ends_with(".atlas.txt"));
+            let has_json_skeleton = has_atlas && names.iter().any(|n| n.ends_with(".json"));
 
             let has_project = parent.join("project.json").exists();
 
-            // Spine ONLY if has .skel AND no project.json
-            if has_skel && !has_project {
+            // Spine ONLY if has a skeleton AND no project.json
+            if (has_skel || has_json_skeleton) && !has_project {
                 return Some(Self::Spine);
             }
         }
 
+        // A directory with an index page is a web wallpaper
+        if path_obj.is_dir() && path_obj.join("index.html").is_file() {
+            return Some(Self::Web);
+        }
+
         // Fallback to extension-based detection
         if let Some(ext) = path_obj.extension().and_then(|s| s.to_str()) {
             Self::from_extension(ext)
@@ -91,16 +180,122 @@ impl WallpaperType {
             None
         }
     }
+
+    fn is_web_project(project_file: &std::path::Path) -> bool {
+        std::fs::read_to_string(project_file)
+            .ok()
+            .and_then(|text| serde_json::from_str::<serde_json::Value>(&text).ok())
+            .and_then(|project| {
+                project
+                    .get("type")
+                    .and_then(|t| t.as_str())
+                    .map(|t| t.eq_ignore_ascii_case("web"))
+            })
+            .unwrap_or(false)
+    }
+
+    fn is_model3_file(path: &std::path::Path) -> bool {
+        path.file_name()
+            .and_then(|n| n.to_str())
+            .map(|n| n.to_lowercase().ends_with(".model3.json"))
+            .unwrap_or(false)
+    }
+}
+
+impl std::str::FromStr for WallpaperType {
+    type Err = crate::error::Error;
+
+    fn from_str(s: &str) -> crate::error::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "image" => Ok(Self::Image),
+            "video" => Ok(Self::Video),
+            "spine" => Ok(Self::Spine),
+            "wallpaper_engine" => Ok(Self::WallpaperEngine),
+            "live2d" => Ok(Self::Live2D),
+            "shader" => Ok(Self::Shader),
+            "web" => Ok(Self::Web),
+            "generated" => Ok(Self::Generated),
+            _ => Err(crate::error::Error::Parse(format!("Invalid wallpaper type: {}", s))),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tag {
     pub id: i64,
     pub name: String,
-    pub category: Option<String>,
+    pub category: Option<TagCategory>,
+    /// Library wallpapers with the tag
     pub count: i64,
 }
 
+/// Another name for a tag, e.g. `bg` for `background`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagAlias {
+    pub alias: String,
+    pub tag: String,
+}
+
+/// A tag that comes with another, e.g. `hatsune_miku` with `vocaloid`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagImplication {
+    pub tag: String,
+    pub implied: String,
+}
+
+/// Kind of tag, as boorus group them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagCategory {
+    General,
+    Artist,
+    Character,
+    Copyright,
+    Meta,
+}
+
+impl TagCategory {
+    pub const ALL: [Self; 5] = [Self::General, Self::Artist, Self::Character, Self::Copyright, Self::Meta];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::General => "general",
+            Self::Artist => "artist",
+            Self::Character => "character",
+            Self::Copyright => "copyright",
+            Self::Meta => "meta",
+        }
+    }
+
+    /// Place in tag suggestions, lowest first: what's in the picture before
+    /// who made it and how it's filed
+    pub fn rank(&self) -> u8 {
+        match self {
+            Self::General => 0,
+            Self::Character => 1,
+            Self::Copyright => 2,
+            Self::Artist => 3,
+            Self::Meta => 4,
+        }
+    }
+}
+
+impl std::str::FromStr for TagCategory {
+    type Err = crate::error::Error;
+
+    /// Also takes the names some sites use for the same categories
+    fn from_str(s: &str) -> crate::error::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "general" | "default" | "tag" | "species" | "lore" => Ok(Self::General),
+            "artist" | "circle" | "studio" | "author" => Ok(Self::Artist),
+            "character" => Ok(Self::Character),
+            "copyright" | "series" | "source" | "franchise" => Ok(Self::Copyright),
+            "meta" | "medium" | "genre" | "invalid" => Ok(Self::Meta),
+            _ => Err(crate::error::Error::Parse(format!("Invalid tag category: {}", s))),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Monitor {
     pub name: String,
@@ -128,6 +323,165 @@ pub enum DisplayMode {
     Tile,
 }
 
+/// sRGB color, written as `#rgb`, `#rrggbb` or `#rrggbbaa`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 255 }
+    }
+}
+
+impl std::str::FromStr for Color {
+    type Err = crate::error::Error;
+
+    fn from_str(s: &str) -> crate::error::Result<Self> {
+        let invalid = || crate::error::Error::Parse(format!("Invalid color: {}", s));
+        let hex = s.trim().trim_start_matches('#');
+        if !hex.is_ascii() {
+            return Err(invalid());
+        }
+
+        let channel = |i: usize, len: usize| -> crate::error::Result<u8> {
+            let value = u8::from_str_radix(&hex[i * len..(i + 1) * len], 16).map_err(|_| invalid())?;
+            Ok(if len == 1 { value * 17 } else { value })
+        };
+
+        match hex.len() {
+            3 => Ok(Self::rgb(channel(0, 1)?, channel(1, 1)?, channel(2, 1)?)),
+            6 => Ok(Self::rgb(channel(0, 2)?, channel(1, 2)?, channel(2, 2)?)),
+            8 => Ok(Self {
+                r: channel(0, 2)?,
+                g: channel(1, 2)?,
+                b: channel(2, 2)?,
+                a: channel(3, 2)?,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)?;
+        if self.a != 255 {
+            write!(f, "{:02x}", self.a)?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = crate::error::Error;
+
+    fn try_from(s: String) -> crate::error::Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> Self {
+        color.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pattern {
+    Stripes,
+    Checkerboard,
+    Dots,
+    Grid,
+}
+
+/// Parameters of a generated wallpaper, kept so it can be re-rendered
+/// whenever the monitor layout changes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GeneratorSpec {
+    Solid {
+        color: Color,
+    },
+    LinearGradient {
+        /// Evenly spaced color stops
+        colors: Vec<Color>,
+        /// Direction in degrees; 0 runs left to right, 90 top to bottom
+        #[serde(default)]
+        angle: f32,
+    },
+    RadialGradient {
+        colors: Vec<Color>,
+        /// Center relative to the image size (0.5, 0.5 is the middle)
+        #[serde(default = "default_center")]
+        center: [f32; 2],
+        /// Radius relative to half the image diagonal
+        #[serde(default = "default_radius")]
+        radius: f32,
+    },
+    Noise {
+        /// Colors mapped over the noise range, low to high
+        colors: Vec<Color>,
+        /// Feature size in pixels
+        #[serde(default = "default_noise_scale")]
+        scale: f32,
+        #[serde(default = "default_octaves")]
+        octaves: u32,
+        #[serde(default)]
+        seed: u32,
+    },
+    Pattern {
+        pattern: Pattern,
+        foreground: Color,
+        background: Color,
+        /// Pattern cell size in pixels
+        #[serde(default = "default_pattern_size")]
+        size: f32,
+        /// Rotation in degrees
+        #[serde(default)]
+        angle: f32,
+    },
+}
+
+fn default_center() -> [f32; 2] {
+    [0.5, 0.5]
+}
+
+fn default_radius() -> f32 {
+    1.0
+}
+
+fn default_noise_scale() -> f32 {
+    256.0
+}
+
+fn default_octaves() -> u32 {
+    4
+}
+
+fn default_pattern_size() -> f32 {
+    64.0
+}
+
+impl GeneratorSpec {
+    /// Short name of the generator, e.g. `linear_gradient`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Solid { .. } => "solid",
+            Self::LinearGradient { .. } => "linear_gradient",
+            Self::RadialGradient { .. } => "radial_gradient",
+            Self::Noise { .. } => "noise",
+            Self::Pattern { .. } => "pattern",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchQuery {
     pub tags: Vec<String>,
@@ -136,7 +490,8 @@ pub struct SearchQuery {
     pub rating: Option<Rating>,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// Content rating, from safest to least safe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Rating {
     Safe,
@@ -144,6 +499,29 @@ pub enum Rating {
     Explicit,
 }
 
+impl Rating {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Safe => "safe",
+            Self::Questionable => "questionable",
+            Self::Explicit => "explicit",
+        }
+    }
+}
+
+impl std::str::FromStr for Rating {
+    type Err = crate::error::Error;
+
+    fn from_str(s: &str) -> crate::error::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "safe" => Ok(Self::Safe),
+            "questionable" => Ok(Self::Questionable),
+            "explicit" => Ok(Self::Explicit),
+            _ => Err(crate::error::Error::Parse(format!("Invalid rating: {}", s))),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BooruImage {
     pub id: String,
@@ -158,6 +536,81 @@ pub struct BooruImage {
     pub score: Option<i32>,
     pub author: Option<String>,
     pub is_nsfw: bool,
+    /// MD5 of the original file, when the source publishes it
+    #[serde(default)]
+    pub md5: Option<String>,
+    /// Size of the original file in bytes, when the source publishes it
+    #[serde(default)]
+    pub file_size: Option<u64>,
+    /// Page the image is published on, for linking back to the author
+    #[serde(default)]
+    pub page_url: Option<String>,
+    /// Original the source credits the image to, such as the artist's post
+    #[serde(default)]
+    pub origin_url: Option<String>,
+    /// Credit or copyright line the source asks to display with the image
+    #[serde(default)]
+    pub attribution: Option<String>,
+    /// Categories of the tags the source groups; tags not listed have none
+    #[serde(default)]
+    pub tag_categories: HashMap<String, TagCategory>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadState {
+    Queued,
+    Downloading,
+    Paused,
+    Done,
+    Failed,
+}
+
+impl DownloadState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Downloading => "downloading",
+            Self::Paused => "paused",
+            Self::Done => "done",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+impl std::str::FromStr for DownloadState {
+    type Err = crate::error::Error;
+
+    fn from_str(s: &str) -> crate::error::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "queued" => Ok(Self::Queued),
+            "downloading" => Ok(Self::Downloading),
+            "paused" => Ok(Self::Paused),
+            "done" => Ok(Self::Done),
+            "failed" => Ok(Self::Failed),
+            _ => Err(crate::error::Error::Parse(format!("Invalid download state: {}", s))),
+        }
+    }
+}
+
+/// A booru image waiting in, or finished by, the download queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadJob {
+    pub id: i64,
+    pub image: BooruImage,
+    pub state: DownloadState,
+    /// Higher runs first
+    pub priority: i32,
+    pub attempts: u32,
+    pub error: Option<String>,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+    /// Library path once the job is done
+    pub path: Option<String>,
+    /// Earliest time a failed attempt is retried
+    pub retry_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -5,7 +5,7 @@ use tracing::{info, error, warn};
 use wallmgr_api::{AppState, create_router};
 use wallmgr_core::{Config, Database};
 use wallmgr_adapters::detector::{detect_environment, detect_desktop_environment, check_command_available};
-use wallmgr_adapters::{Adapter, AdapterTrait};
+use wallmgr_adapters::AdapterTrait;
 
 #[derive(Parser)]
 #[command(name = "wallmgr-daemon")]
@@ -33,8 +33,8 @@ async fn run_daemon(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
     info!("Starting Wallmgr daemon");
 
     // Load configuration
-    let config = if let Some(config_path) = &cli.config {
-        tokio::task::spawn_blocking(move || Config::load_from_path(config_path.as_path()))
+    let config = if let Some(config_path) = cli.config.clone() {
+        tokio::task::spawn_blocking(move || Config::load_from_path(config_path))
             .await??
     } else {
         tokio::task::spawn_blocking(Config::load)
@@ -50,14 +50,17 @@ async fn run_daemon(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
     // Detect and initialize adapter
     let adapter = select_adapter().await?;
     if let Some(adapter) = &adapter {
-        info!("Using adapter: {}", adapter.name());
+        info!("Using adapter: {}", adapter.get_trait().name());
     } else {
         warn!("No suitable adapter found");
     }
 
     // Create app state
-    let state = AppState::new(config, database);
+    let state = AppState::new(config, database)?;
     *state.adapter.write().await = adapter;
+    state.start_download_queue();
+    state.start_daily_wallpaper()?;
+    state.start_favorites_sync();
 
     // Start HTTP server
     let app = create_router(state);
@@ -120,7 +123,7 @@ async fn select_adapter() -> Result<Option<wallmgr_adapters::Adapter>, Box<dyn s
     }
 
     // Wayland compositor specific
-    if let wallmgr_adapters::detector::Environment::Wayland = environment {
+    if let Some(wallmgr_adapters::detector::Environment::Wayland) = environment {
         if check_command_available("swww") && SwwwAdapter::new().is_available() {
             return Ok(Some(wallmgr_adapters::Adapter::Swww(SwwwAdapter::new())));
         }
@@ -156,8 +159,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let cli = Cli::parse();
 
-    match cli.command.unwrap_or(Commands::Run) {
-        Commands::Run => {
+    match cli.command {
+        Some(Commands::Run) | None => {
             run_daemon(&cli).await?;
         }
     }
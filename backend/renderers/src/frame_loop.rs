@@ -0,0 +1,101 @@
+use crate::surface::DesktopSurface;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tiny_skia::Pixmap;
+use wallmgr_core::error::{Error, Result};
+
+/// Per-frame information handed to a `FrameSource`
+#[derive(Debug, Clone, Copy)]
+pub struct FrameContext {
+    /// Seconds since playback started
+    pub time: f32,
+    /// Seconds since the previous frame
+    pub delta: f32,
+    pub width: u32,
+    pub height: u32,
+    /// Pointer position in frame pixels, if the pointer is over the surface
+    pub pointer: Option<(f32, f32)>,
+}
+
+/// Anything that can draw animated wallpaper frames in-process
+pub trait FrameSource: Send + 'static {
+    /// Render one frame at `ctx.width` x `ctx.height`
+    fn render(&mut self, ctx: &FrameContext) -> Result<Pixmap>;
+}
+
+/// Background thread that renders a `FrameSource` onto a `DesktopSurface`
+pub struct FrameLoop {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl FrameLoop {
+    /// Open a surface on `monitor` and start rendering at `fps`
+    pub fn spawn<S: FrameSource>(mut source: S, monitor: Option<&str>, fps: u32) -> Result<Self> {
+        let mut surface = DesktopSurface::open(monitor)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = stop.clone();
+        let frame_time = Duration::from_secs_f64(1.0 / fps.max(1) as f64);
+
+        let handle = std::thread::Builder::new()
+            .name("wallmgr-frame-loop".to_string())
+            .spawn(move || {
+                let (width, height) = surface.size();
+                let started = Instant::now();
+                let mut last = started;
+
+                while !stop_flag.load(Ordering::Relaxed) {
+                    let now = Instant::now();
+                    let ctx = FrameContext {
+                        time: (now - started).as_secs_f32(),
+                        delta: (now - last).as_secs_f32(),
+                        width,
+                        height,
+                        pointer: surface.pointer_position(),
+                    };
+                    last = now;
+
+                    let result = source
+                        .render(&ctx)
+                        .and_then(|frame| surface.present(&frame));
+
+                    if let Err(e) = result {
+                        tracing::error!("Frame loop stopped: {}", e);
+                        break;
+                    }
+
+                    if let Some(remaining) = frame_time.checked_sub(now.elapsed()) {
+                        std::thread::sleep(remaining);
+                    }
+                }
+            })
+            .map_err(|e| Error::Renderer(format!("Failed to start frame loop: {}", e)))?;
+
+        Ok(Self {
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.handle
+            .as_ref()
+            .map(|h| !h.is_finished())
+            .unwrap_or(false)
+    }
+
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for FrameLoop {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
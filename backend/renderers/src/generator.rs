@@ -0,0 +1,272 @@
+//! Procedural wallpapers: solid colors, gradients, noise and patterns
+//!
+//! A generated wallpaper is a directory holding one PNG per resolution
+//! (`<width>x<height>.png`), rendered from its `GeneratorSpec` on demand.
+
+use noise::{Fbm, MultiFractal, NoiseFn, Perlin};
+use std::path::{Path, PathBuf};
+use tiny_skia::{
+    GradientStop, LinearGradient, Paint, Pixmap, Point, RadialGradient, Rect, Shader,
+    SpreadMode, Transform,
+};
+use wallmgr_core::error::{Error, Result};
+use wallmgr_core::types::{Color, GeneratorSpec, Pattern};
+
+/// File name of the rendering for one resolution
+pub fn file_name(width: u32, height: u32) -> String {
+    format!("{}x{}.png", width, height)
+}
+
+/// Render `spec` into `dir`, replacing any existing rendering at this size
+pub fn render_to(spec: &GeneratorSpec, dir: &Path, width: u32, height: u32) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let output = dir.join(file_name(width, height));
+    render(spec, width, height)?
+        .save_png(&output)
+        .map_err(|e| Error::Renderer(format!("Failed to write {}: {}", output.display(), e)))?;
+    Ok(output)
+}
+
+/// Path of the rendering at this size, rendering it first if it doesn't exist
+pub fn ensure_rendered(spec: &GeneratorSpec, dir: &Path, width: u32, height: u32) -> Result<PathBuf> {
+    let output = dir.join(file_name(width, height));
+    if output.exists() {
+        return Ok(output);
+    }
+    render_to(spec, dir, width, height)
+}
+
+/// Render `spec` at `width` x `height`
+pub fn render(spec: &GeneratorSpec, width: u32, height: u32) -> Result<Pixmap> {
+    let mut pixmap = Pixmap::new(width, height)
+        .ok_or_else(|| Error::InvalidInput(format!("Invalid size {}x{}", width, height)))?;
+    let (w, h) = (width as f32, height as f32);
+
+    match spec {
+        GeneratorSpec::Solid { color } => {
+            pixmap.fill(skia_color(*color));
+        }
+        GeneratorSpec::LinearGradient { colors, angle } => {
+            let (sin, cos) = angle.to_radians().sin_cos();
+            // Half the extent of the image along the gradient direction
+            let half = (w * cos.abs() + h * sin.abs()) / 2.0;
+            let (cx, cy) = (w / 2.0, h / 2.0);
+            let shader = LinearGradient::new(
+                Point::from_xy(cx - cos * half, cy - sin * half),
+                Point::from_xy(cx + cos * half, cy + sin * half),
+                gradient_stops(colors)?,
+                SpreadMode::Pad,
+                Transform::identity(),
+            );
+            fill_shader(&mut pixmap, shader, colors)?;
+        }
+        GeneratorSpec::RadialGradient { colors, center, radius } => {
+            let center = Point::from_xy(center[0] * w, center[1] * h);
+            let radius = (radius * w.hypot(h) / 2.0).max(1.0);
+            let shader = RadialGradient::new(
+                center,
+                center,
+                radius,
+                gradient_stops(colors)?,
+                SpreadMode::Pad,
+                Transform::identity(),
+            );
+            fill_shader(&mut pixmap, shader, colors)?;
+        }
+        GeneratorSpec::Noise { colors, scale, octaves, seed } => {
+            if colors.is_empty() {
+                return Err(Error::InvalidInput("Noise needs at least one color".to_string()));
+            }
+            if *scale <= 0.0 {
+                return Err(Error::InvalidInput("Noise scale must be positive".to_string()));
+            }
+
+            let fbm = Fbm::<Perlin>::new(*seed).set_octaves((*octaves).clamp(1, 8) as usize);
+            let scale = *scale as f64;
+            fill_pixels(&mut pixmap, |x, y| {
+                let value = fbm.get([x as f64 / scale, y as f64 / scale]);
+                sample_colors(colors, ((value + 1.0) / 2.0) as f32)
+            });
+        }
+        GeneratorSpec::Pattern { pattern, foreground, background, size, angle } => {
+            if *size < 1.0 {
+                return Err(Error::InvalidInput("Pattern size must be at least 1 pixel".to_string()));
+            }
+
+            let (sin, cos) = angle.to_radians().sin_cos();
+            // 2x2 supersampling smooths the edges
+            const OFFSETS: [(f32, f32); 4] = [(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)];
+            fill_pixels(&mut pixmap, |x, y| {
+                let covered = OFFSETS
+                    .iter()
+                    .filter(|(dx, dy)| {
+                        let (px, py) = (x as f32 + dx, y as f32 + dy);
+                        let u = px * cos + py * sin;
+                        let v = py * cos - px * sin;
+                        in_pattern(*pattern, u / size, v / size)
+                    })
+                    .count();
+                mix(*background, *foreground, covered as f32 / OFFSETS.len() as f32)
+            });
+        }
+    }
+
+    Ok(pixmap)
+}
+
+/// Whether a point, in pattern cell units, is covered by the foreground
+fn in_pattern(pattern: Pattern, u: f32, v: f32) -> bool {
+    let (fu, fv) = (u - u.floor(), v - v.floor());
+    match pattern {
+        Pattern::Stripes => fu < 0.5,
+        Pattern::Checkerboard => (u.floor() + v.floor()).rem_euclid(2.0) < 1.0,
+        Pattern::Dots => (fu - 0.5).hypot(fv - 0.5) < 0.3,
+        Pattern::Grid => fu < 0.08 || fv < 0.08,
+    }
+}
+
+fn skia_color(color: Color) -> tiny_skia::Color {
+    tiny_skia::Color::from_rgba8(color.r, color.g, color.b, color.a)
+}
+
+fn gradient_stops(colors: &[Color]) -> Result<Vec<GradientStop>> {
+    if colors.is_empty() {
+        return Err(Error::InvalidInput("Gradient needs at least one color".to_string()));
+    }
+
+    let last = (colors.len() - 1).max(1) as f32;
+    Ok(colors
+        .iter()
+        .enumerate()
+        .map(|(i, c)| GradientStop::new(i as f32 / last, skia_color(*c)))
+        .collect())
+}
+
+/// Fill with a gradient shader; tiny-skia returns a solid color for single stops
+/// and None for degenerate geometry, where the first color is used instead
+fn fill_shader(pixmap: &mut Pixmap, shader: Option<Shader>, colors: &[Color]) -> Result<()> {
+    let Some(shader) = shader else {
+        pixmap.fill(skia_color(colors[0]));
+        return Ok(());
+    };
+
+    let rect = Rect::from_xywh(0.0, 0.0, pixmap.width() as f32, pixmap.height() as f32)
+        .ok_or_else(|| Error::Renderer("Invalid gradient area".to_string()))?;
+    let paint = Paint {
+        shader,
+        anti_alias: false,
+        ..Paint::default()
+    };
+    pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+    Ok(())
+}
+
+/// Set every pixel from a per-pixel color function
+fn fill_pixels(pixmap: &mut Pixmap, color_at: impl Fn(u32, u32) -> Color) {
+    let width = pixmap.width();
+    for (i, px) in pixmap.pixels_mut().iter_mut().enumerate() {
+        let c = color_at(i as u32 % width, i as u32 / width);
+        *px = tiny_skia::ColorU8::from_rgba(c.r, c.g, c.b, c.a).premultiply();
+    }
+}
+
+fn mix(a: Color, b: Color, t: f32) -> Color {
+    let lerp = |x: u8, y: u8| (x as f32 + (y as f32 - x as f32) * t).round() as u8;
+    Color {
+        r: lerp(a.r, b.r),
+        g: lerp(a.g, b.g),
+        b: lerp(a.b, b.b),
+        a: lerp(a.a, b.a),
+    }
+}
+
+/// Color at `t` (0..1) along evenly spaced stops
+fn sample_colors(colors: &[Color], t: f32) -> Color {
+    if colors.len() == 1 {
+        return colors[0];
+    }
+
+    let position = t.clamp(0.0, 1.0) * (colors.len() - 1) as f32;
+    let index = (position.floor() as usize).min(colors.len() - 2);
+    mix(colors[index], colors[index + 1], position - index as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb(pixmap: &Pixmap, x: u32, y: u32) -> (u8, u8, u8) {
+        let px = pixmap.pixel(x, y).unwrap();
+        (px.red(), px.green(), px.blue())
+    }
+
+    #[test]
+    fn test_gradients() {
+        let black = Color::rgb(0, 0, 0);
+        let white = Color::rgb(255, 255, 255);
+
+        let horizontal = render(
+            &GeneratorSpec::LinearGradient { colors: vec![black, white], angle: 0.0 },
+            100,
+            10,
+        )
+        .unwrap();
+        assert!(rgb(&horizontal, 0, 5).0 < 5);
+        assert!(rgb(&horizontal, 99, 5).0 > 250);
+        assert!((rgb(&horizontal, 50, 5).0 as i32 - 128).abs() < 8);
+
+        let vertical = render(
+            &GeneratorSpec::LinearGradient { colors: vec![black, white], angle: 90.0 },
+            10,
+            100,
+        )
+        .unwrap();
+        assert!(rgb(&vertical, 5, 0).0 < 5);
+        assert!(rgb(&vertical, 5, 99).0 > 250);
+
+        let radial = render(
+            &GeneratorSpec::RadialGradient { colors: vec![white, black], center: [0.5, 0.5], radius: 1.0 },
+            64,
+            64,
+        )
+        .unwrap();
+        assert!(rgb(&radial, 32, 32).0 > rgb(&radial, 0, 0).0);
+    }
+
+    #[test]
+    fn test_patterns_and_noise() {
+        let fg = Color::rgb(255, 0, 0);
+        let bg = Color::rgb(0, 0, 255);
+
+        let checker = render(
+            &GeneratorSpec::Pattern { pattern: Pattern::Checkerboard, foreground: fg, background: bg, size: 8.0, angle: 0.0 },
+            32,
+            32,
+        )
+        .unwrap();
+        assert_eq!(rgb(&checker, 2, 2), (255, 0, 0));
+        assert_eq!(rgb(&checker, 10, 2), (0, 0, 255));
+        assert_eq!(rgb(&checker, 10, 10), (255, 0, 0));
+
+        let spec = GeneratorSpec::Noise { colors: vec![bg, fg], scale: 16.0, octaves: 3, seed: 7 };
+        let a = render(&spec, 32, 32).unwrap();
+        let b = render(&spec, 32, 32).unwrap();
+        assert_eq!(a.data(), b.data());
+        assert!(a.pixels().iter().any(|p| p.red() != a.pixel(0, 0).unwrap().red()));
+    }
+
+    #[test]
+    fn test_renders_per_resolution_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let spec: GeneratorSpec =
+            serde_json::from_str(r##"{"kind": "solid", "color": "#336699"}"##).unwrap();
+
+        let path = ensure_rendered(&spec, dir.path(), 40, 20).unwrap();
+        assert_eq!(path, dir.path().join("40x20.png"));
+
+        let img = image::open(&path).unwrap().into_rgba8();
+        assert_eq!(img.dimensions(), (40, 20));
+        assert_eq!(img.get_pixel(5, 5).0, [0x33, 0x66, 0x99, 255]);
+        assert!(render(&GeneratorSpec::LinearGradient { colors: vec![], angle: 0.0 }, 4, 4).is_err());
+    }
+}
@@ -2,8 +2,20 @@ pub mod renderer;
 pub mod video;
 pub mod spine;
 pub mod wallpaper_engine;
+pub mod live2d;
+pub mod shader;
+pub mod web;
+pub mod generator;
+pub mod raster;
+pub mod surface;
+pub mod frame_loop;
 
 pub use renderer::{Renderer, RendererTrait};
 pub use video::VideoRenderer;
-pub use spine::SpineRenderer;
+pub use spine::{SpineOptions, SpinePlayer, SpineRenderer};
 pub use wallpaper_engine::WallpaperEngineRenderer;
+pub use live2d::{Live2DOptions, Live2DPlayer, Live2DRenderer};
+pub use shader::{ShaderOptions, ShaderPlayer, ShaderRenderer, ShaderSource};
+pub use web::{WebHost, WebOptions, WebProject, WebRenderer};
+pub use surface::DesktopSurface;
+pub use frame_loop::{FrameContext, FrameLoop, FrameSource};
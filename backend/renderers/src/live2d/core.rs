@@ -0,0 +1,388 @@
+//! Minimal bindings to the Live2D Cubism Core, loaded at runtime
+//!
+//! The Core is proprietary and can't be redistributed, so users install
+//! `libLive2DCubismCore.so` from the Cubism SDK themselves and wallmgr
+//! `dlopen`s it.
+
+use libloading::Library;
+use std::alloc::{self, Layout};
+use std::ffi::{c_char, c_void, CStr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use wallmgr_core::error::{Error, Result};
+
+const LIBRARY_NAME: &str = "libLive2DCubismCore.so";
+
+/// `csmAlignofMoc`
+const MOC_ALIGNMENT: usize = 64;
+/// `csmAlignofModel`
+const MODEL_ALIGNMENT: usize = 16;
+
+/// Constant drawable flags
+pub const BLEND_ADDITIVE: u8 = 1 << 0;
+pub const BLEND_MULTIPLICATIVE: u8 = 1 << 1;
+pub const IS_INVERTED_MASK: u8 = 1 << 3;
+/// Dynamic drawable flags
+pub const IS_VISIBLE: u8 = 1 << 0;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Vector2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+type Moc = c_void;
+type Model = c_void;
+
+struct Api {
+    get_version: unsafe extern "C" fn() -> u32,
+    get_latest_moc_version: unsafe extern "C" fn() -> u32,
+    get_moc_version: unsafe extern "C" fn(*const c_void, u32) -> u32,
+    revive_moc_in_place: unsafe extern "C" fn(*mut c_void, u32) -> *mut Moc,
+    get_sizeof_model: unsafe extern "C" fn(*const Moc) -> u32,
+    initialize_model_in_place: unsafe extern "C" fn(*const Moc, *mut c_void, u32) -> *mut Model,
+    update_model: unsafe extern "C" fn(*mut Model),
+    read_canvas_info: unsafe extern "C" fn(*const Model, *mut Vector2, *mut Vector2, *mut f32),
+    get_parameter_count: unsafe extern "C" fn(*const Model) -> i32,
+    get_parameter_ids: unsafe extern "C" fn(*const Model) -> *const *const c_char,
+    get_parameter_minimum_values: unsafe extern "C" fn(*const Model) -> *const f32,
+    get_parameter_maximum_values: unsafe extern "C" fn(*const Model) -> *const f32,
+    get_parameter_default_values: unsafe extern "C" fn(*const Model) -> *const f32,
+    get_parameter_values: unsafe extern "C" fn(*mut Model) -> *mut f32,
+    get_part_count: unsafe extern "C" fn(*const Model) -> i32,
+    get_part_ids: unsafe extern "C" fn(*const Model) -> *const *const c_char,
+    get_part_opacities: unsafe extern "C" fn(*mut Model) -> *mut f32,
+    get_drawable_count: unsafe extern "C" fn(*const Model) -> i32,
+    get_drawable_constant_flags: unsafe extern "C" fn(*const Model) -> *const u8,
+    get_drawable_dynamic_flags: unsafe extern "C" fn(*const Model) -> *const u8,
+    get_drawable_texture_indices: unsafe extern "C" fn(*const Model) -> *const i32,
+    get_drawable_render_orders: unsafe extern "C" fn(*const Model) -> *const i32,
+    get_drawable_opacities: unsafe extern "C" fn(*const Model) -> *const f32,
+    get_drawable_mask_counts: unsafe extern "C" fn(*const Model) -> *const i32,
+    get_drawable_masks: unsafe extern "C" fn(*const Model) -> *const *const i32,
+    get_drawable_vertex_counts: unsafe extern "C" fn(*const Model) -> *const i32,
+    get_drawable_vertex_positions: unsafe extern "C" fn(*const Model) -> *const *const Vector2,
+    get_drawable_vertex_uvs: unsafe extern "C" fn(*const Model) -> *const *const Vector2,
+    get_drawable_index_counts: unsafe extern "C" fn(*const Model) -> *const i32,
+    get_drawable_indices: unsafe extern "C" fn(*const Model) -> *const *const u16,
+    reset_drawable_dynamic_flags: unsafe extern "C" fn(*mut Model),
+}
+
+/// A loaded Cubism Core library
+pub struct CubismCore {
+    api: Api,
+    // Keeps the function pointers in `api` valid
+    _library: Library,
+}
+
+impl CubismCore {
+    /// Locate the Core: explicit path, `$LIVE2D_CUBISM_CORE`, then standard locations
+    pub fn locate(path: Option<&Path>) -> Option<PathBuf> {
+        if let Some(path) = path {
+            return path.exists().then(|| path.to_path_buf());
+        }
+
+        if let Ok(env) = std::env::var("LIVE2D_CUBISM_CORE") {
+            let path = PathBuf::from(env);
+            if path.exists() {
+                return Some(path);
+            }
+        }
+
+        let candidates = [
+            format!("~/.local/share/wallmgr/lib/{}", LIBRARY_NAME),
+            format!("~/.local/lib/{}", LIBRARY_NAME),
+            format!("/usr/local/lib/{}", LIBRARY_NAME),
+            format!("/usr/lib/{}", LIBRARY_NAME),
+        ];
+
+        candidates
+            .iter()
+            .map(|c| PathBuf::from(shellexpand::tilde(c).as_ref()))
+            .find(|p| p.exists())
+    }
+
+    pub fn load(path: Option<&Path>) -> Result<Arc<Self>> {
+        let path = Self::locate(path).ok_or_else(|| {
+            Error::Renderer(format!(
+                "{} not found; install it from the Cubism SDK or set renderer.live2d_core_path",
+                LIBRARY_NAME
+            ))
+        })?;
+
+        // SAFETY: the Cubism Core has no initialization side effects and every
+        // symbol is declared with the signature from Live2DCubismCore.h
+        unsafe {
+            let library = Library::new(&path)
+                .map_err(|e| Error::Renderer(format!("Failed to load {}: {}", path.display(), e)))?;
+
+            macro_rules! symbol {
+                ($name:literal) => {
+                    *library
+                        .get(concat!($name, "\0").as_bytes())
+                        .map_err(|e| Error::Renderer(format!("Cubism Core is missing {}: {}", $name, e)))?
+                };
+            }
+
+            let api = Api {
+                get_version: symbol!("csmGetVersion"),
+                get_latest_moc_version: symbol!("csmGetLatestMocVersion"),
+                get_moc_version: symbol!("csmGetMocVersion"),
+                revive_moc_in_place: symbol!("csmReviveMocInPlace"),
+                get_sizeof_model: symbol!("csmGetSizeofModel"),
+                initialize_model_in_place: symbol!("csmInitializeModelInPlace"),
+                update_model: symbol!("csmUpdateModel"),
+                read_canvas_info: symbol!("csmReadCanvasInfo"),
+                get_parameter_count: symbol!("csmGetParameterCount"),
+                get_parameter_ids: symbol!("csmGetParameterIds"),
+                get_parameter_minimum_values: symbol!("csmGetParameterMinimumValues"),
+                get_parameter_maximum_values: symbol!("csmGetParameterMaximumValues"),
+                get_parameter_default_values: symbol!("csmGetParameterDefaultValues"),
+                get_parameter_values: symbol!("csmGetParameterValues"),
+                get_part_count: symbol!("csmGetPartCount"),
+                get_part_ids: symbol!("csmGetPartIds"),
+                get_part_opacities: symbol!("csmGetPartOpacities"),
+                get_drawable_count: symbol!("csmGetDrawableCount"),
+                get_drawable_constant_flags: symbol!("csmGetDrawableConstantFlags"),
+                get_drawable_dynamic_flags: symbol!("csmGetDrawableDynamicFlags"),
+                get_drawable_texture_indices: symbol!("csmGetDrawableTextureIndices"),
+                get_drawable_render_orders: symbol!("csmGetDrawableRenderOrders"),
+                get_drawable_opacities: symbol!("csmGetDrawableOpacities"),
+                get_drawable_mask_counts: symbol!("csmGetDrawableMaskCounts"),
+                get_drawable_masks: symbol!("csmGetDrawableMasks"),
+                get_drawable_vertex_counts: symbol!("csmGetDrawableVertexCounts"),
+                get_drawable_vertex_positions: symbol!("csmGetDrawableVertexPositions"),
+                get_drawable_vertex_uvs: symbol!("csmGetDrawableVertexUvs"),
+                get_drawable_index_counts: symbol!("csmGetDrawableIndexCounts"),
+                get_drawable_indices: symbol!("csmGetDrawableIndices"),
+                reset_drawable_dynamic_flags: symbol!("csmResetDrawableDynamicFlags"),
+            };
+
+            Ok(Arc::new(Self {
+                api,
+                _library: library,
+            }))
+        }
+    }
+
+    /// Core version as `major.minor.patch`
+    pub fn version(&self) -> String {
+        let v = unsafe { (self.api.get_version)() };
+        format!("{}.{}.{}", v >> 24, (v >> 16) & 0xff, v & 0xffff)
+    }
+}
+
+/// Heap buffer with the alignment the Core requires for in-place objects
+struct AlignedBuffer {
+    ptr: *mut u8,
+    layout: Layout,
+}
+
+impl AlignedBuffer {
+    fn new(size: usize, align: usize) -> Result<Self> {
+        let layout = Layout::from_size_align(size.max(1), align)
+            .map_err(|e| Error::Renderer(format!("Invalid Cubism buffer layout: {}", e)))?;
+        let ptr = unsafe { alloc::alloc_zeroed(layout) };
+        if ptr.is_null() {
+            return Err(Error::Renderer("Out of memory allocating Cubism buffer".to_string()));
+        }
+        Ok(Self { ptr, layout })
+    }
+}
+
+impl Drop for AlignedBuffer {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.ptr, self.layout) };
+    }
+}
+
+/// One drawable mesh as of the last `update`
+pub struct Drawable<'a> {
+    pub constant_flags: u8,
+    pub dynamic_flags: u8,
+    pub texture: usize,
+    pub render_order: i32,
+    pub opacity: f32,
+    pub masks: &'a [i32],
+    pub positions: &'a [Vector2],
+    pub uvs: &'a [Vector2],
+    pub indices: &'a [u16],
+}
+
+/// An instantiated `.moc3` model
+pub struct CubismModel {
+    core: Arc<CubismCore>,
+    model: *mut Model,
+    parameter_ids: Vec<String>,
+    part_ids: Vec<String>,
+    _moc: AlignedBuffer,
+    _buffer: AlignedBuffer,
+}
+
+// SAFETY: the model memory is owned by this struct and only touched through
+// `&self`/`&mut self`, so moving it to the render thread is fine
+unsafe impl Send for CubismModel {}
+
+impl CubismModel {
+    pub fn load(core: Arc<CubismCore>, moc_path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(moc_path)?;
+        let size = u32::try_from(bytes.len())
+            .map_err(|_| Error::InvalidInput("moc3 file is too large".to_string()))?;
+
+        let moc_buffer = AlignedBuffer::new(bytes.len(), MOC_ALIGNMENT)?;
+        unsafe {
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), moc_buffer.ptr, bytes.len());
+
+            let api = &core.api;
+            let moc_version = (api.get_moc_version)(moc_buffer.ptr as *const c_void, size);
+            let latest = (api.get_latest_moc_version)();
+            if moc_version == 0 || moc_version > latest {
+                return Err(Error::Renderer(format!(
+                    "moc3 version {} is not supported by Cubism Core {}",
+                    moc_version,
+                    core.version()
+                )));
+            }
+
+            let moc = (api.revive_moc_in_place)(moc_buffer.ptr as *mut c_void, size);
+            if moc.is_null() {
+                return Err(Error::Parse(format!("Invalid moc3 file: {}", moc_path.display())));
+            }
+
+            let model_size = (api.get_sizeof_model)(moc);
+            let model_buffer = AlignedBuffer::new(model_size as usize, MODEL_ALIGNMENT)?;
+            let model = (api.initialize_model_in_place)(moc, model_buffer.ptr as *mut c_void, model_size);
+            if model.is_null() {
+                return Err(Error::Renderer("Failed to initialize Live2D model".to_string()));
+            }
+
+            let parameter_ids = read_ids((api.get_parameter_ids)(model), (api.get_parameter_count)(model));
+            let part_ids = read_ids((api.get_part_ids)(model), (api.get_part_count)(model));
+
+            Ok(Self {
+                model,
+                parameter_ids,
+                part_ids,
+                _moc: moc_buffer,
+                _buffer: model_buffer,
+                core,
+            })
+        }
+    }
+
+    pub fn parameter_ids(&self) -> &[String] {
+        &self.parameter_ids
+    }
+
+    pub fn parameter_index(&self, id: &str) -> Option<usize> {
+        self.parameter_ids.iter().position(|p| p == id)
+    }
+
+    pub fn part_index(&self, id: &str) -> Option<usize> {
+        self.part_ids.iter().position(|p| p == id)
+    }
+
+    pub fn parameter_defaults(&self) -> &[f32] {
+        unsafe {
+            slice((self.core.api.get_parameter_default_values)(self.model), self.parameter_ids.len())
+        }
+    }
+
+    pub fn parameter_range(&self, index: usize) -> (f32, f32) {
+        let n = self.parameter_ids.len();
+        unsafe {
+            let min = slice((self.core.api.get_parameter_minimum_values)(self.model), n);
+            let max = slice((self.core.api.get_parameter_maximum_values)(self.model), n);
+            (min[index], max[index])
+        }
+    }
+
+    pub fn parameter_values(&mut self) -> &mut [f32] {
+        let n = self.parameter_ids.len();
+        unsafe { slice_mut((self.core.api.get_parameter_values)(self.model), n) }
+    }
+
+    pub fn part_opacities(&mut self) -> &mut [f32] {
+        let n = self.part_ids.len();
+        unsafe { slice_mut((self.core.api.get_part_opacities)(self.model), n) }
+    }
+
+    /// Canvas size and origin in pixels, and pixels per model unit
+    pub fn canvas(&self) -> (Vector2, Vector2, f32) {
+        let mut size = Vector2::default();
+        let mut origin = Vector2::default();
+        let mut pixels_per_unit = 1.0;
+        unsafe { (self.core.api.read_canvas_info)(self.model, &mut size, &mut origin, &mut pixels_per_unit) };
+        (size, origin, pixels_per_unit)
+    }
+
+    /// Apply parameter and part changes to the drawables
+    pub fn update(&mut self) {
+        unsafe {
+            (self.core.api.reset_drawable_dynamic_flags)(self.model);
+            (self.core.api.update_model)(self.model);
+        }
+    }
+
+    pub fn drawables(&self) -> Vec<Drawable<'_>> {
+        let api = &self.core.api;
+        let model = self.model as *const Model;
+
+        unsafe {
+            let n = (api.get_drawable_count)(model).max(0) as usize;
+            let constant_flags = slice((api.get_drawable_constant_flags)(model), n);
+            let dynamic_flags = slice((api.get_drawable_dynamic_flags)(model), n);
+            let textures = slice((api.get_drawable_texture_indices)(model), n);
+            let render_orders = slice((api.get_drawable_render_orders)(model), n);
+            let opacities = slice((api.get_drawable_opacities)(model), n);
+            let mask_counts = slice((api.get_drawable_mask_counts)(model), n);
+            let masks = slice((api.get_drawable_masks)(model), n);
+            let vertex_counts = slice((api.get_drawable_vertex_counts)(model), n);
+            let positions = slice((api.get_drawable_vertex_positions)(model), n);
+            let uvs = slice((api.get_drawable_vertex_uvs)(model), n);
+            let index_counts = slice((api.get_drawable_index_counts)(model), n);
+            let indices = slice((api.get_drawable_indices)(model), n);
+
+            (0..n)
+                .map(|i| {
+                    let vertices = vertex_counts[i].max(0) as usize;
+                    Drawable {
+                        constant_flags: constant_flags[i],
+                        dynamic_flags: dynamic_flags[i],
+                        texture: textures[i].max(0) as usize,
+                        render_order: render_orders[i],
+                        opacity: opacities[i],
+                        masks: slice(masks[i], mask_counts[i].max(0) as usize),
+                        positions: slice(positions[i], vertices),
+                        uvs: slice(uvs[i], vertices),
+                        indices: slice(indices[i], index_counts[i].max(0) as usize),
+                    }
+                })
+                .collect()
+        }
+    }
+}
+
+unsafe fn read_ids(ids: *const *const c_char, count: i32) -> Vec<String> {
+    slice(ids, count.max(0) as usize)
+        .iter()
+        .map(|&id| CStr::from_ptr(id).to_string_lossy().into_owned())
+        .collect()
+}
+
+unsafe fn slice<'a, T>(ptr: *const T, len: usize) -> &'a [T] {
+    if ptr.is_null() || len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(ptr, len)
+    }
+}
+
+unsafe fn slice_mut<'a, T>(ptr: *mut T, len: usize) -> &'a mut [T] {
+    if ptr.is_null() || len == 0 {
+        &mut []
+    } else {
+        std::slice::from_raw_parts_mut(ptr, len)
+    }
+}
@@ -0,0 +1,497 @@
+//! Live2D Cubism renderer
+//!
+//! Reads `*.model3.json` bundles, drives the model with its idle motions,
+//! an optional expression, eye blinking and mouse-follow, and rasterizes the
+//! drawables in software. Mesh deformation is done by the Cubism Core, which
+//! is loaded at runtime (see [`core`]). Physics and pose files are not
+//! evaluated yet.
+
+pub mod core;
+pub mod model;
+
+use crate::frame_loop::{FrameContext, FrameLoop, FrameSource};
+use crate::raster;
+use crate::renderer::RendererTrait;
+use crate::surface::DesktopSurface;
+use self::core::{CubismCore, CubismModel, Drawable, Vector2};
+use model::{CurveTarget, Expression, ExpressionBlend, Live2DModel, Motion};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tiny_skia::{Color, Mask, MaskType, Pixmap};
+use wallmgr_core::error::{Error, Result};
+
+/// Seconds between eye blinks
+const BLINK_INTERVAL: f32 = 4.0;
+const BLINK_DURATION: f32 = 0.15;
+
+/// Playback settings for a Live2D wallpaper
+#[derive(Debug, Clone)]
+pub struct Live2DOptions {
+    /// Motion group to loop (None = `Idle`, else the first group)
+    pub motion_group: Option<String>,
+    /// Expression applied on top of the motions
+    pub expression: Option<String>,
+    /// Turn head, body and eyes towards the pointer
+    pub follow_mouse: bool,
+    /// Explicit path to `libLive2DCubismCore.so`
+    pub core_path: Option<PathBuf>,
+    pub fps: u32,
+    /// Fraction of the frame the canvas may cover
+    pub fit: f32,
+    /// Straight-alpha RGBA background
+    pub background: [u8; 4],
+}
+
+impl Default for Live2DOptions {
+    fn default() -> Self {
+        Self {
+            motion_group: None,
+            expression: None,
+            follow_mouse: false,
+            core_path: None,
+            fps: 30,
+            fit: 1.0,
+            background: [0, 0, 0, 255],
+        }
+    }
+}
+
+/// A loaded Live2D model with its textures and motions
+pub struct Live2DPlayer {
+    model: CubismModel,
+    parameters: HashMap<String, usize>,
+    defaults: Vec<f32>,
+    textures: Vec<Option<Pixmap>>,
+    motions: Vec<Motion>,
+    current_motion: usize,
+    motion_started: f32,
+    expression: Option<Expression>,
+    eye_blink: Vec<usize>,
+    /// Smoothed pointer direction in [-1, 1]
+    look: (f32, f32),
+    options: Live2DOptions,
+}
+
+impl Live2DPlayer {
+    pub fn load(path: &Path, options: Live2DOptions) -> Result<Self> {
+        let metadata = Live2DModel::load(path)?;
+        let core = CubismCore::load(options.core_path.as_deref())?;
+        let model = CubismModel::load(core, &metadata.moc)?;
+
+        let textures = metadata
+            .textures
+            .iter()
+            .map(|path| {
+                let texture = raster::load_texture(path, false);
+                if texture.is_none() {
+                    tracing::warn!("Failed to load Live2D texture {}", path.display());
+                }
+                texture
+            })
+            .collect();
+
+        let motions = metadata
+            .idle_motions(options.motion_group.as_deref())
+            .iter()
+            .filter_map(|m| match Motion::load(m) {
+                Ok(motion) => Some(motion),
+                Err(e) => {
+                    tracing::warn!("Skipping Live2D motion {}: {}", m.path.display(), e);
+                    None
+                }
+            })
+            .collect();
+
+        let expression = match &options.expression {
+            Some(name) => {
+                let (_, path) = metadata
+                    .expressions
+                    .iter()
+                    .find(|(n, _)| n == name)
+                    .ok_or_else(|| Error::NotFound(format!("Expression not found: {}", name)))?;
+                Some(Expression::load(path)?)
+            }
+            None => None,
+        };
+
+        let parameters: HashMap<String, usize> = model
+            .parameter_ids()
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id.clone(), i))
+            .collect();
+        let eye_blink = metadata
+            .eye_blink_parameters
+            .iter()
+            .filter_map(|id| parameters.get(id).copied())
+            .collect();
+        let defaults = model.parameter_defaults().to_vec();
+
+        Ok(Self {
+            model,
+            parameters,
+            defaults,
+            textures,
+            motions,
+            current_motion: 0,
+            motion_started: 0.0,
+            expression,
+            eye_blink,
+            look: (0.0, 0.0),
+            options,
+        })
+    }
+
+    /// Compute parameter values for this frame and update the model
+    fn animate(&mut self, ctx: &FrameContext) {
+        let time = ctx.time;
+        let values = self.model.parameter_values();
+        values.copy_from_slice(&self.defaults);
+
+        if !self.motions.is_empty() {
+            let mut elapsed = time - self.motion_started;
+            let motion = &self.motions[self.current_motion];
+            if !motion.looped && elapsed >= motion.duration {
+                self.current_motion = (self.current_motion + 1) % self.motions.len();
+                self.motion_started = time;
+                elapsed = 0.0;
+            }
+
+            let motion = &self.motions[self.current_motion];
+            let local = if motion.looped && motion.duration > 0.0 {
+                elapsed % motion.duration
+            } else {
+                elapsed
+            };
+            let weight = motion.weight(elapsed);
+
+            for curve in &motion.curves {
+                match curve.target {
+                    CurveTarget::Parameter => {
+                        if let Some(&i) = self.parameters.get(&curve.id) {
+                            let values = self.model.parameter_values();
+                            values[i] += (curve.value(local) - values[i]) * weight;
+                        }
+                    }
+                    CurveTarget::PartOpacity => {
+                        if let Some(i) = self.model.part_index(&curve.id) {
+                            self.model.part_opacities()[i] = curve.value(local);
+                        }
+                    }
+                    CurveTarget::Model => {}
+                }
+            }
+        }
+
+        if let Some(expression) = &self.expression {
+            for (id, value, blend) in &expression.parameters {
+                if let Some(&i) = self.parameters.get(id) {
+                    let values = self.model.parameter_values();
+                    match blend {
+                        ExpressionBlend::Add => values[i] += value,
+                        ExpressionBlend::Multiply => values[i] *= value,
+                        ExpressionBlend::Overwrite => values[i] = *value,
+                    }
+                }
+            }
+        }
+
+        // Close and reopen the eyes once per interval
+        let phase = time % BLINK_INTERVAL;
+        if phase < BLINK_DURATION {
+            let openness = (phase / (BLINK_DURATION / 2.0) - 1.0).abs();
+            let values = self.model.parameter_values();
+            for &i in &self.eye_blink {
+                values[i] *= openness;
+            }
+        }
+
+        if self.options.follow_mouse {
+            let target = match ctx.pointer {
+                Some((x, y)) if ctx.width > 0 && ctx.height > 0 => {
+                    let half_w = ctx.width as f32 / 2.0;
+                    let half_h = ctx.height as f32 / 2.0;
+                    (
+                        ((x - half_w) / half_w).clamp(-1.0, 1.0),
+                        ((half_h - y) / half_h).clamp(-1.0, 1.0),
+                    )
+                }
+                _ => (0.0, 0.0),
+            };
+            let follow = (ctx.delta * 5.0).min(1.0);
+            self.look.0 += (target.0 - self.look.0) * follow;
+            self.look.1 += (target.1 - self.look.1) * follow;
+
+            let (x, y) = self.look;
+            for (id, value, add) in [
+                ("ParamAngleX", x * 30.0, true),
+                ("ParamAngleY", y * 30.0, true),
+                ("ParamAngleZ", x * y * -30.0, true),
+                ("ParamBodyAngleX", x * 10.0, true),
+                ("ParamEyeBallX", x, false),
+                ("ParamEyeBallY", y, false),
+            ] {
+                if let Some(&i) = self.parameters.get(id) {
+                    let values = self.model.parameter_values();
+                    if add {
+                        values[i] += value;
+                    } else {
+                        values[i] = value;
+                    }
+                }
+            }
+        }
+
+        for i in 0..self.defaults.len() {
+            let (min, max) = self.model.parameter_range(i);
+            let values = self.model.parameter_values();
+            values[i] = values[i].clamp(min, max);
+        }
+
+        self.model.update();
+    }
+
+    fn draw(&self, width: u32, height: u32) -> Result<Pixmap> {
+        let mut pixmap = Pixmap::new(width, height)
+            .ok_or_else(|| Error::Renderer(format!("Invalid frame size {}x{}", width, height)))?;
+        let [r, g, b, a] = self.options.background;
+        pixmap.fill(Color::from_rgba8(r, g, b, a));
+
+        let (size, origin, pixels_per_unit) = self.model.canvas();
+        let scale = (width as f32 / size.x.max(1.0)).min(height as f32 / size.y.max(1.0)) * self.options.fit;
+        let offset_x = (width as f32 - size.x * scale) / 2.0;
+        let offset_y = (height as f32 - size.y * scale) / 2.0;
+        // Model units are y-up around the canvas origin
+        let to_screen = |v: &Vector2| {
+            (
+                offset_x + (origin.x + v.x * pixels_per_unit) * scale,
+                offset_y + (origin.y - v.y * pixels_per_unit) * scale,
+            )
+        };
+
+        let drawables = self.model.drawables();
+        let mut order: Vec<usize> = (0..drawables.len()).collect();
+        order.sort_by_key(|&i| drawables[i].render_order);
+
+        let mut masks: HashMap<(Vec<i32>, bool), Mask> = HashMap::new();
+
+        for i in order {
+            let drawable = &drawables[i];
+            if drawable.dynamic_flags & self::core::IS_VISIBLE == 0 || drawable.opacity <= 0.0 {
+                continue;
+            }
+
+            let blend_mode = if drawable.constant_flags & self::core::BLEND_ADDITIVE != 0 {
+                tiny_skia::BlendMode::Plus
+            } else if drawable.constant_flags & self::core::BLEND_MULTIPLICATIVE != 0 {
+                tiny_skia::BlendMode::Multiply
+            } else {
+                tiny_skia::BlendMode::SourceOver
+            };
+
+            let mask = if drawable.masks.is_empty() {
+                None
+            } else {
+                let inverted = drawable.constant_flags & self::core::IS_INVERTED_MASK != 0;
+                let key = (drawable.masks.to_vec(), inverted);
+                if !masks.contains_key(&key) {
+                    let mut mask_pixmap = Pixmap::new(width, height)
+                        .ok_or_else(|| Error::Renderer("Failed to allocate clip mask".to_string()))?;
+                    for &m in drawable.masks {
+                        if let Some(clip) = drawables.get(m as usize) {
+                            self.fill_drawable(&mut mask_pixmap, clip, &to_screen, 1.0, tiny_skia::BlendMode::SourceOver, None);
+                        }
+                    }
+                    let mut mask = Mask::from_pixmap(mask_pixmap.as_ref(), MaskType::Alpha);
+                    if inverted {
+                        mask.invert();
+                    }
+                    masks.insert(key.clone(), mask);
+                }
+                masks.get(&key)
+            };
+
+            self.fill_drawable(&mut pixmap, drawable, &to_screen, drawable.opacity, blend_mode, mask);
+        }
+
+        Ok(pixmap)
+    }
+
+    fn fill_drawable(
+        &self,
+        target: &mut Pixmap,
+        drawable: &Drawable,
+        to_screen: &dyn Fn(&Vector2) -> (f32, f32),
+        opacity: f32,
+        blend_mode: tiny_skia::BlendMode,
+        mask: Option<&Mask>,
+    ) {
+        let Some(texture) = self.textures.get(drawable.texture).and_then(|t| t.as_ref()) else {
+            return;
+        };
+        let (tw, th) = (texture.width() as f32, texture.height() as f32);
+
+        let dst: Vec<(f32, f32)> = drawable.positions.iter().map(to_screen).collect();
+        // Cubism UVs have their origin at the bottom left
+        let src: Vec<(f32, f32)> = drawable.uvs.iter().map(|uv| (uv.x * tw, (1.0 - uv.y) * th)).collect();
+
+        for tri in drawable.indices.chunks_exact(3) {
+            let (i0, i1, i2) = (tri[0] as usize, tri[1] as usize, tri[2] as usize);
+            if i0.max(i1).max(i2) >= dst.len() {
+                continue;
+            }
+            raster::fill_textured(
+                target,
+                texture.as_ref(),
+                &[dst[i0], dst[i1], dst[i2]],
+                &[src[i0], src[i1], src[i2]],
+                opacity,
+                blend_mode,
+                false,
+                mask,
+            );
+        }
+    }
+}
+
+impl FrameSource for Live2DPlayer {
+    fn render(&mut self, ctx: &FrameContext) -> Result<Pixmap> {
+        self.animate(ctx);
+        self.draw(ctx.width, ctx.height)
+    }
+}
+
+/// Live2D renderer - Renders Cubism models (`*.model3.json`) in-process
+pub struct Live2DRenderer {
+    options: Live2DOptions,
+    frame_loop: Option<FrameLoop>,
+    current_path: Option<String>,
+}
+
+impl Live2DRenderer {
+    pub fn new() -> Self {
+        Self::with_options(Live2DOptions::default())
+    }
+
+    pub fn with_options(options: Live2DOptions) -> Self {
+        Self {
+            options,
+            frame_loop: None,
+            current_path: None,
+        }
+    }
+
+    /// Detect if directory contains a Live2D model
+    pub fn is_live2d_directory(path: &Path) -> bool {
+        path.is_dir() && Live2DModel::find_model_file(path).is_some()
+    }
+
+    /// Read model metadata (motions, expressions, textures) without the Cubism Core
+    pub fn read_model(path: &Path) -> Result<Live2DModel> {
+        Live2DModel::load(path)
+    }
+
+    /// Render a single frame offscreen, e.g. for thumbnails and previews
+    pub fn render_frame(&self, path: &Path, width: u32, height: u32, time: f32) -> Result<Pixmap> {
+        let mut player = Live2DPlayer::load(path, self.options.clone())?;
+        player.render(&FrameContext {
+            time,
+            delta: 0.0,
+            width,
+            height,
+            pointer: None,
+        })
+    }
+
+    /// Render the first frame to a PNG file
+    pub fn render_thumbnail(&self, path: &Path, output: &Path, width: u32, height: u32) -> Result<()> {
+        self.render_frame(path, width, height, 0.0)?
+            .save_png(output)
+            .map_err(|e| Error::Renderer(format!("Failed to write thumbnail: {}", e)))
+    }
+}
+
+impl Default for Live2DRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RendererTrait for Live2DRenderer {
+    fn name(&self) -> &str {
+        "live2d"
+    }
+
+    fn is_available(&self) -> bool {
+        CubismCore::locate(self.options.core_path.as_deref()).is_some() && DesktopSurface::is_supported()
+    }
+
+    fn start(&mut self, path: &Path, monitor: Option<&str>) -> Result<()> {
+        // Stop any existing playback
+        self.stop()?;
+
+        let player = Live2DPlayer::load(path, self.options.clone())?;
+        self.frame_loop = Some(FrameLoop::spawn(player, monitor, self.options.fps)?);
+        self.current_path = Some(path.to_string_lossy().to_string());
+
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        if let Some(mut frame_loop) = self.frame_loop.take() {
+            frame_loop.stop();
+        }
+        self.current_path = None;
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.frame_loop
+            .as_ref()
+            .map(|f| f.is_running())
+            .unwrap_or(false)
+    }
+}
+
+impl Drop for Live2DRenderer {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODEL: &str = r#"{
+        "Version": 3,
+        "FileReferences": { "Moc": "hiyori.moc3", "Textures": ["texture_00.png"] }
+    }"#;
+
+    #[test]
+    fn test_detects_live2d_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!Live2DRenderer::is_live2d_directory(dir.path()));
+
+        std::fs::write(dir.path().join("hiyori.model3.json"), MODEL).unwrap();
+        assert!(Live2DRenderer::is_live2d_directory(dir.path()));
+
+        let model = Live2DRenderer::read_model(dir.path()).unwrap();
+        assert_eq!(model.moc, dir.path().join("hiyori.moc3"));
+    }
+
+    #[test]
+    fn test_missing_core_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("hiyori.model3.json"), MODEL).unwrap();
+
+        let renderer = Live2DRenderer::with_options(Live2DOptions {
+            core_path: Some(dir.path().join("libLive2DCubismCore.so")),
+            ..Live2DOptions::default()
+        });
+        assert!(!renderer.is_available());
+
+        let err = renderer.render_frame(dir.path(), 64, 64, 0.0).err().unwrap();
+        assert!(err.to_string().contains("libLive2DCubismCore.so"));
+    }
+}
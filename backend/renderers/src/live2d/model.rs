@@ -0,0 +1,501 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use wallmgr_core::error::{Error, Result};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Model3Json {
+    file_references: FileReferences,
+    #[serde(default)]
+    groups: Vec<ParameterGroup>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct FileReferences {
+    moc: String,
+    #[serde(default)]
+    textures: Vec<String>,
+    physics: Option<String>,
+    pose: Option<String>,
+    #[serde(default)]
+    expressions: Vec<ExpressionRefJson>,
+    #[serde(default)]
+    motions: BTreeMap<String, Vec<MotionRefJson>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ExpressionRefJson {
+    name: String,
+    file: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct MotionRefJson {
+    file: String,
+    fade_in_time: Option<f32>,
+    fade_out_time: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ParameterGroup {
+    name: String,
+    #[serde(default)]
+    ids: Vec<String>,
+}
+
+/// A motion file referenced from a model3.json group
+#[derive(Debug, Clone)]
+pub struct MotionRef {
+    pub path: PathBuf,
+    pub fade_in: Option<f32>,
+    pub fade_out: Option<f32>,
+}
+
+/// Metadata of a Live2D Cubism 3+ model bundle (`*.model3.json`)
+#[derive(Debug, Clone)]
+pub struct Live2DModel {
+    pub model_path: PathBuf,
+    pub moc: PathBuf,
+    pub textures: Vec<PathBuf>,
+    pub physics: Option<PathBuf>,
+    pub pose: Option<PathBuf>,
+    /// Expression name -> exp3.json path
+    pub expressions: Vec<(String, PathBuf)>,
+    /// Motion group name -> motions
+    pub motions: BTreeMap<String, Vec<MotionRef>>,
+    pub eye_blink_parameters: Vec<String>,
+    pub lip_sync_parameters: Vec<String>,
+}
+
+impl Live2DModel {
+    /// Load from a model3.json file or a directory containing one
+    pub fn load(path: &Path) -> Result<Self> {
+        let model_path = if path.is_dir() {
+            Self::find_model_file(path)
+                .ok_or_else(|| Error::NotFound("No .model3.json file found".to_string()))?
+        } else {
+            path.to_path_buf()
+        };
+
+        let content = std::fs::read_to_string(&model_path)?;
+        let dir = model_path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        Self::parse(&content, model_path, &dir)
+    }
+
+    fn parse(content: &str, model_path: PathBuf, dir: &Path) -> Result<Self> {
+        let json: Model3Json = serde_json::from_str(content)
+            .map_err(|e| Error::Parse(format!("Invalid model3.json: {}", e)))?;
+        let refs = json.file_references;
+
+        let group = |name: &str| {
+            json.groups
+                .iter()
+                .find(|g| g.name == name)
+                .map(|g| g.ids.clone())
+                .unwrap_or_default()
+        };
+
+        Ok(Self {
+            moc: dir.join(&refs.moc),
+            textures: refs.textures.iter().map(|t| dir.join(t)).collect(),
+            physics: refs.physics.map(|p| dir.join(p)),
+            pose: refs.pose.map(|p| dir.join(p)),
+            expressions: refs
+                .expressions
+                .into_iter()
+                .map(|e| (e.name, dir.join(e.file)))
+                .collect(),
+            motions: refs
+                .motions
+                .into_iter()
+                .map(|(group, motions)| {
+                    let motions = motions
+                        .into_iter()
+                        .map(|m| MotionRef {
+                            path: dir.join(m.file),
+                            fade_in: m.fade_in_time,
+                            fade_out: m.fade_out_time,
+                        })
+                        .collect();
+                    (group, motions)
+                })
+                .collect(),
+            eye_blink_parameters: group("EyeBlink"),
+            lip_sync_parameters: group("LipSync"),
+            model_path,
+        })
+    }
+
+    /// Find the first `*.model3.json` in a directory
+    pub fn find_model_file(dir: &Path) -> Option<PathBuf> {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+            .ok()?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|n| n.to_lowercase().ends_with(".model3.json"))
+                    .unwrap_or(false)
+            })
+            .collect();
+        files.sort();
+        files.into_iter().next()
+    }
+
+    /// Motions of the idle group (`Idle`, case-insensitive), else of the first group
+    pub fn idle_motions(&self, group: Option<&str>) -> &[MotionRef] {
+        let found = match group {
+            Some(name) => self.motions.get(name),
+            None => self
+                .motions
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("idle"))
+                .map(|(_, m)| m)
+                .or_else(|| self.motions.values().next()),
+        };
+        found.map(|m| m.as_slice()).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurveTarget {
+    Model,
+    Parameter,
+    PartOpacity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Segment {
+    Linear { end: (f32, f32) },
+    Bezier { c1: (f32, f32), c2: (f32, f32), end: (f32, f32) },
+    Stepped { end: (f32, f32) },
+    InverseStepped { end: (f32, f32) },
+}
+
+impl Segment {
+    fn end(&self) -> (f32, f32) {
+        match *self {
+            Segment::Linear { end }
+            | Segment::Bezier { end, .. }
+            | Segment::Stepped { end }
+            | Segment::InverseStepped { end } => end,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MotionCurve {
+    pub target: CurveTarget,
+    pub id: String,
+    pub fade_in: Option<f32>,
+    pub fade_out: Option<f32>,
+    start: (f32, f32),
+    segments: Vec<Segment>,
+}
+
+impl MotionCurve {
+    /// Value of the curve at `time` seconds
+    pub fn value(&self, time: f32) -> f32 {
+        let mut from = self.start;
+        if time <= from.0 {
+            return from.1;
+        }
+
+        for segment in &self.segments {
+            let to = segment.end();
+            if time <= to.0 {
+                let t = if to.0 > from.0 { (time - from.0) / (to.0 - from.0) } else { 1.0 };
+                return match *segment {
+                    Segment::Linear { .. } => from.1 + (to.1 - from.1) * t,
+                    Segment::Stepped { .. } => from.1,
+                    Segment::InverseStepped { .. } => to.1,
+                    Segment::Bezier { c1, c2, .. } => bezier_value(time, from, c1, c2, to),
+                };
+            }
+            from = to;
+        }
+
+        from.1
+    }
+}
+
+fn bezier_value(time: f32, p0: (f32, f32), p1: (f32, f32), p2: (f32, f32), p3: (f32, f32)) -> f32 {
+    let cubic = |a: f32, b: f32, c: f32, d: f32, s: f32| {
+        let inv = 1.0 - s;
+        inv * inv * inv * a + 3.0 * inv * inv * s * b + 3.0 * inv * s * s * c + s * s * s * d
+    };
+
+    let (mut lo, mut hi) = (0.0f32, 1.0f32);
+    for _ in 0..24 {
+        let mid = (lo + hi) * 0.5;
+        if cubic(p0.0, p1.0, p2.0, p3.0, mid) < time {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    cubic(p0.1, p1.1, p2.1, p3.1, (lo + hi) * 0.5)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Motion3Json {
+    meta: Motion3Meta,
+    #[serde(default)]
+    curves: Vec<Motion3Curve>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Motion3Meta {
+    duration: f32,
+    #[serde(default)]
+    r#loop: bool,
+    fade_in_time: Option<f32>,
+    fade_out_time: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Motion3Curve {
+    target: String,
+    id: String,
+    fade_in_time: Option<f32>,
+    fade_out_time: Option<f32>,
+    segments: Vec<f32>,
+}
+
+/// A parsed `*.motion3.json`
+#[derive(Debug, Clone)]
+pub struct Motion {
+    pub duration: f32,
+    pub looped: bool,
+    pub fade_in: f32,
+    pub fade_out: f32,
+    pub curves: Vec<MotionCurve>,
+}
+
+impl Motion {
+    pub fn load(motion: &MotionRef) -> Result<Self> {
+        let mut parsed = Self::parse(&std::fs::read_to_string(&motion.path)?)?;
+        if let Some(fade_in) = motion.fade_in {
+            parsed.fade_in = fade_in;
+        }
+        if let Some(fade_out) = motion.fade_out {
+            parsed.fade_out = fade_out;
+        }
+        Ok(parsed)
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let json: Motion3Json = serde_json::from_str(content)
+            .map_err(|e| Error::Parse(format!("Invalid motion3.json: {}", e)))?;
+
+        let curves = json
+            .curves
+            .into_iter()
+            .filter_map(|curve| {
+                let target = match curve.target.as_str() {
+                    "Parameter" => CurveTarget::Parameter,
+                    "PartOpacity" => CurveTarget::PartOpacity,
+                    "Model" => CurveTarget::Model,
+                    _ => return None,
+                };
+                let (start, segments) = parse_segments(&curve.segments)?;
+                Some(MotionCurve {
+                    target,
+                    id: curve.id,
+                    fade_in: curve.fade_in_time,
+                    fade_out: curve.fade_out_time,
+                    start,
+                    segments,
+                })
+            })
+            .collect();
+
+        Ok(Self {
+            duration: json.meta.duration,
+            looped: json.meta.r#loop,
+            fade_in: json.meta.fade_in_time.unwrap_or(1.0),
+            fade_out: json.meta.fade_out_time.unwrap_or(1.0),
+            curves,
+        })
+    }
+
+    /// Blend weight at `time`: eased fade in from the start, fade out before the end
+    pub fn weight(&self, time: f32) -> f32 {
+        let ease = |t: f32| 0.5 - 0.5 * (t.clamp(0.0, 1.0) * std::f32::consts::PI).cos();
+        let fade_in = if self.fade_in > 0.0 { ease(time / self.fade_in) } else { 1.0 };
+        let fade_out = if self.fade_out > 0.0 && !self.looped {
+            ease((self.duration - time) / self.fade_out)
+        } else {
+            1.0
+        };
+        fade_in * fade_out
+    }
+}
+
+/// Decode the flat motion3 segment array: a start point, then per segment a type
+/// id followed by its points (0 linear, 1 bezier, 2 stepped, 3 inverse stepped)
+fn parse_segments(flat: &[f32]) -> Option<((f32, f32), Vec<Segment>)> {
+    let point = |i: usize| Some((*flat.get(i)?, *flat.get(i + 1)?));
+    let start = point(0)?;
+
+    let mut segments = Vec::new();
+    let mut i = 2;
+    while i < flat.len() {
+        let kind = flat[i] as u32;
+        i += 1;
+        let segment = match kind {
+            0 => Segment::Linear { end: point(i)? },
+            1 => Segment::Bezier {
+                c1: point(i)?,
+                c2: point(i + 2)?,
+                end: point(i + 4)?,
+            },
+            2 => Segment::Stepped { end: point(i)? },
+            3 => Segment::InverseStepped { end: point(i)? },
+            _ => return None,
+        };
+        i += if kind == 1 { 6 } else { 2 };
+        segments.push(segment);
+    }
+
+    Some((start, segments))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpressionBlend {
+    Add,
+    Multiply,
+    Overwrite,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Exp3Json {
+    #[serde(default)]
+    parameters: Vec<Exp3Parameter>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Exp3Parameter {
+    id: String,
+    value: f32,
+    blend: Option<String>,
+}
+
+/// A parsed `*.exp3.json`
+#[derive(Debug, Clone)]
+pub struct Expression {
+    pub parameters: Vec<(String, f32, ExpressionBlend)>,
+}
+
+impl Expression {
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let json: Exp3Json = serde_json::from_str(content)
+            .map_err(|e| Error::Parse(format!("Invalid exp3.json: {}", e)))?;
+
+        Ok(Self {
+            parameters: json
+                .parameters
+                .into_iter()
+                .map(|p| {
+                    let blend = match p.blend.as_deref() {
+                        Some("Multiply") => ExpressionBlend::Multiply,
+                        Some("Overwrite") => ExpressionBlend::Overwrite,
+                        _ => ExpressionBlend::Add,
+                    };
+                    (p.id, p.value, blend)
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_model3() {
+        let content = r#"{
+            "Version": 3,
+            "FileReferences": {
+                "Moc": "haru.moc3",
+                "Textures": ["haru.2048/texture_00.png"],
+                "Physics": "haru.physics3.json",
+                "Expressions": [{ "Name": "smile", "File": "exp/smile.exp3.json" }],
+                "Motions": {
+                    "Idle": [{ "File": "motion/idle_01.motion3.json", "FadeInTime": 0.5 }],
+                    "TapBody": [{ "File": "motion/tap.motion3.json" }]
+                }
+            },
+            "Groups": [{ "Target": "Parameter", "Name": "EyeBlink", "Ids": ["ParamEyeLOpen", "ParamEyeROpen"] }]
+        }"#;
+
+        let dir = Path::new("/models/haru");
+        let model = Live2DModel::parse(content, dir.join("haru.model3.json"), dir).unwrap();
+
+        assert_eq!(model.moc, dir.join("haru.moc3"));
+        assert_eq!(model.textures, vec![dir.join("haru.2048/texture_00.png")]);
+        assert_eq!(model.expressions[0].0, "smile");
+        assert_eq!(model.motions.len(), 2);
+        assert_eq!(model.eye_blink_parameters, vec!["ParamEyeLOpen", "ParamEyeROpen"]);
+
+        let idle = model.idle_motions(None);
+        assert_eq!(idle.len(), 1);
+        assert_eq!(idle[0].fade_in, Some(0.5));
+        assert_eq!(model.idle_motions(Some("TapBody")).len(), 1);
+    }
+
+    #[test]
+    fn test_motion_segments() {
+        let content = r#"{
+            "Version": 3,
+            "Meta": { "Duration": 3.0, "Fps": 30.0, "Loop": true, "CurveCount": 1 },
+            "Curves": [{
+                "Target": "Parameter",
+                "Id": "ParamAngleX",
+                "Segments": [0, 0, 0, 1, 10, 2, 2, 20, 3, 3, 30]
+            }]
+        }"#;
+
+        let motion = Motion::parse(content).unwrap();
+        assert!(motion.looped);
+        let curve = &motion.curves[0];
+
+        assert_eq!(curve.value(0.5), 5.0);
+        // Stepped keeps the start value
+        assert_eq!(curve.value(1.5), 10.0);
+        // Inverse stepped jumps to the end value
+        assert_eq!(curve.value(2.5), 30.0);
+        assert_eq!(curve.value(5.0), 30.0);
+    }
+
+    #[test]
+    fn test_parse_expression() {
+        let content = r#"{
+            "Type": "Live2D Expression",
+            "Parameters": [
+                { "Id": "ParamMouthForm", "Value": 1, "Blend": "Overwrite" },
+                { "Id": "ParamEyeLSmile", "Value": 0.5 }
+            ]
+        }"#;
+
+        let expression = Expression::parse(content).unwrap();
+        assert_eq!(expression.parameters[0].2, ExpressionBlend::Overwrite);
+        assert_eq!(expression.parameters[1].2, ExpressionBlend::Add);
+    }
+}
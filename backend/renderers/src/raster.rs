@@ -0,0 +1,134 @@
+//! Software rasterization helpers shared by the in-process renderers
+//!
+//! Textures are sampled in pixel coordinates of the source pixmap, so callers
+//! never deal with normalized UVs.
+
+use tiny_skia::{
+    BlendMode, FillRule, FilterQuality, IntRect, Mask, Paint, PathBuilder, Pattern, Pixmap, PixmapRef,
+    SpreadMode, Transform,
+};
+
+/// Affine transform mapping the `src` triangle onto the `dst` triangle
+pub(crate) fn triangle_transform(src: [(f32, f32); 3], dst: [(f32, f32); 3]) -> Option<Transform> {
+    let (su1, sv1) = (src[1].0 - src[0].0, src[1].1 - src[0].1);
+    let (su2, sv2) = (src[2].0 - src[0].0, src[2].1 - src[0].1);
+    let (du1, dv1) = (dst[1].0 - dst[0].0, dst[1].1 - dst[0].1);
+    let (du2, dv2) = (dst[2].0 - dst[0].0, dst[2].1 - dst[0].1);
+
+    let det = su1 * sv2 - su2 * sv1;
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let m00 = (du1 * sv2 - du2 * sv1) / det;
+    let m01 = (du2 * su1 - du1 * su2) / det;
+    let m10 = (dv1 * sv2 - dv2 * sv1) / det;
+    let m11 = (dv2 * su1 - dv1 * su2) / det;
+    let tx = dst[0].0 - (m00 * src[0].0 + m01 * src[0].1);
+    let ty = dst[0].1 - (m10 * src[0].0 + m11 * src[0].1);
+
+    Some(Transform::from_row(m00, m10, m01, m11, tx, ty))
+}
+
+/// Fill a polygon with a texture mapped by the first three vertex pairs
+///
+/// Works for triangles and for parallelograms such as region attachments,
+/// where a single affine transform covers the whole quad. Pixels outside
+/// `mask` (if any) are left untouched.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn fill_textured(
+    target: &mut Pixmap,
+    texture: PixmapRef,
+    dst: &[(f32, f32)],
+    src: &[(f32, f32)],
+    opacity: f32,
+    blend_mode: BlendMode,
+    anti_alias: bool,
+    mask: Option<&Mask>,
+) {
+    if dst.len() < 3 || src.len() < 3 || opacity <= 0.0 {
+        return;
+    }
+
+    let transform = match triangle_transform([src[0], src[1], src[2]], [dst[0], dst[1], dst[2]]) {
+        Some(t) => t,
+        None => return,
+    };
+
+    let mut pb = PathBuilder::new();
+    pb.move_to(dst[0].0, dst[0].1);
+    for point in &dst[1..] {
+        pb.line_to(point.0, point.1);
+    }
+    pb.close();
+
+    let path = match pb.finish() {
+        Some(p) => p,
+        None => return,
+    };
+
+    let paint = Paint {
+        shader: Pattern::new(
+            texture,
+            SpreadMode::Pad,
+            FilterQuality::Bilinear,
+            opacity.min(1.0),
+            transform,
+        ),
+        blend_mode,
+        anti_alias,
+        ..Paint::default()
+    };
+
+    target.fill_path(&path, &paint, FillRule::Winding, Transform::identity(), mask);
+}
+
+/// Copy of the `rect` (x, y, x2, y2) part of `texture` with its colors
+/// multiplied by `tint`, and where the copy starts in `texture`
+pub(crate) fn tinted(texture: PixmapRef, rect: (f32, f32, f32, f32), tint: [f32; 3]) -> Option<(Pixmap, (f32, f32))> {
+    let x = rect.0.floor().max(0.0) as u32;
+    let y = rect.1.floor().max(0.0) as u32;
+    let x2 = (rect.2.ceil().max(0.0) as u32).min(texture.width());
+    let y2 = (rect.3.ceil().max(0.0) as u32).min(texture.height());
+    let mut copy = texture.clone_rect(IntRect::from_xywh(x as i32, y as i32, x2.checked_sub(x)?, y2.checked_sub(y)?)?)?;
+
+    // Scaling premultiplied color channels down keeps them premultiplied
+    let tint = tint.map(|c| c.clamp(0.0, 1.0));
+    for px in copy.data_mut().chunks_exact_mut(4) {
+        for (channel, factor) in px.iter_mut().zip(tint) {
+            *channel = (*channel as f32 * factor).round() as u8;
+        }
+    }
+
+    Some((copy, (x as f32, y as f32)))
+}
+
+/// Decode an image file into a premultiplied pixmap
+pub(crate) fn load_texture(path: &std::path::Path, premultiplied: bool) -> Option<Pixmap> {
+    let rgba = image::open(path).ok()?.into_rgba8();
+    let (width, height) = rgba.dimensions();
+    let mut data = rgba.into_raw();
+
+    if !premultiplied {
+        for px in data.chunks_exact_mut(4) {
+            let a = px[3] as u16;
+            px[0] = ((px[0] as u16 * a + 127) / 255) as u8;
+            px[1] = ((px[1] as u16 * a + 127) / 255) as u8;
+            px[2] = ((px[2] as u16 * a + 127) / 255) as u8;
+        }
+    }
+
+    Pixmap::from_vec(data, tiny_skia::IntSize::from_wh(width, height)?)
+}
+
+/// Convert a premultiplied pixmap into straight-alpha RGBA bytes
+pub fn pixmap_to_rgba(pixmap: &Pixmap) -> Vec<u8> {
+    pixmap
+        .pixels()
+        .iter()
+        .flat_map(|px| {
+            let c = px.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect()
+}
@@ -22,6 +22,9 @@ pub enum Renderer {
     Video(crate::video::VideoRenderer),
     Spine(crate::spine::SpineRenderer),
     WallpaperEngine(crate::wallpaper_engine::WallpaperEngineRenderer),
+    Live2D(crate::live2d::Live2DRenderer),
+    Shader(crate::shader::ShaderRenderer),
+    Web(crate::web::WebRenderer),
 }
 
 impl Renderer {
@@ -30,6 +33,9 @@ impl Renderer {
             Renderer::Video(r) => r,
             Renderer::Spine(r) => r,
             Renderer::WallpaperEngine(r) => r,
+            Renderer::Live2D(r) => r,
+            Renderer::Shader(r) => r,
+            Renderer::Web(r) => r,
         }
     }
 }
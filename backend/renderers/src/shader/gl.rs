@@ -0,0 +1,339 @@
+//! Headless OpenGL ES 3.0 through EGL
+//!
+//! Contexts are surfaceless: frames are drawn into a framebuffer object and
+//! read back, so the same path works for the live wallpaper, thumbnails and
+//! tests. `libEGL.so.1` is loaded at runtime.
+
+use glow::HasContext;
+use khronos_egl as egl;
+use std::ffi::{c_char, c_void, CStr};
+use tiny_skia::Pixmap;
+use wallmgr_core::error::{Error, Result};
+
+const EGL_LIBRARY: &str = "libEGL.so.1";
+const EGL_PLATFORM_DEVICE_EXT: egl::Enum = 0x313F;
+const EGL_PLATFORM_SURFACELESS_MESA: egl::Enum = 0x31DD;
+
+type QueryDevices = unsafe extern "C" fn(i32, *mut *mut c_void, *mut i32) -> egl::Boolean;
+type QueryDeviceString = unsafe extern "C" fn(*mut c_void, egl::Int) -> *const c_char;
+
+/// Fullscreen triangle without vertex buffers
+const VERTEX_SHADER: &str = "#version 300 es
+void main() {
+    vec2 p = vec2(float((gl_VertexID << 1) & 2), float(gl_VertexID & 2));
+    gl_Position = vec4(p * 2.0 - 1.0, 0.0, 1.0);
+}
+";
+
+pub struct GlContext {
+    egl: egl::DynamicInstance<egl::EGL1_5>,
+    display: egl::Display,
+    context: egl::Context,
+    pub gl: glow::Context,
+}
+
+impl GlContext {
+    /// Check whether EGL can be loaded at all
+    pub fn is_available() -> bool {
+        unsafe { egl::DynamicInstance::<egl::EGL1_5>::load_required_from_filename(EGL_LIBRARY).is_ok() }
+    }
+
+    /// Create a surfaceless GLES 3.0 context and make it current on this thread
+    ///
+    /// With `software` the context runs on Mesa's software device (llvmpipe)
+    /// so output doesn't depend on the GPU.
+    pub fn new(software: bool) -> Result<Self> {
+        let egl = unsafe { egl::DynamicInstance::<egl::EGL1_5>::load_required_from_filename(EGL_LIBRARY) }
+            .map_err(|e| Error::Renderer(format!("Failed to load {}: {}", EGL_LIBRARY, e)))?;
+
+        let display = unsafe {
+            if software {
+                let device = software_device(&egl).ok_or_else(|| {
+                    Error::Renderer("No software EGL device (EGL_MESA_device_software) found".to_string())
+                })?;
+                egl.get_platform_display(EGL_PLATFORM_DEVICE_EXT, device, &[egl::ATTRIB_NONE])
+            } else {
+                egl.get_platform_display(EGL_PLATFORM_SURFACELESS_MESA, egl::DEFAULT_DISPLAY, &[egl::ATTRIB_NONE])
+            }
+        }
+        .map_err(|e| Error::Renderer(format!("Failed to open EGL display: {}", e)))?;
+
+        egl.initialize(display)
+            .map_err(|e| Error::Renderer(format!("Failed to initialize EGL: {}", e)))?;
+        egl.bind_api(egl::OPENGL_ES_API)
+            .map_err(|e| Error::Renderer(format!("OpenGL ES is not supported: {}", e)))?;
+
+        let config = egl
+            .choose_first_config(
+                display,
+                &[
+                    egl::SURFACE_TYPE,
+                    egl::PBUFFER_BIT,
+                    egl::RENDERABLE_TYPE,
+                    egl::OPENGL_ES3_BIT,
+                    egl::NONE,
+                ],
+            )
+            .ok()
+            .flatten()
+            .ok_or_else(|| Error::Renderer("No EGL config supports OpenGL ES 3".to_string()))?;
+
+        let context = egl
+            .create_context(display, config, None, &[egl::CONTEXT_MAJOR_VERSION, 3, egl::NONE])
+            .map_err(|e| Error::Renderer(format!("Failed to create GL context: {}", e)))?;
+        egl.make_current(display, None, None, Some(context))
+            .map_err(|e| Error::Renderer(format!("Failed to make GL context current: {}", e)))?;
+
+        let gl = unsafe {
+            glow::Context::from_loader_function(|name| {
+                egl.get_proc_address(name)
+                    .map(|p| p as *const c_void)
+                    .unwrap_or(std::ptr::null())
+            })
+        };
+
+        Ok(Self {
+            egl,
+            display,
+            context,
+            gl,
+        })
+    }
+
+    /// `GL_RENDERER`, e.g. "llvmpipe (LLVM 15.0.6, 256 bits)"
+    pub fn renderer_name(&self) -> String {
+        unsafe { self.gl.get_parameter_string(glow::RENDERER) }
+    }
+}
+
+impl Drop for GlContext {
+    fn drop(&mut self) {
+        let _ = self.egl.make_current(self.display, None, None, None);
+        let _ = self.egl.destroy_context(self.display, self.context);
+        let _ = self.egl.terminate(self.display);
+    }
+}
+
+unsafe fn software_device(egl: &egl::DynamicInstance<egl::EGL1_5>) -> Option<*mut c_void> {
+    let query_devices: QueryDevices = std::mem::transmute(egl.get_proc_address("eglQueryDevicesEXT")?);
+    let query_string: QueryDeviceString = std::mem::transmute(egl.get_proc_address("eglQueryDeviceStringEXT")?);
+
+    let mut devices = [std::ptr::null_mut(); 16];
+    let mut count = 0;
+    if query_devices(devices.len() as i32, devices.as_mut_ptr(), &mut count) == egl::FALSE {
+        return None;
+    }
+
+    devices[..count.max(0) as usize].iter().copied().find(|&device| {
+        let extensions = query_string(device, egl::EXTENSIONS);
+        !extensions.is_null()
+            && CStr::from_ptr(extensions)
+                .to_string_lossy()
+                .contains("EGL_MESA_device_software")
+    })
+}
+
+/// Per-frame Shadertoy inputs
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Uniforms {
+    pub time: f32,
+    pub time_delta: f32,
+    pub frame: i32,
+    /// xy = pointer, zw = click position (Shadertoy convention, y-up)
+    pub mouse: [f32; 4],
+    /// Year, month (0-11), day, seconds since midnight
+    pub date: [f32; 4],
+}
+
+/// A compiled Shadertoy program rendering into its own framebuffer
+pub struct ShaderProgram {
+    program: glow::Program,
+    vertex_array: glow::VertexArray,
+    channels: Vec<Option<(glow::Texture, u32, u32)>>,
+    target: Option<(glow::Framebuffer, glow::Renderbuffer, u32, u32)>,
+}
+
+impl ShaderProgram {
+    /// Compile `fragment` and upload channel textures (straight RGBA, top row first)
+    pub fn new(ctx: &GlContext, fragment: &str, channels: &[Option<image::RgbaImage>]) -> Result<Self> {
+        let gl = &ctx.gl;
+
+        unsafe {
+            let vertex = compile(gl, glow::VERTEX_SHADER, VERTEX_SHADER)?;
+            let fragment = compile(gl, glow::FRAGMENT_SHADER, fragment).inspect_err(|_| gl.delete_shader(vertex))?;
+
+            let program = gl.create_program().map_err(Error::Renderer)?;
+            gl.attach_shader(program, vertex);
+            gl.attach_shader(program, fragment);
+            gl.link_program(program);
+            gl.delete_shader(vertex);
+            gl.delete_shader(fragment);
+
+            if !gl.get_program_link_status(program) {
+                let log = gl.get_program_info_log(program);
+                gl.delete_program(program);
+                return Err(Error::Renderer(format!("Failed to link shader: {}", log.trim())));
+            }
+
+            let vertex_array = gl.create_vertex_array().map_err(Error::Renderer)?;
+
+            let channels = channels
+                .iter()
+                .map(|image| {
+                    let image = image.as_ref()?;
+                    // GL samples bottom row first, like Shadertoy's default vflip
+                    let flipped = image::imageops::flip_vertical(image);
+                    let (w, h) = flipped.dimensions();
+
+                    let texture = gl.create_texture().ok()?;
+                    gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+                    gl.tex_image_2d(
+                        glow::TEXTURE_2D,
+                        0,
+                        glow::RGBA8 as i32,
+                        w as i32,
+                        h as i32,
+                        0,
+                        glow::RGBA,
+                        glow::UNSIGNED_BYTE,
+                        Some(flipped.as_raw()),
+                    );
+                    gl.generate_mipmap(glow::TEXTURE_2D);
+                    gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::LINEAR_MIPMAP_LINEAR as i32);
+                    gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::LINEAR as i32);
+                    gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::REPEAT as i32);
+                    gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::REPEAT as i32);
+                    Some((texture, w, h))
+                })
+                .collect();
+
+            Ok(Self {
+                program,
+                vertex_array,
+                channels,
+                target: None,
+            })
+        }
+    }
+
+    /// Draw one frame and read it back as an opaque pixmap
+    pub fn render(&mut self, ctx: &GlContext, width: u32, height: u32, uniforms: &Uniforms) -> Result<Pixmap> {
+        let gl = &ctx.gl;
+
+        unsafe {
+            self.ensure_target(gl, width, height)?;
+
+            gl.viewport(0, 0, width as i32, height as i32);
+            gl.use_program(Some(self.program));
+            gl.bind_vertex_array(Some(self.vertex_array));
+
+            let location = |name: &str| gl.get_uniform_location(self.program, name);
+            gl.uniform_3_f32(location("iResolution").as_ref(), width as f32, height as f32, 1.0);
+            gl.uniform_1_f32(location("iTime").as_ref(), uniforms.time);
+            gl.uniform_1_f32(location("iTimeDelta").as_ref(), uniforms.time_delta);
+            gl.uniform_1_f32(location("iFrameRate").as_ref(), if uniforms.time_delta > 0.0 { 1.0 / uniforms.time_delta } else { 0.0 });
+            gl.uniform_1_i32(location("iFrame").as_ref(), uniforms.frame);
+            gl.uniform_4_f32_slice(location("iMouse").as_ref(), &uniforms.mouse);
+            gl.uniform_4_f32_slice(location("iDate").as_ref(), &uniforms.date);
+
+            let mut resolutions = [0.0f32; 12];
+            for (i, channel) in self.channels.iter().enumerate().take(4) {
+                gl.active_texture(glow::TEXTURE0 + i as u32);
+                gl.bind_texture(glow::TEXTURE_2D, channel.map(|(t, _, _)| t));
+                gl.uniform_1_i32(location(&format!("iChannel{}", i)).as_ref(), i as i32);
+                if let Some((_, w, h)) = channel {
+                    resolutions[i * 3] = *w as f32;
+                    resolutions[i * 3 + 1] = *h as f32;
+                    resolutions[i * 3 + 2] = 1.0;
+                }
+            }
+            gl.uniform_3_f32_slice(location("iChannelResolution").as_ref(), &resolutions);
+
+            gl.draw_arrays(glow::TRIANGLES, 0, 3);
+
+            let mut rgba = vec![0u8; width as usize * height as usize * 4];
+            gl.read_pixels(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelPackData::Slice(&mut rgba),
+            );
+
+            // GL rows start at the bottom; force opaque output
+            let row = width as usize * 4;
+            let mut data = Vec::with_capacity(rgba.len());
+            for line in rgba.chunks_exact(row).rev() {
+                data.extend_from_slice(line);
+            }
+            for px in data.chunks_exact_mut(4) {
+                px[3] = 255;
+            }
+
+            tiny_skia::IntSize::from_wh(width, height)
+                .and_then(|size| Pixmap::from_vec(data, size))
+                .ok_or_else(|| Error::Renderer(format!("Invalid frame size {}x{}", width, height)))
+        }
+    }
+
+    unsafe fn ensure_target(&mut self, gl: &glow::Context, width: u32, height: u32) -> Result<()> {
+        if let Some((fbo, _, w, h)) = self.target {
+            if (w, h) == (width, height) {
+                gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fbo));
+                return Ok(());
+            }
+        }
+        self.delete_target(gl);
+
+        let fbo = gl.create_framebuffer().map_err(Error::Renderer)?;
+        let color = gl.create_renderbuffer().map_err(Error::Renderer)?;
+        gl.bind_renderbuffer(glow::RENDERBUFFER, Some(color));
+        gl.renderbuffer_storage(glow::RENDERBUFFER, glow::RGBA8, width as i32, height as i32);
+        gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fbo));
+        gl.framebuffer_renderbuffer(glow::FRAMEBUFFER, glow::COLOR_ATTACHMENT0, glow::RENDERBUFFER, Some(color));
+        self.target = Some((fbo, color, width, height));
+
+        if gl.check_framebuffer_status(glow::FRAMEBUFFER) != glow::FRAMEBUFFER_COMPLETE {
+            return Err(Error::Renderer(format!(
+                "Framebuffer {}x{} is incomplete",
+                width, height
+            )));
+        }
+        Ok(())
+    }
+
+    unsafe fn delete_target(&mut self, gl: &glow::Context) {
+        if let Some((fbo, color, _, _)) = self.target.take() {
+            gl.delete_framebuffer(fbo);
+            gl.delete_renderbuffer(color);
+        }
+    }
+
+    /// Free GL objects; the context must still be current
+    pub fn delete(mut self, ctx: &GlContext) {
+        let gl = &ctx.gl;
+        unsafe {
+            self.delete_target(gl);
+            for (texture, _, _) in self.channels.iter().flatten() {
+                gl.delete_texture(*texture);
+            }
+            gl.delete_vertex_array(self.vertex_array);
+            gl.delete_program(self.program);
+        }
+    }
+}
+
+unsafe fn compile(gl: &glow::Context, kind: u32, source: &str) -> Result<glow::Shader> {
+    let shader = gl.create_shader(kind).map_err(Error::Renderer)?;
+    gl.shader_source(shader, source);
+    gl.compile_shader(shader);
+
+    if !gl.get_shader_compile_status(shader) {
+        let log = gl.get_shader_info_log(shader);
+        gl.delete_shader(shader);
+        return Err(Error::Renderer(format!("Failed to compile shader: {}", log.trim())));
+    }
+    Ok(shader)
+}
@@ -0,0 +1,471 @@
+//! Shadertoy-style fragment shader wallpapers
+//!
+//! A shader is a GLSL file defining `mainImage(out vec4, in vec2)` that may
+//! use `iResolution`, `iTime`, `iTimeDelta`, `iFrameRate`, `iFrame`, `iMouse`,
+//! `iDate`, `iChannelResolution` and `iChannel0`..`iChannel3`. Channel
+//! textures are images named `iChannel0.png` .. `iChannel3.png` (any image
+//! format) next to the shader.
+
+pub mod gl;
+
+use crate::frame_loop::{FrameContext, FrameLoop, FrameSource};
+use crate::renderer::RendererTrait;
+use crate::surface::DesktopSurface;
+use gl::{GlContext, ShaderProgram, Uniforms};
+use std::path::{Path, PathBuf};
+use tiny_skia::{FilterQuality, Pixmap, PixmapPaint, Transform};
+use wallmgr_core::error::{Error, Result};
+
+/// File extensions recognized as shaders
+pub const SHADER_EXTENSIONS: &[&str] = &["frag", "glsl"];
+const CHANNEL_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "bmp"];
+const CHANNEL_COUNT: usize = 4;
+
+const UNIFORMS: &str = "uniform vec3 iResolution;
+uniform float iTime;
+uniform float iTimeDelta;
+uniform float iFrameRate;
+uniform int iFrame;
+uniform vec4 iMouse;
+uniform vec4 iDate;
+uniform vec3 iChannelResolution[4];
+";
+
+/// A Shadertoy shader and its channel textures
+#[derive(Debug, Clone)]
+pub struct ShaderSource {
+    pub path: PathBuf,
+    pub code: String,
+    pub channels: [Option<PathBuf>; CHANNEL_COUNT],
+}
+
+impl ShaderSource {
+    /// Load from a shader file or a directory containing one
+    pub fn load(path: &Path) -> Result<Self> {
+        let path = if path.is_dir() {
+            ShaderRenderer::find_shader_file(path)
+                .ok_or_else(|| Error::NotFound("No .frag or .glsl shader found".to_string()))?
+        } else {
+            path.to_path_buf()
+        };
+
+        let code = std::fs::read_to_string(&path)?;
+        let dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        let channels = std::array::from_fn(|i| {
+            CHANNEL_EXTENSIONS
+                .iter()
+                .map(|ext| dir.join(format!("iChannel{}.{}", i, ext)))
+                .find(|p| p.exists())
+        });
+
+        Ok(Self { path, code, channels })
+    }
+
+    /// User code with any `#version` line removed
+    fn body(&self) -> String {
+        self.code
+            .lines()
+            .map(|line| if line.trim_start().starts_with("#version") { "" } else { line })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Complete GLSL ES 3.0 fragment shader
+    pub fn fragment_source(&self) -> String {
+        let samplers: String = (0..CHANNEL_COUNT)
+            .map(|i| format!("uniform sampler2D iChannel{};\n", i))
+            .collect();
+
+        format!(
+            "#version 300 es\nprecision highp float;\nprecision highp int;\n{}{}out vec4 wallmgr_FragColor;\n#line 1\n{}\nvoid main() {{\n    wallmgr_FragColor = vec4(0.0, 0.0, 0.0, 1.0);\n    mainImage(wallmgr_FragColor, gl_FragCoord.xy);\n}}\n",
+            UNIFORMS,
+            samplers,
+            self.body()
+        )
+    }
+
+    /// Check the shader compiles, without needing a GL driver
+    ///
+    /// Parses the code with naga as desktop GLSL 4.50. Constructs naga doesn't
+    /// implement yet are let through; the driver has the final word when the
+    /// shader is rendered.
+    pub fn validate(&self) -> Result<()> {
+        let body = self.body();
+        let has_main_image = body
+            .split("mainImage")
+            .skip(1)
+            .any(|rest| rest.trim_start().starts_with('('));
+        if !has_main_image {
+            return Err(Error::InvalidInput("Shader does not define mainImage()".to_string()));
+        }
+
+        // naga has no combined samplers, so split each channel and alias it back
+        let mut prelude = String::from("#version 450\nlayout(location = 0) out vec4 wallmgr_FragColor;\n");
+        prelude.push_str("layout(set = 0, binding = 0) uniform ShaderToy {\n");
+        for line in UNIFORMS.lines() {
+            prelude.push_str(&format!("    {}\n", line.trim_start_matches("uniform ")));
+        }
+        prelude.push_str("};\n");
+        for i in 0..CHANNEL_COUNT {
+            prelude.push_str(&format!(
+                "layout(set = 1, binding = {t}) uniform texture2D iChannel{i}_texture;\nlayout(set = 1, binding = {s}) uniform sampler iChannel{i}_sampler;\n#define iChannel{i} sampler2D(iChannel{i}_texture, iChannel{i}_sampler)\n",
+                i = i,
+                t = i * 2,
+                s = i * 2 + 1
+            ));
+        }
+
+        let prelude_lines = prelude.lines().count();
+        let source = format!(
+            "{}{}\nvoid main() {{ mainImage(wallmgr_FragColor, gl_FragCoord.xy); }}\n",
+            prelude, body
+        );
+
+        let mut frontend = naga::front::glsl::Frontend::default();
+        let options = naga::front::glsl::Options::from(naga::ShaderStage::Fragment);
+        let Err(errors) = frontend.parse(&options, &source) else {
+            return Ok(());
+        };
+
+        let mut messages = Vec::new();
+        for error in &errors.errors {
+            if matches!(error.kind, naga::front::glsl::ErrorKind::NotImplemented(_)) {
+                tracing::warn!("Skipping shader check naga can't do: {}", error.kind);
+                continue;
+            }
+            let line = error
+                .meta
+                .location(&source)
+                .line_number
+                .saturating_sub(prelude_lines as u32);
+            messages.push(format!("line {}: {}", line, error.kind));
+        }
+
+        if messages.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidInput(format!("Invalid shader: {}", messages.join("; "))))
+        }
+    }
+
+    fn load_channels(&self) -> Vec<Option<image::RgbaImage>> {
+        self.channels
+            .iter()
+            .map(|channel| {
+                let path = channel.as_ref()?;
+                match image::open(path) {
+                    Ok(img) => Some(img.into_rgba8()),
+                    Err(e) => {
+                        tracing::warn!("Failed to load shader channel {}: {}", path.display(), e);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+/// Settings for shader wallpapers
+#[derive(Debug, Clone)]
+pub struct ShaderOptions {
+    pub fps: u32,
+    /// Render below native resolution and upscale (0.25 - 1.0)
+    pub resolution_scale: f32,
+    /// Force Mesa's software rasterizer instead of the GPU
+    pub software: bool,
+}
+
+impl Default for ShaderOptions {
+    fn default() -> Self {
+        Self {
+            fps: 30,
+            resolution_scale: 1.0,
+            software: false,
+        }
+    }
+}
+
+struct GlState {
+    context: GlContext,
+    program: ShaderProgram,
+}
+
+/// Renders a `ShaderSource` frame by frame
+pub struct ShaderPlayer {
+    source: ShaderSource,
+    options: ShaderOptions,
+    state: Option<GlState>,
+    frame: i32,
+    mouse: [f32; 4],
+}
+
+// SAFETY: the GL context is created lazily by the first `render` call and is
+// only used from that thread afterwards; before that the player holds no GL state
+unsafe impl Send for ShaderPlayer {}
+
+impl ShaderPlayer {
+    pub fn new(source: ShaderSource, options: ShaderOptions) -> Self {
+        Self {
+            source,
+            options,
+            state: None,
+            frame: 0,
+            mouse: [0.0; 4],
+        }
+    }
+
+    /// GL_RENDERER of the context, once the first frame has been drawn
+    pub fn renderer_name(&self) -> Option<String> {
+        self.state.as_ref().map(|s| s.context.renderer_name())
+    }
+
+    fn state(&mut self) -> Result<&mut GlState> {
+        if self.state.is_none() {
+            let context = GlContext::new(self.options.software)?;
+            let program = ShaderProgram::new(&context, &self.source.fragment_source(), &self.source.load_channels())?;
+            self.state = Some(GlState { context, program });
+        }
+        Ok(self.state.as_mut().expect("GL state was just created"))
+    }
+}
+
+impl Drop for ShaderPlayer {
+    fn drop(&mut self) {
+        if let Some(state) = self.state.take() {
+            state.program.delete(&state.context);
+        }
+    }
+}
+
+impl FrameSource for ShaderPlayer {
+    fn render(&mut self, ctx: &FrameContext) -> Result<Pixmap> {
+        let scale = self.options.resolution_scale.clamp(0.25, 1.0);
+        let width = ((ctx.width as f32 * scale).round() as u32).max(1);
+        let height = ((ctx.height as f32 * scale).round() as u32).max(1);
+
+        // Shadertoy's iMouse is y-up; keep the last position when the pointer leaves
+        if let Some((x, y)) = ctx.pointer {
+            self.mouse[0] = x * scale;
+            self.mouse[1] = height as f32 - y * scale;
+        }
+
+        let now = chrono::Local::now();
+        use chrono::{Datelike, Timelike};
+        let uniforms = Uniforms {
+            time: ctx.time,
+            time_delta: ctx.delta,
+            frame: self.frame,
+            mouse: self.mouse,
+            date: [
+                now.year() as f32,
+                now.month0() as f32,
+                now.day() as f32,
+                now.num_seconds_from_midnight() as f32 + now.nanosecond() as f32 / 1e9,
+            ],
+        };
+        self.frame += 1;
+
+        let state = self.state()?;
+        let frame = state.program.render(&state.context, width, height, &uniforms)?;
+
+        if (width, height) == (ctx.width, ctx.height) {
+            return Ok(frame);
+        }
+
+        let mut full = Pixmap::new(ctx.width, ctx.height)
+            .ok_or_else(|| Error::Renderer(format!("Invalid frame size {}x{}", ctx.width, ctx.height)))?;
+        let paint = PixmapPaint {
+            quality: FilterQuality::Bilinear,
+            ..PixmapPaint::default()
+        };
+        full.draw_pixmap(
+            0,
+            0,
+            frame.as_ref(),
+            &paint,
+            Transform::from_scale(ctx.width as f32 / width as f32, ctx.height as f32 / height as f32),
+            None,
+        );
+        Ok(full)
+    }
+}
+
+/// Shader renderer - Renders Shadertoy-style GLSL shaders in-process
+pub struct ShaderRenderer {
+    options: ShaderOptions,
+    frame_loop: Option<FrameLoop>,
+    current_path: Option<String>,
+}
+
+impl ShaderRenderer {
+    pub fn new() -> Self {
+        Self::with_options(ShaderOptions::default())
+    }
+
+    pub fn with_options(options: ShaderOptions) -> Self {
+        Self {
+            options,
+            frame_loop: None,
+            current_path: None,
+        }
+    }
+
+    /// Find the first shader file in a directory
+    pub fn find_shader_file(dir: &Path) -> Option<PathBuf> {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+            .ok()?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| {
+                p.extension()
+                    .and_then(|s| s.to_str())
+                    .map(|s| SHADER_EXTENSIONS.contains(&s.to_lowercase().as_str()))
+                    .unwrap_or(false)
+            })
+            .collect();
+        files.sort();
+        files.into_iter().next()
+    }
+
+    /// Validate a shader file or directory, e.g. on import
+    pub fn validate(path: &Path) -> Result<()> {
+        ShaderSource::load(path)?.validate()
+    }
+
+    /// Render a single frame offscreen with the software rasterizer
+    pub fn render_frame(&self, path: &Path, width: u32, height: u32, time: f32) -> Result<Pixmap> {
+        let source = ShaderSource::load(path)?;
+        let options = ShaderOptions {
+            software: true,
+            ..self.options.clone()
+        };
+        ShaderPlayer::new(source, options).render(&FrameContext {
+            time,
+            delta: 0.0,
+            width,
+            height,
+            pointer: None,
+        })
+    }
+
+    /// Render a still frame to a PNG file
+    pub fn render_thumbnail(&self, path: &Path, output: &Path, width: u32, height: u32) -> Result<()> {
+        self.render_frame(path, width, height, 1.0)?
+            .save_png(output)
+            .map_err(|e| Error::Renderer(format!("Failed to write thumbnail: {}", e)))
+    }
+}
+
+impl Default for ShaderRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RendererTrait for ShaderRenderer {
+    fn name(&self) -> &str {
+        "shader"
+    }
+
+    fn is_available(&self) -> bool {
+        GlContext::is_available() && DesktopSurface::is_supported()
+    }
+
+    fn start(&mut self, path: &Path, monitor: Option<&str>) -> Result<()> {
+        // Stop any existing playback
+        self.stop()?;
+
+        let source = ShaderSource::load(path)?;
+        source.validate()?;
+
+        let player = ShaderPlayer::new(source, self.options.clone());
+        self.frame_loop = Some(FrameLoop::spawn(player, monitor, self.options.fps)?);
+        self.current_path = Some(path.to_string_lossy().to_string());
+
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        if let Some(mut frame_loop) = self.frame_loop.take() {
+            frame_loop.stop();
+        }
+        self.current_path = None;
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.frame_loop
+            .as_ref()
+            .map(|f| f.is_running())
+            .unwrap_or(false)
+    }
+}
+
+impl Drop for ShaderRenderer {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRADIENT: &str = "void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 uv = fragCoord / iResolution.xy;
+    fragColor = vec4(uv.x, 0.0, 1.0 - uv.x, 1.0) + 0.0 * texture(iChannel0, uv);
+}
+";
+
+    fn source(code: &str) -> ShaderSource {
+        ShaderSource {
+            path: PathBuf::from("test.frag"),
+            code: code.to_string(),
+            channels: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_validate_shadertoy_code() {
+        assert!(source(GRADIENT).validate().is_ok());
+
+        let err = source("void main() {}").validate().unwrap_err();
+        assert!(err.to_string().contains("mainImage"));
+
+        let err = source("void mainImage(out vec4 c, in vec2 f) {\n    c = vec4(missing);\n}\n")
+            .validate()
+            .unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{}", err);
+    }
+
+    #[test]
+    fn test_finds_channels() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("waves.frag"), GRADIENT).unwrap();
+        image::RgbaImage::new(2, 2).save(dir.path().join("iChannel1.png")).unwrap();
+
+        let source = ShaderSource::load(dir.path()).unwrap();
+        assert_eq!(source.path, dir.path().join("waves.frag"));
+        assert!(source.channels[0].is_none());
+        assert_eq!(source.channels[1], Some(dir.path().join("iChannel1.png")));
+    }
+
+    #[test]
+    fn test_renders_offscreen_with_software_gl() {
+        if !GlContext::is_available() {
+            eprintln!("libEGL not available, skipping");
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gradient.frag");
+        std::fs::write(&path, GRADIENT).unwrap();
+
+        let frame = ShaderRenderer::new().render_frame(&path, 64, 32, 0.0).unwrap();
+        let left = frame.pixel(0, 16).unwrap();
+        let right = frame.pixel(63, 16).unwrap();
+
+        assert!(left.blue() > 240 && left.red() < 15);
+        assert!(right.red() > 240 && right.blue() < 15);
+        assert_eq!(left.alpha(), 255);
+    }
+}
@@ -0,0 +1,365 @@
+use super::skeleton::Skeleton;
+
+/// Interpolation from one keyframe to the next
+#[derive(Debug, Clone, PartialEq)]
+pub enum Curve {
+    Linear,
+    Stepped,
+    /// Per channel control points (cx1, cy1, cx2, cy2) in absolute time/value units
+    Bezier(Vec<[f32; 4]>),
+}
+
+/// Keyframed values with `channels` floats per frame
+#[derive(Debug, Clone, Default)]
+pub struct CurveTimeline {
+    pub channels: usize,
+    pub times: Vec<f32>,
+    pub values: Vec<f32>,
+    pub curves: Vec<Curve>,
+}
+
+impl CurveTimeline {
+    pub fn new(channels: usize) -> Self {
+        Self {
+            channels,
+            ..Self::default()
+        }
+    }
+
+    pub fn push(&mut self, time: f32, values: &[f32], curve: Curve) {
+        self.times.push(time);
+        self.values.extend_from_slice(&values[..self.channels]);
+        self.curves.push(curve);
+    }
+
+    fn frame_values(&self, frame: usize) -> &[f32] {
+        &self.values[frame * self.channels..(frame + 1) * self.channels]
+    }
+
+    /// Interpolated value of `channel` at `time`
+    pub fn value(&self, time: f32, channel: usize) -> Option<f32> {
+        if self.times.is_empty() {
+            return None;
+        }
+
+        let frame = match self.times.iter().rposition(|&t| t <= time) {
+            Some(f) => f,
+            None => return Some(self.frame_values(0)[channel]),
+        };
+
+        let v1 = self.frame_values(frame)[channel];
+        if frame + 1 >= self.times.len() {
+            return Some(v1);
+        }
+
+        let t1 = self.times[frame];
+        let t2 = self.times[frame + 1];
+        let v2 = self.frame_values(frame + 1)[channel];
+
+        Some(match &self.curves[frame] {
+            Curve::Stepped => v1,
+            Curve::Linear => {
+                let alpha = if t2 > t1 { (time - t1) / (t2 - t1) } else { 0.0 };
+                v1 + (v2 - v1) * alpha
+            }
+            Curve::Bezier(points) => match points.get(channel) {
+                Some(&[cx1, cy1, cx2, cy2]) => bezier_value(time, t1, v1, cx1, cy1, cx2, cy2, t2, v2),
+                None => v1,
+            },
+        })
+    }
+}
+
+/// Solve the cubic for `time` by bisection and return its value
+#[allow(clippy::too_many_arguments)]
+fn bezier_value(time: f32, t1: f32, v1: f32, cx1: f32, cy1: f32, cx2: f32, cy2: f32, t2: f32, v2: f32) -> f32 {
+    let cubic = |a: f32, b: f32, c: f32, d: f32, s: f32| {
+        let inv = 1.0 - s;
+        inv * inv * inv * a + 3.0 * inv * inv * s * b + 3.0 * inv * s * s * c + s * s * s * d
+    };
+
+    let (mut lo, mut hi) = (0.0f32, 1.0f32);
+    for _ in 0..24 {
+        let mid = (lo + hi) * 0.5;
+        if cubic(t1, cx1, cx2, t2, mid) < time {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    cubic(v1, cy1, cy2, v2, (lo + hi) * 0.5)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoneProperty {
+    Rotate,
+    TranslateX,
+    TranslateY,
+    ScaleX,
+    ScaleY,
+    ShearX,
+    ShearY,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChannel {
+    R,
+    G,
+    B,
+    A,
+}
+
+#[derive(Debug, Clone)]
+pub enum Timeline {
+    /// Bone values relative to the setup pose; one property per curve channel
+    Bone {
+        bone: usize,
+        properties: Vec<BoneProperty>,
+        curve: CurveTimeline,
+    },
+    /// Absolute slot color; one color channel per curve channel
+    Color {
+        slot: usize,
+        channels: Vec<ColorChannel>,
+        curve: CurveTimeline,
+    },
+    Attachment {
+        slot: usize,
+        times: Vec<f32>,
+        names: Vec<Option<String>>,
+    },
+    /// IK mix and softness per curve channel; bend direction, compress and
+    /// stretch are stepped per key
+    Ik {
+        constraint: usize,
+        curve: CurveTimeline,
+        bends: Vec<(f32, bool, bool)>,
+    },
+    /// Mesh vertices per key. The curve's value is the key index, so its
+    /// fraction is how far to blend into the next key.
+    Deform {
+        slot: usize,
+        attachment: String,
+        curve: CurveTimeline,
+        vertices: Vec<Vec<f32>>,
+    },
+    /// Full draw order per key; `None` means setup order
+    DrawOrder {
+        times: Vec<f32>,
+        orders: Vec<Option<Vec<usize>>>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Animation {
+    pub name: String,
+    pub duration: f32,
+    pub timelines: Vec<Timeline>,
+}
+
+impl Animation {
+    pub fn new(name: String, timelines: Vec<Timeline>) -> Self {
+        let duration = timelines
+            .iter()
+            .filter_map(|t| match t {
+                Timeline::Bone { curve, .. }
+                | Timeline::Color { curve, .. }
+                | Timeline::Ik { curve, .. }
+                | Timeline::Deform { curve, .. } => curve.times.last().copied(),
+                Timeline::Attachment { times, .. } | Timeline::DrawOrder { times, .. } => {
+                    times.last().copied()
+                }
+            })
+            .fold(0.0f32, f32::max);
+
+        Self {
+            name,
+            duration,
+            timelines,
+        }
+    }
+
+    /// Pose `skeleton` at `time`, looping over the animation duration
+    pub fn apply(&self, skeleton: &mut Skeleton, time: f32) {
+        let time = if self.duration > 0.0 {
+            time.rem_euclid(self.duration)
+        } else {
+            0.0
+        };
+
+        for timeline in &self.timelines {
+            match timeline {
+                Timeline::Bone {
+                    bone,
+                    properties,
+                    curve,
+                } => {
+                    let setup = skeleton.data.bones[*bone].clone();
+                    let pose = &mut skeleton.bones[*bone];
+                    for (channel, property) in properties.iter().enumerate() {
+                        let Some(v) = curve.value(time, channel) else {
+                            continue;
+                        };
+                        match property {
+                            BoneProperty::Rotate => pose.rotation = setup.rotation + v,
+                            BoneProperty::TranslateX => pose.x = setup.x + v,
+                            BoneProperty::TranslateY => pose.y = setup.y + v,
+                            BoneProperty::ScaleX => pose.scale_x = setup.scale_x * v,
+                            BoneProperty::ScaleY => pose.scale_y = setup.scale_y * v,
+                            BoneProperty::ShearX => pose.shear_x = setup.shear_x + v,
+                            BoneProperty::ShearY => pose.shear_y = setup.shear_y + v,
+                        }
+                    }
+                }
+                Timeline::Color {
+                    slot,
+                    channels,
+                    curve,
+                } => {
+                    let color = &mut skeleton.slots[*slot].color;
+                    for (channel, which) in channels.iter().enumerate() {
+                        let Some(v) = curve.value(time, channel) else {
+                            continue;
+                        };
+                        let v = v.clamp(0.0, 1.0);
+                        match which {
+                            ColorChannel::R => color.r = v,
+                            ColorChannel::G => color.g = v,
+                            ColorChannel::B => color.b = v,
+                            ColorChannel::A => color.a = v,
+                        }
+                    }
+                }
+                Timeline::Attachment { slot, times, names } => {
+                    if let Some(frame) = times.iter().rposition(|&t| t <= time) {
+                        let slot = &mut skeleton.slots[*slot];
+                        if slot.attachment != names[frame] {
+                            slot.attachment = names[frame].clone();
+                            slot.deform.clear();
+                        }
+                    }
+                }
+                Timeline::Ik { constraint, curve, bends } => {
+                    let ik = &mut skeleton.ik_constraints[*constraint];
+                    if let Some(mix) = curve.value(time, 0) {
+                        ik.mix = mix;
+                    }
+                    if let Some(softness) = curve.value(time, 1) {
+                        ik.softness = softness;
+                    }
+                    let frame = curve.times.iter().rposition(|&t| t <= time).unwrap_or(0);
+                    if let Some(&(bend_direction, compress, stretch)) = bends.get(frame) {
+                        ik.bend_direction = bend_direction;
+                        ik.compress = compress;
+                        ik.stretch = stretch;
+                    }
+                }
+                Timeline::Deform {
+                    slot,
+                    attachment,
+                    curve,
+                    vertices,
+                } => {
+                    let slot = &mut skeleton.slots[*slot];
+                    if slot.attachment.as_deref() != Some(attachment.as_str()) {
+                        continue;
+                    }
+                    let Some(position) = curve.value(time, 0) else {
+                        continue;
+                    };
+                    let frame = (position.max(0.0) as usize).min(vertices.len() - 1);
+                    let alpha = position - frame as f32;
+                    slot.deform = match vertices.get(frame + 1) {
+                        Some(next) if alpha > 0.0 => vertices[frame]
+                            .iter()
+                            .zip(next)
+                            .map(|(v1, v2)| v1 + (v2 - v1) * alpha)
+                            .collect(),
+                        _ => vertices[frame].clone(),
+                    };
+                }
+                Timeline::DrawOrder { times, orders } => {
+                    if let Some(frame) = times.iter().rposition(|&t| t <= time) {
+                        skeleton.draw_order = match &orders[frame] {
+                            Some(order) => order.clone(),
+                            None => (0..skeleton.slots.len()).collect(),
+                        };
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Expand Spine's draw order offsets `(slot, offset)` into a full slot order
+pub fn draw_order_from_offsets(slot_count: usize, offsets: &mut [(usize, i32)]) -> Vec<usize> {
+    offsets.sort_by_key(|(slot, _)| *slot);
+
+    let mut order: Vec<Option<usize>> = vec![None; slot_count];
+    let mut unchanged = Vec::new();
+    let mut original = 0usize;
+
+    for &(slot, offset) in offsets.iter() {
+        while original < slot.min(slot_count) {
+            unchanged.push(original);
+            original += 1;
+        }
+        let target = original as i64 + offset as i64;
+        if (0..slot_count as i64).contains(&target) {
+            order[target as usize] = Some(original);
+        }
+        original += 1;
+    }
+
+    while original < slot_count {
+        unchanged.push(original);
+        original += 1;
+    }
+
+    for entry in order.iter_mut().rev() {
+        if entry.is_none() {
+            *entry = unchanged.pop();
+        }
+    }
+
+    order.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linear_and_stepped_curves() {
+        let mut curve = CurveTimeline::new(1);
+        curve.push(0.0, &[0.0], Curve::Linear);
+        curve.push(1.0, &[10.0], Curve::Stepped);
+        curve.push(2.0, &[20.0], Curve::Linear);
+
+        assert_eq!(curve.value(0.5, 0), Some(5.0));
+        assert_eq!(curve.value(1.5, 0), Some(10.0));
+        assert_eq!(curve.value(3.0, 0), Some(20.0));
+    }
+
+    #[test]
+    fn test_bezier_matches_linear_when_control_points_are_on_the_line() {
+        let mut curve = CurveTimeline::new(1);
+        curve.push(0.0, &[0.0], Curve::Bezier(vec![[1.0 / 3.0, 10.0 / 3.0, 2.0 / 3.0, 20.0 / 3.0]]));
+        curve.push(1.0, &[10.0], Curve::Linear);
+
+        let v = curve.value(0.25, 0).unwrap();
+        assert!((v - 2.5).abs() < 0.01, "got {}", v);
+    }
+
+    #[test]
+    fn test_draw_order_offsets() {
+        // Move slot 0 two places forward
+        let order = draw_order_from_offsets(4, &mut [(0, 2)]);
+        assert_eq!(order, vec![1, 2, 0, 3]);
+
+        // Move slot 3 back to the front
+        let order = draw_order_from_offsets(4, &mut [(3, -3)]);
+        assert_eq!(order, vec![3, 0, 1, 2]);
+    }
+}
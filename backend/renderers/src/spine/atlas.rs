@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use wallmgr_core::error::{Error, Result};
+
+/// One texture page of a libGDX/Spine texture atlas
+#[derive(Debug, Clone)]
+pub struct AtlasPage {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// Texture is already premultiplied (`pma: true`)
+    pub premultiplied: bool,
+}
+
+/// A packed region inside an atlas page, in page pixels
+#[derive(Debug, Clone)]
+pub struct AtlasRegion {
+    pub name: String,
+    pub page: usize,
+    pub x: f32,
+    pub y: f32,
+    /// Packed size before rotation
+    pub width: f32,
+    pub height: f32,
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub original_width: f32,
+    pub original_height: f32,
+    /// 0 or 90 (rotated clockwise in the page)
+    pub degrees: u32,
+}
+
+impl AtlasRegion {
+    /// Page-space rectangle corners (u, v, u2, v2) in pixels
+    pub fn page_rect(&self) -> (f32, f32, f32, f32) {
+        if self.degrees == 90 {
+            (self.x, self.y, self.x + self.height, self.y + self.width)
+        } else {
+            (self.x, self.y, self.x + self.width, self.y + self.height)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Atlas {
+    pub dir: PathBuf,
+    pub pages: Vec<AtlasPage>,
+    pub regions: HashMap<String, AtlasRegion>,
+}
+
+impl Atlas {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        Self::parse(&content, dir)
+    }
+
+    /// Parse both the 4.x (`bounds:`/`offsets:`) and the older
+    /// (`xy:`/`size:`/`orig:`/`offset:`) atlas layouts
+    pub fn parse(content: &str, dir: PathBuf) -> Result<Self> {
+        let mut pages: Vec<AtlasPage> = Vec::new();
+        let mut regions = HashMap::new();
+        let mut current: Option<AtlasRegion> = None;
+        let mut expect_page = true;
+
+        let finish = |region: Option<AtlasRegion>, regions: &mut HashMap<String, AtlasRegion>| {
+            if let Some(mut r) = region {
+                if r.original_width == 0.0 {
+                    r.original_width = r.width;
+                }
+                if r.original_height == 0.0 {
+                    r.original_height = r.height;
+                }
+                regions.insert(r.name.clone(), r);
+            }
+        };
+
+        for line in content.lines() {
+            let trimmed = line.trim();
+
+            if trimmed.is_empty() {
+                finish(current.take(), &mut regions);
+                expect_page = true;
+                continue;
+            }
+
+            let Some((key, value)) = trimmed.split_once(':') else {
+                finish(current.take(), &mut regions);
+                if expect_page {
+                    pages.push(AtlasPage {
+                        name: trimmed.to_string(),
+                        width: 0,
+                        height: 0,
+                        premultiplied: false,
+                    });
+                    expect_page = false;
+                } else {
+                    let page = pages.len().checked_sub(1).ok_or_else(|| {
+                        Error::Parse("Atlas region before any page".to_string())
+                    })?;
+                    current = Some(AtlasRegion {
+                        name: trimmed.to_string(),
+                        page,
+                        x: 0.0,
+                        y: 0.0,
+                        width: 0.0,
+                        height: 0.0,
+                        offset_x: 0.0,
+                        offset_y: 0.0,
+                        original_width: 0.0,
+                        original_height: 0.0,
+                        degrees: 0,
+                    });
+                }
+                continue;
+            };
+
+            let key = key.trim();
+            let nums: Vec<f32> = value
+                .split(',')
+                .filter_map(|v| v.trim().parse::<f32>().ok())
+                .collect();
+            let num = |i: usize| nums.get(i).copied().unwrap_or(0.0);
+
+            match current.as_mut() {
+                Some(region) => match key {
+                    "bounds" => {
+                        region.x = num(0);
+                        region.y = num(1);
+                        region.width = num(2);
+                        region.height = num(3);
+                    }
+                    "xy" => {
+                        region.x = num(0);
+                        region.y = num(1);
+                    }
+                    "size" => {
+                        region.width = num(0);
+                        region.height = num(1);
+                    }
+                    "offsets" => {
+                        region.offset_x = num(0);
+                        region.offset_y = num(1);
+                        region.original_width = num(2);
+                        region.original_height = num(3);
+                    }
+                    "orig" => {
+                        region.original_width = num(0);
+                        region.original_height = num(1);
+                    }
+                    "offset" => {
+                        region.offset_x = num(0);
+                        region.offset_y = num(1);
+                    }
+                    "rotate" => {
+                        region.degrees = match value.trim() {
+                            "true" => 90,
+                            "false" => 0,
+                            other => other.parse().unwrap_or(0),
+                        };
+                    }
+                    _ => {}
+                },
+                None => {
+                    if let Some(page) = pages.last_mut() {
+                        match key {
+                            "size" => {
+                                page.width = num(0) as u32;
+                                page.height = num(1) as u32;
+                            }
+                            "pma" => page.premultiplied = value.trim() == "true",
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+
+        finish(current.take(), &mut regions);
+
+        if pages.is_empty() {
+            return Err(Error::Parse("Atlas has no pages".to_string()));
+        }
+
+        Ok(Self { dir, pages, regions })
+    }
+
+    pub fn page_path(&self, page: usize) -> PathBuf {
+        self.dir.join(&self.pages[page].name)
+    }
+
+    pub fn find_region(&self, name: &str) -> Option<&AtlasRegion> {
+        self.regions.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_v4_atlas() {
+        let atlas = Atlas::parse(
+            "\nhero.png\nsize: 512,256\nfilter: Linear,Linear\npma: true\nhead\n  bounds: 2,4,100,50\n  offsets: 1,2,104,56\n  rotate: 90\nbody\n  bounds: 110,4,60,80\n",
+            PathBuf::from("/tmp"),
+        )
+        .unwrap();
+
+        assert_eq!(atlas.pages.len(), 1);
+        assert_eq!(atlas.pages[0].width, 512);
+        assert!(atlas.pages[0].premultiplied);
+
+        let head = atlas.find_region("head").unwrap();
+        assert_eq!(head.degrees, 90);
+        assert_eq!(head.original_width, 104.0);
+        assert_eq!(head.page_rect(), (2.0, 4.0, 52.0, 104.0));
+
+        let body = atlas.find_region("body").unwrap();
+        assert_eq!(body.original_height, 80.0);
+    }
+
+    #[test]
+    fn test_parse_legacy_atlas() {
+        let atlas = Atlas::parse(
+            "\nlegacy.png\nsize: 256,256\nformat: RGBA8888\nfilter: Linear,Linear\nrepeat: none\neye\n  rotate: false\n  xy: 10, 20\n  size: 30, 40\n  orig: 32, 44\n  offset: 1, 2\n  index: -1\n",
+            PathBuf::from("/tmp"),
+        )
+        .unwrap();
+
+        let eye = atlas.find_region("eye").unwrap();
+        assert_eq!((eye.x, eye.y, eye.width, eye.height), (10.0, 20.0, 30.0, 40.0));
+        assert_eq!((eye.offset_x, eye.offset_y), (1.0, 2.0));
+        assert_eq!(eye.degrees, 0);
+    }
+}
@@ -0,0 +1,861 @@
+use super::animation::{
+    draw_order_from_offsets, Animation, BoneProperty, ColorChannel, Curve, CurveTimeline, Timeline,
+};
+use super::data::{
+    Attachment, BlendMode, BoneData, Color, IkConstraintData, Inherit, MeshAttachment,
+    MeshVertices, RegionAttachment, SkeletonData, SlotData, Skin,
+};
+use std::collections::HashMap;
+use wallmgr_core::error::{Error, Result};
+
+const CURVE_STEPPED: i8 = 1;
+const CURVE_BEZIER: i8 = 2;
+
+/// Load a binary `.skel` exported by Spine 4.0 or 4.1
+///
+/// Transform and path constraints and events are read so the stream stays
+/// aligned; the constraints are noted as unsupported.
+pub fn read_skeleton_binary(bytes: &[u8]) -> Result<SkeletonData> {
+    let mut input = Input::new(bytes);
+
+    let _hash = input.int()?;
+    let _hash_low = input.int()?;
+    let version = input.string()?.unwrap_or_default();
+
+    let sequences = if version.starts_with("4.1") {
+        true
+    } else if version.starts_with("4.0") {
+        false
+    } else {
+        return Err(Error::Parse(format!(
+            "Spine binary version {} is not supported; export the skeleton as JSON",
+            if version.is_empty() { "unknown" } else { &version }
+        )));
+    };
+
+    for _ in 0..4 {
+        input.float()?; // x, y, width, height
+    }
+    let nonessential = input.bool()?;
+    if nonessential {
+        input.float()?;
+        input.string()?;
+        input.string()?;
+    }
+
+    let string_count = input.varint(true)?;
+    for _ in 0..string_count {
+        let s = input.string()?.unwrap_or_default();
+        input.strings.push(s);
+    }
+
+    let mut data = SkeletonData {
+        version,
+        ..SkeletonData::default()
+    };
+
+    let bone_count = input.varint(true)? as usize;
+    for i in 0..bone_count {
+        let name = input.string()?.unwrap_or_default();
+        let parent = if i == 0 {
+            None
+        } else {
+            Some(input.varint(true)? as usize)
+        };
+        let rotation = input.float()?;
+        let x = input.float()?;
+        let y = input.float()?;
+        let scale_x = input.float()?;
+        let scale_y = input.float()?;
+        let shear_x = input.float()?;
+        let shear_y = input.float()?;
+        let length = input.float()?;
+        let inherit = Inherit::from_index(input.varint(true)?);
+        input.bool()?; // skin required
+        if nonessential {
+            input.int()?;
+        }
+
+        data.bones.push(BoneData {
+            name,
+            parent,
+            length,
+            x,
+            y,
+            rotation,
+            scale_x,
+            scale_y,
+            shear_x,
+            shear_y,
+            inherit,
+        });
+    }
+
+    let slot_count = input.varint(true)? as usize;
+    for _ in 0..slot_count {
+        let name = input.string()?.unwrap_or_default();
+        let bone = input.varint(true)? as usize;
+        let color = Color::from_rgba8888(input.int()? as u32);
+        input.int()?; // dark color
+        let attachment = input.string_ref()?;
+        let blend = BlendMode::from_index(input.varint(true)?);
+
+        data.slots.push(SlotData {
+            name,
+            bone,
+            color,
+            attachment,
+            blend,
+        });
+    }
+
+    for _ in 0..input.varint(true)? {
+        let name = input.string()?.unwrap_or_default();
+        let order = input.varint(true)? as i32;
+        input.bool()?; // skin required
+        let bones = input.varints()?;
+        data.ik_constraints.push(IkConstraintData {
+            name,
+            order,
+            bones,
+            target: input.varint(true)? as usize,
+            mix: input.float()?,
+            softness: input.float()?,
+            bend_direction: input.byte()? as f32,
+            compress: input.bool()?,
+            stretch: input.bool()?,
+            uniform: input.bool()?,
+        });
+    }
+
+    // Transform constraints
+    let count = input.varint(true)?;
+    if count > 0 {
+        data.unsupported.push("transform constraints".to_string());
+    }
+    for _ in 0..count {
+        input.string()?;
+        input.varint(true)?;
+        input.bool()?;
+        input.skip_varints()?;
+        input.varint(true)?;
+        input.bool()?;
+        input.bool()?;
+        input.skip_floats(12)?;
+    }
+
+    // Path constraints
+    let count = input.varint(true)?;
+    if count > 0 {
+        data.unsupported.push("path constraints".to_string());
+    }
+    for _ in 0..count {
+        input.string()?;
+        input.varint(true)?;
+        input.bool()?;
+        input.skip_varints()?;
+        for _ in 0..4 {
+            input.varint(true)?;
+        }
+        input.skip_floats(6)?;
+    }
+
+    let mut linked = Vec::new();
+    if let Some(skin) = read_skin(&mut input, &data, true, nonessential, sequences, &mut linked)? {
+        data.skins.push(skin);
+    }
+    for _ in 0..input.varint(true)? {
+        if let Some(skin) = read_skin(&mut input, &data, false, nonessential, sequences, &mut linked)? {
+            data.skins.push(skin);
+        }
+    }
+
+    for link in linked {
+        let parent_skin = link
+            .parent_skin
+            .as_deref()
+            .and_then(|s| data.find_skin(s))
+            .unwrap_or(0);
+        let parent = data
+            .skins
+            .get(parent_skin)
+            .and_then(|s| s.attachments.get(&(link.slot, link.parent.clone())))
+            .cloned();
+        if let Some(Attachment::Mesh(mut mesh)) = parent {
+            mesh.path = link.path;
+            mesh.color = link.color;
+            if let Some(skin) = data.skins.get_mut(link.skin) {
+                skin.attachments.insert((link.slot, link.name), Attachment::Mesh(mesh));
+            }
+        }
+    }
+
+    // Events: remember which ones carry audio, their keys read extra floats
+    let mut event_audio = Vec::new();
+    for _ in 0..input.varint(true)? {
+        input.string_ref()?;
+        input.varint(false)?;
+        input.float()?;
+        input.string()?;
+        let audio = input.string()?;
+        if audio.is_some() {
+            input.float()?;
+            input.float()?;
+        }
+        event_audio.push(audio.is_some());
+    }
+
+    for _ in 0..input.varint(true)? {
+        let name = input.string()?.unwrap_or_default();
+        let animation = read_animation(&mut input, &data, name, &event_audio, sequences)?;
+        data.animations.push(animation);
+    }
+
+    Ok(data)
+}
+
+struct LinkedMesh {
+    skin: usize,
+    slot: usize,
+    name: String,
+    parent: String,
+    parent_skin: Option<String>,
+    path: String,
+    color: Color,
+}
+
+fn read_skin(
+    input: &mut Input,
+    data: &SkeletonData,
+    default_skin: bool,
+    nonessential: bool,
+    sequences: bool,
+    linked: &mut Vec<LinkedMesh>,
+) -> Result<Option<Skin>> {
+    let skin_index = data.skins.len();
+    let (name, slot_count) = if default_skin {
+        let slot_count = input.varint(true)?;
+        if slot_count == 0 {
+            return Ok(None);
+        }
+        ("default".to_string(), slot_count)
+    } else {
+        let name = input.string_ref()?.unwrap_or_default();
+        // Bones, IK, transform and path constraints
+        for _ in 0..4 {
+            input.skip_varints()?;
+        }
+        (name, input.varint(true)?)
+    };
+
+    let mut skin = Skin {
+        name,
+        attachments: HashMap::new(),
+    };
+
+    for _ in 0..slot_count {
+        let slot = input.varint(true)? as usize;
+        for _ in 0..input.varint(true)? {
+            let key = input.string_ref()?.unwrap_or_default();
+            let name = input.string_ref()?.unwrap_or_else(|| key.clone());
+
+            match input.byte()? {
+                // Region
+                0 => {
+                    let path = input.string_ref()?.unwrap_or_else(|| name.clone());
+                    let rotation = input.float()?;
+                    let x = input.float()?;
+                    let y = input.float()?;
+                    let scale_x = input.float()?;
+                    let scale_y = input.float()?;
+                    let width = input.float()?;
+                    let height = input.float()?;
+                    let color = Color::from_rgba8888(input.int()? as u32);
+                    if sequences {
+                        input.sequence()?;
+                    }
+
+                    skin.attachments.insert(
+                        (slot, key),
+                        Attachment::Region(RegionAttachment {
+                            path,
+                            x,
+                            y,
+                            rotation,
+                            scale_x,
+                            scale_y,
+                            width,
+                            height,
+                            color,
+                        }),
+                    );
+                }
+                // Bounding box
+                1 => {
+                    let count = input.varint(true)?;
+                    input.vertices(count)?;
+                    if nonessential {
+                        input.int()?;
+                    }
+                }
+                // Mesh
+                2 => {
+                    let path = input.string_ref()?.unwrap_or_else(|| name.clone());
+                    let color = Color::from_rgba8888(input.int()? as u32);
+                    let count = input.varint(true)?;
+                    let uvs = input.floats(count as usize * 2)?;
+                    let triangles = input.shorts()?;
+                    let vertices = input.vertices(count)?;
+                    input.varint(true)?; // hull length
+                    if sequences {
+                        input.sequence()?;
+                    }
+                    if nonessential {
+                        input.shorts()?;
+                        input.float()?;
+                        input.float()?;
+                    }
+
+                    skin.attachments.insert(
+                        (slot, key),
+                        Attachment::Mesh(MeshAttachment {
+                            path,
+                            color,
+                            uvs,
+                            triangles,
+                            vertices,
+                        }),
+                    );
+                }
+                // Linked mesh
+                3 => {
+                    let path = input.string_ref()?.unwrap_or_else(|| name.clone());
+                    let color = Color::from_rgba8888(input.int()? as u32);
+                    let parent_skin = input.string_ref()?;
+                    let parent = input.string_ref()?.unwrap_or_default();
+                    input.bool()?;
+                    if sequences {
+                        input.sequence()?;
+                    }
+                    if nonessential {
+                        input.float()?;
+                        input.float()?;
+                    }
+
+                    linked.push(LinkedMesh {
+                        skin: skin_index,
+                        slot,
+                        name: key,
+                        parent,
+                        parent_skin,
+                        path,
+                        color,
+                    });
+                }
+                // Path
+                4 => {
+                    input.bool()?;
+                    input.bool()?;
+                    let count = input.varint(true)?;
+                    input.vertices(count)?;
+                    input.skip_floats(count as usize / 3)?;
+                    if nonessential {
+                        input.int()?;
+                    }
+                }
+                // Point
+                5 => {
+                    input.skip_floats(3)?;
+                    if nonessential {
+                        input.int()?;
+                    }
+                }
+                // Clipping
+                6 => {
+                    input.varint(true)?;
+                    let count = input.varint(true)?;
+                    input.vertices(count)?;
+                    if nonessential {
+                        input.int()?;
+                    }
+                }
+                other => {
+                    return Err(Error::Parse(format!("Unknown Spine attachment type {}", other)));
+                }
+            }
+        }
+    }
+
+    Ok(Some(skin))
+}
+
+fn read_animation(
+    input: &mut Input,
+    data: &SkeletonData,
+    name: String,
+    event_audio: &[bool],
+    sequences: bool,
+) -> Result<Animation> {
+    let mut timelines = Vec::new();
+    input.varint(true)?; // timeline count hint
+
+    // Slot timelines
+    for _ in 0..input.varint(true)? {
+        let slot = input.varint(true)? as usize;
+        for _ in 0..input.varint(true)? {
+            let kind = input.byte()?;
+            let frame_count = input.varint(true)? as usize;
+
+            if kind == 0 {
+                let mut times = Vec::with_capacity(frame_count);
+                let mut names = Vec::with_capacity(frame_count);
+                for _ in 0..frame_count {
+                    times.push(input.float()?);
+                    names.push(input.string_ref()?);
+                }
+                timelines.push(Timeline::Attachment { slot, times, names });
+                continue;
+            }
+
+            use ColorChannel::*;
+            let (channels, stored) = match kind {
+                1 => (vec![R, G, B, A], 4),
+                2 => (vec![R, G, B], 3),
+                3 => (vec![R, G, B, A], 7),
+                4 => (vec![R, G, B], 6),
+                5 => (vec![A], 1),
+                other => {
+                    return Err(Error::Parse(format!("Unknown Spine slot timeline {}", other)));
+                }
+            };
+
+            input.varint(true)?; // bezier count
+            let curve = input.curve_timeline(frame_count, stored, channels.len(), 1.0, true)?;
+            timelines.push(Timeline::Color { slot, channels, curve });
+        }
+    }
+
+    // Bone timelines
+    for _ in 0..input.varint(true)? {
+        let bone = input.varint(true)? as usize;
+        for _ in 0..input.varint(true)? {
+            let kind = input.byte()?;
+            let frame_count = input.varint(true)? as usize;
+            input.varint(true)?; // bezier count
+
+            use BoneProperty::*;
+            let properties = match kind {
+                0 => vec![Rotate],
+                1 => vec![TranslateX, TranslateY],
+                2 => vec![TranslateX],
+                3 => vec![TranslateY],
+                4 => vec![ScaleX, ScaleY],
+                5 => vec![ScaleX],
+                6 => vec![ScaleY],
+                7 => vec![ShearX, ShearY],
+                8 => vec![ShearX],
+                9 => vec![ShearY],
+                other => {
+                    return Err(Error::Parse(format!("Unknown Spine bone timeline {}", other)));
+                }
+            };
+
+            let n = properties.len();
+            let curve = input.curve_timeline(frame_count, n, n, 1.0, false)?;
+            timelines.push(Timeline::Bone { bone, properties, curve });
+        }
+    }
+
+    // IK timelines: mix, softness plus per-frame bend/compress/stretch
+    for _ in 0..input.varint(true)? {
+        let constraint = input.varint(true)? as usize;
+        let frame_count = input.varint(true)? as usize;
+        input.varint(true)?; // bezier count
+        let mut curve = CurveTimeline::new(2);
+        let mut bends = Vec::with_capacity(frame_count);
+        let mut time = input.float()?;
+        let mut values = [input.float()?, input.float()?];
+        for frame in 0..frame_count {
+            bends.push((input.byte()? as f32, input.bool()?, input.bool()?));
+            if frame + 1 == frame_count {
+                curve.push(time, &values, Curve::Linear);
+                break;
+            }
+            let time2 = input.float()?;
+            let values2 = [input.float()?, input.float()?];
+            curve.push(time, &values, input.curve(2)?);
+            time = time2;
+            values = values2;
+        }
+        if constraint < data.ik_constraints.len() {
+            timelines.push(Timeline::Ik { constraint, curve, bends });
+        }
+    }
+
+    // Transform constraint timelines
+    for _ in 0..input.varint(true)? {
+        input.varint(true)?;
+        let frame_count = input.varint(true)? as usize;
+        input.varint(true)?;
+        input.curve_timeline(frame_count, 6, 6, 1.0, false)?;
+    }
+
+    // Path constraint timelines
+    for _ in 0..input.varint(true)? {
+        input.varint(true)?;
+        for _ in 0..input.varint(true)? {
+            let kind = input.byte()?;
+            let frame_count = input.varint(true)? as usize;
+            input.varint(true)?;
+            let values = if kind == 2 { 3 } else { 1 };
+            input.curve_timeline(frame_count, values, values, 1.0, false)?;
+        }
+    }
+
+    // Deform and sequence timelines
+    for _ in 0..input.varint(true)? {
+        let skin = input.varint(true)? as usize;
+        for _ in 0..input.varint(true)? {
+            let slot = input.varint(true)? as usize;
+            for _ in 0..input.varint(true)? {
+                let attachment = input.string_ref()?.unwrap_or_default();
+                let kind = if sequences { input.byte()? } else { 0 };
+                let frame_count = input.varint(true)? as usize;
+
+                if kind == 1 {
+                    for _ in 0..frame_count {
+                        input.float()?;
+                        input.int()?;
+                        input.float()?;
+                    }
+                    continue;
+                }
+
+                let mesh = match data.skins.get(skin).and_then(|s| s.attachments.get(&(slot, attachment.clone()))) {
+                    Some(Attachment::Mesh(mesh)) => Some(mesh),
+                    _ => None,
+                };
+                let mut curve = CurveTimeline::new(1);
+                let mut vertices = Vec::with_capacity(frame_count);
+                input.varint(true)?; // bezier count
+                let mut time = input.float()?;
+                for frame in 0..frame_count {
+                    let end = input.varint(true)? as usize;
+                    let (start, values) = if end == 0 {
+                        (0, Vec::new())
+                    } else {
+                        (input.varint(true)? as usize, input.floats(end)?)
+                    };
+                    if let Some(mesh) = mesh {
+                        vertices.push(mesh.vertices.deform_key(start, &values));
+                    }
+
+                    // The curve's value is the key index, see Timeline::Deform
+                    let index = frame as f32;
+                    if frame + 1 == frame_count {
+                        curve.push(time, &[index], Curve::Linear);
+                        break;
+                    }
+                    let time2 = input.float()?;
+                    let key_curve = match input.curve(1)? {
+                        Curve::Bezier(points) => Curve::Bezier(
+                            points
+                                .into_iter()
+                                .map(|[cx1, cy1, cx2, cy2]| [cx1, cy1 + index, cx2, cy2 + index])
+                                .collect(),
+                        ),
+                        other => other,
+                    };
+                    curve.push(time, &[index], key_curve);
+                    time = time2;
+                }
+
+                if !vertices.is_empty() {
+                    timelines.push(Timeline::Deform {
+                        slot,
+                        attachment,
+                        curve,
+                        vertices,
+                    });
+                }
+            }
+        }
+    }
+
+    // Draw order
+    let draw_order_count = input.varint(true)?;
+    if draw_order_count > 0 {
+        let mut times = Vec::new();
+        let mut orders = Vec::new();
+        for _ in 0..draw_order_count {
+            times.push(input.float()?);
+            let offset_count = input.varint(true)?;
+            if offset_count == 0 {
+                orders.push(None);
+                continue;
+            }
+            let mut offsets = Vec::with_capacity(offset_count as usize);
+            for _ in 0..offset_count {
+                let slot = input.varint(true)? as usize;
+                // Negative offsets arrive as wrapped unsigned varints
+                let offset = input.varint(true)? as i32;
+                offsets.push((slot, offset));
+            }
+            orders.push(Some(draw_order_from_offsets(data.slots.len(), &mut offsets)));
+        }
+        timelines.push(Timeline::DrawOrder { times, orders });
+    }
+
+    // Events
+    for _ in 0..input.varint(true)? {
+        input.float()?;
+        let event = input.varint(true)? as usize;
+        input.varint(false)?;
+        input.float()?;
+        if input.bool()? {
+            input.string()?;
+        }
+        if event_audio.get(event).copied().unwrap_or(false) {
+            input.float()?;
+            input.float()?;
+        }
+    }
+
+    Ok(Animation::new(name, timelines))
+}
+
+struct Input<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    strings: Vec<String>,
+}
+
+impl<'a> Input<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            pos: 0,
+            strings: Vec::new(),
+        }
+    }
+
+    fn read(&mut self) -> Result<u8> {
+        let b = *self
+            .bytes
+            .get(self.pos)
+            .ok_or_else(|| Error::Parse("Unexpected end of Spine binary".to_string()))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn byte(&mut self) -> Result<i8> {
+        Ok(self.read()? as i8)
+    }
+
+    fn bool(&mut self) -> Result<bool> {
+        Ok(self.read()? != 0)
+    }
+
+    fn int(&mut self) -> Result<i32> {
+        let mut v = 0u32;
+        for _ in 0..4 {
+            v = (v << 8) | self.read()? as u32;
+        }
+        Ok(v as i32)
+    }
+
+    fn float(&mut self) -> Result<f32> {
+        Ok(f32::from_bits(self.int()? as u32))
+    }
+
+    fn varint(&mut self, optimize_positive: bool) -> Result<u32> {
+        let mut result = 0u32;
+        for shift in [0, 7, 14, 21, 28] {
+            let b = self.read()?;
+            result |= ((b & 0x7f) as u32) << shift;
+            if b & 0x80 == 0 {
+                break;
+            }
+        }
+        Ok(if optimize_positive {
+            result
+        } else {
+            (result >> 1) ^ (!(result & 1)).wrapping_add(1)
+        })
+    }
+
+    fn string(&mut self) -> Result<Option<String>> {
+        let count = self.varint(true)? as usize;
+        match count {
+            0 => Ok(None),
+            1 => Ok(Some(String::new())),
+            _ => {
+                let end = self.pos + count - 1;
+                let bytes = self
+                    .bytes
+                    .get(self.pos..end)
+                    .ok_or_else(|| Error::Parse("Unexpected end of Spine binary".to_string()))?;
+                self.pos = end;
+                Ok(Some(String::from_utf8_lossy(bytes).into_owned()))
+            }
+        }
+    }
+
+    fn string_ref(&mut self) -> Result<Option<String>> {
+        let index = self.varint(true)? as usize;
+        Ok(index.checked_sub(1).and_then(|i| self.strings.get(i).cloned()))
+    }
+
+    fn floats(&mut self, count: usize) -> Result<Vec<f32>> {
+        (0..count).map(|_| self.float()).collect()
+    }
+
+    fn skip_floats(&mut self, count: usize) -> Result<()> {
+        self.pos += count * 4;
+        if self.pos > self.bytes.len() {
+            return Err(Error::Parse("Unexpected end of Spine binary".to_string()));
+        }
+        Ok(())
+    }
+
+    fn varints(&mut self) -> Result<Vec<usize>> {
+        (0..self.varint(true)?).map(|_| Ok(self.varint(true)? as usize)).collect()
+    }
+
+    fn skip_varints(&mut self) -> Result<()> {
+        for _ in 0..self.varint(true)? {
+            self.varint(true)?;
+        }
+        Ok(())
+    }
+
+    fn shorts(&mut self) -> Result<Vec<u16>> {
+        let count = self.varint(true)?;
+        (0..count)
+            .map(|_| Ok(((self.read()? as u16) << 8) | self.read()? as u16))
+            .collect()
+    }
+
+    fn sequence(&mut self) -> Result<()> {
+        if self.bool()? {
+            for _ in 0..4 {
+                self.varint(true)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn vertices(&mut self, count: u32) -> Result<MeshVertices> {
+        if !self.bool()? {
+            return Ok(MeshVertices::Unweighted(self.floats(count as usize * 2)?));
+        }
+
+        let mut weighted = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let bone_count = self.varint(true)?;
+            let mut influences = Vec::with_capacity(bone_count as usize);
+            for _ in 0..bone_count {
+                let bone = self.varint(true)? as usize;
+                influences.push((bone, self.float()?, self.float()?, self.float()?));
+            }
+            weighted.push(influences);
+        }
+        Ok(MeshVertices::Weighted(weighted))
+    }
+
+    /// Curve to the next key with `beziers` sets of handles when it's a bezier
+    fn curve(&mut self, beziers: usize) -> Result<Curve> {
+        Ok(match self.byte()? {
+            CURVE_STEPPED => Curve::Stepped,
+            CURVE_BEZIER => Curve::Bezier(
+                (0..beziers)
+                    .map(|_| Ok([self.float()?, self.float()?, self.float()?, self.float()?]))
+                    .collect::<Result<_>>()?,
+            ),
+            _ => Curve::Linear,
+        })
+    }
+
+    /// Read `frame_count` keys with `stored` values each, keeping the first `keep`
+    ///
+    /// Color values are stored as unsigned bytes, everything else as floats.
+    fn curve_timeline(
+        &mut self,
+        frame_count: usize,
+        stored: usize,
+        keep: usize,
+        scale: f32,
+        byte_values: bool,
+    ) -> Result<CurveTimeline> {
+        let mut timeline = CurveTimeline::new(keep);
+        if frame_count == 0 {
+            return Ok(timeline);
+        }
+
+        let read_values = |input: &mut Self| -> Result<Vec<f32>> {
+            (0..stored)
+                .map(|_| {
+                    if byte_values {
+                        Ok(input.read()? as f32 / 255.0)
+                    } else {
+                        Ok(input.float()? * scale)
+                    }
+                })
+                .collect()
+        };
+
+        let mut time = self.float()?;
+        let mut values = read_values(self)?;
+
+        for frame in 0..frame_count {
+            if frame + 1 == frame_count {
+                timeline.push(time, &values, Curve::Linear);
+                break;
+            }
+
+            let time2 = self.float()?;
+            let values2 = read_values(self)?;
+            let curve = match self.byte()? {
+                CURVE_STEPPED => Curve::Stepped,
+                CURVE_BEZIER => {
+                    let mut points = Vec::with_capacity(stored);
+                    for _ in 0..stored {
+                        points.push([self.float()?, self.float()? * scale, self.float()?, self.float()? * scale]);
+                    }
+                    points.truncate(keep);
+                    Curve::Bezier(points)
+                }
+                _ => Curve::Linear,
+            };
+
+            timeline.push(time, &values, curve);
+            time = time2;
+            values = values2;
+        }
+
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varint_decoding() {
+        let mut input = Input::new(&[0x05, 0xac, 0x02, 0x03]);
+        assert_eq!(input.varint(true).unwrap(), 5);
+        assert_eq!(input.varint(true).unwrap(), 300);
+        // Zig-zag: 3 -> -2
+        assert_eq!(input.varint(false).unwrap() as i32, -2);
+    }
+
+    #[test]
+    fn test_rejects_unsupported_versions() {
+        let mut bytes = vec![0u8; 8];
+        bytes.push(6); // string of 5 bytes
+        bytes.extend_from_slice(b"3.8.9");
+        let err = read_skeleton_binary(&bytes).unwrap_err();
+        assert!(err.to_string().contains("3.8.9"));
+    }
+}
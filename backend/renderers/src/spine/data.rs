@@ -0,0 +1,276 @@
+use super::animation::Animation;
+use std::collections::HashMap;
+
+/// Setup-pose data shared by all skeleton instances
+#[derive(Debug, Clone, Default)]
+pub struct SkeletonData {
+    pub version: String,
+    pub bones: Vec<BoneData>,
+    pub slots: Vec<SlotData>,
+    pub skins: Vec<Skin>,
+    /// IK constraints in the order they are applied
+    pub ik_constraints: Vec<IkConstraintData>,
+    pub animations: Vec<Animation>,
+    /// What the skeleton uses that the player can't show, such as
+    /// "path constraints"
+    pub unsupported: Vec<String>,
+}
+
+impl SkeletonData {
+    pub fn find_bone(&self, name: &str) -> Option<usize> {
+        self.bones.iter().position(|b| b.name == name)
+    }
+
+    pub fn find_slot(&self, name: &str) -> Option<usize> {
+        self.slots.iter().position(|s| s.name == name)
+    }
+
+    pub fn find_skin(&self, name: &str) -> Option<usize> {
+        self.skins.iter().position(|s| s.name == name)
+    }
+
+    pub fn find_ik_constraint(&self, name: &str) -> Option<usize> {
+        self.ik_constraints.iter().position(|c| c.name == name)
+    }
+
+    pub fn find_animation(&self, name: &str) -> Option<usize> {
+        self.animations.iter().position(|a| a.name == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Inherit {
+    Normal,
+    OnlyTranslation,
+    NoRotationOrReflection,
+    NoScale,
+    NoScaleOrReflection,
+}
+
+impl Inherit {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "onlyTranslation" => Self::OnlyTranslation,
+            "noRotationOrReflection" => Self::NoRotationOrReflection,
+            "noScale" => Self::NoScale,
+            "noScaleOrReflection" => Self::NoScaleOrReflection,
+            _ => Self::Normal,
+        }
+    }
+
+    pub fn from_index(index: u32) -> Self {
+        match index {
+            1 => Self::OnlyTranslation,
+            2 => Self::NoRotationOrReflection,
+            3 => Self::NoScale,
+            4 => Self::NoScaleOrReflection,
+            _ => Self::Normal,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BoneData {
+    pub name: String,
+    pub parent: Option<usize>,
+    pub length: f32,
+    pub x: f32,
+    pub y: f32,
+    pub rotation: f32,
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub shear_x: f32,
+    pub shear_y: f32,
+    pub inherit: Inherit,
+}
+
+/// Rotates one bone, or a parent and child, so the last one points at `target`
+#[derive(Debug, Clone)]
+pub struct IkConstraintData {
+    pub name: String,
+    pub order: i32,
+    /// One bone, or a parent and its child
+    pub bones: Vec<usize>,
+    pub target: usize,
+    pub mix: f32,
+    pub softness: f32,
+    /// 1 or -1
+    pub bend_direction: f32,
+    pub compress: bool,
+    pub stretch: bool,
+    pub uniform: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const WHITE: Color = Color { r: 1.0, g: 1.0, b: 1.0, a: 1.0 };
+
+    /// Parse `RRGGBB` or `RRGGBBAA`
+    pub fn from_hex(hex: &str) -> Self {
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|s| u8::from_str_radix(s, 16).ok())
+                .map(|v| v as f32 / 255.0)
+                .unwrap_or(1.0)
+        };
+
+        Self {
+            r: channel(0),
+            g: channel(2),
+            b: channel(4),
+            a: channel(6),
+        }
+    }
+
+    pub fn from_rgba8888(value: u32) -> Self {
+        Self {
+            r: ((value >> 24) & 0xff) as f32 / 255.0,
+            g: ((value >> 16) & 0xff) as f32 / 255.0,
+            b: ((value >> 8) & 0xff) as f32 / 255.0,
+            a: (value & 0xff) as f32 / 255.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    Normal,
+    Additive,
+    Multiply,
+    Screen,
+}
+
+impl BlendMode {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "additive" => Self::Additive,
+            "multiply" => Self::Multiply,
+            "screen" => Self::Screen,
+            _ => Self::Normal,
+        }
+    }
+
+    pub fn from_index(index: u32) -> Self {
+        match index {
+            1 => Self::Additive,
+            2 => Self::Multiply,
+            3 => Self::Screen,
+            _ => Self::Normal,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SlotData {
+    pub name: String,
+    pub bone: usize,
+    pub color: Color,
+    pub attachment: Option<String>,
+    pub blend: BlendMode,
+}
+
+/// Attachments keyed by (slot index, attachment name)
+#[derive(Debug, Clone, Default)]
+pub struct Skin {
+    pub name: String,
+    pub attachments: HashMap<(usize, String), Attachment>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Attachment {
+    Region(RegionAttachment),
+    Mesh(MeshAttachment),
+}
+
+#[derive(Debug, Clone)]
+pub struct RegionAttachment {
+    /// Atlas region name
+    pub path: String,
+    pub x: f32,
+    pub y: f32,
+    pub rotation: f32,
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub color: Color,
+}
+
+#[derive(Debug, Clone)]
+pub struct MeshAttachment {
+    /// Atlas region name
+    pub path: String,
+    pub color: Color,
+    /// Region-relative UVs, two per vertex
+    pub uvs: Vec<f32>,
+    pub triangles: Vec<u16>,
+    pub vertices: MeshVertices,
+}
+
+#[derive(Debug, Clone)]
+pub enum MeshVertices {
+    /// Bone-local x/y pairs, relative to the slot's bone
+    Unweighted(Vec<f32>),
+    /// One entry per vertex: (bone index, x, y, weight) influences
+    Weighted(Vec<Vec<(usize, f32, f32, f32)>>),
+}
+
+impl MeshVertices {
+    /// Length of a deform key: an x/y pair per vertex, or per influence when weighted
+    pub fn deform_len(&self) -> usize {
+        match self {
+            Self::Unweighted(flat) => flat.len(),
+            Self::Weighted(weighted) => weighted.iter().map(|influences| influences.len() * 2).sum(),
+        }
+    }
+
+    /// Full deform key from `offset` and the `values` after it. Unweighted keys
+    /// hold absolute positions, weighted ones offsets from each influence.
+    pub fn deform_key(&self, offset: usize, values: &[f32]) -> Vec<f32> {
+        let mut key = match self {
+            Self::Unweighted(flat) => flat.clone(),
+            Self::Weighted(_) => vec![0.0; self.deform_len()],
+        };
+        for (target, value) in key.iter_mut().skip(offset).zip(values) {
+            *target += value;
+        }
+        key
+    }
+
+    /// Build from Spine's flat vertex array; weighted when it is longer than the UVs
+    pub fn from_flat(vertices: &[f32], uv_count: usize) -> Self {
+        if vertices.len() == uv_count {
+            return Self::Unweighted(vertices.to_vec());
+        }
+
+        let mut weighted = Vec::new();
+        let mut i = 0;
+        while i < vertices.len() {
+            let bone_count = vertices[i] as usize;
+            i += 1;
+            let mut influences = Vec::with_capacity(bone_count);
+            for _ in 0..bone_count {
+                if i + 4 > vertices.len() {
+                    break;
+                }
+                influences.push((
+                    vertices[i] as usize,
+                    vertices[i + 1],
+                    vertices[i + 2],
+                    vertices[i + 3],
+                ));
+                i += 4;
+            }
+            weighted.push(influences);
+        }
+
+        Self::Weighted(weighted)
+    }
+}
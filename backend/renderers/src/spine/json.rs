@@ -0,0 +1,550 @@
+use super::animation::{
+    draw_order_from_offsets, Animation, BoneProperty, ColorChannel, Curve, CurveTimeline, Timeline,
+};
+use super::data::{
+    Attachment, BlendMode, BoneData, Color, IkConstraintData, Inherit, MeshAttachment,
+    MeshVertices, RegionAttachment, SkeletonData, SlotData, Skin,
+};
+use serde_json::Value;
+use std::collections::HashMap;
+use wallmgr_core::error::{Error, Result};
+
+/// Load a skeleton exported as JSON (Spine 3.7 through 4.2)
+pub fn read_skeleton_json(content: &str) -> Result<SkeletonData> {
+    let root: Value = serde_json::from_str(content)?;
+
+    let version = root["skeleton"]["spine"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    // 4.x stores bezier handles in absolute time/value units
+    let absolute_curves = !version.starts_with('3') && !version.starts_with('2');
+
+    let mut data = SkeletonData {
+        version,
+        ..SkeletonData::default()
+    };
+
+    for bone in root["bones"].as_array().into_iter().flatten() {
+        let name = str_field(bone, "name")?;
+        let parent = match bone["parent"].as_str() {
+            Some(p) => Some(data.find_bone(p).ok_or_else(|| {
+                Error::Parse(format!("Bone {} has unknown parent {}", name, p))
+            })?),
+            None => None,
+        };
+        let inherit = bone["inherit"]
+            .as_str()
+            .or_else(|| bone["transform"].as_str())
+            .map(Inherit::from_name)
+            .unwrap_or(Inherit::Normal);
+
+        data.bones.push(BoneData {
+            name,
+            parent,
+            length: num(bone, "length", 0.0),
+            x: num(bone, "x", 0.0),
+            y: num(bone, "y", 0.0),
+            rotation: num(bone, "rotation", 0.0),
+            scale_x: num(bone, "scaleX", 1.0),
+            scale_y: num(bone, "scaleY", 1.0),
+            shear_x: num(bone, "shearX", 0.0),
+            shear_y: num(bone, "shearY", 0.0),
+            inherit,
+        });
+    }
+
+    for slot in root["slots"].as_array().into_iter().flatten() {
+        let name = str_field(slot, "name")?;
+        let bone_name = str_field(slot, "bone")?;
+        let bone = data
+            .find_bone(&bone_name)
+            .ok_or_else(|| Error::Parse(format!("Slot {} has unknown bone {}", name, bone_name)))?;
+
+        data.slots.push(SlotData {
+            name,
+            bone,
+            color: slot["color"].as_str().map(Color::from_hex).unwrap_or(Color::WHITE),
+            attachment: slot["attachment"].as_str().map(|s| s.to_string()),
+            blend: slot["blend"].as_str().map(BlendMode::from_name).unwrap_or(BlendMode::Normal),
+        });
+    }
+
+    for (index, ik) in root["ik"].as_array().into_iter().flatten().enumerate() {
+        let name = str_field(ik, "name")?;
+        let find_bone = |bone: &str| {
+            data.find_bone(bone)
+                .ok_or_else(|| Error::Parse(format!("IK constraint {} has unknown bone {}", name, bone)))
+        };
+        let bones = ik["bones"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|b| b.as_str())
+            .map(find_bone)
+            .collect::<Result<Vec<_>>>()?;
+        let target = find_bone(&str_field(ik, "target")?)?;
+
+        data.ik_constraints.push(IkConstraintData {
+            order: ik["order"].as_i64().unwrap_or(index as i64) as i32,
+            bones,
+            target,
+            mix: num(ik, "mix", 1.0),
+            softness: num(ik, "softness", 0.0),
+            bend_direction: bend_direction(ik),
+            compress: ik["compress"].as_bool().unwrap_or(false),
+            stretch: ik["stretch"].as_bool().unwrap_or(false),
+            uniform: ik["uniform"].as_bool().unwrap_or(false),
+            name,
+        });
+    }
+
+    for (key, feature) in [
+        ("transform", "transform constraints"),
+        ("path", "path constraints"),
+        ("physics", "physics constraints"),
+    ] {
+        if root[key].as_array().is_some_and(|constraints| !constraints.is_empty()) {
+            data.unsupported.push(feature.to_string());
+        }
+    }
+
+    read_skins(&root["skins"], &mut data)?;
+
+    if let Some(animations) = root["animations"].as_object() {
+        for (name, animation) in animations {
+            let timelines = read_animation(animation, &data, absolute_curves)?;
+            data.animations.push(Animation::new(name.clone(), timelines));
+        }
+    }
+
+    Ok(data)
+}
+
+fn str_field(value: &Value, key: &str) -> Result<String> {
+    value[key]
+        .as_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| Error::Parse(format!("Missing \"{}\" in Spine skeleton", key)))
+}
+
+fn num(value: &Value, key: &str, default: f32) -> f32 {
+    value[key].as_f64().map(|v| v as f32).unwrap_or(default)
+}
+
+fn bend_direction(value: &Value) -> f32 {
+    if value["bendPositive"].as_bool().unwrap_or(true) {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+fn floats(value: &Value) -> Vec<f32> {
+    value
+        .as_array()
+        .map(|a| a.iter().filter_map(|v| v.as_f64()).map(|v| v as f32).collect())
+        .unwrap_or_default()
+}
+
+struct LinkedMesh {
+    skin: usize,
+    slot: usize,
+    name: String,
+    parent: String,
+    parent_skin: Option<String>,
+    path: String,
+    color: Color,
+}
+
+fn read_skins(skins: &Value, data: &mut SkeletonData) -> Result<()> {
+    // 3.8+ uses an array of {name, attachments}, older exports an object keyed by skin name
+    let entries: Vec<(String, &Value)> = match skins {
+        Value::Array(list) => list
+            .iter()
+            .map(|s| (s["name"].as_str().unwrap_or("default").to_string(), &s["attachments"]))
+            .collect(),
+        Value::Object(map) => map.iter().map(|(k, v)| (k.clone(), v)).collect(),
+        _ => Vec::new(),
+    };
+
+    let mut linked = Vec::new();
+
+    for (skin_name, slots) in entries {
+        let skin_index = data.skins.len();
+        let mut skin = Skin {
+            name: skin_name,
+            attachments: HashMap::new(),
+        };
+
+        for (slot_name, attachments) in slots.as_object().into_iter().flatten() {
+            let Some(slot) = data.find_slot(slot_name) else {
+                continue;
+            };
+
+            for (name, att) in attachments.as_object().into_iter().flatten() {
+                let path = att["path"]
+                    .as_str()
+                    .or_else(|| att["name"].as_str())
+                    .unwrap_or(name)
+                    .to_string();
+                let color = att["color"].as_str().map(Color::from_hex).unwrap_or(Color::WHITE);
+
+                match att["type"].as_str().unwrap_or("region") {
+                    "region" => {
+                        skin.attachments.insert(
+                            (slot, name.clone()),
+                            Attachment::Region(RegionAttachment {
+                                path,
+                                x: num(att, "x", 0.0),
+                                y: num(att, "y", 0.0),
+                                rotation: num(att, "rotation", 0.0),
+                                scale_x: num(att, "scaleX", 1.0),
+                                scale_y: num(att, "scaleY", 1.0),
+                                width: num(att, "width", 32.0),
+                                height: num(att, "height", 32.0),
+                                color,
+                            }),
+                        );
+                    }
+                    "mesh" | "skinnedmesh" | "weightedmesh" => {
+                        let uvs = floats(&att["uvs"]);
+                        let vertices = floats(&att["vertices"]);
+                        let triangles = att["triangles"]
+                            .as_array()
+                            .map(|a| a.iter().filter_map(|v| v.as_u64()).map(|v| v as u16).collect())
+                            .unwrap_or_default();
+
+                        skin.attachments.insert(
+                            (slot, name.clone()),
+                            Attachment::Mesh(MeshAttachment {
+                                path,
+                                color,
+                                vertices: MeshVertices::from_flat(&vertices, uvs.len()),
+                                uvs,
+                                triangles,
+                            }),
+                        );
+                    }
+                    "linkedmesh" => linked.push(LinkedMesh {
+                        skin: skin_index,
+                        slot,
+                        name: name.clone(),
+                        parent: att["parent"].as_str().unwrap_or_default().to_string(),
+                        parent_skin: att["skin"].as_str().map(|s| s.to_string()),
+                        path,
+                        color,
+                    }),
+                    // Bounding boxes, paths, points and clipping don't draw anything
+                    _ => {}
+                }
+            }
+        }
+
+        data.skins.push(skin);
+    }
+
+    resolve_linked_meshes(data, linked);
+    Ok(())
+}
+
+fn resolve_linked_meshes(data: &mut SkeletonData, linked: Vec<LinkedMesh>) {
+    for link in linked {
+        let parent_skin = link
+            .parent_skin
+            .as_deref()
+            .and_then(|s| data.find_skin(s))
+            .or_else(|| data.find_skin("default"))
+            .unwrap_or(link.skin);
+
+        let parent = data.skins[parent_skin]
+            .attachments
+            .get(&(link.slot, link.parent.clone()))
+            .cloned();
+
+        if let Some(Attachment::Mesh(mut mesh)) = parent {
+            mesh.path = link.path;
+            mesh.color = link.color;
+            data.skins[link.skin]
+                .attachments
+                .insert((link.slot, link.name), Attachment::Mesh(mesh));
+        }
+    }
+}
+
+/// Deform keys per (skin, slot, attachment): `deform` up to 4.1,
+/// `attachments` > skin > slot > attachment > `deform` in 4.2
+fn deform_keys(animation: &Value) -> Vec<(&str, &str, &str, &[Value])> {
+    fn entries(value: &Value) -> impl Iterator<Item = (&str, &Value)> {
+        value.as_object().into_iter().flatten().map(|(k, v)| (k.as_str(), v))
+    }
+
+    let mut keys = Vec::new();
+    for (skin, slots) in entries(&animation["deform"]) {
+        for (slot, attachments) in entries(slots) {
+            for (attachment, frames) in entries(attachments) {
+                keys.push((skin, slot, attachment, frames));
+            }
+        }
+    }
+    for (skin, slots) in entries(&animation["attachments"]) {
+        for (slot, attachments) in entries(slots) {
+            for (attachment, timelines) in entries(attachments) {
+                keys.push((skin, slot, attachment, &timelines["deform"]));
+            }
+        }
+    }
+
+    keys.into_iter()
+        .filter_map(|(skin, slot, attachment, frames)| Some((skin, slot, attachment, frames.as_array()?.as_slice())))
+        .collect()
+}
+
+fn read_animation(animation: &Value, data: &SkeletonData, absolute_curves: bool) -> Result<Vec<Timeline>> {
+    let mut timelines = Vec::new();
+
+    for (slot_name, types) in animation["slots"].as_object().into_iter().flatten() {
+        let Some(slot) = data.find_slot(slot_name) else {
+            continue;
+        };
+
+        for (kind, frames) in types.as_object().into_iter().flatten() {
+            let frames = frames.as_array().map(|f| f.as_slice()).unwrap_or_default();
+
+            match kind.as_str() {
+                "attachment" => timelines.push(Timeline::Attachment {
+                    slot,
+                    times: frames.iter().map(|f| num(f, "time", 0.0)).collect(),
+                    names: frames
+                        .iter()
+                        .map(|f| f["name"].as_str().map(|s| s.to_string()))
+                        .collect(),
+                }),
+                "rgba" | "color" | "rgb" | "alpha" => {
+                    let channels = match kind.as_str() {
+                        "rgb" => vec![ColorChannel::R, ColorChannel::G, ColorChannel::B],
+                        "alpha" => vec![ColorChannel::A],
+                        _ => vec![ColorChannel::R, ColorChannel::G, ColorChannel::B, ColorChannel::A],
+                    };
+                    let values = |f: &Value| -> Vec<f32> {
+                        if kind == "alpha" {
+                            return vec![num(f, "value", 1.0)];
+                        }
+                        let c = f["color"].as_str().map(Color::from_hex).unwrap_or(Color::WHITE);
+                        vec![c.r, c.g, c.b, c.a]
+                    };
+                    let curve = read_curve_timeline(frames, channels.len(), absolute_curves, values);
+                    timelines.push(Timeline::Color { slot, channels, curve });
+                }
+                // Two-color tinting is approximated by its light color
+                "twoColor" | "rgba2" | "rgb2" => {
+                    let channels = if kind == "rgb2" {
+                        vec![ColorChannel::R, ColorChannel::G, ColorChannel::B]
+                    } else {
+                        vec![ColorChannel::R, ColorChannel::G, ColorChannel::B, ColorChannel::A]
+                    };
+                    let values = |f: &Value| -> Vec<f32> {
+                        let c = f["light"].as_str().map(Color::from_hex).unwrap_or(Color::WHITE);
+                        vec![c.r, c.g, c.b, c.a]
+                    };
+                    let curve = read_curve_timeline(frames, channels.len(), absolute_curves, values);
+                    timelines.push(Timeline::Color { slot, channels, curve });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    for (bone_name, types) in animation["bones"].as_object().into_iter().flatten() {
+        let Some(bone) = data.find_bone(bone_name) else {
+            continue;
+        };
+
+        for (kind, frames) in types.as_object().into_iter().flatten() {
+            let frames = frames.as_array().map(|f| f.as_slice()).unwrap_or_default();
+
+            let (properties, default) = match kind.as_str() {
+                "rotate" => (vec![BoneProperty::Rotate], 0.0),
+                "translate" => (vec![BoneProperty::TranslateX, BoneProperty::TranslateY], 0.0),
+                "translatex" => (vec![BoneProperty::TranslateX], 0.0),
+                "translatey" => (vec![BoneProperty::TranslateY], 0.0),
+                "scale" => (vec![BoneProperty::ScaleX, BoneProperty::ScaleY], 1.0),
+                "scalex" => (vec![BoneProperty::ScaleX], 1.0),
+                "scaley" => (vec![BoneProperty::ScaleY], 1.0),
+                "shear" => (vec![BoneProperty::ShearX, BoneProperty::ShearY], 0.0),
+                "shearx" => (vec![BoneProperty::ShearX], 0.0),
+                "sheary" => (vec![BoneProperty::ShearY], 0.0),
+                _ => continue,
+            };
+
+            let two = properties.len() == 2;
+            let values = |f: &Value| -> Vec<f32> {
+                if two {
+                    vec![num(f, "x", default), num(f, "y", default)]
+                } else if kind == "rotate" && f.get("angle").is_some() {
+                    vec![num(f, "angle", default)]
+                } else {
+                    vec![num(f, "value", default)]
+                }
+            };
+
+            let curve = read_curve_timeline(frames, properties.len(), absolute_curves, values);
+            timelines.push(Timeline::Bone { bone, properties, curve });
+        }
+    }
+
+    for (name, frames) in animation["ik"].as_object().into_iter().flatten() {
+        let Some(constraint) = data.find_ik_constraint(name) else {
+            continue;
+        };
+        let frames = frames.as_array().map(|f| f.as_slice()).unwrap_or_default();
+        let values = |f: &Value| vec![num(f, "mix", 1.0), num(f, "softness", 0.0)];
+        timelines.push(Timeline::Ik {
+            constraint,
+            curve: read_curve_timeline(frames, 2, absolute_curves, values),
+            bends: frames
+                .iter()
+                .map(|f| {
+                    let flag = |key: &str| f[key].as_bool().unwrap_or(false);
+                    (bend_direction(f), flag("compress"), flag("stretch"))
+                })
+                .collect(),
+        });
+    }
+
+    for (skin, slot_name, attachment, frames) in deform_keys(animation) {
+        let (Some(skin), Some(slot)) = (data.find_skin(skin), data.find_slot(slot_name)) else {
+            continue;
+        };
+        let Some(Attachment::Mesh(mesh)) = data.skins[skin].attachments.get(&(slot, attachment.to_string())) else {
+            continue;
+        };
+        if frames.is_empty() {
+            continue;
+        }
+
+        let vertices = frames
+            .iter()
+            .map(|f| mesh.vertices.deform_key(f["offset"].as_u64().unwrap_or(0) as usize, &floats(&f["vertices"])))
+            .collect();
+        timelines.push(Timeline::Deform {
+            slot,
+            attachment: attachment.to_string(),
+            curve: key_index_curve(frames, absolute_curves),
+            vertices,
+        });
+    }
+
+    let draw_order = animation
+        .get("drawOrder")
+        .or_else(|| animation.get("draworder"))
+        .and_then(|v| v.as_array());
+
+    if let Some(keys) = draw_order {
+        let mut times = Vec::new();
+        let mut orders = Vec::new();
+
+        for key in keys {
+            times.push(num(key, "time", 0.0));
+            orders.push(key["offsets"].as_array().map(|offsets| {
+                let mut pairs: Vec<(usize, i32)> = offsets
+                    .iter()
+                    .filter_map(|o| {
+                        let slot = data.find_slot(o["slot"].as_str()?)?;
+                        Some((slot, o["offset"].as_i64().unwrap_or(0) as i32))
+                    })
+                    .collect();
+                draw_order_from_offsets(data.slots.len(), &mut pairs)
+            }));
+        }
+
+        timelines.push(Timeline::DrawOrder { times, orders });
+    }
+
+    Ok(timelines)
+}
+
+/// Curve over the key index of `frames`, whose curves go from 0 to 1
+/// between two keys
+fn key_index_curve(frames: &[Value], absolute_curves: bool) -> CurveTimeline {
+    let indexed: Vec<Value> = frames
+        .iter()
+        .enumerate()
+        .map(|(index, frame)| {
+            let mut frame = frame.clone();
+            frame["value"] = index.into();
+            // 4.x handles are absolute, so lift them from 0..1 to this key
+            if let (true, Some(curve)) = (absolute_curves, frame["curve"].as_array_mut()) {
+                for value in curve.iter_mut().skip(1).step_by(2) {
+                    *value = (value.as_f64().unwrap_or(0.0) + index as f64).into();
+                }
+            }
+            frame
+        })
+        .collect();
+
+    read_curve_timeline(&indexed, 1, absolute_curves, |f| vec![num(f, "value", 0.0)])
+}
+
+fn read_curve_timeline(
+    frames: &[Value],
+    channels: usize,
+    absolute_curves: bool,
+    values: impl Fn(&Value) -> Vec<f32>,
+) -> CurveTimeline {
+    let mut timeline = CurveTimeline::new(channels);
+    let keys: Vec<(f32, Vec<f32>)> = frames.iter().map(|f| (num(f, "time", 0.0), values(f))).collect();
+
+    for (i, frame) in frames.iter().enumerate() {
+        let (t1, v1) = &keys[i];
+        let curve = match (&frame["curve"], keys.get(i + 1)) {
+            (Value::String(s), _) if s == "stepped" => Curve::Stepped,
+            (Value::Array(c), Some((t2, v2))) if absolute_curves => {
+                let c: Vec<f32> = c.iter().filter_map(|v| v.as_f64()).map(|v| v as f32).collect();
+                Curve::Bezier(
+                    (0..channels)
+                        .map(|ch| match c.get(ch * 4..ch * 4 + 4) {
+                            Some(p) => [p[0], p[1], p[2], p[3]],
+                            None => [*t1, v1[ch], *t2, v2[ch]],
+                        })
+                        .collect(),
+                )
+            }
+            // 3.x: normalized handles as `curve: cx1, c2: cy1, c3: cx2, c4: cy2`, or a 4-element array
+            (curve, Some((t2, v2))) if !curve.is_null() => {
+                let normalized = match curve {
+                    Value::Array(c) if c.len() >= 4 => Some([
+                        c[0].as_f64().unwrap_or(0.0) as f32,
+                        c[1].as_f64().unwrap_or(0.0) as f32,
+                        c[2].as_f64().unwrap_or(1.0) as f32,
+                        c[3].as_f64().unwrap_or(1.0) as f32,
+                    ]),
+                    Value::Number(n) => Some([
+                        n.as_f64().unwrap_or(0.0) as f32,
+                        num(frame, "c2", 0.0),
+                        num(frame, "c3", 1.0),
+                        num(frame, "c4", 1.0),
+                    ]),
+                    _ => None,
+                };
+
+                match normalized {
+                    Some([cx1, cy1, cx2, cy2]) => Curve::Bezier(
+                        (0..channels)
+                            .map(|ch| {
+                                let (dt, dv) = (t2 - t1, v2[ch] - v1[ch]);
+                                [t1 + cx1 * dt, v1[ch] + cy1 * dv, t1 + cx2 * dt, v1[ch] + cy2 * dv]
+                            })
+                            .collect(),
+                    ),
+                    None => Curve::Linear,
+                }
+            }
+            _ => Curve::Linear,
+        };
+
+        timeline.push(*t1, v1, curve);
+    }
+
+    timeline
+}
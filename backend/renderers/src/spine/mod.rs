@@ -0,0 +1,622 @@
+//! In-process Spine runtime
+//!
+//! Loads skeletons exported as JSON (3.7 – 4.2) or binary `.skel` (4.0/4.1)
+//! together with their texture atlas, plays one looping animation and
+//! rasterizes it in software. Frames are presented on a desktop surface for
+//! live wallpapers or returned as pixmaps for thumbnails.
+//!
+//! Slot and attachment colors tint what they draw, IK constraints and mesh
+//! deform keys are applied. Transform, path and physics constraints are not
+//! supported; skeletons using them are refused rather than shown wrongly.
+
+pub mod animation;
+pub mod atlas;
+pub mod binary;
+pub mod data;
+pub mod json;
+pub mod skeleton;
+
+use crate::frame_loop::{FrameContext, FrameLoop, FrameSource};
+use crate::raster;
+use crate::renderer::RendererTrait;
+use crate::surface::DesktopSurface;
+use atlas::Atlas;
+use data::{Attachment, BlendMode, SkeletonData};
+use skeleton::Skeleton;
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tiny_skia::{Color, Pixmap};
+use wallmgr_core::error::{Error, Result};
+
+/// Animation names tried when none is configured
+const DEFAULT_ANIMATIONS: &[&str] = &["idle", "loop", "animation", "Idle", "default"];
+
+/// Playback settings for a Spine wallpaper
+#[derive(Debug, Clone)]
+pub struct SpineOptions {
+    /// Animation to loop (None = idle-like name, else the first one)
+    pub animation: Option<String>,
+    /// Skin to show (None = first non-default skin, if any)
+    pub skin: Option<String>,
+    pub fps: u32,
+    /// Playback speed multiplier
+    pub speed: f32,
+    /// Fraction of the frame the skeleton may cover
+    pub fit: f32,
+    /// Straight-alpha RGBA background
+    pub background: [u8; 4],
+}
+
+impl Default for SpineOptions {
+    fn default() -> Self {
+        Self {
+            animation: None,
+            skin: None,
+            fps: 30,
+            speed: 1.0,
+            fit: 0.95,
+            background: [0, 0, 0, 255],
+        }
+    }
+}
+
+/// A loaded skeleton, its atlas and textures, ready to render frames
+pub struct SpinePlayer {
+    skeleton: Skeleton,
+    atlas: Atlas,
+    textures: Vec<Option<Pixmap>>,
+    animation: Option<usize>,
+    bounds: (f32, f32, f32, f32),
+    options: SpineOptions,
+}
+
+impl SpinePlayer {
+    /// Load from a Spine directory or directly from a `.skel`/`.json` file
+    pub fn load(path: &Path, options: SpineOptions) -> Result<Self> {
+        let skeleton_path = if path.is_dir() {
+            SpineRenderer::find_skeleton_file(path)
+                .ok_or_else(|| Error::NotFound("No Spine skeleton found".to_string()))?
+        } else {
+            path.to_path_buf()
+        };
+
+        let data = match skeleton_path.extension().and_then(|s| s.to_str()) {
+            Some("json") => json::read_skeleton_json(&std::fs::read_to_string(&skeleton_path)?)?,
+            Some("skel") => binary::read_skeleton_binary(&std::fs::read(&skeleton_path)?)?,
+            _ => {
+                return Err(Error::InvalidPath(format!(
+                    "Not a Spine skeleton: {}",
+                    skeleton_path.display()
+                )))
+            }
+        };
+
+        let atlas_path = Self::find_atlas(&skeleton_path)
+            .ok_or_else(|| Error::NotFound("No .atlas file found next to the skeleton".to_string()))?;
+        let atlas = Atlas::load(&atlas_path)?;
+
+        let textures = (0..atlas.pages.len())
+            .map(|page| {
+                let path = atlas.page_path(page);
+                let texture = raster::load_texture(&path, atlas.pages[page].premultiplied);
+                if texture.is_none() {
+                    tracing::warn!("Failed to load Spine texture {}", path.display());
+                }
+                texture
+            })
+            .collect();
+
+        Self::from_parts(data, atlas, textures, options)
+    }
+
+    fn from_parts(
+        data: SkeletonData,
+        atlas: Atlas,
+        textures: Vec<Option<Pixmap>>,
+        options: SpineOptions,
+    ) -> Result<Self> {
+        let animation = match &options.animation {
+            Some(name) => Some(
+                data.find_animation(name)
+                    .ok_or_else(|| Error::NotFound(format!("Animation not found: {}", name)))?,
+            ),
+            None => DEFAULT_ANIMATIONS
+                .iter()
+                .find_map(|name| data.find_animation(name))
+                .or(if data.animations.is_empty() { None } else { Some(0) }),
+        };
+
+        if !data.unsupported.is_empty() {
+            return Err(Error::Renderer(format!(
+                "Spine skeleton uses {}, which the player doesn't support",
+                data.unsupported.join(" and ")
+            )));
+        }
+
+        let skin = match &options.skin {
+            Some(name) => Some(
+                data.find_skin(name)
+                    .ok_or_else(|| Error::NotFound(format!("Skin not found: {}", name)))?,
+            ),
+            None => data.skins.iter().position(|s| s.name != "default"),
+        };
+
+        let mut skeleton = Skeleton::new(Arc::new(data));
+        skeleton.skin = skin;
+
+        // Frame the skeleton on its pose at the start of the animation
+        let mut player = Self {
+            skeleton,
+            atlas,
+            textures,
+            animation,
+            bounds: (0.0, 0.0, 0.0, 0.0),
+            options,
+        };
+        player.pose(0.0);
+        player.bounds = player
+            .skeleton
+            .bounds(&player.atlas)
+            .ok_or_else(|| Error::Renderer("Spine skeleton has nothing to draw".to_string()))?;
+
+        Ok(player)
+    }
+
+    /// Atlas named after the skeleton, otherwise any atlas in the same directory
+    fn find_atlas(skeleton_path: &Path) -> Option<PathBuf> {
+        let dir = skeleton_path.parent()?;
+        let stem = skeleton_path.file_stem()?.to_str()?;
+
+        for candidate in [format!("{}.atlas", stem), format!("{}.atlas.txt", stem)] {
+            let path = dir.join(candidate);
+            if path.exists() {
+                return Some(path);
+            }
+        }
+
+        std::fs::read_dir(dir).ok()?.find_map(|entry| {
+            let path = entry.ok()?.path();
+            let name = path.file_name()?.to_str()?;
+            (name.ends_with(".atlas") || name.ends_with(".atlas.txt")).then_some(path)
+        })
+    }
+
+    pub fn animations(&self) -> Vec<&str> {
+        self.skeleton.data.animations.iter().map(|a| a.name.as_str()).collect()
+    }
+
+    pub fn skins(&self) -> Vec<&str> {
+        self.skeleton.data.skins.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    fn pose(&mut self, time: f32) {
+        self.skeleton.set_to_setup_pose();
+        if let Some(index) = self.animation {
+            let data = self.skeleton.data.clone();
+            data.animations[index].apply(&mut self.skeleton, time * self.options.speed);
+        }
+        self.skeleton.update_world_transform();
+    }
+
+    /// Render the animation at `time` seconds into a new pixmap
+    pub fn render(&mut self, width: u32, height: u32, time: f32) -> Result<Pixmap> {
+        let mut pixmap = Pixmap::new(width, height)
+            .ok_or_else(|| Error::Renderer(format!("Invalid frame size {}x{}", width, height)))?;
+        let [r, g, b, a] = self.options.background;
+        pixmap.fill(Color::from_rgba8(r, g, b, a));
+
+        self.pose(time);
+
+        let (x0, y0, x1, y1) = self.bounds;
+        let scale = (width as f32 / (x1 - x0).max(1.0))
+            .min(height as f32 / (y1 - y0).max(1.0))
+            * self.options.fit;
+        let (cx, cy) = ((x0 + x1) / 2.0, (y0 + y1) / 2.0);
+        let (half_w, half_h) = (width as f32 / 2.0, height as f32 / 2.0);
+        // Spine is y-up, pixmaps are y-down
+        let to_screen = |(x, y): (f32, f32)| ((x - cx) * scale + half_w, half_h - (y - cy) * scale);
+
+        let skeleton = &self.skeleton;
+        for &slot_index in &skeleton.draw_order {
+            let slot = &skeleton.slots[slot_index];
+            let Some(name) = &slot.attachment else {
+                continue;
+            };
+            let slot_data = &skeleton.data.slots[slot_index];
+            let tint = |color: &data::Color| [slot.color.r * color.r, slot.color.g * color.g, slot.color.b * color.b];
+            let blend_mode = match slot_data.blend {
+                BlendMode::Normal => tiny_skia::BlendMode::SourceOver,
+                BlendMode::Additive => tiny_skia::BlendMode::Plus,
+                BlendMode::Multiply => tiny_skia::BlendMode::Multiply,
+                BlendMode::Screen => tiny_skia::BlendMode::Screen,
+            };
+
+            match skeleton.attachment(slot_index, name) {
+                Some(Attachment::Region(region)) => {
+                    let Some(atlas_region) = self.atlas.find_region(&region.path) else {
+                        continue;
+                    };
+                    let Some(texture) = self.textures.get(atlas_region.page).and_then(|t| t.as_ref()) else {
+                        continue;
+                    };
+                    let Some((texture, (ox, oy))) = tinted_texture(texture, atlas_region, tint(&region.color)) else {
+                        continue;
+                    };
+
+                    let dst = skeleton
+                        .region_vertices(slot_data.bone, region, atlas_region)
+                        .map(to_screen);
+                    let (u, v, u2, v2) = atlas_region.page_rect();
+                    // Corner order matches region_vertices: BL, UL, UR, BR
+                    let src = if atlas_region.degrees == 90 {
+                        [(u2, v2), (u, v2), (u, v), (u2, v)]
+                    } else {
+                        [(u, v2), (u, v), (u2, v), (u2, v2)]
+                    }
+                    .map(|(x, y)| (x - ox, y - oy));
+
+                    raster::fill_textured(
+                        &mut pixmap,
+                        Pixmap::as_ref(&texture),
+                        &dst,
+                        &src,
+                        slot.color.a * region.color.a,
+                        blend_mode,
+                        true,
+                        None,
+                    );
+                }
+                Some(Attachment::Mesh(mesh)) => {
+                    let Some(atlas_region) = self.atlas.find_region(&mesh.path) else {
+                        continue;
+                    };
+                    let Some(texture) = self.textures.get(atlas_region.page).and_then(|t| t.as_ref()) else {
+                        continue;
+                    };
+                    let Some((texture, (ox, oy))) = tinted_texture(texture, atlas_region, tint(&mesh.color)) else {
+                        continue;
+                    };
+
+                    let dst: Vec<(f32, f32)> = skeleton
+                        .mesh_vertices(slot_index, &mesh.vertices)
+                        .into_iter()
+                        .map(to_screen)
+                        .collect();
+                    let src: Vec<(f32, f32)> = mesh_page_uvs(&mesh.uvs, atlas_region)
+                        .into_iter()
+                        .map(|(x, y)| (x - ox, y - oy))
+                        .collect();
+                    let opacity = slot.color.a * mesh.color.a;
+
+                    for tri in mesh.triangles.chunks_exact(3) {
+                        let (i0, i1, i2) = (tri[0] as usize, tri[1] as usize, tri[2] as usize);
+                        if i0.max(i1).max(i2) >= dst.len().min(src.len()) {
+                            continue;
+                        }
+                        raster::fill_textured(
+                            &mut pixmap,
+                            Pixmap::as_ref(&texture),
+                            &[dst[i0], dst[i1], dst[i2]],
+                            &[src[i0], src[i1], src[i2]],
+                            opacity,
+                            blend_mode,
+                            false,
+                            None,
+                        );
+                    }
+                }
+                None => {}
+            }
+        }
+
+        Ok(pixmap)
+    }
+}
+
+impl FrameSource for SpinePlayer {
+    fn render(&mut self, ctx: &FrameContext) -> Result<Pixmap> {
+        SpinePlayer::render(self, ctx.width, ctx.height, ctx.time)
+    }
+}
+
+/// `texture`, or when `tint` isn't white a tinted copy of `region`'s part of
+/// it, with where the copy starts in page pixels
+fn tinted_texture<'a>(
+    texture: &'a Pixmap,
+    region: &atlas::AtlasRegion,
+    tint: [f32; 3],
+) -> Option<(Cow<'a, Pixmap>, (f32, f32))> {
+    if tint.iter().all(|&c| c >= 1.0) {
+        return Some((Cow::Borrowed(texture), (0.0, 0.0)));
+    }
+    let (copy, offset) = raster::tinted(texture.as_ref(), region.page_rect(), tint)?;
+    Some((Cow::Owned(copy), offset))
+}
+
+/// Map region-relative mesh UVs into page pixels, undoing whitespace
+/// stripping and 90° packing the same way the official runtimes do
+fn mesh_page_uvs(uvs: &[f32], region: &atlas::AtlasRegion) -> Vec<(f32, f32)> {
+    let pairs = uvs.chunks_exact(2).map(|uv| (uv[0], uv[1]));
+
+    if region.degrees == 90 {
+        let u = region.x - (region.original_height - region.offset_y - region.height);
+        let v = region.y - (region.original_width - region.offset_x - region.width);
+        pairs
+            .map(|(ru, rv)| (u + rv * region.original_height, v + (1.0 - ru) * region.original_width))
+            .collect()
+    } else {
+        let u = region.x - region.offset_x;
+        let v = region.y - (region.original_height - region.offset_y - region.height);
+        pairs
+            .map(|(ru, rv)| (u + ru * region.original_width, v + rv * region.original_height))
+            .collect()
+    }
+}
+
+/// Spine renderer - Renders standalone Spine animations in-process
+pub struct SpineRenderer {
+    options: SpineOptions,
+    frame_loop: Option<FrameLoop>,
+    current_path: Option<String>,
+}
+
+impl SpineRenderer {
+    pub fn new() -> Self {
+        Self::with_options(SpineOptions::default())
+    }
+
+    pub fn with_options(options: SpineOptions) -> Self {
+        Self {
+            options,
+            frame_loop: None,
+            current_path: None,
+        }
+    }
+
+    /// Detect if directory contains Spine files
+    pub fn is_spine_directory(path: &Path) -> bool {
+        if !path.is_dir() {
+            return false;
+        }
+
+        // Must NOT have project.json (that would be WallpaperEngine)
+        let has_project = path.join("project.json").exists();
+
+        !has_project && Self::find_skeleton_file(path).is_some()
+    }
+
+    /// Find the skeleton in a directory: a `.skel`, or a `.json` with an atlas beside it
+    pub(crate) fn find_skeleton_file(dir: &Path) -> Option<PathBuf> {
+        let files: Vec<PathBuf> = std::fs::read_dir(dir)
+            .ok()?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .collect();
+
+        let extension = |p: &Path| p.extension().and_then(|s| s.to_str()).map(str::to_string);
+
+        if let Some(skel) = files.iter().find(|p| extension(p).as_deref() == Some("skel")) {
+            return Some(skel.clone());
+        }
+
+        let has_atlas = files.iter().any(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .map(|n| n.ends_with(".atlas") || n.ends_with(".atlas.txt"))
+                .unwrap_or(false)
+        });
+        if !has_atlas {
+            return None;
+        }
+
+        files
+            .into_iter()
+            .filter(|p| extension(p).as_deref() == Some("json"))
+            .find(|p| {
+                std::fs::read_to_string(p)
+                    .ok()
+                    .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
+                    .map(|v| v.get("bones").is_some() && v.get("slots").is_some())
+                    .unwrap_or(false)
+            })
+    }
+
+    /// Render a single frame offscreen, e.g. for thumbnails and previews
+    pub fn render_frame(&self, path: &Path, width: u32, height: u32, time: f32) -> Result<Pixmap> {
+        SpinePlayer::load(path, self.options.clone())?.render(width, height, time)
+    }
+
+    /// Render the first frame to a PNG file
+    pub fn render_thumbnail(&self, path: &Path, output: &Path, width: u32, height: u32) -> Result<()> {
+        self.render_frame(path, width, height, 0.0)?
+            .save_png(output)
+            .map_err(|e| Error::Renderer(format!("Failed to write thumbnail: {}", e)))
+    }
+}
+
+impl Default for SpineRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RendererTrait for SpineRenderer {
+    fn name(&self) -> &str {
+        "spine"
+    }
+
+    fn is_available(&self) -> bool {
+        DesktopSurface::is_supported()
+    }
+
+    fn start(&mut self, path: &Path, monitor: Option<&str>) -> Result<()> {
+        // Stop any existing playback
+        self.stop()?;
+
+        let player = SpinePlayer::load(path, self.options.clone())?;
+        tracing::info!(
+            "Playing Spine skeleton {} ({} animations)",
+            path.display(),
+            player.animations().len()
+        );
+
+        self.frame_loop = Some(FrameLoop::spawn(player, monitor, self.options.fps)?);
+        self.current_path = Some(path.to_string_lossy().to_string());
+
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        if let Some(mut frame_loop) = self.frame_loop.take() {
+            frame_loop.stop();
+        }
+        self.current_path = None;
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.frame_loop
+            .as_ref()
+            .map(|f| f.is_running())
+            .unwrap_or(false)
+    }
+}
+
+impl Drop for SpineRenderer {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ATLAS: &str = "square.png\nsize: 4,4\nfilter: Linear,Linear\nsquare\n  bounds: 0,0,4,4\n";
+
+    const SKELETON: &str = r#"{
+        "skeleton": { "spine": "4.1.24" },
+        "bones": [ { "name": "root" } ],
+        "slots": [ { "name": "body", "bone": "root", "attachment": "square" } ],
+        "skins": [ { "name": "default", "attachments": {
+            "body": { "square": { "width": 100, "height": 100 } }
+        } } ],
+        "animations": { "idle": { "slots": { "body": { "rgba": [
+            { "time": 0, "color": "ffffffff" },
+            { "time": 1, "color": "ffffff00" }
+        ] } } } }
+    }"#;
+
+    fn write_project(dir: &Path) {
+        let texture = image::RgbaImage::from_pixel(4, 4, image::Rgba([255, 0, 0, 255]));
+        texture.save(dir.join("square.png")).unwrap();
+        std::fs::write(dir.join("square.atlas"), ATLAS).unwrap();
+        std::fs::write(dir.join("square.json"), SKELETON).unwrap();
+    }
+
+    #[test]
+    fn test_detects_json_spine_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!SpineRenderer::is_spine_directory(dir.path()));
+
+        write_project(dir.path());
+        assert!(SpineRenderer::is_spine_directory(dir.path()));
+
+        std::fs::write(dir.path().join("project.json"), "{}").unwrap();
+        assert!(!SpineRenderer::is_spine_directory(dir.path()));
+    }
+
+    #[test]
+    fn test_renders_frames() {
+        let dir = tempfile::tempdir().unwrap();
+        write_project(dir.path());
+
+        let options = SpineOptions {
+            background: [0, 0, 0, 0],
+            ..SpineOptions::default()
+        };
+        let mut player = SpinePlayer::load(dir.path(), options).unwrap();
+        assert_eq!(player.animations(), vec!["idle"]);
+
+        let frame = player.render(64, 64, 0.0).unwrap();
+        let center = frame.pixel(32, 32).unwrap().demultiply();
+        assert_eq!((center.red(), center.green(), center.alpha()), (255, 0, 255));
+        // The square is fit with a margin, so the corner stays clear
+        assert_eq!(frame.pixel(0, 0).unwrap().alpha(), 0);
+
+        // Half way through the fade
+        let frame = player.render(64, 64, 0.5).unwrap();
+        let alpha = frame.pixel(32, 32).unwrap().alpha();
+        assert!((120..=135).contains(&alpha), "alpha {}", alpha);
+    }
+
+    #[test]
+    fn test_tints_with_slot_and_attachment_colors() {
+        let dir = tempfile::tempdir().unwrap();
+        write_project(dir.path());
+        let skeleton = SKELETON
+            .replace(r#"{ "time": 0, "color": "ffffffff" }"#, r#"{ "time": 0, "color": "7f7f7fff" }"#)
+            .replace(r#""width": 100,"#, r#""color": "ff00ffff", "width": 100,"#);
+        std::fs::write(dir.path().join("square.json"), skeleton).unwrap();
+
+        let options = SpineOptions {
+            background: [0, 0, 0, 0],
+            ..SpineOptions::default()
+        };
+        let frame = SpinePlayer::load(dir.path(), options).unwrap().render(64, 64, 0.0).unwrap();
+        let center = frame.pixel(32, 32).unwrap().demultiply();
+        assert!((125..=129).contains(&center.red()), "red {}", center.red());
+        assert_eq!((center.green(), center.alpha()), (0, 255));
+    }
+
+    #[test]
+    fn test_refuses_what_it_cannot_show() {
+        let dir = tempfile::tempdir().unwrap();
+        write_project(dir.path());
+        let transform = SKELETON.replace(
+            r#""slots": ["#,
+            r#""transform": [ { "name": "follow", "bones": [ "root" ], "target": "root" } ], "slots": ["#,
+        );
+        std::fs::write(dir.path().join("square.json"), transform).unwrap();
+
+        match SpinePlayer::load(dir.path(), SpineOptions::default()) {
+            Err(e) => assert!(e.to_string().contains("transform constraints"), "{}", e),
+            Ok(_) => panic!("loaded a skeleton it can't show"),
+        }
+    }
+
+    #[test]
+    fn test_plays_ik_rig() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/spine-arm");
+        let mut player = SpinePlayer::load(&fixture, SpineOptions::default()).unwrap();
+        let lower = player.skeleton.data.find_bone("lower").unwrap();
+        let hand = player.skeleton.data.find_slot("lower").unwrap();
+
+        // The target starts at (120, 80) and moves by (-40, 60) over a second
+        for (time, target) in [(0.0, (120.0, 80.0)), (0.5, (100.0, 110.0))] {
+            player.pose(time);
+            let bone = &player.skeleton.bones[lower];
+            let tip = (bone.world_x + bone.a * 100.0, bone.world_y + bone.c * 100.0);
+            assert!(
+                (tip.0 - target.0).abs() < 0.5 && (tip.1 - target.1).abs() < 0.5,
+                "tip {:?} at {}s",
+                tip,
+                time
+            );
+            // bendPositive: false keeps the elbow left of the line to the target
+            assert!(bone.world_x * target.1 - bone.world_y * target.0 < 0.0);
+        }
+
+        // Halfway through, the hand mesh is pushed 5 units along its bone
+        let Some(Attachment::Mesh(mesh)) = player.skeleton.attachment(hand, "hand") else {
+            panic!("hand isn't a mesh");
+        };
+        let deformed = player.skeleton.mesh_vertices(hand, &mesh.vertices);
+        let bone = &player.skeleton.bones[lower];
+        let expected = (bone.world_x + bone.a * 5.0 - bone.b * 10.0, bone.world_y + bone.c * 5.0 - bone.d * 10.0);
+        assert!((deformed[0].0 - expected.0).abs() < 0.01 && (deformed[0].1 - expected.1).abs() < 0.01);
+
+        let frame = player.render(64, 64, 0.5).unwrap();
+        assert!(frame.pixels().iter().any(|p| p.red() > 0));
+    }
+}
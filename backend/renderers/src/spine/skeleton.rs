@@ -0,0 +1,571 @@
+use super::atlas::{Atlas, AtlasRegion};
+use super::data::{Attachment, Color, Inherit, MeshVertices, RegionAttachment, SkeletonData};
+use std::sync::Arc;
+
+/// Local transform the world transform is computed from
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Local {
+    pub x: f32,
+    pub y: f32,
+    pub rotation: f32,
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub shear_x: f32,
+    pub shear_y: f32,
+}
+
+/// Current local and world transform of a bone
+#[derive(Debug, Clone, Default)]
+pub struct Bone {
+    pub x: f32,
+    pub y: f32,
+    pub rotation: f32,
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub shear_x: f32,
+    pub shear_y: f32,
+    /// Local transform after constraints
+    pub applied: Local,
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub world_x: f32,
+    pub world_y: f32,
+}
+
+impl Bone {
+    fn local(&self) -> Local {
+        Local {
+            x: self.x,
+            y: self.y,
+            rotation: self.rotation,
+            scale_x: self.scale_x,
+            scale_y: self.scale_y,
+            shear_x: self.shear_x,
+            shear_y: self.shear_y,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Slot {
+    pub color: Color,
+    pub attachment: Option<String>,
+    /// Deformed vertices of the current mesh; empty for its setup vertices
+    pub deform: Vec<f32>,
+}
+
+/// Current settings of an IK constraint
+#[derive(Debug, Clone, Copy)]
+pub struct IkConstraint {
+    pub mix: f32,
+    pub softness: f32,
+    pub bend_direction: f32,
+    pub compress: bool,
+    pub stretch: bool,
+}
+
+/// A posable instance of `SkeletonData`
+#[derive(Debug, Clone)]
+pub struct Skeleton {
+    pub data: Arc<SkeletonData>,
+    pub bones: Vec<Bone>,
+    pub slots: Vec<Slot>,
+    pub ik_constraints: Vec<IkConstraint>,
+    pub draw_order: Vec<usize>,
+    pub skin: Option<usize>,
+}
+
+impl Skeleton {
+    pub fn new(data: Arc<SkeletonData>) -> Self {
+        let mut skeleton = Self {
+            bones: vec![Bone::default(); data.bones.len()],
+            slots: data
+                .slots
+                .iter()
+                .map(|s| Slot {
+                    color: s.color,
+                    attachment: s.attachment.clone(),
+                    deform: Vec::new(),
+                })
+                .collect(),
+            ik_constraints: data
+                .ik_constraints
+                .iter()
+                .map(|c| IkConstraint {
+                    mix: c.mix,
+                    softness: c.softness,
+                    bend_direction: c.bend_direction,
+                    compress: c.compress,
+                    stretch: c.stretch,
+                })
+                .collect(),
+            draw_order: (0..data.slots.len()).collect(),
+            skin: None,
+            data,
+        };
+        skeleton.set_to_setup_pose();
+        skeleton
+    }
+
+    pub fn set_to_setup_pose(&mut self) {
+        for (bone, setup) in self.bones.iter_mut().zip(&self.data.bones) {
+            bone.x = setup.x;
+            bone.y = setup.y;
+            bone.rotation = setup.rotation;
+            bone.scale_x = setup.scale_x;
+            bone.scale_y = setup.scale_y;
+            bone.shear_x = setup.shear_x;
+            bone.shear_y = setup.shear_y;
+        }
+
+        for (slot, setup) in self.slots.iter_mut().zip(&self.data.slots) {
+            slot.color = setup.color;
+            slot.attachment = setup.attachment.clone();
+            slot.deform.clear();
+        }
+
+        for (ik, setup) in self.ik_constraints.iter_mut().zip(&self.data.ik_constraints) {
+            ik.mix = setup.mix;
+            ik.softness = setup.softness;
+            ik.bend_direction = setup.bend_direction;
+            ik.compress = setup.compress;
+            ik.stretch = setup.stretch;
+        }
+
+        self.draw_order = (0..self.slots.len()).collect();
+    }
+
+    /// Resolve an attachment through the active skin, then the default skin
+    pub fn attachment(&self, slot: usize, name: &str) -> Option<&Attachment> {
+        let key = (slot, name.to_string());
+        self.skin
+            .and_then(|s| self.data.skins[s].attachments.get(&key))
+            .or_else(|| {
+                self.data
+                    .find_skin("default")
+                    .and_then(|s| self.data.skins[s].attachments.get(&key))
+            })
+    }
+
+    /// Compute world transforms, then apply IK constraints in order
+    pub fn update_world_transform(&mut self) {
+        for bone in &mut self.bones {
+            bone.applied = bone.local();
+        }
+        self.update_bones(0);
+
+        let mut order: Vec<usize> = (0..self.data.ik_constraints.len()).collect();
+        order.sort_by_key(|&i| self.data.ik_constraints[i].order);
+        for index in order {
+            let data = self.data.clone();
+            let constraint = &data.ik_constraints[index];
+            let ik = self.ik_constraints[index];
+            if ik.mix == 0.0 {
+                continue;
+            }
+
+            let target = &self.bones[constraint.target];
+            let (target_x, target_y) = (target.world_x, target.world_y);
+            match constraint.bones[..] {
+                [bone] => {
+                    self.apply_ik1(bone, target_x, target_y, ik.compress, ik.stretch, constraint.uniform, ik.mix);
+                    self.update_bones(bone);
+                }
+                [parent, child] => {
+                    self.apply_ik2(parent, child, target_x, target_y, &ik, constraint.uniform);
+                    self.update_bones(parent);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Recompute world transforms from their applied local transforms,
+    /// starting at `from`; bones are stored parents-first
+    fn update_bones(&mut self, from: usize) {
+        for i in from..self.bones.len() {
+            let setup = &self.data.bones[i];
+            let local = self.bones[i].applied;
+
+            let rotation_x = (local.rotation + local.shear_x).to_radians();
+            let rotation_y = (local.rotation + 90.0 + local.shear_y).to_radians();
+            let la = rotation_x.cos() * local.scale_x;
+            let lb = rotation_y.cos() * local.scale_y;
+            let lc = rotation_x.sin() * local.scale_x;
+            let ld = rotation_y.sin() * local.scale_y;
+
+            let (x, y) = (local.x, local.y);
+            let (a, b, c, d, world_x, world_y) = match setup.parent {
+                None => (la, lb, lc, ld, x, y),
+                Some(p) => {
+                    let parent = &self.bones[p];
+                    let (pa, pb, pc, pd) = (parent.a, parent.b, parent.c, parent.d);
+                    let world_x = pa * x + pb * y + parent.world_x;
+                    let world_y = pc * x + pd * y + parent.world_y;
+
+                    match setup.inherit {
+                        Inherit::OnlyTranslation => (la, lb, lc, ld, world_x, world_y),
+                        Inherit::NoScale | Inherit::NoScaleOrReflection => {
+                            // Keep the parent's rotation but drop its scale
+                            let angle = pc.atan2(pa);
+                            let (cos, sin) = (angle.cos(), angle.sin());
+                            (
+                                cos * la - sin * lc,
+                                cos * lb - sin * ld,
+                                sin * la + cos * lc,
+                                sin * lb + cos * ld,
+                                world_x,
+                                world_y,
+                            )
+                        }
+                        Inherit::Normal | Inherit::NoRotationOrReflection => (
+                            pa * la + pb * lc,
+                            pa * lb + pb * ld,
+                            pc * la + pd * lc,
+                            pc * lb + pd * ld,
+                            world_x,
+                            world_y,
+                        ),
+                    }
+                }
+            };
+
+            let bone = &mut self.bones[i];
+            bone.a = a;
+            bone.b = b;
+            bone.c = c;
+            bone.d = d;
+            bone.world_x = world_x;
+            bone.world_y = world_y;
+        }
+    }
+
+    /// World transform (a, b, c, d, x, y) of `bone`'s parent, identity for the root
+    fn parent_world(&self, bone: usize) -> (f32, f32, f32, f32, f32, f32) {
+        match self.data.bones[bone].parent {
+            Some(p) => {
+                let p = &self.bones[p];
+                (p.a, p.b, p.c, p.d, p.world_x, p.world_y)
+            }
+            None => (1.0, 0.0, 0.0, 1.0, 0.0, 0.0),
+        }
+    }
+
+    /// Rotate `bone` towards the target, stretching or compressing it to reach
+    #[allow(clippy::too_many_arguments)]
+    fn apply_ik1(
+        &mut self,
+        bone: usize,
+        target_x: f32,
+        target_y: f32,
+        compress: bool,
+        stretch: bool,
+        uniform: bool,
+        alpha: f32,
+    ) {
+        let (pa, mut pb, pc, mut pd, parent_x, parent_y) = self.parent_world(bone);
+        let setup = &self.data.bones[bone];
+        let local = self.bones[bone].applied;
+        let (world_x, world_y) = (self.bones[bone].world_x, self.bones[bone].world_y);
+
+        let mut rotation_ik = -local.shear_x - local.rotation;
+        let (mut tx, mut ty) = if setup.inherit == Inherit::OnlyTranslation {
+            (target_x - world_x, target_y - world_y)
+        } else {
+            if setup.inherit == Inherit::NoRotationOrReflection {
+                let s = (pa * pd - pb * pc).abs() / (pa * pa + pc * pc).max(0.0001);
+                pb = -pc * s;
+                pd = pa * s;
+                rotation_ik += pc.atan2(pa).to_degrees();
+            }
+            let (x, y) = (target_x - parent_x, target_y - parent_y);
+            let d = pa * pd - pb * pc;
+            if d.abs() <= 0.0001 {
+                (0.0, 0.0)
+            } else {
+                ((x * pd - y * pb) / d - local.x, (y * pa - x * pc) / d - local.y)
+            }
+        };
+        rotation_ik += ty.atan2(tx).to_degrees();
+        if local.scale_x < 0.0 {
+            rotation_ik += 180.0;
+        }
+        let rotation_ik = wrap_degrees(rotation_ik);
+
+        let (mut sx, mut sy) = (local.scale_x, local.scale_y);
+        if compress || stretch {
+            if matches!(setup.inherit, Inherit::NoScale | Inherit::NoScaleOrReflection) {
+                (tx, ty) = (target_x - world_x, target_y - world_y);
+            }
+            let length = setup.length * sx;
+            let distance = (tx * tx + ty * ty).sqrt();
+            if ((compress && distance < length) || (stretch && distance > length)) && length > 0.0001 {
+                let s = (distance / length - 1.0) * alpha + 1.0;
+                sx *= s;
+                if uniform {
+                    sy *= s;
+                }
+            }
+        }
+
+        let applied = &mut self.bones[bone].applied;
+        applied.rotation += rotation_ik * alpha;
+        applied.scale_x = sx;
+        applied.scale_y = sy;
+    }
+
+    /// Bend `parent` and `child` so the child's tip reaches the target
+    fn apply_ik2(&mut self, parent: usize, child: usize, target_x: f32, target_y: f32, ik: &IkConstraint, uniform: bool) {
+        let (alpha, bend, stretch) = (ik.mix, ik.bend_direction, ik.stretch);
+        let parent_bone = &self.bones[parent];
+        let (pl, cl) = (parent_bone.applied, self.bones[child].applied);
+
+        let (px, py) = (pl.x, pl.y);
+        let (mut psx, mut psy, mut csx) = (pl.scale_x, pl.scale_y, cl.scale_x);
+        let (mut sx, mut sy) = (psx, psy);
+        let (os1, mut s2) = if psx < 0.0 { (180.0, -1.0) } else { (0.0, 1.0) };
+        psx = psx.abs();
+        if psy < 0.0 {
+            psy = -psy;
+            s2 = -s2;
+        }
+        let os2 = if csx < 0.0 { 180.0 } else { 0.0 };
+        csx = csx.abs();
+
+        // Where the child sits in world space; a non-uniform parent ignores its y
+        let cx = cl.x;
+        let u = (psx - psy).abs() <= 0.0001;
+        let cy = if !u || stretch { 0.0 } else { cl.y };
+        let cwx = parent_bone.a * cx + parent_bone.b * cy + parent_bone.world_x;
+        let cwy = parent_bone.c * cx + parent_bone.d * cy + parent_bone.world_y;
+
+        let (a, b, c, d, ppx, ppy) = self.parent_world(parent);
+        let id = a * d - b * c;
+        let id = if id.abs() <= 0.0001 { 0.0 } else { 1.0 / id };
+        let (x, y) = (cwx - ppx, cwy - ppy);
+        let dx = (x * d - y * b) * id - px;
+        let dy = (y * a - x * c) * id - py;
+        let l1 = (dx * dx + dy * dy).sqrt();
+        let mut l2 = self.data.bones[child].length * csx;
+
+        if l1 < 0.0001 {
+            self.apply_ik1(parent, target_x, target_y, false, stretch, false, alpha);
+            let applied = &mut self.bones[child].applied;
+            applied.x = cx;
+            applied.y = cy;
+            applied.rotation = 0.0;
+            return;
+        }
+
+        let (x, y) = (target_x - ppx, target_y - ppy);
+        let mut tx = (x * d - y * b) * id - px;
+        let mut ty = (y * a - x * c) * id - py;
+        let mut dd = tx * tx + ty * ty;
+        if ik.softness != 0.0 {
+            let softness = ik.softness * psx * (csx + 1.0) * 0.5;
+            let td = dd.sqrt();
+            let sd = td - l1 - l2 * psx + softness;
+            if sd > 0.0 {
+                let p = (sd / (softness * 2.0)).min(1.0) - 1.0;
+                let p = (sd - softness * (1.0 - p * p)) / td;
+                tx -= p * tx;
+                ty -= p * ty;
+                dd = tx * tx + ty * ty;
+            }
+        }
+
+        let (a1, a2) = if u {
+            l2 *= psx;
+            let cos = (dd - l1 * l1 - l2 * l2) / (2.0 * l1 * l2);
+            let a2 = if cos < -1.0 {
+                std::f32::consts::PI * bend
+            } else if cos > 1.0 {
+                if stretch {
+                    let s = (dd.sqrt() / (l1 + l2) - 1.0) * alpha + 1.0;
+                    sx *= s;
+                    if uniform {
+                        sy *= s;
+                    }
+                }
+                0.0
+            } else {
+                cos.acos() * bend
+            };
+            let cos = cos.clamp(-1.0, 1.0);
+            let (a, b) = (l1 + l2 * cos, l2 * a2.sin());
+            ((ty * a - tx * b).atan2(tx * a + ty * b), a2)
+        } else {
+            ik2_nonuniform(l1, psx * l2, psy * l2, psx, psy, tx, ty, dd, bend)
+        };
+
+        let os = cy.atan2(cx) * s2;
+        let a1 = wrap_degrees((a1 - os).to_degrees() + os1 - pl.rotation);
+        self.bones[parent].applied = Local {
+            x: px,
+            y: py,
+            rotation: pl.rotation + a1 * alpha,
+            scale_x: sx,
+            scale_y: sy,
+            shear_x: 0.0,
+            shear_y: 0.0,
+        };
+
+        let a2 = wrap_degrees(((a2 + os).to_degrees() - cl.shear_x) * s2 + os2 - cl.rotation);
+        let applied = &mut self.bones[child].applied;
+        applied.x = cx;
+        applied.y = cy;
+        applied.rotation = cl.rotation + a2 * alpha;
+    }
+
+    fn to_world(&self, bone: usize, x: f32, y: f32) -> (f32, f32) {
+        let b = &self.bones[bone];
+        (x * b.a + y * b.b + b.world_x, x * b.c + y * b.d + b.world_y)
+    }
+
+    /// World-space corners of a region attachment: BL, UL, UR, BR
+    pub fn region_vertices(&self, bone: usize, region: &RegionAttachment, atlas_region: &AtlasRegion) -> [(f32, f32); 4] {
+        let region_scale_x = region.width / atlas_region.original_width * region.scale_x;
+        let region_scale_y = region.height / atlas_region.original_height * region.scale_y;
+        let local_x = -region.width / 2.0 * region.scale_x + atlas_region.offset_x * region_scale_x;
+        let local_y = -region.height / 2.0 * region.scale_y + atlas_region.offset_y * region_scale_y;
+        let local_x2 = local_x + atlas_region.width * region_scale_x;
+        let local_y2 = local_y + atlas_region.height * region_scale_y;
+
+        let (sin, cos) = region.rotation.to_radians().sin_cos();
+        let corner = |lx: f32, ly: f32| {
+            let x = lx * cos - ly * sin + region.x;
+            let y = lx * sin + ly * cos + region.y;
+            self.to_world(bone, x, y)
+        };
+
+        [
+            corner(local_x, local_y),
+            corner(local_x, local_y2),
+            corner(local_x2, local_y2),
+            corner(local_x2, local_y),
+        ]
+    }
+
+    /// World-space vertices of a mesh in `slot`, deformed by the slot's keys
+    pub fn mesh_vertices(&self, slot: usize, vertices: &MeshVertices) -> Vec<(f32, f32)> {
+        let bone = self.data.slots[slot].bone;
+        let deform = &self.slots[slot].deform;
+        let deform = (deform.len() == vertices.deform_len()).then_some(deform.as_slice());
+
+        match vertices {
+            MeshVertices::Unweighted(flat) => deform
+                .unwrap_or(flat)
+                .chunks_exact(2)
+                .map(|v| self.to_world(bone, v[0], v[1]))
+                .collect(),
+            MeshVertices::Weighted(weighted) => {
+                let mut offsets = deform.unwrap_or_default().chunks_exact(2);
+                weighted
+                    .iter()
+                    .map(|influences| {
+                        influences.iter().fold((0.0, 0.0), |acc, &(b, x, y, w)| {
+                            let (ox, oy) = offsets.next().map_or((0.0, 0.0), |o| (o[0], o[1]));
+                            let (wx, wy) = self.to_world(b.min(self.bones.len() - 1), x + ox, y + oy);
+                            (acc.0 + wx * w, acc.1 + wy * w)
+                        })
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Axis-aligned bounds (min_x, min_y, max_x, max_y) of all visible attachments
+    pub fn bounds(&self, atlas: &Atlas) -> Option<(f32, f32, f32, f32)> {
+        let mut bounds: Option<(f32, f32, f32, f32)> = None;
+        let mut extend = |(x, y): (f32, f32)| {
+            bounds = Some(match bounds {
+                None => (x, y, x, y),
+                Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+            });
+        };
+
+        for &slot_index in &self.draw_order {
+            let slot = &self.slots[slot_index];
+            let Some(name) = &slot.attachment else {
+                continue;
+            };
+            let bone = self.data.slots[slot_index].bone;
+
+            match self.attachment(slot_index, name) {
+                Some(Attachment::Region(region)) => {
+                    if let Some(atlas_region) = atlas.find_region(&region.path) {
+                        for v in self.region_vertices(bone, region, atlas_region) {
+                            extend(v);
+                        }
+                    }
+                }
+                Some(Attachment::Mesh(mesh)) => {
+                    for v in self.mesh_vertices(slot_index, &mesh.vertices) {
+                        extend(v);
+                    }
+                }
+                None => {}
+            }
+        }
+
+        bounds
+    }
+}
+
+/// Wrap an angle in degrees into -180..=180
+fn wrap_degrees(angle: f32) -> f32 {
+    if angle > 180.0 {
+        angle - 360.0
+    } else if angle < -180.0 {
+        angle + 360.0
+    } else {
+        angle
+    }
+}
+
+/// Two-bone IK angles in radians when the parent's scale isn't uniform; `a`
+/// and `b` are the child's length scaled by the parent's x and y scale
+#[allow(clippy::too_many_arguments)]
+fn ik2_nonuniform(l1: f32, a: f32, b: f32, psx: f32, psy: f32, tx: f32, ty: f32, dd: f32, bend: f32) -> (f32, f32) {
+    let (aa, bb) = (a * a, b * b);
+    let ta = ty.atan2(tx);
+    let c = bb * l1 * l1 + aa * dd - aa * bb;
+    let c1 = -2.0 * bb * l1;
+    let c2 = bb - aa;
+    let d = c1 * c1 - 4.0 * c2 * c;
+    if d >= 0.0 {
+        let q = if c1 < 0.0 { -d.sqrt() } else { d.sqrt() };
+        let q = -(c1 + q) * 0.5;
+        let (r0, r1) = (q / c2, c / q);
+        let r = if r0.abs() < r1.abs() { r0 } else { r1 };
+        if r * r <= dd {
+            let y = (dd - r * r).sqrt() * bend;
+            return (ta - y.atan2(r), (y / psy).atan2((r - l1) / psx));
+        }
+    }
+
+    // No exact solution: take the closest or farthest reachable point
+    let (mut min_angle, mut min_x, mut min_y) = (std::f32::consts::PI, l1 - a, 0.0f32);
+    let (mut max_angle, mut max_x, mut max_y) = (0.0f32, l1 + a, 0.0f32);
+    let (mut min_dist, mut max_dist) = (min_x * min_x, max_x * max_x);
+    let c = -a * l1 / (aa - bb);
+    if (-1.0..=1.0).contains(&c) {
+        let angle = c.acos();
+        let (x, y) = (a * angle.cos() + l1, b * angle.sin());
+        let dist = x * x + y * y;
+        if dist < min_dist {
+            (min_angle, min_dist, min_x, min_y) = (angle, dist, x, y);
+        }
+        if dist > max_dist {
+            (max_angle, max_dist, max_x, max_y) = (angle, dist, x, y);
+        }
+    }
+
+    if dd <= (min_dist + max_dist) * 0.5 {
+        (ta - (min_y * bend).atan2(min_x), min_angle * bend)
+    } else {
+        (ta - (max_y * bend).atan2(max_x), max_angle * bend)
+    }
+}
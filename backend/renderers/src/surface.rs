@@ -0,0 +1,458 @@
+use tiny_skia::Pixmap;
+use wallmgr_core::error::{Error, Result};
+
+/// Background window that in-process renderers present their frames to
+///
+/// On X11 this is a borderless window with `_NET_WM_WINDOW_TYPE_DESKTOP`
+/// covering one monitor, which EWMH window managers keep below everything else.
+///
+/// Wayland isn't supported: it would need a wlr-layer-shell surface, and an
+/// XWayland window can't be kept below the desktop. Wayland sessions are
+/// refused up front, so renderers built on this report themselves unavailable
+/// there rather than opening a window on top of everything.
+pub struct DesktopSurface {
+    #[cfg(feature = "x11")]
+    inner: x11::X11Surface,
+}
+
+impl DesktopSurface {
+    /// Open a surface covering `monitor` (None = primary monitor)
+    pub fn open(monitor: Option<&str>) -> Result<Self> {
+        if let Some(reason) = unsupported_reason() {
+            return Err(Error::Renderer(reason.to_string()));
+        }
+
+        #[cfg(feature = "x11")]
+        {
+            Ok(Self {
+                inner: x11::X11Surface::open(monitor)?,
+            })
+        }
+
+        #[cfg(not(feature = "x11"))]
+        {
+            let _ = monitor;
+            Err(Error::Renderer(
+                "wallmgr-renderers was built without X11 support".to_string(),
+            ))
+        }
+    }
+
+    /// Check whether a desktop surface can be opened in this session
+    pub fn is_supported() -> bool {
+        unsupported_reason().is_none()
+    }
+
+    /// Surface size in pixels
+    pub fn size(&self) -> (u32, u32) {
+        #[cfg(feature = "x11")]
+        {
+            self.inner.size()
+        }
+
+        #[cfg(not(feature = "x11"))]
+        {
+            (0, 0)
+        }
+    }
+
+    /// Pointer position relative to the surface, if the pointer is on it
+    pub fn pointer_position(&self) -> Option<(f32, f32)> {
+        #[cfg(feature = "x11")]
+        {
+            self.inner.pointer_position()
+        }
+
+        #[cfg(not(feature = "x11"))]
+        {
+            None
+        }
+    }
+
+    /// Position and size of the surface on the root window
+    pub fn geometry(&self) -> (i32, i32, u32, u32) {
+        #[cfg(feature = "x11")]
+        {
+            self.inner.geometry()
+        }
+
+        #[cfg(not(feature = "x11"))]
+        {
+            (0, 0, 0, 0)
+        }
+    }
+
+    /// Native window handle (the X11 window id), for clients that embed into it
+    pub fn window_id(&self) -> Option<u32> {
+        #[cfg(feature = "x11")]
+        {
+            Some(self.inner.window_id())
+        }
+
+        #[cfg(not(feature = "x11"))]
+        {
+            None
+        }
+    }
+
+    /// Move the top-level window of process `pid` into the surface
+    ///
+    /// For programs that can't embed themselves. Waits up to `timeout` for the
+    /// window to be mapped.
+    pub fn adopt_window(&mut self, pid: u32, timeout: std::time::Duration) -> Result<()> {
+        #[cfg(feature = "x11")]
+        {
+            self.inner.adopt_window(pid, timeout)
+        }
+
+        #[cfg(not(feature = "x11"))]
+        {
+            let _ = (pid, timeout);
+            Err(Error::Renderer(
+                "wallmgr-renderers was built without X11 support".to_string(),
+            ))
+        }
+    }
+
+    /// Show a frame; it must match `size()`
+    pub fn present(&mut self, frame: &Pixmap) -> Result<()> {
+        #[cfg(feature = "x11")]
+        {
+            self.inner.present(frame)
+        }
+
+        #[cfg(not(feature = "x11"))]
+        {
+            let _ = frame;
+            Ok(())
+        }
+    }
+}
+
+/// Why no surface can be opened in this session, if it can't
+fn unsupported_reason() -> Option<&'static str> {
+    let var = |name| std::env::var(name).ok().filter(|value| !value.is_empty());
+    session_problem(
+        cfg!(feature = "x11"),
+        var("DISPLAY").is_some(),
+        var("WAYLAND_DISPLAY").is_some() || var("XDG_SESSION_TYPE").as_deref() == Some("wayland"),
+    )
+}
+
+fn session_problem(x11_support: bool, display: bool, wayland: bool) -> Option<&'static str> {
+    if !x11_support {
+        Some("wallmgr-renderers was built without X11 support")
+    } else if wayland {
+        Some("In-process renderers need an X11 session; Wayland (wlr-layer-shell) is not supported yet")
+    } else if !display {
+        Some("In-process renderers need an X11 display")
+    } else {
+        None
+    }
+}
+
+#[cfg(feature = "x11")]
+mod x11 {
+    use std::time::{Duration, Instant};
+    use tiny_skia::Pixmap;
+    use wallmgr_core::error::{Error, Result};
+    use x11rb::connection::{Connection, RequestConnection};
+    use x11rb::protocol::randr::ConnectionExt as _;
+    use x11rb::protocol::xproto::{
+        AtomEnum, ConfigureWindowAux, ConnectionExt as _, CreateGCAux, CreateWindowAux, EventMask, ImageFormat,
+        PropMode, Window, WindowClass,
+    };
+    use x11rb::rust_connection::RustConnection;
+    use x11rb::wrapper::ConnectionExt as _;
+
+    pub struct X11Surface {
+        conn: RustConnection,
+        root: Window,
+        window: Window,
+        gc: u32,
+        depth: u8,
+        x: i16,
+        y: i16,
+        width: u16,
+        height: u16,
+        buffer: Vec<u8>,
+    }
+
+    fn x11_err(e: impl std::fmt::Display) -> Error {
+        Error::Renderer(format!("X11 error: {}", e))
+    }
+
+    impl X11Surface {
+        pub fn open(monitor: Option<&str>) -> Result<Self> {
+            let (conn, screen_num) = RustConnection::connect(None).map_err(x11_err)?;
+            let screen = &conn.setup().roots[screen_num];
+            let root = screen.root;
+            let depth = screen.root_depth;
+            let visual = screen.root_visual;
+            let black = screen.black_pixel;
+
+            if depth != 24 && depth != 32 {
+                return Err(Error::Renderer(format!(
+                    "Unsupported X11 visual depth: {}",
+                    depth
+                )));
+            }
+
+            let (x, y, width, height) = Self::monitor_geometry(&conn, root, monitor)?
+                .unwrap_or((0, 0, screen.width_in_pixels, screen.height_in_pixels));
+
+            let window = conn.generate_id().map_err(x11_err)?;
+            conn.create_window(
+                depth,
+                window,
+                root,
+                x,
+                y,
+                width,
+                height,
+                0,
+                WindowClass::INPUT_OUTPUT,
+                visual,
+                &CreateWindowAux::new()
+                    .background_pixel(black)
+                    .event_mask(EventMask::EXPOSURE),
+            )
+            .map_err(x11_err)?;
+
+            let atom = |name: &[u8]| -> Result<u32> {
+                Ok(conn
+                    .intern_atom(false, name)
+                    .map_err(x11_err)?
+                    .reply()
+                    .map_err(x11_err)?
+                    .atom)
+            };
+
+            let window_type = atom(b"_NET_WM_WINDOW_TYPE")?;
+            let desktop_type = atom(b"_NET_WM_WINDOW_TYPE_DESKTOP")?;
+            let wm_state = atom(b"_NET_WM_STATE")?;
+            let states = [
+                atom(b"_NET_WM_STATE_BELOW")?,
+                atom(b"_NET_WM_STATE_STICKY")?,
+                atom(b"_NET_WM_STATE_SKIP_TASKBAR")?,
+                atom(b"_NET_WM_STATE_SKIP_PAGER")?,
+            ];
+
+            conn.change_property32(PropMode::REPLACE, window, window_type, AtomEnum::ATOM, &[desktop_type])
+                .map_err(x11_err)?;
+            conn.change_property32(PropMode::REPLACE, window, wm_state, AtomEnum::ATOM, &states)
+                .map_err(x11_err)?;
+            conn.change_property8(PropMode::REPLACE, window, AtomEnum::WM_NAME, AtomEnum::STRING, b"wallmgr")
+                .map_err(x11_err)?;
+
+            let gc = conn.generate_id().map_err(x11_err)?;
+            conn.create_gc(gc, window, &CreateGCAux::new()).map_err(x11_err)?;
+
+            conn.map_window(window).map_err(x11_err)?;
+            conn.flush().map_err(x11_err)?;
+
+            Ok(Self {
+                conn,
+                root,
+                window,
+                gc,
+                depth,
+                x,
+                y,
+                width,
+                height,
+                buffer: Vec::new(),
+            })
+        }
+
+        fn monitor_geometry(
+            conn: &RustConnection,
+            root: Window,
+            monitor: Option<&str>,
+        ) -> Result<Option<(i16, i16, u16, u16)>> {
+            let monitors = match conn.randr_get_monitors(root, true) {
+                Ok(cookie) => match cookie.reply() {
+                    Ok(reply) => reply.monitors,
+                    Err(_) => return Ok(None),
+                },
+                Err(_) => return Ok(None),
+            };
+
+            for info in &monitors {
+                let matches = match monitor {
+                    Some(wanted) => conn
+                        .get_atom_name(info.name)
+                        .ok()
+                        .and_then(|c| c.reply().ok())
+                        .map(|r| r.name == wanted.as_bytes())
+                        .unwrap_or(false),
+                    None => info.primary,
+                };
+
+                if matches {
+                    return Ok(Some((info.x, info.y, info.width, info.height)));
+                }
+            }
+
+            match monitor {
+                Some(wanted) => Err(Error::NotFound(format!("Monitor {} not found", wanted))),
+                None => Ok(monitors
+                    .first()
+                    .map(|info| (info.x, info.y, info.width, info.height))),
+            }
+        }
+
+        pub fn size(&self) -> (u32, u32) {
+            (self.width as u32, self.height as u32)
+        }
+
+        pub fn geometry(&self) -> (i32, i32, u32, u32) {
+            (self.x as i32, self.y as i32, self.width as u32, self.height as u32)
+        }
+
+        pub fn window_id(&self) -> u32 {
+            self.window
+        }
+
+        pub fn adopt_window(&mut self, pid: u32, timeout: Duration) -> Result<()> {
+            let atom = |name: &[u8]| -> Result<u32> {
+                Ok(self
+                    .conn
+                    .intern_atom(false, name)
+                    .map_err(x11_err)?
+                    .reply()
+                    .map_err(x11_err)?
+                    .atom)
+            };
+            let client_list = atom(b"_NET_CLIENT_LIST")?;
+            let wm_pid = atom(b"_NET_WM_PID")?;
+
+            let deadline = Instant::now() + timeout;
+            let client = loop {
+                let clients = self
+                    .conn
+                    .get_property(false, self.root, client_list, AtomEnum::WINDOW, 0, u32::MAX)
+                    .map_err(x11_err)?
+                    .reply()
+                    .map_err(x11_err)?;
+
+                let found = clients.value32().into_iter().flatten().find(|&window| {
+                    self.conn
+                        .get_property(false, window, wm_pid, AtomEnum::CARDINAL, 0, 1)
+                        .ok()
+                        .and_then(|c| c.reply().ok())
+                        .and_then(|r| r.value32().and_then(|mut v| v.next()))
+                        == Some(pid)
+                });
+
+                if let Some(window) = found {
+                    break window;
+                }
+                if Instant::now() >= deadline {
+                    return Err(Error::Renderer(format!(
+                        "No window from process {} appeared",
+                        pid
+                    )));
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            };
+
+            self.conn
+                .reparent_window(client, self.window, 0, 0)
+                .map_err(x11_err)?;
+            self.conn
+                .configure_window(
+                    client,
+                    &ConfigureWindowAux::new()
+                        .x(0)
+                        .y(0)
+                        .width(self.width as u32)
+                        .height(self.height as u32),
+                )
+                .map_err(x11_err)?;
+            self.conn.map_window(client).map_err(x11_err)?;
+            self.conn.flush().map_err(x11_err)?;
+            Ok(())
+        }
+
+        pub fn pointer_position(&self) -> Option<(f32, f32)> {
+            let reply = self.conn.query_pointer(self.root).ok()?.reply().ok()?;
+            let px = reply.root_x as i32 - self.x as i32;
+            let py = reply.root_y as i32 - self.y as i32;
+
+            if px < 0 || py < 0 || px >= self.width as i32 || py >= self.height as i32 {
+                return None;
+            }
+
+            Some((px as f32, py as f32))
+        }
+
+        pub fn present(&mut self, frame: &Pixmap) -> Result<()> {
+            let (width, height) = (self.width as u32, self.height as u32);
+            if frame.width() != width || frame.height() != height {
+                return Err(Error::Renderer(format!(
+                    "Frame size {}x{} does not match surface {}x{}",
+                    frame.width(),
+                    frame.height(),
+                    width,
+                    height
+                )));
+            }
+
+            // Premultiplied RGBA composited over black is exactly BGRX
+            self.buffer.clear();
+            self.buffer.reserve(frame.data().len());
+            for px in frame.data().chunks_exact(4) {
+                self.buffer.extend_from_slice(&[px[2], px[1], px[0], 0xff]);
+            }
+
+            // Split into bands that fit the server's request size limit
+            let row_bytes = width as usize * 4;
+            let max_bytes = self.conn.maximum_request_bytes().saturating_sub(64);
+            let band_rows = (max_bytes / row_bytes).clamp(1, height as usize);
+
+            for (band, chunk) in self.buffer.chunks(band_rows * row_bytes).enumerate() {
+                let rows = (chunk.len() / row_bytes) as u16;
+                self.conn
+                    .put_image(
+                        ImageFormat::Z_PIXMAP,
+                        self.window,
+                        self.gc,
+                        self.width,
+                        rows,
+                        0,
+                        (band * band_rows) as i16,
+                        0,
+                        self.depth,
+                        chunk,
+                    )
+                    .map_err(x11_err)?;
+            }
+
+            self.conn.flush().map_err(x11_err)?;
+            Ok(())
+        }
+    }
+
+    impl Drop for X11Surface {
+        fn drop(&mut self) {
+            let _ = self.conn.free_gc(self.gc);
+            let _ = self.conn.destroy_window(self.window);
+            let _ = self.conn.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wayland_sessions_are_refused() {
+        assert_eq!(session_problem(true, true, false), None);
+        // XWayland sets DISPLAY too
+        assert!(session_problem(true, true, true).unwrap().contains("Wayland"));
+        assert!(session_problem(true, false, false).is_some());
+        assert!(session_problem(false, true, false).is_some());
+    }
+}
@@ -0,0 +1,518 @@
+//! HTML/web wallpapers
+//!
+//! Hosts a local page (a plain `.html` file, a directory with an `index.html`,
+//! or a Wallpaper Engine project of type `web`) or a remote URL in a browser
+//! window placed on the desktop surface. Local pages are served over loopback
+//! with a shim that feeds Wallpaper Engine's `wallpaperPropertyListener`.
+
+pub mod server;
+
+use crate::renderer::RendererTrait;
+use crate::surface::DesktopSurface;
+use serde_json::{Map, Value};
+use server::PageServer;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+use wallmgr_core::error::{Error, Result};
+
+/// Where a web wallpaper's page comes from
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebSource {
+    /// A directory served over loopback, opened at `index`
+    Local { root: PathBuf, index: String },
+    Url(String),
+}
+
+/// A web wallpaper and its Wallpaper Engine user properties
+#[derive(Debug, Clone)]
+pub struct WebProject {
+    pub source: WebSource,
+    pub title: Option<String>,
+    /// `general.properties` from project.json, keyed by property name
+    pub properties: Map<String, Value>,
+}
+
+impl WebProject {
+    /// Load from a URL, an HTML file, a directory or a project.json
+    pub fn load(target: &Path) -> Result<Self> {
+        let text = target.to_string_lossy();
+        if is_url(&text) {
+            return Ok(Self {
+                source: WebSource::Url(text.to_string()),
+                title: None,
+                properties: Map::new(),
+            });
+        }
+
+        let project_file = if target.is_dir() {
+            Some(target.join("project.json")).filter(|p| p.exists())
+        } else if target.file_name().and_then(|n| n.to_str()) == Some("project.json") {
+            Some(target.to_path_buf())
+        } else {
+            None
+        };
+
+        if let Some(project_file) = project_file {
+            return Self::load_project(&project_file);
+        }
+
+        if target.is_dir() {
+            let index = find_index(target)
+                .ok_or_else(|| Error::NotFound("No HTML page found in directory".to_string()))?;
+            return Ok(Self {
+                source: WebSource::Local {
+                    root: target.to_path_buf(),
+                    index,
+                },
+                title: None,
+                properties: Map::new(),
+            });
+        }
+
+        if !is_html_file(target) || !target.is_file() {
+            return Err(Error::InvalidPath(format!(
+                "Not a web wallpaper: {}",
+                target.display()
+            )));
+        }
+
+        Ok(Self {
+            source: WebSource::Local {
+                root: target.parent().unwrap_or(Path::new(".")).to_path_buf(),
+                index: target
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            },
+            title: None,
+            properties: Map::new(),
+        })
+    }
+
+    fn load_project(project_file: &Path) -> Result<Self> {
+        let project: Value = serde_json::from_str(&std::fs::read_to_string(project_file)?)?;
+
+        let kind = project.get("type").and_then(Value::as_str).unwrap_or("");
+        if !kind.eq_ignore_ascii_case("web") {
+            return Err(Error::InvalidInput(format!(
+                "Wallpaper Engine project is of type '{}', not 'web'",
+                kind
+            )));
+        }
+
+        let root = project_file.parent().unwrap_or(Path::new(".")).to_path_buf();
+        let index = project
+            .get("file")
+            .and_then(Value::as_str)
+            .unwrap_or("index.html")
+            .to_string();
+        if !root.join(&index).is_file() {
+            return Err(Error::NotFound(format!("Project page {} not found", index)));
+        }
+
+        let properties = project
+            .pointer("/general/properties")
+            .and_then(Value::as_object)
+            .cloned()
+            .unwrap_or_default();
+
+        Ok(Self {
+            source: WebSource::Local { root, index },
+            title: project.get("title").and_then(Value::as_str).map(str::to_string),
+            properties,
+        })
+    }
+
+    /// Check whether a path looks like a web wallpaper
+    pub fn is_web_wallpaper(path: &Path) -> bool {
+        is_url(&path.to_string_lossy()) || Self::load(path).is_ok()
+    }
+
+    /// Script that stubs the Wallpaper Engine web API and delivers properties
+    ///
+    /// `overrides` replace the `value` of matching project properties; keys
+    /// the project doesn't declare are passed through as new properties.
+    pub fn shim(&self, overrides: &Map<String, Value>, fps: u32) -> String {
+        let mut properties = self.properties.clone();
+        for (key, value) in overrides {
+            match properties.get_mut(key).and_then(Value::as_object_mut) {
+                Some(property) => {
+                    property.insert("value".to_string(), value.clone());
+                }
+                None => {
+                    properties.insert(key.clone(), serde_json::json!({ "value": value }));
+                }
+            }
+        }
+
+        let general = serde_json::json!({ "fps": fps });
+        // Keep "</script>" inside string values from ending the tag early
+        let json = |v: &Value| v.to_string().replace("</", "<\\/");
+
+        format!(
+            r#"(function () {{
+  var userProperties = {};
+  var generalProperties = {};
+  window.wallpaperRegisterAudioListener = function () {{}};
+  window.wallpaperRegisterMediaStatusListener = function () {{}};
+  window.wallpaperRegisterMediaPropertiesListener = function () {{}};
+  window.wallpaperRegisterMediaThumbnailListener = function () {{}};
+  window.wallpaperRegisterMediaPlaybackListener = function () {{}};
+  window.wallpaperRegisterMediaTimelineListener = function () {{}};
+  window.wallpaperRequestRandomFileForProperty = function () {{}};
+  function apply() {{
+    var listener = window.wallpaperPropertyListener;
+    if (!listener) return;
+    if (listener.applyGeneralProperties) listener.applyGeneralProperties(generalProperties);
+    if (listener.applyUserProperties) listener.applyUserProperties(userProperties);
+  }}
+  if (document.readyState === "complete") apply();
+  else window.addEventListener("load", apply);
+}})();"#,
+            json(&Value::Object(properties)),
+            json(&general)
+        )
+    }
+}
+
+fn is_url(text: &str) -> bool {
+    text.starts_with("http://") || text.starts_with("https://")
+}
+
+fn is_html_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .map(|s| matches!(s.to_lowercase().as_str(), "html" | "htm"))
+        .unwrap_or(false)
+}
+
+fn find_index(dir: &Path) -> Option<String> {
+    if dir.join("index.html").is_file() {
+        return Some("index.html".to_string());
+    }
+
+    let mut pages: Vec<String> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && is_html_file(p))
+        .filter_map(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+        .collect();
+    pages.sort();
+    pages.into_iter().next()
+}
+
+/// Browser used to display the page
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebHost {
+    /// suckless surf (WebKitGTK); embeds itself into the surface via XEmbed
+    Surf(PathBuf),
+    /// Chromium-family browser in app mode; its window is reparented into the surface
+    Chromium(PathBuf),
+}
+
+impl WebHost {
+    const CHROMIUM_BINARIES: &'static [&'static str] = &[
+        "chromium",
+        "chromium-browser",
+        "google-chrome-stable",
+        "google-chrome",
+        "brave-browser",
+        "microsoft-edge",
+    ];
+
+    /// Use `browser` if given, otherwise the first supported browser on PATH
+    pub fn locate(browser: Option<&Path>) -> Option<Self> {
+        if let Some(browser) = browser {
+            let expanded = PathBuf::from(shellexpand::tilde(&browser.to_string_lossy()).as_ref());
+            let is_surf = expanded.file_name().and_then(|n| n.to_str()) == Some("surf");
+            return Some(if is_surf {
+                Self::Surf(expanded)
+            } else {
+                Self::Chromium(expanded)
+            });
+        }
+
+        let paths: Vec<PathBuf> = std::env::var_os("PATH")
+            .map(|p| std::env::split_paths(&p).collect())
+            .unwrap_or_default();
+        let find = |name: &str| paths.iter().map(|dir| dir.join(name)).find(|p| p.is_file());
+
+        find("surf").map(Self::Surf).or_else(|| {
+            Self::CHROMIUM_BINARIES
+                .iter()
+                .find_map(|name| find(name))
+                .map(Self::Chromium)
+        })
+    }
+
+    fn command(&self, url: &str, surface: &DesktopSurface, profile: &Path, offline: bool) -> Command {
+        match self {
+            Self::Surf(binary) => {
+                let mut cmd = Command::new(binary);
+                if let Some(window) = surface.window_id() {
+                    cmd.arg("-e").arg(window.to_string());
+                }
+                cmd.arg(url);
+                cmd
+            }
+            Self::Chromium(binary) => {
+                let (x, y, width, height) = surface.geometry();
+                let mut cmd = Command::new(binary);
+                cmd.arg(format!("--app={}", url))
+                    .arg(format!("--user-data-dir={}", profile.display()))
+                    .arg(format!("--window-position={},{}", x, y))
+                    .arg(format!("--window-size={},{}", width, height))
+                    .arg("--class=wallmgr-web")
+                    .arg("--no-first-run")
+                    .arg("--no-default-browser-check")
+                    .arg("--disable-infobars")
+                    .arg("--disable-session-crashed-bubble")
+                    .arg("--disable-features=Translate")
+                    .arg("--autoplay-policy=no-user-gesture-required");
+
+                if offline {
+                    // Unresolvable names and a dead proxy for everything but loopback
+                    cmd.arg("--host-resolver-rules=MAP * ~NOTFOUND, EXCLUDE 127.0.0.1")
+                        .arg("--proxy-server=127.0.0.1:9")
+                        .arg("--disable-background-networking")
+                        .arg("--disable-component-update")
+                        .arg("--disable-sync");
+                }
+                cmd
+            }
+        }
+    }
+}
+
+/// Settings for web wallpapers
+#[derive(Debug, Clone)]
+pub struct WebOptions {
+    /// Block all network access except the page's own files
+    pub offline: bool,
+    /// Browser binary (surf or a Chromium-family browser); searched on PATH if unset
+    pub browser: Option<PathBuf>,
+    /// User property values, overriding the project's defaults
+    pub properties: Map<String, Value>,
+    /// Reported to pages via `applyGeneralProperties`
+    pub fps: u32,
+}
+
+impl Default for WebOptions {
+    fn default() -> Self {
+        Self {
+            offline: false,
+            browser: None,
+            properties: Map::new(),
+            fps: 30,
+        }
+    }
+}
+
+/// Web renderer - Shows HTML wallpapers in a browser on the desktop
+pub struct WebRenderer {
+    options: WebOptions,
+    host: Option<WebHost>,
+    process: Option<Child>,
+    server: Option<PageServer>,
+    // Boxed: the X11 connection is large and the renderer sits in the `Renderer` enum
+    surface: Option<Box<DesktopSurface>>,
+    profile_dir: Option<PathBuf>,
+    current_path: Option<String>,
+}
+
+impl WebRenderer {
+    pub fn new() -> Self {
+        Self::with_options(WebOptions::default())
+    }
+
+    pub fn with_options(options: WebOptions) -> Self {
+        let host = WebHost::locate(options.browser.as_deref());
+
+        Self {
+            options,
+            host,
+            process: None,
+            server: None,
+            surface: None,
+            profile_dir: None,
+            current_path: None,
+        }
+    }
+
+    /// Resolve the URL to open, starting the loopback server for local pages
+    fn serve(&mut self, project: &WebProject) -> Result<String> {
+        match &project.source {
+            WebSource::Url(url) => {
+                if self.options.offline {
+                    return Err(Error::InvalidInput(
+                        "Offline mode only allows local web wallpapers".to_string(),
+                    ));
+                }
+                Ok(url.clone())
+            }
+            WebSource::Local { root, index } => {
+                let shim = project.shim(&self.options.properties, self.options.fps);
+                let server = PageServer::start(root, index, &shim, self.options.offline)?;
+                let url = server.url();
+                self.server = Some(server);
+                Ok(url)
+            }
+        }
+    }
+}
+
+impl Default for WebRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RendererTrait for WebRenderer {
+    fn name(&self) -> &str {
+        "web"
+    }
+
+    fn is_available(&self) -> bool {
+        self.host.is_some() && DesktopSurface::is_supported()
+    }
+
+    fn start(&mut self, path: &Path, monitor: Option<&str>) -> Result<()> {
+        let host = self
+            .host
+            .clone()
+            .ok_or_else(|| Error::Renderer("No supported browser (surf or Chromium) found".to_string()))?;
+
+        // Stop any existing page
+        self.stop()?;
+
+        let project = WebProject::load(path)?;
+        let url = self.serve(&project)?;
+        let mut surface = DesktopSurface::open(monitor)?;
+
+        let profile_dir = std::env::temp_dir().join(format!("wallmgr-web-{}", std::process::id()));
+        std::fs::create_dir_all(&profile_dir)?;
+
+        let child = host
+            .command(&url, &surface, &profile_dir, self.options.offline)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| Error::Renderer(format!("Failed to start browser: {}", e)))?;
+
+        if matches!(host, WebHost::Chromium(_)) {
+            if let Err(e) = surface.adopt_window(child.id(), Duration::from_secs(10)) {
+                tracing::warn!("Browser window stays a normal window: {}", e);
+            }
+        }
+
+        tracing::info!(
+            "Showing web wallpaper {} at {}",
+            project.title.as_deref().unwrap_or(&path.to_string_lossy()),
+            url
+        );
+
+        self.process = Some(child);
+        self.surface = Some(Box::new(surface));
+        self.profile_dir = Some(profile_dir);
+        self.current_path = Some(path.to_string_lossy().to_string());
+
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        if let Some(mut child) = self.process.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        if let Some(mut server) = self.server.take() {
+            server.stop();
+        }
+        self.surface = None;
+        if let Some(dir) = self.profile_dir.take() {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+        self.current_path = None;
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.process
+            .as_ref()
+            .map(|child| {
+                nix::sys::signal::kill(nix::unistd::Pid::from_raw(child.id() as i32), None).is_ok()
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl Drop for WebRenderer {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loads_wallpaper_engine_web_project() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("page.html"), "<html></html>").unwrap();
+        std::fs::write(
+            dir.path().join("project.json"),
+            r#"{
+                "title": "Clock",
+                "type": "web",
+                "file": "page.html",
+                "general": {
+                    "properties": {
+                        "schemecolor": { "type": "color", "value": "0 0 0" },
+                        "showseconds": { "type": "bool", "value": true }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let project = WebProject::load(dir.path()).unwrap();
+        assert_eq!(project.title.as_deref(), Some("Clock"));
+        assert_eq!(
+            project.source,
+            WebSource::Local {
+                root: dir.path().to_path_buf(),
+                index: "page.html".to_string()
+            }
+        );
+
+        let mut overrides = Map::new();
+        overrides.insert("showseconds".to_string(), Value::Bool(false));
+        overrides.insert("note".to_string(), Value::String("</script>".to_string()));
+        let shim = project.shim(&overrides, 60);
+
+        assert!(shim.contains(r#""showseconds":{"type":"bool","value":false}"#));
+        assert!(shim.contains(r#""schemecolor":{"type":"color","value":"0 0 0"}"#));
+        assert!(shim.contains(r#""note":{"value":"<\/script>"}"#));
+        assert!(shim.contains(r#"{"fps":60}"#));
+    }
+
+    #[test]
+    fn test_detects_web_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("dashboard.htm"), "<html></html>").unwrap();
+        std::fs::write(dir.path().join("project.json"), r#"{"type": "scene", "file": "scene.pkg"}"#).unwrap();
+
+        assert!(WebProject::is_web_wallpaper(Path::new("https://example.com/wall")));
+        assert!(WebProject::is_web_wallpaper(&dir.path().join("dashboard.htm")));
+        // A scene project is not a web wallpaper even though it has a page next to it
+        assert!(!WebProject::is_web_wallpaper(dir.path()));
+
+        let mut renderer = WebRenderer::with_options(WebOptions {
+            offline: true,
+            ..WebOptions::default()
+        });
+        let project = WebProject::load(Path::new("https://example.com/wall")).unwrap();
+        assert!(renderer.serve(&project).is_err());
+    }
+}
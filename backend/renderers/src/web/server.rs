@@ -0,0 +1,319 @@
+//! Loopback HTTP server for local web wallpapers
+//!
+//! Pages are served from `http://127.0.0.1:<port>/` rather than `file://` so
+//! that scripts behave as they do in Wallpaper Engine (same-origin fetches,
+//! modules, workers), and so the shim can be injected into HTML responses and
+//! network access restricted with a Content-Security-Policy.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use wallmgr_core::error::{Error, Result};
+
+/// Only allow the page's own origin plus inline/data/blob content
+pub const OFFLINE_CSP: &str = "default-src 'self' 'unsafe-inline' 'unsafe-eval' data: blob:; \
+     connect-src 'self' data: blob:; form-action 'none'";
+
+struct Site {
+    root: PathBuf,
+    index: String,
+    inject: String,
+    offline: bool,
+}
+
+/// Serves one wallpaper directory on a random loopback port
+pub struct PageServer {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl PageServer {
+    /// Serve `root`, with `/` mapped to `index` and `inject` added to every HTML page
+    pub fn start(root: &Path, index: &str, inject: &str, offline: bool) -> Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+
+        let site = Arc::new(Site {
+            root: root.to_path_buf(),
+            index: index.to_string(),
+            inject: inject.to_string(),
+            offline,
+        });
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = stop.clone();
+
+        let handle = std::thread::Builder::new()
+            .name("wallmgr-web-server".to_string())
+            .spawn(move || {
+                while !stop_flag.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            let site = site.clone();
+                            std::thread::spawn(move || {
+                                if let Err(e) = handle_connection(stream, &site) {
+                                    tracing::debug!("Web wallpaper request failed: {}", e);
+                                }
+                            });
+                        }
+                        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                            std::thread::sleep(Duration::from_millis(25));
+                        }
+                        Err(e) => {
+                            tracing::error!("Web wallpaper server stopped: {}", e);
+                            break;
+                        }
+                    }
+                }
+            })
+            .map_err(|e| Error::Renderer(format!("Failed to start web server: {}", e)))?;
+
+        Ok(Self {
+            addr,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    /// URL of the page's index
+    pub fn url(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for PageServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn handle_connection(stream: TcpStream, site: &Site) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Drain headers; nothing in them matters to a static file server
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let target = parts.next().unwrap_or("/");
+
+    let mut stream = stream;
+    if method != "GET" && method != "HEAD" {
+        return respond(&mut stream, site, "405 Method Not Allowed", "text/plain", b"", method == "HEAD");
+    }
+
+    let Some(file) = resolve(site, target) else {
+        return respond(&mut stream, site, "404 Not Found", "text/plain", b"Not found", method == "HEAD");
+    };
+
+    let body = match std::fs::read(&file) {
+        Ok(body) => body,
+        Err(_) => {
+            return respond(&mut stream, site, "404 Not Found", "text/plain", b"Not found", method == "HEAD");
+        }
+    };
+
+    let content_type = content_type(&file);
+    let body = if content_type.starts_with("text/html") {
+        inject_html(&String::from_utf8_lossy(&body), &site.inject).into_bytes()
+    } else {
+        body
+    };
+
+    respond(&mut stream, site, "200 OK", content_type, &body, method == "HEAD")
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    site: &Site,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+    head_only: bool,
+) -> std::io::Result<()> {
+    let mut header = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    if site.offline {
+        header.push_str(&format!("Content-Security-Policy: {}\r\n", OFFLINE_CSP));
+    }
+    header.push_str("\r\n");
+
+    stream.write_all(header.as_bytes())?;
+    if !head_only {
+        stream.write_all(body)?;
+    }
+    stream.flush()
+}
+
+/// Map a request target to a file under the site root
+fn resolve(site: &Site, target: &str) -> Option<PathBuf> {
+    let path = target.split(['?', '#']).next().unwrap_or("/");
+    let decoded = percent_decode(path)?;
+
+    let relative = Path::new(decoded.trim_start_matches('/'));
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    let mut file = site.root.join(relative);
+    if relative.as_os_str().is_empty() {
+        file = site.root.join(&site.index);
+    } else if file.is_dir() {
+        file = file.join("index.html");
+    }
+
+    file.is_file().then_some(file)
+}
+
+fn percent_decode(input: &str) -> Option<String> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+/// Insert `script` at the start of `<head>` so it runs before the page's own scripts
+pub fn inject_html(html: &str, script: &str) -> String {
+    if script.is_empty() {
+        return html.to_string();
+    }
+
+    let tag = format!("<script>{}</script>", script);
+    let lower = html.to_ascii_lowercase();
+    let position = ["<head", "<html"]
+        .iter()
+        .find_map(|open| {
+            let start = lower.find(open)?;
+            Some(start + lower[start..].find('>')? + 1)
+        })
+        .unwrap_or(0);
+
+    let mut out = String::with_capacity(html.len() + tag.len());
+    out.push_str(&html[..position]);
+    out.push_str(&tag);
+    out.push_str(&html[position..]);
+    out
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "json" => "application/json",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "wasm" => "application/wasm",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "txt" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn get(server: &PageServer, path: &str) -> String {
+        let mut stream = TcpStream::connect(server.addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_serves_pages_with_shim() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("main.html"),
+            "<!DOCTYPE html><html><HEAD><title>t</title></HEAD><body></body></html>",
+        )
+        .unwrap();
+        std::fs::create_dir(dir.path().join("js")).unwrap();
+        std::fs::write(dir.path().join("js/app file.js"), "let x = 1;").unwrap();
+
+        let server = PageServer::start(dir.path(), "main.html", "window.shim=1", true).unwrap();
+
+        let index = get(&server, "/");
+        assert!(index.starts_with("HTTP/1.1 200"));
+        assert!(index.contains("<HEAD><script>window.shim=1</script><title>"));
+        assert!(index.contains("Content-Security-Policy: default-src 'self'"));
+
+        let script = get(&server, "/js/app%20file.js?v=2");
+        assert!(script.contains("text/javascript"));
+        assert!(script.ends_with("let x = 1;"));
+        assert!(!script.contains("window.shim"));
+    }
+
+    #[test]
+    fn test_rejects_paths_outside_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("site");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(root.join("index.html"), "<html></html>").unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+
+        let server = PageServer::start(&root, "index.html", "", false).unwrap();
+        assert!(get(&server, "/../secret.txt").starts_with("HTTP/1.1 404"));
+        assert!(get(&server, "/%2e%2e/secret.txt").starts_with("HTTP/1.1 404"));
+        assert!(!get(&server, "/").contains("Content-Security-Policy"));
+    }
+}
@@ -34,33 +34,144 @@ enum Commands {
 
     /// List wallpapers
     List {
-        /// Filter by type (image, video, spine, wallpaper_engine)
+        /// Library query, e.g. "landscape -city res:>=2560x1440 order:size:desc"
+        #[arg(short, long)]
+        query: Option<String>,
+        /// Filter by type (image, video, spine, wallpaper_engine, live2d, shader, web, generated)
         #[arg(short, long)]
         r#type: Option<String>,
         /// Limit results
         #[arg(short, long, default_value = "50")]
         limit: usize,
+        /// Skip this many results
+        #[arg(long, default_value = "0")]
+        offset: usize,
     },
 
+    /// Generate a procedural wallpaper at each monitor's resolution
+    Generate {
+        /// Generator (solid, linear, radial, noise, stripes, checkerboard, dots, grid)
+        kind: String,
+        /// Colors as hex, comma-separated (e.g. "#1e1e2e,#89b4fa")
+        #[arg(short, long)]
+        colors: String,
+        /// Gradient direction or pattern rotation in degrees
+        #[arg(short, long, default_value = "0")]
+        angle: f32,
+        /// Noise feature size or pattern cell size in pixels
+        #[arg(short, long)]
+        size: Option<f32>,
+        /// Noise seed
+        #[arg(long, default_value = "0")]
+        seed: u32,
+        /// Name shown in the library
+        #[arg(short, long)]
+        name: Option<String>,
+        /// Tags to add (comma-separated)
+        #[arg(short, long)]
+        tags: Option<String>,
+    },
+
+    /// Re-render generated wallpapers for the current monitors
+    Regenerate,
+
     /// Remove wallpaper
     Remove {
         /// Wallpaper ID
         id: String,
     },
 
+    /// Mark a wallpaper as favorite
+    Favorite {
+        /// Wallpaper ID
+        id: String,
+        /// Unmark it instead
+        #[arg(short, long)]
+        remove: bool,
+    },
+
+    /// Rate a wallpaper
+    Rate {
+        /// Wallpaper ID
+        id: String,
+        /// 1 to 5 stars; 0 clears the rating
+        stars: u8,
+    },
+
+    /// Pick a random wallpaper, favoring favorites and higher rated ones
+    Random {
+        /// Library query, e.g. "-recent:20 weight:favorite=5" or "in:COLLECTION playlist:NAME"
+        #[arg(short, long)]
+        query: Option<String>,
+        /// Set the picked wallpaper
+        #[arg(short, long)]
+        set: bool,
+        /// Monitor to set it on (optional)
+        #[arg(short, long)]
+        monitor: Option<String>,
+    },
+
     /// Search for wallpapers
     Search {
-        /// Search query/tags
+        /// Search query, e.g. "sky -city rating:safe res:1920x1080 order:score"
         query: Option<String>,
         /// Tags to search for (comma-separated)
         #[arg(short, long)]
         tags: Option<String>,
-        /// Source booru (danbooru, yandere, safebooru, gelbooru)
+        /// Source name, e.g. danbooru or a configured instance (default: all enabled)
         #[arg(short, long)]
         source: Option<String>,
         /// Limit results
         #[arg(short, long, default_value = "20")]
         limit: u32,
+        /// Fetch the next page of an earlier search
+        #[arg(long)]
+        cursor: Option<String>,
+        /// Only wallpapers that fit this monitor, or "span" for all monitors combined
+        #[arg(long)]
+        for_monitor: Option<String>,
+    },
+
+    /// List configured booru and wallpaper sources
+    Sources,
+
+    /// Store a source's API key in the system keyring (read from stdin)
+    Login {
+        /// Source name; set `keyring = true` in its config entry
+        source: String,
+        /// Remove the stored key instead
+        #[arg(long)]
+        clear: bool,
+    },
+
+    /// Queue a source account's favorites for download
+    Sync {
+        /// Source name
+        source: String,
+    },
+
+    /// Manage the download queue
+    Downloads {
+        #[command(subcommand)]
+        downloads_cmd: DownloadCommands,
+    },
+
+    /// Edit tags, aliases and implications
+    Tags {
+        #[command(subcommand)]
+        tags_cmd: TagCommands,
+    },
+
+    /// Manage collections, hand-ordered lists of wallpapers (query them with in:NAME)
+    Collections {
+        #[command(subcommand)]
+        collections_cmd: CollectionCommands,
+    },
+
+    /// Manage smart playlists, saved queries (query them with playlist:NAME)
+    Playlists {
+        #[command(subcommand)]
+        playlists_cmd: PlaylistCommands,
     },
 
     /// Get current status
@@ -85,6 +196,173 @@ enum DaemonCommands {
     Status,
 }
 
+#[derive(Subcommand)]
+enum DownloadCommands {
+    /// List download jobs
+    List {
+        /// Filter by state (queued, downloading, paused, done, failed)
+        #[arg(short, long)]
+        state: Option<String>,
+    },
+    /// Pause a job, keeping what has been downloaded
+    Pause {
+        /// Job ID
+        id: i64,
+    },
+    /// Resume a paused or failed job
+    Resume {
+        /// Job ID
+        id: i64,
+    },
+    /// Remove a job and its partial file
+    Cancel {
+        /// Job ID
+        id: i64,
+    },
+    /// Change a job's priority (higher runs first)
+    Priority {
+        /// Job ID
+        id: i64,
+        #[arg(allow_negative_numbers = true)]
+        priority: i32,
+    },
+}
+
+#[derive(Subcommand)]
+enum TagCommands {
+    /// Add tags to wallpapers
+    Add {
+        /// Tags to add (comma-separated)
+        tags: String,
+        /// Wallpaper IDs
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    /// Remove tags from wallpapers
+    Remove {
+        /// Tags to remove (comma-separated)
+        tags: String,
+        /// Wallpaper IDs
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    /// Rename a tag
+    Rename {
+        from: String,
+        to: String,
+    },
+    /// Merge a tag into another, keeping its name as an alias
+    Merge {
+        from: String,
+        into: String,
+    },
+    /// Make one tag name stand for another (e.g. bg background)
+    Alias {
+        alias: String,
+        tag: String,
+    },
+    /// Remove an alias
+    Unalias {
+        alias: String,
+    },
+    /// List aliases
+    Aliases,
+    /// Make a tag come with another (e.g. hatsune_miku vocaloid)
+    Imply {
+        tag: String,
+        implied: String,
+    },
+    /// Remove an implication
+    Unimply {
+        tag: String,
+        implied: String,
+    },
+    /// List implications
+    Implications,
+    /// Import a source's aliases and implications of the library's tags
+    Import {
+        /// Source name, e.g. danbooru
+        source: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum CollectionCommands {
+    /// List collections
+    List,
+    /// Create a collection
+    Create {
+        /// Name, a single word
+        name: String,
+        #[arg(short, long)]
+        description: Option<String>,
+    },
+    /// Show a collection's wallpapers in order
+    Show {
+        name: String,
+    },
+    /// Rename a collection or change its description
+    Edit {
+        name: String,
+        /// New name
+        #[arg(short, long)]
+        rename: Option<String>,
+        #[arg(short, long)]
+        description: Option<String>,
+    },
+    /// Delete a collection, keeping its wallpapers
+    Delete {
+        name: String,
+    },
+    /// Add wallpapers, or move those already in it
+    Add {
+        name: String,
+        /// Wallpaper IDs
+        #[arg(required = true)]
+        ids: Vec<String>,
+        /// Position to insert at (0 is first); the end by default
+        #[arg(long)]
+        at: Option<usize>,
+    },
+    /// Take wallpapers out of a collection
+    Remove {
+        name: String,
+        /// Wallpaper IDs
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum PlaylistCommands {
+    /// List playlists
+    List,
+    /// Save a query as a playlist
+    Create {
+        /// Name, a single word
+        name: String,
+        /// Library query, e.g. "is:favorite ratio:landscape -recent:20"
+        query: String,
+    },
+    /// Show the wallpapers a playlist matches now
+    Show {
+        name: String,
+    },
+    /// Change a playlist's query or name
+    Edit {
+        name: String,
+        /// New library query
+        query: String,
+        /// New name
+        #[arg(short, long)]
+        rename: Option<String>,
+    },
+    /// Delete a playlist
+    Delete {
+        name: String,
+    },
+}
+
 struct WallmgrClient {
     client: Client,
     base_url: String,
@@ -100,7 +378,7 @@ impl WallmgrClient {
 
     async fn health_check(&self) -> Result<(), Box<dyn std::error::Error>> {
         let resp: serde_json::Value = self.client
-            .get(&format!("{}/health", self.base_url))
+            .get(format!("{}/health", self.base_url))
             .send()
             .await?
             .json()
@@ -128,7 +406,7 @@ impl WallmgrClient {
         });
 
         let resp = self.client
-            .post(&format!("{}/wallpapers/add", self.base_url))
+            .post(format!("{}/wallpapers/add", self.base_url))
             .json(&request)
             .send()
             .await?;
@@ -145,18 +423,16 @@ impl WallmgrClient {
 
     async fn set_wallpaper(&self, wallpaper: &str, monitor: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
         let request = serde_json::json!({
-            "wallpaper_id": if wallpaper.chars().next().map_or(false, |c| c.is_ascii_digit()) {
-                // If starts with digit, it's an ID
-                wallpaper.parse::<uuid::Uuid>()?
-            } else {
+            "wallpaper_id": match wallpaper.parse::<uuid::Uuid>() {
+                Ok(id) => id,
                 // Otherwise, assume it's a path (TODO: handle this properly)
-                return Err("ID based setting not implemented yet".into());
+                Err(_) => return Err("Path based setting not implemented yet".into()),
             },
             "monitor": monitor
         });
 
         let resp = self.client
-            .post(&format!("{}/wallpapers/set", self.base_url))
+            .post(format!("{}/wallpapers/set", self.base_url))
             .json(&request)
             .send()
             .await?;
@@ -171,51 +447,127 @@ impl WallmgrClient {
         Ok(())
     }
 
-    async fn list_wallpapers(&self, r#type: Option<&str>, limit: usize) -> Result<(), Box<dyn std::error::Error>> {
-        let mut url = format!("{}?limit={}", self.base_url, limit);
+    async fn list_wallpapers(&self, query: Option<&str>, r#type: Option<&str>, limit: usize, offset: usize) -> Result<(), Box<dyn std::error::Error>> {
+        let mut url = format!("{}/wallpapers?limit={}&offset={}", self.base_url, limit, offset);
+        if let Some(q) = query {
+            url.push_str(&format!("&q={}", urlencoding::encode(q)));
+        }
         if let Some(t) = r#type {
-            url.push_str(&format!("&type={}", t));
+            url.push_str(&format!("&type={}", urlencoding::encode(t)));
         }
 
-        let resp: Vec<serde_json::Value> = self.client
-            .get(&url)
-            .send()
-            .await?
-            .json()
-            .await?;
+        let resp = self.client.get(&url).send().await?;
+        if resp.status() == reqwest::StatusCode::BAD_REQUEST {
+            println!("{} Invalid query or type", "✗".red());
+            return Ok(());
+        }
+        let resp: Vec<serde_json::Value> = resp.json().await?;
 
         println!("{}\n", "Wallpapers:".bold());
         for wallpaper in resp {
-            let id = wallpaper["id"].as_str().unwrap_or("unknown");
-            let filename = wallpaper["filename"].as_str().unwrap_or("unknown");
-            let wtype = wallpaper["wallpaper_type"].as_str().unwrap_or("unknown");
-            let size = wallpaper["size"].as_u64().unwrap_or(0);
-
-            println!("{} {} ({}) - {}",
-                id.blue(),
-                filename,
-                wtype.yellow(),
-                human_bytes::human_bytes(size as f64)
-            );
+            print_wallpaper(&wallpaper);
+            println!();
+        }
 
-            if let Some(tags) = wallpaper["tags"].as_array() {
-                if !tags.is_empty() {
-                    let tags_str: Vec<_> = tags.iter()
-                        .filter_map(|t| t.as_str())
-                        .collect();
-                    println!("  Tags: {}", tags_str.join(", ").cyan());
-                }
+        Ok(())
+    }
+
+    async fn rate_wallpaper(&self, id: &str, favorite: Option<bool>, stars: Option<u8>) -> Result<(), Box<dyn std::error::Error>> {
+        let request = serde_json::json!({
+            "wallpaper_id": id.parse::<uuid::Uuid>()?,
+            "favorite": favorite,
+            "stars": stars
+        });
+
+        let resp = self.client
+            .post(format!("{}/wallpapers/rate", self.base_url))
+            .json(&request)
+            .send()
+            .await?;
+
+        match resp.status() {
+            status if status.is_success() => {
+                let wallpaper: serde_json::Value = resp.json().await?;
+                println!("{} Wallpaper updated", "✓".green());
+                print_wallpaper(&wallpaper);
             }
+            reqwest::StatusCode::NOT_FOUND => println!("{} No such wallpaper", "✗".red()),
+            reqwest::StatusCode::BAD_REQUEST => println!("{} Ratings are 1 to 5 stars", "✗".red()),
+            status => println!("{} Failed to rate wallpaper: {}", "✗".red(), status),
+        }
 
-            println!();
+        Ok(())
+    }
+
+    /// The picked wallpaper's ID, if any matched
+    async fn random_wallpaper(&self, query: Option<&str>) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let resp = self.client
+            .post(format!("{}/wallpapers/random", self.base_url))
+            .json(&serde_json::json!({ "q": query }))
+            .send()
+            .await?;
+
+        match resp.status() {
+            status if status.is_success() => {
+                let wallpaper: serde_json::Value = resp.json().await?;
+                print_wallpaper(&wallpaper);
+                Ok(wallpaper["id"].as_str().map(String::from))
+            }
+            reqwest::StatusCode::NOT_FOUND => {
+                println!("{} No wallpaper matches", "✗".red());
+                Ok(None)
+            }
+            reqwest::StatusCode::BAD_REQUEST => {
+                println!("{} Invalid query", "✗".red());
+                Ok(None)
+            }
+            status => Err(format!("Failed to pick a wallpaper: {}", status).into()),
+        }
+    }
+
+    async fn generate_wallpaper(&self, spec: serde_json::Value, name: Option<&str>, tags: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        let tags_vec: Option<Vec<String>> = tags.map(|t| t.split(',').map(|s| s.trim().to_string()).collect());
+
+        let request = serde_json::json!({
+            "spec": spec,
+            "name": name,
+            "tags": tags_vec
+        });
+
+        let resp = self.client
+            .post(format!("{}/wallpapers/generate", self.base_url))
+            .json(&request)
+            .send()
+            .await?;
+
+        if resp.status().is_success() {
+            let wallpaper: serde_json::Value = resp.json().await?;
+            println!("{} Generated wallpaper {}", "✓".green(), wallpaper["id"].as_str().unwrap_or("unknown").blue());
+        } else if resp.status() == reqwest::StatusCode::CONFLICT {
+            println!("{} A wallpaper with these parameters already exists", "✗".red());
+        } else {
+            println!("{} Failed to generate wallpaper: {}", "✗".red(), resp.status());
         }
 
         Ok(())
     }
 
+    async fn regenerate_wallpapers(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let resp: serde_json::Value = self.client
+            .post(format!("{}/wallpapers/generated/rerender", self.base_url))
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        println!("{} Re-rendered {} generated wallpapers", "✓".green(), resp["rendered"].as_u64().unwrap_or(0));
+
+        Ok(())
+    }
+
     async fn remove_wallpaper(&self, id: &str) -> Result<(), Box<dyn std::error::Error>> {
         let resp = self.client
-            .delete(&format!("{}/wallpapers/{}", self.base_url, id))
+            .delete(format!("{}/wallpapers/{}", self.base_url, id))
             .send()
             .await?;
 
@@ -228,25 +580,36 @@ impl WallmgrClient {
         Ok(())
     }
 
-    async fn search_booru(&self, tags: &[String], source: Option<&str>, limit: u32) -> Result<(), Box<dyn std::error::Error>> {
-        let mut sources = vec!["danbooru", "yandere", "safebooru", "gelbooru"];
-        if let Some(s) = source {
-            sources = vec![s];
-        }
+    async fn search_booru(
+        &self,
+        tags: &[String],
+        source: Option<&str>,
+        limit: u32,
+        cursor: Option<&str>,
+        for_monitor: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let sources: Vec<&str> = source.into_iter().collect();
 
         let request = serde_json::json!({
             "tags": tags,
             "sources": sources,
-            "limit": limit
+            "limit": limit,
+            "cursor": cursor,
+            "for_monitor": for_monitor
         });
 
-        let resp: serde_json::Value = self.client
-            .post(&format!("{}/search", self.base_url))
+        let resp = self.client
+            .post(format!("{}/search", self.base_url))
             .json(&request)
             .send()
-            .await?
-            .json()
             .await?;
+        if resp.status() == reqwest::StatusCode::BAD_REQUEST {
+            return Err("Invalid search query or cursor".into());
+        }
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(format!("No monitor named {}", for_monitor.unwrap_or_default()).into());
+        }
+        let resp: serde_json::Value = resp.json().await?;
 
         println!("{}\n", "Search Results:".bold());
 
@@ -266,6 +629,13 @@ impl WallmgrClient {
                     url
                 );
 
+                if let Some(also_on) = image["also_on"].as_array() {
+                    if !also_on.is_empty() {
+                        let names: Vec<_> = also_on.iter().filter_map(|s| s.as_str()).collect();
+                        println!("  Also on: {}", names.join(", "));
+                    }
+                }
+
                 if let Some(tags) = image["tags"].as_array() {
                     if !tags.is_empty() {
                         let tags_str: Vec<_> = tags.iter()
@@ -280,10 +650,413 @@ impl WallmgrClient {
             }
         }
 
+        // Results of the other sources are still shown
+        if let Some(errors) = resp["errors"].as_array() {
+            for error in errors {
+                let source = error["source"].as_str().unwrap_or("unknown");
+                let message = error["message"].as_str().unwrap_or("unknown error");
+                println!("{} {} failed: {}", "✗".red(), source.yellow(), message);
+            }
+        }
+
+        if let Some(cursor) = resp["next_cursor"].as_str() {
+            println!("\nMore results: wallmgr search --cursor {}", cursor);
+        }
+
+        Ok(())
+    }
+
+    async fn list_sources(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let sources: Vec<serde_json::Value> = self.client
+            .get(format!("{}/sources", self.base_url))
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        println!("{}\n", "Sources:".bold());
+        for source in sources {
+            let name = source["name"].as_str().unwrap_or("unknown");
+            let engine = source["engine"].as_str().unwrap_or("unknown");
+            let base_url = source["base_url"].as_str().unwrap_or("");
+
+            print!("{} {} {}", name.blue(), engine.yellow(), base_url);
+            if source["authenticated"].as_bool().unwrap_or(false) {
+                print!(" {}", "(authenticated)".green());
+            }
+            println!();
+        }
+
+        Ok(())
+    }
+
+    async fn sync_favorites(&self, source: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let resp = self.client
+            .post(format!("{}/sources/{}/sync", self.base_url, source))
+            .send()
+            .await?;
+
+        if resp.status().is_success() {
+            let result: serde_json::Value = resp.json().await?;
+            let queued = result["queued"].as_u64().unwrap_or(0);
+            println!("{} Queued {} new favorites from {}", "✓".green(), queued, source);
+        } else {
+            println!("{} Failed to sync {}: {}", "✗".red(), source, resp.status());
+        }
+
+        Ok(())
+    }
+
+    async fn edit_tags(&self, ids: &[String], add: &[String], remove: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+        let resp = self.client
+            .post(format!("{}/wallpapers/tags", self.base_url))
+            .json(&serde_json::json!({
+                "wallpaper_ids": ids,
+                "add": add,
+                "remove": remove
+            }))
+            .send()
+            .await?;
+
+        match resp.status() {
+            status if status.is_success() => {
+                let result: serde_json::Value = resp.json().await?;
+                println!("{} Added {} and removed {} tags",
+                    "✓".green(),
+                    result["added"].as_u64().unwrap_or(0),
+                    result["removed"].as_u64().unwrap_or(0)
+                );
+            }
+            reqwest::StatusCode::NOT_FOUND => println!("{} No such wallpaper", "✗".red()),
+            reqwest::StatusCode::UNPROCESSABLE_ENTITY => println!("{} Invalid wallpaper ID", "✗".red()),
+            status => println!("{} Failed to edit tags: {}", "✗".red(), status),
+        }
+
+        Ok(())
+    }
+
+    /// Send a collection or playlist edit and report how it went
+    async fn library_action(&self, request: reqwest::RequestBuilder, done: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let resp = request.send().await?;
+
+        match resp.status() {
+            status if status.is_success() => println!("{} {}", "✓".green(), done),
+            reqwest::StatusCode::NOT_FOUND => println!("{} No such collection, playlist or wallpaper", "✗".red()),
+            reqwest::StatusCode::CONFLICT => println!("{} That name is taken", "✗".red()),
+            reqwest::StatusCode::BAD_REQUEST => println!("{} Invalid name or query", "✗".red()),
+            reqwest::StatusCode::UNPROCESSABLE_ENTITY => println!("{} Invalid wallpaper ID", "✗".red()),
+            status => println!("{} Request failed: {}", "✗".red(), status),
+        }
+
+        Ok(())
+    }
+
+    async fn list_collections(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let collections: Vec<serde_json::Value> = self.client
+            .get(format!("{}/collections", self.base_url))
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        for collection in collections {
+            let count = collection["wallpaper_ids"].as_array().map_or(0, |ids| ids.len());
+            print!("{} ({} wallpapers)", collection["name"].as_str().unwrap_or("unknown").blue(), count);
+            match collection["description"].as_str() {
+                Some(description) => println!(" - {}", description),
+                None => println!(),
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn list_playlists(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let playlists: Vec<serde_json::Value> = self.client
+            .get(format!("{}/playlists", self.base_url))
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        for playlist in playlists {
+            println!("{}: {}",
+                playlist["name"].as_str().unwrap_or("unknown").blue(),
+                playlist["q"].as_str().unwrap_or("").cyan()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Print the wallpapers of a collection or playlist from `url`
+    async fn show_wallpapers(&self, url: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let resp = self.client.get(url).send().await?;
+
+        match resp.status() {
+            status if status.is_success() => {
+                let page: serde_json::Value = resp.json().await?;
+                println!("{}\n", format!("{} wallpapers:", page["total"].as_u64().unwrap_or(0)).bold());
+                for wallpaper in page["wallpapers"].as_array().into_iter().flatten() {
+                    print_wallpaper(wallpaper);
+                    println!();
+                }
+            }
+            reqwest::StatusCode::NOT_FOUND => println!("{} No such collection or playlist", "✗".red()),
+            reqwest::StatusCode::BAD_REQUEST => println!("{} The playlist's query is invalid", "✗".red()),
+            status => println!("{} Failed to list wallpapers: {}", "✗".red(), status),
+        }
+
+        Ok(())
+    }
+
+    /// Send a tag edit and report how it went
+    async fn tag_action(&self, request: reqwest::RequestBuilder, done: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let resp = request.send().await?;
+
+        match resp.status() {
+            status if status.is_success() => println!("{} {}", "✓".green(), done),
+            reqwest::StatusCode::NOT_FOUND => println!("{} No such tag, alias or implication", "✗".red()),
+            reqwest::StatusCode::CONFLICT => println!("{} The new name is taken; merge the tags instead", "✗".red()),
+            reqwest::StatusCode::BAD_REQUEST => println!("{} That would make a tag refer to itself", "✗".red()),
+            status => println!("{} Failed to edit tags: {}", "✗".red(), status),
+        }
+
+        Ok(())
+    }
+
+    async fn list_tag_relations(&self, kind: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let relations: Vec<serde_json::Value> = self.client
+            .get(format!("{}/tags/{}", self.base_url, kind))
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        let (from, to) = if kind == "aliases" { ("alias", "tag") } else { ("tag", "implied") };
+        for relation in relations {
+            println!("{} → {}",
+                relation[from].as_str().unwrap_or("unknown").blue(),
+                relation[to].as_str().unwrap_or("unknown").cyan()
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn import_tag_relations(&self, source: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let resp = self.client
+            .post(format!("{}/tags/import/{}", self.base_url, source))
+            .send()
+            .await?;
+
+        match resp.status() {
+            status if status.is_success() => {
+                let result: serde_json::Value = resp.json().await?;
+                println!("{} Imported {} aliases and {} implications from {}",
+                    "✓".green(),
+                    result["aliases"].as_u64().unwrap_or(0),
+                    result["implications"].as_u64().unwrap_or(0),
+                    source
+                );
+            }
+            reqwest::StatusCode::BAD_REQUEST => println!("{} {} has no tag aliases or implications", "✗".red(), source),
+            status => println!("{} Failed to import from {}: {}", "✗".red(), source, status),
+        }
+
+        Ok(())
+    }
+
+    async fn list_downloads(&self, state: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        let mut url = format!("{}/downloads", self.base_url);
+        if let Some(state) = state {
+            url.push_str(&format!("?state={}", state));
+        }
+
+        let resp = self.client.get(&url).send().await?;
+        if !resp.status().is_success() {
+            return Err(format!("Failed to list downloads: {}", resp.status()).into());
+        }
+        let jobs: Vec<serde_json::Value> = resp.json().await?;
+
+        println!("{}\n", "Downloads:".bold());
+        for job in jobs {
+            let id = job["id"].as_i64().unwrap_or(0);
+            let source = job["image"]["source"].as_str().unwrap_or("unknown");
+            let image_id = job["image"]["id"].as_str().unwrap_or("unknown");
+            let state = job["state"].as_str().unwrap_or("unknown");
+            let downloaded = job["downloaded"].as_u64().unwrap_or(0);
+
+            let state = match state {
+                "done" => state.green(),
+                "failed" => state.red(),
+                "paused" => state.yellow(),
+                _ => state.cyan(),
+            };
+            let progress = match job["total"].as_u64().filter(|&t| t > 0) {
+                Some(total) => format!(
+                    "{} / {}",
+                    human_bytes::human_bytes(downloaded as f64),
+                    human_bytes::human_bytes(total as f64)
+                ),
+                None => human_bytes::human_bytes(downloaded as f64),
+            };
+
+            println!("{} {}:{} {} - {} (priority {})",
+                id.to_string().blue(),
+                source,
+                image_id,
+                state,
+                progress,
+                job["priority"].as_i64().unwrap_or(0)
+            );
+            if let Some(error) = job["error"].as_str() {
+                println!("  Error: {}", error.red());
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn download_action(&self, id: i64, action: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let resp = self.client
+            .post(format!("{}/downloads/{}/{}", self.base_url, id, action))
+            .send()
+            .await?;
+
+        match resp.status() {
+            status if status.is_success() => {
+                let job: serde_json::Value = resp.json().await?;
+                println!("{} Job {} is now {}", "✓".green(), id, job["state"].as_str().unwrap_or("unknown"));
+            }
+            reqwest::StatusCode::NOT_FOUND => println!("{} No download job {}", "✗".red(), id),
+            reqwest::StatusCode::CONFLICT => println!("{} Job {} can't be {}d in its current state", "✗".red(), id, action),
+            status => println!("{} Failed to {} job {}: {}", "✗".red(), action, id, status),
+        }
+
+        Ok(())
+    }
+
+    async fn cancel_download(&self, id: i64) -> Result<(), Box<dyn std::error::Error>> {
+        let resp = self.client
+            .delete(format!("{}/downloads/{}", self.base_url, id))
+            .send()
+            .await?;
+
+        if resp.status().is_success() {
+            println!("{} Download job {} cancelled", "✓".green(), id);
+        } else {
+            println!("{} Failed to cancel job {}: {}", "✗".red(), id, resp.status());
+        }
+
+        Ok(())
+    }
+
+    async fn set_download_priority(&self, id: i64, priority: i32) -> Result<(), Box<dyn std::error::Error>> {
+        let resp = self.client
+            .put(format!("{}/downloads/{}/priority", self.base_url, id))
+            .json(&serde_json::json!({ "priority": priority }))
+            .send()
+            .await?;
+
+        if resp.status().is_success() {
+            println!("{} Job {} priority set to {}", "✓".green(), id, priority);
+        } else {
+            println!("{} Failed to set priority of job {}: {}", "✗".red(), id, resp.status());
+        }
+
         Ok(())
     }
 }
 
+/// One wallpaper of the API with its tags, rating and use
+fn print_wallpaper(wallpaper: &serde_json::Value) {
+    let id = wallpaper["id"].as_str().unwrap_or("unknown");
+    let filename = wallpaper["filename"].as_str().unwrap_or("unknown");
+    let wtype = wallpaper["wallpaper_type"].as_str().unwrap_or("unknown");
+    let size = wallpaper["size"].as_u64().unwrap_or(0);
+
+    println!("{} {} ({}) - {}",
+        id.blue(),
+        filename,
+        wtype.yellow(),
+        human_bytes::human_bytes(size as f64)
+    );
+
+    if let Some(tags) = wallpaper["tags"].as_array() {
+        if !tags.is_empty() {
+            let tags_str: Vec<_> = tags.iter()
+                .filter_map(|t| t.as_str())
+                .collect();
+            println!("  Tags: {}", tags_str.join(", ").cyan());
+        }
+    }
+
+    let mut stats = Vec::new();
+    if wallpaper["favorite"].as_bool() == Some(true) {
+        stats.push("♥ favorite".red().to_string());
+    }
+    if let Some(stars) = wallpaper["stars"].as_u64() {
+        stats.push(format!("{}{}", "★".repeat(stars as usize), "☆".repeat(5 - stars.min(5) as usize)).yellow().to_string());
+    }
+    let times_set = wallpaper["times_set"].as_u64().unwrap_or(0);
+    if times_set > 0 {
+        let seconds = wallpaper["display_seconds"].as_u64().unwrap_or(0);
+        stats.push(format!("set {} times, shown {}h {}m", times_set, seconds / 3600, seconds % 3600 / 60));
+    }
+    if !stats.is_empty() {
+        println!("  {}", stats.join(" · "));
+    }
+}
+
+/// Tags from a comma-separated list
+fn split_tags(tags: &str) -> Vec<String> {
+    tags.split(',')
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// Build a generator spec from command line arguments
+fn generator_spec(kind: &str, colors: &str, angle: f32, size: Option<f32>, seed: u32) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    let colors: Vec<String> = colors
+        .split(',')
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+        .collect();
+    if colors.is_empty() {
+        return Err("At least one color is required".into());
+    }
+
+    let spec = match kind {
+        "solid" => serde_json::json!({ "kind": "solid", "color": colors[0] }),
+        "linear" => serde_json::json!({ "kind": "linear_gradient", "colors": colors, "angle": angle }),
+        "radial" => serde_json::json!({ "kind": "radial_gradient", "colors": colors }),
+        "noise" => serde_json::json!({
+            "kind": "noise",
+            "colors": colors,
+            "scale": size.unwrap_or(256.0),
+            "seed": seed
+        }),
+        "stripes" | "checkerboard" | "dots" | "grid" => {
+            if colors.len() < 2 {
+                return Err("Patterns need a foreground and a background color".into());
+            }
+            serde_json::json!({
+                "kind": "pattern",
+                "pattern": kind,
+                "foreground": colors[0],
+                "background": colors[1],
+                "size": size.unwrap_or(64.0),
+                "angle": angle
+            })
+        }
+        other => return Err(format!("Unknown generator: {}", other).into()),
+    };
+
+    Ok(spec)
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize logging
@@ -305,9 +1078,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             client.set_wallpaper(&wallpaper, monitor.as_deref()).await?;
         }
 
-        Commands::List { r#type, limit } => {
+        Commands::List { query, r#type, limit, offset } => {
+            client.health_check().await?;
+            client.list_wallpapers(query.as_deref(), r#type.as_deref(), limit, offset).await?;
+        }
+
+        Commands::Generate { kind, colors, angle, size, seed, name, tags } => {
+            let spec = generator_spec(&kind, &colors, angle, size, seed)?;
+            client.health_check().await?;
+            client.generate_wallpaper(spec, name.as_deref(), tags.as_deref()).await?;
+        }
+
+        Commands::Regenerate => {
             client.health_check().await?;
-            client.list_wallpapers(r#type.as_deref(), limit).await?;
+            client.regenerate_wallpapers().await?;
         }
 
         Commands::Remove { id } => {
@@ -315,18 +1099,197 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             client.remove_wallpaper(&id).await?;
         }
 
-        Commands::Search { query, tags, source, limit } => {
+        Commands::Favorite { id, remove } => {
+            client.health_check().await?;
+            client.rate_wallpaper(&id, Some(!remove), None).await?;
+        }
+
+        Commands::Rate { id, stars } => {
+            client.health_check().await?;
+            client.rate_wallpaper(&id, None, Some(stars)).await?;
+        }
+
+        Commands::Random { query, set, monitor } => {
+            client.health_check().await?;
+            if let Some(id) = client.random_wallpaper(query.as_deref()).await? {
+                if set {
+                    client.set_wallpaper(&id, monitor.as_deref()).await?;
+                }
+            }
+        }
+
+        Commands::Search { query, tags, source, limit, cursor, for_monitor } => {
             client.health_check().await?;
 
             let search_tags: Vec<String> = if let Some(tag_str) = tags {
                 tag_str.split(',').map(|s| s.trim().to_string()).collect()
             } else if let Some(q) = query {
                 vec![q]
+            } else if cursor.is_some() || for_monitor.is_some() {
+                Vec::new()
             } else {
                 return Err("Must specify either --tags or query".into());
             };
 
-            client.search_booru(&search_tags, source.as_deref(), limit).await?;
+            client
+                .search_booru(&search_tags, source.as_deref(), limit, cursor.as_deref(), for_monitor.as_deref())
+                .await?;
+        }
+
+        Commands::Sources => {
+            client.health_check().await?;
+            client.list_sources().await?;
+        }
+
+        Commands::Login { source, clear } => {
+            if clear {
+                wallmgr_core::keyring::clear(&source)?;
+                println!("{} Removed the key for {}", "✓".green(), source);
+            } else {
+                println!("Enter the API key for {}:", source);
+                let mut key = String::new();
+                std::io::stdin().read_line(&mut key)?;
+                let key = key.trim();
+                if key.is_empty() {
+                    return Err("No key entered".into());
+                }
+                wallmgr_core::keyring::store(&source, key)?;
+                println!("{} Stored the key for {}", "✓".green(), source);
+                println!("{} Set `keyring = true` for {} in config.toml", "Note:".yellow(), source);
+            }
+        }
+
+        Commands::Sync { source } => {
+            client.health_check().await?;
+            client.sync_favorites(&source).await?;
+        }
+
+        Commands::Downloads { downloads_cmd } => {
+            client.health_check().await?;
+            match downloads_cmd {
+                DownloadCommands::List { state } => client.list_downloads(state.as_deref()).await?,
+                DownloadCommands::Pause { id } => client.download_action(id, "pause").await?,
+                DownloadCommands::Resume { id } => client.download_action(id, "resume").await?,
+                DownloadCommands::Cancel { id } => client.cancel_download(id).await?,
+                DownloadCommands::Priority { id, priority } => client.set_download_priority(id, priority).await?,
+            }
+        }
+
+        Commands::Tags { tags_cmd } => {
+            client.health_check().await?;
+            let url = |path: &str| format!("{}/tags/{}", client.base_url, path);
+            match tags_cmd {
+                TagCommands::Add { tags, ids } => client.edit_tags(&ids, &split_tags(&tags), &[]).await?,
+                TagCommands::Remove { tags, ids } => client.edit_tags(&ids, &[], &split_tags(&tags)).await?,
+                TagCommands::Rename { from, to } => {
+                    let request = client.client
+                        .post(url("rename"))
+                        .json(&serde_json::json!({ "from": from, "to": to }));
+                    client.tag_action(request, &format!("Renamed {} to {}", from, to)).await?
+                }
+                TagCommands::Merge { from, into } => {
+                    let request = client.client
+                        .post(url("merge"))
+                        .json(&serde_json::json!({ "from": from, "into": into }));
+                    client.tag_action(request, &format!("Merged {} into {}", from, into)).await?
+                }
+                TagCommands::Alias { alias, tag } => {
+                    let request = client.client
+                        .post(url("aliases"))
+                        .json(&serde_json::json!({ "alias": alias, "tag": tag }));
+                    client.tag_action(request, &format!("{} is now an alias of {}", alias, tag)).await?
+                }
+                TagCommands::Unalias { alias } => {
+                    let request = client.client
+                        .delete(url(&format!("aliases/{}", urlencoding::encode(&alias))));
+                    client.tag_action(request, &format!("Removed alias {}", alias)).await?
+                }
+                TagCommands::Aliases => client.list_tag_relations("aliases").await?,
+                TagCommands::Imply { tag, implied } => {
+                    let request = client.client
+                        .post(url("implications"))
+                        .json(&serde_json::json!({ "tag": tag, "implied": implied }));
+                    client.tag_action(request, &format!("{} now implies {}", tag, implied)).await?
+                }
+                TagCommands::Unimply { tag, implied } => {
+                    let request = client.client.delete(url(&format!(
+                        "implications/{}/{}",
+                        urlencoding::encode(&tag),
+                        urlencoding::encode(&implied)
+                    )));
+                    client.tag_action(request, &format!("{} no longer implies {}", tag, implied)).await?
+                }
+                TagCommands::Implications => client.list_tag_relations("implications").await?,
+                TagCommands::Import { source } => client.import_tag_relations(&source).await?,
+            }
+        }
+
+        Commands::Collections { collections_cmd } => {
+            client.health_check().await?;
+            let url = |name: &str, path: &str| {
+                format!("{}/collections/{}{}", client.base_url, urlencoding::encode(name), path)
+            };
+            match collections_cmd {
+                CollectionCommands::List => client.list_collections().await?,
+                CollectionCommands::Create { name, description } => {
+                    let request = client.client
+                        .post(format!("{}/collections", client.base_url))
+                        .json(&serde_json::json!({ "name": name, "description": description }));
+                    client.library_action(request, &format!("Created collection {}", name)).await?
+                }
+                CollectionCommands::Show { name } => client.show_wallpapers(&url(&name, "/wallpapers")).await?,
+                CollectionCommands::Edit { name, rename, description } => {
+                    let new_name = rename.unwrap_or_else(|| name.clone());
+                    let request = client.client
+                        .put(url(&name, ""))
+                        .json(&serde_json::json!({ "name": new_name, "description": description }));
+                    client.library_action(request, &format!("Updated collection {}", new_name)).await?
+                }
+                CollectionCommands::Delete { name } => {
+                    let request = client.client.delete(url(&name, ""));
+                    client.library_action(request, &format!("Deleted collection {}", name)).await?
+                }
+                CollectionCommands::Add { name, ids, at } => {
+                    let request = client.client
+                        .post(url(&name, "/wallpapers"))
+                        .json(&serde_json::json!({ "wallpaper_ids": ids, "position": at }));
+                    client.library_action(request, &format!("Added to {}", name)).await?
+                }
+                CollectionCommands::Remove { name, ids } => {
+                    let request = client.client
+                        .post(url(&name, "/remove"))
+                        .json(&serde_json::json!({ "wallpaper_ids": ids }));
+                    client.library_action(request, &format!("Removed from {}", name)).await?
+                }
+            }
+        }
+
+        Commands::Playlists { playlists_cmd } => {
+            client.health_check().await?;
+            let url = |name: &str, path: &str| {
+                format!("{}/playlists/{}{}", client.base_url, urlencoding::encode(name), path)
+            };
+            match playlists_cmd {
+                PlaylistCommands::List => client.list_playlists().await?,
+                PlaylistCommands::Create { name, query } => {
+                    let request = client.client
+                        .post(format!("{}/playlists", client.base_url))
+                        .json(&serde_json::json!({ "name": name, "q": query }));
+                    client.library_action(request, &format!("Created playlist {}", name)).await?
+                }
+                PlaylistCommands::Show { name } => client.show_wallpapers(&url(&name, "/wallpapers")).await?,
+                PlaylistCommands::Edit { name, query, rename } => {
+                    let new_name = rename.unwrap_or_else(|| name.clone());
+                    let request = client.client
+                        .put(url(&name, ""))
+                        .json(&serde_json::json!({ "name": new_name, "q": query }));
+                    client.library_action(request, &format!("Updated playlist {}", new_name)).await?
+                }
+                PlaylistCommands::Delete { name } => {
+                    let request = client.client.delete(url(&name, ""));
+                    client.library_action(request, &format!("Deleted playlist {}", name)).await?
+                }
+            }
         }
 
         Commands::Status => {
@@ -1,4 +1,7 @@
 use serde::{Deserialize, Serialize};
+use wallmgr_connectors::anime_pictures::AnimePicturesConnector;
+use wallmgr_connectors::zerochan::ZerochanConnector;
+use wallmgr_connectors::BooruConnector;
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum EngineType {
@@ -103,8 +106,21 @@ impl ImageSource {
         ]
     }
 
-    pub fn api_url(&self, tags: &str, page: u32, limit: u32) -> String {
+    /// Sites searched through the shared backend connectors instead of `api_url`
+    pub fn connector(&self) -> Option<Box<dyn BooruConnector>> {
         match self.engine {
+            EngineType::Zerochan => {
+                Some(Box::new(ZerochanConnector::with_base_url(&self.id, &self.base_url)))
+            }
+            EngineType::AnimePictures => {
+                Some(Box::new(AnimePicturesConnector::with_base_url(&self.id, &self.base_url)))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn api_url(&self, tags: &str, page: u32, limit: u32) -> Option<String> {
+        let url = match self.engine {
             EngineType::Moebooru => {
                 format!(
                     "{}/post.json?tags={}&page={}&limit={}",
@@ -133,21 +149,8 @@ impl ImageSource {
                     limit.min(self.max_limit)
                 )
             }
-            EngineType::Zerochan => {
-                format!(
-                    "{}/{}?json&p={}",
-                    self.base_url,
-                    urlencoding::encode(tags),
-                    page
-                )
-            }
-            EngineType::AnimePictures => {
-                let page_idx = if page > 0 { page - 1 } else { 0 };
-                format!(
-                    "{}/api/v3/posts?page={}&posts_per_page={}&lang=en",
-                    self.base_url, page_idx, limit.min(self.max_limit)
-                )
-            }
-        }
+            EngineType::Zerochan | EngineType::AnimePictures => return None,
+        };
+        Some(url)
     }
 }
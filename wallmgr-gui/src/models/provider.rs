@@ -6,8 +6,6 @@ pub enum BooruProvider {
     Konachan,
     Yandere,
     Danbooru,
-    Zerochan,
-    AnimePictures,
     Gelbooru,
     #[allow(dead_code)]
     WallHaven,
@@ -19,8 +17,6 @@ impl BooruProvider {
             Self::Konachan,
             Self::Yandere,
             Self::Danbooru,
-            Self::Zerochan,
-            Self::AnimePictures,
             // Gelbooru removed - 401 unauthorized errors
             // WallHaven removed - use anime-focused sites only
         ]
@@ -31,8 +27,6 @@ impl BooruProvider {
             Self::Konachan => "Konachan",
             Self::Yandere => "yande.re",
             Self::Danbooru => "Danbooru",
-            Self::Zerochan => "Zerochan",
-            Self::AnimePictures => "Anime-Pictures",
             Self::Gelbooru => "Gelbooru",
             Self::WallHaven => "WallHaven",
         }
@@ -43,8 +37,6 @@ impl BooruProvider {
             Self::Konachan => "https://konachan.net/post.json",
             Self::Yandere => "https://yande.re/post.json",
             Self::Danbooru => "https://danbooru.donmai.us/posts.json",
-            Self::Zerochan => "https://www.zerochan.net",
-            Self::AnimePictures => "https://api.anime-pictures.net/api/v3/posts",
             Self::Gelbooru => "https://gelbooru.com/index.php?page=dapi&s=post&q=index&json=1",
             Self::WallHaven => "https://wallhaven.cc/api/v1/search",
         }
@@ -65,8 +57,6 @@ impl BooruProvider {
         match self {
             Self::Konachan | Self::Yandere => Self::map_moebooru(json),
             Self::Danbooru => Self::map_danbooru(json),
-            Self::Zerochan => Self::map_zerochan(json),
-            Self::AnimePictures => Self::map_anime_pictures(json),
             Self::Gelbooru => Self::map_gelbooru(json),
             Self::WallHaven => Self::map_wallhaven(json),
         }
@@ -156,54 +146,4 @@ impl BooruProvider {
             })
         }).collect())
     }
-
-    fn map_zerochan(json: Value) -> Result<Vec<BooruImage>, String> {
-        // Zerochan returns simple JSON array
-        let items = json.as_array().ok_or("Invalid Zerochan response")?;
-        
-        Ok(items.iter().filter_map(|item| {
-            Some(BooruImage {
-                id: item["id"].as_str()?.to_string(),
-                width: item["width"].as_u64().unwrap_or(1920) as u32,
-                height: item["height"].as_u64().unwrap_or(1080) as u32,
-                tags: item["tag"].as_str().unwrap_or("").replace(", ", " "),
-                rating: "s".to_string(), // Zerochan is generally safe
-                is_nsfw: false,
-                preview_url: item["thumbnail"].as_str()?.to_string(),
-                sample_url: item["image"].as_str().unwrap_or(item["thumbnail"].as_str()?).to_string(),
-                file_url: item["full"].as_str().unwrap_or(item["image"].as_str()?).to_string(),
-                selected: false,
-            })
-        }).collect())
-    }
-
-    fn map_anime_pictures(json: Value) -> Result<Vec<BooruImage>, String> {
-        // Anime-Pictures uses "posts" array
-        let posts = json["posts"].as_array().ok_or("Invalid Anime-Pictures response")?;
-        
-        Ok(posts.iter().filter_map(|post| {
-            let id = post["id"].as_u64()?.to_string();
-            let md5 = post["md5"].as_str()?;
-            let ext = post["ext"].as_str()?.trim_start_matches('.');
-            let width = post["width"].as_u64()? as u32;
-            let height = post["height"].as_u64()? as u32;
-            
-            // Build URLs like imgbrd-grabber does
-            let md5_part = format!("{}/{}", &md5[0..3], md5);
-            let preview_ext = if post["have_alpha"].as_bool().unwrap_or(false) { "png" } else { "jpg" };
-            
-            Some(BooruImage {
-                id,
-                width,
-                height,
-                tags: post["tags_string"].as_str().unwrap_or("").to_string(),
-                rating: "s".to_string(),
-                is_nsfw: post["adult"].as_bool().unwrap_or(false),
-                preview_url: format!("https://opreviews.anime-pictures.net/{}_sp.{}", md5_part, preview_ext),
-                sample_url: format!("https://opreviews.anime-pictures.net/{}_bp.{}", md5_part, preview_ext),
-                file_url: format!("https://oimages.anime-pictures.net/{}.{}", md5_part, ext),
-                selected: false,
-            })
-        }).collect())
-    }
 }
@@ -86,6 +86,23 @@ pub struct BooruImage {
     pub selected: bool,
 }
 
+impl From<wallmgr_core::types::BooruImage> for BooruImage {
+    fn from(image: wallmgr_core::types::BooruImage) -> Self {
+        Self {
+            preview_url: image.preview_url.clone().unwrap_or_else(|| image.file_url.clone()),
+            sample_url: image.sample_url.clone().unwrap_or_else(|| image.file_url.clone()),
+            id: image.id,
+            width: image.width,
+            height: image.height,
+            tags: image.tags.join(" "),
+            rating: image.rating.as_str()[..1].to_string(),
+            is_nsfw: image.is_nsfw,
+            file_url: image.file_url,
+            selected: false,
+        }
+    }
+}
+
 impl BooruImage {
     #[allow(dead_code)]
     pub fn to_wallpaper(&self, download_path: PathBuf) -> Wallpaper {
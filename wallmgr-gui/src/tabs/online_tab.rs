@@ -1,6 +1,13 @@
 use eframe::egui;
 use poll_promise::Promise;
 use crate::models::{BooruImage, AppSettings, ImageSource};
+use wallmgr_connectors::Query;
+
+/// Images found, and the sources that failed with why
+struct SearchOutcome {
+    images: Vec<BooruImage>,
+    failures: Vec<(String, String)>,
+}
 
 pub struct OnlineTab {
     // Search
@@ -8,7 +15,7 @@ pub struct OnlineTab {
     
     // Results
     images: Vec<BooruImage>,
-    promise: Option<Promise<Result<Vec<BooruImage>, String>>>,
+    promise: Option<Promise<Result<SearchOutcome, String>>>,
     
     // Suggestions
     tag_suggestions: Vec<String>,
@@ -58,9 +65,16 @@ impl OnlineTab {
             if let Some(result) = promise.ready() {
                 self.is_loading = false;
                 match result {
-                    Ok(images) => {
-                        self.status_message = format!("Found {} images from: {}", images.len(), self.active_sources.join(", "));
-                        self.images = images.clone();
+                    Ok(outcome) => {
+                        self.status_message = format!(
+                            "Found {} images from: {}",
+                            outcome.images.len(),
+                            self.active_sources.join(", ")
+                        );
+                        for (source, error) in &outcome.failures {
+                            self.status_message.push_str(&format!("\n⚠ {} failed: {}", source, error));
+                        }
+                        self.images = outcome.images.clone();
                     }
                     Err(e) => {
                         self.status_message = format!("Error: {}", e);
@@ -247,26 +261,53 @@ impl OnlineTab {
         // Create promises for each source
         let promise = Promise::spawn_thread("multi_source_search", move || {
             let mut all_images = Vec::new();
+            let mut failures = Vec::new();
             
             // Use blocking reqwest client for thread
             let client = reqwest::blocking::Client::new();
+            // Drives the async backend connectors
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(|e| e.to_string())?;
+            let query = Query::parse(&search_query).map_err(|e| e.to_string())?;
             
             for source in enabled_sources {
-                let url = source.api_url(&search_query, 1, limit as u32);
+                if let Some(connector) = source.connector() {
+                    let limit = (limit as u32).min(source.max_limit);
+                    match runtime.block_on(connector.search_query(&query, limit, 1)) {
+                        Ok(found) => all_images.extend(found.images.into_iter().map(BooruImage::from)),
+                        Err(e) => failures.push((source.name.clone(), e.to_string())),
+                    }
+                    continue;
+                }
+
+                let Some(url) = source.api_url(&search_query, 1, limit as u32) else {
+                    continue;
+                };
                 
                 match client.get(&url).send() {
+                    Ok(response) if !response.status().is_success() => {
+                        failures.push((source.name.clone(), format!("returned status {}", response.status())));
+                    }
                     Ok(response) => {
-                        if let Ok(json) = response.json::<serde_json::Value>() {
-                            if let Ok(images) = Self::parse_response(&source, json) {
-                                all_images.extend(images);
-                            }
+                        let parsed = response
+                            .json::<serde_json::Value>()
+                            .map_err(|e| e.to_string())
+                            .and_then(|json| Self::parse_response(&source, json));
+                        match parsed {
+                            Ok(images) => all_images.extend(images),
+                            Err(e) => failures.push((source.name.clone(), e)),
                         }
                     }
-                    Err(_) => continue,
+                    Err(e) => failures.push((source.name.clone(), format!("could not be reached: {}", e))),
                 }
             }
 
-            Ok(all_images)
+            Ok(SearchOutcome {
+                images: all_images,
+                failures,
+            })
         });
 
         self.promise = Some(promise);
@@ -279,8 +320,9 @@ impl OnlineTab {
             EngineType::Moebooru => Self::parse_moebooru(json),
             EngineType::Danbooru2 => Self::parse_danbooru2(json),
             EngineType::Gelbooru02 => Self::parse_gelbooru02(json),
-            EngineType::Zerochan => Self::parse_zerochan(json),
-            EngineType::AnimePictures => Self::parse_anime_pictures(json),
+            EngineType::Zerochan | EngineType::AnimePictures => {
+                Err(format!("{} is searched through its connector", source.name))
+            }
         }
     }
 
@@ -347,51 +389,4 @@ impl OnlineTab {
             })
         }).collect())
     }
-
-    fn parse_zerochan(json: serde_json::Value) -> Result<Vec<BooruImage>, String> {
-        let items = json.as_array().ok_or("Invalid response")?;
-        
-        Ok(items.iter().filter_map(|item| {
-            Some(BooruImage {
-                id: item["id"].as_str()?.to_string(),
-                width: item["width"].as_u64().unwrap_or(1920) as u32,
-                height: item["height"].as_u64().unwrap_or(1080) as u32,
-                tags: item["tag"].as_str().unwrap_or("").replace(", ", " "),
-                rating: "s".to_string(),
-                is_nsfw: false,
-                preview_url: item["thumbnail"].as_str()?.to_string(),
-                sample_url: item["image"].as_str().unwrap_or(item["thumbnail"].as_str()?).to_string(),
-                file_url: item["full"].as_str().unwrap_or(item["image"].as_str()?).to_string(),
-                selected: false,
-            })
-        }).collect())
-    }
-
-    fn parse_anime_pictures(json: serde_json::Value) -> Result<Vec<BooruImage>, String> {
-        let posts = json["posts"].as_array().ok_or("Invalid response")?;
-        
-        Ok(posts.iter().filter_map(|post| {
-            let id = post["id"].as_u64()?.to_string();
-            let md5 = post["md5"].as_str()?;
-            let ext = post["ext"].as_str()?.trim_start_matches('.');
-            let width = post["width"].as_u64()? as u32;
-            let height = post["height"].as_u64()? as u32;
-            
-            let md5_part = format!("{}/{}", &md5[0..3], md5);
-            let preview_ext = if post["have_alpha"].as_bool().unwrap_or(false) { "png" } else { "jpg" };
-            
-            Some(BooruImage {
-                id,
-                width,
-                height,
-                tags: post["tags_string"].as_str().unwrap_or("").to_string(),
-                rating: "s".to_string(),
-                is_nsfw: post["adult"].as_bool().unwrap_or(false),
-                preview_url: format!("https://opreviews.anime-pictures.net/{}_sp.{}", md5_part, preview_ext),
-                sample_url: format!("https://opreviews.anime-pictures.net/{}_bp.{}", md5_part, preview_ext),
-                file_url: format!("https://oimages.anime-pictures.net/{}.{}", md5_part, ext),
-                selected: false,
-            })
-        }).collect())
-    }
 }